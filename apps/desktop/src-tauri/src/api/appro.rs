@@ -2,9 +2,11 @@
 //!
 //! Tauri commands for procurement management.
 
+use manchengo_core::{Currency, ExchangeRate, UserRole};
 use tauri::State;
 
 use crate::dto::appro::*;
+use crate::dto::CommandError;
 use crate::state::AppState;
 
 // ============================================================================
@@ -16,11 +18,11 @@ use crate::state::AppState;
 pub fn list_purchase_orders(
     state: State<AppState>,
     filter: Option<PurchaseOrderFilter>,
-) -> Result<Vec<PurchaseOrderDto>, String> {
+) -> Result<Vec<PurchaseOrderDto>, CommandError> {
     state
         .appro_service
         .list_orders(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single purchase order
@@ -28,11 +30,11 @@ pub fn list_purchase_orders(
 pub fn get_purchase_order(
     state: State<AppState>,
     id: String,
-) -> Result<Option<PurchaseOrderDto>, String> {
+) -> Result<Option<PurchaseOrderDto>, CommandError> {
     state
         .appro_service
         .get_order(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create new purchase order
@@ -40,11 +42,11 @@ pub fn get_purchase_order(
 pub fn create_purchase_order(
     state: State<AppState>,
     data: CreatePurchaseOrderDto,
-) -> Result<PurchaseOrderDto, String> {
+) -> Result<PurchaseOrderDto, CommandError> {
     state
         .appro_service
         .create_order(data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Confirm purchase order (DRAFT -> CONFIRMED)
@@ -52,11 +54,11 @@ pub fn create_purchase_order(
 pub fn confirm_purchase_order(
     state: State<AppState>,
     id: String,
-) -> Result<PurchaseOrderDto, String> {
+) -> Result<PurchaseOrderDto, CommandError> {
     state
         .appro_service
         .confirm_order(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Send purchase order (CONFIRMED -> SENT)
@@ -64,11 +66,11 @@ pub fn confirm_purchase_order(
 pub fn send_purchase_order(
     state: State<AppState>,
     id: String,
-) -> Result<PurchaseOrderDto, String> {
+) -> Result<PurchaseOrderDto, CommandError> {
     state
         .appro_service
         .send_order(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Receive purchase order (SENT -> RECEIVED)
@@ -77,27 +79,26 @@ pub fn receive_purchase_order(
     state: State<AppState>,
     id: String,
     data: ReceivePurchaseOrderDto,
-) -> Result<PurchaseOrderDto, String> {
+) -> Result<PurchaseOrderDto, CommandError> {
     let user_id = state
         .session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state
         .appro_service
         .receive_order(&id, data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Cancel purchase order
 #[tauri::command]
-pub fn cancel_purchase_order(state: State<AppState>, id: String) -> Result<(), String> {
+pub fn cancel_purchase_order(state: State<AppState>, id: String) -> Result<(), CommandError> {
     state
         .appro_service
         .cancel_order(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -110,17 +111,22 @@ pub fn update_supplier(
     state: State<AppState>,
     id: String,
     data: crate::repositories::supplier_repo::CreateSupplierDto,
-) -> Result<crate::repositories::supplier_repo::SupplierDto, String> {
+) -> Result<crate::repositories::supplier_repo::SupplierDto, CommandError> {
+    manchengo_core::fiscal_id::check_identifiers(
+        data.nif.as_deref(),
+        data.nis.as_deref(),
+        data.rc.as_deref(),
+        data.article_imposition.as_deref(),
+    )?;
+
     state
         .supplier_repo
-        .update(&id, &data)
-        .map_err(|e| e.to_string())?;
+        .update(&id, &data)?;
 
     state
         .supplier_repo
-        .get(&id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Supplier not found after update".to_string())
+        .get(&id)?
+        .ok_or_else(|| CommandError::not_found("Supplier", id.as_str()))
 }
 
 /// Get supplier performance
@@ -128,11 +134,78 @@ pub fn update_supplier(
 pub fn get_supplier_performance(
     state: State<AppState>,
     supplier_id: String,
-) -> Result<SupplierPerformanceDto, String> {
+) -> Result<SupplierPerformanceDto, CommandError> {
     state
         .appro_service
         .get_supplier_performance(&supplier_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Get supplier statement in original currencies
+#[tauri::command]
+pub fn get_supplier_statement(
+    state: State<AppState>,
+    supplier_id: String,
+    from_date: Option<String>,
+    to_date: Option<String>,
+) -> Result<SupplierStatementDto, CommandError> {
+    state.session.require_role(UserRole::Appro)?;
+
+    state
+        .appro_service
+        .get_supplier_statement(&supplier_id, from_date, to_date)
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// EXCHANGE RATE COMMANDS
+// ============================================================================
+
+/// List exchange rates (optionally for one currency)
+#[tauri::command]
+pub fn list_exchange_rates(
+    state: State<AppState>,
+    currency: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<ExchangeRate>, CommandError> {
+    let currency = currency
+        .map(|c| Currency::from_code(&c).ok_or_else(|| CommandError::validation("currency", format!("Devise inconnue: {}", c))))
+        .transpose()?;
+
+    state
+        .appro_service
+        .list_exchange_rates(currency, limit)
+        .map_err(CommandError::from)
+}
+
+/// Enter an exchange rate by hand
+#[tauri::command]
+pub fn set_exchange_rate(state: State<AppState>, data: SetExchangeRateDto) -> Result<ExchangeRate, CommandError> {
+    let user_id = state
+        .session
+        .require_role(UserRole::Appro)?
+        .id
+        .to_string();
+
+    state
+        .appro_service
+        .set_exchange_rate(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Import exchange rates from CSV content (`date;currency;rate`)
+#[tauri::command]
+pub fn import_exchange_rates(state: State<AppState>, content: String) -> Result<ExchangeRateImportDto, CommandError> {
+    let user_id = state
+        .session
+        .require_role(UserRole::Appro)?
+        .id
+        .to_string();
+
+    state
+        .appro_service
+        .import_exchange_rates(&content, &user_id)
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -141,6 +214,6 @@ pub fn get_supplier_performance(
 
 /// Get appro dashboard
 #[tauri::command]
-pub fn get_appro_dashboard(state: State<AppState>) -> Result<ApproDashboardDto, String> {
-    state.appro_service.get_dashboard().map_err(|e| e.to_string())
+pub fn get_appro_dashboard(state: State<AppState>) -> Result<ApproDashboardDto, CommandError> {
+    state.appro_service.get_dashboard().map_err(CommandError::from)
 }
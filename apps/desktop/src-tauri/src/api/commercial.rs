@@ -1,10 +1,11 @@
 //! Commercial API Commands
 //!
-//! Tauri commands for client and pricing management.
+//! Tauri commands for client, pricing and sales order management.
 
 use tauri::State;
 
 use crate::dto::commercial::*;
+use crate::dto::CommandError;
 use crate::state::AppState;
 
 // ============================================================================
@@ -16,29 +17,29 @@ use crate::state::AppState;
 pub fn list_clients(
     state: State<AppState>,
     filter: Option<ClientFilter>,
-) -> Result<Vec<ClientDto>, String> {
+) -> Result<Vec<ClientDto>, CommandError> {
     state
         .commercial_service
         .list_clients(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single client
 #[tauri::command]
-pub fn get_client(state: State<AppState>, id: String) -> Result<Option<ClientDto>, String> {
+pub fn get_client(state: State<AppState>, id: String) -> Result<Option<ClientDto>, CommandError> {
     state
         .commercial_service
         .get_client(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create new client
 #[tauri::command]
-pub fn create_client(state: State<AppState>, data: CreateClientDto) -> Result<ClientDto, String> {
+pub fn create_client(state: State<AppState>, data: CreateClientDto) -> Result<ClientDto, CommandError> {
     state
         .commercial_service
         .create_client(data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Update client
@@ -47,20 +48,26 @@ pub fn update_client(
     state: State<AppState>,
     id: String,
     data: CreateClientDto,
-) -> Result<ClientDto, String> {
+) -> Result<ClientDto, CommandError> {
     state
         .commercial_service
         .update_client(&id, data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Delete client
 #[tauri::command]
-pub fn delete_client(state: State<AppState>, id: String) -> Result<(), String> {
+pub fn delete_client(state: State<AppState>, id: String) -> Result<(), CommandError> {
+    let user_id = state
+        .session
+        .require_user()?
+        .id
+        .to_string();
+
     state
         .commercial_service
-        .delete_client(&id)
-        .map_err(|e| e.to_string())
+        .delete_client(&id, &user_id)
+        .map_err(CommandError::from)
 }
 
 /// Get client balance
@@ -68,11 +75,11 @@ pub fn delete_client(state: State<AppState>, id: String) -> Result<(), String> {
 pub fn get_client_balance(
     state: State<AppState>,
     id: String,
-) -> Result<ClientBalanceDto, String> {
+) -> Result<ClientBalanceDto, CommandError> {
     state
         .commercial_service
         .get_client_balance(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get client history
@@ -81,11 +88,11 @@ pub fn get_client_history(
     state: State<AppState>,
     id: String,
     limit: Option<u32>,
-) -> Result<Vec<ClientHistoryItemDto>, String> {
+) -> Result<Vec<ClientHistoryItemDto>, CommandError> {
     state
         .commercial_service
         .get_client_history(&id, limit)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -97,11 +104,11 @@ pub fn get_client_history(
 pub fn list_price_lists(
     state: State<AppState>,
     active_only: Option<bool>,
-) -> Result<Vec<PriceListDto>, String> {
+) -> Result<Vec<PriceListDto>, CommandError> {
     state
         .commercial_service
         .list_price_lists(active_only.unwrap_or(true))
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get client prices
@@ -109,9 +116,80 @@ pub fn list_price_lists(
 pub fn get_client_prices(
     state: State<AppState>,
     client_id: String,
-) -> Result<Vec<ClientPriceDto>, String> {
+) -> Result<Vec<ClientPriceDto>, CommandError> {
     state
         .commercial_service
         .get_client_prices(&client_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// SALES ORDER COMMANDS
+// ============================================================================
+
+/// Confirm a sales order and hold its lots
+#[tauri::command]
+pub fn confirm_sales_order(
+    state: State<AppState>,
+    data: ConfirmSalesOrderDto,
+) -> Result<Vec<LotReservationDto>, CommandError> {
+    let user_id = state
+        .session
+        .require_user()?
+        .id
+        .to_string();
+
+    state
+        .sales_order_service
+        .confirm_order(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Cancel a sales order and release its lots
+#[tauri::command]
+pub fn cancel_sales_order(
+    state: State<AppState>,
+    id: String,
+    reason: String,
+) -> Result<Vec<LotReservationDto>, CommandError> {
+    let user_id = state
+        .session
+        .require_user()?
+        .id
+        .to_string();
+
+    state
+        .sales_order_service
+        .cancel_order(&id, &reason, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Prepare a delivery line from the lots held for its order
+#[tauri::command]
+pub fn prepare_delivery_line(
+    state: State<AppState>,
+    data: PrepareDeliveryLineDto,
+) -> Result<Vec<LotReservationDto>, CommandError> {
+    let user_id = state
+        .session
+        .require_user()?
+        .id
+        .to_string();
+
+    state
+        .sales_order_service
+        .prepare_delivery_line(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// List the lot reservations of a sales order
+#[tauri::command]
+pub fn list_order_reservations(
+    state: State<AppState>,
+    id: String,
+) -> Result<Vec<LotReservationDto>, CommandError> {
+    state
+        .sales_order_service
+        .list_reservations(&id)
+        .map_err(CommandError::from)
 }
@@ -2,15 +2,18 @@
 //!
 //! Tauri commands for invoice and payment management.
 
+use manchengo_core::{AmountInWords, FiscalRuleSet, Money, UserRole};
+use manchengo_database::{DocumentSequences, SequenceAudit};
 use tauri::State;
 use uuid::Uuid;
 
 use crate::dto::invoice::*;
+use crate::dto::CommandError;
 use crate::state::AppState;
 
 /// Validate that a string is a valid UUID
-fn validate_uuid(id: &str) -> Result<(), String> {
-    Uuid::parse_str(id).map_err(|_| format!("Invalid UUID: {}", id))?;
+fn validate_uuid(id: &str) -> Result<(), CommandError> {
+    Uuid::parse_str(id).map_err(|_| CommandError::invalid_id(id))?;
     Ok(())
 }
 
@@ -23,40 +26,40 @@ fn validate_uuid(id: &str) -> Result<(), String> {
 pub fn list_invoices(
     state: State<AppState>,
     filter: Option<InvoiceFilter>,
-) -> Result<Vec<InvoiceDto>, String> {
+) -> Result<Vec<InvoiceDto>, CommandError> {
     state
         .invoice_service
         .list_invoices(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single invoice
 #[tauri::command]
-pub fn get_invoice(state: State<AppState>, id: String) -> Result<Option<InvoiceDto>, String> {
+pub fn get_invoice(state: State<AppState>, id: String) -> Result<Option<InvoiceDto>, CommandError> {
     validate_uuid(&id)?;
     state
         .invoice_service
         .get_invoice(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create new invoice
 #[tauri::command]
-pub fn create_invoice(state: State<AppState>, data: CreateInvoiceDto) -> Result<InvoiceDto, String> {
+pub fn create_invoice(state: State<AppState>, data: CreateInvoiceDto) -> Result<InvoiceDto, CommandError> {
     state
         .invoice_service
         .create_invoice(data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Validate invoice (DRAFT -> VALIDATED)
 #[tauri::command]
-pub fn validate_invoice(state: State<AppState>, id: String) -> Result<InvoiceDto, String> {
+pub fn validate_invoice(state: State<AppState>, id: String) -> Result<InvoiceDto, CommandError> {
     validate_uuid(&id)?;
     state
         .invoice_service
         .validate_invoice(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Void invoice (annulation)
@@ -65,24 +68,36 @@ pub fn void_invoice(
     state: State<AppState>,
     id: String,
     reason: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     validate_uuid(&id)?;
     state
         .invoice_service
         .void_invoice(&id, reason.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
-/// Calculate invoice totals
+/// Document numbering gap audit for a year (admin only)
+#[tauri::command]
+pub fn get_sequence_gap_report(state: State<AppState>, year: i32) -> Result<Vec<SequenceAudit>, CommandError> {
+    state.session
+        .require_role(UserRole::Admin)?;
+
+    state
+        .db
+        .with_connection(|conn| DocumentSequences::gap_report(conn, year))
+        .map_err(CommandError::from)
+}
+
+/// Calculate invoice totals (preview, same rules as create_invoice)
 #[tauri::command]
 pub fn calculate_invoice_totals(
     state: State<AppState>,
-    lines: Vec<CreateInvoiceLineDto>,
-) -> Result<InvoiceTotalsDto, String> {
+    data: CreateInvoiceDto,
+) -> Result<InvoiceTotalsDto, CommandError> {
     state
         .invoice_service
-        .calculate_totals(&lines)
-        .map_err(|e| e.to_string())
+        .calculate_totals(&data)
+        .map_err(CommandError::from)
 }
 
 /// Calculate timbre fiscal
@@ -90,8 +105,46 @@ pub fn calculate_invoice_totals(
 pub fn calculate_timbre_fiscal(
     state: State<AppState>,
     total_ttc: i64,
-) -> Result<TimbreFiscalDto, String> {
-    Ok(state.invoice_service.get_timbre_fiscal(total_ttc))
+    payment_method: Option<String>,
+    invoice_date: Option<String>,
+) -> Result<TimbreFiscalDto, CommandError> {
+    state
+        .invoice_service
+        .get_timbre_fiscal(total_ttc, payment_method.as_deref(), invoice_date.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Amount in words (French and Arabic) for invoices, receipts and delivery notes
+#[tauri::command]
+pub fn amount_in_words(amount: i64) -> AmountInWords {
+    AmountInWords::new(Money::from_centimes(amount))
+}
+
+// ============================================================================
+// FISCAL RULE COMMANDS
+// ============================================================================
+
+/// List fiscal rule sets (one per Finance Law)
+#[tauri::command]
+pub fn list_fiscal_rules(state: State<AppState>) -> Result<Vec<FiscalRuleSet>, CommandError> {
+    state
+        .invoice_service
+        .list_fiscal_rules()
+        .map_err(CommandError::from)
+}
+
+/// Add a fiscal rule set (admin only)
+#[tauri::command]
+pub fn create_fiscal_rules(state: State<AppState>, rule_set: FiscalRuleSet) -> Result<(), CommandError> {
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state
+        .invoice_service
+        .create_fiscal_rules(rule_set, &user_id)
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -103,20 +156,20 @@ pub fn calculate_timbre_fiscal(
 pub fn list_payments(
     state: State<AppState>,
     filter: Option<PaymentFilter>,
-) -> Result<Vec<PaymentDto>, String> {
+) -> Result<Vec<PaymentDto>, CommandError> {
     state
         .invoice_service
         .list_payments(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create payment
 #[tauri::command]
-pub fn create_payment(state: State<AppState>, data: CreatePaymentDto) -> Result<PaymentDto, String> {
+pub fn create_payment(state: State<AppState>, data: CreatePaymentDto) -> Result<PaymentDto, CommandError> {
     state
         .invoice_service
         .create_payment(data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get outstanding invoices for client
@@ -124,10 +177,10 @@ pub fn create_payment(state: State<AppState>, data: CreatePaymentDto) -> Result<
 pub fn get_outstanding_invoices(
     state: State<AppState>,
     client_id: String,
-) -> Result<Vec<InvoiceDto>, String> {
+) -> Result<Vec<InvoiceDto>, CommandError> {
     validate_uuid(&client_id)?;
     state
         .invoice_service
         .get_outstanding_invoices(&client_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
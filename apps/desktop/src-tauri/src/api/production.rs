@@ -3,6 +3,7 @@
 //! Tauri commands for production management (recipes and orders).
 //! All business logic is in ProductionService - commands just delegate.
 
+use std::collections::HashMap;
 use tauri::State;
 
 use crate::dto::*;
@@ -17,29 +18,29 @@ use crate::state::AppState;
 pub fn list_recipes(
     state: State<AppState>,
     filter: Option<RecipeFilter>,
-) -> Result<Vec<RecipeDto>, String> {
+) -> Result<Vec<RecipeDto>, CommandError> {
     state
         .production_service
         .list_recipes(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single recipe
 #[tauri::command]
-pub fn get_recipe(state: State<AppState>, id: String) -> Result<Option<RecipeDto>, String> {
+pub fn get_recipe(state: State<AppState>, id: String) -> Result<Option<RecipeDto>, CommandError> {
     state
         .production_service
         .get_recipe(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create new recipe
 #[tauri::command]
-pub fn create_recipe(state: State<AppState>, data: CreateRecipeDto) -> Result<RecipeDto, String> {
+pub fn create_recipe(state: State<AppState>, data: CreateRecipeDto) -> Result<RecipeDto, CommandError> {
     state
         .production_service
         .create_recipe(data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Update recipe
@@ -48,20 +49,26 @@ pub fn update_recipe(
     state: State<AppState>,
     id: String,
     data: CreateRecipeDto,
-) -> Result<RecipeDto, String> {
+) -> Result<RecipeDto, CommandError> {
     state
         .production_service
         .update_recipe(&id, data)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Delete recipe
 #[tauri::command]
-pub fn delete_recipe(state: State<AppState>, id: String) -> Result<(), String> {
+pub fn delete_recipe(state: State<AppState>, id: String) -> Result<(), CommandError> {
+    let user_id = state
+        .session
+        .require_user()?
+        .id
+        .to_string();
+
     state
         .production_service
-        .delete_recipe(&id)
-        .map_err(|e| e.to_string())
+        .delete_recipe(&id, &user_id)
+        .map_err(CommandError::from)
 }
 
 /// Get scaled recipe for production planning
@@ -70,11 +77,11 @@ pub fn get_scaled_recipe(
     state: State<AppState>,
     product_pf_id: String,
     batch_count: i32,
-) -> Result<ScaledRecipeDto, String> {
+) -> Result<ScaledRecipeDto, CommandError> {
     state
         .production_service
         .get_scaled_recipe(&product_pf_id, batch_count)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Check recipe availability (can production start?)
@@ -83,11 +90,11 @@ pub fn check_recipe_availability(
     state: State<AppState>,
     product_pf_id: String,
     batch_count: i32,
-) -> Result<AvailabilityDto, String> {
+) -> Result<AvailabilityDto, CommandError> {
     state
         .production_service
         .check_availability(&product_pf_id, batch_count)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -99,11 +106,11 @@ pub fn check_recipe_availability(
 pub fn list_production_orders(
     state: State<AppState>,
     filter: Option<ProductionOrderFilter>,
-) -> Result<Vec<ProductionOrderDto>, String> {
+) -> Result<Vec<ProductionOrderDto>, CommandError> {
     state
         .production_service
         .list_orders(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single production order
@@ -111,11 +118,11 @@ pub fn list_production_orders(
 pub fn get_production_order(
     state: State<AppState>,
     id: String,
-) -> Result<Option<ProductionOrderDto>, String> {
+) -> Result<Option<ProductionOrderDto>, CommandError> {
     state
         .production_service
         .get_order(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create new production order
@@ -123,37 +130,39 @@ pub fn get_production_order(
 pub fn create_production_order(
     state: State<AppState>,
     data: CreateProductionOrderDto,
-) -> Result<ProductionOrderDto, String> {
+) -> Result<ProductionOrderDto, CommandError> {
     let user_id = state
         .session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state
         .production_service
         .create_order(data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
-/// Start production (consume MP via FIFO)
+/// Start production (consume MP with each product strategy)
+///
+/// `manual_lots` maps an MP product id to the lots picked by the operator,
+/// needed for the products on the manual strategy.
 #[tauri::command]
 pub fn start_production(
     state: State<AppState>,
     order_id: String,
-) -> Result<ProductionOrderDto, String> {
+    manual_lots: Option<HashMap<String, Vec<ManualLotPickDto>>>,
+) -> Result<ProductionOrderDto, CommandError> {
     let user_id = state
         .session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state
         .production_service
-        .start_production(&order_id, &user_id)
-        .map_err(|e| e.to_string())
+        .start_production(&order_id, &manual_lots.unwrap_or_default(), &user_id)
+        .map_err(CommandError::from)
 }
 
 /// Complete production (create PF lot)
@@ -162,18 +171,17 @@ pub fn complete_production(
     state: State<AppState>,
     order_id: String,
     data: CompleteProductionDto,
-) -> Result<ProductionCompletionDto, String> {
+) -> Result<ProductionCompletionDto, CommandError> {
     let user_id = state
         .session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state
         .production_service
         .complete_production(&order_id, data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Cancel production order
@@ -182,11 +190,11 @@ pub fn cancel_production(
     state: State<AppState>,
     order_id: String,
     reason: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     state
         .production_service
         .cancel_production(&order_id, reason.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -195,11 +203,11 @@ pub fn cancel_production(
 
 /// Get production dashboard
 #[tauri::command]
-pub fn get_production_dashboard(state: State<AppState>) -> Result<ProductionDashboardDto, String> {
+pub fn get_production_dashboard(state: State<AppState>) -> Result<ProductionDashboardDto, CommandError> {
     state
         .production_service
         .get_dashboard()
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get production calendar
@@ -208,11 +216,11 @@ pub fn get_production_calendar(
     state: State<AppState>,
     from_date: String,
     to_date: String,
-) -> Result<Vec<ProductionCalendarEntry>, String> {
+) -> Result<Vec<ProductionCalendarEntry>, CommandError> {
     state
         .production_service
         .get_calendar(&from_date, &to_date)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Calculate production cost
@@ -220,9 +228,9 @@ pub fn get_production_calendar(
 pub fn calculate_production_cost(
     state: State<AppState>,
     order_id: String,
-) -> Result<ProductionCostDto, String> {
+) -> Result<ProductionCostDto, CommandError> {
     state
         .production_service
         .calculate_cost(&order_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
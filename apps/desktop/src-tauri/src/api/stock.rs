@@ -3,6 +3,7 @@
 //! Tauri commands for stock management.
 //! All business logic is in StockService - commands just delegate.
 
+use manchengo_core::{Gs1Label, Gtin, Label, LabelData, LabelFormat, LabelTemplate, Qty, UnitOfMeasure, UserRole};
 use tauri::State;
 use uuid::Uuid;
 
@@ -10,8 +11,8 @@ use crate::dto::*;
 use crate::state::AppState;
 
 /// Validate that a string is a valid UUID
-fn validate_uuid(id: &str) -> Result<(), String> {
-    Uuid::parse_str(id).map_err(|_| format!("Invalid UUID: {}", id))?;
+fn validate_uuid(id: &str) -> Result<(), CommandError> {
+    Uuid::parse_str(id).map_err(|_| CommandError::invalid_id(id))?;
     Ok(())
 }
 
@@ -24,10 +25,10 @@ fn validate_uuid(id: &str) -> Result<(), String> {
 pub fn list_products_mp(
     state: State<AppState>,
     filter: Option<ProductFilter>,
-) -> Result<Vec<ProductMpDto>, String> {
+) -> Result<Vec<ProductMpDto>, CommandError> {
     state.product_repo
         .list_mp(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single MP product
@@ -35,11 +36,11 @@ pub fn list_products_mp(
 pub fn get_product_mp(
     state: State<AppState>,
     id: String,
-) -> Result<Option<ProductMpDto>, String> {
+) -> Result<Option<ProductMpDto>, CommandError> {
     validate_uuid(&id)?;
     state.product_repo
         .get_mp(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// List PF products
@@ -47,10 +48,10 @@ pub fn get_product_mp(
 pub fn list_products_pf(
     state: State<AppState>,
     filter: Option<ProductFilter>,
-) -> Result<Vec<ProductPfDto>, String> {
+) -> Result<Vec<ProductPfDto>, CommandError> {
     state.product_repo
         .list_pf(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single PF product
@@ -58,11 +59,126 @@ pub fn list_products_pf(
 pub fn get_product_pf(
     state: State<AppState>,
     id: String,
-) -> Result<Option<ProductPfDto>, String> {
+) -> Result<Option<ProductPfDto>, CommandError> {
     validate_uuid(&id)?;
     state.product_repo
         .get_pf(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Get product pack (pieces per carton, cartons per palette)
+#[tauri::command]
+pub fn get_product_pack(
+    state: State<AppState>,
+    product_type: String,
+    product_id: String,
+) -> Result<ProductPackDto, CommandError> {
+    validate_uuid(&product_id)?;
+    let pack = state.product_repo
+        .get_pack(&product_type, &product_id)?;
+
+    Ok(ProductPackDto {
+        product_type,
+        product_id,
+        pieces_per_carton: pack.pieces_per_carton,
+        cartons_per_pallet: pack.cartons_per_pallet,
+    })
+}
+
+/// Set product pack (admin)
+#[tauri::command]
+pub fn set_product_pack(
+    state: State<AppState>,
+    pack: ProductPackDto,
+) -> Result<(), CommandError> {
+    state.session
+        .require_role(UserRole::Admin)?;
+    validate_uuid(&pack.product_id)?;
+
+    if !matches!(pack.product_type.as_str(), "MP" | "PF") {
+        return Err(CommandError::validation("product_type", format!("Type de produit invalide: {}", pack.product_type)));
+    }
+    if [pack.pieces_per_carton, pack.cartons_per_pallet]
+        .iter()
+        .flatten()
+        .any(|v| *v <= 0.0)
+    {
+        return Err(CommandError::validation("pieces_per_carton", "Les conditionnements doivent etre positifs"));
+    }
+
+    state.product_repo
+        .set_pack(&pack)
+        .map_err(CommandError::from)
+}
+
+/// Get product lot selection strategy (FIFO, FEFO, manual, minimum shelf life)
+#[tauri::command]
+pub fn get_product_lot_strategy(
+    state: State<AppState>,
+    product_type: String,
+    product_id: String,
+) -> Result<ProductLotStrategyDto, CommandError> {
+    validate_uuid(&product_id)?;
+    let strategy = state.product_repo
+        .get_lot_strategy(&product_type, &product_id)?;
+
+    Ok(ProductLotStrategyDto {
+        product_type,
+        product_id,
+        strategy,
+    })
+}
+
+/// Set product lot selection strategy (admin)
+#[tauri::command]
+pub fn set_product_lot_strategy(
+    state: State<AppState>,
+    data: ProductLotStrategyDto,
+) -> Result<(), CommandError> {
+    state.session
+        .require_role(UserRole::Admin)?;
+    validate_uuid(&data.product_id)?;
+
+    if !matches!(data.product_type.as_str(), "MP" | "PF") {
+        return Err(CommandError::validation("product_type", format!("Type de produit invalide: {}", data.product_type)));
+    }
+    if data.strategy.min_shelf_life_days() == Some(0) {
+        return Err(CommandError::validation("min_days", "La duree de vie minimale doit etre positive"));
+    }
+
+    state.product_repo
+        .set_lot_strategy(&data.product_type, &data.product_id, &data.strategy)
+        .map_err(CommandError::from)
+}
+
+/// Set PF product EAN-13 and carton GTIN-14 (admin)
+#[tauri::command]
+pub fn set_product_gtins(
+    state: State<AppState>,
+    product_id: String,
+    gtin: Option<String>,
+    carton_gtin: Option<String>,
+) -> Result<(), CommandError> {
+    state.session
+        .require_role(UserRole::Admin)?;
+    validate_uuid(&product_id)?;
+
+    let parse = |value: Option<String>| {
+        value
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| Gtin::parse(&v))
+            .transpose()
+            .map_err(CommandError::from)
+    };
+    let gtin = parse(gtin)?;
+    let carton_gtin = parse(carton_gtin)?;
+    if gtin.as_ref().is_some_and(|g| !g.is_ean13()) {
+        return Err(CommandError::validation("gtin", "Le GTIN de l'unite consommateur doit etre un EAN-13"));
+    }
+
+    state.product_repo
+        .set_pf_gtins(&product_id, gtin.as_ref(), carton_gtin.as_ref())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -74,10 +190,10 @@ pub fn get_product_pf(
 pub fn list_lots_mp(
     state: State<AppState>,
     filter: Option<LotFilter>,
-) -> Result<Vec<LotMpDto>, String> {
+) -> Result<Vec<LotMpDto>, CommandError> {
     state.lot_repo
         .list_mp(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single MP lot
@@ -85,11 +201,11 @@ pub fn list_lots_mp(
 pub fn get_lot_mp(
     state: State<AppState>,
     id: String,
-) -> Result<Option<LotMpDto>, String> {
+) -> Result<Option<LotMpDto>, CommandError> {
     validate_uuid(&id)?;
     state.lot_repo
         .get_mp(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// List PF lots
@@ -97,10 +213,10 @@ pub fn get_lot_mp(
 pub fn list_lots_pf(
     state: State<AppState>,
     filter: Option<LotFilter>,
-) -> Result<Vec<LotPfDto>, String> {
+) -> Result<Vec<LotPfDto>, CommandError> {
     state.lot_repo
         .list_pf(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get single PF lot
@@ -108,11 +224,63 @@ pub fn list_lots_pf(
 pub fn get_lot_pf(
     state: State<AppState>,
     id: String,
-) -> Result<Option<LotPfDto>, String> {
+) -> Result<Option<LotPfDto>, CommandError> {
     validate_uuid(&id)?;
     state.lot_repo
         .get_pf(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Render a PF lot label (SVG, PNG, ZPL or EPL bytes)
+///
+/// The DLC is the lot expiry date, the net weight its remaining quantity.
+#[tauri::command]
+pub fn render_lot_pf_label(
+    state: State<AppState>,
+    id: String,
+    company_header: String,
+    format: LabelFormat,
+    template: Option<LabelTemplate>,
+) -> Result<Vec<u8>, CommandError> {
+    validate_uuid(&id)?;
+    let lot = state.lot_repo
+        .get_pf(&id)?
+        .ok_or_else(|| CommandError::not_found("LotPf", id.as_str()))?;
+    let qr_payload = lot.qr_code
+        .ok_or_else(|| CommandError::business_rule(format!("Lot {} sans QR code", lot.lot_number)))?;
+
+    let data = LabelData {
+        company_header,
+        product_name: lot.product_name,
+        lot_number: lot.lot_number,
+        dlc: lot.expiry_date.as_deref().and_then(|d| d.parse().ok()),
+        ddm: None,
+        net_weight: UnitOfMeasure::from_code(&lot.unit).map(|unit| (lot.quantity_remaining, unit)),
+        qr_payload,
+    };
+
+    Label::new(template.unwrap_or_default(), data)
+        .render(format)
+        .map_err(CommandError::from)
+}
+
+/// Resolve a scanned GS1-128 carton label to its PF lot
+#[tauri::command]
+pub fn resolve_gs1_scan(
+    state: State<AppState>,
+    code: String,
+) -> Result<LotPfDto, CommandError> {
+    let label = Gs1Label::decode(&code)?;
+    let lot_number = label.lot
+        .ok_or_else(|| CommandError::business_rule("Code GS1 sans numero de lot (10)"))?;
+
+    let product_id = state.product_repo
+        .find_pf_by_gtin(&label.gtin)?
+        .ok_or_else(|| CommandError::not_found("ProductPf", label.gtin.to_string()))?;
+
+    state.lot_repo
+        .find_pf_by_number(&product_id, &lot_number)?
+        .ok_or_else(|| CommandError::not_found("LotPf", format!("{} / {}", label.gtin, lot_number)))
 }
 
 /// Block lot
@@ -122,11 +290,11 @@ pub fn block_lot(
     lot_id: String,
     product_type: String,
     reason: String,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     validate_uuid(&lot_id)?;
     state.stock_service
         .block_lot(&lot_id, &product_type, &reason)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Unblock lot
@@ -135,11 +303,11 @@ pub fn unblock_lot(
     state: State<AppState>,
     lot_id: String,
     product_type: String,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     validate_uuid(&lot_id)?;
     state.stock_service
         .unblock_lot(&lot_id, &product_type)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -148,26 +316,26 @@ pub fn unblock_lot(
 
 /// Get MP stock levels
 #[tauri::command]
-pub fn get_stock_mp(state: State<AppState>) -> Result<Vec<StockLevelDto>, String> {
+pub fn get_stock_mp(state: State<AppState>) -> Result<Vec<StockLevelDto>, CommandError> {
     state.stock_service
         .get_stock_mp()
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get PF stock levels
 #[tauri::command]
-pub fn get_stock_pf(state: State<AppState>) -> Result<Vec<StockLevelDto>, String> {
+pub fn get_stock_pf(state: State<AppState>) -> Result<Vec<StockLevelDto>, CommandError> {
     state.stock_service
         .get_stock_pf()
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get stock alerts
 #[tauri::command]
-pub fn get_stock_alerts(state: State<AppState>) -> Result<StockAlertsDto, String> {
+pub fn get_stock_alerts(state: State<AppState>) -> Result<StockAlertsDto, CommandError> {
     state.stock_service
         .get_stock_alerts()
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get expiring lots
@@ -175,26 +343,28 @@ pub fn get_stock_alerts(state: State<AppState>) -> Result<StockAlertsDto, String
 pub fn get_expiring_lots(
     state: State<AppState>,
     days: Option<i32>,
-) -> Result<Vec<ExpiringLotDto>, String> {
+) -> Result<Vec<ExpiringLotDto>, CommandError> {
     state.stock_service
         .get_expiring_lots(days.unwrap_or(30))
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
 // FIFO COMMANDS
 // ============================================================================
 
-/// Preview FIFO consumption
+/// Preview FIFO consumption (with the product lot selection strategy)
 #[tauri::command]
 pub fn preview_fifo_consumption(
     state: State<AppState>,
     product_id: String,
-    quantity: f64,
-) -> Result<FifoPreviewDto, String> {
+    quantity: Qty,
+    warehouse_id: Option<String>,
+    manual_lots: Option<Vec<ManualLotPickDto>>,
+) -> Result<FifoPreviewDto, CommandError> {
     state.stock_service
-        .preview_fifo(&product_id, quantity)
-        .map_err(|e| e.to_string())
+        .preview_fifo(&product_id, warehouse_id.as_deref(), quantity, &manual_lots.unwrap_or_default())
+        .map_err(CommandError::from)
 }
 
 /// Execute FIFO consumption
@@ -202,27 +372,30 @@ pub fn preview_fifo_consumption(
 pub fn consume_fifo(
     state: State<AppState>,
     product_id: String,
-    quantity: f64,
+    quantity: Qty,
     origin: String,
     reference_type: Option<String>,
     reference_id: Option<String>,
-) -> Result<FifoResultDto, String> {
+    warehouse_id: Option<String>,
+    manual_lots: Option<Vec<ManualLotPickDto>>,
+) -> Result<FifoResultDto, CommandError> {
     let user_id = state.session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state.stock_service
         .consume_fifo(
             &product_id,
+            warehouse_id.as_deref(),
             quantity,
+            &manual_lots.unwrap_or_default(),
             &origin,
             reference_type.as_deref(),
             reference_id.as_deref(),
             &user_id,
         )
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -234,16 +407,311 @@ pub fn consume_fifo(
 pub fn create_reception(
     state: State<AppState>,
     data: CreateReceptionDto,
-) -> Result<ReceptionDto, String> {
+) -> Result<ReceptionDto, CommandError> {
     let user_id = state.session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state.stock_service
         .create_reception(data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// WAREHOUSE COMMANDS
+// ============================================================================
+
+/// List warehouses
+#[tauri::command]
+pub fn list_warehouses(
+    state: State<AppState>,
+    active_only: Option<bool>,
+) -> Result<Vec<WarehouseDto>, CommandError> {
+    state.warehouse_repo
+        .list_warehouses(active_only.unwrap_or(true))
+        .map_err(CommandError::from)
+}
+
+/// Create warehouse (admin only)
+#[tauri::command]
+pub fn create_warehouse(
+    state: State<AppState>,
+    data: CreateWarehouseDto,
+) -> Result<WarehouseDto, CommandError> {
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state.stock_service
+        .create_warehouse(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// List locations of a warehouse
+#[tauri::command]
+pub fn list_locations(
+    state: State<AppState>,
+    warehouse_id: String,
+) -> Result<Vec<LocationDto>, CommandError> {
+    state.warehouse_repo
+        .list_locations(&warehouse_id)
+        .map_err(CommandError::from)
+}
+
+/// Create location (admin only)
+#[tauri::command]
+pub fn create_location(
+    state: State<AppState>,
+    data: CreateLocationDto,
+) -> Result<LocationDto, CommandError> {
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state.stock_service
+        .create_location(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Render a location QR label (PDF or ZPL)
+#[tauri::command]
+pub fn render_location_label(
+    state: State<AppState>,
+    id: String,
+    company_header: String,
+    format: LabelFormat,
+    template: Option<LabelTemplate>,
+) -> Result<Vec<u8>, CommandError> {
+    validate_uuid(&id)?;
+    let location = state.warehouse_repo
+        .get_location(&id)?
+        .ok_or_else(|| CommandError::not_found("Location", id.as_str()))?;
+    let qr_payload = location.qr_code
+        .ok_or_else(|| CommandError::business_rule(format!("Emplacement {} sans QR code", location.code)))?;
+
+    let data = LabelData {
+        company_header,
+        product_name: location.name,
+        lot_number: format!("{} / {}", location.warehouse_code, location.code),
+        dlc: None,
+        ddm: None,
+        net_weight: None,
+        qr_payload,
+    };
+
+    Label::new(template.unwrap_or_default(), data)
+        .render(format)
+        .map_err(CommandError::from)
+}
+
+/// Resolve a scanned location label
+#[tauri::command]
+pub fn resolve_location_scan(
+    state: State<AppState>,
+    code: String,
+) -> Result<LocationDto, CommandError> {
+    state.stock_service
+        .resolve_location_scan(&code)
+        .map_err(CommandError::from)
+}
+
+/// List lots waiting for put-away
+#[tauri::command]
+pub fn list_pending_put_away(
+    state: State<AppState>,
+    warehouse_id: Option<String>,
+) -> Result<Vec<PendingPutAwayDto>, CommandError> {
+    state.warehouse_repo
+        .list_pending_put_away(warehouse_id.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Put a received lot away into a storage location
+#[tauri::command]
+pub fn put_away_lot(
+    state: State<AppState>,
+    data: PutAwayDto,
+) -> Result<LocationDto, CommandError> {
+    validate_uuid(&data.lot_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.stock_service
+        .put_away(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Get stock levels per warehouse
+#[tauri::command]
+pub fn get_warehouse_stock(
+    state: State<AppState>,
+    warehouse_id: Option<String>,
+) -> Result<Vec<WarehouseStockDto>, CommandError> {
+    state.stock_service
+        .get_warehouse_stock(warehouse_id.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Get products below their warehouse minimum
+#[tauri::command]
+pub fn get_warehouse_alerts(
+    state: State<AppState>,
+    warehouse_id: Option<String>,
+) -> Result<Vec<WarehouseStockDto>, CommandError> {
+    state.stock_service
+        .get_warehouse_alerts(warehouse_id.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Set the minimum stock of a product in a warehouse
+#[tauri::command]
+pub fn set_warehouse_threshold(
+    state: State<AppState>,
+    data: SetWarehouseThresholdDto,
+) -> Result<(), CommandError> {
+    state.session
+        .require_role(UserRole::Appro)?;
+
+    state.stock_service
+        .set_warehouse_threshold(data)
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// TRANSFER COMMANDS
+// ============================================================================
+
+/// List inter-warehouse transfers
+#[tauri::command]
+pub fn list_transfers(
+    state: State<AppState>,
+    filter: Option<TransferFilter>,
+) -> Result<Vec<TransferOrderDto>, CommandError> {
+    state.transfer_service
+        .list_transfers(filter.unwrap_or_default())
+        .map_err(CommandError::from)
+}
+
+/// Get transfer with its picked lots
+#[tauri::command]
+pub fn get_transfer(
+    state: State<AppState>,
+    id: String,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&id)?;
+    state.transfer_service
+        .get_transfer(&id)
+        .map_err(CommandError::from)
+}
+
+/// Create draft transfer between two warehouses
+#[tauri::command]
+pub fn create_transfer(
+    state: State<AppState>,
+    data: CreateTransferDto,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&data.from_warehouse_id)?;
+    validate_uuid(&data.to_warehouse_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .create_transfer(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Pick a lot (by id or scanned QR code)
+#[tauri::command]
+pub fn add_transfer_line(
+    state: State<AppState>,
+    data: AddTransferLineDto,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&data.transfer_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .add_line(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Remove a picked lot
+#[tauri::command]
+pub fn remove_transfer_line(
+    state: State<AppState>,
+    transfer_id: String,
+    line_id: String,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&transfer_id)?;
+    validate_uuid(&line_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .remove_line(&transfer_id, &line_id, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Ship transfer (DRAFT -> SHIPPED)
+#[tauri::command]
+pub fn ship_transfer(
+    state: State<AppState>,
+    id: String,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .ship(&id, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Receive transfer (SHIPPED -> RECEIVED), with discrepancies
+#[tauri::command]
+pub fn receive_transfer(
+    state: State<AppState>,
+    data: ReceiveTransferDto,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&data.transfer_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .receive(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Cancel a draft transfer
+#[tauri::command]
+pub fn cancel_transfer(
+    state: State<AppState>,
+    id: String,
+) -> Result<TransferOrderDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.transfer_service
+        .cancel(&id, &user_id)
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -255,16 +723,15 @@ pub fn create_reception(
 pub fn adjust_inventory(
     state: State<AppState>,
     data: AdjustInventoryDto,
-) -> Result<InventoryAdjustmentDto, String> {
+) -> Result<InventoryAdjustmentDto, CommandError> {
     let user_id = state.session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state.stock_service
         .adjust_inventory(data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Declare loss
@@ -272,16 +739,233 @@ pub fn adjust_inventory(
 pub fn declare_loss(
     state: State<AppState>,
     data: DeclareLossDto,
-) -> Result<LossDeclarationDto, String> {
+) -> Result<LossDeclarationDto, CommandError> {
     let user_id = state.session
-        .require_user()
-        .map_err(|e| e.to_string())?
+        .require_user()?
         .id
         .to_string();
 
     state.stock_service
         .declare_loss(data, &user_id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// INVENTORY SESSION COMMANDS
+// ============================================================================
+
+/// List inventory sessions
+#[tauri::command]
+pub fn list_inventory_sessions(
+    state: State<AppState>,
+    filter: Option<InventoryFilter>,
+) -> Result<Vec<InventorySessionDto>, CommandError> {
+    state.inventory_service
+        .list_sessions(filter.unwrap_or_default())
+        .map_err(CommandError::from)
+}
+
+/// Get inventory session with its count sheet
+#[tauri::command]
+pub fn get_inventory_session(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&id)?;
+    state.inventory_service
+        .get_session(&id)
+        .map_err(CommandError::from)
+}
+
+/// Open an inventory session, freezing the warehouse lots
+#[tauri::command]
+pub fn open_inventory_session(
+    state: State<AppState>,
+    data: OpenInventoryDto,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&data.warehouse_id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .open_session(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Record a count, by lot id or scanned QR code
+#[tauri::command]
+pub fn record_inventory_count(
+    state: State<AppState>,
+    data: RecordCountDto,
+) -> Result<InventoryCountResultDto, CommandError> {
+    validate_uuid(&data.session_id)?;
+    if let Some(ref lot_id) = data.lot_id {
+        validate_uuid(lot_id)?;
+    }
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .record_count(data, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Close counting and submit the variances for approval
+#[tauri::command]
+pub fn submit_inventory_session(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .submit(&id, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Send a session in review back to counting (manager only)
+#[tauri::command]
+pub fn reopen_inventory_session(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .reopen(&id, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Variance report of an inventory session
+#[tauri::command]
+pub fn get_inventory_variance_report(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventoryVarianceReportDto, CommandError> {
+    validate_uuid(&id)?;
+    state.inventory_service
+        .variance_report(&id)
+        .map_err(CommandError::from)
+}
+
+/// Approve the variances and post the adjustments (manager only)
+#[tauri::command]
+pub fn approve_inventory_session(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .approve(&id, &user_id)
+        .map_err(CommandError::from)
+}
+
+/// Cancel an inventory session not yet approved
+#[tauri::command]
+pub fn cancel_inventory_session(
+    state: State<AppState>,
+    id: String,
+) -> Result<InventorySessionDto, CommandError> {
+    validate_uuid(&id)?;
+    let user_id = state.session
+        .require_user()?
+        .id
+        .to_string();
+
+    state.inventory_service
+        .cancel(&id, &user_id)
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// STOCK VALUATION COMMANDS
+// ============================================================================
+
+/// Stock valuation (CMUP and FIFO) at the end of a day, today by default
+#[tauri::command]
+pub fn get_stock_valuation(
+    state: State<AppState>,
+    filter: Option<StockValuationFilter>,
+) -> Result<StockValuationReportDto, CommandError> {
+    let filter = filter.unwrap_or_default();
+    if let Some(ref warehouse_id) = filter.warehouse_id {
+        validate_uuid(warehouse_id)?;
+    }
+    state.session
+        .require_user()?;
+
+    state.valuation_service
+        .report(filter)
+        .map_err(CommandError::from)
+}
+
+/// Running weighted average cost of a product
+#[tauri::command]
+pub fn get_product_cost_history(
+    state: State<AppState>,
+    product_type: String,
+    product_id: String,
+    as_of: Option<String>,
+) -> Result<Vec<CostHistoryDto>, CommandError> {
+    validate_uuid(&product_id)?;
+    state.session
+        .require_user()?;
+
+    state.valuation_service
+        .cost_history(&product_type, &product_id, as_of.as_deref())
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// POINT-IN-TIME STOCK COMMANDS
+// ============================================================================
+
+/// Stock per product, lot and warehouse at the end of a past day
+#[tauri::command]
+pub fn get_stock_as_of(
+    state: State<AppState>,
+    filter: StockAsOfFilter,
+) -> Result<StockAsOfDto, CommandError> {
+    if let Some(ref product_id) = filter.product_id {
+        validate_uuid(product_id)?;
+    }
+    if let Some(ref warehouse_id) = filter.warehouse_id {
+        validate_uuid(warehouse_id)?;
+    }
+    state.session
+        .require_user()?;
+
+    state.stock_history_service
+        .stock_as_of(filter)
+        .map_err(CommandError::from)
+}
+
+/// List daily closing snapshots
+#[tauri::command]
+pub fn list_stock_snapshots(
+    state: State<AppState>,
+    limit: Option<u32>,
+) -> Result<Vec<StockSnapshotDto>, CommandError> {
+    state.stock_history_service
+        .list_snapshots(limit.unwrap_or(31))
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -293,10 +977,10 @@ pub fn declare_loss(
 pub fn list_movements(
     state: State<AppState>,
     filter: Option<MovementFilter>,
-) -> Result<Vec<MovementDto>, String> {
+) -> Result<Vec<MovementDto>, CommandError> {
     state.movement_repo
         .list(filter.unwrap_or_default())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get movement history for product
@@ -306,10 +990,45 @@ pub fn get_movement_history(
     product_type: String,
     product_id: String,
     limit: Option<u32>,
-) -> Result<Vec<MovementDto>, String> {
+) -> Result<Vec<MovementDto>, CommandError> {
     state.movement_repo
         .get_product_history(&product_type, &product_id, limit.unwrap_or(50))
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// LEDGER INTEGRITY COMMANDS
+// ============================================================================
+
+/// Check stock ledger integrity (lots vs. movements)
+#[tauri::command]
+pub fn check_stock_integrity(state: State<AppState>) -> Result<LedgerIntegrityReportDto, CommandError> {
+    state.session
+        .require_role(UserRole::Admin)?;
+
+    state.integrity_service
+        .check()
+        .map_err(CommandError::from)
+}
+
+/// Apply corrective adjustments on approved lots (admin only)
+#[tauri::command]
+pub fn repair_stock_integrity(
+    state: State<AppState>,
+    data: RepairLedgerDto,
+) -> Result<LedgerRepairResultDto, CommandError> {
+    for lot_id in &data.lot_ids {
+        validate_uuid(lot_id)?;
+    }
+
+    let user_id = state.session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state.integrity_service
+        .repair(data, &user_id)
+        .map_err(CommandError::from)
 }
 
 // ============================================================================
@@ -321,10 +1040,10 @@ pub fn get_movement_history(
 pub fn list_suppliers(
     state: State<AppState>,
     active_only: Option<bool>,
-) -> Result<Vec<crate::repositories::supplier_repo::SupplierDto>, String> {
+) -> Result<Vec<crate::repositories::supplier_repo::SupplierDto>, CommandError> {
     state.supplier_repo
         .list(active_only.unwrap_or(true))
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get supplier
@@ -332,10 +1051,10 @@ pub fn list_suppliers(
 pub fn get_supplier(
     state: State<AppState>,
     id: String,
-) -> Result<Option<crate::repositories::supplier_repo::SupplierDto>, String> {
+) -> Result<Option<crate::repositories::supplier_repo::SupplierDto>, CommandError> {
     state.supplier_repo
         .get(&id)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Create supplier
@@ -343,14 +1062,20 @@ pub fn get_supplier(
 pub fn create_supplier(
     state: State<AppState>,
     data: crate::repositories::supplier_repo::CreateSupplierDto,
-) -> Result<crate::repositories::supplier_repo::SupplierDto, String> {
+) -> Result<crate::repositories::supplier_repo::SupplierDto, CommandError> {
+    manchengo_core::fiscal_id::check_identifiers(
+        data.nif.as_deref(),
+        data.nis.as_deref(),
+        data.rc.as_deref(),
+        data.article_imposition.as_deref(),
+    )?;
+
     let id = manchengo_core::EntityId::new().to_string();
-    let code = state.supplier_repo.generate_code().map_err(|e| e.to_string())?;
+    let code = state.supplier_repo.generate_code()?;
 
-    state.supplier_repo.create(&id, &code, &data).map_err(|e| e.to_string())?;
+    state.supplier_repo.create(&id, &code, &data)?;
 
     state.supplier_repo
-        .get(&id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Fournisseur non trouve apres creation".to_string())
+        .get(&id)?
+        .ok_or_else(|| CommandError::internal("Fournisseur non trouve apres creation"))
 }
@@ -9,48 +9,48 @@ use crate::state::AppState;
 
 /// Push local events to server
 #[tauri::command]
-pub async fn sync_push(state: State<'_, AppState>) -> Result<PushResultDto, String> {
+pub async fn sync_push(state: State<'_, AppState>) -> Result<PushResultDto, CommandError> {
     let token = state.session
         .get_auth_token()
-        .ok_or("Non authentifie")?;
+        .ok_or_else(CommandError::unauthorized)?;
 
     state.sync_service
         .push(&token)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Pull events from server
 #[tauri::command]
-pub async fn sync_pull(state: State<'_, AppState>) -> Result<PullResultDto, String> {
+pub async fn sync_pull(state: State<'_, AppState>) -> Result<PullResultDto, CommandError> {
     let token = state.session
         .get_auth_token()
-        .ok_or("Non authentifie")?;
+        .ok_or_else(CommandError::unauthorized)?;
 
     state.sync_service
         .pull(&token)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Full sync (push then pull)
 #[tauri::command]
-pub async fn sync_full(state: State<'_, AppState>) -> Result<SyncResultDto, String> {
+pub async fn sync_full(state: State<'_, AppState>) -> Result<SyncResultDto, CommandError> {
     let token = state.session
         .get_auth_token()
-        .ok_or("Non authentifie")?;
+        .ok_or_else(CommandError::unauthorized)?;
 
     state.sync_service
         .sync(&token)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 /// Get sync status
 #[tauri::command]
-pub async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatusDto, String> {
+pub async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatusDto, CommandError> {
     state.sync_service
         .get_status()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
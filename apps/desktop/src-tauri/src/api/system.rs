@@ -2,6 +2,9 @@
 //!
 //! System information, health checks, and configuration commands.
 
+use chrono::NaiveDate;
+use manchengo_core::{Clock, UserRole};
+use manchengo_database::profiler::QueryProfiler;
 use tauri::State;
 
 use crate::dto::*;
@@ -24,7 +27,7 @@ pub fn get_app_info(state: State<AppState>) -> AppInfo {
 
 /// Get health status
 #[tauri::command]
-pub fn get_health_status(state: State<AppState>) -> Result<HealthStatus, String> {
+pub fn get_health_status(state: State<AppState>) -> Result<HealthStatus, CommandError> {
     let db_ok = state.db.with_connection(|conn| {
         match conn.query_row("SELECT 1", [], |_| Ok(true)) {
             Ok(v) => Ok(v),
@@ -46,10 +49,9 @@ pub fn get_health_status(state: State<AppState>) -> Result<HealthStatus, String>
 
 /// Get database statistics
 #[tauri::command]
-pub fn get_database_stats(state: State<AppState>) -> Result<DatabaseStats, String> {
+pub fn get_database_stats(state: State<AppState>) -> Result<DatabaseStats, CommandError> {
     let _user_id = state.session
-        .require_user()
-        .map_err(|e| e.to_string())?;
+        .require_user()?;
 
     let config = state.config.blocking_read();
 
@@ -64,6 +66,34 @@ pub fn get_database_stats(state: State<AppState>) -> Result<DatabaseStats, Strin
     let movements = state.movement_repo.count().unwrap_or(0);
     let pending_sync = state.event_store.unsynced_count().unwrap_or(0) as u64;
 
+    if QueryProfiler::config().explain_slow_queries {
+        let _ = state.db.with_connection(QueryProfiler::explain_slow_queries);
+    }
+
+    let query_stats = QueryProfiler::stats(20)
+        .into_iter()
+        .map(|s| QueryStatDto {
+            sql: s.sql,
+            params: s.params,
+            count: s.count,
+            total_ms: s.total_ms,
+            p50_ms: s.p50_ms,
+            p95_ms: s.p95_ms,
+            max_ms: s.max_ms,
+        })
+        .collect();
+
+    let slow_queries = QueryProfiler::slow_queries()
+        .into_iter()
+        .map(|q| SlowQueryDto {
+            sql: q.sql,
+            params: q.params,
+            duration_ms: q.duration_ms,
+            recorded_at: q.recorded_at,
+            query_plan: q.query_plan,
+        })
+        .collect();
+
     Ok(DatabaseStats {
         size_bytes: size,
         tables_count: 20, // Approximate
@@ -73,18 +103,20 @@ pub fn get_database_stats(state: State<AppState>) -> Result<DatabaseStats, Strin
         lots_pf_count: lots_pf,
         movements_count: movements,
         pending_sync_events: pending_sync,
+        query_stats,
+        slow_queries,
     })
 }
 
 /// Check connectivity
 #[tauri::command]
-pub async fn check_connectivity(state: State<'_, AppState>) -> Result<bool, String> {
+pub async fn check_connectivity(state: State<'_, AppState>) -> Result<bool, CommandError> {
     Ok(state.sync_service.check_connectivity().await)
 }
 
 /// Get device info
 #[tauri::command]
-pub fn get_device_info(state: State<AppState>) -> Result<serde_json::Value, String> {
+pub fn get_device_info(state: State<AppState>) -> Result<serde_json::Value, CommandError> {
     let config = state.config.blocking_read();
 
     Ok(serde_json::json!({
@@ -97,14 +129,159 @@ pub fn get_device_info(state: State<AppState>) -> Result<serde_json::Value, Stri
 
 /// Clear local cache
 #[tauri::command]
-pub fn clear_local_cache(_state: State<AppState>) -> Result<(), String> {
+pub fn clear_local_cache(_state: State<AppState>) -> Result<(), CommandError> {
     use crate::core::AppConfig;
 
     let cache_dir = AppConfig::cache_dir();
     if cache_dir.exists() {
-        std::fs::remove_dir_all(&cache_dir).map_err(|e| e.to_string())?;
-        std::fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        std::fs::remove_dir_all(&cache_dir)?;
+        std::fs::create_dir_all(&cache_dir)?;
     }
 
     Ok(())
 }
+
+// ============================================================================
+// BUSINESS DATE
+// ============================================================================
+
+/// Get the business date override of the session (None = real date)
+#[tauri::command]
+pub fn get_business_date(state: State<AppState>) -> Result<Option<String>, CommandError> {
+    state.session.require_user()?;
+
+    Ok(state
+        .session
+        .business_date()
+        .map(|d| d.format("%Y-%m-%d").to_string()))
+}
+
+/// Backdate entries of the session (YYYY-MM-DD), or clear with None
+#[tauri::command]
+pub fn set_business_date(state: State<AppState>, date: Option<String>) -> Result<Option<String>, CommandError> {
+    let date = date
+        .map(|d| {
+            NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+                .map_err(|_| CommandError::validation("business_date", format!("Date invalide: {}", d)))
+        })
+        .transpose()?;
+
+    let previous = state.session.business_date();
+    let user = state.session.set_business_date(date)?;
+    let current = state.session.business_date();
+
+    if previous != current {
+        let action = if current.is_some() { "BUSINESS_DATE_SET" } else { "BUSINESS_DATE_CLEARED" };
+        state.audit_repo.record(
+            &user.id.to_string(),
+            action,
+            "SESSION",
+            None,
+            Some(&serde_json::json!({ "business_date": previous })),
+            Some(&serde_json::json!({ "business_date": current })),
+        )?;
+    }
+
+    Ok(current.map(|d| d.format("%Y-%m-%d").to_string()))
+}
+
+// ============================================================================
+// MAINTENANCE (ADMIN)
+// ============================================================================
+
+/// Get database maintenance run history
+#[tauri::command]
+pub fn get_maintenance_history(
+    state: State<AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<MaintenanceRunDto>, CommandError> {
+    state
+        .session
+        .require_role(UserRole::Admin)?;
+
+    state
+        .maintenance_service
+        .history(limit.unwrap_or(50))
+        .map_err(CommandError::from)
+}
+
+/// Run a maintenance job now (skipped if sync or production is active)
+#[tauri::command]
+pub fn run_maintenance_job(state: State<AppState>, job: String) -> Result<MaintenanceRunDto, CommandError> {
+    state
+        .session
+        .require_role(UserRole::Admin)?;
+
+    state
+        .maintenance_service
+        .run_job(&job)
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// FISCAL ARCHIVES (ADMIN)
+// ============================================================================
+
+/// List archived fiscal years
+#[tauri::command]
+pub fn list_fiscal_archives(state: State<AppState>) -> Result<Vec<FiscalArchiveDto>, CommandError> {
+    state
+        .session
+        .require_role(UserRole::Admin)?;
+
+    state.archive_service.list().map_err(CommandError::from)
+}
+
+/// Archive a closed fiscal year into a read-only file
+#[tauri::command]
+pub fn archive_fiscal_year(state: State<AppState>, year: i32) -> Result<ArchiveYearResultDto, CommandError> {
+    let user_id = state
+        .session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state
+        .archive_service
+        .archive_year(year, &user_id)
+        .map_err(CommandError::from)
+}
+
+// ============================================================================
+// TRASH (ADMIN)
+// ============================================================================
+
+/// List soft-deleted entities, optionally filtered by type
+#[tauri::command]
+pub fn list_trash(
+    state: State<AppState>,
+    entity_type: Option<String>,
+) -> Result<Vec<TrashItemDto>, CommandError> {
+    state
+        .session
+        .require_role(UserRole::Admin)?;
+
+    state
+        .trash_service
+        .list(entity_type.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Restore a soft-deleted entity
+#[tauri::command]
+pub fn restore_from_trash(
+    state: State<AppState>,
+    entity_type: String,
+    id: String,
+) -> Result<(), CommandError> {
+    let user_id = state
+        .session
+        .require_role(UserRole::Admin)?
+        .id
+        .to_string();
+
+    state
+        .trash_service
+        .restore(&entity_type, &id, &user_id)
+        .map_err(CommandError::from)
+}
@@ -20,6 +20,41 @@ pub struct AppConfig {
     pub device_name: String,
     /// Offline mode flag
     pub offline_mode: bool,
+    /// Maintenance check interval in seconds (default: 900)
+    #[serde(default = "default_maintenance_interval_secs")]
+    pub maintenance_interval_secs: u64,
+    /// Synced events kept locally, in days (default: 90)
+    #[serde(default = "default_event_retention_days")]
+    pub event_retention_days: i64,
+    /// QR scans kept locally, in days (default: 180)
+    #[serde(default = "default_qr_scan_retention_days")]
+    pub qr_scan_retention_days: i64,
+    /// Queries slower than this are logged, in milliseconds (default: 200)
+    #[serde(default = "default_slow_query_threshold_ms")]
+    pub slow_query_threshold_ms: u64,
+    /// Run EXPLAIN QUERY PLAN on slow queries (default: false)
+    #[serde(default)]
+    pub explain_slow_queries: bool,
+    /// Document number prefix of this PC, e.g. "P2" (default: derived from
+    /// the device ID)
+    #[serde(default)]
+    pub document_prefix: Option<String>,
+}
+
+fn default_maintenance_interval_secs() -> u64 {
+    900
+}
+
+fn default_event_retention_days() -> i64 {
+    90
+}
+
+fn default_qr_scan_retention_days() -> i64 {
+    180
+}
+
+fn default_slow_query_threshold_ms() -> u64 {
+    200
 }
 
 impl Default for AppConfig {
@@ -33,6 +68,12 @@ impl Default for AppConfig {
                 .map(|h| h.to_string_lossy().to_string())
                 .unwrap_or_else(|_| "Desktop".to_string()),
             offline_mode: false,
+            maintenance_interval_secs: default_maintenance_interval_secs(),
+            event_retention_days: default_event_retention_days(),
+            qr_scan_retention_days: default_qr_scan_retention_days(),
+            slow_query_threshold_ms: default_slow_query_threshold_ms(),
+            explain_slow_queries: false,
+            document_prefix: None,
         }
     }
 }
@@ -10,5 +10,5 @@ pub mod security;
 pub mod scheduler;
 
 pub use config::AppConfig;
-pub use security::{AuthenticatedUser, SessionManager};
-pub use scheduler::BackgroundScheduler;
+pub use security::{AuthenticatedUser, SecurityError, SessionManager};
+pub use scheduler::{Activity, ActivityTracker, BackgroundScheduler};
@@ -1,8 +1,9 @@
 //! Background Scheduler
 //!
-//! Manages background tasks like sync, expiry checks, and connectivity monitoring.
+//! Manages background tasks like sync, expiry checks, connectivity monitoring
+//! and database maintenance.
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -12,6 +13,7 @@ use tracing::{error, info, warn};
 /// Background task scheduler
 pub struct BackgroundScheduler {
     running: Arc<AtomicBool>,
+    maintenance_running: Arc<AtomicBool>,
     shutdown_tx: Option<mpsc::Sender<()>>,
 }
 
@@ -20,6 +22,7 @@ impl BackgroundScheduler {
     pub fn new() -> Self {
         Self {
             running: Arc::new(AtomicBool::new(false)),
+            maintenance_running: Arc::new(AtomicBool::new(false)),
             shutdown_tx: None,
         }
     }
@@ -111,12 +114,54 @@ impl BackgroundScheduler {
         });
     }
 
+    /// Start the database maintenance loop.
+    ///
+    /// The task is run on each tick (never concurrently); it decides itself
+    /// which jobs are due and whether the database is busy.
+    pub fn start_maintenance<F>(&self, maintenance_interval: Duration, maintenance_task: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        if self.maintenance_running.swap(true, Ordering::SeqCst) {
+            warn!("Maintenance already scheduled");
+            return;
+        }
+
+        let running = self.maintenance_running.clone();
+        let task = Arc::new(maintenance_task);
+        let task_running = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(async move {
+            let mut timer = interval(maintenance_interval);
+            info!("Maintenance scheduled every {}s", maintenance_interval.as_secs());
+
+            loop {
+                timer.tick().await;
+                if !running.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                if !task_running.swap(true, Ordering::SeqCst) {
+                    let task = task.clone();
+                    let flag = task_running.clone();
+                    tokio::task::spawn_blocking(move || {
+                        task();
+                        flag.store(false, Ordering::SeqCst);
+                    });
+                }
+            }
+
+            info!("Maintenance stopped");
+        });
+    }
+
     /// Stop the scheduler
     pub fn stop(&mut self) {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.blocking_send(());
         }
         self.running.store(false, Ordering::SeqCst);
+        self.maintenance_running.store(false, Ordering::SeqCst);
     }
 }
 
@@ -132,6 +177,70 @@ impl Drop for BackgroundScheduler {
     }
 }
 
+/// Activity that must not overlap with database maintenance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Sync,
+    Production,
+}
+
+/// Tracks in-flight sync and production transactions
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    sync: AtomicUsize,
+    production: AtomicUsize,
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark an activity as started until the guard is dropped
+    pub fn begin(self: &Arc<Self>, activity: Activity) -> ActivityGuard {
+        self.counter(activity).fetch_add(1, Ordering::SeqCst);
+        ActivityGuard {
+            tracker: self.clone(),
+            activity,
+        }
+    }
+
+    /// Check if an activity is in progress
+    pub fn is_active(&self, activity: Activity) -> bool {
+        self.counter(activity).load(Ordering::SeqCst) > 0
+    }
+
+    /// Reason maintenance must wait, if any
+    pub fn busy_reason(&self) -> Option<&'static str> {
+        if self.is_active(Activity::Sync) {
+            Some("Synchronisation en cours")
+        } else if self.is_active(Activity::Production) {
+            Some("Transaction de production en cours")
+        } else {
+            None
+        }
+    }
+
+    fn counter(&self, activity: Activity) -> &AtomicUsize {
+        match activity {
+            Activity::Sync => &self.sync,
+            Activity::Production => &self.production,
+        }
+    }
+}
+
+/// Guard returned by [`ActivityTracker::begin`]
+pub struct ActivityGuard {
+    tracker: Arc<ActivityTracker>,
+    activity: Activity,
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        self.tracker.counter(self.activity).fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 /// Simple task that can be scheduled
 pub struct ScheduledTask {
     pub name: String,
@@ -167,4 +276,18 @@ mod tests {
         let scheduler = BackgroundScheduler::new();
         assert!(!scheduler.is_running());
     }
+
+    #[test]
+    fn test_activity_guard() {
+        let tracker = Arc::new(ActivityTracker::new());
+        assert!(tracker.busy_reason().is_none());
+
+        let guard = tracker.begin(Activity::Sync);
+        let _second = tracker.begin(Activity::Sync);
+        assert!(tracker.is_active(Activity::Sync));
+        assert!(!tracker.is_active(Activity::Production));
+
+        drop(guard);
+        assert!(tracker.busy_reason().is_some());
+    }
 }
@@ -2,10 +2,10 @@
 //!
 //! Handles user authentication, session management, and role-based access control.
 
-use chrono::{DateTime, Utc};
-use manchengo_core::{EntityId, UserRole};
+use chrono::{DateTime, NaiveDate, Utc};
+use manchengo_core::{Clock, EntityId, OffsetClock, SharedClock, SystemClock, UserRole};
 use serde::{Deserialize, Serialize};
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
 
 /// Security-related errors
@@ -25,8 +25,14 @@ pub enum SecurityError {
 
     #[error("Erreur interne de verrouillage")]
     InternalLockError,
+
+    #[error("Date de saisie {date} hors limites (J-{max_days} a aujourd'hui)")]
+    BusinessDateOutOfRange { date: NaiveDate, max_days: i64 },
 }
 
+/// How far back a business date can be set for late data entry
+pub const MAX_BACKDATE_DAYS: i64 = 31;
+
 /// Authenticated user information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthenticatedUser {
@@ -56,25 +62,49 @@ impl AuthenticatedUser {
             _ => true, // Default modules accessible to all
         }
     }
+
+    /// Check if user may enter data at an earlier business date
+    pub fn can_backdate(&self) -> bool {
+        matches!(self.role, UserRole::Admin | UserRole::Appro | UserRole::Production)
+    }
 }
 
 /// Session manager for handling user authentication state
+///
+/// The session is also the application clock: it runs on the system clock,
+/// shifted to the business date while one is set for late data entry.
+#[derive(Debug)]
 pub struct SessionManager {
     current_user: RwLock<Option<AuthenticatedUser>>,
     device_id: EntityId,
     device_token: RwLock<Option<String>>,
+    clock: SharedClock,
+    business_date: RwLock<Option<NaiveDate>>,
 }
 
 impl SessionManager {
     /// Create new session manager
     pub fn new(device_id: EntityId) -> Self {
+        Self::with_clock(device_id, Arc::new(SystemClock))
+    }
+
+    /// Create session manager on another clock (tests)
+    pub fn with_clock(device_id: EntityId, clock: SharedClock) -> Self {
         Self {
             current_user: RwLock::new(None),
             device_id,
             device_token: RwLock::new(None),
+            clock,
+            business_date: RwLock::new(None),
         }
     }
 
+    /// The system clock under the business date, for background jobs and
+    /// time-based expiries
+    pub fn system_clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
     /// Get device ID
     pub fn device_id(&self) -> EntityId {
         self.device_id
@@ -95,6 +125,9 @@ impl SessionManager {
         let mut token = self.device_token.write().map_err(|_| SecurityError::InternalLockError)?;
         *token = None;
 
+        let mut business_date = self.business_date.write().map_err(|_| SecurityError::InternalLockError)?;
+        *business_date = None;
+
         Ok(())
     }
 
@@ -148,6 +181,45 @@ impl SessionManager {
         self.current_user()
             .and_then(|u| u.token.clone())
     }
+
+    /// Set or clear the business date for late data entry
+    ///
+    /// Only roles entering receptions and production may backdate, at most
+    /// `MAX_BACKDATE_DAYS` in the past. Returns the user who changed it.
+    pub fn set_business_date(&self, date: Option<NaiveDate>) -> Result<AuthenticatedUser, SecurityError> {
+        let user = self.require_user()?;
+        if date.is_some() && !user.can_backdate() {
+            return Err(SecurityError::InsufficientRole { required: UserRole::Appro });
+        }
+
+        let today = self.clock.today();
+        let date = date.filter(|d| *d != today);
+        if let Some(d) = date {
+            if d > today || (today - d).num_days() > MAX_BACKDATE_DAYS {
+                return Err(SecurityError::BusinessDateOutOfRange {
+                    date: d,
+                    max_days: MAX_BACKDATE_DAYS,
+                });
+            }
+        }
+
+        let mut business_date = self.business_date.write().map_err(|_| SecurityError::InternalLockError)?;
+        *business_date = date;
+        Ok(user)
+    }
+}
+
+impl Clock for SessionManager {
+    fn now(&self) -> DateTime<Utc> {
+        match self.business_date() {
+            Some(date) => OffsetClock::at_date(self.clock.clone(), date).now(),
+            None => self.clock.now(),
+        }
+    }
+
+    fn business_date(&self) -> Option<NaiveDate> {
+        self.business_date.read().ok().and_then(|guard| *guard)
+    }
 }
 
 // Extension trait for UserRole to check access hierarchy
@@ -167,6 +239,18 @@ impl UserRoleExt for UserRole {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use manchengo_core::FixedClock;
+
+    fn test_user(role: UserRole) -> AuthenticatedUser {
+        AuthenticatedUser {
+            id: EntityId::new(),
+            email: "test@example.com".to_string(),
+            name: "Test User".to_string(),
+            role,
+            authenticated_at: Utc::now(),
+            token: Some("test-token".to_string()),
+        }
+    }
 
     #[test]
     fn test_session_manager() {
@@ -192,4 +276,29 @@ mod tests {
         manager.logout().unwrap();
         assert!(!manager.is_authenticated());
     }
+
+    #[test]
+    fn test_business_date_override() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let manager = SessionManager::with_clock(EntityId::new(), Arc::new(FixedClock::at_date(date(15))));
+        assert!(manager.set_business_date(Some(date(14))).is_err());
+
+        manager.login(test_user(UserRole::Commercial)).unwrap();
+        assert!(matches!(
+            manager.set_business_date(Some(date(14))),
+            Err(SecurityError::InsufficientRole { .. })
+        ));
+
+        manager.login(test_user(UserRole::Appro)).unwrap();
+        assert!(manager.set_business_date(Some(date(16))).is_err());
+        manager.set_business_date(Some(date(14))).unwrap();
+        assert_eq!(manager.today(), date(14));
+        assert_eq!(Clock::business_date(&manager), Some(date(14)));
+        // Background jobs keep the real date
+        assert_eq!(manager.system_clock().today(), date(15));
+
+        manager.logout().unwrap();
+        assert_eq!(manager.today(), date(15));
+        assert_eq!(Clock::business_date(&manager), None);
+    }
 }
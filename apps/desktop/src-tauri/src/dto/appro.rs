@@ -1,5 +1,6 @@
 //! Appro-related DTOs (Purchase Orders, Suppliers)
 
+use manchengo_core::Currency;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -41,7 +42,13 @@ pub struct PurchaseOrderDto {
     pub status: PurchaseOrderStatus,
     pub expected_delivery: Option<String>,
     pub received_date: Option<String>,
+    pub currency: Currency,
+    /// DZD per unit at the order date (foreign currency only)
+    pub exchange_rate: Option<f64>,
+    /// In the order currency, minor units
     pub total_amount: i64,
+    /// DZD centimes at the order-date rate
+    pub total_amount_dzd: i64,
     pub notes: Option<String>,
     pub lines: Vec<PurchaseOrderLineDto>,
     pub created_at: String,
@@ -65,6 +72,9 @@ pub struct PurchaseOrderLineDto {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreatePurchaseOrderDto {
     pub supplier_id: String,
+    /// Currency of the unit prices (DZD if omitted)
+    #[serde(default)]
+    pub currency: Currency,
     pub expected_delivery: Option<String>,
     pub notes: Option<String>,
     pub lines: Vec<CreatePurchaseOrderLineDto>,
@@ -92,6 +102,9 @@ pub struct PurchaseOrderFilter {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReceivePurchaseOrderDto {
     pub lines: Vec<ReceiveLineDto>,
+    /// Receiving warehouse (default warehouse if omitted)
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
 }
 
 /// Receive line
@@ -101,9 +114,67 @@ pub struct ReceiveLineDto {
     pub quantity_received: i32,
     pub lot_number: Option<String>,
     pub expiry_date: Option<String>,
+    /// In the order currency, minor units
     pub unit_cost: Option<i64>,
 }
 
+// ============================================================================
+// EXCHANGE RATE & SUPPLIER STATEMENT DTOs
+// ============================================================================
+
+/// Manual exchange rate entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetExchangeRateDto {
+    pub currency: Currency,
+    pub rate_date: String,
+    /// DZD per unit of currency
+    pub rate: f64,
+}
+
+/// CSV import result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRateImportDto {
+    pub imported: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+}
+
+/// Supplier statement line (one received lot)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierStatementLineDto {
+    pub lot_id: String,
+    pub lot_number: String,
+    pub reception_date: String,
+    pub product_mp_id: String,
+    pub product_mp_name: String,
+    pub quantity: f64,
+    pub currency: Currency,
+    /// Supplier price in its currency, minor units
+    pub unit_cost_currency: i64,
+    pub total_currency: i64,
+    pub exchange_rate: Option<f64>,
+    pub total_dzd: i64,
+}
+
+/// Supplier total in one currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierStatementTotalDto {
+    pub currency: Currency,
+    pub total_currency: i64,
+    pub total_dzd: i64,
+}
+
+/// Supplier statement in original currencies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierStatementDto {
+    pub supplier_id: String,
+    pub supplier_name: String,
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub lines: Vec<SupplierStatementLineDto>,
+    pub totals: Vec<SupplierStatementTotalDto>,
+}
+
 // ============================================================================
 // SUPPLIER PERFORMANCE DTOs
 // ============================================================================
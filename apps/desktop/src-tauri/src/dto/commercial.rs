@@ -1,5 +1,6 @@
-//! Commercial-related DTOs (Clients, Price Lists)
+//! Commercial-related DTOs (Clients, Price Lists, Sales orders)
 
+use manchengo_core::Qty;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -24,6 +25,8 @@ pub struct ClientDto {
     pub is_active: bool,
     pub credit_limit: i64,
     pub current_balance: i64,
+    /// Minimum remaining shelf life of delivered lots, in days
+    pub min_shelf_life_days: Option<u32>,
     pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: Option<String>,
@@ -43,6 +46,7 @@ pub struct CreateClientDto {
     pub rc: Option<String>,
     pub ai: Option<String>,
     pub credit_limit: Option<i64>,
+    pub min_shelf_life_days: Option<u32>,
     pub notes: Option<String>,
 }
 
@@ -120,3 +124,40 @@ pub struct ClientPriceDto {
     pub discount_percentage: f64,
     pub price_list_name: Option<String>,
 }
+
+// ============================================================================
+// SALES ORDER DTOs
+// ============================================================================
+
+/// Confirm sales order request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmSalesOrderDto {
+    pub order_id: String,
+    /// How long the lots stay held, in hours (default 48)
+    pub hold_hours: Option<u32>,
+}
+
+/// Prepare a delivery client line from its order reservations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrepareDeliveryLineDto {
+    pub delivery_id: String,
+    pub delivery_line_id: String,
+}
+
+/// Lot held for a sales order line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotReservationDto {
+    pub id: String,
+    pub lot_id: String,
+    pub lot_number: String,
+    pub product_id: String,
+    pub sales_order_id: String,
+    pub sales_order_line_id: String,
+    /// Quantity held, in the lot unit
+    pub quantity: Qty,
+    /// ACTIVE, CONSUMED, RELEASED or EXPIRED
+    pub status: String,
+    pub expires_at: String,
+    pub release_reason: Option<String>,
+    pub created_at: String,
+}
@@ -1,6 +1,10 @@
 //! Common DTOs shared across modules
 
+use manchengo_core::{Error, ErrorCode, Gs1Error, LocalizedMessage, Severity};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::core::SecurityError;
 
 /// Pagination parameters
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -115,6 +119,62 @@ pub struct DatabaseStats {
     pub lots_pf_count: u64,
     pub movements_count: u64,
     pub pending_sync_events: u64,
+    /// Slowest statements by p95
+    pub query_stats: Vec<QueryStatDto>,
+    /// Most recent slow queries
+    pub slow_queries: Vec<SlowQueryDto>,
+}
+
+/// Aggregated timings for one normalised statement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryStatDto {
+    pub sql: String,
+    pub params: usize,
+    pub count: u64,
+    pub total_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Slow query log entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQueryDto {
+    pub sql: String,
+    pub params: usize,
+    pub duration_ms: f64,
+    pub recorded_at: String,
+    pub query_plan: Option<Vec<String>>,
+}
+
+/// Database maintenance run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRunDto {
+    pub job: String,
+    pub status: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub rows_affected: u64,
+    pub message: Option<String>,
+}
+
+/// Archived fiscal year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiscalArchiveDto {
+    pub fiscal_year: i32,
+    pub file_path: String,
+    pub rows_archived: i64,
+    pub archived_at: String,
+    pub archived_by: String,
+}
+
+/// Fiscal year archiving result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveYearResultDto {
+    pub fiscal_year: i32,
+    pub file_path: String,
+    pub rows_archived: i64,
+    pub opening_balances: u32,
 }
 
 /// Sync status response
@@ -152,60 +212,182 @@ pub struct SyncResultDto {
     pub duration_ms: u64,
 }
 
+/// Soft-deleted entity shown in the admin trash view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItemDto {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub label: String,
+    pub deleted_at: String,
+    pub deleted_by: Option<String>,
+}
+
 /// Error response for Tauri commands
+///
+/// `code` is stable (see `ErrorCode`), `message` is rendered from the
+/// FR/AR templates with `params`, `details` is for logs and support only.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandError {
-    pub code: String,
-    pub message: String,
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub params: BTreeMap<String, String>,
+    pub message: LocalizedMessage,
     pub details: Option<String>,
 }
 
 impl CommandError {
-    pub fn new(code: &str, message: &str) -> Self {
+    pub fn new(code: ErrorCode, params: BTreeMap<String, String>) -> Self {
         Self {
-            code: code.to_string(),
-            message: message.to_string(),
+            code,
+            severity: code.severity(),
+            message: code.localize(&params),
+            params,
             details: None,
         }
     }
 
-    pub fn with_details(code: &str, message: &str, details: &str) -> Self {
-        Self {
-            code: code.to_string(),
-            message: message.to_string(),
-            details: Some(details.to_string()),
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    fn with_param(code: ErrorCode, name: &str, value: impl Into<String>) -> Self {
+        Self::new(code, BTreeMap::from([(name.to_string(), value.into())]))
+    }
+
+    pub fn not_found(entity_type: &str, id: impl Into<String>) -> Self {
+        Self::new(
+            ErrorCode::NotFound,
+            BTreeMap::from([
+                ("entity_type".to_string(), entity_type.to_string()),
+                ("id".to_string(), id.into()),
+            ]),
+        )
+    }
+
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        Error::Validation {
+            field: field.to_string(),
+            message: message.into(),
         }
+        .into()
     }
 
-    pub fn not_found(entity: &str) -> Self {
-        Self::new("NOT_FOUND", &format!("{} non trouve", entity))
+    pub fn business_rule(message: impl Into<String>) -> Self {
+        Self::with_param(ErrorCode::BusinessRule, "message", message)
     }
 
-    pub fn validation(message: &str) -> Self {
-        Self::new("VALIDATION_ERROR", message)
+    pub fn invalid_id(id: &str) -> Self {
+        Self::with_param(ErrorCode::InvalidId, "id", id)
     }
 
     pub fn unauthorized() -> Self {
-        Self::new("UNAUTHORIZED", "Non authentifie")
+        Self::new(ErrorCode::NotAuthenticated, BTreeMap::new())
     }
 
-    pub fn forbidden(message: &str) -> Self {
-        Self::new("FORBIDDEN", message)
+    pub fn internal(details: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, BTreeMap::new()).with_details(details)
     }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message.fr)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<Error> for CommandError {
+    fn from(err: Error) -> Self {
+        let command_error = Self::new(err.code(), err.params());
+        match err {
+            Error::Database(details)
+            | Error::Serialization(details)
+            | Error::Io(details)
+            | Error::Internal(details) => command_error.with_details(details),
+            _ => command_error,
+        }
+    }
+}
 
-    pub fn internal(message: &str) -> Self {
-        Self::new("INTERNAL_ERROR", message)
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        Error::from(err).into()
+    }
+}
+
+impl From<Gs1Error> for CommandError {
+    fn from(err: Gs1Error) -> Self {
+        Error::from(err).into()
+    }
+}
+
+impl From<SecurityError> for CommandError {
+    fn from(err: SecurityError) -> Self {
+        match err {
+            SecurityError::NotAuthenticated => Self::unauthorized(),
+            SecurityError::InsufficientRole { required } => {
+                Self::with_param(ErrorCode::Forbidden, "required", format!("{:?}", required).to_uppercase())
+            }
+            SecurityError::SessionExpired => Self::new(ErrorCode::SessionExpired, BTreeMap::new()),
+            SecurityError::DeviceNotRegistered => Self::new(ErrorCode::DeviceNotRegistered, BTreeMap::new()),
+            SecurityError::InternalLockError => Self::internal(err.to_string()),
+            SecurityError::BusinessDateOutOfRange { .. } => Self::validation("business_date", err.to_string()),
+        }
     }
 }
 
 impl From<anyhow::Error> for CommandError {
     fn from(err: anyhow::Error) -> Self {
-        Self::internal(&err.to_string())
+        let err = match err.downcast::<Error>() {
+            Ok(err) => return err.into(),
+            Err(err) => err,
+        };
+        match err.downcast::<SecurityError>() {
+            Ok(err) => err.into(),
+            Err(err) => Self::internal(format!("{:#}", err)),
+        }
     }
 }
 
 impl From<rusqlite::Error> for CommandError {
     fn from(err: rusqlite::Error) -> Self {
-        Self::with_details("DATABASE_ERROR", "Erreur base de donnees", &err.to_string())
+        Self::new(ErrorCode::Database, BTreeMap::new()).with_details(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn consume(required: f64, available: f64) -> anyhow::Result<()> {
+        Err(Error::InsufficientStock {
+            product: "MP-LAIT".to_string(),
+            required,
+            available,
+        })
+        .context("Consommation FIFO")
+    }
+
+    fn command() -> std::result::Result<(), CommandError> {
+        consume(8.0, 5.0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_business_errors_keep_their_code_through_anyhow() {
+        let err = command().unwrap_err();
+        assert_eq!(err.code, ErrorCode::InsufficientStock);
+        assert_eq!(err.severity, ErrorCode::InsufficientStock.severity());
+        assert_eq!(err.params.get("product").map(String::as_str), Some("MP-LAIT"));
+
+        let err = CommandError::from(anyhow::Error::from(SecurityError::NotAuthenticated));
+        assert_eq!(err.code, ErrorCode::NotAuthenticated);
+
+        let err = CommandError::from(anyhow::anyhow!("disque plein"));
+        assert_eq!(err.code, ErrorCode::Internal);
+        assert_eq!(err.details.as_deref(), Some("disque plein"));
     }
 }
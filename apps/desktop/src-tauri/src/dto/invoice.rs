@@ -1,5 +1,6 @@
 //! Invoice-related DTOs
 
+use manchengo_core::{AmountInWords, TvaGroup};
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -40,6 +41,8 @@ pub struct InvoiceDto {
     pub total_ht: i64,
     pub total_tva: i64,
     pub total_ttc: i64,
+    /// Total TTC in words, for "Arretee la presente facture a la somme de ..."
+    pub total_in_words: AmountInWords,
     pub timbre_fiscal: i64,
     pub payment_method: Option<String>,
     pub payment_due_date: Option<String>,
@@ -68,6 +71,8 @@ pub struct InvoiceLineDto {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateInvoiceDto {
     pub client_id: String,
+    /// Invoice date (YYYY-MM-DD), defaults to today; selects the fiscal rules
+    pub invoice_date: Option<String>,
     pub payment_method: Option<String>,
     pub payment_due_date: Option<String>,
     pub notes: Option<String>,
@@ -79,7 +84,10 @@ pub struct CreateInvoiceDto {
 pub struct CreateInvoiceLineDto {
     pub product_pf_id: String,
     pub quantity: i32,
+    #[serde(default)]
     pub unit_price_ht: i64,
+    /// Price agreed TTC; the HT price is derived from it
+    pub unit_price_ttc: Option<i64>,
 }
 
 /// Invoice totals (calculated)
@@ -90,6 +98,8 @@ pub struct InvoiceTotalsDto {
     pub timbre_fiscal: i64,
     pub total_ttc: i64,
     pub lines: Vec<InvoiceLineTotalsDto>,
+    /// TVA summary per rate
+    pub tva_breakdown: Vec<TvaGroup>,
 }
 
 /// Invoice line totals
@@ -126,6 +136,8 @@ pub struct PaymentDto {
     pub client_id: String,
     pub client_name: String,
     pub amount: i64,
+    /// Amount in words, printed on the receipt
+    pub amount_in_words: AmountInWords,
     pub payment_method: String,
     pub payment_date: String,
     pub reference: Option<String>,
@@ -164,6 +176,6 @@ pub struct TimbreFiscalDto {
     pub total_ttc: i64,
     pub timbre_amount: i64,
     pub final_total: i64,
-    pub timbre_rate: f64,
     pub threshold: i64,
+    pub rule_version: String,
 }
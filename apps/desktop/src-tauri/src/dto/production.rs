@@ -1,5 +1,6 @@
 //! Production-related DTOs
 
+use manchengo_core::Qty;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -16,7 +17,7 @@ pub struct RecipeDto {
     pub product_pf_name: String,
     pub product_pf_code: String,
     pub batch_weight: f64,
-    pub output_quantity: f64,
+    pub output_quantity: Qty,
     pub output_unit: String,
     pub loss_tolerance: f64,
     pub shelf_life_days: i32,
@@ -34,7 +35,7 @@ pub struct RecipeItemDto {
     pub product_mp_id: Option<String>,
     pub product_mp_name: Option<String>,
     pub product_mp_code: Option<String>,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: String,
     pub affects_stock: bool,
     pub is_mandatory: bool,
@@ -56,7 +57,7 @@ pub struct CreateRecipeDto {
     pub name: String,
     pub product_pf_id: String,
     pub batch_weight: f64,
-    pub output_quantity: f64,
+    pub output_quantity: Qty,
     pub loss_tolerance: Option<f64>,
     pub shelf_life_days: Option<i32>,
     pub items: Vec<CreateRecipeItemDto>,
@@ -68,7 +69,7 @@ pub struct CreateRecipeItemDto {
     #[serde(rename = "type")]
     pub item_type: String, // "MP", "FLUID", "PACKAGING"
     pub product_mp_id: Option<String>,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: String,
     pub affects_stock: Option<bool>,
     pub is_mandatory: Option<bool>,
@@ -83,7 +84,7 @@ pub struct ScaledRecipeDto {
     pub product_pf_id: String,
     pub product_pf_name: String,
     pub batch_count: f64,
-    pub target_output: f64,
+    pub target_output: Qty,
     pub items: Vec<ScaledRecipeItemDto>,
 }
 
@@ -92,12 +93,12 @@ pub struct ScaledRecipeDto {
 pub struct ScaledRecipeItemDto {
     pub product_mp_id: Option<String>,
     pub product_mp_name: Option<String>,
-    pub quantity_per_batch: f64,
-    pub total_quantity: f64,
+    pub quantity_per_batch: Qty,
+    pub total_quantity: Qty,
     pub unit: String,
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub is_available: bool,
-    pub shortage: f64,
+    pub shortage: Qty,
 }
 
 /// Check availability response
@@ -113,11 +114,11 @@ pub struct AvailabilityDto {
 pub struct AvailabilityItemDto {
     pub product_mp_id: String,
     pub product_mp_name: String,
-    pub required: f64,
-    pub available: f64,
+    pub required: Qty,
+    pub available: Qty,
     pub unit: String,
     pub is_available: bool,
-    pub shortage: f64,
+    pub shortage: Qty,
 }
 
 // ============================================================================
@@ -135,8 +136,8 @@ pub struct ProductionOrderDto {
     pub recipe_id: String,
     pub recipe_name: String,
     pub batch_count: i32,
-    pub target_quantity: f64,
-    pub actual_quantity: Option<f64>,
+    pub target_quantity: Qty,
+    pub actual_quantity: Option<Qty>,
     pub status: ProductionStatus,
     pub scheduled_date: Option<String>,
     pub started_at: Option<String>,
@@ -181,7 +182,7 @@ pub struct ProductionConsumptionDto {
     pub product_mp_name: String,
     pub lot_mp_id: String,
     pub lot_mp_number: String,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: String,
     pub consumed_at: String,
 }
@@ -198,7 +199,7 @@ pub struct CreateProductionOrderDto {
 /// Complete production request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompleteProductionDto {
-    pub quantity_produced: f64,
+    pub quantity_produced: Qty,
     pub batch_weight_real: Option<f64>,
     pub quality_notes: Option<String>,
     pub quality_status: Option<String>,
@@ -210,7 +211,7 @@ pub struct ProductionCompletionDto {
     pub order_id: String,
     pub lot_pf_id: String,
     pub lot_pf_number: String,
-    pub quantity_produced: f64,
+    pub quantity_produced: Qty,
     pub yield_percentage: f64,
     pub consumptions_count: i32,
     pub completed_at: String,
@@ -225,7 +226,7 @@ pub struct ProductionCostDto {
     pub overhead_cost: i64,
     pub total_cost: i64,
     pub cost_per_unit: i64,
-    pub quantity_produced: f64,
+    pub quantity_produced: Qty,
 }
 
 /// MP cost item
@@ -233,7 +234,7 @@ pub struct ProductionCostDto {
 pub struct MpCostItem {
     pub product_mp_id: String,
     pub product_mp_name: String,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: String,
     pub unit_cost: i64,
     pub total_cost: i64,
@@ -280,7 +281,7 @@ pub struct BlockedOrderDto {
 pub struct StockPfSummaryDto {
     pub product_id: String,
     pub product_name: String,
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub min_stock: f64,
     pub status: String,
 }
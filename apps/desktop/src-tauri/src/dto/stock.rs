@@ -1,6 +1,8 @@
 //! Stock-related DTOs
 
 use chrono::{DateTime, NaiveDate, Utc};
+use manchengo_core::{Currency, Qty};
+use manchengo_domain::stock::{CountMethod, CountOutcome, InventoryStatus, LocationType, LotSelectionStrategy, TransferStatus};
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -20,7 +22,7 @@ pub struct ProductMpDto {
     pub is_perishable: bool,
     pub shelf_life_days: Option<i32>,
     pub is_active: bool,
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub stock_status: StockStatus,
 }
 
@@ -37,8 +39,30 @@ pub struct ProductPfDto {
     pub price_ht: i64, // In centimes
     pub tva_rate: f64,
     pub is_active: bool,
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub stock_status: StockStatus,
+    #[serde(default)]
+    pub gtin: Option<String>,
+    #[serde(default)]
+    pub carton_gtin: Option<String>,
+}
+
+/// Product packaging (pieces per carton, cartons per palette)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductPackDto {
+    pub product_type: String, // MP or PF
+    pub product_id: String,
+    pub pieces_per_carton: Option<f64>,
+    pub cartons_per_pallet: Option<f64>,
+}
+
+/// Lot selection strategy of a product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductLotStrategyDto {
+    pub product_type: String, // MP or PF
+    pub product_id: String,
+    #[serde(flatten)]
+    pub strategy: LotSelectionStrategy,
 }
 
 /// Stock status enum
@@ -52,7 +76,8 @@ pub enum StockStatus {
 }
 
 impl StockStatus {
-    pub fn from_levels(current: f64, min_stock: f64, reorder_point: f64) -> Self {
+    pub fn from_levels(current: Qty, min_stock: f64, reorder_point: f64) -> Self {
+        let current = current.as_f64();
         if current <= 0.0 {
             StockStatus::Rupture
         } else if current < min_stock {
@@ -77,8 +102,8 @@ pub struct LotMpDto {
     pub product_id: String,
     pub product_code: String,
     pub product_name: String,
-    pub quantity_initial: f64,
-    pub quantity_remaining: f64,
+    pub quantity_initial: Qty,
+    pub quantity_remaining: Qty,
     pub unit: String,
     pub unit_cost: i64, // In centimes
     pub total_cost: i64,
@@ -90,6 +115,13 @@ pub struct LotMpDto {
     pub is_expired: bool,
     pub days_until_expiry: Option<i32>,
     pub qr_code: Option<String>,
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
+    #[serde(default)]
+    pub location_id: Option<String>,
+    /// Still waiting in the reception location
+    #[serde(default)]
+    pub pending_put_away: bool,
 }
 
 /// Lot PF DTO
@@ -100,16 +132,28 @@ pub struct LotPfDto {
     pub product_id: String,
     pub product_code: String,
     pub product_name: String,
-    pub quantity_initial: f64,
-    pub quantity_remaining: f64,
+    pub quantity_initial: Qty,
+    pub quantity_remaining: Qty,
     pub unit: String,
     pub status: LotStatus,
     pub production_order_id: Option<String>,
+    /// Production cost, in centimes
+    #[serde(default)]
+    pub unit_cost: i64,
     pub production_date: String,
     pub expiry_date: Option<String>,
     pub is_expired: bool,
     pub days_until_expiry: Option<i32>,
     pub qr_code: Option<String>,
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
+    #[serde(default)]
+    pub location_id: Option<String>,
+    #[serde(default)]
+    pub pending_put_away: bool,
+    /// Quantity held for confirmed sales orders
+    #[serde(default)]
+    pub quantity_reserved: Qty,
 }
 
 /// Lot status
@@ -144,7 +188,7 @@ pub struct ExpiringLotDto {
     pub product_id: String,
     pub product_name: String,
     pub product_type: String, // "MP" or "PF"
-    pub quantity_remaining: f64,
+    pub quantity_remaining: Qty,
     pub unit: String,
     pub expiry_date: String,
     pub days_until_expiry: i32,
@@ -162,12 +206,15 @@ pub struct StockLevelDto {
     pub product_code: String,
     pub product_name: String,
     pub unit: String,
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub min_stock: f64,
     pub reorder_point: f64,
     pub status: StockStatus,
     pub last_movement_date: Option<String>,
     pub lots_count: u32,
+    /// Stock not held for sales orders (finished products only)
+    #[serde(default)]
+    pub available_to_promise: Option<Qty>,
 }
 
 /// Stock alerts summary
@@ -190,7 +237,7 @@ pub struct StockAlertDto {
     pub product_code: String,
     pub product_name: String,
     pub product_type: String, // "MP" or "PF"
-    pub current_stock: f64,
+    pub current_stock: Qty,
     pub min_stock: f64,
     pub deficit: f64,
     pub status: StockStatus,
@@ -211,7 +258,7 @@ pub struct MovementDto {
     pub product_name: String,
     pub lot_id: Option<String>,
     pub lot_number: Option<String>,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: String,
     pub unit_cost: Option<i64>,
     pub origin: MovementOrigin,
@@ -219,8 +266,11 @@ pub struct MovementDto {
     pub reference_id: Option<String>,
     pub user_id: String,
     pub user_name: Option<String>,
+    /// Business timestamp (business date of backdated entries)
     pub created_at: String,
     pub note: Option<String>,
+    /// Real entry time, for the audit trail
+    pub recorded_at: Option<String>,
 }
 
 /// Movement type (IN or OUT)
@@ -245,6 +295,8 @@ pub enum MovementOrigin {
     RetourClient,
     Transfert,
     Expiry,
+    /// Opening balance after a fiscal year is archived
+    Ouverture,
 }
 
 impl From<&str> for MovementOrigin {
@@ -260,6 +312,7 @@ impl From<&str> for MovementOrigin {
             "RETOUR_CLIENT" => MovementOrigin::RetourClient,
             "TRANSFERT" => MovementOrigin::Transfert,
             "EXPIRY" => MovementOrigin::Expiry,
+            "OUVERTURE" => MovementOrigin::Ouverture,
             _ => MovementOrigin::Inventaire,
         }
     }
@@ -270,14 +323,19 @@ impl From<&str> for MovementOrigin {
 // ============================================================================
 
 /// FIFO consumption preview
+///
+/// Lots are chosen with the product selection strategy (FIFO by default).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FifoPreviewDto {
     pub product_id: String,
-    pub requested_quantity: f64,
-    pub available_quantity: f64,
+    pub strategy: LotSelectionStrategy,
+    pub requested_quantity: Qty,
+    pub available_quantity: Qty,
     pub can_fulfill: bool,
     pub lots: Vec<FifoLotPreview>,
-    pub shortage: f64,
+    /// Lots skipped by the strategy (expired, shelf life too short)
+    pub excluded: Vec<ExcludedLotDto>,
+    pub shortage: Qty,
 }
 
 /// Single lot in FIFO preview
@@ -285,16 +343,33 @@ pub struct FifoPreviewDto {
 pub struct FifoLotPreview {
     pub lot_id: String,
     pub lot_number: String,
-    pub quantity_available: f64,
-    pub quantity_to_consume: f64,
+    pub quantity_available: Qty,
+    pub quantity_to_consume: Qty,
     pub expiry_date: Option<String>,
     pub reception_date: String,
+    /// Why the strategy chose this lot
+    pub reason: String,
+}
+
+/// Lot skipped by a selection strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedLotDto {
+    pub lot_id: String,
+    pub lot_number: String,
+    pub reason: String,
+}
+
+/// Lot picked by the operator (manual strategy)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualLotPickDto {
+    pub lot_id: String,
+    pub quantity: Qty,
 }
 
 /// FIFO consumption result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FifoResultDto {
-    pub total_consumed: f64,
+    pub total_consumed: Qty,
     pub consumptions: Vec<FifoConsumption>,
     pub movements_created: Vec<String>,
 }
@@ -304,8 +379,10 @@ pub struct FifoResultDto {
 pub struct FifoConsumption {
     pub lot_id: String,
     pub lot_number: String,
-    pub quantity_consumed: f64,
+    pub quantity_consumed: Qty,
     pub lot_depleted: bool,
+    /// Why the strategy chose this lot
+    pub reason: String,
 }
 
 // ============================================================================
@@ -317,6 +394,15 @@ pub struct FifoConsumption {
 pub struct CreateReceptionDto {
     pub supplier_id: String,
     pub date: String, // ISO date
+    /// Currency of the line unit costs (DZD if omitted)
+    #[serde(default)]
+    pub currency: Currency,
+    /// Receiving warehouse (default warehouse if omitted)
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
+    /// Store lots directly here instead of the reception location
+    #[serde(default)]
+    pub location_id: Option<String>,
     pub bl_number: Option<String>,
     pub note: Option<String>,
     pub lines: Vec<ReceptionLineDto>,
@@ -327,7 +413,7 @@ pub struct CreateReceptionDto {
 pub struct ReceptionLineDto {
     pub product_mp_id: String,
     pub quantity: f64,
-    pub unit_cost: i64, // In centimes
+    pub unit_cost: i64, // Minor units of the reception currency (centimes for DZD)
     pub lot_number: Option<String>,
     pub expiry_date: Option<String>,
     pub tva_rate: Option<f64>,
@@ -344,6 +430,11 @@ pub struct ReceptionDto {
     pub bl_number: Option<String>,
     pub note: Option<String>,
     pub lines: Vec<ReceptionLineResponseDto>,
+    pub currency: Currency,
+    /// DZD per unit at the reception date (foreign currency only)
+    pub exchange_rate: Option<f64>,
+    /// HT total in the reception currency, minor units
+    pub total_ht_currency: i64,
     pub total_ht: i64,
     pub total_tva: i64,
     pub total_ttc: i64,
@@ -361,11 +452,206 @@ pub struct ReceptionLineResponseDto {
     pub quantity: f64,
     pub unit: String,
     pub unit_cost: i64,
+    /// Supplier price in the reception currency, minor units
+    pub unit_cost_currency: i64,
     pub line_total: i64,
     pub tva_rate: f64,
     pub lot_id: String,
     pub lot_number: String,
     pub expiry_date: Option<String>,
+    pub warehouse_id: String,
+    pub location_id: Option<String>,
+}
+
+// ============================================================================
+// WAREHOUSE DTOs
+// ============================================================================
+
+/// Warehouse (factory, depot)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseDto {
+    pub id: String,
+    pub code: String,
+    pub name: String,
+    pub wilaya_code: Option<String>,
+    pub address: Option<String>,
+    pub is_default: bool,
+    pub is_active: bool,
+    pub locations_count: u32,
+}
+
+/// Create warehouse request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWarehouseDto {
+    pub code: String,
+    pub name: String,
+    pub wilaya_code: Option<String>,
+    pub address: Option<String>,
+}
+
+/// Bin location inside a warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationDto {
+    pub id: String,
+    pub warehouse_id: String,
+    pub warehouse_code: String,
+    pub code: String,
+    pub name: String,
+    pub location_type: LocationType,
+    pub is_active: bool,
+    /// Lots here can be picked by FIFO
+    pub is_pickable: bool,
+    pub qr_code: Option<String>,
+    pub lots_count: u32,
+}
+
+/// Create location request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLocationDto {
+    pub warehouse_id: String,
+    pub code: String,
+    pub name: String,
+    pub location_type: LocationType,
+}
+
+/// Put-away request: move a lot from reception to its storage location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PutAwayDto {
+    pub product_type: String, // "MP" or "PF"
+    pub lot_id: String,
+    /// Location id, or the scanned location QR code
+    pub location_id: Option<String>,
+    pub location_qr: Option<String>,
+}
+
+/// Lot waiting for put-away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPutAwayDto {
+    pub lot_id: String,
+    pub lot_number: String,
+    pub product_type: String,
+    pub product_id: String,
+    pub product_name: String,
+    pub quantity_remaining: Qty,
+    pub unit: String,
+    pub warehouse_id: String,
+    pub location_id: Option<String>,
+    pub location_code: Option<String>,
+    pub received_at: String,
+}
+
+/// Stock of one product in one warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseStockDto {
+    pub warehouse_id: String,
+    pub warehouse_code: String,
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub unit: String,
+    pub current_stock: Qty,
+    /// Part of current stock still in reception (not pickable yet)
+    pub pending_put_away: Qty,
+    /// Part of current stock shipped by another warehouse, not received yet
+    pub in_transit: Qty,
+    pub lots_count: u32,
+    pub min_stock: f64,
+    pub reorder_point: f64,
+    pub status: StockStatus,
+}
+
+/// Minimum stock of a product in a warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetWarehouseThresholdDto {
+    pub warehouse_id: String,
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub min_stock: Qty,
+    pub reorder_point: Qty,
+}
+
+// ============================================================================
+// TRANSFER DTOs
+// ============================================================================
+
+/// Transfer order between two warehouses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferOrderDto {
+    pub id: String,
+    pub reference: String,
+    pub from_warehouse_id: String,
+    pub from_warehouse_code: String,
+    pub to_warehouse_id: String,
+    pub to_warehouse_code: String,
+    pub status: TransferStatus,
+    pub notes: Option<String>,
+    pub lines: Vec<TransferLineDto>,
+    /// Stock value that left the origin warehouse (centimes)
+    pub value_shipped: i64,
+    /// Stock value that arrived (centimes), lower on a discrepancy
+    pub value_received: i64,
+    pub shipped_at: Option<String>,
+    pub shipped_by: Option<String>,
+    pub received_at: Option<String>,
+    pub received_by: Option<String>,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+/// Lot picked for a transfer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferLineDto {
+    pub id: String,
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_name: String,
+    pub lot_id: String,
+    pub lot_number: String,
+    pub quantity_shipped: Qty,
+    pub quantity_received: Option<Qty>,
+    pub unit: String,
+    pub unit_cost: i64, // In centimes
+    /// Lot carrying the goods at the destination (the picked lot, or the
+    /// part split off it)
+    pub transit_lot_id: Option<String>,
+    pub discrepancy_reason: Option<String>,
+}
+
+/// Create transfer request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTransferDto {
+    pub from_warehouse_id: String,
+    pub to_warehouse_id: String,
+    pub notes: Option<String>,
+}
+
+/// Pick a lot: by id, or by its scanned QR code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddTransferLineDto {
+    pub transfer_id: String,
+    pub product_type: Option<String>, // "MP" or "PF", with lot_id
+    pub lot_id: Option<String>,
+    pub lot_qr: Option<String>,
+    /// Whole lot if not given
+    pub quantity: Option<Qty>,
+}
+
+/// Receive transfer request; lines not listed are received in full
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiveTransferDto {
+    pub transfer_id: String,
+    #[serde(default)]
+    pub lines: Vec<TransferReceiptDto>,
+}
+
+/// Quantity counted at the destination for a line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferReceiptDto {
+    pub line_id: String,
+    pub quantity_received: Qty,
+    /// Required when less than shipped
+    pub reason: Option<String>,
 }
 
 // ============================================================================
@@ -377,7 +663,7 @@ pub struct ReceptionLineResponseDto {
 pub struct AdjustInventoryDto {
     pub product_type: String, // "MP" or "PF"
     pub product_id: String,
-    pub physical_quantity: f64,
+    pub physical_quantity: Qty,
     pub reason: String,
 }
 
@@ -387,15 +673,232 @@ pub struct InventoryAdjustmentDto {
     pub id: String,
     pub product_id: String,
     pub product_name: String,
-    pub previous_stock: f64,
-    pub physical_stock: f64,
-    pub difference: f64,
+    pub previous_stock: Qty,
+    pub physical_stock: Qty,
+    pub difference: Qty,
     pub movement_type: MovementType,
     pub reason: String,
     pub adjusted_at: String,
     pub adjusted_by: String,
 }
 
+// ============================================================================
+// INVENTORY SESSION DTOs
+// ============================================================================
+
+/// Physical inventory session of a warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventorySessionDto {
+    pub id: String,
+    pub reference: String,
+    pub warehouse_id: String,
+    pub warehouse_code: String,
+    pub status: InventoryStatus,
+    /// Theoretical quantities hidden while counting
+    pub blind: bool,
+    /// Variance (% of theoretical) above which a lot is counted twice
+    pub recount_threshold_pct: f64,
+    pub notes: Option<String>,
+    pub lines: Vec<InventoryLineDto>,
+    pub lots_counted: u32,
+    pub submitted_at: Option<String>,
+    pub approved_at: Option<String>,
+    pub approved_by: Option<String>,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+/// Lot of the count sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryLineDto {
+    pub id: String,
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_name: String,
+    pub lot_id: String,
+    pub lot_number: String,
+    pub location_id: Option<String>,
+    pub location_code: Option<String>,
+    /// Frozen at opening; `None` on a blind session still counting
+    pub quantity_theoretical: Option<Qty>,
+    pub unit: String,
+    pub unit_cost: i64, // In centimes
+    pub first_count: Option<Qty>,
+    pub first_count_method: Option<CountMethod>,
+    pub first_counted_by: Option<String>,
+    pub second_count: Option<Qty>,
+    pub second_count_method: Option<CountMethod>,
+    pub second_counted_by: Option<String>,
+    pub needs_second_count: bool,
+}
+
+/// Open inventory session request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenInventoryDto {
+    pub warehouse_id: String,
+    #[serde(default)]
+    pub blind: bool,
+    /// 10% if not given
+    pub recount_threshold_pct: Option<f64>,
+    pub notes: Option<String>,
+}
+
+/// Count a lot: by id, or by its scanned QR code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordCountDto {
+    pub session_id: String,
+    pub lot_id: Option<String>,
+    pub lot_qr: Option<String>,
+    pub quantity: Qty,
+}
+
+/// Count recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryCountResultDto {
+    pub lot_number: String,
+    pub outcome: CountOutcome,
+    pub session: InventorySessionDto,
+}
+
+/// Variance report, valued at lot cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryVarianceReportDto {
+    pub session_id: String,
+    pub reference: String,
+    pub status: InventoryStatus,
+    pub lines: Vec<InventoryVarianceDto>,
+    pub lots_counted: u32,
+    pub lots_uncounted: u32,
+    pub value_gain: i64, // In centimes
+    pub value_loss: i64, // In centimes
+    pub net_value: i64,  // In centimes
+}
+
+/// Variance of one lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryVarianceDto {
+    pub line_id: String,
+    pub product_type: String,
+    pub product_name: String,
+    pub lot_id: String,
+    pub lot_number: String,
+    pub unit: String,
+    pub quantity_theoretical: Qty,
+    pub quantity_counted: Qty,
+    pub variance: Qty,
+    pub unit_cost: i64, // In centimes
+    pub value: i64,     // In centimes
+}
+
+// ============================================================================
+// STOCK VALUATION DTOs
+// ============================================================================
+
+/// Stock valuation at a date, CMUP and FIFO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockValuationReportDto {
+    pub as_of: String,
+    pub warehouse_id: Option<String>,
+    pub products: Vec<ProductValuationDto>,
+    pub categories: Vec<ValuationGroupDto>,
+    pub warehouses: Vec<ValuationGroupDto>,
+    pub cmup_value: i64, // In centimes
+    pub fifo_value: i64, // In centimes
+    /// CMUP minus FIFO, to reconcile
+    pub difference: i64, // In centimes
+}
+
+/// Stock value of a product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductValuationDto {
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub unit: String,
+    pub category_id: Option<String>,
+    pub category_name: Option<String>,
+    pub quantity: Qty,
+    pub average_cost: i64, // In centimes
+    pub cmup_value: i64,   // In centimes
+    pub fifo_value: i64,   // In centimes
+    pub difference: i64,   // In centimes
+}
+
+/// Stock value of a category or a warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValuationGroupDto {
+    /// `None` for products without category (or movements without warehouse)
+    pub id: Option<String>,
+    pub code: String,
+    pub name: String,
+    pub products_count: u32,
+    pub cmup_value: i64, // In centimes
+    pub fifo_value: i64, // In centimes
+    pub difference: i64, // In centimes
+}
+
+/// Weighted average cost after one movement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostHistoryDto {
+    pub occurred_at: String,
+    pub movement_type: MovementType,
+    pub quantity: Qty,
+    pub unit_cost: i64, // In centimes
+    pub quantity_after: Qty,
+    pub average_cost: i64, // In centimes
+    pub value_after: i64,  // In centimes
+}
+
+// ============================================================================
+// POINT-IN-TIME STOCK DTOs
+// ============================================================================
+
+/// Stock held at the end of a day, rebuilt from the movement ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockAsOfDto {
+    pub as_of: String,
+    /// Daily snapshot the balances started from, if any
+    pub snapshot_date: Option<String>,
+    pub products: Vec<ProductStockAsOfDto>,
+    pub positions: Vec<StockPositionDto>,
+}
+
+/// Stock of a product at the date, all lots and warehouses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductStockAsOfDto {
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub unit: String,
+    pub quantity: Qty,
+    pub lots_count: u32,
+    pub last_movement_date: Option<String>,
+}
+
+/// Balance of a lot in a warehouse at the date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockPositionDto {
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_code: String,
+    pub lot_id: Option<String>,
+    pub lot_number: Option<String>,
+    pub warehouse_id: Option<String>,
+    pub warehouse_code: Option<String>,
+    pub quantity: Qty,
+    pub last_movement_date: Option<String>,
+}
+
+/// Daily closing snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockSnapshotDto {
+    pub snapshot_date: String,
+    pub lines_count: u32,
+    pub created_at: String,
+}
+
 // ============================================================================
 // LOSS DECLARATION DTOs
 // ============================================================================
@@ -406,7 +909,7 @@ pub struct DeclareLossDto {
     pub product_type: String, // "MP" or "PF"
     pub product_id: String,
     pub lot_id: Option<String>,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub reason: String,
     pub description: Option<String>,
 }
@@ -419,13 +922,94 @@ pub struct LossDeclarationDto {
     pub product_name: String,
     pub lot_id: Option<String>,
     pub lot_number: Option<String>,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub reason: String,
     pub description: Option<String>,
     pub declared_at: String,
     pub declared_by: String,
 }
 
+// ============================================================================
+// LEDGER INTEGRITY DTOs
+// ============================================================================
+
+/// Lot balance as stored vs. recomputed from its movements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotLedgerDto {
+    pub lot_id: String,
+    pub lot_number: String,
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub status: String,
+    pub quantity_remaining: Qty,
+    pub ledger_balance: Qty,
+    pub movements_count: i64,
+}
+
+/// Movement pointing to a missing or mismatched lot/product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanMovementDto {
+    pub movement_id: String,
+    pub movement_type: String,
+    pub product_type: String,
+    pub product_id: Option<String>,
+    pub lot_id: Option<String>,
+    pub quantity: Qty,
+    pub reason: String,
+    pub created_at: String,
+}
+
+/// Single integrity issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerIssueDto {
+    pub kind: String, // BALANCE_MISMATCH, NEGATIVE_BALANCE, ZERO_NOT_CONSUMED, ORPHAN_MOVEMENT
+    pub product_type: String,
+    pub product_id: Option<String>,
+    pub product_name: Option<String>,
+    pub lot_id: Option<String>,
+    pub lot_number: Option<String>,
+    pub movement_id: Option<String>,
+    pub quantity_remaining: Option<Qty>,
+    pub ledger_balance: Option<Qty>,
+    pub drift: Qty,
+    pub repairable: bool,
+    pub message: String,
+}
+
+/// Integrity report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerIntegrityReportDto {
+    pub checked_at: String,
+    pub lots_checked: u32,
+    pub movements_checked: u64,
+    pub issues: Vec<LedgerIssueDto>,
+    pub total_mismatches: u32,
+    pub total_negative: u32,
+    pub total_zero_not_consumed: u32,
+    pub total_orphans: u32,
+    pub is_consistent: bool,
+}
+
+/// Repair request (admin approval)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairLedgerDto {
+    pub lot_ids: Vec<String>,
+    pub reason: String,
+}
+
+/// Repair result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerRepairResultDto {
+    pub lots_repaired: u32,
+    pub movements_created: Vec<String>,
+    pub lots_marked_consumed: u32,
+    pub lots_clamped: u32,
+    pub skipped: Vec<String>,
+    pub repaired_at: String,
+    pub repaired_by: String,
+}
+
 // ============================================================================
 // FILTER DTOs
 // ============================================================================
@@ -444,6 +1028,44 @@ pub struct LotFilter {
     pub product_id: Option<String>,
     pub status: Option<String>,
     pub expiring_within_days: Option<i32>,
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
+}
+
+/// Inventory session filter
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct InventoryFilter {
+    pub status: Option<String>,
+    pub warehouse_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Stock valuation filter
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StockValuationFilter {
+    /// End of this day (YYYY-MM-DD), today if absent
+    pub as_of: Option<String>,
+    pub product_type: Option<String>,
+    pub warehouse_id: Option<String>,
+}
+
+/// Point-in-time stock filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockAsOfFilter {
+    /// End of this day (YYYY-MM-DD)
+    pub as_of: String,
+    pub product_type: Option<String>,
+    pub product_id: Option<String>,
+    pub warehouse_id: Option<String>,
+}
+
+/// Transfer filter
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TransferFilter {
+    pub status: Option<String>,
+    /// Transfers leaving or arriving at this warehouse
+    pub warehouse_id: Option<String>,
+    pub limit: Option<u32>,
 }
 
 /// Movement filter
@@ -13,10 +13,12 @@ mod services;
 mod api;
 mod state;
 
+use manchengo_database::profiler::{ProfilerConfig, QueryProfiler};
 use manchengo_database::{Database, DatabaseConfig};
 use manchengo_database::migrations::initialize_database;
 use state::AppState;
 use crate::core::AppConfig;
+use std::time::Duration;
 use tauri::http::Response;
 use tracing::{info, error, Level};
 use tracing_subscriber::FmtSubscriber;
@@ -49,6 +51,12 @@ fn main() {
 
     let db_path = config.database_path.to_string_lossy().to_string();
 
+    // Slow-query profiling applies to every connection opened below
+    QueryProfiler::configure(ProfilerConfig {
+        slow_threshold_ms: config.slow_query_threshold_ms,
+        explain_slow_queries: config.explain_slow_queries,
+    });
+
     // Initialize database
     let db_config = DatabaseConfig {
         path: db_path.clone(),
@@ -83,13 +91,35 @@ fn main() {
         }
     };
 
+    // Schedule database maintenance (WAL, ANALYZE, vacuum, purges), daily stock
+    // closing and the timeout of lot reservations
+    let scheduler = app_state.scheduler.clone();
+    let maintenance_service = app_state.maintenance_service.clone();
+    let stock_history_service = app_state.stock_history_service.clone();
+    let sales_order_service = app_state.sales_order_service.clone();
+    let maintenance_interval =
+        Duration::from_secs(app_state.config.blocking_read().maintenance_interval_secs);
+    tauri::async_runtime::spawn(async move {
+        scheduler.start_maintenance(maintenance_interval, move || {
+            if let Err(e) = maintenance_service.run_due() {
+                error!("Database maintenance failed: {}", e);
+            }
+            if let Err(e) = stock_history_service.close_days() {
+                error!("Daily stock closing failed: {}", e);
+            }
+            if let Err(e) = sales_order_service.expire_reservations() {
+                error!("Reservation expiry failed: {}", e);
+            }
+        });
+    });
+
     // Build and run Tauri app
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             // ================================================================
-            // SYSTEM COMMANDS (6)
+            // SYSTEM COMMANDS (12)
             // ================================================================
             api::get_app_info,
             api::get_health_status,
@@ -97,21 +127,39 @@ fn main() {
             api::check_connectivity,
             api::get_device_info,
             api::clear_local_cache,
+            api::get_business_date,
+            api::set_business_date,
+            // Maintenance
+            api::get_maintenance_history,
+            api::run_maintenance_job,
+            // Fiscal archives
+            api::list_fiscal_archives,
+            api::archive_fiscal_year,
+            // Trash
+            api::list_trash,
+            api::restore_from_trash,
 
             // ================================================================
-            // STOCK COMMANDS (31) - FIFO, Receptions, etc.
+            // STOCK COMMANDS (35) - FIFO, Receptions, etc.
             // ================================================================
             // Products
             api::list_products_mp,
             api::get_product_mp,
             api::list_products_pf,
             api::get_product_pf,
+            api::get_product_pack,
+            api::set_product_pack,
+            api::get_product_lot_strategy,
+            api::set_product_lot_strategy,
+            api::set_product_gtins,
 
             // Lots
             api::list_lots_mp,
             api::get_lot_mp,
             api::list_lots_pf,
             api::get_lot_pf,
+            api::render_lot_pf_label,
+            api::resolve_gs1_scan,
             api::block_lot,
             api::unblock_lot,
 
@@ -128,14 +176,58 @@ fn main() {
             // Receptions
             api::create_reception,
 
+            // Warehouses, locations & put-away
+            api::list_warehouses,
+            api::create_warehouse,
+            api::list_locations,
+            api::create_location,
+            api::render_location_label,
+            api::resolve_location_scan,
+            api::list_pending_put_away,
+            api::put_away_lot,
+            api::get_warehouse_stock,
+            api::get_warehouse_alerts,
+            api::set_warehouse_threshold,
+
+            // Inter-warehouse transfers
+            api::list_transfers,
+            api::get_transfer,
+            api::create_transfer,
+            api::add_transfer_line,
+            api::remove_transfer_line,
+            api::ship_transfer,
+            api::receive_transfer,
+            api::cancel_transfer,
+
             // Inventory adjustments
             api::adjust_inventory,
             api::declare_loss,
 
+            // Physical inventory sessions
+            api::list_inventory_sessions,
+            api::get_inventory_session,
+            api::open_inventory_session,
+            api::record_inventory_count,
+            api::submit_inventory_session,
+            api::reopen_inventory_session,
+            api::get_inventory_variance_report,
+            api::approve_inventory_session,
+            api::cancel_inventory_session,
+            // Stock valuation
+            api::get_stock_valuation,
+            api::get_product_cost_history,
+            // Point-in-time stock
+            api::get_stock_as_of,
+            api::list_stock_snapshots,
+
             // Movements
             api::list_movements,
             api::get_movement_history,
 
+            // Ledger integrity
+            api::check_stock_integrity,
+            api::repair_stock_integrity,
+
             // Suppliers (moved from appro)
             api::list_suppliers,
             api::get_supplier,
@@ -181,6 +273,12 @@ fn main() {
             // Supplier Management
             api::update_supplier,
             api::get_supplier_performance,
+            api::get_supplier_statement,
+
+            // Exchange rates
+            api::list_exchange_rates,
+            api::set_exchange_rate,
+            api::import_exchange_rates,
 
             // Dashboard
             api::get_appro_dashboard,
@@ -201,8 +299,14 @@ fn main() {
             api::list_price_lists,
             api::get_client_prices,
 
+            // Sales Orders
+            api::confirm_sales_order,
+            api::cancel_sales_order,
+            api::prepare_delivery_line,
+            api::list_order_reservations,
+
             // ================================================================
-            // INVOICE COMMANDS (10) - NEW
+            // INVOICE COMMANDS (13) - NEW
             // ================================================================
             // Invoices
             api::list_invoices,
@@ -210,8 +314,14 @@ fn main() {
             api::create_invoice,
             api::validate_invoice,
             api::void_invoice,
+            api::get_sequence_gap_report,
             api::calculate_invoice_totals,
             api::calculate_timbre_fiscal,
+            api::amount_in_words,
+
+            // Fiscal rules
+            api::list_fiscal_rules,
+            api::create_fiscal_rules,
 
             // Payments
             api::list_payments,
@@ -0,0 +1,50 @@
+//! Audit Log Repository
+//!
+//! Append-only trail of sensitive user actions (business date overrides, ...).
+
+use manchengo_core::{EntityId, Error, Result};
+use manchengo_database::Database;
+use rusqlite::params;
+use std::sync::Arc;
+
+/// Audit log repository
+pub struct AuditRepository {
+    db: Arc<Database>,
+    device_id: EntityId,
+}
+
+impl AuditRepository {
+    pub fn new(db: Arc<Database>, device_id: EntityId) -> Self {
+        Self { db, device_id }
+    }
+
+    /// Append an audit entry; old and new values are stored as JSON
+    pub fn record(
+        &self,
+        user_id: &str,
+        action: &str,
+        entity_type: &str,
+        entity_id: Option<&str>,
+        old_value: Option<&serde_json::Value>,
+        new_value: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO audit_log (id, user_id, action, entity_type, entity_id, old_value, new_value, device_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    EntityId::new().to_string(),
+                    user_id,
+                    action,
+                    entity_type,
+                    entity_id,
+                    old_value.map(|v| v.to_string()),
+                    new_value.map(|v| v.to_string()),
+                    self.device_id.to_string(),
+                ],
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+}
@@ -26,9 +26,9 @@ impl ClientRepository {
                 "SELECT id, code, name, company_name, email, phone, address,
                         wilaya, client_type, nif, rc, ai, is_active,
                         credit_limit, current_balance, notes,
-                        created_at, updated_at
+                        created_at, updated_at, min_shelf_life_days
                  FROM clients
-                 WHERE is_deleted = 0",
+                 WHERE deleted_at IS NULL",
             );
 
             let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -100,9 +100,9 @@ impl ClientRepository {
                     "SELECT id, code, name, company_name, email, phone, address,
                             wilaya, client_type, nif, rc, ai, is_active,
                             credit_limit, current_balance, notes,
-                            created_at, updated_at
+                            created_at, updated_at, min_shelf_life_days
                      FROM clients
-                     WHERE id = ? AND is_deleted = 0",
+                     WHERE id = ? AND deleted_at IS NULL",
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
 
@@ -119,9 +119,9 @@ impl ClientRepository {
                 "INSERT INTO clients (
                     id, code, name, company_name, email, phone, address,
                     wilaya, client_type, nif, rc, ai, is_active,
-                    credit_limit, current_balance, notes,
-                    created_at, is_deleted
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, 0, ?, datetime('now'), 0)",
+                    credit_limit, current_balance, notes, min_shelf_life_days,
+                    created_at
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, 0, ?, ?, datetime('now'))",
                 params![
                     id,
                     code,
@@ -137,6 +137,7 @@ impl ClientRepository {
                     data.ai,
                     data.credit_limit.unwrap_or(0),
                     data.notes,
+                    data.min_shelf_life_days,
                 ],
             )
             .map_err(|e| Error::Database(e.to_string()))?;
@@ -152,7 +153,7 @@ impl ClientRepository {
                     name = ?, company_name = ?, email = ?, phone = ?,
                     address = ?, wilaya = ?, client_type = ?,
                     nif = ?, rc = ?, ai = ?, credit_limit = ?, notes = ?,
-                    updated_at = datetime('now')
+                    min_shelf_life_days = ?, updated_at = datetime('now')
                  WHERE id = ?",
                 params![
                     data.name,
@@ -167,6 +168,7 @@ impl ClientRepository {
                     data.ai,
                     data.credit_limit.unwrap_or(0),
                     data.notes,
+                    data.min_shelf_life_days,
                     id,
                 ],
             )
@@ -175,18 +177,6 @@ impl ClientRepository {
         })
     }
 
-    /// Soft delete client
-    pub fn delete(&self, id: &str) -> Result<()> {
-        self.db.with_connection(|conn| {
-            conn.execute(
-                "UPDATE clients SET is_deleted = 1, updated_at = datetime('now') WHERE id = ?",
-                [id],
-            )
-            .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(())
-        })
-    }
-
     /// Update client balance
     pub fn update_balance(&self, id: &str, amount: i64) -> Result<()> {
         self.db.with_connection(|conn| {
@@ -202,6 +192,20 @@ impl ClientRepository {
         })
     }
 
+    /// Minimum remaining shelf life required by a client, if any
+    pub fn get_min_shelf_life(&self, id: &str) -> Result<Option<u32>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT min_shelf_life_days FROM clients WHERE id = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(|e| Error::Database(e.to_string()))
+        })
+    }
+
     /// Get client balance
     pub fn get_balance(&self, id: &str) -> Result<i64> {
         self.db.with_connection(|conn| {
@@ -231,7 +235,7 @@ impl ClientRepository {
         self.db.with_connection(|conn| {
             let count: i64 = conn
                 .query_row(
-                    "SELECT COUNT(*) FROM clients WHERE is_deleted = 0",
+                    "SELECT COUNT(*) FROM clients WHERE deleted_at IS NULL",
                     [],
                     |row| row.get(0),
                 )
@@ -257,6 +261,7 @@ impl ClientRepository {
             is_active: row.get(12)?,
             credit_limit: row.get(13)?,
             current_balance: row.get(14)?,
+            min_shelf_life_days: row.get(18)?,
             notes: row.get(15)?,
             created_at: row.get(16)?,
             updated_at: row.get(17)?,
@@ -0,0 +1,240 @@
+//! Delivery Repository
+//!
+//! Data access for delivery notes, loaded as domain deliveries so preparation
+//! runs the domain rules.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use manchengo_core::{AuditInfo, EntityId, Error, Money, Result, UnitOfMeasure};
+use manchengo_domain::delivery::{
+    Delivery, DeliveryLine, DeliveryLineItem, DeliveryLineStatus, DeliveryStatus, PaymentMethod,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::repositories::MovementRepository;
+
+/// Delivery repository
+///
+/// Only transactional helpers: deliveries are read and saved on the caller's
+/// transaction, next to the stock they take.
+pub struct DeliveryRepository;
+
+impl DeliveryRepository {
+    /// Get a delivery with its client lines and their items
+    pub fn get(conn: &Connection, id: &str) -> Result<Option<Delivery>> {
+        let delivery = conn.query_row(
+            "SELECT id, delivery_number, vehicle_id, driver_name, driver_phone, planned_date,
+                    departure_at, completed_at, status, total_ht, total_tva, total_ttc,
+                    total_weight_kg, qr_code, notes, created_at, created_by, updated_at, updated_by
+             FROM deliveries WHERE id = ?",
+            [id],
+            |row| Ok(Self::row_to_delivery(row)),
+        ).optional().map_err(|e| Error::Database(e.to_string()))?;
+
+        let Some(delivery) = delivery else {
+            return Ok(None);
+        };
+        let mut delivery = delivery?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, client_id, sales_order_id, sequence_number, status, delivered_at,
+                    total_ht, total_ttc, signature_path, photo_path, payment_collected,
+                    payment_method, notes, refusal_reason
+             FROM delivery_lines WHERE delivery_id = ? ORDER BY sequence_number"
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        let mut rows = stmt.query([id]).map_err(|e| Error::Database(e.to_string()))?;
+        while let Some(row) = rows.next().map_err(|e| Error::Database(e.to_string()))? {
+            let mut line = Self::row_to_line(row, delivery.id)?;
+            Self::load_items(conn, &mut line)?;
+            delivery.lines.push(line);
+        }
+
+        Ok(Some(delivery))
+    }
+
+    /// Save the items added to a client line from `first_new` on, with the new totals
+    pub fn save_added_items(conn: &Connection, delivery: &Delivery, line_id: EntityId, first_new: usize) -> Result<()> {
+        let line = delivery.lines.iter().find(|l| l.id == line_id).ok_or_else(|| Error::NotFound {
+            entity_type: "DeliveryLine".to_string(),
+            id: line_id.to_string(),
+        })?;
+
+        for item in line.items.iter().skip(first_new) {
+            conn.execute(
+                "INSERT INTO delivery_line_items (
+                    id, delivery_line_id, product_pf_id, lot_pf_id, quantity_planned, quantity_delivered,
+                    unit, unit_price_ht, total_ht
+                 ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    item.id.to_string(),
+                    line.id.to_string(),
+                    item.product_pf_id.to_string(),
+                    item.lot_pf_id.to_string(),
+                    item.quantity_planned,
+                    item.quantity_delivered,
+                    item.unit.code(),
+                    item.unit_price_ht.centimes(),
+                    item.total_ht.centimes(),
+                ],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+        }
+
+        conn.execute(
+            "UPDATE delivery_lines SET total_ht = ?, total_ttc = ? WHERE id = ?",
+            params![line.total_ht.centimes(), line.total_ttc.centimes(), line.id.to_string()],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+
+        conn.execute(
+            "UPDATE deliveries SET total_ht = ?, total_tva = ?, total_ttc = ?, updated_at = ?, updated_by = ?
+             WHERE id = ?",
+            params![
+                delivery.total_ht.centimes(),
+                delivery.total_tva.centimes(),
+                delivery.total_ttc.centimes(),
+                MovementRepository::timestamp(delivery.audit.updated_at),
+                delivery.audit.updated_by.to_string(),
+                delivery.id.to_string(),
+            ],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    // Internal helpers
+
+    fn load_items(conn: &Connection, line: &mut DeliveryLine) -> Result<()> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let mut stmt = conn.prepare(
+            "SELECT id, product_pf_id, lot_pf_id, quantity_planned, quantity_delivered, unit,
+                    unit_price_ht, total_ht, qr_scanned_at, qr_scanned_by
+             FROM delivery_line_items WHERE delivery_line_id = ? ORDER BY rowid"
+        ).map_err(db_err)?;
+        let mut rows = stmt.query([line.id.to_string()]).map_err(db_err)?;
+        while let Some(row) = rows.next().map_err(db_err)? {
+            let unit: String = row.get(5).map_err(db_err)?;
+            line.items.push(DeliveryLineItem {
+                id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+                delivery_line_id: line.id,
+                product_pf_id: Self::parse_id(&row.get::<_, String>(1).map_err(db_err)?)?,
+                lot_pf_id: Self::parse_id(&row.get::<_, String>(2).map_err(db_err)?)?,
+                quantity_planned: row.get(3).map_err(db_err)?,
+                quantity_delivered: row.get::<_, Option<f64>>(4).map_err(db_err)?.unwrap_or(0.0),
+                unit: UnitOfMeasure::from_code(&unit)
+                    .ok_or_else(|| Error::Internal(format!("Unite inconnue en base: {}", unit)))?,
+                unit_price_ht: Money::from_centimes(row.get(6).map_err(db_err)?),
+                total_ht: Money::from_centimes(row.get(7).map_err(db_err)?),
+                qr_scanned_at: Self::parse_optional_timestamp(row.get(8).map_err(db_err)?)?,
+                qr_scanned_by: row
+                    .get::<_, Option<String>>(9)
+                    .map_err(db_err)?
+                    .as_deref()
+                    .map(Self::parse_id)
+                    .transpose()?,
+            });
+        }
+        Ok(())
+    }
+
+    fn row_to_delivery(row: &Row) -> Result<Delivery> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let status: String = row.get(8).map_err(db_err)?;
+        let planned_date: String = row.get(5).map_err(db_err)?;
+
+        Ok(Delivery {
+            id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+            delivery_number: row.get(1).map_err(db_err)?,
+            vehicle_id: row
+                .get::<_, Option<String>>(2)
+                .map_err(db_err)?
+                .as_deref()
+                .map(Self::parse_id)
+                .transpose()?,
+            driver_name: row.get(3).map_err(db_err)?,
+            driver_phone: row.get(4).map_err(db_err)?,
+            planned_date: planned_date
+                .get(..10)
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .ok_or_else(|| Error::Internal(format!("Date invalide en base: {}", planned_date)))?,
+            departure_at: Self::parse_optional_timestamp(row.get(6).map_err(db_err)?)?,
+            completed_at: Self::parse_optional_timestamp(row.get(7).map_err(db_err)?)?,
+            status: match status.as_str() {
+                "DRAFT" => DeliveryStatus::Draft,
+                "PREPARED" => DeliveryStatus::Prepared,
+                "LOADED" => DeliveryStatus::Loaded,
+                "IN_TRANSIT" => DeliveryStatus::InTransit,
+                "DELIVERED" => DeliveryStatus::Delivered,
+                "PARTIAL" => DeliveryStatus::Partial,
+                "RETURNED" => DeliveryStatus::Returned,
+                other => return Err(Error::Internal(format!("Statut de livraison inconnu en base: {}", other))),
+            },
+            lines: Vec::new(), // Will be populated separately
+            total_ht: Money::from_centimes(row.get(9).map_err(db_err)?),
+            total_tva: Money::from_centimes(row.get(10).map_err(db_err)?),
+            total_ttc: Money::from_centimes(row.get(11).map_err(db_err)?),
+            total_weight_kg: row.get::<_, Option<f64>>(12).map_err(db_err)?.unwrap_or(0.0),
+            qr_code: row.get(13).map_err(db_err)?,
+            notes: row.get(14).map_err(db_err)?,
+            audit: AuditInfo {
+                created_at: Self::parse_timestamp(&row.get::<_, String>(15).map_err(db_err)?)?,
+                created_by: Self::parse_id(&row.get::<_, String>(16).map_err(db_err)?)?,
+                updated_at: Self::parse_timestamp(&row.get::<_, String>(17).map_err(db_err)?)?,
+                updated_by: Self::parse_id(&row.get::<_, String>(18).map_err(db_err)?)?,
+                business_date: None,
+            },
+        })
+    }
+
+    fn row_to_line(row: &Row, delivery_id: EntityId) -> Result<DeliveryLine> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let status: String = row.get(4).map_err(db_err)?;
+        let payment_method: Option<String> = row.get(11).map_err(db_err)?;
+
+        Ok(DeliveryLine {
+            id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+            delivery_id,
+            client_id: Self::parse_id(&row.get::<_, String>(1).map_err(db_err)?)?,
+            sales_order_id: row
+                .get::<_, Option<String>>(2)
+                .map_err(db_err)?
+                .as_deref()
+                .map(Self::parse_id)
+                .transpose()?,
+            sequence_number: row.get(3).map_err(db_err)?,
+            status: match status.as_str() {
+                "DELIVERED" => DeliveryLineStatus::Delivered,
+                "PARTIAL" => DeliveryLineStatus::Partial,
+                "REFUSED" => DeliveryLineStatus::Refused,
+                _ => DeliveryLineStatus::Pending,
+            },
+            delivered_at: Self::parse_optional_timestamp(row.get(5).map_err(db_err)?)?,
+            total_ht: Money::from_centimes(row.get(6).map_err(db_err)?),
+            total_ttc: Money::from_centimes(row.get(7).map_err(db_err)?),
+            items: Vec::new(), // Will be populated separately
+            signature_path: row.get(8).map_err(db_err)?,
+            photo_path: row.get(9).map_err(db_err)?,
+            payment_collected: Money::from_centimes(row.get::<_, Option<i64>>(10).map_err(db_err)?.unwrap_or(0)),
+            payment_method: payment_method.as_deref().map(|method| match method {
+                "CASH" => PaymentMethod::Cash,
+                "CHECK" => PaymentMethod::Check,
+                "TRANSFER" => PaymentMethod::Transfer,
+                _ => PaymentMethod::Other,
+            }),
+            notes: row.get(12).map_err(db_err)?,
+            refusal_reason: row.get(13).map_err(db_err)?,
+        })
+    }
+
+    fn parse_optional_timestamp(value: Option<String>) -> Result<Option<DateTime<Utc>>> {
+        value.as_deref().map(Self::parse_timestamp).transpose()
+    }
+
+    /// Stored timestamp (`datetime('now')` format or RFC 3339)
+    fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map(|t| t.and_utc())
+            .or_else(|_| DateTime::parse_from_rfc3339(value).map(|t| t.with_timezone(&Utc)))
+            .map_err(|_| Error::Internal(format!("Horodatage invalide en base: {}", value)))
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+}
@@ -0,0 +1,140 @@
+//! Exchange Rate Repository
+//!
+//! Dated DZD rates of foreign currencies, entered by hand or imported from
+//! CSV. Lot costs use the latest rate on or before the reception date.
+
+use chrono::NaiveDate;
+use manchengo_core::{Currency, Error, ExchangeRate, ExchangeRateTable, RateSource, Result};
+use manchengo_database::Database;
+use rusqlite::{params, Connection, Row};
+use std::sync::Arc;
+
+/// (currency, rate_date, rate, source) as stored
+type RawRate = (String, String, i64, String);
+
+/// Exchange rate repository
+pub struct ExchangeRateRepository {
+    db: Arc<Database>,
+}
+
+impl ExchangeRateRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// List rates, newest first
+    pub fn list(&self, currency: Option<Currency>, limit: u32) -> Result<Vec<ExchangeRate>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT currency, rate_date, rate, source FROM exchange_rates
+                     WHERE ?1 IS NULL OR currency = ?1
+                     ORDER BY rate_date DESC, currency
+                     LIMIT ?2",
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt
+                .query_map(params![currency.map(|c| c.code()), limit], Self::row_to_rate)
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(Self::to_rate(row.map_err(|e| Error::Database(e.to_string()))?)?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Insert or replace rates (same currency and date) in one transaction
+    pub fn save(&self, rates: &[ExchangeRate], user_id: &str) -> Result<usize> {
+        for rate in rates {
+            rate.validate()?;
+        }
+
+        self.db.transaction(|tx| {
+            for rate in rates {
+                tx.execute(
+                    "INSERT INTO exchange_rates (currency, rate_date, rate, source, created_by)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (currency, rate_date) DO UPDATE SET
+                        rate = excluded.rate,
+                        source = excluded.source,
+                        created_by = excluded.created_by,
+                        created_at = datetime('now')",
+                    params![
+                        rate.currency.code(),
+                        rate.rate_date.to_string(),
+                        rate.rate,
+                        rate.source.as_str(),
+                        user_id,
+                    ],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+            }
+            Ok(rates.len())
+        })
+    }
+
+    /// Rate in force on a date for a currency
+    pub fn rate_on(&self, currency: Currency, date: NaiveDate) -> Result<ExchangeRate> {
+        self.db.with_connection(|conn| Self::rate_on_internal(conn, currency, date))
+    }
+
+    /// All rates of a currency up to a date, for bulk conversions
+    pub fn table(&self, currency: Currency, until: NaiveDate) -> Result<ExchangeRateTable> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT currency, rate_date, rate, source FROM exchange_rates
+                     WHERE currency = ? AND rate_date <= ?
+                     ORDER BY rate_date",
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt
+                .query_map(params![currency.code(), until.to_string()], Self::row_to_rate)
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut rates = Vec::new();
+            for row in rows {
+                rates.push(Self::to_rate(row.map_err(|e| Error::Database(e.to_string()))?)?);
+            }
+            ExchangeRateTable::new(rates)
+        })
+    }
+
+    fn rate_on_internal(conn: &Connection, currency: Currency, date: NaiveDate) -> Result<ExchangeRate> {
+        let rate = conn.query_row(
+            "SELECT currency, rate_date, rate, source FROM exchange_rates
+             WHERE currency = ? AND rate_date <= ?
+             ORDER BY rate_date DESC LIMIT 1",
+            params![currency.code(), date.to_string()],
+            Self::row_to_rate,
+        );
+
+        match rate {
+            Ok(raw) => Self::to_rate(raw),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(Error::BusinessRule(format!(
+                "Aucun taux de change {} au {}",
+                currency, date
+            ))),
+            Err(e) => Err(Error::Database(e.to_string())),
+        }
+    }
+
+    fn row_to_rate(row: &Row) -> rusqlite::Result<RawRate> {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    }
+
+    fn to_rate((currency, rate_date, rate, source): RawRate) -> Result<ExchangeRate> {
+        Ok(ExchangeRate {
+            currency: Currency::from_code(&currency)
+                .ok_or_else(|| Error::Database(format!("Devise inconnue: {}", currency)))?,
+            rate_date: NaiveDate::parse_from_str(&rate_date, "%Y-%m-%d")
+                .map_err(|e| Error::Database(e.to_string()))?,
+            rate,
+            source: RateSource::from_code(&source),
+        })
+    }
+}
@@ -0,0 +1,142 @@
+//! Fiscal Rule Repository
+//!
+//! Data access for date-effective fiscal rule sets (TVA, timbre, exemptions).
+//! Each set is stored as JSON; the 2025 Finance Law seeds an empty table,
+//! preceded by the historical rates so older invoices keep their rules.
+
+use chrono::NaiveDate;
+use manchengo_core::{Error, FiscalRuleBook, FiscalRuleSet, Result};
+use manchengo_database::Database;
+use rusqlite::{params, Connection};
+use std::sync::Arc;
+
+/// Fiscal rule set repository
+pub struct FiscalRuleRepository {
+    db: Arc<Database>,
+}
+
+impl FiscalRuleRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// List all rule sets, oldest first
+    pub fn list(&self) -> Result<Vec<FiscalRuleSet>> {
+        self.db.with_connection(|conn| {
+            Self::seed_if_empty(conn)?;
+            Self::load(conn)
+        })
+    }
+
+    /// Rule book used to compute invoices
+    pub fn rule_book(&self) -> Result<FiscalRuleBook> {
+        FiscalRuleBook::new(self.list()?)
+    }
+
+    /// Add a rule set; the set still in force is closed on its start date
+    pub fn create(&self, rule_set: &FiscalRuleSet, user_id: &str) -> Result<()> {
+        rule_set.validate()?;
+
+        self.db.transaction(|tx| {
+            Self::seed_if_empty(tx)?;
+
+            let mut sets = Self::load(tx)?;
+            for set in sets.iter_mut() {
+                if set.valid_to.is_none() && set.valid_from < rule_set.valid_from {
+                    set.valid_to = Some(rule_set.valid_from);
+                    Self::update(tx, set)?;
+                }
+            }
+
+            sets.push(rule_set.clone());
+            FiscalRuleBook::new(sets)?;
+
+            Self::insert(tx, rule_set, user_id)
+        })
+    }
+
+    fn load(conn: &Connection) -> Result<Vec<FiscalRuleSet>> {
+        let mut stmt = conn
+            .prepare("SELECT rules FROM fiscal_rule_sets ORDER BY valid_from")
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut sets = Vec::new();
+        for row in rows {
+            let json = row.map_err(|e| Error::Database(e.to_string()))?;
+            sets.push(serde_json::from_str(&json).map_err(|e| Error::Internal(e.to_string()))?);
+        }
+        Ok(sets)
+    }
+
+    fn seed_if_empty(conn: &Connection) -> Result<()> {
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM fiscal_rule_sets", [], |row| row.get(0))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        if count == 0 {
+            Self::insert(conn, &FiscalRuleSet::finance_law_2025(), "system")?;
+        }
+
+        // Tables seeded before the historical set get it in front of the oldest set
+        let oldest = Self::load(conn)?.into_iter().map(|s| s.valid_from).min();
+        if let Some(first) = oldest.filter(|d| *d > NaiveDate::MIN) {
+            Self::insert(conn, &FiscalRuleSet::historical(first), "system")?;
+        }
+        Ok(())
+    }
+
+    fn insert(conn: &Connection, rule_set: &FiscalRuleSet, user_id: &str) -> Result<()> {
+        let json = serde_json::to_string(rule_set).map_err(|e| Error::Internal(e.to_string()))?;
+
+        conn.execute(
+            "INSERT INTO fiscal_rule_sets (version, valid_from, valid_to, rules, created_at, created_by)
+             VALUES (?, ?, ?, ?, datetime('now'), ?)",
+            params![
+                rule_set.version,
+                rule_set.valid_from.to_string(),
+                rule_set.valid_to.map(|d| d.to_string()),
+                json,
+                user_id,
+            ],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    fn update(conn: &Connection, rule_set: &FiscalRuleSet) -> Result<()> {
+        let json = serde_json::to_string(rule_set).map_err(|e| Error::Internal(e.to_string()))?;
+
+        conn.execute(
+            "UPDATE fiscal_rule_sets SET valid_to = ?, rules = ? WHERE version = ?",
+            params![rule_set.valid_to.map(|d| d.to_string()), json, rule_set.version],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manchengo_database::DatabaseConfig;
+
+    #[test]
+    fn test_invoice_before_2025_finds_rules() {
+        let db = Arc::new(Database::open(DatabaseConfig::in_memory()).unwrap());
+        db.with_connection(manchengo_database::migrations::initialize_database)
+            .unwrap();
+        let repo = FiscalRuleRepository::new(db);
+
+        let book = repo.rule_book().unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 11, 20).unwrap();
+        assert_eq!(book.in_force_on(date).unwrap().version, "HISTORIQUE");
+        assert_eq!(book.in_force_on(date).unwrap().tva_standard, manchengo_core::fiscal::TVA_STANDARD);
+
+        // Seeded once
+        assert_eq!(repo.list().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,412 @@
+//! Inventory Repository
+//!
+//! Data access for physical inventory sessions and their count sheets.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use manchengo_core::{EntityId, Error, Money, Qty, Result};
+use manchengo_database::{Database, DocumentSequences, DocumentType};
+use manchengo_domain::stock::{
+    CountMethod, InventoryAdjustment, InventoryLine, InventorySession, InventorySnapshotLot, InventoryStatus,
+    ProductType,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use std::sync::Arc;
+
+use crate::dto::{InventoryFilter, InventoryLineDto, InventorySessionDto};
+use crate::repositories::MovementRepository;
+
+const SESSION_SELECT: &str =
+    "SELECT s.id, s.reference, s.warehouse_id, w.code, s.status, s.blind, s.recount_threshold_pct,
+            s.notes, s.submitted_at, s.approved_at, s.approved_by, s.created_at, s.created_by
+     FROM inventory_sessions s
+     JOIN warehouses w ON w.id = s.warehouse_id";
+
+/// Inventory session repository
+pub struct InventoryRepository {
+    db: Arc<Database>,
+}
+
+impl InventoryRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// List sessions, newest first
+    pub fn list(&self, filter: InventoryFilter) -> Result<Vec<InventorySessionDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "{} WHERE (?1 IS NULL OR s.status = ?1)
+                   AND (?2 IS NULL OR s.warehouse_id = ?2)
+                 ORDER BY s.created_at DESC
+                 LIMIT ?3",
+                SESSION_SELECT
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map(
+                params![filter.status, filter.warehouse_id, filter.limit.unwrap_or(100)],
+                Self::row_to_dto,
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                let mut dto = row.map_err(|e| Error::Database(e.to_string()))?;
+                Self::load_lines(conn, &mut dto)?;
+                result.push(dto);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Get session with its count sheet
+    pub fn get(&self, id: &str) -> Result<Option<InventorySessionDto>> {
+        self.db.with_connection(|conn| {
+            let session = conn
+                .query_row(&format!("{} WHERE s.id = ?", SESSION_SELECT), [id], Self::row_to_dto)
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            match session {
+                Some(mut dto) => {
+                    Self::load_lines(conn, &mut dto)?;
+                    Ok(Some(dto))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// Session still counting or in review on a warehouse
+    pub fn open_session_of(&self, warehouse_id: &str) -> Result<Option<String>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT reference FROM inventory_sessions
+                 WHERE warehouse_id = ? AND status IN ('COUNTING', 'REVIEW')
+                 LIMIT 1",
+                [warehouse_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Database(e.to_string()))
+        })
+    }
+
+    /// Lots of a warehouse holding stock, as they stand now
+    ///
+    /// Lots in its TRANSIT location are on the road and cannot be counted.
+    pub fn snapshot(&self, warehouse_id: &str) -> Result<Vec<InventorySnapshotLot>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT 'MP', l.product_mp_id, l.id, l.lot_number, l.location_id, l.quantity_remaining, l.unit_cost
+                 FROM lots_mp l
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
+                 WHERE l.warehouse_id = ?1 AND l.status <> 'CONSUMED' AND l.quantity_remaining > 0
+                   AND l.deleted_at IS NULL AND (wl.location_type IS NULL OR wl.location_type <> 'TRANSIT')
+                 UNION ALL
+                 SELECT 'PF', l.product_pf_id, l.id, l.lot_number, l.location_id, l.quantity_remaining, l.unit_cost
+                 FROM lots_pf l
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
+                 WHERE l.warehouse_id = ?1 AND l.status <> 'CONSUMED' AND l.quantity_remaining > 0
+                   AND l.deleted_at IS NULL AND (wl.location_type IS NULL OR wl.location_type <> 'TRANSIT')
+                 ORDER BY 5, 4"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([warehouse_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Qty>(5)?,
+                    row.get::<_, i64>(6)?,
+                ))
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                let (product_type, product_id, lot_id, lot_number, location_id, quantity, unit_cost) =
+                    row.map_err(|e| Error::Database(e.to_string()))?;
+                result.push(InventorySnapshotLot {
+                    product_type: if product_type == "MP" { ProductType::Mp } else { ProductType::Pf },
+                    product_id: Self::parse_id(&product_id)?,
+                    lot_id: Self::parse_id(&lot_id)?,
+                    lot_number,
+                    location_id: location_id.as_deref().map(Self::parse_id).transpose()?,
+                    quantity,
+                    unit_cost: Money::from_centimes(unit_cost),
+                });
+            }
+            Ok(result)
+        })
+    }
+
+    /// Create session and its count sheet
+    ///
+    /// The reference is allocated in the same transaction and returned.
+    pub fn create(&self, session: &InventorySession, date: NaiveDate, user_id: &str) -> Result<String> {
+        let id = session.id.to_string();
+        self.db.transaction(|conn| {
+            let reference = DocumentSequences::allocate(conn, DocumentType::Inventory, date, &id)?.number;
+
+            conn.execute(
+                "INSERT INTO inventory_sessions (id, reference, warehouse_id, status, blind, recount_threshold_pct, notes, created_by)
+                 VALUES (?, ?, ?, 'COUNTING', ?, ?, ?, ?)",
+                params![
+                    id,
+                    reference,
+                    session.warehouse_id.to_string(),
+                    session.blind,
+                    session.recount_threshold_pct,
+                    session.notes,
+                    user_id,
+                ],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            for line in &session.lines {
+                conn.execute(
+                    "INSERT INTO inventory_lines (
+                        id, session_id, product_type, product_id, lot_id, lot_number, location_id,
+                        quantity_theoretical, unit_cost
+                     ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        line.id.to_string(),
+                        id,
+                        if line.product_type == ProductType::Mp { "MP" } else { "PF" },
+                        line.product_id.to_string(),
+                        line.lot_id.to_string(),
+                        line.lot_number,
+                        line.location_id.map(|l| l.to_string()),
+                        line.quantity_theoretical,
+                        line.unit_cost.centimes(),
+                    ],
+                ).map_err(|e| Error::Database(e.to_string()))?;
+            }
+
+            Ok(reference)
+        })
+    }
+
+    /// Save the counts of a line
+    pub fn save_counts(&self, session_id: &str, line: &InventoryLine) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "UPDATE inventory_lines SET
+                    first_count = ?1, first_count_method = ?2, first_counted_by = ?3,
+                    first_counted_at = CASE WHEN ?1 IS NULL THEN NULL ELSE COALESCE(first_counted_at, datetime('now')) END,
+                    second_count = ?4, second_count_method = ?5, second_counted_by = ?6,
+                    second_counted_at = CASE WHEN ?4 IS NULL THEN NULL ELSE COALESCE(second_counted_at, datetime('now')) END
+                 WHERE id = ?7",
+                params![
+                    line.first_count,
+                    line.first_count_method.map(|m| m.as_str()),
+                    line.first_counted_by.map(|u| u.to_string()),
+                    line.second_count,
+                    line.second_count_method.map(|m| m.as_str()),
+                    line.second_counted_by.map(|u| u.to_string()),
+                    line.id.to_string(),
+                ],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            conn.execute(
+                "UPDATE inventory_sessions SET updated_at = datetime('now') WHERE id = ?",
+                [session_id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    /// Move the session to REVIEW or back to COUNTING
+    pub fn set_status(&self, id: &str, status: InventoryStatus) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "UPDATE inventory_sessions SET
+                    status = ?1,
+                    submitted_at = CASE WHEN ?1 = 'REVIEW' THEN datetime('now') ELSE NULL END,
+                    updated_at = datetime('now')
+                 WHERE id = ?2",
+                params![status.as_str(), id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    /// Post the approved adjustments and close the session, in one transaction
+    ///
+    /// Each variance moves its lot by the counted difference from the
+    /// quantity it holds now, so movements made while counting are kept. A
+    /// loss larger than the lot holds empties it, and only that much is
+    /// posted, so the ledger matches the lot. Movements take the business
+    /// date of `movements`.
+    pub fn approve(
+        &self,
+        session: &InventorySessionDto,
+        adjustments: &[InventoryAdjustment],
+        movements: &MovementRepository,
+        user_id: &str,
+    ) -> Result<Vec<String>> {
+        self.db.transaction(|conn| {
+            let mut movement_ids = Vec::with_capacity(adjustments.len());
+            for adjustment in adjustments {
+                let (lot_table, product_type) = match adjustment.product_type {
+                    ProductType::Mp => ("lots_mp", "MP"),
+                    ProductType::Pf => ("lots_pf", "PF"),
+                };
+                let lot_id = adjustment.lot_id.to_string();
+                let entry = adjustment.movement_type.is_entry();
+
+                let current: Qty = conn
+                    .query_row(&format!("SELECT quantity_remaining FROM {} WHERE id = ?", lot_table), [&lot_id], |row| row.get(0))
+                    .map_err(|e| Error::Database(e.to_string()))?;
+                let (updated, delta) = if entry {
+                    (current + adjustment.quantity, adjustment.quantity)
+                } else {
+                    let updated = (current - adjustment.quantity).max(Qty::zero());
+                    (updated, current - updated)
+                };
+                if delta.is_zero() {
+                    continue;
+                }
+
+                conn.execute(
+                    &format!(
+                        "UPDATE {} SET
+                            quantity_remaining = ?1,
+                            status = CASE
+                                WHEN ?1 = 0 AND status = 'AVAILABLE' THEN 'CONSUMED'
+                                WHEN ?1 > 0 AND status = 'CONSUMED' THEN 'AVAILABLE'
+                                ELSE status
+                            END,
+                            updated_at = datetime('now')
+                         WHERE id = ?2",
+                        lot_table
+                    ),
+                    params![updated, lot_id],
+                ).map_err(|e| Error::Database(e.to_string()))?;
+
+                let movement_id = EntityId::new().to_string();
+                movements.create_on(
+                    conn,
+                    &movement_id,
+                    if entry { "IN" } else { "OUT" },
+                    product_type,
+                    &adjustment.product_id.to_string(),
+                    Some(&lot_id),
+                    delta,
+                    Some(adjustment.unit_cost.centimes()),
+                    "INVENTAIRE",
+                    Some("INVENTORY"),
+                    Some(&session.id),
+                    user_id,
+                    &format!("INV-{}", adjustment.line_id),
+                    Some(&format!("Inventaire {} ({})", session.reference, adjustment.movement_type.as_str())),
+                )?;
+                movement_ids.push(movement_id);
+            }
+
+            conn.execute(
+                "UPDATE inventory_sessions SET
+                    status = 'APPROVED', approved_at = datetime('now'), approved_by = ?,
+                    updated_at = datetime('now')
+                 WHERE id = ?",
+                params![user_id, session.id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            Ok(movement_ids)
+        })
+    }
+
+    /// Cancel session
+    ///
+    /// The session keeps its reference, marked VOIDED in the sequence.
+    pub fn cancel(&self, id: &str) -> Result<()> {
+        self.db.transaction(|conn| {
+            conn.execute(
+                "UPDATE inventory_sessions SET status = 'CANCELLED', updated_at = datetime('now') WHERE id = ?",
+                [id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let reference: Option<String> = conn
+                .query_row("SELECT reference FROM inventory_sessions WHERE id = ?", [id], |row| row.get(0))
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))?;
+            if let Some(reference) = reference {
+                DocumentSequences::void(conn, &reference, "Inventaire annule")?;
+            }
+            Ok(())
+        })
+    }
+
+    // Internal helpers
+
+    fn load_lines(conn: &rusqlite::Connection, session: &mut InventorySessionDto) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "SELECT il.id, il.product_type, il.product_id, COALESCE(mp.name, pf.name, ''),
+                    il.lot_id, il.lot_number, il.location_id, wl.code,
+                    il.quantity_theoretical, COALESCE(mp.unit, pf.unit, ''), il.unit_cost,
+                    il.first_count, il.first_count_method, il.first_counted_by,
+                    il.second_count, il.second_count_method, il.second_counted_by
+             FROM inventory_lines il
+             LEFT JOIN products_mp mp ON il.product_type = 'MP' AND mp.id = il.product_id
+             LEFT JOIN products_pf pf ON il.product_type = 'PF' AND pf.id = il.product_id
+             LEFT JOIN warehouse_locations wl ON wl.id = il.location_id
+             WHERE il.session_id = ?
+             ORDER BY wl.code, il.lot_number"
+        ).map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([&session.id], |row| {
+            Ok(InventoryLineDto {
+                id: row.get(0)?,
+                product_type: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                lot_id: row.get(4)?,
+                lot_number: row.get(5)?,
+                location_id: row.get(6)?,
+                location_code: row.get(7)?,
+                quantity_theoretical: Some(row.get(8)?),
+                unit: row.get(9)?,
+                unit_cost: row.get(10)?,
+                first_count: row.get(11)?,
+                first_count_method: row.get::<_, Option<String>>(12)?.as_deref().and_then(CountMethod::from_code),
+                first_counted_by: row.get(13)?,
+                second_count: row.get(14)?,
+                second_count_method: row.get::<_, Option<String>>(15)?.as_deref().and_then(CountMethod::from_code),
+                second_counted_by: row.get(16)?,
+                needs_second_count: false,
+            })
+        }).map_err(|e| Error::Database(e.to_string()))?;
+
+        for row in rows {
+            let line = row.map_err(|e| Error::Database(e.to_string()))?;
+            if line.first_count.is_some() || line.second_count.is_some() {
+                session.lots_counted += 1;
+            }
+            session.lines.push(line);
+        }
+        Ok(())
+    }
+
+    fn row_to_dto(row: &Row) -> rusqlite::Result<InventorySessionDto> {
+        Ok(InventorySessionDto {
+            id: row.get(0)?,
+            reference: row.get(1)?,
+            warehouse_id: row.get(2)?,
+            warehouse_code: row.get(3)?,
+            status: InventoryStatus::from_code(&row.get::<_, String>(4)?).unwrap_or(InventoryStatus::Counting),
+            blind: row.get(5)?,
+            recount_threshold_pct: row.get(6)?,
+            notes: row.get(7)?,
+            lines: Vec::new(), // Will be populated separately
+            lots_counted: 0,
+            submitted_at: row.get(8)?,
+            approved_at: row.get(9)?,
+            approved_by: row.get(10)?,
+            created_at: row.get(11)?,
+            created_by: row.get(12)?,
+        })
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! Data access for Invoice and InvoiceLine entities.
 
-use manchengo_core::{Error, Result};
-use manchengo_database::Database;
+use chrono::NaiveDate;
+use manchengo_core::{AmountInWords, Error, Money, Result};
+use manchengo_database::archive::FiscalArchive;
+use manchengo_database::{Database, DocumentSequences, DocumentType};
 use rusqlite::{params, Row, OptionalExtension};
 use std::sync::Arc;
 
@@ -22,74 +24,85 @@ impl InvoiceRepository {
     /// List invoices with optional filter
     pub fn list(&self, filter: InvoiceFilter) -> Result<Vec<InvoiceDto>> {
         self.db.with_connection(|conn| {
-            let mut sql = String::from(
-                "SELECT i.id, i.invoice_number, i.client_id,
-                        c.name as client_name, c.code as client_code,
-                        i.status, i.total_ht, i.total_tva, i.total_ttc,
-                        i.timbre_fiscal, i.payment_method, i.payment_due_date,
-                        i.notes, i.created_at, i.validated_at, i.voided_at
-                 FROM invoices i
-                 LEFT JOIN clients c ON c.id = i.client_id
-                 WHERE i.is_deleted = 0",
-            );
-
-            let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-
-            if let Some(ref status) = filter.status {
-                sql.push_str(" AND i.status = ?");
-                params_vec.push(Box::new(status.clone()));
-            }
-
-            if let Some(ref client_id) = filter.client_id {
-                sql.push_str(" AND i.client_id = ?");
-                params_vec.push(Box::new(client_id.clone()));
-            }
-
-            if let Some(ref from_date) = filter.from_date {
-                sql.push_str(" AND i.created_at >= ?");
-                params_vec.push(Box::new(from_date.clone()));
-            }
-
-            if let Some(ref to_date) = filter.to_date {
-                sql.push_str(" AND i.created_at <= ?");
-                params_vec.push(Box::new(to_date.clone()));
-            }
-
-            sql.push_str(" ORDER BY i.created_at DESC");
-
-            if let Some(limit) = filter.limit {
-                sql.push_str(&format!(" LIMIT {}", limit));
-            } else {
-                sql.push_str(" LIMIT 100");
-            }
-
-            let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
-
-            let mut result: Vec<InvoiceDto> = Vec::new();
-
-            if params_vec.is_empty() {
-                let invoices = stmt
-                    .query_map([], |row| Self::row_to_dto(row))
-                    .map_err(|e| Error::Database(e.to_string()))?;
-                for invoice in invoices {
-                    let mut dto = invoice.map_err(|e| Error::Database(e.to_string()))?;
-                    dto.lines = self.get_lines_internal(conn, &dto.id)?;
-                    result.push(dto);
-                }
-            } else {
-                let params_refs: Vec<&dyn rusqlite::ToSql> =
-                    params_vec.iter().map(|p| p.as_ref()).collect();
-                let invoices = stmt
-                    .query_map(params_refs.as_slice(), |row| Self::row_to_dto(row))
-                    .map_err(|e| Error::Database(e.to_string()))?;
-                for invoice in invoices {
-                    let mut dto = invoice.map_err(|e| Error::Database(e.to_string()))?;
-                    dto.lines = self.get_lines_internal(conn, &dto.id)?;
-                    result.push(dto);
-                }
-            }
-
-            Ok(result)
+            FiscalArchive::with_range(
+                conn,
+                filter.from_date.as_deref(),
+                filter.to_date.as_deref(),
+                |archives| {
+                    let invoices_source = archives.source(conn, "invoices")?;
+                    let lines_source = archives.source(conn, "invoice_lines")?;
+
+                    let mut sql = format!(
+                        "SELECT i.id, i.invoice_number, i.client_id,
+                                c.name as client_name, c.code as client_code,
+                                i.status, i.total_ht, i.total_tva, i.total_ttc,
+                                i.timbre_fiscal, i.payment_method, i.payment_due_date,
+                                i.notes, i.created_at, i.validated_at, i.voided_at
+                         FROM {} i
+                         LEFT JOIN clients c ON c.id = i.client_id
+                         WHERE i.is_deleted = 0 AND i.deleted_at IS NULL",
+                        invoices_source
+                    );
+
+                    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+                    if let Some(ref status) = filter.status {
+                        sql.push_str(" AND i.status = ?");
+                        params_vec.push(Box::new(status.clone()));
+                    }
+
+                    if let Some(ref client_id) = filter.client_id {
+                        sql.push_str(" AND i.client_id = ?");
+                        params_vec.push(Box::new(client_id.clone()));
+                    }
+
+                    if let Some(ref from_date) = filter.from_date {
+                        sql.push_str(" AND i.created_at >= ?");
+                        params_vec.push(Box::new(from_date.clone()));
+                    }
+
+                    if let Some(ref to_date) = filter.to_date {
+                        sql.push_str(" AND i.created_at <= ?");
+                        params_vec.push(Box::new(to_date.clone()));
+                    }
+
+                    sql.push_str(" ORDER BY i.created_at DESC");
+
+                    if let Some(limit) = filter.limit {
+                        sql.push_str(&format!(" LIMIT {}", limit));
+                    } else {
+                        sql.push_str(" LIMIT 100");
+                    }
+
+                    let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
+
+                    let mut result: Vec<InvoiceDto> = Vec::new();
+
+                    if params_vec.is_empty() {
+                        let invoices = stmt
+                            .query_map([], |row| Self::row_to_dto(row))
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                        for invoice in invoices {
+                            let mut dto = invoice.map_err(|e| Error::Database(e.to_string()))?;
+                            dto.lines = self.get_lines_internal(conn, &lines_source, &dto.id)?;
+                            result.push(dto);
+                        }
+                    } else {
+                        let params_refs: Vec<&dyn rusqlite::ToSql> =
+                            params_vec.iter().map(|p| p.as_ref()).collect();
+                        let invoices = stmt
+                            .query_map(params_refs.as_slice(), |row| Self::row_to_dto(row))
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                        for invoice in invoices {
+                            let mut dto = invoice.map_err(|e| Error::Database(e.to_string()))?;
+                            dto.lines = self.get_lines_internal(conn, &lines_source, &dto.id)?;
+                            result.push(dto);
+                        }
+                    }
+
+                    Ok(result)
+                },
+            )
         })
     }
 
@@ -105,7 +118,7 @@ impl InvoiceRepository {
                             i.notes, i.created_at, i.validated_at, i.voided_at
                      FROM invoices i
                      LEFT JOIN clients c ON c.id = i.client_id
-                     WHERE i.id = ? AND i.is_deleted = 0",
+                     WHERE i.id = ? AND i.is_deleted = 0 AND i.deleted_at IS NULL",
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
 
@@ -115,7 +128,7 @@ impl InvoiceRepository {
                 .map_err(|e| Error::Database(e.to_string()))?;
 
             if let Some(mut dto) = invoice {
-                dto.lines = self.get_lines_internal(conn, &dto.id)?;
+                dto.lines = self.get_lines_internal(conn, "invoice_lines", &dto.id)?;
                 Ok(Some(dto))
             } else {
                 Ok(None)
@@ -124,19 +137,24 @@ impl InvoiceRepository {
     }
 
     /// Create new invoice
-    pub fn create(&self, id: &str, invoice_number: &str, data: &CreateInvoiceDto, totals: &InvoiceTotalsDto) -> Result<()> {
-        self.db.with_connection(|conn| {
+    ///
+    /// The invoice number is allocated in the same transaction and returned.
+    pub fn create(&self, id: &str, invoice_date: NaiveDate, data: &CreateInvoiceDto, totals: &InvoiceTotalsDto) -> Result<String> {
+        self.db.transaction(|conn| {
+            let invoice_number = DocumentSequences::allocate(conn, DocumentType::Invoice, invoice_date, id)?.number;
+
             conn.execute(
                 "INSERT INTO invoices (
-                    id, invoice_number, client_id, status,
+                    id, invoice_number, client_id, invoice_date, status,
                     total_ht, total_tva, total_ttc, timbre_fiscal,
                     payment_method, payment_due_date, notes,
                     created_at, is_deleted
-                ) VALUES (?, ?, ?, 'DRAFT', ?, ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
+                ) VALUES (?, ?, ?, ?, 'DRAFT', ?, ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
                 params![
                     id,
                     invoice_number,
                     data.client_id,
+                    invoice_date.to_string(),
                     totals.total_ht,
                     totals.total_tva,
                     totals.total_ttc,
@@ -148,12 +166,9 @@ impl InvoiceRepository {
             )
             .map_err(|e| Error::Database(e.to_string()))?;
 
-            // Insert lines
-            for (idx, line) in data.lines.iter().enumerate() {
+            // Insert lines (amounts computed with the fiscal rules of the invoice date)
+            for (idx, line) in totals.lines.iter().enumerate() {
                 let line_id = manchengo_core::EntityId::new().to_string();
-                let line_ht = (line.unit_price_ht as f64 * line.quantity as f64) as i64;
-                let line_tva = (line_ht as f64 * 0.19) as i64; // 19% TVA
-                let line_ttc = line_ht + line_tva;
 
                 conn.execute(
                     "INSERT INTO invoice_lines (
@@ -167,16 +182,16 @@ impl InvoiceRepository {
                         line.product_pf_id,
                         line.quantity,
                         line.unit_price_ht,
-                        line_ht,
-                        line_tva,
-                        line_ttc,
+                        line.line_ht,
+                        line.line_tva,
+                        line.line_ttc,
                         idx + 1,
                     ],
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
             }
 
-            Ok(())
+            Ok(invoice_number)
         })
     }
 
@@ -193,17 +208,27 @@ impl InvoiceRepository {
     }
 
     /// Void invoice (annulation)
+    ///
+    /// The invoice keeps its number, marked VOIDED in the sequence.
     pub fn void(&self, id: &str, reason: Option<&str>) -> Result<()> {
-        self.db.with_connection(|conn| {
-            conn.execute(
+        self.db.transaction(|conn| {
+            let reason = reason.unwrap_or("Annulation");
+            let voided = conn.execute(
                 "UPDATE invoices SET
                     status = 'VOIDED',
                     voided_at = datetime('now'),
                     notes = COALESCE(notes || ' | Annulée: ' || ?, notes)
                  WHERE id = ? AND status != 'PAID'",
-                params![reason.unwrap_or("Annulation"), id],
+                params![reason, id],
             )
             .map_err(|e| Error::Database(e.to_string()))?;
+
+            if voided > 0 {
+                let number: String = conn
+                    .query_row("SELECT invoice_number FROM invoices WHERE id = ?", [id], |row| row.get(0))
+                    .map_err(|e| Error::Database(e.to_string()))?;
+                DocumentSequences::void(conn, &number, reason)?;
+            }
             Ok(())
         })
     }
@@ -220,21 +245,6 @@ impl InvoiceRepository {
         })
     }
 
-    /// Generate unique invoice number
-    pub fn generate_number(&self) -> Result<String> {
-        self.db.with_connection(|conn| {
-            let today = chrono::Utc::now().format("%Y%m%d").to_string();
-            let count: i64 = conn
-                .query_row(
-                    "SELECT COUNT(*) FROM invoices WHERE invoice_number LIKE ?",
-                    [format!("FAC-{}-%%", today)],
-                    |row| row.get(0),
-                )
-                .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(format!("FAC-{}-{:03}", today, count + 1))
-        })
-    }
-
     /// Get outstanding invoices for client
     pub fn get_outstanding(&self, client_id: &str) -> Result<Vec<InvoiceDto>> {
         self.list(InvoiceFilter {
@@ -249,7 +259,7 @@ impl InvoiceRepository {
         self.db.with_connection(|conn| {
             let count: i64 = conn
                 .query_row(
-                    "SELECT COUNT(*) FROM invoices WHERE status = ? AND is_deleted = 0",
+                    "SELECT COUNT(*) FROM invoices WHERE status = ? AND is_deleted = 0 AND deleted_at IS NULL",
                     [status],
                     |row| row.get(0),
                 )
@@ -264,7 +274,7 @@ impl InvoiceRepository {
             let total: i64 = conn
                 .query_row(
                     "SELECT COALESCE(SUM(total_ttc), 0) FROM invoices
-                     WHERE date(created_at) = date('now') AND status != 'VOIDED' AND is_deleted = 0",
+                     WHERE date(created_at) = date('now') AND status != 'VOIDED' AND is_deleted = 0 AND deleted_at IS NULL",
                     [],
                     |row| row.get(0),
                 )
@@ -278,18 +288,20 @@ impl InvoiceRepository {
     fn get_lines_internal(
         &self,
         conn: &rusqlite::Connection,
+        lines_source: &str,
         invoice_id: &str,
     ) -> Result<Vec<InvoiceLineDto>> {
         let mut stmt = conn
-            .prepare(
+            .prepare(&format!(
                 "SELECT il.id, il.product_pf_id, pf.name as pf_name, pf.code as pf_code,
                         il.quantity, il.unit_price_ht, il.line_total_ht,
                         il.line_total_tva, il.line_total_ttc
-                 FROM invoice_lines il
+                 FROM {} il
                  LEFT JOIN products_pf pf ON pf.id = il.product_pf_id
                  WHERE il.invoice_id = ?
                  ORDER BY il.sort_order ASC",
-            )
+                lines_source
+            ))
             .map_err(|e| Error::Database(e.to_string()))?;
 
         let lines = stmt
@@ -326,6 +338,7 @@ impl InvoiceRepository {
             total_ht: row.get(6)?,
             total_tva: row.get(7)?,
             total_ttc: row.get(8)?,
+            total_in_words: AmountInWords::new(Money::from_centimes(row.get(8)?)),
             timbre_fiscal: row.get(9)?,
             payment_method: row.get(10)?,
             payment_due_date: row.get(11)?,
@@ -2,31 +2,56 @@
 //!
 //! Data access for LotMp and LotPf entities with FIFO support.
 
-use chrono::{NaiveDate, Utc};
-use manchengo_core::{Error, Result};
+use chrono::NaiveDate;
+use manchengo_core::{
+    AuditInfo, Clock, Currency, CurrencyAmount, EntityId, Error, ExchangeRate, Money, Qty, Result, SharedClock, UnitOfMeasure,
+};
 use manchengo_database::Database;
-use rusqlite::{params, Row};
+use manchengo_domain::stock::{LotPf, LotStatus as DomainLotStatus};
+use rusqlite::{params, Connection, Row};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::dto::{ExpiringLotDto, LotFilter, LotMpDto, LotPfDto, LotStatus};
+use crate::dto::appro::SupplierStatementLineDto;
+use crate::dto::{ExpiringLotDto, LotFilter, LotLedgerDto, LotMpDto, LotPfDto, LotStatus};
+use crate::repositories::{MovementRepository, ReservationRepository};
+
+/// Where a new lot is stored
+///
+/// Lots not yet put away wait in the reception location and are skipped by FIFO.
+#[derive(Debug, Clone, Copy)]
+pub struct LotPlacement<'a> {
+    pub warehouse_id: &'a str,
+    pub location_id: Option<&'a str>,
+    pub put_away: bool,
+}
 
 /// Lot repository with FIFO queries
+///
+/// Lot ages follow the business date; sales order holds expire on the
+/// system clock.
 pub struct LotRepository {
     db: Arc<Database>,
+    clock: SharedClock,
+    system_clock: SharedClock,
 }
 
 impl LotRepository {
-    pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Database>, clock: SharedClock, system_clock: SharedClock) -> Self {
+        Self { db, clock, system_clock }
     }
 
     // =========================================================================
     // LOT MP
     // =========================================================================
 
-    /// Get lots ordered by FIFO (oldest reception first, earliest expiry first)
-    /// This is the CRITICAL method for FIFO consumption
-    pub fn get_available_fifo(&self, product_id: &str) -> Result<Vec<LotMpDto>> {
+    /// Get the lots a consumption may draw from, oldest reception first
+    ///
+    /// Only lots of `warehouse_id` that were put away in a pickable location
+    /// (not reception, not quarantine) are returned. The product lot
+    /// selection strategy decides which of them are consumed.
+    pub fn get_available_lots(&self, product_id: &str, warehouse_id: &str) -> Result<Vec<LotMpDto>> {
+        let today = self.clock.today();
         self.db.with_connection(|conn| {
             let mut stmt = conn.prepare(
                 "SELECT
@@ -35,17 +60,23 @@ impl LotRepository {
                     l.quantity_initial, l.quantity_remaining, p.unit,
                     l.unit_cost, l.total_cost, l.status,
                     l.supplier_id, s.name as supplier_name,
-                    l.reception_date, l.expiry_date, l.qr_code
+                    l.reception_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL
                  FROM lots_mp l
                  JOIN products_mp p ON p.id = l.product_mp_id
                  LEFT JOIN suppliers s ON s.id = l.supplier_id
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
                  WHERE l.product_mp_id = ?
+                   AND l.warehouse_id = ?
+                   AND l.deleted_at IS NULL
                    AND l.status = 'AVAILABLE'
                    AND l.quantity_remaining > 0
+                   AND l.put_away_at IS NOT NULL
+                   AND COALESCE(wl.location_type, '') NOT IN ('RECEPTION', 'QUARANTINE')
                  ORDER BY l.reception_date ASC, l.expiry_date ASC NULLS LAST, l.id ASC"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
-            let lots = stmt.query_map([product_id], |row| Self::row_to_mp_dto(row))
+            let lots = stmt.query_map([product_id, warehouse_id], |row| Self::row_to_mp_dto(row, today))
                 .map_err(|e| Error::Database(e.to_string()))?;
 
             let mut result = Vec::new();
@@ -58,6 +89,7 @@ impl LotRepository {
 
     /// List lots with filters
     pub fn list_mp(&self, filter: LotFilter) -> Result<Vec<LotMpDto>> {
+        let today = self.clock.today();
         self.db.with_connection(|conn| {
             let mut sql = String::from(
                 "SELECT
@@ -66,11 +98,12 @@ impl LotRepository {
                     l.quantity_initial, l.quantity_remaining, p.unit,
                     l.unit_cost, l.total_cost, l.status,
                     l.supplier_id, s.name as supplier_name,
-                    l.reception_date, l.expiry_date, l.qr_code
+                    l.reception_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL
                  FROM lots_mp l
                  JOIN products_mp p ON p.id = l.product_mp_id
                  LEFT JOIN suppliers s ON s.id = l.supplier_id
-                 WHERE 1=1"
+                 WHERE l.deleted_at IS NULL"
             );
 
             let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -85,8 +118,14 @@ impl LotRepository {
                 params_vec.push(Box::new(status.clone()));
             }
 
+            if let Some(ref warehouse_id) = filter.warehouse_id {
+                sql.push_str(" AND l.warehouse_id = ?");
+                params_vec.push(Box::new(warehouse_id.clone()));
+            }
+
             if let Some(days) = filter.expiring_within_days {
-                sql.push_str(" AND l.expiry_date IS NOT NULL AND l.expiry_date <= date('now', '+' || ? || ' days')");
+                sql.push_str(" AND l.expiry_date IS NOT NULL AND l.expiry_date <= date(?, '+' || ? || ' days')");
+                params_vec.push(Box::new(today.to_string()));
                 params_vec.push(Box::new(days));
             }
 
@@ -96,14 +135,14 @@ impl LotRepository {
 
             let mut result: Vec<LotMpDto> = Vec::new();
             if params_vec.is_empty() {
-                let lots = stmt.query_map([], |row| Self::row_to_mp_dto(row))
+                let lots = stmt.query_map([], |row| Self::row_to_mp_dto(row, today))
                     .map_err(|e| Error::Database(e.to_string()))?;
                 for lot in lots {
                     result.push(lot.map_err(|e| Error::Database(e.to_string()))?);
                 }
             } else {
                 let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-                let lots = stmt.query_map(params_refs.as_slice(), |row| Self::row_to_mp_dto(row))
+                let lots = stmt.query_map(params_refs.as_slice(), |row| Self::row_to_mp_dto(row, today))
                     .map_err(|e| Error::Database(e.to_string()))?;
                 for lot in lots {
                     result.push(lot.map_err(|e| Error::Database(e.to_string()))?);
@@ -115,6 +154,7 @@ impl LotRepository {
 
     /// Get lot by ID
     pub fn get_mp(&self, id: &str) -> Result<Option<LotMpDto>> {
+        let today = self.clock.today();
         self.db.with_connection(|conn| {
             let mut stmt = conn.prepare(
                 "SELECT
@@ -123,14 +163,15 @@ impl LotRepository {
                     l.quantity_initial, l.quantity_remaining, p.unit,
                     l.unit_cost, l.total_cost, l.status,
                     l.supplier_id, s.name as supplier_name,
-                    l.reception_date, l.expiry_date, l.qr_code
+                    l.reception_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL
                  FROM lots_mp l
                  JOIN products_mp p ON p.id = l.product_mp_id
                  LEFT JOIN suppliers s ON s.id = l.supplier_id
-                 WHERE l.id = ?"
+                 WHERE l.id = ? AND l.deleted_at IS NULL"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
-            match stmt.query_row([id], |row| Self::row_to_mp_dto(row)) {
+            match stmt.query_row([id], |row| Self::row_to_mp_dto(row, today)) {
                 Ok(dto) => Ok(Some(dto)),
                 Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
                 Err(e) => Err(Error::Database(e.to_string())),
@@ -139,23 +180,33 @@ impl LotRepository {
     }
 
     /// Create new lot
+    ///
+    /// `unit_cost` is in DZD centimes; imported lots also keep the supplier
+    /// price in its currency and the rate used to convert it.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_mp(
         &self,
         id: &str,
         lot_number: &str,
         product_id: &str,
         supplier_id: Option<&str>,
-        quantity: f64,
+        quantity: Qty,
         unit_cost: i64,
         reception_date: &str,
         expiry_date: Option<&str>,
+        purchase: Option<(CurrencyAmount, &ExchangeRate)>,
+        placement: LotPlacement,
     ) -> Result<()> {
         self.db.with_connection(|conn| {
-            let total_cost = (quantity * unit_cost as f64) as i64;
+            let total_cost = quantity.value_at(Money::from_centimes(unit_cost)).centimes();
+            let currency = purchase.map(|(price, _)| price.currency).unwrap_or_default();
 
             conn.execute(
-                "INSERT INTO lots_mp (id, lot_number, product_mp_id, supplier_id, quantity_initial, quantity_remaining, unit_cost, total_cost, status, reception_date, expiry_date, created_at)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'AVAILABLE', ?, ?, datetime('now'))",
+                "INSERT INTO lots_mp (id, lot_number, product_mp_id, supplier_id, quantity_initial, quantity_remaining, unit_cost, total_cost, status, reception_date, expiry_date,
+                                      currency, purchase_unit_cost, exchange_rate, exchange_rate_date,
+                                      warehouse_id, location_id, put_away_at, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'AVAILABLE', ?, ?, ?, ?, ?, ?, ?, ?,
+                         CASE WHEN ? THEN datetime('now') END, datetime('now'))",
                 params![
                     id,
                     lot_number,
@@ -166,26 +217,90 @@ impl LotRepository {
                     unit_cost,
                     total_cost,
                     reception_date,
-                    expiry_date
+                    expiry_date,
+                    currency.code(),
+                    purchase.map(|(price, _)| price.amount),
+                    purchase.map(|(_, rate)| rate.rate),
+                    purchase.map(|(_, rate)| rate.rate_date.to_string()),
+                    placement.warehouse_id,
+                    placement.location_id,
+                    placement.put_away,
                 ]
             ).map_err(|e| Error::Database(e.to_string()))?;
             Ok(())
         })
     }
 
-    /// Update lot quantity (for FIFO consumption)
-    pub fn update_quantity_mp(&self, id: &str, new_quantity: f64) -> Result<()> {
+    /// Lots received from a supplier, with their original currency amounts
+    pub fn list_mp_by_supplier(
+        &self,
+        supplier_id: &str,
+        from_date: Option<&str>,
+        to_date: Option<&str>,
+    ) -> Result<Vec<SupplierStatementLineDto>> {
         self.db.with_connection(|conn| {
-            let status = if new_quantity <= 0.0 { "CONSUMED" } else { "AVAILABLE" };
-
-            conn.execute(
-                "UPDATE lots_mp SET quantity_remaining = ?, status = ?, updated_at = datetime('now') WHERE id = ?",
-                params![new_quantity, status, id]
+            let mut stmt = conn.prepare(
+                "SELECT l.id, l.lot_number, l.reception_date, l.product_mp_id, p.name,
+                        l.quantity_initial, l.currency, COALESCE(l.purchase_unit_cost, l.unit_cost),
+                        l.exchange_rate, l.total_cost
+                 FROM lots_mp l
+                 LEFT JOIN products_mp p ON p.id = l.product_mp_id
+                 WHERE l.supplier_id = ?1 AND l.deleted_at IS NULL
+                   AND (?2 IS NULL OR l.reception_date >= ?2)
+                   AND (?3 IS NULL OR l.reception_date <= ?3)
+                 ORDER BY l.reception_date, l.lot_number"
             ).map_err(|e| Error::Database(e.to_string()))?;
-            Ok(())
+
+            let lines = stmt.query_map(params![supplier_id, from_date, to_date], |row| {
+                let quantity: Qty = row.get(5)?;
+                let currency: String = row.get(6)?;
+                let unit_cost_currency: i64 = row.get(7)?;
+                let exchange_rate: Option<i64> = row.get(8)?;
+
+                Ok(SupplierStatementLineDto {
+                    lot_id: row.get(0)?,
+                    lot_number: row.get(1)?,
+                    reception_date: row.get(2)?,
+                    product_mp_id: row.get(3)?,
+                    product_mp_name: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                    quantity: quantity.as_f64(),
+                    currency: Currency::from_code(&currency).unwrap_or_default(),
+                    unit_cost_currency,
+                    total_currency: quantity.value_at(Money::from_centimes(unit_cost_currency)).centimes(),
+                    exchange_rate: exchange_rate.map(|r| r as f64 / manchengo_core::RATE_SCALE as f64),
+                    total_dzd: row.get(9)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for line in lines {
+                result.push(line.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
         })
     }
 
+    /// Update lot quantity (for FIFO consumption)
+    pub fn update_quantity_mp(&self, id: &str, new_quantity: Qty) -> Result<()> {
+        self.db.with_connection(|conn| Self::update_quantity_on(conn, "MP", id, new_quantity))
+    }
+
+    /// Update lot quantity on the caller's connection or transaction; an
+    /// emptied lot becomes CONSUMED
+    pub fn update_quantity_on(conn: &Connection, product_type: &str, id: &str, new_quantity: Qty) -> Result<()> {
+        let table = if product_type == "MP" { "lots_mp" } else { "lots_pf" };
+        let status = if !new_quantity.is_positive() { "CONSUMED" } else { "AVAILABLE" };
+
+        conn.execute(
+            &format!(
+                "UPDATE {} SET quantity_remaining = ?, status = ?, updated_at = datetime('now') WHERE id = ?",
+                table
+            ),
+            params![new_quantity, status, id]
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
     /// Block/unblock lot
     pub fn set_status_mp(&self, id: &str, status: &str) -> Result<()> {
         self.db.with_connection(|conn| {
@@ -197,11 +312,127 @@ impl LotRepository {
         })
     }
 
+    /// Update PF lot quantity
+    pub fn update_quantity_pf(&self, id: &str, new_quantity: Qty) -> Result<()> {
+        self.db.with_connection(|conn| Self::update_quantity_on(conn, "PF", id, new_quantity))
+    }
+
+    /// Find a lot by its printed QR code, returns (product type, lot id)
+    pub fn find_by_qr(&self, qr_code: &str) -> Result<Option<(String, String)>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT 'MP', id FROM lots_mp WHERE qr_code = ?1 AND deleted_at IS NULL
+                 UNION ALL
+                 SELECT 'PF', id FROM lots_pf WHERE qr_code = ?1 AND deleted_at IS NULL
+                 LIMIT 1",
+                [qr_code],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ) {
+                Ok(found) => Ok(Some(found)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Move a whole lot to another warehouse, pending put-away at `location_id`
+    pub fn move_to_warehouse(
+        conn: &Connection,
+        product_type: &str,
+        id: &str,
+        warehouse_id: &str,
+        location_id: Option<&str>,
+    ) -> Result<()> {
+        let table = if product_type == "MP" { "lots_mp" } else { "lots_pf" };
+        conn.execute(
+            &format!(
+                "UPDATE {} SET warehouse_id = ?, location_id = ?, put_away_at = NULL, updated_at = datetime('now')
+                 WHERE id = ?",
+                table
+            ),
+            params![warehouse_id, location_id, id]
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Split `quantity` off a lot into a new lot of another warehouse
+    ///
+    /// The new lot keeps the dates and unit cost of its parent (FIFO order and
+    /// stock value are unchanged) and is numbered `<parent>-T<n>`. The parent
+    /// quantity is not touched. Returns the new lot number.
+    #[allow(clippy::too_many_arguments)]
+    pub fn split(
+        conn: &Connection,
+        product_type: &str,
+        parent_id: &str,
+        id: &str,
+        quantity: Qty,
+        unit_cost: i64,
+        warehouse_id: &str,
+        location_id: Option<&str>,
+    ) -> Result<String> {
+        let total_cost = quantity.value_at(Money::from_centimes(unit_cost)).centimes();
+        {
+            let table = if product_type == "MP" { "lots_mp" } else { "lots_pf" };
+            let (parent_number, children): (String, i64) = conn.query_row(
+                &format!(
+                    "SELECT lot_number, (SELECT COUNT(*) FROM {0} c WHERE c.parent_lot_id = p.id) FROM {0} p WHERE p.id = ?",
+                    table
+                ),
+                [parent_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            let lot_number = format!("{}-T{}", parent_number, children + 1);
+
+            let sql = if product_type == "MP" {
+                "INSERT INTO lots_mp (id, lot_number, product_mp_id, supplier_id, quantity_initial, quantity_remaining, unit_cost, total_cost, status, reception_date, expiry_date,
+                                      currency, purchase_unit_cost, exchange_rate, exchange_rate_date,
+                                      warehouse_id, location_id, parent_lot_id, created_at)
+                 SELECT ?, ?, product_mp_id, supplier_id, ?, ?, unit_cost, ?, 'AVAILABLE', reception_date, expiry_date,
+                        currency, purchase_unit_cost, exchange_rate, exchange_rate_date,
+                        ?, ?, id, datetime('now')
+                 FROM lots_mp WHERE id = ?"
+            } else {
+                "INSERT INTO lots_pf (id, lot_number, product_pf_id, production_order_id, quantity_initial, quantity_remaining, unit_cost, total_cost, status, production_date, expiry_date,
+                                      warehouse_id, location_id, parent_lot_id, created_at)
+                 SELECT ?, ?, product_pf_id, production_order_id, ?, ?, unit_cost, ?, 'AVAILABLE', production_date, expiry_date,
+                        ?, ?, id, datetime('now')
+                 FROM lots_pf WHERE id = ?"
+            };
+            conn.execute(
+                sql,
+                params![id, lot_number, quantity, quantity, total_cost, warehouse_id, location_id, parent_id]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(lot_number)
+        }
+    }
+
+    /// Move a lot to a location; `put_away` marks the end of the reception step
+    pub fn assign_location(&self, product_type: &str, id: &str, location_id: &str, put_away: bool) -> Result<()> {
+        self.db.with_connection(|conn| Self::assign_location_on(conn, product_type, id, location_id, put_away))
+    }
+
+    /// Move a lot to a location on the caller's connection or transaction
+    pub fn assign_location_on(conn: &Connection, product_type: &str, id: &str, location_id: &str, put_away: bool) -> Result<()> {
+        let table = if product_type == "MP" { "lots_mp" } else { "lots_pf" };
+        conn.execute(
+            &format!(
+                "UPDATE {} SET location_id = ?,
+                    put_away_at = CASE WHEN ? THEN COALESCE(put_away_at, datetime('now')) ELSE put_away_at END,
+                    updated_at = datetime('now')
+                 WHERE id = ?",
+                table
+            ),
+            params![location_id, put_away, id]
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
     /// Count lots MP
     pub fn count_mp(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM lots_mp WHERE status = 'AVAILABLE'",
+                "SELECT COUNT(*) FROM lots_mp WHERE status = 'AVAILABLE' AND deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
@@ -209,35 +440,62 @@ impl LotRepository {
         })
     }
 
+    /// Lots still holding stock, per product of a type
+    pub fn open_lots_count(&self, product_type: &str) -> Result<HashMap<String, u32>> {
+        let (table, column) = if product_type == "MP" {
+            ("lots_mp", "product_mp_id")
+        } else {
+            ("lots_pf", "product_pf_id")
+        };
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {column}, COUNT(*) FROM {table}
+                 WHERE status <> 'CONSUMED' AND quantity_remaining > 0 AND deleted_at IS NULL
+                 GROUP BY {column}"
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = HashMap::new();
+            for row in rows {
+                let (product_id, count): (String, u32) = row.map_err(|e| Error::Database(e.to_string()))?;
+                result.insert(product_id, count);
+            }
+            Ok(result)
+        })
+    }
+
     /// Get expiring lots within N days
     pub fn get_expiring(&self, days: i32) -> Result<Vec<ExpiringLotDto>> {
+        let today = self.clock.today();
         self.db.with_connection(|conn| {
             let mut stmt = conn.prepare(
                 "SELECT
                     l.id, l.lot_number, l.product_mp_id,
                     p.name as product_name, 'MP' as product_type,
                     l.quantity_remaining, p.unit, l.expiry_date,
-                    julianday(l.expiry_date) - julianday('now') as days_until
+                    julianday(l.expiry_date) - julianday(?1) as days_until
                  FROM lots_mp l
                  JOIN products_mp p ON p.id = l.product_mp_id
-                 WHERE l.status = 'AVAILABLE'
+                 WHERE l.status = 'AVAILABLE' AND l.deleted_at IS NULL
                    AND l.expiry_date IS NOT NULL
-                   AND l.expiry_date <= date('now', '+' || ? || ' days')
+                   AND l.expiry_date <= date(?1, '+' || ?2 || ' days')
                  UNION ALL
                  SELECT
                     l.id, l.lot_number, l.product_pf_id,
                     p.name as product_name, 'PF' as product_type,
                     l.quantity_remaining, p.unit, l.expiry_date,
-                    julianday(l.expiry_date) - julianday('now') as days_until
+                    julianday(l.expiry_date) - julianday(?1) as days_until
                  FROM lots_pf l
                  JOIN products_pf p ON p.id = l.product_pf_id
-                 WHERE l.status = 'AVAILABLE'
+                 WHERE l.status = 'AVAILABLE' AND l.deleted_at IS NULL
                    AND l.expiry_date IS NOT NULL
-                   AND l.expiry_date <= date('now', '+' || ? || ' days')
+                   AND l.expiry_date <= date(?1, '+' || ?2 || ' days')
                  ORDER BY days_until ASC"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
-            let lots = stmt.query_map([days, days], |row| {
+            let lots = stmt.query_map(params![today.to_string(), days], |row| {
                 let days_until: f64 = row.get(8)?;
                 let days_int = days_until.ceil() as i32;
 
@@ -263,14 +521,13 @@ impl LotRepository {
         })
     }
 
-    fn row_to_mp_dto(row: &Row) -> rusqlite::Result<LotMpDto> {
+    fn row_to_mp_dto(row: &Row, today: NaiveDate) -> rusqlite::Result<LotMpDto> {
         let expiry_date: Option<String> = row.get(14)?;
         let reception_date: String = row.get(13)?;
 
         // Calculate days until expiry
         let (is_expired, days_until) = if let Some(ref exp) = expiry_date {
             if let Ok(exp_date) = NaiveDate::parse_from_str(exp, "%Y-%m-%d") {
-                let today = Utc::now().date_naive();
                 let days = (exp_date - today).num_days() as i32;
                 (days < 0, Some(days))
             } else {
@@ -299,6 +556,9 @@ impl LotRepository {
             is_expired,
             days_until_expiry: days_until,
             qr_code: row.get(15)?,
+            warehouse_id: row.get(16)?,
+            location_id: row.get(17)?,
+            pending_put_away: row.get(18)?,
         })
     }
 
@@ -308,31 +568,45 @@ impl LotRepository {
 
     /// List PF lots
     pub fn list_pf(&self, filter: LotFilter) -> Result<Vec<LotPfDto>> {
+        let today = self.clock.today();
+        let held = ReservationRepository::held_sql(&MovementRepository::timestamp(self.system_clock.now()));
         self.db.with_connection(|conn| {
-            let mut sql = String::from(
+            let mut sql = format!(
                 "SELECT
                     l.id, l.lot_number, l.product_pf_id,
                     p.code as product_code, p.name as product_name,
                     l.quantity_initial, l.quantity_remaining, p.unit,
                     l.status, l.production_order_id,
-                    l.production_date, l.expiry_date, l.qr_code
+                    l.production_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL, l.unit_cost, {}
                  FROM lots_pf l
                  JOIN products_pf p ON p.id = l.product_pf_id
-                 WHERE 1=1"
+                 WHERE l.deleted_at IS NULL",
+                held
             );
 
+            let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
             if let Some(ref product_id) = filter.product_id {
-                sql.push_str(&format!(" AND l.product_pf_id = '{}'", product_id));
+                sql.push_str(" AND l.product_pf_id = ?");
+                params_vec.push(Box::new(product_id.clone()));
             }
 
             if let Some(ref status) = filter.status {
-                sql.push_str(&format!(" AND l.status = '{}'", status));
+                sql.push_str(" AND l.status = ?");
+                params_vec.push(Box::new(status.clone()));
+            }
+
+            if let Some(ref warehouse_id) = filter.warehouse_id {
+                sql.push_str(" AND l.warehouse_id = ?");
+                params_vec.push(Box::new(warehouse_id.clone()));
             }
 
             sql.push_str(" ORDER BY l.production_date DESC");
 
             let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
-            let lots = stmt.query_map([], |row| Self::row_to_pf_dto(row))
+            let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+            let lots = stmt.query_map(params_refs.as_slice(), |row| Self::row_to_pf_dto(row, today))
                 .map_err(|e| Error::Database(e.to_string()))?;
 
             let mut result = Vec::new();
@@ -345,20 +619,51 @@ impl LotRepository {
 
     /// Get PF lot by ID
     pub fn get_pf(&self, id: &str) -> Result<Option<LotPfDto>> {
+        let today = self.clock.today();
+        let held = ReservationRepository::held_sql(&MovementRepository::timestamp(self.system_clock.now()));
         self.db.with_connection(|conn| {
-            let mut stmt = conn.prepare(
+            let mut stmt = conn.prepare(&format!(
                 "SELECT
                     l.id, l.lot_number, l.product_pf_id,
                     p.code as product_code, p.name as product_name,
                     l.quantity_initial, l.quantity_remaining, p.unit,
                     l.status, l.production_order_id,
-                    l.production_date, l.expiry_date, l.qr_code
+                    l.production_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL, l.unit_cost, {}
                  FROM lots_pf l
                  JOIN products_pf p ON p.id = l.product_pf_id
-                 WHERE l.id = ?"
-            ).map_err(|e| Error::Database(e.to_string()))?;
+                 WHERE l.id = ? AND l.deleted_at IS NULL",
+                held
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            match stmt.query_row([id], |row| Self::row_to_pf_dto(row, today)) {
+                Ok(dto) => Ok(Some(dto)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Get PF lot by product and lot number (GS1-128 AI(10) scans)
+    pub fn find_pf_by_number(&self, product_id: &str, lot_number: &str) -> Result<Option<LotPfDto>> {
+        let today = self.clock.today();
+        let held = ReservationRepository::held_sql(&MovementRepository::timestamp(self.system_clock.now()));
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT
+                    l.id, l.lot_number, l.product_pf_id,
+                    p.code as product_code, p.name as product_name,
+                    l.quantity_initial, l.quantity_remaining, p.unit,
+                    l.status, l.production_order_id,
+                    l.production_date, l.expiry_date, l.qr_code,
+                    l.warehouse_id, l.location_id, l.put_away_at IS NULL, l.unit_cost, {}
+                 FROM lots_pf l
+                 JOIN products_pf p ON p.id = l.product_pf_id
+                 WHERE l.product_pf_id = ? AND l.lot_number = ? AND l.deleted_at IS NULL",
+                held
+            )).map_err(|e| Error::Database(e.to_string()))?;
 
-            match stmt.query_row([id], |row| Self::row_to_pf_dto(row)) {
+            match stmt.query_row([product_id, lot_number], |row| Self::row_to_pf_dto(row, today)) {
                 Ok(dto) => Ok(Some(dto)),
                 Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
                 Err(e) => Err(Error::Database(e.to_string())),
@@ -373,14 +678,18 @@ impl LotRepository {
         lot_number: &str,
         product_id: &str,
         production_order_id: &str,
-        quantity: f64,
+        quantity: Qty,
         production_date: &str,
         expiry_date: Option<&str>,
     ) -> Result<()> {
         self.db.with_connection(|conn| {
             conn.execute(
-                "INSERT INTO lots_pf (id, lot_number, product_pf_id, production_order_id, quantity_initial, quantity_remaining, status, production_date, expiry_date, created_at)
-                 VALUES (?, ?, ?, ?, ?, ?, 'AVAILABLE', ?, ?, datetime('now'))",
+                "INSERT INTO lots_pf (id, lot_number, product_pf_id, production_order_id, quantity_initial, quantity_remaining, status, production_date, expiry_date,
+                                      warehouse_id, location_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, 'AVAILABLE', ?, ?, (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1),
+                         (SELECT wl.id FROM warehouse_locations wl JOIN warehouses w ON w.id = wl.warehouse_id
+                          WHERE w.is_default = 1 AND wl.location_type = 'RECEPTION' LIMIT 1),
+                         datetime('now'))",
                 params![
                     id,
                     lot_number,
@@ -396,11 +705,119 @@ impl LotRepository {
         })
     }
 
+    /// PF lots holding stock among `lots_sql` (one `?` bound to `param`), as domain lots
+    ///
+    /// Used with the reservation book, which takes the holds off their
+    /// quantity. The audit is attributed to `user_id`.
+    pub fn pf_lots(
+        conn: &Connection,
+        lots_sql: &str,
+        param: &str,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<Vec<LotPf>> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let mut stmt = conn.prepare(&format!(
+            "SELECT l.id, l.lot_number, l.product_pf_id, l.production_order_id,
+                    l.quantity_initial, l.quantity_remaining, p.unit,
+                    l.production_date, l.expiry_date, l.unit_cost, l.total_cost,
+                    l.warehouse_id, l.location_id, l.status, l.qr_code
+             FROM lots_pf l
+             JOIN products_pf p ON p.id = l.product_pf_id
+             WHERE l.id IN ({}) AND l.quantity_remaining > 0 AND l.deleted_at IS NULL
+             ORDER BY l.production_date ASC, l.expiry_date ASC NULLS LAST, l.id ASC",
+            lots_sql
+        )).map_err(db_err)?;
+
+        let mut rows = stmt.query([param]).map_err(db_err)?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().map_err(db_err)? {
+            let unit: String = row.get(6).map_err(db_err)?;
+            let production_date: String = row.get(7).map_err(db_err)?;
+            let status: String = row.get(13).map_err(db_err)?;
+            result.push(LotPf {
+                id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+                lot_number: row.get(1).map_err(db_err)?,
+                product_id: Self::parse_id(&row.get::<_, String>(2).map_err(db_err)?)?,
+                production_order_id: row
+                    .get::<_, Option<String>>(3)
+                    .map_err(db_err)?
+                    .as_deref()
+                    .map(Self::parse_id)
+                    .transpose()?,
+                quantity_initial: row.get(4).map_err(db_err)?,
+                quantity_remaining: row.get(5).map_err(db_err)?,
+                unit: UnitOfMeasure::from_code(&unit)
+                    .ok_or_else(|| Error::Internal(format!("Unite inconnue en base: {}", unit)))?,
+                production_date: Self::lot_date(&production_date)
+                    .ok_or_else(|| Error::Internal(format!("Date de production invalide en base: {}", production_date)))?,
+                expiry_date: row.get::<_, Option<String>>(8).map_err(db_err)?.as_deref().and_then(Self::lot_date),
+                unit_cost: Money::from_centimes(row.get(9).map_err(db_err)?),
+                total_cost: Money::from_centimes(row.get(10).map_err(db_err)?),
+                warehouse_id: row
+                    .get::<_, Option<String>>(11)
+                    .map_err(db_err)?
+                    .as_deref()
+                    .map(Self::parse_id)
+                    .transpose()?,
+                location_id: row
+                    .get::<_, Option<String>>(12)
+                    .map_err(db_err)?
+                    .as_deref()
+                    .map(Self::parse_id)
+                    .transpose()?,
+                status: match status.as_str() {
+                    "RESERVED" => DomainLotStatus::Reserved,
+                    "CONSUMED" => DomainLotStatus::Consumed,
+                    "EXPIRED" => DomainLotStatus::Expired,
+                    "BLOCKED" => DomainLotStatus::Blocked,
+                    _ => DomainLotStatus::Available,
+                },
+                blocked_reason: None,
+                qr_code: row.get::<_, Option<String>>(14).map_err(db_err)?.unwrap_or_default(),
+                notes: None,
+                audit: AuditInfo::new(user_id, clock),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Save the quantity and status of a domain PF lot
+    pub fn save_pf_state(conn: &Connection, lot: &LotPf) -> Result<()> {
+        conn.execute(
+            "UPDATE lots_pf SET quantity_remaining = ?, status = ?, updated_at = datetime('now') WHERE id = ?",
+            params![lot.quantity_remaining, lot.status.as_str(), lot.id.to_string()],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Quantity of each PF product held for sales orders right now
+    pub fn reserved_pf(&self) -> Result<HashMap<String, Qty>> {
+        let now = MovementRepository::timestamp(self.system_clock.now());
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT product_pf_id, SUM(quantity) FROM lot_reservations
+                 WHERE status = 'ACTIVE' AND expires_at > ?
+                 GROUP BY product_pf_id"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([now], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Qty>(1)?)))
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = HashMap::new();
+            for row in rows {
+                let (product_id, held) = row.map_err(|e| Error::Database(e.to_string()))?;
+                result.insert(product_id, held);
+            }
+            Ok(result)
+        })
+    }
+
     /// Count PF lots
     pub fn count_pf(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM lots_pf WHERE status = 'AVAILABLE'",
+                "SELECT COUNT(*) FROM lots_pf WHERE status = 'AVAILABLE' AND deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
@@ -408,13 +825,72 @@ impl LotRepository {
         })
     }
 
-    fn row_to_pf_dto(row: &Row) -> rusqlite::Result<LotPfDto> {
+    // =========================================================================
+    // LEDGER INTEGRITY
+    // =========================================================================
+
+    /// Lot quantities alongside the balance recomputed from movements
+    pub fn ledger_balances(&self) -> Result<Vec<LotLedgerDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT
+                    l.id, l.lot_number, 'MP' as product_type, l.product_mp_id, p.name,
+                    l.status, l.quantity_remaining,
+                    COALESCE(SUM(CASE WHEN m.movement_type = 'IN' THEN m.quantity ELSE -m.quantity END), 0),
+                    COUNT(m.id)
+                 FROM lots_mp l
+                 LEFT JOIN products_mp p ON p.id = l.product_mp_id
+                 LEFT JOIN stock_movements m ON m.lot_mp_id = l.id AND m.is_deleted = 0
+                 GROUP BY l.id
+                 UNION ALL
+                 SELECT
+                    l.id, l.lot_number, 'PF' as product_type, l.product_pf_id, p.name,
+                    l.status, l.quantity_remaining,
+                    COALESCE(SUM(CASE WHEN m.movement_type = 'IN' THEN m.quantity ELSE -m.quantity END), 0),
+                    COUNT(m.id)
+                 FROM lots_pf l
+                 LEFT JOIN products_pf p ON p.id = l.product_pf_id
+                 LEFT JOIN stock_movements m ON m.lot_pf_id = l.id AND m.is_deleted = 0
+                 GROUP BY l.id"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let lots = stmt.query_map([], |row| {
+                Ok(LotLedgerDto {
+                    lot_id: row.get(0)?,
+                    lot_number: row.get(1)?,
+                    product_type: row.get(2)?,
+                    product_id: row.get(3)?,
+                    product_name: row.get(4)?,
+                    status: row.get(5)?,
+                    quantity_remaining: row.get(6)?,
+                    ledger_balance: row.get(7)?,
+                    movements_count: row.get(8)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for lot in lots {
+                result.push(lot.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+
+    /// Date part of a stored lot date (plain date or RFC 3339 timestamp)
+    fn lot_date(value: &str) -> Option<NaiveDate> {
+        value.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    }
+
+    fn row_to_pf_dto(row: &Row, today: NaiveDate) -> rusqlite::Result<LotPfDto> {
         let expiry_date: Option<String> = row.get(11)?;
         let production_date: String = row.get(10)?;
 
         let (is_expired, days_until) = if let Some(ref exp) = expiry_date {
             if let Ok(exp_date) = NaiveDate::parse_from_str(exp, "%Y-%m-%d") {
-                let today = Utc::now().date_naive();
                 let days = (exp_date - today).num_days() as i32;
                 (days < 0, Some(days))
             } else {
@@ -435,11 +911,16 @@ impl LotRepository {
             unit: row.get(7)?,
             status: LotStatus::from(row.get::<_, String>(8)?.as_str()),
             production_order_id: row.get(9)?,
+            unit_cost: row.get(16)?,
             production_date,
             expiry_date,
             is_expired,
             days_until_expiry: days_until,
             qr_code: row.get(12)?,
+            warehouse_id: row.get(13)?,
+            location_id: row.get(14)?,
+            pending_put_away: row.get(15)?,
+            quantity_reserved: row.get(17)?,
         })
     }
 }
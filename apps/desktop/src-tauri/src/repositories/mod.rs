@@ -12,6 +12,17 @@ pub mod production_repo;
 pub mod purchase_order_repo;
 pub mod client_repo;
 pub mod invoice_repo;
+pub mod fiscal_rule_repo;
+pub mod audit_repo;
+pub mod exchange_rate_repo;
+pub mod warehouse_repo;
+pub mod transfer_repo;
+pub mod inventory_repo;
+pub mod valuation_repo;
+pub mod stock_history_repo;
+pub mod reservation_repo;
+pub mod sales_order_repo;
+pub mod delivery_repo;
 
 pub use product_repo::ProductRepository;
 pub use lot_repo::LotRepository;
@@ -22,6 +33,17 @@ pub use production_repo::ProductionRepository;
 pub use purchase_order_repo::PurchaseOrderRepository;
 pub use client_repo::ClientRepository;
 pub use invoice_repo::InvoiceRepository;
+pub use fiscal_rule_repo::FiscalRuleRepository;
+pub use audit_repo::AuditRepository;
+pub use exchange_rate_repo::ExchangeRateRepository;
+pub use warehouse_repo::WarehouseRepository;
+pub use transfer_repo::TransferRepository;
+pub use inventory_repo::InventoryRepository;
+pub use valuation_repo::ValuationRepository;
+pub use stock_history_repo::StockHistoryRepository;
+pub use reservation_repo::ReservationRepository;
+pub use sales_order_repo::SalesOrderRepository;
+pub use delivery_repo::DeliveryRepository;
 
 use manchengo_database::Database;
 use std::sync::Arc;
@@ -2,24 +2,39 @@
 //!
 //! Data access for StockMovement - the immutable audit trail.
 
-use manchengo_core::{Error, Result};
+use chrono::{DateTime, Utc};
+use manchengo_core::{Error, Qty, Result, SharedClock};
+use manchengo_database::archive::FiscalArchive;
 use manchengo_database::Database;
-use rusqlite::{params, Row};
+use rusqlite::{params, Connection, Row};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::dto::{MovementDto, MovementFilter, MovementOrigin, MovementType};
+use crate::dto::{MovementDto, MovementFilter, MovementOrigin, MovementType, OrphanMovementDto};
 
 /// Stock movement repository (append-only for audit)
 pub struct MovementRepository {
     db: Arc<Database>,
+    clock: SharedClock,
 }
 
 impl MovementRepository {
-    pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Database>, clock: SharedClock) -> Self {
+        Self { db, clock }
+    }
+
+    /// Business timestamp of a movement, in the `datetime('now')` format
+    ///
+    /// `created_at` carries the business date of backdated entries; the real
+    /// entry time goes to `recorded_at`.
+    pub fn timestamp(at: DateTime<Utc>) -> String {
+        at.format("%Y-%m-%d %H:%M:%S").to_string()
     }
 
     /// Create new stock movement (immutable - cannot be updated/deleted)
+    ///
+    /// The movement is recorded in the lot's warehouse and location at that
+    /// time, dated with the clock (business date).
     pub fn create(
         &self,
         id: &str,
@@ -27,7 +42,7 @@ impl MovementRepository {
         product_type: &str,  // "MP" or "PF"
         product_id: &str,
         lot_id: Option<&str>,
-        quantity: f64,
+        quantity: Qty,
         unit_cost: Option<i64>,
         origin: &str,
         reference_type: Option<&str>,
@@ -37,134 +52,176 @@ impl MovementRepository {
         note: Option<&str>,
     ) -> Result<()> {
         self.db.with_connection(|conn| {
-            // Check idempotency to prevent duplicates
-            let exists: bool = conn.query_row(
-                "SELECT EXISTS(SELECT 1 FROM stock_movements WHERE idempotency_key = ?)",
-                [idempotency_key],
-                |row| row.get(0)
-            ).map_err(|e| Error::Database(e.to_string()))?;
+            self.create_on(
+                conn, id, movement_type, product_type, product_id, lot_id, quantity, unit_cost,
+                origin, reference_type, reference_id, user_id, idempotency_key, note,
+            )
+        })
+    }
 
-            if exists {
-                tracing::warn!("Duplicate movement detected: {}", idempotency_key);
-                return Ok(());
-            }
+    /// Create a stock movement on the caller's connection or transaction
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_on(
+        &self,
+        conn: &Connection,
+        id: &str,
+        movement_type: &str, // "IN" or "OUT"
+        product_type: &str,  // "MP" or "PF"
+        product_id: &str,
+        lot_id: Option<&str>,
+        quantity: Qty,
+        unit_cost: Option<i64>,
+        origin: &str,
+        reference_type: Option<&str>,
+        reference_id: Option<&str>,
+        user_id: &str,
+        idempotency_key: &str,
+        note: Option<&str>,
+    ) -> Result<()> {
+        let created_at = Self::timestamp(self.clock.now());
+        // Check idempotency to prevent duplicates
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM stock_movements WHERE idempotency_key = ?)",
+            [idempotency_key],
+            |row| row.get(0)
+        ).map_err(|e| Error::Database(e.to_string()))?;
 
-            // Insert based on product type
-            if product_type == "MP" {
-                conn.execute(
-                    "INSERT INTO stock_movements (
-                        id, movement_type, product_type, product_mp_id, lot_mp_id,
-                        quantity, unit_cost, origin, reference_type, reference_id,
-                        user_id, idempotency_key, note, created_at, is_deleted
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
-                    params![
-                        id, movement_type, product_type, product_id, lot_id,
-                        quantity, unit_cost, origin, reference_type, reference_id,
-                        user_id, idempotency_key, note
-                    ]
-                ).map_err(|e| Error::Database(e.to_string()))?;
-            } else {
-                conn.execute(
-                    "INSERT INTO stock_movements (
-                        id, movement_type, product_type, product_pf_id, lot_pf_id,
-                        quantity, unit_cost, origin, reference_type, reference_id,
-                        user_id, idempotency_key, note, created_at, is_deleted
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
-                    params![
-                        id, movement_type, product_type, product_id, lot_id,
-                        quantity, unit_cost, origin, reference_type, reference_id,
-                        user_id, idempotency_key, note
-                    ]
-                ).map_err(|e| Error::Database(e.to_string()))?;
-            }
+        if exists {
+            tracing::warn!("Duplicate movement detected: {}", idempotency_key);
+            return Ok(());
+        }
 
-            Ok(())
-        })
+        // Insert based on product type
+        if product_type == "MP" {
+            conn.execute(
+                "INSERT INTO stock_movements (
+                    id, movement_type, product_type, product_mp_id, lot_mp_id,
+                    quantity, unit_cost, origin, reference_type, reference_id,
+                    user_id, idempotency_key, note, warehouse_id, location_id, created_at, recorded_at, is_deleted
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                    COALESCE((SELECT warehouse_id FROM lots_mp WHERE id = ?), (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1)),
+                    (SELECT location_id FROM lots_mp WHERE id = ?),
+                    ?, datetime('now'), 0)",
+                params![
+                    id, movement_type, product_type, product_id, lot_id,
+                    quantity, unit_cost, origin, reference_type, reference_id,
+                    user_id, idempotency_key, note, lot_id, lot_id, created_at
+                ]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+        } else {
+            conn.execute(
+                "INSERT INTO stock_movements (
+                    id, movement_type, product_type, product_pf_id, lot_pf_id,
+                    quantity, unit_cost, origin, reference_type, reference_id,
+                    user_id, idempotency_key, note, warehouse_id, location_id, created_at, recorded_at, is_deleted
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                    COALESCE((SELECT warehouse_id FROM lots_pf WHERE id = ?), (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1)),
+                    (SELECT location_id FROM lots_pf WHERE id = ?),
+                    ?, datetime('now'), 0)",
+                params![
+                    id, movement_type, product_type, product_id, lot_id,
+                    quantity, unit_cost, origin, reference_type, reference_id,
+                    user_id, idempotency_key, note, lot_id, lot_id, created_at
+                ]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+        }
+
+        Ok(())
     }
 
     /// List movements with filters
     pub fn list(&self, filter: MovementFilter) -> Result<Vec<MovementDto>> {
         self.db.with_connection(|conn| {
-            let mut sql = String::from(
-                "SELECT
-                    m.id, m.movement_type, m.product_type,
-                    COALESCE(m.product_mp_id, m.product_pf_id) as product_id,
-                    COALESCE(pmp.code, ppf.code) as product_code,
-                    COALESCE(pmp.name, ppf.name) as product_name,
-                    COALESCE(m.lot_mp_id, m.lot_pf_id) as lot_id,
-                    COALESCE(lmp.lot_number, lpf.lot_number) as lot_number,
-                    m.quantity,
-                    COALESCE(pmp.unit, ppf.unit) as unit,
-                    m.unit_cost, m.origin, m.reference_type, m.reference_id,
-                    m.user_id, u.name as user_name, m.created_at, m.note
-                 FROM stock_movements m
-                 LEFT JOIN products_mp pmp ON pmp.id = m.product_mp_id
-                 LEFT JOIN products_pf ppf ON ppf.id = m.product_pf_id
-                 LEFT JOIN lots_mp lmp ON lmp.id = m.lot_mp_id
-                 LEFT JOIN lots_pf lpf ON lpf.id = m.lot_pf_id
-                 LEFT JOIN users u ON u.id = m.user_id
-                 WHERE m.is_deleted = 0"
-            );
+            FiscalArchive::with_range(
+                conn,
+                filter.from_date.as_deref(),
+                filter.to_date.as_deref(),
+                |archives| {
+                    let movements_source = archives.source(conn, "stock_movements")?;
 
-            let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+                    let mut sql = format!(
+                        "SELECT
+                            m.id, m.movement_type, m.product_type,
+                            COALESCE(m.product_mp_id, m.product_pf_id) as product_id,
+                            COALESCE(pmp.code, ppf.code) as product_code,
+                            COALESCE(pmp.name, ppf.name) as product_name,
+                            COALESCE(m.lot_mp_id, m.lot_pf_id) as lot_id,
+                            COALESCE(lmp.lot_number, lpf.lot_number) as lot_number,
+                            m.quantity,
+                            COALESCE(pmp.unit, ppf.unit) as unit,
+                            m.unit_cost, m.origin, m.reference_type, m.reference_id,
+                            m.user_id, u.name as user_name, m.created_at, m.note, m.recorded_at
+                         FROM {} m
+                         LEFT JOIN products_mp pmp ON pmp.id = m.product_mp_id
+                         LEFT JOIN products_pf ppf ON ppf.id = m.product_pf_id
+                         LEFT JOIN lots_mp lmp ON lmp.id = m.lot_mp_id
+                         LEFT JOIN lots_pf lpf ON lpf.id = m.lot_pf_id
+                         LEFT JOIN users u ON u.id = m.user_id
+                         WHERE m.is_deleted = 0",
+                        movements_source
+                    );
 
-            if let Some(ref product_type) = filter.product_type {
-                sql.push_str(" AND m.product_type = ?");
-                params_vec.push(Box::new(product_type.clone()));
-            }
+                    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
-            if let Some(ref product_id) = filter.product_id {
-                sql.push_str(" AND (m.product_mp_id = ? OR m.product_pf_id = ?)");
-                params_vec.push(Box::new(product_id.clone()));
-                params_vec.push(Box::new(product_id.clone()));
-            }
+                    if let Some(ref product_type) = filter.product_type {
+                        sql.push_str(" AND m.product_type = ?");
+                        params_vec.push(Box::new(product_type.clone()));
+                    }
 
-            if let Some(ref movement_type) = filter.movement_type {
-                sql.push_str(" AND m.movement_type = ?");
-                params_vec.push(Box::new(movement_type.clone()));
-            }
+                    if let Some(ref product_id) = filter.product_id {
+                        sql.push_str(" AND (m.product_mp_id = ? OR m.product_pf_id = ?)");
+                        params_vec.push(Box::new(product_id.clone()));
+                        params_vec.push(Box::new(product_id.clone()));
+                    }
 
-            if let Some(ref origin) = filter.origin {
-                sql.push_str(" AND m.origin = ?");
-                params_vec.push(Box::new(origin.clone()));
-            }
+                    if let Some(ref movement_type) = filter.movement_type {
+                        sql.push_str(" AND m.movement_type = ?");
+                        params_vec.push(Box::new(movement_type.clone()));
+                    }
 
-            if let Some(ref from_date) = filter.from_date {
-                sql.push_str(" AND m.created_at >= ?");
-                params_vec.push(Box::new(from_date.clone()));
-            }
+                    if let Some(ref origin) = filter.origin {
+                        sql.push_str(" AND m.origin = ?");
+                        params_vec.push(Box::new(origin.clone()));
+                    }
 
-            if let Some(ref to_date) = filter.to_date {
-                sql.push_str(" AND m.created_at <= ?");
-                params_vec.push(Box::new(to_date.clone()));
-            }
+                    if let Some(ref from_date) = filter.from_date {
+                        sql.push_str(" AND m.created_at >= ?");
+                        params_vec.push(Box::new(from_date.clone()));
+                    }
 
-            sql.push_str(" ORDER BY m.created_at DESC");
+                    if let Some(ref to_date) = filter.to_date {
+                        sql.push_str(" AND m.created_at <= ?");
+                        params_vec.push(Box::new(to_date.clone()));
+                    }
 
-            if let Some(limit) = filter.limit {
-                sql.push_str(&format!(" LIMIT {}", limit));
-            } else {
-                sql.push_str(" LIMIT 100");
-            }
+                    sql.push_str(" ORDER BY m.created_at DESC");
 
-            let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
+                    if let Some(limit) = filter.limit {
+                        sql.push_str(&format!(" LIMIT {}", limit));
+                    } else {
+                        sql.push_str(" LIMIT 100");
+                    }
 
-            let mut result: Vec<MovementDto> = Vec::new();
-            if params_vec.is_empty() {
-                let movements = stmt.query_map([], |row| Self::row_to_dto(row))
-                    .map_err(|e| Error::Database(e.to_string()))?;
-                for movement in movements {
-                    result.push(movement.map_err(|e| Error::Database(e.to_string()))?);
-                }
-            } else {
-                let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-                let movements = stmt.query_map(params_refs.as_slice(), |row| Self::row_to_dto(row))
-                    .map_err(|e| Error::Database(e.to_string()))?;
-                for movement in movements {
-                    result.push(movement.map_err(|e| Error::Database(e.to_string()))?);
-                }
-            }
-            Ok(result)
+                    let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
+
+                    let mut result: Vec<MovementDto> = Vec::new();
+                    if params_vec.is_empty() {
+                        let movements = stmt.query_map([], |row| Self::row_to_dto(row))
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                        for movement in movements {
+                            result.push(movement.map_err(|e| Error::Database(e.to_string()))?);
+                        }
+                    } else {
+                        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+                        let movements = stmt.query_map(params_refs.as_slice(), |row| Self::row_to_dto(row))
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                        for movement in movements {
+                            result.push(movement.map_err(|e| Error::Database(e.to_string()))?);
+                        }
+                    }
+                    Ok(result)
+                },
+            )
         })
     }
 
@@ -184,7 +241,7 @@ impl MovementRepository {
     }
 
     /// Calculate current stock for a product (SUM(IN) - SUM(OUT))
-    pub fn calculate_stock(&self, product_type: &str, product_id: &str) -> Result<f64> {
+    pub fn calculate_stock(&self, product_type: &str, product_id: &str) -> Result<Qty> {
         self.db.with_connection(|conn| {
             let column = if product_type == "MP" {
                 "product_mp_id"
@@ -192,7 +249,7 @@ impl MovementRepository {
                 "product_pf_id"
             };
 
-            let stock: f64 = conn.query_row(
+            let stock: Qty = conn.query_row(
                 &format!(
                     "SELECT COALESCE(
                         SUM(CASE WHEN movement_type = 'IN' THEN quantity ELSE -quantity END),
@@ -210,6 +267,28 @@ impl MovementRepository {
         })
     }
 
+    /// Date of the latest movement of each product of a type
+    pub fn last_movement_dates(&self, product_type: &str) -> Result<HashMap<String, String>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT COALESCE(product_mp_id, product_pf_id), MAX(created_at)
+                 FROM stock_movements
+                 WHERE product_type = ? AND is_deleted = 0
+                 GROUP BY 1"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([product_type], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = HashMap::new();
+            for row in rows {
+                let (product_id, created_at): (String, String) = row.map_err(|e| Error::Database(e.to_string()))?;
+                result.insert(product_id, created_at);
+            }
+            Ok(result)
+        })
+    }
+
     /// Count total movements
     pub fn count(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
@@ -222,6 +301,61 @@ impl MovementRepository {
         })
     }
 
+    /// Find movements whose lot or product no longer matches an existing row
+    pub fn find_orphans(&self) -> Result<Vec<OrphanMovementDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT
+                    m.id, m.movement_type, m.product_type,
+                    COALESCE(m.product_mp_id, m.product_pf_id) as product_id,
+                    COALESCE(m.lot_mp_id, m.lot_pf_id) as lot_id,
+                    m.quantity,
+                    CASE
+                        WHEN m.lot_mp_id IS NOT NULL AND lmp.id IS NULL THEN 'LOT_MISSING'
+                        WHEN m.lot_pf_id IS NOT NULL AND lpf.id IS NULL THEN 'LOT_MISSING'
+                        WHEN m.product_type = 'MP' AND pmp.id IS NULL THEN 'PRODUCT_MISSING'
+                        WHEN m.product_type = 'PF' AND ppf.id IS NULL THEN 'PRODUCT_MISSING'
+                        ELSE 'PRODUCT_MISMATCH'
+                    END as reason,
+                    m.created_at
+                 FROM stock_movements m
+                 LEFT JOIN lots_mp lmp ON lmp.id = m.lot_mp_id
+                 LEFT JOIN lots_pf lpf ON lpf.id = m.lot_pf_id
+                 LEFT JOIN products_mp pmp ON pmp.id = m.product_mp_id
+                 LEFT JOIN products_pf ppf ON ppf.id = m.product_pf_id
+                 WHERE m.is_deleted = 0
+                   AND (
+                        (m.lot_mp_id IS NOT NULL AND lmp.id IS NULL)
+                     OR (m.lot_pf_id IS NOT NULL AND lpf.id IS NULL)
+                     OR (m.product_type = 'MP' AND pmp.id IS NULL)
+                     OR (m.product_type = 'PF' AND ppf.id IS NULL)
+                     OR (lmp.id IS NOT NULL AND lmp.product_mp_id != m.product_mp_id)
+                     OR (lpf.id IS NOT NULL AND lpf.product_pf_id != m.product_pf_id)
+                   )
+                 ORDER BY m.created_at ASC"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let movements = stmt.query_map([], |row| {
+                Ok(OrphanMovementDto {
+                    movement_id: row.get(0)?,
+                    movement_type: row.get(1)?,
+                    product_type: row.get(2)?,
+                    product_id: row.get(3)?,
+                    lot_id: row.get(4)?,
+                    quantity: row.get(5)?,
+                    reason: row.get(6)?,
+                    created_at: row.get(7)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for movement in movements {
+                result.push(movement.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
     fn row_to_dto(row: &Row) -> rusqlite::Result<MovementDto> {
         Ok(MovementDto {
             id: row.get(0)?,
@@ -246,6 +380,25 @@ impl MovementRepository {
             user_name: row.get(15)?,
             created_at: row.get(16)?,
             note: row.get(17)?,
+            recorded_at: row.get(18)?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use manchengo_core::{Clock, FixedClock, OffsetClock};
+
+    #[test]
+    fn test_backdated_movement_carries_business_date() {
+        let real: SharedClock = Arc::new(FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()));
+        let yesterday = OffsetClock::at_date(real.clone(), NaiveDate::from_ymd_opt(2024, 3, 14).unwrap());
+
+        assert_eq!(MovementRepository::timestamp(yesterday.now()), "2024-03-14 12:00:00");
+        // Same format as datetime('now'), so day filters (`created_at < next day`) hold
+        assert!(MovementRepository::timestamp(real.now()).as_str() < "2024-03-16");
+        assert!(MovementRepository::timestamp(yesterday.now()).as_str() < "2024-03-15");
+    }
+}
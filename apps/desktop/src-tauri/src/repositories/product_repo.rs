@@ -2,12 +2,13 @@
 //!
 //! Data access for ProductMp and ProductPf entities.
 
-use manchengo_core::{Error, Result};
+use manchengo_core::{Error, Gtin, PackDefinition, Qty, Result, UnitConversions};
 use manchengo_database::Database;
+use manchengo_domain::stock::LotSelectionStrategy;
 use rusqlite::{params, Row};
 use std::sync::Arc;
 
-use crate::dto::{ProductFilter, ProductMpDto, ProductPfDto, StockStatus};
+use crate::dto::{ProductFilter, ProductMpDto, ProductPackDto, ProductPfDto, StockStatus};
 
 /// Product repository for MP and PF
 pub struct ProductRepository {
@@ -41,7 +42,7 @@ impl ProductRepository {
                         0
                     ) as current_stock
                  FROM products_mp p
-                 WHERE 1=1"
+                 WHERE p.deleted_at IS NULL"
             );
 
             let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -103,7 +104,7 @@ impl ProductRepository {
                         0
                     ) as current_stock
                  FROM products_mp p
-                 WHERE p.id = ?"
+                 WHERE p.id = ? AND p.deleted_at IS NULL"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
             match stmt.query_row([id], |row| Self::row_to_mp_dto(row)) {
@@ -167,7 +168,7 @@ impl ProductRepository {
     pub fn count_mp(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM products_mp WHERE is_active = 1",
+                "SELECT COUNT(*) FROM products_mp WHERE is_active = 1 AND deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
@@ -176,7 +177,7 @@ impl ProductRepository {
     }
 
     fn row_to_mp_dto(row: &Row) -> rusqlite::Result<ProductMpDto> {
-        let current_stock: f64 = row.get(10)?;
+        let current_stock: Qty = row.get(10)?;
         let min_stock: f64 = row.get(5)?;
         let reorder_point: f64 = row.get(6)?;
 
@@ -216,9 +217,10 @@ impl ProductRepository {
                         (SELECT SUM(CASE WHEN m.movement_type = 'IN' THEN m.quantity ELSE -m.quantity END)
                          FROM stock_movements m WHERE m.product_type = 'PF' AND m.product_id = p.id),
                         0
-                    ) as current_stock
+                    ) as current_stock,
+                    p.gtin, p.carton_gtin
                  FROM products_pf p
-                 WHERE 1=1"
+                 WHERE p.deleted_at IS NULL"
             );
 
             if filter.active_only.unwrap_or(false) {
@@ -255,9 +257,10 @@ impl ProductRepository {
                         (SELECT SUM(CASE WHEN m.movement_type = 'IN' THEN m.quantity ELSE -m.quantity END)
                          FROM stock_movements m WHERE m.product_type = 'PF' AND m.product_id = p.id),
                         0
-                    ) as current_stock
+                    ) as current_stock,
+                    p.gtin, p.carton_gtin
                  FROM products_pf p
-                 WHERE p.id = ?"
+                 WHERE p.id = ? AND p.deleted_at IS NULL"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
             match stmt.query_row([id], |row| Self::row_to_pf_dto(row)) {
@@ -268,11 +271,65 @@ impl ProductRepository {
         })
     }
 
+    /// Category code of a PF product (e.g. "PF-LAIT"), used for TVA rates
+    pub fn pf_category_code(&self, id: &str) -> Result<Option<String>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT c.code FROM products_pf p
+                 JOIN ref_categories c ON c.id = p.category_id
+                 WHERE p.id = ?",
+                [id],
+                |row| row.get(0),
+            ) {
+                Ok(code) => Ok(Some(code)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Set the EAN-13 and carton GTIN-14 of a PF product
+    pub fn set_pf_gtins(&self, id: &str, gtin: Option<&Gtin>, carton_gtin: Option<&Gtin>) -> Result<()> {
+        self.db.with_connection(|conn| {
+            let updated = conn.execute(
+                "UPDATE products_pf SET gtin = ?, carton_gtin = ?, updated_at = datetime('now')
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![gtin.map(Gtin::as_str), carton_gtin.map(Gtin::as_str), id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            if updated == 0 {
+                return Err(Error::NotFound {
+                    entity_type: "ProductPf".to_string(),
+                    id: id.to_string(),
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// Find the PF product carrying a GTIN (unit or carton, any length)
+    pub fn find_pf_by_gtin(&self, gtin: &Gtin) -> Result<Option<String>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT id FROM products_pf
+                 WHERE deleted_at IS NULL
+                   AND (substr('0000000000000' || gtin, -14) = ?1
+                        OR substr('0000000000000' || carton_gtin, -14) = ?1)",
+                [gtin.to_gtin14()],
+                |row| row.get(0),
+            ) {
+                Ok(id) => Ok(Some(id)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
     /// Count PF products
     pub fn count_pf(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM products_pf WHERE is_active = 1",
+                "SELECT COUNT(*) FROM products_pf WHERE is_active = 1 AND deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
@@ -281,7 +338,7 @@ impl ProductRepository {
     }
 
     fn row_to_pf_dto(row: &Row) -> rusqlite::Result<ProductPfDto> {
-        let current_stock: f64 = row.get(10)?;
+        let current_stock: Qty = row.get(10)?;
         let min_stock: f64 = row.get(5)?;
 
         Ok(ProductPfDto {
@@ -297,6 +354,99 @@ impl ProductRepository {
             is_active: row.get::<_, i32>(9)? == 1,
             current_stock,
             stock_status: StockStatus::from_levels(current_stock, min_stock, min_stock * 1.5),
+            gtin: row.get(11)?,
+            carton_gtin: row.get(12)?,
+        })
+    }
+
+    // =========================================================================
+    // UNITS & PACKS
+    // =========================================================================
+
+    /// Unit conversions from ref_units
+    pub fn unit_conversions(&self) -> Result<UnitConversions> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT code, base_unit, COALESCE(conversion_factor, 1.0)
+                 FROM ref_units WHERE is_active = 1"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, f64>(2)?))
+            }).map_err(|e| Error::Database(e.to_string()))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+            Ok(UnitConversions::from_ref_units(rows))
+        })
+    }
+
+    /// Get product pack (empty when not defined)
+    pub fn get_pack(&self, product_type: &str, product_id: &str) -> Result<PackDefinition> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT pieces_per_carton, cartons_per_pallet FROM product_packs
+                 WHERE product_type = ? AND product_id = ?",
+                [product_type, product_id],
+                |row| Ok(PackDefinition::new(row.get(0)?, row.get(1)?)),
+            ) {
+                Ok(pack) => Ok(pack),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(PackDefinition::default()),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Create or replace product pack
+    pub fn set_pack(&self, pack: &ProductPackDto) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO product_packs (product_type, product_id, pieces_per_carton, cartons_per_pallet, updated_at)
+                 VALUES (?, ?, ?, ?, datetime('now'))
+                 ON CONFLICT(product_type, product_id) DO UPDATE SET
+                    pieces_per_carton = excluded.pieces_per_carton,
+                    cartons_per_pallet = excluded.cartons_per_pallet,
+                    updated_at = excluded.updated_at",
+                params![
+                    pack.product_type,
+                    pack.product_id,
+                    pack.pieces_per_carton,
+                    pack.cartons_per_pallet,
+                ]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    /// Get product lot selection strategy (FIFO when not defined)
+    pub fn get_lot_strategy(&self, product_type: &str, product_id: &str) -> Result<LotSelectionStrategy> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT strategy, min_shelf_life_days FROM product_lot_strategies
+                 WHERE product_type = ? AND product_id = ?",
+                [product_type, product_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<u32>>(1)?)),
+            ) {
+                Ok((code, min_days)) => Ok(LotSelectionStrategy::from_code(&code, min_days).unwrap_or_default()),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(LotSelectionStrategy::default()),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Create or replace product lot selection strategy
+    pub fn set_lot_strategy(&self, product_type: &str, product_id: &str, strategy: &LotSelectionStrategy) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO product_lot_strategies (product_type, product_id, strategy, min_shelf_life_days, updated_at)
+                 VALUES (?, ?, ?, ?, datetime('now'))
+                 ON CONFLICT(product_type, product_id) DO UPDATE SET
+                    strategy = excluded.strategy,
+                    min_shelf_life_days = excluded.min_shelf_life_days,
+                    updated_at = excluded.updated_at",
+                params![product_type, product_id, strategy.as_str(), strategy.min_shelf_life_days()]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
         })
     }
 }
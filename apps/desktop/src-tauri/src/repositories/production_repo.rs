@@ -2,8 +2,9 @@
 //!
 //! Data access for ProductionOrder and ProductionConsumption entities.
 
-use manchengo_core::{Error, Result};
-use manchengo_database::Database;
+use chrono::NaiveDate;
+use manchengo_core::{Error, Qty, Result};
+use manchengo_database::{Database, DocumentSequences, DocumentType};
 use rusqlite::{params, Row, OptionalExtension};
 use std::sync::Arc;
 
@@ -139,13 +140,15 @@ impl ProductionRepository {
     pub fn create(
         &self,
         id: &str,
-        reference: &str,
+        order_date: NaiveDate,
         recipe_id: &str,
-        target_quantity: f64,
+        target_quantity: Qty,
         data: &CreateProductionOrderDto,
         user_id: &str,
-    ) -> Result<()> {
-        self.db.with_connection(|conn| {
+    ) -> Result<String> {
+        self.db.transaction(|conn| {
+            let reference = DocumentSequences::allocate(conn, DocumentType::ProductionOrder, order_date, id)?.number;
+
             conn.execute(
                 "INSERT INTO production_orders (
                     id, reference, product_pf_id, recipe_id, batch_count,
@@ -165,7 +168,7 @@ impl ProductionRepository {
                 ],
             )
             .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(())
+            Ok(reference)
         })
     }
 
@@ -206,13 +209,23 @@ impl ProductionRepository {
     }
 
     /// Cancel production order
+    ///
+    /// The order keeps its reference, marked VOIDED in the sequence.
     pub fn cancel(&self, id: &str, reason: Option<&str>) -> Result<()> {
-        self.db.with_connection(|conn| {
+        self.db.transaction(|conn| {
             conn.execute(
                 "UPDATE production_orders SET status = 'CANCELLED', notes = COALESCE(?, notes) WHERE id = ?",
                 params![reason, id],
             )
             .map_err(|e| Error::Database(e.to_string()))?;
+
+            let reference: Option<String> = conn
+                .query_row("SELECT reference FROM production_orders WHERE id = ?", [id], |row| row.get(0))
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))?;
+            if let Some(reference) = reference {
+                DocumentSequences::void(conn, &reference, reason.unwrap_or("Ordre annule"))?;
+            }
             Ok(())
         })
     }
@@ -224,7 +237,7 @@ impl ProductionRepository {
         order_id: &str,
         product_mp_id: &str,
         lot_mp_id: &str,
-        quantity: f64,
+        quantity: Qty,
         unit: &str,
     ) -> Result<()> {
         self.db.with_connection(|conn| {
@@ -240,21 +253,6 @@ impl ProductionRepository {
         })
     }
 
-    /// Generate unique reference
-    pub fn generate_reference(&self) -> Result<String> {
-        self.db.with_connection(|conn| {
-            let today = chrono::Utc::now().format("%Y%m%d").to_string();
-            let count: i64 = conn
-                .query_row(
-                    "SELECT COUNT(*) FROM production_orders WHERE reference LIKE ?",
-                    [format!("PROD-{}-%%", today)],
-                    |row| row.get(0),
-                )
-                .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(format!("PROD-{}-{:03}", today, count + 1))
-        })
-    }
-
     /// Count by status
     pub fn count_by_status(&self, status: &str) -> Result<u64> {
         self.db.with_connection(|conn| {
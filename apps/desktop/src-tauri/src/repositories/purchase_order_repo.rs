@@ -2,8 +2,9 @@
 //!
 //! Data access for PurchaseOrder and PurchaseOrderLine entities.
 
-use manchengo_core::{Error, Result};
-use manchengo_database::Database;
+use chrono::NaiveDate;
+use manchengo_core::{Currency, CurrencyAmount, Error, ExchangeRate, Result, RATE_SCALE};
+use manchengo_database::{Database, DocumentSequences, DocumentType};
 use rusqlite::{params, Row, OptionalExtension};
 use std::sync::Arc;
 
@@ -27,10 +28,11 @@ impl PurchaseOrderRepository {
                         s.name as supplier_name, s.code as supplier_code,
                         po.status, po.expected_delivery, po.received_date,
                         po.total_amount, po.notes,
-                        po.created_at, po.updated_at
+                        po.created_at, po.updated_at,
+                        po.currency, po.exchange_rate, po.total_amount_dzd
                  FROM purchase_orders po
                  LEFT JOIN suppliers s ON s.id = po.supplier_id
-                 WHERE po.is_deleted = 0",
+                 WHERE po.is_deleted = 0 AND po.deleted_at IS NULL",
             );
 
             let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -102,10 +104,11 @@ impl PurchaseOrderRepository {
                             s.name as supplier_name, s.code as supplier_code,
                             po.status, po.expected_delivery, po.received_date,
                             po.total_amount, po.notes,
-                            po.created_at, po.updated_at
+                            po.created_at, po.updated_at,
+                            po.currency, po.exchange_rate, po.total_amount_dzd
                      FROM purchase_orders po
                      LEFT JOIN suppliers s ON s.id = po.supplier_id
-                     WHERE po.id = ? AND po.is_deleted = 0",
+                     WHERE po.id = ? AND po.is_deleted = 0 AND po.deleted_at IS NULL",
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
 
@@ -124,26 +127,46 @@ impl PurchaseOrderRepository {
     }
 
     /// Create new purchase order
-    pub fn create(&self, id: &str, reference: &str, data: &CreatePurchaseOrderDto) -> Result<()> {
-        self.db.with_connection(|conn| {
-            // Calculate total
-            let total: i64 = data
-                .lines
-                .iter()
-                .map(|l| (l.unit_price * l.quantity as f64) as i64)
-                .sum();
+    ///
+    /// The reference is allocated in the same transaction and returned.
+    /// Prices are in the order currency; `exchange_rate` (order-date rate,
+    /// foreign currency only) gives the DZD total.
+    pub fn create(
+        &self,
+        id: &str,
+        order_date: NaiveDate,
+        data: &CreatePurchaseOrderDto,
+        exchange_rate: Option<&ExchangeRate>,
+    ) -> Result<String> {
+        // Calculate total
+        let total: i64 = data
+            .lines
+            .iter()
+            .map(|l| (l.unit_price * l.quantity as f64) as i64)
+            .sum();
+        let total_dzd = match exchange_rate {
+            Some(rate) => rate.convert(CurrencyAmount::new(total, data.currency))?.centimes(),
+            None => total,
+        };
+
+        self.db.transaction(|conn| {
+            let reference = DocumentSequences::allocate(conn, DocumentType::PurchaseOrder, order_date, id)?.number;
 
             conn.execute(
                 "INSERT INTO purchase_orders (
                     id, reference, supplier_id, status, expected_delivery,
-                    total_amount, notes, created_at, is_deleted
-                ) VALUES (?, ?, ?, 'DRAFT', ?, ?, ?, datetime('now'), 0)",
+                    currency, exchange_rate, total_amount, total_amount_dzd,
+                    notes, created_at, is_deleted
+                ) VALUES (?, ?, ?, 'DRAFT', ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
                 params![
                     id,
                     reference,
                     data.supplier_id,
                     data.expected_delivery,
+                    data.currency.code(),
+                    exchange_rate.map(|r| r.rate),
                     total,
+                    total_dzd,
                     data.notes,
                 ],
             )
@@ -170,7 +193,7 @@ impl PurchaseOrderRepository {
                 .map_err(|e| Error::Database(e.to_string()))?;
             }
 
-            Ok(())
+            Ok(reference)
         })
     }
 
@@ -203,29 +226,24 @@ impl PurchaseOrderRepository {
     }
 
     /// Cancel order
+    ///
+    /// The order keeps its reference, marked VOIDED in the sequence.
     pub fn cancel(&self, id: &str) -> Result<()> {
-        self.db.with_connection(|conn| {
+        self.db.transaction(|conn| {
             conn.execute(
                 "UPDATE purchase_orders SET status = 'CANCELLED', updated_at = datetime('now') WHERE id = ?",
                 [id],
             )
             .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(())
-        })
-    }
 
-    /// Generate unique reference
-    pub fn generate_reference(&self) -> Result<String> {
-        self.db.with_connection(|conn| {
-            let today = chrono::Utc::now().format("%Y%m%d").to_string();
-            let count: i64 = conn
-                .query_row(
-                    "SELECT COUNT(*) FROM purchase_orders WHERE reference LIKE ?",
-                    [format!("BC-{}-%%", today)],
-                    |row| row.get(0),
-                )
+            let reference: Option<String> = conn
+                .query_row("SELECT reference FROM purchase_orders WHERE id = ?", [id], |row| row.get(0))
+                .optional()
                 .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(format!("BC-{}-{:03}", today, count + 1))
+            if let Some(reference) = reference {
+                DocumentSequences::void(conn, &reference, "Commande annulee")?;
+            }
+            Ok(())
         })
     }
 
@@ -234,7 +252,7 @@ impl PurchaseOrderRepository {
         self.db.with_connection(|conn| {
             let count: i64 = conn
                 .query_row(
-                    "SELECT COUNT(*) FROM purchase_orders WHERE status = ? AND is_deleted = 0",
+                    "SELECT COUNT(*) FROM purchase_orders WHERE status = ? AND is_deleted = 0 AND deleted_at IS NULL",
                     [status],
                     |row| row.get(0),
                 )
@@ -293,7 +311,10 @@ impl PurchaseOrderRepository {
             status: PurchaseOrderStatus::from(row.get::<_, String>(5)?.as_str()),
             expected_delivery: row.get(6)?,
             received_date: row.get(7)?,
+            currency: Currency::from_code(&row.get::<_, String>(12)?).unwrap_or_default(),
+            exchange_rate: row.get::<_, Option<i64>>(13)?.map(|r| r as f64 / RATE_SCALE as f64),
             total_amount: row.get(8)?,
+            total_amount_dzd: row.get(14)?,
             notes: row.get(9)?,
             lines: Vec::new(), // Will be populated separately
             created_at: row.get(10)?,
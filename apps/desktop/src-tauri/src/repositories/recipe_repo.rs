@@ -32,7 +32,7 @@ impl RecipeRepository {
                         r.created_at, r.updated_at
                  FROM recipes r
                  LEFT JOIN products_pf pf ON pf.id = r.product_pf_id
-                 WHERE 1=1",
+                 WHERE r.deleted_at IS NULL",
             );
 
             let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -100,7 +100,7 @@ impl RecipeRepository {
                             r.created_at, r.updated_at
                      FROM recipes r
                      LEFT JOIN products_pf pf ON pf.id = r.product_pf_id
-                     WHERE r.id = ?",
+                     WHERE r.id = ? AND r.deleted_at IS NULL",
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
 
@@ -130,7 +130,7 @@ impl RecipeRepository {
                             r.created_at, r.updated_at
                      FROM recipes r
                      LEFT JOIN products_pf pf ON pf.id = r.product_pf_id
-                     WHERE r.product_pf_id = ? AND r.is_active = 1
+                     WHERE r.product_pf_id = ? AND r.is_active = 1 AND r.deleted_at IS NULL
                      ORDER BY r.created_at DESC
                      LIMIT 1",
                 )
@@ -157,8 +157,8 @@ impl RecipeRepository {
                 "INSERT INTO recipes (
                     id, name, code, product_pf_id, batch_weight, output_quantity,
                     output_unit, loss_tolerance, shelf_life_days, is_active,
-                    created_at, is_deleted
-                ) VALUES (?, ?, ?, ?, ?, ?, 'UNIT', ?, ?, 1, datetime('now'), 0)",
+                    created_at
+                ) VALUES (?, ?, ?, ?, ?, ?, 'UNIT', ?, ?, 1, datetime('now'))",
                 params![
                     id,
                     data.name,
@@ -215,18 +215,6 @@ impl RecipeRepository {
         })
     }
 
-    /// Soft delete recipe
-    pub fn delete(&self, id: &str) -> Result<()> {
-        self.db.with_connection(|conn| {
-            conn.execute(
-                "UPDATE recipes SET is_deleted = 1, updated_at = datetime('now') WHERE id = ?",
-                [id],
-            )
-            .map_err(|e| Error::Database(e.to_string()))?;
-            Ok(())
-        })
-    }
-
     /// Activate/deactivate recipe
     pub fn set_active(&self, id: &str, active: bool) -> Result<()> {
         self.db.with_connection(|conn| {
@@ -0,0 +1,176 @@
+//! Lot Reservation Repository
+//!
+//! Data access for the finished product lots held for confirmed sales orders.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use manchengo_core::{AuditInfo, EntityId, Error, Result};
+use manchengo_database::Database;
+use manchengo_domain::stock::{LotReservation, ReservationBook, ReservationStatus};
+use rusqlite::{params, Connection, Row};
+use std::sync::Arc;
+
+use crate::dto::LotReservationDto;
+use crate::repositories::MovementRepository;
+
+const RESERVATION_SELECT: &str =
+    "SELECT r.id, r.lot_pf_id, l.lot_number, r.product_pf_id, r.sales_order_id, r.sales_order_line_id,
+            r.quantity, r.status, r.expires_at, r.release_reason, r.created_at, r.created_by, r.updated_at
+     FROM lot_reservations r
+     JOIN lots_pf l ON l.id = r.lot_pf_id";
+
+/// Lot reservation repository
+pub struct ReservationRepository {
+    db: Arc<Database>,
+}
+
+impl ReservationRepository {
+    /// Lots a sales order holds (`?` is the order id)
+    pub const ORDER_LOTS: &'static str =
+        "SELECT lot_pf_id FROM lot_reservations WHERE sales_order_id = ? AND status = 'ACTIVE'";
+
+    /// Lots with a hold past its expiry (`?` is the current timestamp)
+    pub const DUE_LOTS: &'static str =
+        "SELECT lot_pf_id FROM lot_reservations WHERE status = 'ACTIVE' AND expires_at <= ?";
+
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Quantity still held on the lot aliased `l` at `now`, as an SQL expression
+    ///
+    /// `now` must come from [`MovementRepository::timestamp`]; timed-out holds
+    /// stop counting before the sweep closes them.
+    pub fn held_sql(now: &str) -> String {
+        format!(
+            "(SELECT COALESCE(SUM(r.quantity), 0) FROM lot_reservations r
+              WHERE r.lot_pf_id = l.id AND r.status = 'ACTIVE' AND r.expires_at > '{}')",
+            now
+        )
+    }
+
+    /// Active reservations on the lots selected by `lots_sql` (one `?` bound to `param`)
+    pub fn active_on_lots(conn: &Connection, lots_sql: &str, param: &str) -> Result<ReservationBook> {
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE r.status = 'ACTIVE' AND r.lot_pf_id IN ({}) ORDER BY r.created_at, r.id",
+            RESERVATION_SELECT, lots_sql
+        )).map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut rows = stmt.query([param]).map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut reservations = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| Error::Database(e.to_string()))? {
+            reservations.push(Self::row_to_domain(row)?);
+        }
+        Ok(ReservationBook::new(reservations))
+    }
+
+    /// Record a new reservation
+    pub fn insert(conn: &Connection, reservation: &LotReservation) -> Result<()> {
+        conn.execute(
+            "INSERT INTO lot_reservations (
+                id, lot_pf_id, product_pf_id, sales_order_id, sales_order_line_id,
+                quantity, status, expires_at, created_at, created_by, updated_at
+             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                reservation.id.to_string(),
+                reservation.lot_id.to_string(),
+                reservation.product_id.to_string(),
+                reservation.sales_order_id.to_string(),
+                reservation.sales_order_line_id.to_string(),
+                reservation.quantity,
+                reservation.status.as_str(),
+                MovementRepository::timestamp(reservation.expires_at),
+                MovementRepository::timestamp(reservation.audit.created_at),
+                reservation.audit.created_by.to_string(),
+                MovementRepository::timestamp(reservation.audit.updated_at),
+            ],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Save the end of a reservation (consumed, released or expired)
+    pub fn close(conn: &Connection, reservation: &LotReservation) -> Result<()> {
+        conn.execute(
+            "UPDATE lot_reservations SET status = ?, release_reason = ?, updated_at = ?
+             WHERE id = ? AND status = 'ACTIVE'",
+            params![
+                reservation.status.as_str(),
+                reservation.release_reason,
+                MovementRepository::timestamp(reservation.audit.updated_at),
+                reservation.id.to_string(),
+            ],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Reservations of a sales order, all statuses
+    pub fn list_for_order(&self, sales_order_id: &str) -> Result<Vec<LotReservationDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "{} WHERE r.sales_order_id = ? ORDER BY r.created_at, l.lot_number",
+                RESERVATION_SELECT
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([sales_order_id], |row| {
+                Ok(LotReservationDto {
+                    id: row.get(0)?,
+                    lot_id: row.get(1)?,
+                    lot_number: row.get(2)?,
+                    product_id: row.get(3)?,
+                    sales_order_id: row.get(4)?,
+                    sales_order_line_id: row.get(5)?,
+                    quantity: row.get(6)?,
+                    status: row.get(7)?,
+                    expires_at: row.get(8)?,
+                    release_reason: row.get(9)?,
+                    created_at: row.get(10)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    // Internal helpers
+
+    fn row_to_domain(row: &Row) -> Result<LotReservation> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let status: String = row.get(7).map_err(db_err)?;
+        let created_by = Self::parse_id(&row.get::<_, Option<String>>(11).map_err(db_err)?.unwrap_or_default())?;
+
+        Ok(LotReservation {
+            id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+            lot_id: Self::parse_id(&row.get::<_, String>(1).map_err(db_err)?)?,
+            lot_number: row.get(2).map_err(db_err)?,
+            product_id: Self::parse_id(&row.get::<_, String>(3).map_err(db_err)?)?,
+            sales_order_id: Self::parse_id(&row.get::<_, String>(4).map_err(db_err)?)?,
+            sales_order_line_id: Self::parse_id(&row.get::<_, String>(5).map_err(db_err)?)?,
+            quantity: row.get(6).map_err(db_err)?,
+            status: ReservationStatus::from_code(&status)
+                .ok_or_else(|| Error::Internal(format!("Statut de reservation inconnu en base: {}", status)))?,
+            expires_at: Self::parse_timestamp(&row.get::<_, String>(8).map_err(db_err)?)?,
+            release_reason: row.get(9).map_err(db_err)?,
+            audit: AuditInfo {
+                created_at: Self::parse_timestamp(&row.get::<_, String>(10).map_err(db_err)?)?,
+                created_by,
+                updated_at: Self::parse_timestamp(&row.get::<_, String>(12).map_err(db_err)?)?,
+                updated_by: created_by,
+                business_date: None,
+            },
+        })
+    }
+
+    fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map(|t| t.and_utc())
+            .map_err(|_| Error::Internal(format!("Horodatage invalide en base: {}", value)))
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+}
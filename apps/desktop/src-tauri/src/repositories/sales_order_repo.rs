@@ -0,0 +1,159 @@
+//! Sales Order Repository
+//!
+//! Data access for sales orders, loaded as domain orders so confirmation and
+//! cancellation run the domain rules.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use manchengo_core::{AuditInfo, EntityId, Error, Money, Quantity, Result, UnitOfMeasure};
+use manchengo_domain::commercial::{PaymentStatus, SalesOrder, SalesOrderLine, SalesOrderStatus};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::repositories::MovementRepository;
+
+/// Sales order repository
+///
+/// Only transactional helpers: orders are read and saved on the caller's
+/// transaction, next to their reservations.
+pub struct SalesOrderRepository;
+
+impl SalesOrderRepository {
+    /// Lots of the products of a sales order (`?` is the order id)
+    pub const PRODUCT_LOTS: &'static str =
+        "SELECT id FROM lots_pf WHERE product_pf_id IN (SELECT product_pf_id FROM sales_order_lines WHERE sales_order_id = ?)";
+
+    /// Get a sales order with its lines
+    ///
+    /// `stock_quantity` of each line is left in the ordered unit; the caller
+    /// converts it to the product stock unit.
+    pub fn get(conn: &Connection, id: &str) -> Result<Option<SalesOrder>> {
+        let order = conn.query_row(
+            "SELECT id, order_number, client_id, order_date, requested_date, status,
+                    total_ht, total_tva, total_ttc, payment_status, amount_paid, notes,
+                    created_at, created_by, updated_at, updated_by
+             FROM sales_orders WHERE id = ?",
+            [id],
+            |row| Ok(Self::row_to_order(row)),
+        ).optional().map_err(|e| Error::Database(e.to_string()))?;
+
+        let Some(order) = order else {
+            return Ok(None);
+        };
+        let mut order = order?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, product_pf_id, quantity, unit, unit_price_ht, tva_rate,
+                    total_ht, total_tva, total_ttc, quantity_delivered, notes
+             FROM sales_order_lines WHERE sales_order_id = ? ORDER BY rowid"
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        let mut rows = stmt.query([id]).map_err(|e| Error::Database(e.to_string()))?;
+        while let Some(row) = rows.next().map_err(|e| Error::Database(e.to_string()))? {
+            let line = Self::row_to_line(row, order.id)?;
+            order.lines.push(line);
+        }
+
+        Ok(Some(order))
+    }
+
+    /// Save the status and notes of an order
+    pub fn save_status(conn: &Connection, order: &SalesOrder) -> Result<()> {
+        conn.execute(
+            "UPDATE sales_orders SET status = ?, notes = ?, updated_at = ?, updated_by = ? WHERE id = ?",
+            params![
+                order.status.as_str(),
+                order.notes,
+                MovementRepository::timestamp(order.audit.updated_at),
+                order.audit.updated_by.to_string(),
+                order.id.to_string(),
+            ],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    // Internal helpers
+
+    fn row_to_order(row: &Row) -> Result<SalesOrder> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let status: String = row.get(5).map_err(db_err)?;
+        let payment_status: String = row.get(9).map_err(db_err)?;
+
+        Ok(SalesOrder {
+            id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+            order_number: row.get(1).map_err(db_err)?,
+            client_id: Self::parse_id(&row.get::<_, String>(2).map_err(db_err)?)?,
+            order_date: Self::parse_date(&row.get::<_, String>(3).map_err(db_err)?)?,
+            requested_date: row
+                .get::<_, Option<String>>(4)
+                .map_err(db_err)?
+                .as_deref()
+                .map(Self::parse_date)
+                .transpose()?,
+            status: match status.as_str() {
+                "DRAFT" => SalesOrderStatus::Draft,
+                "CONFIRMED" => SalesOrderStatus::Confirmed,
+                "PREPARED" => SalesOrderStatus::Prepared,
+                "DELIVERED" => SalesOrderStatus::Delivered,
+                "CANCELLED" => SalesOrderStatus::Cancelled,
+                other => return Err(Error::Internal(format!("Statut de commande inconnu en base: {}", other))),
+            },
+            lines: Vec::new(), // Will be populated separately
+            total_ht: Money::from_centimes(row.get(6).map_err(db_err)?),
+            total_tva: Money::from_centimes(row.get(7).map_err(db_err)?),
+            total_ttc: Money::from_centimes(row.get(8).map_err(db_err)?),
+            payment_status: match payment_status.as_str() {
+                "PAID" => PaymentStatus::Paid,
+                "PARTIAL" => PaymentStatus::Partial,
+                _ => PaymentStatus::Unpaid,
+            },
+            amount_paid: Money::from_centimes(row.get(10).map_err(db_err)?),
+            notes: row.get(11).map_err(db_err)?,
+            audit: AuditInfo {
+                created_at: Self::parse_timestamp(&row.get::<_, String>(12).map_err(db_err)?)?,
+                created_by: Self::parse_id(&row.get::<_, String>(13).map_err(db_err)?)?,
+                updated_at: Self::parse_timestamp(&row.get::<_, String>(14).map_err(db_err)?)?,
+                updated_by: Self::parse_id(&row.get::<_, String>(15).map_err(db_err)?)?,
+                business_date: None,
+            },
+        })
+    }
+
+    fn row_to_line(row: &Row, sales_order_id: EntityId) -> Result<SalesOrderLine> {
+        let db_err = |e: rusqlite::Error| Error::Database(e.to_string());
+        let quantity: f64 = row.get(2).map_err(db_err)?;
+        let unit_code: String = row.get(3).map_err(db_err)?;
+        let unit = UnitOfMeasure::from_code(&unit_code)
+            .ok_or_else(|| Error::Internal(format!("Unite inconnue en base: {}", unit_code)))?;
+
+        Ok(SalesOrderLine {
+            id: Self::parse_id(&row.get::<_, String>(0).map_err(db_err)?)?,
+            sales_order_id,
+            product_pf_id: Self::parse_id(&row.get::<_, String>(1).map_err(db_err)?)?,
+            quantity,
+            unit,
+            stock_quantity: Quantity::new(quantity, unit),
+            unit_price_ht: Money::from_centimes(row.get(4).map_err(db_err)?),
+            tva_rate: row.get(5).map_err(db_err)?,
+            total_ht: Money::from_centimes(row.get(6).map_err(db_err)?),
+            total_tva: Money::from_centimes(row.get(7).map_err(db_err)?),
+            total_ttc: Money::from_centimes(row.get(8).map_err(db_err)?),
+            quantity_delivered: row.get::<_, Option<f64>>(9).map_err(db_err)?.unwrap_or(0.0),
+            notes: row.get(10).map_err(db_err)?,
+        })
+    }
+
+    fn parse_date(value: &str) -> Result<NaiveDate> {
+        value
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .ok_or_else(|| Error::Internal(format!("Date invalide en base: {}", value)))
+    }
+
+    fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map(|t| t.and_utc())
+            .map_err(|_| Error::Internal(format!("Horodatage invalide en base: {}", value)))
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+}
@@ -0,0 +1,255 @@
+//! Stock History Repository
+//!
+//! Point-in-time lot balances rebuilt from the movement ledger, and the daily
+//! closing snapshots that shortcut the replay.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use manchengo_core::{Error, Qty, Result};
+use manchengo_database::archive::FiscalArchive;
+use manchengo_database::Database;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::dto::StockSnapshotDto;
+
+/// Ledger balance of a lot in a warehouse
+#[derive(Debug, Clone)]
+pub struct LedgerBalance {
+    pub product_type: String, // "MP" or "PF"
+    pub product_id: String,
+    pub lot_id: Option<String>,
+    pub warehouse_id: Option<String>,
+    pub quantity: Qty,
+    pub last_movement_at: Option<String>,
+}
+
+type BalanceKey = (String, String, Option<String>, Option<String>);
+
+/// Stock history repository
+pub struct StockHistoryRepository {
+    db: Arc<Database>,
+}
+
+impl StockHistoryRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Non-zero balances at the end of `as_of`, with the snapshot they
+    /// started from
+    pub fn balances(&self, as_of: NaiveDate) -> Result<(Option<NaiveDate>, Vec<LedgerBalance>)> {
+        self.db.with_connection(|conn| Self::balances_at(conn, as_of))
+    }
+
+    /// Store the closing balances of a day; returns the number of lines
+    pub fn close_day(&self, date: NaiveDate) -> Result<u32> {
+        self.db.with_connection_mut(|conn| {
+            let (_, balances) = Self::balances_at(conn, date)?;
+
+            let tx = conn.transaction().map_err(|e| Error::Database(e.to_string()))?;
+            tx.execute(
+                "INSERT INTO stock_snapshots (snapshot_date, lines_count) VALUES (?, ?)",
+                params![date.to_string(), balances.len() as i64],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            for balance in &balances {
+                tx.execute(
+                    "INSERT INTO stock_snapshot_lines (
+                        snapshot_date, product_type, product_id, lot_id, warehouse_id, quantity, last_movement_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        date.to_string(),
+                        balance.product_type,
+                        balance.product_id,
+                        balance.lot_id,
+                        balance.warehouse_id,
+                        balance.quantity,
+                        balance.last_movement_at,
+                    ],
+                ).map_err(|e| Error::Database(e.to_string()))?;
+            }
+
+            tx.commit().map_err(|e| Error::Database(e.to_string()))?;
+            Ok(balances.len() as u32)
+        })
+    }
+
+    /// Most recent closed day
+    pub fn last_snapshot_date(&self) -> Result<Option<NaiveDate>> {
+        self.db.with_connection(|conn| Self::snapshot_on_or_before(conn, None))
+    }
+
+    /// Day of the oldest live movement
+    pub fn first_movement_date(&self) -> Result<Option<NaiveDate>> {
+        self.db.with_connection(|conn| {
+            let first: Option<String> = conn.query_row(
+                "SELECT MIN(created_at) FROM stock_movements WHERE is_deleted = 0",
+                [],
+                |row| row.get(0),
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            first.as_deref().map(Self::parse_date).transpose()
+        })
+    }
+
+    /// Closed days, most recent first
+    pub fn list_snapshots(&self, limit: u32) -> Result<Vec<StockSnapshotDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT snapshot_date, lines_count, created_at FROM stock_snapshots
+                 ORDER BY snapshot_date DESC LIMIT ?"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([limit], |row| {
+                Ok(StockSnapshotDto {
+                    snapshot_date: row.get(0)?,
+                    lines_count: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Lot numbers of all lots, by id
+    pub fn lot_numbers(&self) -> Result<HashMap<String, String>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, lot_number FROM lots_mp
+                 UNION ALL
+                 SELECT id, lot_number FROM lots_pf"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = HashMap::new();
+            for row in rows {
+                let (id, lot_number): (String, String) = row.map_err(|e| Error::Database(e.to_string()))?;
+                result.insert(id, lot_number);
+            }
+            Ok(result)
+        })
+    }
+
+    // Internal helpers
+
+    /// Start from the latest snapshot up to `as_of` and replay the movements
+    /// recorded after it.
+    ///
+    /// Opening balances only restate the closing of an archived year: they
+    /// are skipped when replaying after a snapshot. Without a snapshot, a date
+    /// in an archived year is replayed from that year's archive, which starts
+    /// with its own opening balances.
+    fn balances_at(conn: &Connection, as_of: NaiveDate) -> Result<(Option<NaiveDate>, Vec<LedgerBalance>)> {
+        let snapshot = Self::snapshot_on_or_before(conn, Some(as_of))?;
+
+        let mut balances: HashMap<BalanceKey, LedgerBalance> = HashMap::new();
+        if let Some(date) = snapshot {
+            let mut stmt = conn.prepare(
+                "SELECT product_type, product_id, lot_id, warehouse_id, quantity, last_movement_at
+                 FROM stock_snapshot_lines WHERE snapshot_date = ?"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([date.to_string()], Self::row_to_balance)
+                .map_err(|e| Error::Database(e.to_string()))?;
+            for row in rows {
+                Self::merge(&mut balances, row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+        }
+
+        if snapshot != Some(as_of) {
+            let (from, skip_opening) = match snapshot {
+                Some(date) => (Some((date + Duration::days(1)).to_string()), true),
+                None => {
+                    let archived = FiscalArchive::get(conn, as_of.year())?.is_some();
+                    (archived.then(|| FiscalArchive::year_bounds(as_of.year()).0), false)
+                }
+            };
+            let to = as_of.to_string();
+            let end = (as_of + Duration::days(1)).to_string();
+
+            FiscalArchive::with_range(conn, from.as_deref(), Some(&to), |archives| {
+                let movements_source = archives.source(conn, "stock_movements")?;
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT product_type, COALESCE(product_mp_id, product_pf_id),
+                            COALESCE(lot_mp_id, lot_pf_id), warehouse_id,
+                            SUM(CASE WHEN movement_type = 'IN' THEN quantity ELSE -quantity END),
+                            MAX(created_at)
+                     FROM {}
+                     WHERE is_deleted = 0 AND created_at < ?1
+                       AND (?2 IS NULL OR created_at >= ?2)
+                       AND (?3 = 0 OR origin <> 'OUVERTURE')
+                     GROUP BY 1, 2, 3, 4",
+                    movements_source
+                )).map_err(|e| Error::Database(e.to_string()))?;
+
+                let rows = stmt.query_map(params![end, from, skip_opening], Self::row_to_balance)
+                    .map_err(|e| Error::Database(e.to_string()))?;
+                for row in rows {
+                    Self::merge(&mut balances, row.map_err(|e| Error::Database(e.to_string()))?);
+                }
+                Ok(())
+            })?;
+        }
+
+        let mut result: Vec<LedgerBalance> = balances.into_values().filter(|b| !b.quantity.is_zero()).collect();
+        result.sort_by(|a, b| {
+            (&a.product_type, &a.product_id, &a.lot_id, &a.warehouse_id)
+                .cmp(&(&b.product_type, &b.product_id, &b.lot_id, &b.warehouse_id))
+        });
+        Ok((snapshot, result))
+    }
+
+    fn merge(balances: &mut HashMap<BalanceKey, LedgerBalance>, balance: LedgerBalance) {
+        let key = (
+            balance.product_type.clone(),
+            balance.product_id.clone(),
+            balance.lot_id.clone(),
+            balance.warehouse_id.clone(),
+        );
+        match balances.get_mut(&key) {
+            Some(existing) => {
+                existing.quantity += balance.quantity;
+                if balance.last_movement_at > existing.last_movement_at {
+                    existing.last_movement_at = balance.last_movement_at;
+                }
+            }
+            None => {
+                balances.insert(key, balance);
+            }
+        }
+    }
+
+    fn snapshot_on_or_before(conn: &Connection, date: Option<NaiveDate>) -> Result<Option<NaiveDate>> {
+        let snapshot: Option<String> = conn.query_row(
+            "SELECT MAX(snapshot_date) FROM stock_snapshots WHERE ?1 IS NULL OR snapshot_date <= ?1",
+            [date.map(|d| d.to_string())],
+            |row| row.get(0),
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        snapshot.as_deref().map(Self::parse_date).transpose()
+    }
+
+    fn row_to_balance(row: &rusqlite::Row) -> rusqlite::Result<LedgerBalance> {
+        Ok(LedgerBalance {
+            product_type: row.get(0)?,
+            product_id: row.get(1)?,
+            lot_id: row.get(2)?,
+            warehouse_id: row.get(3)?,
+            quantity: row.get(4)?,
+            last_movement_at: row.get(5)?,
+        })
+    }
+
+    fn parse_date(value: &str) -> Result<NaiveDate> {
+        value
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .ok_or_else(|| Error::Internal(format!("Date invalide en base: {}", value)))
+    }
+}
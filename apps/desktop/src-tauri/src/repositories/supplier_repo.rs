@@ -63,12 +63,12 @@ impl SupplierRepository {
                 "SELECT id, code, name, contact_name, phone, email,
                         address_line1, address_line2, commune, wilaya_code,
                         nif, nis, rc, article_imposition, is_active, created_at
-                 FROM suppliers WHERE is_active = 1 ORDER BY name"
+                 FROM suppliers WHERE is_active = 1 AND deleted_at IS NULL ORDER BY name"
             } else {
                 "SELECT id, code, name, contact_name, phone, email,
                         address_line1, address_line2, commune, wilaya_code,
                         nif, nis, rc, article_imposition, is_active, created_at
-                 FROM suppliers ORDER BY name"
+                 FROM suppliers WHERE deleted_at IS NULL ORDER BY name"
             };
 
             let mut stmt = conn.prepare(sql).map_err(|e| Error::Database(e.to_string()))?;
@@ -90,7 +90,7 @@ impl SupplierRepository {
                 "SELECT id, code, name, contact_name, phone, email,
                         address_line1, address_line2, commune, wilaya_code,
                         nif, nis, rc, article_imposition, is_active, created_at
-                 FROM suppliers WHERE id = ?"
+                 FROM suppliers WHERE id = ? AND deleted_at IS NULL"
             ).map_err(|e| Error::Database(e.to_string()))?;
 
             match stmt.query_row([id], |row| Self::row_to_dto(row)) {
@@ -175,7 +175,7 @@ impl SupplierRepository {
     pub fn generate_code(&self) -> Result<String> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM suppliers",
+                "SELECT COUNT(*) FROM suppliers WHERE deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
@@ -188,7 +188,7 @@ impl SupplierRepository {
     pub fn count(&self) -> Result<u64> {
         self.db.with_connection(|conn| {
             let count: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM suppliers WHERE is_active = 1",
+                "SELECT COUNT(*) FROM suppliers WHERE is_active = 1 AND deleted_at IS NULL",
                 [],
                 |row| row.get(0)
             ).map_err(|e| Error::Database(e.to_string()))?;
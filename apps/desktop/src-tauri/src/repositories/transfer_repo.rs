@@ -0,0 +1,263 @@
+//! Transfer Repository
+//!
+//! Data access for inter-warehouse TransferOrder and TransferOrderLine entities.
+
+use chrono::NaiveDate;
+use manchengo_core::{Error, Money, Qty, Result};
+use manchengo_database::{Database, DocumentSequences, DocumentType};
+use manchengo_domain::stock::{ProductType, TransferLine, TransferStatus};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::sync::Arc;
+
+use crate::dto::{CreateTransferDto, TransferFilter, TransferLineDto, TransferOrderDto};
+
+const TRANSFER_SELECT: &str =
+    "SELECT t.id, t.reference, t.from_warehouse_id, wf.code, t.to_warehouse_id, wt.code,
+            t.status, t.notes, t.shipped_at, t.shipped_by, t.received_at, t.received_by,
+            t.created_at, t.created_by
+     FROM transfer_orders t
+     JOIN warehouses wf ON wf.id = t.from_warehouse_id
+     JOIN warehouses wt ON wt.id = t.to_warehouse_id";
+
+/// Transfer order repository
+pub struct TransferRepository {
+    db: Arc<Database>,
+}
+
+impl TransferRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// List transfers, newest first
+    pub fn list(&self, filter: TransferFilter) -> Result<Vec<TransferOrderDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "{} WHERE (?1 IS NULL OR t.status = ?1)
+                   AND (?2 IS NULL OR t.from_warehouse_id = ?2 OR t.to_warehouse_id = ?2)
+                 ORDER BY t.created_at DESC
+                 LIMIT ?3",
+                TRANSFER_SELECT
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map(
+                params![filter.status, filter.warehouse_id, filter.limit.unwrap_or(100)],
+                Self::row_to_dto,
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                let mut dto = row.map_err(|e| Error::Database(e.to_string()))?;
+                Self::load_lines(conn, &mut dto)?;
+                result.push(dto);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Get transfer with its lines
+    pub fn get(&self, id: &str) -> Result<Option<TransferOrderDto>> {
+        self.db.with_connection(|conn| {
+            let transfer = conn
+                .query_row(&format!("{} WHERE t.id = ?", TRANSFER_SELECT), [id], Self::row_to_dto)
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            match transfer {
+                Some(mut dto) => {
+                    Self::load_lines(conn, &mut dto)?;
+                    Ok(Some(dto))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// Create draft transfer
+    ///
+    /// The reference is allocated in the same transaction and returned.
+    pub fn create(&self, id: &str, date: NaiveDate, data: &CreateTransferDto, user_id: &str) -> Result<String> {
+        self.db.transaction(|conn| {
+            let reference = DocumentSequences::allocate(conn, DocumentType::Transfer, date, id)?.number;
+
+            conn.execute(
+                "INSERT INTO transfer_orders (id, reference, from_warehouse_id, to_warehouse_id, status, notes, created_by)
+                 VALUES (?, ?, ?, ?, 'DRAFT', ?, ?)",
+                params![id, reference, data.from_warehouse_id, data.to_warehouse_id, data.notes, user_id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            Ok(reference)
+        })
+    }
+
+    /// Add picked lot
+    pub fn add_line(&self, transfer_id: &str, line: &TransferLine) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO transfer_order_lines (
+                    id, transfer_id, product_type, product_id, lot_id, lot_number, quantity_shipped, unit_cost
+                 ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    line.id.to_string(),
+                    transfer_id,
+                    if line.product_type == ProductType::Mp { "MP" } else { "PF" },
+                    line.product_id.to_string(),
+                    line.lot_id.to_string(),
+                    line.lot_number,
+                    line.quantity_shipped,
+                    line.unit_cost.centimes(),
+                ],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            conn.execute(
+                "UPDATE transfer_orders SET updated_at = datetime('now') WHERE id = ?",
+                [transfer_id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    /// Remove picked lot
+    pub fn remove_line(&self, transfer_id: &str, line_id: &str) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM transfer_order_lines WHERE id = ? AND transfer_id = ?",
+                params![line_id, transfer_id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            conn.execute(
+                "UPDATE transfer_orders SET updated_at = datetime('now') WHERE id = ?",
+                [transfer_id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    /// Record the lot travelling for a line
+    pub fn set_transit_lot(conn: &Connection, line_id: &str, transit_lot_id: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE transfer_order_lines SET transit_lot_id = ? WHERE id = ?",
+            params![transit_lot_id, line_id],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Mark as shipped
+    pub fn mark_shipped(conn: &Connection, id: &str, user_id: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE transfer_orders SET
+                status = 'SHIPPED', shipped_at = datetime('now'), shipped_by = ?,
+                updated_at = datetime('now')
+             WHERE id = ?",
+            params![user_id, id],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Mark as received with the quantity counted on each line
+    pub fn mark_received(conn: &Connection, id: &str, lines: &[TransferLine], user_id: &str) -> Result<()> {
+        for line in lines {
+            conn.execute(
+                "UPDATE transfer_order_lines SET quantity_received = ?, discrepancy_reason = ? WHERE id = ?",
+                params![line.quantity_received, line.discrepancy_reason, line.id.to_string()],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+        }
+
+        conn.execute(
+            "UPDATE transfer_orders SET
+                status = 'RECEIVED', received_at = datetime('now'), received_by = ?,
+                updated_at = datetime('now')
+             WHERE id = ?",
+            params![user_id, id],
+        ).map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Cancel transfer
+    ///
+    /// The transfer keeps its reference, marked VOIDED in the sequence.
+    pub fn cancel(&self, id: &str) -> Result<()> {
+        self.db.transaction(|conn| {
+            conn.execute(
+                "UPDATE transfer_orders SET status = 'CANCELLED', updated_at = datetime('now') WHERE id = ?",
+                [id],
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let reference: Option<String> = conn
+                .query_row("SELECT reference FROM transfer_orders WHERE id = ?", [id], |row| row.get(0))
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))?;
+            if let Some(reference) = reference {
+                DocumentSequences::void(conn, &reference, "Transfert annule")?;
+            }
+            Ok(())
+        })
+    }
+
+    // Internal helpers
+
+    fn load_lines(conn: &rusqlite::Connection, transfer: &mut TransferOrderDto) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "SELECT tl.id, tl.product_type, tl.product_id, COALESCE(mp.name, pf.name, ''),
+                    tl.lot_id, tl.lot_number, tl.quantity_shipped, tl.quantity_received,
+                    COALESCE(mp.unit, pf.unit, ''), tl.unit_cost, tl.transit_lot_id, tl.discrepancy_reason
+             FROM transfer_order_lines tl
+             LEFT JOIN products_mp mp ON tl.product_type = 'MP' AND mp.id = tl.product_id
+             LEFT JOIN products_pf pf ON tl.product_type = 'PF' AND pf.id = tl.product_id
+             WHERE tl.transfer_id = ?
+             ORDER BY tl.rowid"
+        ).map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([&transfer.id], |row| {
+            Ok(TransferLineDto {
+                id: row.get(0)?,
+                product_type: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                lot_id: row.get(4)?,
+                lot_number: row.get(5)?,
+                quantity_shipped: row.get(6)?,
+                quantity_received: row.get(7)?,
+                unit: row.get(8)?,
+                unit_cost: row.get(9)?,
+                transit_lot_id: row.get(10)?,
+                discrepancy_reason: row.get(11)?,
+            })
+        }).map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut value_shipped = Money::zero();
+        let mut value_received = Money::zero();
+        for row in rows {
+            let line = row.map_err(|e| Error::Database(e.to_string()))?;
+            let unit_cost = Money::from_centimes(line.unit_cost);
+            value_shipped = value_shipped + line.quantity_shipped.value_at(unit_cost);
+            value_received = value_received + line.quantity_received.unwrap_or(Qty::zero()).value_at(unit_cost);
+            transfer.lines.push(line);
+        }
+        transfer.value_shipped = value_shipped.centimes();
+        transfer.value_received = value_received.centimes();
+        Ok(())
+    }
+
+    fn row_to_dto(row: &Row) -> rusqlite::Result<TransferOrderDto> {
+        Ok(TransferOrderDto {
+            id: row.get(0)?,
+            reference: row.get(1)?,
+            from_warehouse_id: row.get(2)?,
+            from_warehouse_code: row.get(3)?,
+            to_warehouse_id: row.get(4)?,
+            to_warehouse_code: row.get(5)?,
+            status: TransferStatus::from_code(&row.get::<_, String>(6)?).unwrap_or(TransferStatus::Draft),
+            notes: row.get(7)?,
+            lines: Vec::new(), // Will be populated separately
+            value_shipped: 0,
+            value_received: 0,
+            shipped_at: row.get(8)?,
+            shipped_by: row.get(9)?,
+            received_at: row.get(10)?,
+            received_by: row.get(11)?,
+            created_at: row.get(12)?,
+            created_by: row.get(13)?,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Valuation Repository
+//!
+//! Reads the movement ledger and product categories for stock valuation.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use manchengo_core::{EntityId, Error, Money, Qty, Result};
+use manchengo_database::archive::FiscalArchive;
+use manchengo_database::Database;
+use manchengo_domain::stock::{ProductType, ValuationMovement};
+use std::sync::Arc;
+
+/// Product with its category, as shown on valuation reports
+#[derive(Debug, Clone)]
+pub struct ValuedProduct {
+    pub product_type: String, // "MP" or "PF"
+    pub id: String,
+    pub code: String,
+    pub name: String,
+    pub unit: String,
+    pub category_id: Option<String>,
+    pub category_code: Option<String>,
+    pub category_name: Option<String>,
+}
+
+/// Valuation repository (read-only)
+pub struct ValuationRepository {
+    db: Arc<Database>,
+}
+
+impl ValuationRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Movements recorded before `before` (an ISO date), oldest first
+    ///
+    /// Transfer legs are internal, except the OUT of a receipt shortage
+    /// (idempotency key `TRF-<line>-ECART`), which is a real loss. A movement
+    /// without cost carries its lot cost. When `before` falls in an archived
+    /// fiscal year, that year is read from its archive, from its opening
+    /// balances on.
+    pub fn movements(
+        &self,
+        product_type: Option<&str>,
+        product_id: Option<&str>,
+        before: &str,
+    ) -> Result<Vec<ValuationMovement>> {
+        self.db.with_connection(|conn| {
+            // Year of the last day valued, the day before `before`
+            let last_day = before
+                .get(..10)
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .and_then(|d| d.pred_opt());
+            let from = match last_day {
+                Some(day) if FiscalArchive::get(conn, day.year())?.is_some() => {
+                    Some(FiscalArchive::year_bounds(day.year()).0)
+                }
+                _ => None,
+            };
+
+            FiscalArchive::with_range(conn, from.as_deref(), Some(before), |archives| {
+                let movements_source = archives.source(conn, "stock_movements")?;
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT m.product_type, COALESCE(m.product_mp_id, m.product_pf_id),
+                            COALESCE(m.lot_mp_id, m.lot_pf_id), m.warehouse_id, m.movement_type,
+                            m.origin = 'TRANSFERT' AND m.idempotency_key NOT LIKE 'TRF-%-ECART',
+                            m.quantity, COALESCE(m.unit_cost, lmp.unit_cost, lpf.unit_cost),
+                            COALESCE(lmp.unit_cost, lpf.unit_cost, m.unit_cost, 0), m.created_at
+                     FROM {} m
+                     LEFT JOIN lots_mp lmp ON lmp.id = m.lot_mp_id
+                     LEFT JOIN lots_pf lpf ON lpf.id = m.lot_pf_id
+                     WHERE m.is_deleted = 0 AND m.created_at < ?1
+                       AND (?2 IS NULL OR m.product_type = ?2)
+                       AND (?3 IS NULL OR m.product_mp_id = ?3 OR m.product_pf_id = ?3)
+                     ORDER BY m.created_at, m.recorded_at",
+                    movements_source
+                )).map_err(|e| Error::Database(e.to_string()))?;
+
+                let rows = stmt.query_map(rusqlite::params![before, product_type, product_id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, bool>(5)?,
+                        row.get::<_, Qty>(6)?,
+                        row.get::<_, Option<i64>>(7)?,
+                        row.get::<_, i64>(8)?,
+                        row.get::<_, String>(9)?,
+                    ))
+                }).map_err(|e| Error::Database(e.to_string()))?;
+
+                let mut result = Vec::new();
+                for row in rows {
+                    let (product_type, product_id, lot_id, warehouse_id, movement_type, internal, quantity, unit_cost, lot_cost, created_at) =
+                        row.map_err(|e| Error::Database(e.to_string()))?;
+                    result.push(ValuationMovement {
+                        product_type: if product_type == "MP" { ProductType::Mp } else { ProductType::Pf },
+                        product_id: Self::parse_id(&product_id)?,
+                        lot_id: lot_id.as_deref().map(Self::parse_id).transpose()?,
+                        warehouse_id: warehouse_id.as_deref().map(Self::parse_id).transpose()?,
+                        is_entry: movement_type == "IN",
+                        internal,
+                        quantity,
+                        unit_cost: unit_cost.map(Money::from_centimes),
+                        lot_cost: Money::from_centimes(lot_cost),
+                        occurred_at: Self::parse_timestamp(&created_at)?,
+                    });
+                }
+                Ok(result)
+            })
+        })
+    }
+
+    /// All products with their category
+    pub fn products(&self) -> Result<Vec<ValuedProduct>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT 'MP', p.id, p.code, p.name, p.unit, p.category_id, c.code, c.name
+                 FROM products_mp p
+                 LEFT JOIN ref_categories c ON c.id = p.category_id
+                 UNION ALL
+                 SELECT 'PF', p.id, p.code, p.name, p.unit, p.category_id, c.code, c.name
+                 FROM products_pf p
+                 LEFT JOIN ref_categories c ON c.id = p.category_id"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([], |row| {
+                Ok(ValuedProduct {
+                    product_type: row.get(0)?,
+                    id: row.get(1)?,
+                    code: row.get(2)?,
+                    name: row.get(3)?,
+                    unit: row.get(4)?,
+                    category_id: row.get(5)?,
+                    category_code: row.get(6)?,
+                    category_name: row.get(7)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    fn parse_id(value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", value)))
+    }
+
+    /// SQLite `datetime('now')` or RFC 3339 timestamp
+    fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+        let normalized = value.get(..19).unwrap_or(value).replace('T', " ");
+        NaiveDateTime::parse_from_str(&normalized, "%Y-%m-%d %H:%M:%S")
+            .map(|at| at.and_utc())
+            .map_err(|_| Error::Internal(format!("Date invalide en base: {}", value)))
+    }
+}
@@ -0,0 +1,406 @@
+//! Warehouse Repository
+//!
+//! Warehouses, their bin locations, per-warehouse stock and thresholds.
+//! Stock per warehouse is the sum of the remaining quantity of its lots.
+
+use manchengo_core::{Error, Qty, Result};
+use manchengo_database::Database;
+use manchengo_domain::stock::{Location, LocationType, Warehouse};
+use rusqlite::{params, Row};
+use std::sync::Arc;
+
+use crate::dto::{LocationDto, PendingPutAwayDto, StockStatus, WarehouseDto, WarehouseStockDto};
+
+const LOCATION_SELECT: &str = "SELECT
+        wl.id, wl.warehouse_id, w.code, wl.code, wl.name, wl.location_type,
+        wl.is_active, wl.qr_code,
+        (SELECT COUNT(*) FROM lots_mp l WHERE l.location_id = wl.id AND l.quantity_remaining > 0)
+      + (SELECT COUNT(*) FROM lots_pf l WHERE l.location_id = wl.id AND l.quantity_remaining > 0)
+     FROM warehouse_locations wl
+     JOIN warehouses w ON w.id = wl.warehouse_id";
+
+/// Warehouse and location repository
+pub struct WarehouseRepository {
+    db: Arc<Database>,
+}
+
+impl WarehouseRepository {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    // =========================================================================
+    // WAREHOUSES
+    // =========================================================================
+
+    /// List warehouses, default first
+    pub fn list_warehouses(&self, active_only: bool) -> Result<Vec<WarehouseDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT w.id, w.code, w.name, w.wilaya_code, w.address, w.is_default, w.is_active,
+                        (SELECT COUNT(*) FROM warehouse_locations wl WHERE wl.warehouse_id = w.id)
+                 FROM warehouses w
+                 WHERE w.deleted_at IS NULL AND (? = 0 OR w.is_active = 1)
+                 ORDER BY w.is_default DESC, w.code"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([active_only], Self::row_to_warehouse)
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Get warehouse by ID
+    pub fn get_warehouse(&self, id: &str) -> Result<Option<WarehouseDto>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT w.id, w.code, w.name, w.wilaya_code, w.address, w.is_default, w.is_active,
+                        (SELECT COUNT(*) FROM warehouse_locations wl WHERE wl.warehouse_id = w.id)
+                 FROM warehouses w
+                 WHERE w.id = ? AND w.deleted_at IS NULL",
+                [id],
+                Self::row_to_warehouse,
+            ) {
+                Ok(dto) => Ok(Some(dto)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Warehouse used when none is given
+    pub fn default_warehouse_id(&self) -> Result<String> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                "SELECT id FROM warehouses WHERE is_default = 1 AND deleted_at IS NULL LIMIT 1",
+                [],
+                |row| row.get(0),
+            ) {
+                Ok(id) => Ok(id),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    Err(Error::Configuration("Aucun entrepot par defaut".to_string()))
+                }
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Create warehouse with its reception and in-transit locations
+    pub fn create_warehouse(&self, warehouse: &Warehouse, user_id: &str) -> Result<()> {
+        self.db.transaction(|tx| {
+            tx.execute(
+                "INSERT INTO warehouses (id, code, name, wilaya_code, address, is_default, is_active, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+                params![
+                    warehouse.id.to_string(),
+                    warehouse.code,
+                    warehouse.name,
+                    warehouse.wilaya_code,
+                    warehouse.address,
+                    warehouse.is_default,
+                    warehouse.is_active,
+                ]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            tx.execute(
+                "INSERT INTO warehouse_locations (id, warehouse_id, code, name, location_type, created_by)
+                 VALUES (lower(hex(randomblob(16))), ?1, 'RECEPTION', 'Quai de reception', 'RECEPTION', ?2),
+                        (lower(hex(randomblob(16))), ?1, 'EN-TRANSIT', 'En transit', 'TRANSIT', ?2)",
+                params![warehouse.id.to_string(), user_id]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    // =========================================================================
+    // LOCATIONS
+    // =========================================================================
+
+    /// List locations of a warehouse
+    pub fn list_locations(&self, warehouse_id: &str) -> Result<Vec<LocationDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "{} WHERE wl.warehouse_id = ? ORDER BY wl.location_type, wl.code",
+                LOCATION_SELECT
+            )).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map([warehouse_id], Self::row_to_location)
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Get location by ID
+    pub fn get_location(&self, id: &str) -> Result<Option<LocationDto>> {
+        self.find_location("wl.id = ?", id)
+    }
+
+    /// Get location by its QR label payload
+    pub fn find_location_by_qr(&self, qr_code: &str) -> Result<Option<LocationDto>> {
+        self.find_location("wl.qr_code = ?", qr_code)
+    }
+
+    /// Reception location of a warehouse, where received lots wait for put-away
+    pub fn reception_location(&self, warehouse_id: &str) -> Result<Option<LocationDto>> {
+        self.first_location_of_type(warehouse_id, LocationType::Reception)
+    }
+
+    /// Virtual location of lots travelling to a warehouse
+    pub fn transit_location(&self, warehouse_id: &str) -> Result<Option<LocationDto>> {
+        self.first_location_of_type(warehouse_id, LocationType::Transit)
+    }
+
+    fn first_location_of_type(&self, warehouse_id: &str, location_type: LocationType) -> Result<Option<LocationDto>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                &format!(
+                    "{} WHERE wl.warehouse_id = ? AND wl.location_type = ? AND wl.is_active = 1
+                     ORDER BY wl.created_at LIMIT 1",
+                    LOCATION_SELECT
+                ),
+                [warehouse_id, location_type.as_str()],
+                Self::row_to_location,
+            ) {
+                Ok(dto) => Ok(Some(dto)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    /// Create location
+    pub fn create_location(&self, location: &Location) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO warehouse_locations (id, warehouse_id, code, name, location_type, is_active, qr_code, created_at, created_by)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    location.id.to_string(),
+                    location.warehouse_id.to_string(),
+                    location.code,
+                    location.name,
+                    location.location_type.as_str(),
+                    location.is_active,
+                    location.qr_code,
+                    location.audit.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    location.audit.created_by.to_string(),
+                ]
+            ).map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(ref err, _)
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    Error::BusinessRule(format!("Emplacement {} deja existant", location.code))
+                }
+                e => Error::Database(e.to_string()),
+            })?;
+            Ok(())
+        })
+    }
+
+    fn find_location(&self, condition: &str, value: &str) -> Result<Option<LocationDto>> {
+        self.db.with_connection(|conn| {
+            match conn.query_row(
+                &format!("{} WHERE {}", LOCATION_SELECT, condition),
+                [value],
+                Self::row_to_location,
+            ) {
+                Ok(dto) => Ok(Some(dto)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(Error::Database(e.to_string())),
+            }
+        })
+    }
+
+    // =========================================================================
+    // PUT-AWAY
+    // =========================================================================
+
+    /// Lots with stock still waiting for put-away, oldest first (lots still
+    /// in transit are not there yet)
+    pub fn list_pending_put_away(&self, warehouse_id: Option<&str>) -> Result<Vec<PendingPutAwayDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT l.id, l.lot_number, 'MP', l.product_mp_id, p.name, l.quantity_remaining, p.unit,
+                        l.warehouse_id, l.location_id, wl.code, l.created_at
+                 FROM lots_mp l
+                 JOIN products_mp p ON p.id = l.product_mp_id
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
+                 WHERE l.put_away_at IS NULL AND l.quantity_remaining > 0
+                   AND COALESCE(wl.location_type, '') <> 'TRANSIT'
+                   AND (?1 IS NULL OR l.warehouse_id = ?1)
+                 UNION ALL
+                 SELECT l.id, l.lot_number, 'PF', l.product_pf_id, p.name, l.quantity_remaining, p.unit,
+                        l.warehouse_id, l.location_id, wl.code, l.created_at
+                 FROM lots_pf l
+                 JOIN products_pf p ON p.id = l.product_pf_id
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
+                 WHERE l.put_away_at IS NULL AND l.quantity_remaining > 0
+                   AND COALESCE(wl.location_type, '') <> 'TRANSIT'
+                   AND (?1 IS NULL OR l.warehouse_id = ?1)
+                 ORDER BY 11"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map(params![warehouse_id], |row| {
+                Ok(PendingPutAwayDto {
+                    lot_id: row.get(0)?,
+                    lot_number: row.get(1)?,
+                    product_type: row.get(2)?,
+                    product_id: row.get(3)?,
+                    product_name: row.get(4)?,
+                    quantity_remaining: row.get(5)?,
+                    unit: row.get(6)?,
+                    warehouse_id: row.get(7)?,
+                    location_id: row.get(8)?,
+                    location_code: row.get(9)?,
+                    received_at: row.get(10)?,
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    // =========================================================================
+    // STOCK PER WAREHOUSE
+    // =========================================================================
+
+    /// Stock of every active product per warehouse
+    ///
+    /// Lots on their way to a warehouse count in its stock as in transit.
+    ///
+    /// Thresholds come from `warehouse_stock_thresholds`; products without one
+    /// are only flagged when out of stock.
+    pub fn stock_by_warehouse(&self, warehouse_id: Option<&str>) -> Result<Vec<WarehouseStockDto>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "WITH lots AS (
+                    SELECT 'MP' AS product_type, product_mp_id AS product_id, warehouse_id,
+                           quantity_remaining, put_away_at, location_id
+                    FROM lots_mp WHERE status NOT IN ('CONSUMED') AND quantity_remaining > 0
+                    UNION ALL
+                    SELECT 'PF', product_pf_id, warehouse_id, quantity_remaining, put_away_at, location_id
+                    FROM lots_pf WHERE status NOT IN ('CONSUMED') AND quantity_remaining > 0
+                 ),
+                 products AS (
+                    SELECT 'MP' AS product_type, id, code, name, unit FROM products_mp WHERE is_active = 1
+                    UNION ALL
+                    SELECT 'PF', id, code, name, unit FROM products_pf WHERE is_active = 1
+                 )
+                 SELECT w.id, w.code, p.product_type, p.id, p.code, p.name, p.unit,
+                        COALESCE(SUM(l.quantity_remaining), 0),
+                        COALESCE(SUM(CASE WHEN l.put_away_at IS NULL AND COALESCE(wl.location_type, '') <> 'TRANSIT'
+                                          THEN l.quantity_remaining END), 0),
+                        COUNT(l.product_id),
+                        COALESCE(t.min_stock, 0), COALESCE(t.reorder_point, 0),
+                        COALESCE(SUM(CASE WHEN wl.location_type = 'TRANSIT' THEN l.quantity_remaining END), 0)
+                 FROM warehouses w
+                 CROSS JOIN products p
+                 LEFT JOIN lots l
+                   ON l.warehouse_id = w.id AND l.product_type = p.product_type AND l.product_id = p.id
+                 LEFT JOIN warehouse_locations wl ON wl.id = l.location_id
+                 LEFT JOIN warehouse_stock_thresholds t
+                   ON t.warehouse_id = w.id AND t.product_type = p.product_type AND t.product_id = p.id
+                 WHERE w.is_active = 1 AND w.deleted_at IS NULL
+                   AND (?1 IS NULL OR w.id = ?1)
+                 GROUP BY w.id, p.product_type, p.id
+                 HAVING COUNT(l.product_id) > 0 OR t.product_id IS NOT NULL
+                 ORDER BY w.is_default DESC, w.code, p.product_type, p.code"
+            ).map_err(|e| Error::Database(e.to_string()))?;
+
+            let rows = stmt.query_map(params![warehouse_id], |row| {
+                let current_stock: Qty = row.get(7)?;
+                let min_stock = row.get::<_, Qty>(10)?.as_f64();
+                let reorder_point = row.get::<_, Qty>(11)?.as_f64();
+                Ok(WarehouseStockDto {
+                    warehouse_id: row.get(0)?,
+                    warehouse_code: row.get(1)?,
+                    product_type: row.get(2)?,
+                    product_id: row.get(3)?,
+                    product_code: row.get(4)?,
+                    product_name: row.get(5)?,
+                    unit: row.get(6)?,
+                    current_stock,
+                    pending_put_away: row.get(8)?,
+                    in_transit: row.get(12)?,
+                    lots_count: row.get(9)?,
+                    min_stock,
+                    reorder_point,
+                    status: StockStatus::from_levels(current_stock, min_stock, reorder_point),
+                })
+            }).map_err(|e| Error::Database(e.to_string()))?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Set the minimum stock of a product in a warehouse
+    pub fn set_threshold(
+        &self,
+        warehouse_id: &str,
+        product_type: &str,
+        product_id: &str,
+        min_stock: Qty,
+        reorder_point: Qty,
+    ) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO warehouse_stock_thresholds (warehouse_id, product_type, product_id, min_stock, reorder_point, updated_at)
+                 VALUES (?, ?, ?, ?, ?, datetime('now'))
+                 ON CONFLICT (warehouse_id, product_type, product_id) DO UPDATE SET
+                    min_stock = excluded.min_stock,
+                    reorder_point = excluded.reorder_point,
+                    updated_at = excluded.updated_at",
+                params![warehouse_id, product_type, product_id, min_stock, reorder_point]
+            ).map_err(|e| Error::Database(e.to_string()))?;
+            Ok(())
+        })
+    }
+
+    fn row_to_warehouse(row: &Row) -> rusqlite::Result<WarehouseDto> {
+        Ok(WarehouseDto {
+            id: row.get(0)?,
+            code: row.get(1)?,
+            name: row.get(2)?,
+            wilaya_code: row.get(3)?,
+            address: row.get(4)?,
+            is_default: row.get(5)?,
+            is_active: row.get(6)?,
+            locations_count: row.get(7)?,
+        })
+    }
+
+    fn row_to_location(row: &Row) -> rusqlite::Result<LocationDto> {
+        let location_type: String = row.get(5)?;
+        let location_type = LocationType::from_code(&location_type).unwrap_or(LocationType::Storage);
+        Ok(LocationDto {
+            id: row.get(0)?,
+            warehouse_id: row.get(1)?,
+            warehouse_code: row.get(2)?,
+            code: row.get(3)?,
+            name: row.get(4)?,
+            location_type,
+            is_active: row.get(6)?,
+            is_pickable: location_type.is_pickable(),
+            qr_code: row.get(7)?,
+            lots_count: row.get(8)?,
+        })
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! Business logic for procurement (purchase orders, supplier management).
 
-use manchengo_core::{EntityId, Error, Result};
+use chrono::NaiveDate;
+use manchengo_core::{parse_rates_csv, Currency, EntityId, Error, ExchangeRate, RateSource, Result, SharedClock};
 use manchengo_database::Database;
 use manchengo_sync::EventStore;
 use std::sync::Arc;
@@ -10,7 +11,7 @@ use tracing::info;
 
 use crate::dto::appro::*;
 use crate::dto::{CreateReceptionDto, ReceptionLineDto};
-use crate::repositories::{PurchaseOrderRepository, SupplierRepository};
+use crate::repositories::{ExchangeRateRepository, LotRepository, PurchaseOrderRepository, SupplierRepository};
 use crate::services::StockService;
 
 /// Appro service for procurement management
@@ -19,23 +20,33 @@ pub struct ApproService {
     event_store: Arc<EventStore>,
     po_repo: Arc<PurchaseOrderRepository>,
     supplier_repo: Arc<SupplierRepository>,
+    lot_repo: Arc<LotRepository>,
+    exchange_rate_repo: Arc<ExchangeRateRepository>,
     stock_service: Arc<StockService>,
+    clock: SharedClock,
 }
 
 impl ApproService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db: Arc<Database>,
         event_store: Arc<EventStore>,
         po_repo: Arc<PurchaseOrderRepository>,
         supplier_repo: Arc<SupplierRepository>,
+        lot_repo: Arc<LotRepository>,
+        exchange_rate_repo: Arc<ExchangeRateRepository>,
         stock_service: Arc<StockService>,
+        clock: SharedClock,
     ) -> Self {
         Self {
             db,
             event_store,
             po_repo,
             supplier_repo,
+            lot_repo,
+            exchange_rate_repo,
             stock_service,
+            clock,
         }
     }
 
@@ -56,9 +67,13 @@ impl ApproService {
     /// Create new purchase order
     pub fn create_order(&self, data: CreatePurchaseOrderDto) -> Result<PurchaseOrderDto> {
         let id = EntityId::new().to_string();
-        let reference = self.po_repo.generate_reference()?;
-
-        self.po_repo.create(&id, &reference, &data)?;
+        let order_date = self.clock.today();
+        let exchange_rate = if data.currency.is_local() {
+            None
+        } else {
+            Some(self.exchange_rate_repo.rate_on(data.currency, order_date)?)
+        };
+        let reference = self.po_repo.create(&id, order_date, &data, exchange_rate.as_ref())?;
 
         info!("Created purchase order {} for supplier {}", reference, data.supplier_id);
 
@@ -166,7 +181,10 @@ impl ApproService {
         // Create reception via stock service
         let reception_data = CreateReceptionDto {
             supplier_id: order.supplier_id.clone(),
-            date: chrono::Utc::now().to_rfc3339(),
+            date: self.clock.now().to_rfc3339(),
+            currency: order.currency,
+            warehouse_id: data.warehouse_id.clone(),
+            location_id: None,
             bl_number: Some(format!("BL-{}", order.reference)),
             note: Some(format!("Reception from BC {}", order.reference)),
             lines: reception_lines,
@@ -231,7 +249,7 @@ impl ApproService {
         })?;
 
         let total_orders = orders.len() as i32;
-        let total_amount: i64 = orders.iter().map(|o| o.total_amount).sum();
+        let total_amount: i64 = orders.iter().map(|o| o.total_amount_dzd).sum();
 
         // Calculate on-time deliveries (simplified)
         let received_orders: Vec<_> = orders
@@ -271,6 +289,86 @@ impl ApproService {
         })
     }
 
+    /// Supplier statement: received lots in their original currency, with totals per currency
+    pub fn get_supplier_statement(
+        &self,
+        supplier_id: &str,
+        from_date: Option<String>,
+        to_date: Option<String>,
+    ) -> Result<SupplierStatementDto> {
+        let supplier = self
+            .supplier_repo
+            .get(supplier_id)?
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "Supplier".to_string(),
+                id: supplier_id.to_string(),
+            })?;
+
+        let lines = self
+            .lot_repo
+            .list_mp_by_supplier(supplier_id, from_date.as_deref(), to_date.as_deref())?;
+
+        let mut totals: Vec<SupplierStatementTotalDto> = Vec::new();
+        for line in &lines {
+            match totals.iter_mut().find(|t| t.currency == line.currency) {
+                Some(total) => {
+                    total.total_currency += line.total_currency;
+                    total.total_dzd += line.total_dzd;
+                }
+                None => totals.push(SupplierStatementTotalDto {
+                    currency: line.currency,
+                    total_currency: line.total_currency,
+                    total_dzd: line.total_dzd,
+                }),
+            }
+        }
+        totals.sort_by_key(|t| t.currency);
+
+        Ok(SupplierStatementDto {
+            supplier_id: supplier_id.to_string(),
+            supplier_name: supplier.name,
+            from_date,
+            to_date,
+            lines,
+            totals,
+        })
+    }
+
+    // =========================================================================
+    // EXCHANGE RATES
+    // =========================================================================
+
+    /// List exchange rates, newest first
+    pub fn list_exchange_rates(&self, currency: Option<Currency>, limit: Option<u32>) -> Result<Vec<ExchangeRate>> {
+        self.exchange_rate_repo.list(currency, limit.unwrap_or(100))
+    }
+
+    /// Enter a rate by hand (replaces the rate of the same day)
+    pub fn set_exchange_rate(&self, data: SetExchangeRateDto, user_id: &str) -> Result<ExchangeRate> {
+        let rate_date = NaiveDate::parse_from_str(&data.rate_date, "%Y-%m-%d").map_err(|_| Error::Validation {
+            field: "rate_date".to_string(),
+            message: format!("Date invalide: {}", data.rate_date),
+        })?;
+        let rate = ExchangeRate::new(data.currency, rate_date, data.rate, RateSource::Manual)?;
+
+        self.exchange_rate_repo.save(&[rate], user_id)?;
+        info!("Exchange rate {} on {} set to {}", rate.currency, rate.rate_date, rate.rate_dzd());
+        Ok(rate)
+    }
+
+    /// Import rates from a CSV file (`date;currency;rate`), all or nothing
+    pub fn import_exchange_rates(&self, content: &str, user_id: &str) -> Result<ExchangeRateImportDto> {
+        let rates = parse_rates_csv(content)?;
+        let imported = self.exchange_rate_repo.save(&rates, user_id)?;
+
+        info!("Imported {} exchange rates", imported);
+        Ok(ExchangeRateImportDto {
+            imported,
+            first_date: rates.iter().map(|r| r.rate_date).min().map(|d| d.to_string()),
+            last_date: rates.iter().map(|r| r.rate_date).max().map(|d| d.to_string()),
+        })
+    }
+
     // =========================================================================
     // DASHBOARD
     // =========================================================================
@@ -0,0 +1,221 @@
+//! Fiscal Archive Service
+//!
+//! Year-end archiving of closed fiscal years:
+//! - Stock movements, settled invoices (lines, payments) and synced events
+//!   move to a read-only SQLite file per year
+//! - Each lot keeps an opening balance movement in the live database, so
+//!   lot quantities still match their ledger
+//! - Open invoices (DRAFT, VALIDATED) stay live until settled
+
+use chrono::Datelike;
+use manchengo_core::{EntityId, Error, Qty, Result, SharedClock};
+use manchengo_database::archive::{ArchivedTable, FiscalArchive};
+use manchengo_database::Database;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::core::ActivityTracker;
+use crate::dto::{ArchiveYearResultDto, FiscalArchiveDto};
+
+/// Settled invoices of the year (?1 = year start, ?2 = next year start)
+const SETTLED_INVOICES: &str = "created_at >= ?1 AND created_at < ?2 AND status IN ('PAID', 'VOIDED')";
+
+/// Fiscal archive service
+pub struct ArchiveService {
+    db: Arc<Database>,
+    activity: Arc<ActivityTracker>,
+    archive_dir: PathBuf,
+    clock: SharedClock,
+}
+
+impl ArchiveService {
+    pub fn new(db: Arc<Database>, activity: Arc<ActivityTracker>, archive_dir: PathBuf, clock: SharedClock) -> Self {
+        Self {
+            db,
+            activity,
+            archive_dir,
+            clock,
+        }
+    }
+
+    /// List archived fiscal years
+    pub fn list(&self) -> Result<Vec<FiscalArchiveDto>> {
+        let archives = self.db.with_connection(FiscalArchive::list)?;
+
+        Ok(archives
+            .into_iter()
+            .map(|a| FiscalArchiveDto {
+                fiscal_year: a.fiscal_year,
+                file_path: a.file_path,
+                rows_archived: a.rows_archived,
+                archived_at: a.archived_at,
+                archived_by: a.archived_by,
+            })
+            .collect())
+    }
+
+    /// Archive a closed fiscal year
+    pub fn archive_year(&self, year: i32, user_id: &str) -> Result<ArchiveYearResultDto> {
+        if year >= self.clock.today().year() {
+            return Err(Error::Validation {
+                field: "year".to_string(),
+                message: format!("L'exercice {} n'est pas clos", year),
+            });
+        }
+
+        if let Some(reason) = self.activity.busy_reason() {
+            return Err(Error::BusinessRule(format!("Archivage impossible: {}", reason)));
+        }
+
+        std::fs::create_dir_all(&self.archive_dir).map_err(|e| Error::Database(e.to_string()))?;
+        let path = self.archive_dir.join(FiscalArchive::file_name(year));
+
+        let mut opening_balances = 0u32;
+        let rows_archived = self.db.with_connection_mut(|conn| {
+            FiscalArchive::archive_year(conn, year, &path, &Self::archived_tables(), user_id, |tx| {
+                opening_balances = Self::post_opening_balances(tx, year, user_id)?;
+                Ok(())
+            })
+        })?;
+
+        info!(
+            "Fiscal year {} archived by {}: {} rows, {} opening balances",
+            year, user_id, rows_archived, opening_balances
+        );
+
+        Ok(ArchiveYearResultDto {
+            fiscal_year: year,
+            file_path: path.to_string_lossy().to_string(),
+            rows_archived,
+            opening_balances,
+        })
+    }
+
+    // =========================================================================
+    // HELPERS
+    // =========================================================================
+
+    /// Tables moved to the archive, children before parents
+    fn archived_tables() -> Vec<ArchivedTable> {
+        vec![
+            ArchivedTable {
+                table: "invoice_lines",
+                filter: format!("invoice_id IN (SELECT id FROM main.invoices WHERE {})", SETTLED_INVOICES),
+            },
+            ArchivedTable {
+                table: "payments",
+                filter: format!("invoice_id IN (SELECT id FROM main.invoices WHERE {})", SETTLED_INVOICES),
+            },
+            ArchivedTable {
+                table: "invoices",
+                filter: SETTLED_INVOICES.to_string(),
+            },
+            ArchivedTable {
+                table: "stock_movements",
+                filter: "created_at >= ?1 AND created_at < ?2".to_string(),
+            },
+            // Pending uploads and the latest event of each aggregate (version anchor) stay live
+            ArchivedTable {
+                table: "_events",
+                filter: "occurred_at >= ?1 AND occurred_at < ?2
+                         AND synced = 1
+                         AND id NOT IN (SELECT event_id FROM main._sync_queue)
+                         AND version < (
+                             SELECT MAX(e2.version) FROM main._events e2
+                             WHERE e2.aggregate_type = _events.aggregate_type
+                               AND e2.aggregate_id = _events.aggregate_id
+                         )"
+                    .to_string(),
+            },
+        ]
+    }
+
+    /// Post one opening movement per lot and warehouse carrying its archived
+    /// net balance
+    ///
+    /// The opening movement keeps the warehouse of the balance and the
+    /// location of the last movement there (SQLite takes bare columns from
+    /// the `MAX(rowid)` row), so per-warehouse stock survives the archive.
+    fn post_opening_balances(tx: &rusqlite::Transaction, year: i32, user_id: &str) -> Result<u32> {
+        let (start, end) = FiscalArchive::year_bounds(year);
+        let opened_at = format!("{} 00:00:00", end);
+
+        let mut stmt = tx
+            .prepare(
+                "SELECT product_type, product_mp_id, lot_mp_id, product_pf_id, lot_pf_id, warehouse_id,
+                        SUM(CASE WHEN movement_type = 'IN' THEN quantity ELSE -quantity END) as balance,
+                        location_id, MAX(rowid)
+                 FROM stock_movements
+                 WHERE created_at >= ?1 AND created_at < ?2 AND is_deleted = 0
+                 GROUP BY product_type, product_mp_id, lot_mp_id, product_pf_id, lot_pf_id, warehouse_id",
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let balances = stmt
+            .query_map([&start, &end], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Qty>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                ))
+            })
+            .map_err(|e| Error::Database(e.to_string()))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut posted = 0u32;
+        for (product_type, product_mp_id, lot_mp_id, product_pf_id, lot_pf_id, warehouse_id, balance, location_id) in balances {
+            if balance.is_zero() {
+                continue;
+            }
+
+            let key_id = lot_mp_id
+                .as_ref()
+                .or(lot_pf_id.as_ref())
+                .or(product_mp_id.as_ref())
+                .or(product_pf_id.as_ref())
+                .cloned()
+                .unwrap_or_default();
+            let idempotency_key = match &warehouse_id {
+                Some(warehouse_id) => format!("OPEN-{}-{}-{}-{}", year + 1, product_type, key_id, warehouse_id),
+                None => format!("OPEN-{}-{}-{}", year + 1, product_type, key_id),
+            };
+            tx.execute(
+                "INSERT INTO stock_movements (
+                    id, movement_type, product_type, product_mp_id, lot_mp_id,
+                    product_pf_id, lot_pf_id, quantity, unit_cost, origin,
+                    reference_type, reference_id, user_id, idempotency_key, note,
+                    warehouse_id, location_id, created_at, recorded_at, is_deleted
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, NULL, 'OUVERTURE', 'FISCAL_YEAR', ?, ?, ?, ?, ?, ?, ?, datetime('now'), 0)",
+                rusqlite::params![
+                    EntityId::new().to_string(),
+                    if balance.is_positive() { "IN" } else { "OUT" },
+                    product_type,
+                    product_mp_id,
+                    lot_mp_id,
+                    product_pf_id,
+                    lot_pf_id,
+                    balance.abs(),
+                    year.to_string(),
+                    user_id,
+                    idempotency_key,
+                    format!("Solde d'ouverture {} (exercice {} archive)", year + 1, year),
+                    warehouse_id,
+                    location_id,
+                    opened_at,
+                ],
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+            posted += 1;
+        }
+
+        Ok(posted)
+    }
+}
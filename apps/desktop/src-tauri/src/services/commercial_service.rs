@@ -2,7 +2,7 @@
 //!
 //! Business logic for clients and pricing.
 
-use manchengo_core::{EntityId, Error, Result};
+use manchengo_core::{fiscal_id, EntityId, Error, Result};
 use manchengo_database::Database;
 use manchengo_sync::EventStore;
 use std::sync::Arc;
@@ -10,12 +10,14 @@ use tracing::info;
 
 use crate::dto::commercial::*;
 use crate::repositories::ClientRepository;
+use crate::services::TrashService;
 
 /// Commercial service for client management
 pub struct CommercialService {
     db: Arc<Database>,
     event_store: Arc<EventStore>,
     client_repo: Arc<ClientRepository>,
+    trash_service: Arc<TrashService>,
 }
 
 impl CommercialService {
@@ -23,11 +25,13 @@ impl CommercialService {
         db: Arc<Database>,
         event_store: Arc<EventStore>,
         client_repo: Arc<ClientRepository>,
+        trash_service: Arc<TrashService>,
     ) -> Self {
         Self {
             db,
             event_store,
             client_repo,
+            trash_service,
         }
     }
 
@@ -47,6 +51,9 @@ impl CommercialService {
 
     /// Create new client
     pub fn create_client(&self, data: CreateClientDto) -> Result<ClientDto> {
+        Self::check_fiscal_identity(&data)?;
+        Self::check_min_shelf_life(&data)?;
+
         let id = EntityId::new().to_string();
         let code = self.client_repo.generate_code()?;
 
@@ -64,6 +71,9 @@ impl CommercialService {
 
     /// Update client
     pub fn update_client(&self, id: &str, data: CreateClientDto) -> Result<ClientDto> {
+        Self::check_fiscal_identity(&data)?;
+        Self::check_min_shelf_life(&data)?;
+
         self.client_repo.update(id, &data)?;
 
         info!("Updated client {}", id);
@@ -76,13 +86,27 @@ impl CommercialService {
             })
     }
 
-    /// Delete client
-    pub fn delete_client(&self, id: &str) -> Result<()> {
-        self.client_repo.delete(id)?;
-        info!("Deleted client {}", id);
+    /// Reject malformed NIF, RC and article d'imposition
+    fn check_fiscal_identity(data: &CreateClientDto) -> Result<()> {
+        fiscal_id::check_identifiers(data.nif.as_deref(), None, data.rc.as_deref(), data.ai.as_deref())
+    }
+
+    /// Reject a zero minimum shelf life (unset means no minimum)
+    fn check_min_shelf_life(data: &CreateClientDto) -> Result<()> {
+        if data.min_shelf_life_days == Some(0) {
+            return Err(Error::Validation {
+                field: "min_shelf_life_days".to_string(),
+                message: "La duree de vie minimale doit etre positive".to_string(),
+            });
+        }
         Ok(())
     }
 
+    /// Delete client (moved to trash, restorable)
+    pub fn delete_client(&self, id: &str, user_id: &str) -> Result<()> {
+        self.trash_service.delete("Client", id, user_id, None)
+    }
+
     /// Get client balance
     pub fn get_client_balance(&self, id: &str) -> Result<ClientBalanceDto> {
         let client = self
@@ -130,7 +154,7 @@ impl CommercialService {
                 "SELECT id, name, code, is_default, discount_percentage, is_active,
                         valid_from, valid_to, created_at
                  FROM price_lists
-                 WHERE is_deleted = 0",
+                 WHERE deleted_at IS NULL",
             );
 
             if active_only {
@@ -185,7 +209,7 @@ impl CommercialService {
                 .prepare(
                     "SELECT pf.id, pf.name, pf.code, pf.price_ht
                      FROM products_pf pf
-                     WHERE pf.is_deleted = 0 AND pf.is_active = 1
+                     WHERE pf.deleted_at IS NULL AND pf.is_active = 1
                      ORDER BY pf.name",
                 )
                 .map_err(|e| Error::Database(e.to_string()))?;
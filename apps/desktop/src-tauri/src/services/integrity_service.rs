@@ -0,0 +1,303 @@
+//! Stock Ledger Integrity Service
+//!
+//! Lot quantities (`quantity_remaining`) are updated separately from the
+//! `stock_movements` ledger, so both can drift. This service:
+//! - Recomputes each lot balance from its movements
+//! - Flags mismatches, negative balances, zero lots not CONSUMED, orphan movements
+//! - Posts corrective adjustment movements once approved by an admin
+
+use manchengo_core::{EntityId, Error, Qty, Result, SharedClock};
+use manchengo_database::Database;
+use manchengo_domain::stock::{
+    LedgerIntegrityChecker, LedgerIssueKind, LedgerRepair, LotLedgerSnapshot,
+    LotStatus as DomainLotStatus, ProductType,
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::dto::*;
+use crate::repositories::{LotRepository, MovementRepository};
+
+/// Ledger integrity service
+pub struct IntegrityService {
+    db: Arc<Database>,
+    lot_repo: Arc<LotRepository>,
+    movement_repo: Arc<MovementRepository>,
+    clock: SharedClock,
+}
+
+impl IntegrityService {
+    pub fn new(
+        db: Arc<Database>,
+        lot_repo: Arc<LotRepository>,
+        movement_repo: Arc<MovementRepository>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            db,
+            lot_repo,
+            movement_repo,
+            clock,
+        }
+    }
+
+    // =========================================================================
+    // CHECK
+    // =========================================================================
+
+    /// Run the full integrity check (read-only)
+    pub fn check(&self) -> Result<LedgerIntegrityReportDto> {
+        let lots = self.lot_repo.ledger_balances()?;
+        let orphans = self.movement_repo.find_orphans()?;
+        let movements_checked = self.movement_repo.count()?;
+
+        let mut issues = Vec::new();
+
+        for lot in &lots {
+            let snapshot = Self::to_snapshot(lot);
+            let repairable = !LedgerIntegrityChecker::plan_repairs(&snapshot).is_empty();
+
+            for kind in LedgerIntegrityChecker::check_lot(&snapshot) {
+                issues.push(LedgerIssueDto {
+                    kind: kind.as_str().to_string(),
+                    product_type: lot.product_type.clone(),
+                    product_id: Some(lot.product_id.clone()),
+                    product_name: lot.product_name.clone(),
+                    lot_id: Some(lot.lot_id.clone()),
+                    lot_number: Some(lot.lot_number.clone()),
+                    movement_id: None,
+                    quantity_remaining: Some(lot.quantity_remaining),
+                    ledger_balance: Some(lot.ledger_balance),
+                    drift: snapshot.drift(),
+                    repairable,
+                    message: Self::describe(kind, lot),
+                });
+            }
+        }
+
+        for orphan in &orphans {
+            issues.push(LedgerIssueDto {
+                kind: LedgerIssueKind::OrphanMovement.as_str().to_string(),
+                product_type: orphan.product_type.clone(),
+                product_id: orphan.product_id.clone(),
+                product_name: None,
+                lot_id: orphan.lot_id.clone(),
+                lot_number: None,
+                movement_id: Some(orphan.movement_id.clone()),
+                quantity_remaining: None,
+                ledger_balance: None,
+                drift: Qty::zero(),
+                repairable: false,
+                message: format!(
+                    "Mouvement {} orphelin ({}): {} {}",
+                    orphan.movement_id, orphan.reason, orphan.movement_type, orphan.quantity
+                ),
+            });
+        }
+
+        let count = |kind: LedgerIssueKind| {
+            issues.iter().filter(|i| i.kind == kind.as_str()).count() as u32
+        };
+
+        let report = LedgerIntegrityReportDto {
+            checked_at: self.clock.now().to_rfc3339(),
+            lots_checked: lots.len() as u32,
+            movements_checked,
+            total_mismatches: count(LedgerIssueKind::BalanceMismatch),
+            total_negative: count(LedgerIssueKind::NegativeBalance),
+            total_zero_not_consumed: count(LedgerIssueKind::ZeroNotConsumed),
+            total_orphans: count(LedgerIssueKind::OrphanMovement),
+            is_consistent: issues.is_empty(),
+            issues,
+        };
+
+        if report.is_consistent {
+            info!("Ledger integrity: {} lots checked, no issue", report.lots_checked);
+        } else {
+            warn!(
+                "Ledger integrity: {} issues on {} lots ({} orphan movements)",
+                report.issues.len(),
+                report.lots_checked,
+                report.total_orphans
+            );
+        }
+
+        Ok(report)
+    }
+
+    // =========================================================================
+    // REPAIR (ADMIN APPROVAL)
+    // =========================================================================
+
+    /// Apply corrective actions on the approved lots.
+    ///
+    /// The ledger is never rewritten: drifts are corrected with adjustment
+    /// movements (origin INVENTAIRE, reference INTEGRITY_REPAIR).
+    pub fn repair(&self, data: RepairLedgerDto, user_id: &str) -> Result<LedgerRepairResultDto> {
+        if data.lot_ids.is_empty() {
+            return Err(Error::Validation {
+                field: "lot_ids".to_string(),
+                message: "Aucun lot selectionne".to_string(),
+            });
+        }
+
+        if data.reason.len() < 5 {
+            return Err(Error::Validation {
+                field: "reason".to_string(),
+                message: "Raison trop courte (min 5 caracteres)".to_string(),
+            });
+        }
+
+        let lots: Vec<LotLedgerDto> = self
+            .lot_repo
+            .ledger_balances()?
+            .into_iter()
+            .filter(|l| data.lot_ids.contains(&l.lot_id))
+            .collect();
+
+        let skipped: Vec<String> = data
+            .lot_ids
+            .iter()
+            .filter(|id| !lots.iter().any(|l| &l.lot_id == *id))
+            .cloned()
+            .collect();
+
+        let repaired_at = self.clock.now();
+        let created_at = MovementRepository::timestamp(repaired_at);
+        let mut movements_created = Vec::new();
+        let mut lots_repaired = 0;
+        let mut lots_marked_consumed = 0;
+        let mut lots_clamped = 0;
+
+        self.db.transaction(|tx| {
+            for lot in &lots {
+                let repairs = LedgerIntegrityChecker::plan_repairs(&Self::to_snapshot(lot));
+                if repairs.is_empty() {
+                    continue;
+                }
+
+                let (lot_table, product_column, lot_column) = if lot.product_type == "MP" {
+                    ("lots_mp", "product_mp_id", "lot_mp_id")
+                } else {
+                    ("lots_pf", "product_pf_id", "lot_pf_id")
+                };
+
+                for repair in repairs {
+                    match repair {
+                        LedgerRepair::AdjustLedger { quantity } => {
+                            let movement_id = EntityId::new().to_string();
+                            let idempotency_key = format!(
+                                "INTEG-{}-{}",
+                                lot.lot_id,
+                                repaired_at.timestamp_millis()
+                            );
+
+                            tx.execute(
+                                &format!(
+                                    "INSERT INTO stock_movements (
+                                        id, movement_type, product_type, {}, {},
+                                        quantity, unit_cost, origin, reference_type, reference_id,
+                                        user_id, idempotency_key, note, created_at, recorded_at, is_deleted
+                                    ) VALUES (?, ?, ?, ?, ?, ?, NULL, 'INVENTAIRE', 'INTEGRITY_REPAIR', NULL, ?, ?, ?, ?, datetime('now'), 0)",
+                                    product_column, lot_column
+                                ),
+                                rusqlite::params![
+                                    movement_id,
+                                    if quantity.is_positive() { "IN" } else { "OUT" },
+                                    lot.product_type,
+                                    lot.product_id,
+                                    lot.lot_id,
+                                    quantity.abs(),
+                                    user_id,
+                                    idempotency_key,
+                                    data.reason,
+                                    created_at,
+                                ],
+                            )
+                            .map_err(|e| Error::Database(e.to_string()))?;
+
+                            movements_created.push(movement_id);
+                        }
+                        LedgerRepair::ClampLotToZero => {
+                            tx.execute(
+                                &format!(
+                                    "UPDATE {} SET quantity_remaining = 0, updated_at = datetime('now') WHERE id = ?",
+                                    lot_table
+                                ),
+                                [&lot.lot_id],
+                            )
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                            lots_clamped += 1;
+                        }
+                        LedgerRepair::MarkConsumed => {
+                            tx.execute(
+                                &format!(
+                                    "UPDATE {} SET status = 'CONSUMED', updated_at = datetime('now') WHERE id = ?",
+                                    lot_table
+                                ),
+                                [&lot.lot_id],
+                            )
+                            .map_err(|e| Error::Database(e.to_string()))?;
+                            lots_marked_consumed += 1;
+                        }
+                    }
+                }
+
+                lots_repaired += 1;
+            }
+            Ok(())
+        })?;
+
+        warn!(
+            "Ledger repair by {}: {} lots, {} adjustment movements (reason: {})",
+            user_id,
+            lots_repaired,
+            movements_created.len(),
+            data.reason
+        );
+
+        Ok(LedgerRepairResultDto {
+            lots_repaired,
+            movements_created,
+            lots_marked_consumed,
+            lots_clamped,
+            skipped,
+            repaired_at: repaired_at.to_rfc3339(),
+            repaired_by: user_id.to_string(),
+        })
+    }
+
+    // =========================================================================
+    // HELPERS
+    // =========================================================================
+
+    fn to_snapshot(lot: &LotLedgerDto) -> LotLedgerSnapshot {
+        LotLedgerSnapshot {
+            lot_id: lot.lot_id.parse().unwrap_or_default(),
+            product_type: if lot.product_type == "MP" { ProductType::Mp } else { ProductType::Pf },
+            product_id: lot.product_id.parse().unwrap_or_default(),
+            status: DomainLotStatus::from_str(&lot.status).unwrap_or(DomainLotStatus::Available),
+            quantity_remaining: lot.quantity_remaining,
+            ledger_balance: lot.ledger_balance,
+            movements_count: lot.movements_count,
+        }
+    }
+
+    fn describe(kind: LedgerIssueKind, lot: &LotLedgerDto) -> String {
+        match kind {
+            LedgerIssueKind::BalanceMismatch => format!(
+                "Lot {}: quantite {} differente du solde des mouvements {}",
+                lot.lot_number, lot.quantity_remaining, lot.ledger_balance
+            ),
+            LedgerIssueKind::NegativeBalance => format!(
+                "Lot {}: solde negatif (lot {}, mouvements {})",
+                lot.lot_number, lot.quantity_remaining, lot.ledger_balance
+            ),
+            LedgerIssueKind::ZeroNotConsumed => format!(
+                "Lot {}: quantite nulle mais statut {}",
+                lot.lot_number, lot.status
+            ),
+            LedgerIssueKind::OrphanMovement => format!("Lot {}: mouvement orphelin", lot.lot_number),
+        }
+    }
+}
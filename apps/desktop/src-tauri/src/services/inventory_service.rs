@@ -0,0 +1,334 @@
+//! Inventory Service
+//!
+//! Business logic for physical inventory sessions:
+//! - Opening freezes the theoretical quantity of every lot of the warehouse
+//! - Counting by lot QR scan or manual entry, blind if asked, with a second
+//!   count for large variances
+//! - Variance report valued at lot cost, then manager approval posting the
+//!   adjustment movements in one batch
+
+use manchengo_core::{AuditInfo, EntityId, Error, Money, Qty, Result, SharedClock};
+use manchengo_domain::stock::{
+    CountMethod, InventoryLine, InventorySession, InventoryStatus, ProductType,
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::dto::*;
+use crate::repositories::{InventoryRepository, LotRepository, MovementRepository, WarehouseRepository};
+
+/// Variance (% of theoretical) above which a lot is counted twice, by default
+const DEFAULT_RECOUNT_THRESHOLD_PCT: f64 = 10.0;
+
+/// Inventory service for stock-take sessions
+pub struct InventoryService {
+    inventory_repo: Arc<InventoryRepository>,
+    lot_repo: Arc<LotRepository>,
+    movement_repo: Arc<MovementRepository>,
+    warehouse_repo: Arc<WarehouseRepository>,
+    clock: SharedClock,
+}
+
+impl InventoryService {
+    pub fn new(
+        inventory_repo: Arc<InventoryRepository>,
+        lot_repo: Arc<LotRepository>,
+        movement_repo: Arc<MovementRepository>,
+        warehouse_repo: Arc<WarehouseRepository>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            inventory_repo,
+            lot_repo,
+            movement_repo,
+            warehouse_repo,
+            clock,
+        }
+    }
+
+    /// List sessions
+    pub fn list_sessions(&self, filter: InventoryFilter) -> Result<Vec<InventorySessionDto>> {
+        self.inventory_repo
+            .list(filter)?
+            .into_iter()
+            .map(|session| self.present(session))
+            .collect()
+    }
+
+    /// Get single session, as the counters may see it
+    pub fn get_session(&self, id: &str) -> Result<InventorySessionDto> {
+        self.present(self.load(id)?)
+    }
+
+    /// Open a session on an active warehouse, freezing its lots
+    pub fn open_session(&self, data: OpenInventoryDto, user_id: &str) -> Result<InventorySessionDto> {
+        let warehouse = self.warehouse_repo.get_warehouse(&data.warehouse_id)?.ok_or_else(|| Error::NotFound {
+            entity_type: "Warehouse".to_string(),
+            id: data.warehouse_id.clone(),
+        })?;
+        if !warehouse.is_active {
+            return Err(Error::BusinessRule(format!("Entrepot {} inactif", warehouse.code)));
+        }
+        if let Some(reference) = self.inventory_repo.open_session_of(&warehouse.id)? {
+            return Err(Error::BusinessRule(format!(
+                "Inventaire {} deja en cours sur l'entrepot {}",
+                reference, warehouse.code
+            )));
+        }
+
+        let mut session = InventorySession::open(
+            String::new(),
+            Self::parse_id("warehouse_id", &warehouse.id)?,
+            self.inventory_repo.snapshot(&warehouse.id)?,
+            data.blind,
+            data.recount_threshold_pct.unwrap_or(DEFAULT_RECOUNT_THRESHOLD_PCT),
+            Self::parse_id("user_id", user_id)?,
+            self.clock.as_ref(),
+        )?;
+        session.notes = data.notes;
+
+        let reference = self.inventory_repo.create(&session, self.clock.today(), user_id)?;
+
+        info!(
+            "Opened inventory {} on {} ({} lots{})",
+            reference,
+            warehouse.code,
+            session.lines.len(),
+            if session.blind { ", blind" } else { "" }
+        );
+        self.get_session(&session.id.to_string())
+    }
+
+    /// Record the count of a lot, by id or scanned QR code
+    pub fn record_count(&self, data: RecordCountDto, user_id: &str) -> Result<InventoryCountResultDto> {
+        let dto = self.load(&data.session_id)?;
+        let mut session = self.to_domain(&dto)?;
+
+        let (lot_id, method) = match (&data.lot_id, &data.lot_qr) {
+            (Some(lot_id), _) => (lot_id.clone(), CountMethod::Manual),
+            (None, Some(qr_code)) => {
+                let (_, lot_id) = self.lot_repo.find_by_qr(qr_code)?.ok_or_else(|| Error::NotFound {
+                    entity_type: "Lot".to_string(),
+                    id: qr_code.clone(),
+                })?;
+                (lot_id, CountMethod::Scan)
+            }
+            (None, None) => {
+                return Err(Error::Validation {
+                    field: "lot_id".to_string(),
+                    message: "Lot requis (identifiant ou QR code)".to_string(),
+                })
+            }
+        };
+        let lot_id = Self::parse_id("lot_id", &lot_id)?;
+
+        let outcome = session.record_count(
+            lot_id,
+            data.quantity,
+            method,
+            Self::parse_id("user_id", user_id)?,
+            self.clock.as_ref(),
+        )?;
+        let line = session.lines.iter().find(|l| l.lot_id == lot_id).expect("counted line is in the session");
+        self.inventory_repo.save_counts(&dto.id, line)?;
+
+        info!(
+            "Inventory {}: lot {} counted {} ({})",
+            dto.reference,
+            line.lot_number,
+            data.quantity,
+            method.as_str()
+        );
+        Ok(InventoryCountResultDto {
+            lot_number: line.lot_number.clone(),
+            outcome,
+            session: self.get_session(&dto.id)?,
+        })
+    }
+
+    /// Close counting (COUNTING -> REVIEW)
+    pub fn submit(&self, id: &str, user_id: &str) -> Result<InventorySessionDto> {
+        let dto = self.load(id)?;
+        let mut session = self.to_domain(&dto)?;
+        session.submit(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        self.inventory_repo.set_status(id, InventoryStatus::Review)?;
+
+        info!("Inventory {} submitted for approval", dto.reference);
+        self.get_session(id)
+    }
+
+    /// Send a session in review back to counting
+    pub fn reopen(&self, id: &str, user_id: &str) -> Result<InventorySessionDto> {
+        let dto = self.load(id)?;
+        let mut session = self.to_domain(&dto)?;
+        session.reopen(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        self.inventory_repo.set_status(id, InventoryStatus::Counting)?;
+
+        info!("Inventory {} reopened for counting", dto.reference);
+        self.get_session(id)
+    }
+
+    /// Variance report valued at lot cost
+    ///
+    /// Not available on a blind session still counting.
+    pub fn variance_report(&self, id: &str) -> Result<InventoryVarianceReportDto> {
+        let dto = self.load(id)?;
+        if dto.blind && dto.status == InventoryStatus::Counting {
+            return Err(Error::BusinessRule(format!(
+                "Inventaire {} en aveugle: rapport disponible apres cloture du comptage",
+                dto.reference
+            )));
+        }
+
+        let report = self.to_domain(&dto)?.variance_report();
+        let lines = report
+            .variances
+            .iter()
+            .map(|v| {
+                let line = dto
+                    .lines
+                    .iter()
+                    .find(|l| l.id == v.line_id.to_string())
+                    .expect("variance of a session line");
+                InventoryVarianceDto {
+                    line_id: line.id.clone(),
+                    product_type: line.product_type.clone(),
+                    product_name: line.product_name.clone(),
+                    lot_id: line.lot_id.clone(),
+                    lot_number: v.lot_number.clone(),
+                    unit: line.unit.clone(),
+                    quantity_theoretical: v.quantity_theoretical,
+                    quantity_counted: v.quantity_counted,
+                    variance: v.variance,
+                    unit_cost: line.unit_cost,
+                    value: v.value.centimes(),
+                }
+            })
+            .collect();
+
+        Ok(InventoryVarianceReportDto {
+            session_id: dto.id.clone(),
+            reference: dto.reference.clone(),
+            status: dto.status,
+            lines,
+            lots_counted: report.lots_counted as u32,
+            lots_uncounted: report.lots_uncounted as u32,
+            value_gain: report.value_gain.centimes(),
+            value_loss: report.value_loss.centimes(),
+            net_value: report.net_value().centimes(),
+        })
+    }
+
+    /// Approve the variances (REVIEW -> APPROVED)
+    ///
+    /// Posts one adjustment movement per lot with a variance, in one batch.
+    pub fn approve(&self, id: &str, user_id: &str) -> Result<InventorySessionDto> {
+        let dto = self.load(id)?;
+        let mut session = self.to_domain(&dto)?;
+        let report = session.variance_report();
+        let adjustments = session.approve(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        let movements = self.inventory_repo.approve(&dto, &adjustments, &self.movement_repo, user_id)?;
+
+        if !report.value_loss.is_zero() {
+            warn!(
+                "Inventory {}: {} centimes of stock missing",
+                dto.reference,
+                report.value_loss.centimes()
+            );
+        }
+        info!(
+            "Approved inventory {} by {}: {} adjustment movements, net {} centimes",
+            dto.reference,
+            user_id,
+            movements.len(),
+            report.net_value().centimes()
+        );
+        self.get_session(id)
+    }
+
+    /// Cancel a session not yet approved
+    pub fn cancel(&self, id: &str, user_id: &str) -> Result<InventorySessionDto> {
+        let dto = self.load(id)?;
+        let mut session = self.to_domain(&dto)?;
+        session.cancel(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        self.inventory_repo.cancel(id)?;
+
+        info!("Cancelled inventory {}", dto.reference);
+        self.get_session(id)
+    }
+
+    // Internal helpers
+
+    fn load(&self, id: &str) -> Result<InventorySessionDto> {
+        self.inventory_repo.get(id)?.ok_or_else(|| Error::NotFound {
+            entity_type: "InventorySession".to_string(),
+            id: id.to_string(),
+        })
+    }
+
+    /// Flag lots to count again and hide theoretical quantities from blind
+    /// counters
+    fn present(&self, mut dto: InventorySessionDto) -> Result<InventorySessionDto> {
+        let session = self.to_domain(&dto)?;
+        let hide = dto.blind && dto.status == InventoryStatus::Counting;
+        for (line, domain_line) in dto.lines.iter_mut().zip(&session.lines) {
+            line.needs_second_count = domain_line.needs_second_count(session.recount_threshold_pct);
+            if hide {
+                line.quantity_theoretical = None;
+            }
+        }
+        Ok(dto)
+    }
+
+    /// Rebuild the domain session to apply its rules
+    fn to_domain(&self, dto: &InventorySessionDto) -> Result<InventorySession> {
+        let lines = dto
+            .lines
+            .iter()
+            .map(|l| {
+                Ok(InventoryLine {
+                    id: Self::parse_id("line_id", &l.id)?,
+                    product_type: if l.product_type == "MP" { ProductType::Mp } else { ProductType::Pf },
+                    product_id: Self::parse_id("product_id", &l.product_id)?,
+                    lot_id: Self::parse_id("lot_id", &l.lot_id)?,
+                    lot_number: l.lot_number.clone(),
+                    location_id: l.location_id.as_deref().map(|id| Self::parse_id("location_id", id)).transpose()?,
+                    quantity_theoretical: l.quantity_theoretical.unwrap_or(Qty::zero()),
+                    unit_cost: Money::from_centimes(l.unit_cost),
+                    first_count: l.first_count,
+                    first_count_method: l.first_count_method,
+                    first_counted_by: l.first_counted_by.as_deref().map(|id| Self::parse_id("first_counted_by", id)).transpose()?,
+                    second_count: l.second_count,
+                    second_count_method: l.second_count_method,
+                    second_counted_by: l.second_counted_by.as_deref().map(|id| Self::parse_id("second_counted_by", id)).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(InventorySession {
+            id: Self::parse_id("id", &dto.id)?,
+            reference: dto.reference.clone(),
+            warehouse_id: Self::parse_id("warehouse_id", &dto.warehouse_id)?,
+            status: dto.status,
+            blind: dto.blind,
+            recount_threshold_pct: dto.recount_threshold_pct,
+            lines,
+            submitted_at: None,
+            approved_at: None,
+            approved_by: None,
+            notes: dto.notes.clone(),
+            audit: AuditInfo::new(Self::parse_id("created_by", &dto.created_by)?, self.clock.as_ref()),
+        })
+    }
+
+    fn parse_id(field: &str, value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Validation {
+            field: field.to_string(),
+            message: format!("Identifiant invalide: {}", value),
+        })
+    }
+}
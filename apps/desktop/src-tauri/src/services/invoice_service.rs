@@ -1,25 +1,21 @@
 //! Invoice Service
 //!
 //! Business logic for invoices and payments.
-//! Includes Algerian fiscal calculations (TVA 19%, timbre fiscal).
-
-use manchengo_core::{EntityId, Error, Result};
+//! Fiscal calculations (TVA, timbre fiscal) use the rule set in force on
+//! the invoice date.
+
+use chrono::NaiveDate;
+use manchengo_core::fiscal::PaymentMethod;
+use manchengo_core::fiscal_id;
+use manchengo_core::tax::{self, TaxLine};
+use manchengo_core::{AmountInWords, EntityId, Error, FiscalRuleSet, Money, Result, SharedClock};
 use manchengo_database::Database;
 use manchengo_sync::EventStore;
 use std::sync::Arc;
 use tracing::info;
 
 use crate::dto::invoice::*;
-use crate::repositories::{ClientRepository, InvoiceRepository};
-
-/// TVA rate in Algeria (19%)
-const TVA_RATE: f64 = 0.19;
-
-/// Timbre fiscal rate (1%)
-const TIMBRE_RATE: f64 = 0.01;
-
-/// Timbre fiscal minimum threshold
-const TIMBRE_THRESHOLD: i64 = 5000_00; // 5000 DA in centimes
+use crate::repositories::{ClientRepository, FiscalRuleRepository, InvoiceRepository, ProductRepository};
 
 /// Invoice service for invoice management
 pub struct InvoiceService {
@@ -27,6 +23,9 @@ pub struct InvoiceService {
     event_store: Arc<EventStore>,
     invoice_repo: Arc<InvoiceRepository>,
     client_repo: Arc<ClientRepository>,
+    product_repo: Arc<ProductRepository>,
+    fiscal_rule_repo: Arc<FiscalRuleRepository>,
+    clock: SharedClock,
 }
 
 impl InvoiceService {
@@ -35,12 +34,18 @@ impl InvoiceService {
         event_store: Arc<EventStore>,
         invoice_repo: Arc<InvoiceRepository>,
         client_repo: Arc<ClientRepository>,
+        product_repo: Arc<ProductRepository>,
+        fiscal_rule_repo: Arc<FiscalRuleRepository>,
+        clock: SharedClock,
     ) -> Self {
         Self {
             db,
             event_store,
             invoice_repo,
             client_repo,
+            product_repo,
+            fiscal_rule_repo,
+            clock,
         }
     }
 
@@ -61,12 +66,12 @@ impl InvoiceService {
     /// Create new invoice
     pub fn create_invoice(&self, data: CreateInvoiceDto) -> Result<InvoiceDto> {
         // Calculate totals
-        let totals = self.calculate_totals(&data.lines)?;
+        let totals = self.calculate_totals(&data)?;
 
         let id = EntityId::new().to_string();
-        let invoice_number = self.invoice_repo.generate_number()?;
+        let invoice_date = self.invoice_date(data.invoice_date.as_deref())?;
 
-        self.invoice_repo.create(&id, &invoice_number, &data, &totals)?;
+        let invoice_number = self.invoice_repo.create(&id, invoice_date, &data, &totals)?;
 
         info!("Created invoice {} for client {}", invoice_number, data.client_id);
 
@@ -96,6 +101,16 @@ impl InvoiceService {
             });
         }
 
+        // Never issue an invoice to a client with malformed identifiers
+        let client = self
+            .client_repo
+            .get(&invoice.client_id)?
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "Client".to_string(),
+                id: invoice.client_id.clone(),
+            })?;
+        fiscal_id::check_identifiers(client.nif.as_deref(), None, client.rc.as_deref(), client.ai.as_deref())?;
+
         self.invoice_repo.validate(id)?;
 
         // Update client balance
@@ -138,65 +153,127 @@ impl InvoiceService {
         Ok(())
     }
 
-    /// Calculate invoice totals (with Algerian fiscal rules)
-    pub fn calculate_totals(&self, lines: &[CreateInvoiceLineDto]) -> Result<InvoiceTotalsDto> {
-        let mut total_ht: i64 = 0;
-        let mut total_tva: i64 = 0;
-        let mut line_totals = Vec::new();
+    /// Calculate invoice totals with the fiscal rules in force on the invoice date
+    ///
+    /// TVA is rounded with the rule set's policy; lines priced TTC keep their TTC.
+    pub fn calculate_totals(&self, data: &CreateInvoiceDto) -> Result<InvoiceTotalsDto> {
+        let rule_book = self.fiscal_rule_repo.rule_book()?;
+        let rules = rule_book.in_force_on(self.invoice_date(data.invoice_date.as_deref())?)?;
+
+        let client_type = self
+            .client_repo
+            .get(&data.client_id)?
+            .and_then(|c| c.client_type);
+        let exempt = rules.is_exempt(client_type.as_deref());
+
+        let mode = rules.rounding.mode;
+        let mut tax_lines = Vec::with_capacity(data.lines.len());
+        for line in &data.lines {
+            let tva_rate = if exempt {
+                0.0
+            } else {
+                let category = self.product_repo.pf_category_code(&line.product_pf_id)?;
+                rules.tva_rate(category.as_deref())
+            };
 
-        for line in lines {
-            let line_ht = line.unit_price_ht * (line.quantity as i64);
-            let line_tva = (line_ht as f64 * TVA_RATE) as i64;
-            let line_ttc = line_ht + line_tva;
+            tax_lines.push(match line.unit_price_ttc {
+                Some(unit_ttc) => TaxLine::from_ttc(unit_ttc * line.quantity as i64, tva_rate, mode),
+                None => TaxLine::from_ht(line.unit_price_ht * line.quantity as i64, tva_rate),
+            });
+        }
 
-            total_ht += line_ht;
-            total_tva += line_tva;
+        let breakdown = tax::tva_breakdown(&tax_lines, rules.rounding);
 
-            line_totals.push(InvoiceLineTotalsDto {
+        let line_totals = data
+            .lines
+            .iter()
+            .zip(&tax_lines)
+            .zip(&breakdown.line_tva)
+            .map(|((line, tax_line), &line_tva)| InvoiceLineTotalsDto {
                 product_pf_id: line.product_pf_id.clone(),
                 quantity: line.quantity,
-                unit_price_ht: line.unit_price_ht,
-                line_ht,
+                unit_price_ht: match line.unit_price_ttc {
+                    Some(unit_ttc) => tax::ht_from_ttc(unit_ttc, tax_line.tva_rate, mode),
+                    None => line.unit_price_ht,
+                },
+                line_ht: tax_line.amount_ht,
                 line_tva,
-                line_ttc,
-            });
-        }
+                line_ttc: tax_line.amount_ht + line_tva,
+            })
+            .collect();
 
-        // Calculate timbre fiscal (1% of TTC if > 5000 DA)
-        let subtotal_ttc = total_ht + total_tva;
-        let timbre_fiscal = self.calculate_timbre_fiscal_amount(subtotal_ttc);
+        let subtotal_ttc = breakdown.total_ttc();
+        let timbre_fiscal = if exempt {
+            0
+        } else {
+            rules.timbre_centimes(subtotal_ttc, Self::payment_method(data.payment_method.as_deref()))
+        };
         let total_ttc = subtotal_ttc + timbre_fiscal;
 
         Ok(InvoiceTotalsDto {
-            total_ht,
-            total_tva,
+            total_ht: breakdown.total_ht,
+            total_tva: breakdown.total_tva,
             timbre_fiscal,
             total_ttc,
             lines: line_totals,
+            tva_breakdown: breakdown.groups,
         })
     }
 
-    /// Calculate timbre fiscal amount
-    pub fn calculate_timbre_fiscal_amount(&self, total_ttc: i64) -> i64 {
-        if total_ttc >= TIMBRE_THRESHOLD {
-            (total_ttc as f64 * TIMBRE_RATE) as i64
-        } else {
-            0
-        }
-    }
-
     /// Get timbre fiscal details
-    pub fn get_timbre_fiscal(&self, total_ttc: i64) -> TimbreFiscalDto {
-        let timbre_amount = self.calculate_timbre_fiscal_amount(total_ttc);
-        TimbreFiscalDto {
+    pub fn get_timbre_fiscal(
+        &self,
+        total_ttc: i64,
+        payment_method: Option<&str>,
+        invoice_date: Option<&str>,
+    ) -> Result<TimbreFiscalDto> {
+        let rule_book = self.fiscal_rule_repo.rule_book()?;
+        let rules = rule_book.in_force_on(self.invoice_date(invoice_date)?)?;
+
+        let timbre_amount = rules.timbre_centimes(total_ttc, Self::payment_method(payment_method));
+        Ok(TimbreFiscalDto {
             total_ttc,
             timbre_amount,
             final_total: total_ttc + timbre_amount,
-            timbre_rate: TIMBRE_RATE,
-            threshold: TIMBRE_THRESHOLD,
+            threshold: rules.timbre_threshold_da * 100,
+            rule_version: rules.version.clone(),
+        })
+    }
+
+    // =========================================================================
+    // FISCAL RULES
+    // =========================================================================
+
+    /// List fiscal rule sets
+    pub fn list_fiscal_rules(&self) -> Result<Vec<FiscalRuleSet>> {
+        self.fiscal_rule_repo.list()
+    }
+
+    /// Add a fiscal rule set (new Finance Law)
+    pub fn create_fiscal_rules(&self, rule_set: FiscalRuleSet, user_id: &str) -> Result<()> {
+        self.fiscal_rule_repo.create(&rule_set, user_id)?;
+        info!("Fiscal rules {} in force from {}", rule_set.version, rule_set.valid_from);
+        Ok(())
+    }
+
+    /// Parse invoice date, today if absent
+    fn invoice_date(&self, date: Option<&str>) -> Result<NaiveDate> {
+        match date {
+            Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| Error::Validation {
+                field: "invoice_date".to_string(),
+                message: format!("Date invalide: {}", d),
+            }),
+            None => Ok(self.clock.today()),
         }
     }
 
+    /// Payment method, cash if absent (as stored on the invoice)
+    fn payment_method(method: Option<&str>) -> PaymentMethod {
+        method
+            .and_then(PaymentMethod::from_str)
+            .unwrap_or(PaymentMethod::Especes)
+    }
+
     // =========================================================================
     // PAYMENT OPERATIONS
     // =========================================================================
@@ -246,6 +323,7 @@ impl InvoiceService {
                             client_id: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
                             client_name: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
                             amount: row.get(5)?,
+                            amount_in_words: AmountInWords::new(Money::from_centimes(row.get(5)?)),
                             payment_method: row.get(6)?,
                             payment_date: row.get(7)?,
                             reference: row.get(8)?,
@@ -270,6 +348,7 @@ impl InvoiceService {
                             client_id: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
                             client_name: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
                             amount: row.get(5)?,
+                            amount_in_words: AmountInWords::new(Money::from_centimes(row.get(5)?)),
                             payment_method: row.get(6)?,
                             payment_date: row.get(7)?,
                             reference: row.get(8)?,
@@ -387,7 +466,7 @@ impl InvoiceService {
             let count: i64 = conn
                 .query_row(
                     "SELECT COUNT(*) FROM invoices
-                     WHERE date(created_at) = date('now') AND is_deleted = 0",
+                     WHERE date(created_at) = date('now') AND is_deleted = 0 AND deleted_at IS NULL",
                     [],
                     |row| row.get(0),
                 )
@@ -0,0 +1,100 @@
+//! Database Maintenance Service
+//!
+//! Runs the maintenance jobs (WAL checkpoint, ANALYZE, incremental vacuum,
+//! event and QR scan purges) from the background scheduler or on admin demand.
+//! Jobs are skipped while a sync or a production transaction is in progress.
+
+use manchengo_core::{Error, Result};
+use manchengo_database::maintenance::{Maintenance, MaintenanceJob, MaintenancePolicy, MaintenanceRun};
+use manchengo_database::Database;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::core::ActivityTracker;
+use crate::dto::MaintenanceRunDto;
+
+/// Database maintenance service
+pub struct MaintenanceService {
+    db: Arc<Database>,
+    activity: Arc<ActivityTracker>,
+    policy: MaintenancePolicy,
+}
+
+impl MaintenanceService {
+    pub fn new(db: Arc<Database>, activity: Arc<ActivityTracker>, policy: MaintenancePolicy) -> Self {
+        Self {
+            db,
+            activity,
+            policy,
+        }
+    }
+
+    /// Run every job whose interval has elapsed (scheduler entry point)
+    pub fn run_due(&self) -> Result<Vec<MaintenanceRunDto>> {
+        let due = self.db.with_connection(Maintenance::due_jobs)?;
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        info!("{} maintenance jobs due", due.len());
+
+        let mut runs = Vec::new();
+        for job in due {
+            let run = self.execute(job)?;
+            let skipped = run.status == "SKIPPED";
+            runs.push(run);
+
+            // Remaining jobs wait for the next tick while the database is busy
+            if skipped && self.activity.busy_reason().is_some() {
+                break;
+            }
+        }
+
+        Ok(runs)
+    }
+
+    /// Run a single job immediately (admin)
+    ///
+    /// An admin vacuum converts the database to incremental auto-vacuum
+    /// first; the scheduler never runs that full VACUUM.
+    pub fn run_job(&self, job: &str) -> Result<MaintenanceRunDto> {
+        let job = MaintenanceJob::parse(job).ok_or_else(|| Error::Validation {
+            field: "job".to_string(),
+            message: format!("Tache de maintenance inconnue: {}", job),
+        })?;
+
+        if job == MaintenanceJob::IncrementalVacuum && self.activity.busy_reason().is_none() {
+            self.db.with_connection(Maintenance::enable_incremental_vacuum)?;
+        }
+        self.execute(job)
+    }
+
+    /// Most recent runs, newest first
+    pub fn history(&self, limit: i64) -> Result<Vec<MaintenanceRunDto>> {
+        let runs = self
+            .db
+            .with_connection(|conn| Maintenance::history(conn, limit))?;
+
+        Ok(runs.iter().map(Self::to_dto).collect())
+    }
+
+    fn execute(&self, job: MaintenanceJob) -> Result<MaintenanceRunDto> {
+        let run = self.db.with_connection(|conn| match self.activity.busy_reason() {
+            Some(reason) => Maintenance::skip(conn, job, reason),
+            None => Maintenance::run(conn, job, &self.policy),
+        })?;
+
+        Ok(Self::to_dto(&run))
+    }
+
+    fn to_dto(run: &MaintenanceRun) -> MaintenanceRunDto {
+        MaintenanceRunDto {
+            job: run.job.as_str().to_string(),
+            status: run.status.as_str().to_string(),
+            started_at: run.started_at.clone(),
+            duration_ms: run.duration_ms.max(0) as u64,
+            rows_affected: run.rows_affected.max(0) as u64,
+            message: run.message.clone(),
+        }
+    }
+}
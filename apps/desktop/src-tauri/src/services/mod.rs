@@ -9,6 +9,15 @@ pub mod production_service;
 pub mod appro_service;
 pub mod commercial_service;
 pub mod invoice_service;
+pub mod integrity_service;
+pub mod trash_service;
+pub mod maintenance_service;
+pub mod archive_service;
+pub mod transfer_service;
+pub mod inventory_service;
+pub mod valuation_service;
+pub mod stock_history_service;
+pub mod sales_order_service;
 
 pub use stock_service::StockService;
 pub use sync_service::SyncService;
@@ -16,3 +25,12 @@ pub use production_service::ProductionService;
 pub use appro_service::ApproService;
 pub use commercial_service::CommercialService;
 pub use invoice_service::InvoiceService;
+pub use integrity_service::IntegrityService;
+pub use trash_service::TrashService;
+pub use maintenance_service::MaintenanceService;
+pub use archive_service::ArchiveService;
+pub use transfer_service::TransferService;
+pub use inventory_service::InventoryService;
+pub use valuation_service::ValuationService;
+pub use stock_history_service::StockHistoryService;
+pub use sales_order_service::SalesOrderService;
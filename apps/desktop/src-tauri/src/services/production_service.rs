@@ -3,21 +3,23 @@
 //! Business logic for production orders and recipes.
 //! Handles the full production workflow including FIFO MP consumption.
 
-use manchengo_core::{EntityId, Error, Result};
+use manchengo_core::{EntityId, Error, Money, Qty, Result, SharedClock};
 use manchengo_database::Database;
 use manchengo_sync::EventStore;
 use rusqlite::OptionalExtension;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{info, warn};
 
 use crate::dto::{
     AvailabilityDto, AvailabilityItemDto, CompleteProductionDto, CreateProductionOrderDto,
-    CreateRecipeDto, ProductionCalendarEntry, ProductionCompletionDto, ProductionCostDto,
+    CreateRecipeDto, ManualLotPickDto, ProductionCalendarEntry, ProductionCompletionDto, ProductionCostDto,
     ProductionDashboardDto, ProductionKpisDto, ProductionOrderDto, ProductionOrderFilter,
     ProductionStatus, RecipeDto, RecipeFilter, ScaledRecipeDto, ScaledRecipeItemDto,
 };
 use crate::repositories::{LotRepository, ProductionRepository, ProductRepository, RecipeRepository};
-use crate::services::StockService;
+use crate::core::{Activity, ActivityTracker};
+use crate::services::{StockService, TrashService};
 
 /// Production service for managing production orders and recipes
 pub struct ProductionService {
@@ -28,9 +30,13 @@ pub struct ProductionService {
     product_repo: Arc<ProductRepository>,
     lot_repo: Arc<LotRepository>,
     stock_service: Arc<StockService>,
+    trash_service: Arc<TrashService>,
+    activity: Arc<ActivityTracker>,
+    clock: SharedClock,
 }
 
 impl ProductionService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db: Arc<Database>,
         event_store: Arc<EventStore>,
@@ -39,6 +45,9 @@ impl ProductionService {
         product_repo: Arc<ProductRepository>,
         lot_repo: Arc<LotRepository>,
         stock_service: Arc<StockService>,
+        trash_service: Arc<TrashService>,
+        activity: Arc<ActivityTracker>,
+        clock: SharedClock,
     ) -> Self {
         Self {
             db,
@@ -48,6 +57,9 @@ impl ProductionService {
             product_repo,
             lot_repo,
             stock_service,
+            trash_service,
+            activity,
+            clock,
         }
     }
 
@@ -100,10 +112,8 @@ impl ProductionService {
     }
 
     /// Delete recipe (soft delete)
-    pub fn delete_recipe(&self, id: &str) -> Result<()> {
-        self.recipe_repo.delete(id)?;
-        info!("Deleted recipe {}", id);
-        Ok(())
+    pub fn delete_recipe(&self, id: &str, user_id: &str) -> Result<()> {
+        self.trash_service.delete("Recipe", id, user_id, None)
     }
 
     /// Get scaled recipe for production
@@ -116,24 +126,31 @@ impl ProductionService {
                 id: product_pf_id.to_string(),
             })?;
 
-        let target_output = recipe.output_quantity * (batch_count as f64);
+        let target_output = recipe.output_quantity * batch_count as i64;
 
         let mut scaled_items = Vec::new();
         for item in &recipe.items {
-            let quantity_per_batch = item.quantity;
-            let total_quantity = quantity_per_batch * (batch_count as f64);
+            // Quantities are expressed in the MP stock unit (recipe in g, lots in kg)
+            let (quantity_per_batch, unit) = match &item.product_mp_id {
+                Some(mp_id) => self
+                    .stock_service
+                    .to_stock_unit("MP", mp_id, item.quantity.as_f64(), &item.unit)
+                    .map_err(|e| Error::BusinessRule(e.to_string()))?,
+                None => (item.quantity, item.unit.clone()),
+            };
+            let total_quantity = quantity_per_batch * batch_count as i64;
 
             // Get current stock for this MP
             let current_stock = match &item.product_mp_id {
                 Some(mp_id) => self.stock_service
                     .calculate_stock("MP", mp_id)
-                    .unwrap_or(0.0),
-                None => 0.0,
+                    .unwrap_or_default(),
+                None => Qty::zero(),
             };
 
             let is_available = current_stock >= total_quantity;
             let shortage = if is_available {
-                0.0
+                Qty::zero()
             } else {
                 total_quantity - current_stock
             };
@@ -143,7 +160,7 @@ impl ProductionService {
                 product_mp_name: item.product_mp_name.clone(),
                 quantity_per_batch,
                 total_quantity,
-                unit: item.unit.clone(),
+                unit,
                 current_stock,
                 is_available,
                 shortage,
@@ -182,7 +199,7 @@ impl ProductionService {
 
                 if !item.is_available {
                     blockers.push(format!(
-                        "{}: manque {:.2} {} (dispo: {:.2})",
+                        "{}: manque {} {} (dispo: {})",
                         item.product_mp_name.as_deref().unwrap_or("MP"),
                         item.shortage,
                         item.unit,
@@ -227,11 +244,10 @@ impl ProductionService {
             })?;
 
         let id = EntityId::new().to_string();
-        let reference = self.production_repo.generate_reference()?;
-        let target_quantity = recipe.output_quantity * (data.batch_count as f64);
+        let target_quantity = recipe.output_quantity * data.batch_count as i64;
 
-        self.production_repo
-            .create(&id, &reference, &recipe.id, target_quantity, &data, user_id)?;
+        let reference = self.production_repo
+            .create(&id, self.clock.today(), &recipe.id, target_quantity, &data, user_id)?;
 
         info!("Created production order {} for {} batches", reference, data.batch_count);
 
@@ -243,8 +259,18 @@ impl ProductionService {
             })
     }
 
-    /// Start production (consume MP via FIFO)
-    pub fn start_production(&self, order_id: &str, user_id: &str) -> Result<ProductionOrderDto> {
+    /// Start production (consume MP with each product strategy)
+    ///
+    /// `manual_lots` holds the operator picks by MP product id, required for
+    /// products on the manual strategy and ignored for the others.
+    pub fn start_production(
+        &self,
+        order_id: &str,
+        manual_lots: &HashMap<String, Vec<ManualLotPickDto>>,
+        user_id: &str,
+    ) -> Result<ProductionOrderDto> {
+        let _activity = self.activity.begin(Activity::Production);
+
         // Get the order
         let order = self
             .production_repo
@@ -275,17 +301,19 @@ impl ProductionService {
         // Get scaled recipe
         let scaled = self.get_scaled_recipe(&order.product_pf_id, order.batch_count)?;
 
-        // Consume MP via FIFO
+        // Consume MP with each product strategy
         for item in &scaled.items {
             if let Some(ref mp_id) = item.product_mp_id {
                 let fifo_result = self.stock_service.consume_fifo(
                     mp_id,
+                    None,
                     item.total_quantity,
+                    manual_lots.get(mp_id).map(Vec::as_slice).unwrap_or_default(),
                     "PRODUCTION",
                     Some("PRODUCTION_ORDER"),
                     Some(&order.id),
                     user_id,
-                ).map_err(|e| Error::Internal(e.to_string()))?;
+                )?;
 
                 // Record each consumption
                 for consumption in fifo_result.consumptions {
@@ -301,7 +329,7 @@ impl ProductionService {
                 }
 
                 info!(
-                    "Production {}: consumed {:.2} {} of MP {}",
+                    "Production {}: consumed {} {} of MP {}",
                     order.reference, item.total_quantity, item.unit, mp_id
                 );
             }
@@ -327,6 +355,8 @@ impl ProductionService {
         data: CompleteProductionDto,
         user_id: &str,
     ) -> Result<ProductionCompletionDto> {
+        let _activity = self.activity.begin(Activity::Production);
+
         // Get the order
         let order = self
             .production_repo
@@ -346,8 +376,8 @@ impl ProductionService {
         }
 
         // Calculate yield
-        let yield_percentage = if order.target_quantity > 0.0 {
-            (data.quantity_produced / order.target_quantity) * 100.0
+        let yield_percentage = if order.target_quantity.is_positive() {
+            (data.quantity_produced.as_f64() / order.target_quantity.as_f64()) * 100.0
         } else {
             100.0
         };
@@ -360,15 +390,19 @@ impl ProductionService {
         let recipe = self.recipe_repo.get(&order.recipe_id)?;
         let shelf_life_days = recipe.map(|r| r.shelf_life_days).unwrap_or(90);
 
-        // Insert lot_pf
+        // Insert lot_pf, waiting in the factory reception location for put-away
         self.db.with_connection(|conn| {
             conn.execute(
                 "INSERT INTO lots_pf (
                     id, product_pf_id, lot_number, production_order_id,
                     quantity_initial, quantity_remaining, production_date,
-                    expiry_date, status, created_at, is_deleted
+                    expiry_date, status, warehouse_id, location_id, created_at, is_deleted
                 ) VALUES (?, ?, ?, ?, ?, ?, datetime('now'),
-                         datetime('now', '+' || ? || ' days'), 'AVAILABLE', datetime('now'), 0)",
+                         datetime('now', '+' || ? || ' days'), 'AVAILABLE',
+                         (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1),
+                         (SELECT wl.id FROM warehouse_locations wl JOIN warehouses w ON w.id = wl.warehouse_id
+                          WHERE w.is_default = 1 AND wl.location_type = 'RECEPTION' LIMIT 1),
+                         datetime('now'), 0)",
                 rusqlite::params![
                     lot_pf_id,
                     order.product_pf_id,
@@ -388,7 +422,7 @@ impl ProductionService {
             .complete(order_id, &data, &lot_pf_id, yield_percentage)?;
 
         info!(
-            "Completed production order {} with {:.2} units (yield: {:.1}%)",
+            "Completed production order {} with {} units (yield: {:.1}%)",
             order.reference, data.quantity_produced, yield_percentage
         );
 
@@ -399,7 +433,7 @@ impl ProductionService {
             quantity_produced: data.quantity_produced,
             yield_percentage,
             consumptions_count: order.consumptions.len() as i32,
-            completed_at: chrono::Utc::now().to_rfc3339(),
+            completed_at: self.clock.now().to_rfc3339(),
         })
     }
 
@@ -443,7 +477,7 @@ impl ProductionService {
         })?;
 
         // Get today's schedule
-        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let today = self.clock.today().format("%Y-%m-%d").to_string();
         let today_schedule = self.production_repo.get_scheduled_for_date(&today)?;
 
         // TODO: Calculate blocked orders, avg yield, etc.
@@ -541,7 +575,7 @@ impl ProductionService {
                 Ok(cost.unwrap_or(0))
             })?;
 
-            let item_cost = (lot_cost as f64 * consumption.quantity) as i64;
+            let item_cost = consumption.quantity.value_at(Money::from_centimes(lot_cost)).centimes();
             mp_cost += item_cost;
 
             mp_breakdown.push(crate::dto::MpCostItem {
@@ -557,11 +591,10 @@ impl ProductionService {
         let overhead_cost = 0; // TODO: calculate overhead
         let total_cost = mp_cost + overhead_cost;
         let quantity = order.actual_quantity.unwrap_or(order.target_quantity);
-        let cost_per_unit = if quantity > 0.0 {
-            (total_cost as f64 / quantity) as i64
-        } else {
-            0
-        };
+        let cost_per_unit = quantity
+            .unit_price_of(Money::from_centimes(total_cost))
+            .map(|m| m.centimes())
+            .unwrap_or(0);
 
         Ok(ProductionCostDto {
             order_id: order_id.to_string(),
@@ -579,8 +612,8 @@ impl ProductionService {
     // =========================================================================
 
     fn generate_lot_pf_number(&self) -> Result<String> {
+        let today = self.clock.today().format("%Y%m%d").to_string();
         self.db.with_connection(|conn| {
-            let today = chrono::Utc::now().format("%Y%m%d").to_string();
             let count: i64 = conn
                 .query_row(
                     "SELECT COUNT(*) FROM lots_pf WHERE lot_number LIKE ?",
@@ -0,0 +1,279 @@
+//! Sales Order Service
+//!
+//! Business logic for sales order fulfilment:
+//! - Confirmation holds finished product lots until an expiry
+//! - Cancellation releases the holds
+//! - Delivery preparation turns the holds of an order into delivery items
+//! - Timeout sweep of forgotten holds (background scheduler)
+//!
+//! Each operation writes the order or delivery, its reservations and the
+//! lots in one transaction. Holds run on the system clock, so a backdated
+//! session neither shortens them nor stops the sweep; delivery movements
+//! still take the business date.
+
+use chrono::Duration;
+use manchengo_core::{EntityId, Error, Qty, Quantity, Result, SharedClock, UnitOfMeasure};
+use manchengo_database::Database;
+use manchengo_domain::commercial::SalesOrder;
+use manchengo_domain::stock::{LotPf, LotStatus, ReservationBook, ReservationStatus};
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::dto::*;
+use crate::repositories::{
+    ClientRepository, DeliveryRepository, LotRepository, MovementRepository, ProductRepository, ReservationRepository,
+    SalesOrderRepository,
+};
+use crate::services::StockService;
+
+/// How long a confirmed order holds its lots when not specified
+const DEFAULT_HOLD_HOURS: u32 = 48;
+
+/// Sales order service (reservations and delivery preparation)
+pub struct SalesOrderService {
+    db: Arc<Database>,
+    reservation_repo: Arc<ReservationRepository>,
+    product_repo: Arc<ProductRepository>,
+    client_repo: Arc<ClientRepository>,
+    movement_repo: Arc<MovementRepository>,
+    stock_service: Arc<StockService>,
+    /// System clock (hold expiries), not the business date
+    clock: SharedClock,
+}
+
+impl SalesOrderService {
+    pub fn new(
+        db: Arc<Database>,
+        reservation_repo: Arc<ReservationRepository>,
+        product_repo: Arc<ProductRepository>,
+        client_repo: Arc<ClientRepository>,
+        movement_repo: Arc<MovementRepository>,
+        stock_service: Arc<StockService>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            db,
+            reservation_repo,
+            product_repo,
+            client_repo,
+            movement_repo,
+            stock_service,
+            clock,
+        }
+    }
+
+    /// Lots held for an order, all statuses
+    pub fn list_reservations(&self, order_id: &str) -> Result<Vec<LotReservationDto>> {
+        self.reservation_repo.list_for_order(order_id)
+    }
+
+    /// Confirm a draft order and hold its lots (DRAFT -> CONFIRMED)
+    ///
+    /// Lots are chosen with each product strategy, tightened by the client
+    /// minimum shelf life, among the stock not already held; nothing is held
+    /// unless every line is covered.
+    pub fn confirm_order(&self, data: ConfirmSalesOrderDto, user_id: &str) -> Result<Vec<LotReservationDto>> {
+        let user = Self::parse_id("user_id", user_id)?;
+        let hold_until = self.clock.now() + Duration::hours(i64::from(data.hold_hours.unwrap_or(DEFAULT_HOLD_HOURS)));
+        let mut order = self.load_order(&data.order_id)?;
+        let client_min_days = self.client_repo.get_min_shelf_life(&order.client_id.to_string())?;
+        let strategies = order
+            .lines
+            .iter()
+            .map(|line| {
+                let strategy = self.product_repo.get_lot_strategy("PF", &line.product_pf_id.to_string())?;
+                Ok((line.product_pf_id, strategy.with_client_min_shelf_life(client_min_days)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.db.transaction(|tx| {
+            let mut book = ReservationRepository::active_on_lots(tx, SalesOrderRepository::PRODUCT_LOTS, &data.order_id)?;
+            let mut lots = LotRepository::pf_lots(tx, SalesOrderRepository::PRODUCT_LOTS, &data.order_id, user, self.clock.as_ref())?;
+            let held: HashSet<EntityId> = book.reservations.iter().map(|r| r.id).collect();
+            let before = Self::lot_states(&lots);
+
+            order.confirm(&mut lots, &mut book, &strategies, hold_until, user, self.clock.as_ref())?;
+
+            for reservation in book.reservations.iter().filter(|r| !held.contains(&r.id)) {
+                ReservationRepository::insert(tx, reservation)?;
+            }
+            Self::save_changed_lots(tx, &lots, &before)?;
+            SalesOrderRepository::save_status(tx, &order)
+        })?;
+
+        info!("Confirmed sales order {}, lots held until {}", order.order_number, hold_until);
+        self.reservation_repo.list_for_order(&data.order_id)
+    }
+
+    /// Cancel an order and release its holds
+    pub fn cancel_order(&self, order_id: &str, reason: &str, user_id: &str) -> Result<Vec<LotReservationDto>> {
+        let user = Self::parse_id("user_id", user_id)?;
+        let mut order = self.load_order(order_id)?;
+
+        self.db.transaction(|tx| {
+            let mut book = ReservationRepository::active_on_lots(tx, ReservationRepository::ORDER_LOTS, order_id)?;
+            let mut lots = LotRepository::pf_lots(tx, ReservationRepository::ORDER_LOTS, order_id, user, self.clock.as_ref())?;
+            let before = Self::lot_states(&lots);
+
+            order.cancel(&mut lots, &mut book, reason, user, self.clock.as_ref())?;
+
+            Self::close_ended(tx, &book)?;
+            Self::save_changed_lots(tx, &lots, &before)?;
+            SalesOrderRepository::save_status(tx, &order)
+        })?;
+
+        info!("Cancelled sales order {}: {}", order.order_number, reason);
+        self.reservation_repo.list_for_order(order_id)
+    }
+
+    /// Prepare a delivery client line from the lots held for its order
+    ///
+    /// Each hold becomes a delivery item and leaves stock with an OUT
+    /// movement linked to the delivery.
+    pub fn prepare_delivery_line(&self, data: PrepareDeliveryLineDto, user_id: &str) -> Result<Vec<LotReservationDto>> {
+        let user = Self::parse_id("user_id", user_id)?;
+        let line_id = Self::parse_id("delivery_line_id", &data.delivery_line_id)?;
+
+        let mut delivery = self
+            .db
+            .with_connection(|conn| DeliveryRepository::get(conn, &data.delivery_id))?
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "Delivery".to_string(),
+                id: data.delivery_id.clone(),
+            })?;
+        let line = delivery.lines.iter().find(|l| l.id == line_id).ok_or_else(|| Error::NotFound {
+            entity_type: "DeliveryLine".to_string(),
+            id: data.delivery_line_id.clone(),
+        })?;
+        let first_new = line.items.len();
+        let order_id = line
+            .sales_order_id
+            .ok_or_else(|| Error::BusinessRule("Ligne de livraison sans commande client".to_string()))?
+            .to_string();
+        let order = self.load_order(&order_id)?;
+
+        let picked = self.db.transaction(|tx| {
+            let mut book = ReservationRepository::active_on_lots(tx, ReservationRepository::ORDER_LOTS, &order_id)?;
+            let mut lots = LotRepository::pf_lots(tx, ReservationRepository::ORDER_LOTS, &order_id, user, self.clock.as_ref())?;
+            let before = Self::lot_states(&lots);
+
+            let picked = delivery.add_reserved_items(line_id, &order, &mut book, &mut lots, user, self.clock.as_ref())?;
+
+            Self::close_ended(tx, &book)?;
+            Self::save_changed_lots(tx, &lots, &before)?;
+            DeliveryRepository::save_added_items(tx, &delivery, line_id, first_new)?;
+
+            let line = delivery.lines.iter().find(|l| l.id == line_id).expect("line prepared above");
+            for (item, (lot_id, quantity)) in line.items.iter().skip(first_new).zip(&picked) {
+                let lot = lots.iter().find(|l| l.id == *lot_id).expect("picked lot is loaded");
+                self.movement_repo.create_on(
+                    tx,
+                    &EntityId::new().to_string(),
+                    "OUT",
+                    "PF",
+                    &item.product_pf_id.to_string(),
+                    Some(&lot_id.to_string()),
+                    *quantity,
+                    Some(lot.unit_cost.centimes()),
+                    "VENTE",
+                    Some("DELIVERY"),
+                    Some(&data.delivery_id),
+                    user_id,
+                    &format!("DLV-{}", item.id),
+                    Some(&delivery.delivery_number),
+                )?;
+            }
+            Ok(picked)
+        })?;
+
+        info!(
+            "Delivery {}: prepared {} held lot(s) of order {}",
+            delivery.delivery_number,
+            picked.len(),
+            order.order_number
+        );
+        self.reservation_repo.list_for_order(&order_id)
+    }
+
+    /// Expire holds past their expiry (scheduler entry point)
+    ///
+    /// Returns the number of expired holds.
+    pub fn expire_reservations(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let now_sql = MovementRepository::timestamp(now);
+
+        let expired = self.db.transaction(|tx| {
+            let mut book = ReservationRepository::active_on_lots(tx, ReservationRepository::DUE_LOTS, &now_sql)?;
+            // No session runs the sweep: the domain audit goes to the holder
+            let Some(user) = book.reservations.iter().find(|r| r.expires_at <= now).map(|r| r.audit.created_by) else {
+                return Ok(0);
+            };
+            let mut lots = LotRepository::pf_lots(tx, ReservationRepository::DUE_LOTS, &now_sql, user, self.clock.as_ref())?;
+            let before = Self::lot_states(&lots);
+
+            let expired = book.expire(&mut lots, user, self.clock.as_ref());
+
+            Self::close_ended(tx, &book)?;
+            Self::save_changed_lots(tx, &lots, &before)?;
+            Ok(expired.len())
+        })?;
+
+        if expired > 0 {
+            info!("Expired {} lot reservation(s)", expired);
+        }
+        Ok(expired)
+    }
+
+    // Internal helpers
+
+    /// Load an order with its lines in the product stock unit
+    fn load_order(&self, id: &str) -> Result<SalesOrder> {
+        let mut order = self
+            .db
+            .with_connection(|conn| SalesOrderRepository::get(conn, id))?
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "SalesOrder".to_string(),
+                id: id.to_string(),
+            })?;
+
+        for line in &mut order.lines {
+            let (quantity, unit) =
+                self.stock_service
+                    .to_stock_unit("PF", &line.product_pf_id.to_string(), line.quantity, line.unit.code())?;
+            let unit = UnitOfMeasure::from_code(&unit)
+                .ok_or_else(|| Error::Internal(format!("Unite de stock inconnue: {}", unit)))?;
+            line.stock_quantity = Quantity::new(quantity.as_f64(), unit);
+        }
+        Ok(order)
+    }
+
+    fn lot_states(lots: &[LotPf]) -> Vec<(LotStatus, Qty)> {
+        lots.iter().map(|l| (l.status, l.quantity_remaining)).collect()
+    }
+
+    fn save_changed_lots(conn: &Connection, lots: &[LotPf], before: &[(LotStatus, Qty)]) -> Result<()> {
+        for (lot, state) in lots.iter().zip(before) {
+            if (lot.status, lot.quantity_remaining) != *state {
+                LotRepository::save_pf_state(conn, lot)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the holds the operation ended (the book was loaded active)
+    fn close_ended(conn: &Connection, book: &ReservationBook) -> Result<()> {
+        for reservation in book.reservations.iter().filter(|r| r.status != ReservationStatus::Active) {
+            ReservationRepository::close(conn, reservation)?;
+        }
+        Ok(())
+    }
+
+    fn parse_id(field: &str, value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Validation {
+            field: field.to_string(),
+            message: format!("Identifiant invalide: {}", value),
+        })
+    }
+}
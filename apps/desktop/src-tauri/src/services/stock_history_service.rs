@@ -0,0 +1,197 @@
+//! Stock History Service
+//!
+//! Answers "what did we hold on that day?" for auditors:
+//! - Stock per product, lot and warehouse at the end of any past day,
+//!   rebuilt from the movement ledger (SUM(IN) - SUM(OUT) up to that day)
+//! - Daily closing snapshots, taken by the background scheduler, so a query
+//!   only replays the movements after the latest closed day. A day is closed
+//!   once it is out of the backdating window: no movement can land on it.
+
+use chrono::{Duration, NaiveDate};
+use manchengo_core::{Error, Qty, Result, SharedClock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::core::security::MAX_BACKDATE_DAYS;
+use crate::dto::*;
+use crate::repositories::valuation_repo::ValuedProduct;
+use crate::repositories::{StockHistoryRepository, ValuationRepository, WarehouseRepository};
+
+/// Days closed per scheduler run, so a first catch-up does not hold the
+/// database for long
+const MAX_DAYS_CLOSED_PER_RUN: i64 = 31;
+
+/// Stock history service
+pub struct StockHistoryService {
+    history_repo: Arc<StockHistoryRepository>,
+    valuation_repo: Arc<ValuationRepository>,
+    warehouse_repo: Arc<WarehouseRepository>,
+    clock: SharedClock,
+}
+
+impl StockHistoryService {
+    pub fn new(
+        history_repo: Arc<StockHistoryRepository>,
+        valuation_repo: Arc<ValuationRepository>,
+        warehouse_repo: Arc<WarehouseRepository>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            history_repo,
+            valuation_repo,
+            warehouse_repo,
+            clock,
+        }
+    }
+
+    /// Stock held at the end of a day, per product and per lot/warehouse
+    pub fn stock_as_of(&self, filter: StockAsOfFilter) -> Result<StockAsOfDto> {
+        let as_of = NaiveDate::parse_from_str(&filter.as_of, "%Y-%m-%d").map_err(|_| Error::Validation {
+            field: "as_of".to_string(),
+            message: format!("Date invalide: {}", filter.as_of),
+        })?;
+        if as_of > self.clock.today() {
+            return Err(Error::Validation {
+                field: "as_of".to_string(),
+                message: format!("Date future: {}", as_of),
+            });
+        }
+
+        let (snapshot, balances) = self.history_repo.balances(as_of)?;
+
+        let products: HashMap<String, ValuedProduct> = self
+            .valuation_repo
+            .products()?
+            .into_iter()
+            .map(|p| (p.id.clone(), p))
+            .collect();
+        let warehouses: HashMap<String, String> = self
+            .warehouse_repo
+            .list_warehouses(false)?
+            .into_iter()
+            .map(|w| (w.id, w.code))
+            .collect();
+        let lot_numbers = self.history_repo.lot_numbers()?;
+
+        let positions: Vec<StockPositionDto> = balances
+            .into_iter()
+            .filter(|b| filter.product_type.as_ref().is_none_or(|t| *t == b.product_type))
+            .filter(|b| filter.product_id.as_ref().is_none_or(|id| *id == b.product_id))
+            .filter(|b| filter.warehouse_id.is_none() || filter.warehouse_id == b.warehouse_id)
+            .map(|b| StockPositionDto {
+                product_code: products.get(&b.product_id).map(|p| p.code.clone()).unwrap_or_default(),
+                lot_number: b.lot_id.as_ref().and_then(|id| lot_numbers.get(id)).cloned(),
+                warehouse_code: b.warehouse_id.as_ref().and_then(|id| warehouses.get(id)).cloned(),
+                product_type: b.product_type,
+                product_id: b.product_id,
+                lot_id: b.lot_id,
+                warehouse_id: b.warehouse_id,
+                quantity: b.quantity,
+                last_movement_date: b.last_movement_at,
+            })
+            .collect();
+
+        let mut totals: Vec<ProductStockAsOfDto> = Vec::new();
+        for position in &positions {
+            let total = match totals.iter_mut().find(|t| t.product_id == position.product_id) {
+                Some(total) => total,
+                None => {
+                    let product = products.get(&position.product_id);
+                    totals.push(ProductStockAsOfDto {
+                        product_type: position.product_type.clone(),
+                        product_id: position.product_id.clone(),
+                        product_code: position.product_code.clone(),
+                        product_name: product.map(|p| p.name.clone()).unwrap_or_default(),
+                        unit: product.map(|p| p.unit.clone()).unwrap_or_default(),
+                        quantity: Qty::zero(),
+                        lots_count: 0,
+                        last_movement_date: None,
+                    });
+                    totals.last_mut().expect("just pushed")
+                }
+            };
+            total.quantity += position.quantity;
+            if position.lot_id.is_some() {
+                total.lots_count += 1;
+            }
+            if position.last_movement_date > total.last_movement_date {
+                total.last_movement_date = position.last_movement_date.clone();
+            }
+        }
+        totals.sort_by(|a, b| (&a.product_type, &a.product_code).cmp(&(&b.product_type, &b.product_code)));
+
+        Ok(StockAsOfDto {
+            as_of: as_of.to_string(),
+            snapshot_date: snapshot.map(|d| d.to_string()),
+            products: totals,
+            positions,
+        })
+    }
+
+    /// Close every finished day not yet snapshotted (scheduler entry point)
+    ///
+    /// Movements are dated with the business date, up to `MAX_BACKDATE_DAYS`
+    /// back, and a snapshot is never rebuilt: only days no session can still
+    /// write to are closed.
+    pub fn close_days(&self) -> Result<u32> {
+        let last = Self::last_closable_day(self.clock.today());
+        let first = match self.history_repo.last_snapshot_date()? {
+            Some(last) => last + Duration::days(1),
+            None => match self.history_repo.first_movement_date()? {
+                Some(first) => first,
+                None => return Ok(0),
+            },
+        };
+
+        let mut closed = 0u32;
+        let mut day = first;
+        while day <= last && i64::from(closed) < MAX_DAYS_CLOSED_PER_RUN {
+            let lines = self.history_repo.close_day(day)?;
+            info!("Stock closed for {} ({} balances)", day, lines);
+            closed += 1;
+            day += Duration::days(1);
+        }
+        Ok(closed)
+    }
+
+    /// Latest day out of the backdating window on `today`
+    fn last_closable_day(today: NaiveDate) -> NaiveDate {
+        today - Duration::days(MAX_BACKDATE_DAYS + 1)
+    }
+
+    /// Closed days, most recent first
+    pub fn list_snapshots(&self, limit: u32) -> Result<Vec<StockSnapshotDto>> {
+        self.history_repo.list_snapshots(limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{AuthenticatedUser, SessionManager};
+    use chrono::Utc;
+    use manchengo_core::{Clock, EntityId, FixedClock, UserRole};
+
+    #[test]
+    fn test_backdated_day_is_never_closed() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+        let manager = SessionManager::with_clock(EntityId::new(), Arc::new(FixedClock::at_date(today)));
+        manager
+            .login(AuthenticatedUser {
+                id: EntityId::new(),
+                email: "appro@example.com".to_string(),
+                name: "Appro".to_string(),
+                role: UserRole::Appro,
+                authenticated_at: Utc::now(),
+                token: None,
+            })
+            .unwrap();
+
+        // The oldest day a session may still write to stays open
+        let oldest = today - Duration::days(MAX_BACKDATE_DAYS);
+        manager.set_business_date(Some(oldest)).unwrap();
+        assert!(manager.today() > StockHistoryService::last_closable_day(manager.system_clock().today()));
+        assert!(manager.set_business_date(Some(oldest - Duration::days(1))).is_err());
+    }
+}
@@ -4,20 +4,26 @@
 //! This is the CRITICAL service that handles:
 //! - Stock calculations (SUM(IN) - SUM(OUT))
 //! - FIFO consumption for production
-//! - Receptions (MP entry)
+//! - Receptions (MP entry) and put-away into warehouse locations
 //! - Inventory adjustments
 //! - Loss declarations
 
-use anyhow::{anyhow, Result};
 use chrono::{NaiveDate, Utc};
-use manchengo_core::EntityId;
+use manchengo_core::{
+    CurrencyAmount, EntityId, Error, Money, QrCodeData, QrEntityType, QrKeyRing, Qty, Quantity, Result, SharedClock, UnitOfMeasure,
+};
+use manchengo_domain::stock::{Location, LotCandidate, LotSelection, Warehouse};
 use manchengo_database::Database;
 use manchengo_sync::EventStore;
 use std::sync::Arc;
 use tracing::{info, warn};
 
 use crate::dto::*;
-use crate::repositories::{LotRepository, MovementRepository, ProductRepository, SupplierRepository};
+use crate::repositories::lot_repo::LotPlacement;
+use crate::repositories::{
+    ExchangeRateRepository, LotRepository, MovementRepository, ProductRepository, SupplierRepository,
+    WarehouseRepository,
+};
 
 /// Stock service implementation
 pub struct StockService {
@@ -27,9 +33,15 @@ pub struct StockService {
     lot_repo: Arc<LotRepository>,
     movement_repo: Arc<MovementRepository>,
     supplier_repo: Arc<SupplierRepository>,
+    exchange_rate_repo: Arc<ExchangeRateRepository>,
+    warehouse_repo: Arc<WarehouseRepository>,
+    /// Signing keys for location labels (None when not configured)
+    qr_keys: Option<Arc<QrKeyRing>>,
+    clock: SharedClock,
 }
 
 impl StockService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db: Arc<Database>,
         event_store: Arc<EventStore>,
@@ -37,6 +49,10 @@ impl StockService {
         lot_repo: Arc<LotRepository>,
         movement_repo: Arc<MovementRepository>,
         supplier_repo: Arc<SupplierRepository>,
+        exchange_rate_repo: Arc<ExchangeRateRepository>,
+        warehouse_repo: Arc<WarehouseRepository>,
+        qr_keys: Option<Arc<QrKeyRing>>,
+        clock: SharedClock,
     ) -> Self {
         Self {
             db,
@@ -45,6 +61,10 @@ impl StockService {
             lot_repo,
             movement_repo,
             supplier_repo,
+            exchange_rate_repo,
+            warehouse_repo,
+            qr_keys,
+            clock,
         }
     }
 
@@ -53,13 +73,54 @@ impl StockService {
     // =========================================================================
 
     /// Calculate current stock for a product
-    pub fn calculate_stock(&self, product_type: &str, product_id: &str) -> Result<f64> {
+    pub fn calculate_stock(&self, product_type: &str, product_id: &str) -> Result<Qty> {
         self.movement_repo.calculate_stock(product_type, product_id)
-            .map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Convert a quantity to the product stock unit (ref_units, product pack)
+    ///
+    /// Returns the converted quantity, rounded to the stock unit precision,
+    /// and the stock unit code.
+    pub fn to_stock_unit(
+        &self,
+        product_type: &str,
+        product_id: &str,
+        quantity: f64,
+        unit: &str,
+    ) -> Result<(Qty, String)> {
+        let stock_unit = match product_type {
+            "MP" => self.product_repo.get_mp(product_id)?.map(|p| p.unit),
+            _ => self.product_repo.get_pf(product_id)?.map(|p| p.unit),
+        }
+        .ok_or_else(|| Error::NotFound {
+            entity_type: "Product".to_string(),
+            id: product_id.to_string(),
+        })?;
+
+        if stock_unit == unit {
+            let qty = match UnitOfMeasure::from_code(unit) {
+                Some(u) => Qty::new(quantity, u),
+                None => Qty::from_f64(quantity),
+            };
+            return Ok((qty, stock_unit));
+        }
+
+        let (Some(from), Some(to)) = (UnitOfMeasure::from_code(unit), UnitOfMeasure::from_code(&stock_unit)) else {
+            return Err(Error::Validation {
+                field: "unit".to_string(),
+                message: format!("Conversion impossible de {} vers {}", unit, stock_unit),
+            });
+        };
+
+        let conversions = self.product_repo.unit_conversions()?;
+        let pack = self.product_repo.get_pack(product_type, product_id)?;
+        let converted = conversions.convert(Quantity::new(quantity, from), to, Some(&pack))?;
+
+        Ok((converted.to_qty(), stock_unit))
     }
 
     /// Get stock status based on thresholds
-    pub fn get_stock_status(current: f64, min_stock: f64, reorder_point: f64) -> StockStatus {
+    pub fn get_stock_status(current: Qty, min_stock: f64, reorder_point: f64) -> StockStatus {
         StockStatus::from_levels(current, min_stock, reorder_point)
     }
 
@@ -69,8 +130,13 @@ impl StockService {
             active_only: Some(true),
             ..Default::default()
         })?;
+        let mut last_movements = self.movement_repo.last_movement_dates("MP")?;
+        let lots_count = self.lot_repo.open_lots_count("MP")?;
 
         Ok(products.into_iter().map(|p| StockLevelDto {
+            last_movement_date: last_movements.remove(&p.id),
+            lots_count: lots_count.get(&p.id).copied().unwrap_or(0),
+            available_to_promise: None,
             product_id: p.id,
             product_code: p.code,
             product_name: p.name,
@@ -79,19 +145,27 @@ impl StockService {
             min_stock: p.min_stock,
             reorder_point: p.reorder_point,
             status: p.stock_status,
-            last_movement_date: None, // TODO: compute from movements
-            lots_count: 0,            // TODO: count lots
         }).collect())
     }
 
-    /// Get all PF stock levels
+    /// Get all PF stock levels, with what is not held for sales orders
     pub fn get_stock_pf(&self) -> Result<Vec<StockLevelDto>> {
         let products = self.product_repo.list_pf(ProductFilter {
             active_only: Some(true),
             ..Default::default()
         })?;
+        let mut last_movements = self.movement_repo.last_movement_dates("PF")?;
+        let lots_count = self.lot_repo.open_lots_count("PF")?;
+        let reserved = self.lot_repo.reserved_pf()?;
 
         Ok(products.into_iter().map(|p| StockLevelDto {
+            last_movement_date: last_movements.remove(&p.id),
+            lots_count: lots_count.get(&p.id).copied().unwrap_or(0),
+            available_to_promise: Some(
+                p.current_stock
+                    .saturating_sub(reserved.get(&p.id).copied().unwrap_or_default())
+                    .max(Qty::zero()),
+            ),
             product_id: p.id,
             product_code: p.code,
             product_name: p.name,
@@ -100,8 +174,6 @@ impl StockService {
             min_stock: p.min_stock,
             reorder_point: p.min_stock * 1.5,
             status: p.stock_status,
-            last_movement_date: None,
-            lots_count: 0,
         }).collect())
     }
 
@@ -130,7 +202,7 @@ impl StockService {
                 product_type: "MP".to_string(),
                 current_stock: p.current_stock,
                 min_stock: p.min_stock,
-                deficit: (p.min_stock - p.current_stock).max(0.0),
+                deficit: (p.min_stock - p.current_stock.as_f64()).max(0.0),
                 status: p.stock_status.clone(),
             };
 
@@ -151,7 +223,7 @@ impl StockService {
                 product_type: "PF".to_string(),
                 current_stock: p.current_stock,
                 min_stock: p.min_stock,
-                deficit: (p.min_stock - p.current_stock).max(0.0),
+                deficit: (p.min_stock - p.current_stock.as_f64()).max(0.0),
                 status: p.stock_status.clone(),
             };
 
@@ -183,83 +255,101 @@ impl StockService {
     // =========================================================================
 
     /// Preview FIFO consumption without actually consuming
-    pub fn preview_fifo(&self, product_id: &str, quantity: f64) -> Result<FifoPreviewDto> {
-        let lots = self.lot_repo.get_available_fifo(product_id)?;
-
-        let mut remaining = quantity;
-        let mut preview_lots = Vec::new();
-        let mut available = 0.0;
-
-        for lot in lots {
-            available += lot.quantity_remaining;
-
-            let to_consume = remaining.min(lot.quantity_remaining);
-            if to_consume > 0.0 {
-                preview_lots.push(FifoLotPreview {
-                    lot_id: lot.id,
-                    lot_number: lot.lot_number,
+    ///
+    /// Lots are chosen with the product selection strategy; `manual_lots`
+    /// are the operator picks of the manual strategy.
+    pub fn preview_fifo(
+        &self,
+        product_id: &str,
+        warehouse_id: Option<&str>,
+        quantity: Qty,
+        manual_lots: &[ManualLotPickDto],
+    ) -> Result<FifoPreviewDto> {
+        let warehouse_id = self.resolve_warehouse(warehouse_id)?;
+        let lots = self.lot_repo.get_available_lots(product_id, &warehouse_id)?;
+        let selection = self.select_lots("MP", product_id, &lots, quantity, manual_lots)?;
+
+        let preview_lots = selection
+            .picks
+            .iter()
+            .filter_map(|pick| {
+                let lot = lots.iter().find(|l| l.id == pick.lot_id.to_string())?;
+                Some(FifoLotPreview {
+                    lot_id: lot.id.clone(),
+                    lot_number: lot.lot_number.clone(),
                     quantity_available: lot.quantity_remaining,
-                    quantity_to_consume: to_consume,
-                    expiry_date: lot.expiry_date,
-                    reception_date: lot.reception_date,
-                });
-                remaining -= to_consume;
-            }
-
-            if remaining <= 0.0 {
-                break;
-            }
-        }
+                    quantity_to_consume: pick.quantity,
+                    expiry_date: lot.expiry_date.clone(),
+                    reception_date: lot.reception_date.clone(),
+                    reason: pick.reason.clone(),
+                })
+            })
+            .collect();
 
         Ok(FifoPreviewDto {
             product_id: product_id.to_string(),
+            strategy: selection.strategy,
             requested_quantity: quantity,
-            available_quantity: available,
-            can_fulfill: remaining <= 0.0,
+            available_quantity: lots.iter().map(|l| l.quantity_remaining).sum(),
+            can_fulfill: selection.is_complete(),
             lots: preview_lots,
-            shortage: remaining.max(0.0),
+            excluded: selection
+                .excluded
+                .into_iter()
+                .map(|e| ExcludedLotDto {
+                    lot_id: e.lot_id.to_string(),
+                    lot_number: e.lot_number,
+                    reason: e.reason,
+                })
+                .collect(),
+            shortage: selection.shortage,
         })
     }
 
-    /// Consume MP using strict FIFO order
+    /// Consume MP following the product lot selection strategy
     /// This is THE critical business rule for stock management
+    ///
+    /// Only lots of one warehouse are consumed (default warehouse if `None`).
+    /// Products on the manual strategy need `manual_lots`.
+    #[allow(clippy::too_many_arguments)]
     pub fn consume_fifo(
         &self,
         product_id: &str,
-        quantity: f64,
+        warehouse_id: Option<&str>,
+        quantity: Qty,
+        manual_lots: &[ManualLotPickDto],
         origin: &str, // e.g., "PRODUCTION_OUT"
         reference_type: Option<&str>,
         reference_id: Option<&str>,
         user_id: &str,
     ) -> Result<FifoResultDto> {
-        // 1. Get lots sorted by FIFO (oldest first, earliest expiry first)
-        let lots = self.lot_repo.get_available_fifo(product_id)?;
+        // 1. Get the lots of the warehouse and let the strategy choose
+        let warehouse_id = self.resolve_warehouse(warehouse_id)?;
+        let lots = self.lot_repo.get_available_lots(product_id, &warehouse_id)?;
 
         if lots.is_empty() {
-            return Err(anyhow!("Aucun lot disponible pour le produit {}", product_id));
+            return Err(self.insufficient_stock(product_id, quantity, Qty::zero()));
         }
 
-        // 2. Verify we have enough stock
-        let total_available: f64 = lots.iter().map(|l| l.quantity_remaining).sum();
-        if total_available < quantity {
-            return Err(anyhow!(
-                "Stock insuffisant: {} disponible, {} demande",
-                total_available,
-                quantity
-            ));
+        // 2. Verify the chosen lots cover the quantity
+        let selection = self.select_lots("MP", product_id, &lots, quantity, manual_lots)?;
+        if !selection.is_complete() {
+            return Err(self.insufficient_stock(product_id, quantity, quantity - selection.shortage));
         }
 
-        // 3. Consume in FIFO order
-        let mut remaining = quantity;
+        // 3. Consume the chosen lots in order
         let mut consumptions = Vec::new();
         let mut movements_created = Vec::new();
 
-        for lot in lots {
-            if remaining <= 0.0 {
-                break;
-            }
-
-            let to_consume = remaining.min(lot.quantity_remaining);
+        for pick in selection.picks {
+            let lot = lots
+                .iter()
+                .find(|l| l.id == pick.lot_id.to_string())
+                .ok_or_else(|| Error::NotFound {
+                    entity_type: "LotMp".to_string(),
+                    id: pick.lot_id.to_string(),
+                })?;
+            let to_consume = pick.quantity;
             let new_quantity = lot.quantity_remaining - to_consume;
 
             // Create movement (OUT)
@@ -286,19 +376,19 @@ impl StockService {
             self.lot_repo.update_quantity_mp(&lot.id, new_quantity)?;
 
             info!(
-                "FIFO: Consumed {} from lot {} (remaining: {})",
-                to_consume, lot.lot_number, new_quantity
+                "{}: Consumed {} from lot {} (remaining: {}) - {}",
+                selection.strategy.as_str(), to_consume, lot.lot_number, new_quantity, pick.reason
             );
 
             consumptions.push(FifoConsumption {
                 lot_id: lot.id.clone(),
                 lot_number: lot.lot_number.clone(),
                 quantity_consumed: to_consume,
-                lot_depleted: new_quantity <= 0.0,
+                lot_depleted: !new_quantity.is_positive(),
+                reason: pick.reason,
             });
 
             movements_created.push(movement_id);
-            remaining -= to_consume;
         }
 
         // 4. Emit event for sync
@@ -311,6 +401,63 @@ impl StockService {
         })
     }
 
+    /// Run the product strategy over available lots
+    fn select_lots(
+        &self,
+        product_type: &str,
+        product_id: &str,
+        lots: &[LotMpDto],
+        quantity: Qty,
+        manual_lots: &[ManualLotPickDto],
+    ) -> Result<LotSelection> {
+        let strategy = self.product_repo.get_lot_strategy(product_type, product_id)?;
+
+        let candidates = lots
+            .iter()
+            .map(|lot| {
+                Ok(LotCandidate {
+                    lot_id: lot.id.parse().map_err(|_| Error::Internal(format!("Identifiant invalide en base: {}", lot.id)))?,
+                    lot_number: lot.lot_number.clone(),
+                    entry_date: Self::lot_date(&lot.reception_date)
+                        .ok_or_else(|| Error::Internal(format!("Lot {}: date de reception invalide", lot.lot_number)))?,
+                    expiry_date: lot.expiry_date.as_deref().and_then(Self::lot_date),
+                    quantity: lot.quantity_remaining,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let manual = manual_lots
+            .iter()
+            .map(|pick| Ok((Self::parse_id("lot_id", &pick.lot_id)?, pick.quantity)))
+            .collect::<Result<Vec<_>>>()?;
+
+        strategy.select(&candidates, quantity, &manual, self.clock.today())
+    }
+
+    /// Shortage of an MP product, labelled with its code
+    fn insufficient_stock(&self, product_id: &str, required: Qty, available: Qty) -> Error {
+        let product = match self.product_repo.get_mp(product_id) {
+            Ok(Some(product)) => product.code,
+            _ => product_id.to_string(),
+        };
+        Error::InsufficientStock {
+            product,
+            required: required.as_f64(),
+            available: available.as_f64(),
+        }
+    }
+
+    fn parse_id(field: &str, value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Validation {
+            field: field.to_string(),
+            message: format!("Identifiant invalide: {}", value),
+        })
+    }
+
+    /// Date part of a stored lot date (plain date or RFC 3339 timestamp)
+    fn lot_date(value: &str) -> Option<NaiveDate> {
+        value.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    }
+
     // =========================================================================
     // RECEPTION
     // =========================================================================
@@ -323,37 +470,84 @@ impl StockService {
     ) -> Result<ReceptionDto> {
         // Validate supplier exists
         let supplier = self.supplier_repo.get(&data.supplier_id)?
-            .ok_or_else(|| anyhow!("Fournisseur non trouve: {}", data.supplier_id))?;
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "Supplier".to_string(),
+                id: data.supplier_id.clone(),
+            })?;
 
         // Validate date
         let reception_date = data.date.clone();
+        let reception_day = reception_date
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .ok_or_else(|| Error::Validation {
+                field: "date".to_string(),
+                message: format!("Date de reception invalide: {}", reception_date),
+            })?;
+
+        // Lots land in the reception location and wait for put-away, unless
+        // the storage location is given upfront
+        let warehouse_id = self.resolve_warehouse(data.warehouse_id.as_deref())?;
+        let location = match data.location_id.as_deref() {
+            Some(location_id) => Some(self.storage_location(location_id, &warehouse_id)?),
+            None => self.warehouse_repo.reception_location(&warehouse_id)?,
+        };
+        let placement = LotPlacement {
+            warehouse_id: &warehouse_id,
+            location_id: location.as_ref().map(|l| l.id.as_str()),
+            put_away: data.location_id.is_some(),
+        };
+
+        // Foreign currency: unit costs converted at the reception-date rate
+        let exchange_rate = if data.currency.is_local() {
+            None
+        } else {
+            Some(self.exchange_rate_repo.rate_on(data.currency, reception_day)?)
+        };
 
         // Generate reference
         let reference = format!(
             "REC-{}-{:04}",
-            Utc::now().format("%Y%m%d"),
+            self.clock.today().format("%Y%m%d"),
             rand::random::<u16>() % 10000
         );
 
         let reception_id = EntityId::new().to_string();
         let mut lines_response = Vec::new();
         let mut total_ht: i64 = 0;
+        let mut total_ht_currency: i64 = 0;
         let mut total_tva: i64 = 0;
 
         for (idx, line) in data.lines.iter().enumerate() {
             // Validate product exists
             let product = self.product_repo.get_mp(&line.product_mp_id)?
-                .ok_or_else(|| anyhow!("Produit MP non trouve: {}", line.product_mp_id))?;
+                .ok_or_else(|| Error::NotFound {
+                    entity_type: "ProductMp".to_string(),
+                    id: line.product_mp_id.clone(),
+                })?;
 
             // Validate quantity
-            if line.quantity <= 0.0 {
-                return Err(anyhow!("Quantite invalide pour ligne {}", idx + 1));
+            let quantity = match UnitOfMeasure::from_code(&product.unit) {
+                Some(unit) => Qty::new(line.quantity, unit),
+                None => Qty::from_f64(line.quantity),
+            };
+            if !quantity.is_positive() {
+                return Err(Error::Validation {
+                    field: "quantity".to_string(),
+                    message: format!("Quantite invalide pour ligne {}", idx + 1),
+                });
             }
 
+            let purchase_price = CurrencyAmount::new(line.unit_cost, data.currency);
+            let unit_cost = match &exchange_rate {
+                Some(rate) => rate.convert(purchase_price)?.centimes(),
+                None => line.unit_cost,
+            };
+
             // Create lot
             let lot_id = EntityId::new().to_string();
             let lot_number = line.lot_number.clone().unwrap_or_else(|| {
-                format!("LOT-{}-{:03}", Utc::now().format("%Y%m%d"), idx + 1)
+                format!("LOT-{}-{:03}", self.clock.today().format("%Y%m%d"), idx + 1)
             });
 
             self.lot_repo.create_mp(
@@ -361,10 +555,12 @@ impl StockService {
                 &lot_number,
                 &line.product_mp_id,
                 Some(&data.supplier_id),
-                line.quantity,
-                line.unit_cost,
+                quantity,
+                unit_cost,
                 &reception_date,
                 line.expiry_date.as_deref(),
+                exchange_rate.as_ref().map(|rate| (purchase_price, rate)),
+                placement,
             )?;
 
             // Create stock movement (IN)
@@ -377,8 +573,8 @@ impl StockService {
                 "MP",
                 &line.product_mp_id,
                 Some(&lot_id),
-                line.quantity,
-                Some(line.unit_cost),
+                quantity,
+                Some(unit_cost),
                 "RECEPTION",
                 Some("RECEPTION"),
                 Some(&reception_id),
@@ -388,11 +584,12 @@ impl StockService {
             )?;
 
             // Calculate line totals
-            let line_total = (line.quantity * line.unit_cost as f64) as i64;
+            let line_total = quantity.value_at(Money::from_centimes(unit_cost)).centimes();
             let tva_rate = line.tva_rate.unwrap_or(0.19);
             let line_tva = (line_total as f64 * tva_rate) as i64;
 
             total_ht += line_total;
+            total_ht_currency += quantity.value_at(Money::from_centimes(line.unit_cost)).centimes();
             total_tva += line_tva;
 
             lines_response.push(ReceptionLineResponseDto {
@@ -400,21 +597,24 @@ impl StockService {
                 product_mp_id: line.product_mp_id.clone(),
                 product_code: product.code,
                 product_name: product.name,
-                quantity: line.quantity,
+                quantity: quantity.as_f64(),
                 unit: product.unit,
-                unit_cost: line.unit_cost,
+                unit_cost,
+                unit_cost_currency: line.unit_cost,
                 line_total,
                 tva_rate,
                 lot_id,
                 lot_number,
                 expiry_date: line.expiry_date.clone(),
+                warehouse_id: warehouse_id.clone(),
+                location_id: placement.location_id.map(str::to_string),
             });
 
             info!(
                 "Reception: Created lot {} for product {} (qty: {})",
                 lines_response.last().unwrap().lot_number,
                 lines_response.last().unwrap().product_name,
-                line.quantity
+                quantity
             );
         }
 
@@ -427,14 +627,189 @@ impl StockService {
             bl_number: data.bl_number,
             note: data.note,
             lines: lines_response,
+            currency: data.currency,
+            exchange_rate: exchange_rate.map(|rate| rate.rate_dzd()),
+            total_ht_currency,
             total_ht,
             total_tva,
             total_ttc: total_ht + total_tva,
-            created_at: Utc::now().to_rfc3339(),
+            created_at: self.clock.now().to_rfc3339(),
             created_by: user_id.to_string(),
         })
     }
 
+    // =========================================================================
+    // WAREHOUSES & PUT-AWAY
+    // =========================================================================
+
+    /// Create warehouse (and its reception location)
+    pub fn create_warehouse(&self, data: CreateWarehouseDto, user_id: &str) -> Result<WarehouseDto> {
+        let mut warehouse = Warehouse::new(data.code, data.name, data.wilaya_code)?;
+        warehouse.address = data.address;
+
+        self.warehouse_repo.create_warehouse(&warehouse, user_id)?;
+        info!("Warehouse {} created", warehouse.code);
+
+        self.warehouse_repo
+            .get_warehouse(&warehouse.id.to_string())?
+            .ok_or_else(|| Self::warehouse_not_found(&warehouse.id.to_string()))
+    }
+
+    /// Create location with its signed QR label
+    pub fn create_location(&self, data: CreateLocationDto, user_id: &str) -> Result<LocationDto> {
+        let warehouse = self.warehouse_repo.get_warehouse(&data.warehouse_id)?
+            .ok_or_else(|| Self::warehouse_not_found(&data.warehouse_id))?;
+        let qr_keys = self.qr_keys.as_ref()
+            .ok_or_else(|| Error::Configuration("Cles QR non configurees (QR_KEYS)".to_string()))?;
+
+        let location = Location::new(
+            Self::parse_id("warehouse_id", &warehouse.id)?,
+            data.code,
+            data.name,
+            data.location_type,
+            Self::parse_id("user_id", user_id)?,
+            qr_keys,
+            self.clock.as_ref(),
+        )?;
+
+        self.warehouse_repo.create_location(&location)?;
+        info!("Location {} created in warehouse {}", location.code, warehouse.code);
+
+        self.warehouse_repo
+            .get_location(&location.id.to_string())?
+            .ok_or_else(|| Self::location_not_found(&location.id.to_string()))
+    }
+
+    /// Resolve a scanned location label
+    pub fn resolve_location_scan(&self, qr_code: &str) -> Result<LocationDto> {
+        let qr_keys = self.qr_keys.as_ref()
+            .ok_or_else(|| Error::Configuration("Cles QR non configurees (QR_KEYS)".to_string()))?;
+        let qr = QrCodeData::decode(qr_code, qr_keys)
+            .map_err(|e| Error::QrCode(format!("QR code invalide: {:?}", e)))?;
+        if qr.entity_type != QrEntityType::Location {
+            return Err(Error::QrCode("Ce QR code n'est pas un emplacement".to_string()));
+        }
+
+        self.warehouse_repo
+            .get_location(&qr.entity_id.to_string())?
+            .ok_or_else(|| Self::location_not_found(&qr.reference))
+    }
+
+    /// Put a received lot away into its storage location
+    ///
+    /// The location must be active and in the lot's warehouse; once put away
+    /// in a pickable location the lot becomes available to FIFO.
+    pub fn put_away(&self, data: PutAwayDto, user_id: &str) -> Result<LocationDto> {
+        let (lot_number, warehouse_id) = match data.product_type.as_str() {
+            "MP" => self.lot_repo.get_mp(&data.lot_id)?.map(|l| (l.lot_number, l.warehouse_id)),
+            _ => self.lot_repo.get_pf(&data.lot_id)?.map(|l| (l.lot_number, l.warehouse_id)),
+        }
+        .ok_or_else(|| Error::NotFound {
+            entity_type: "Lot".to_string(),
+            id: data.lot_id.clone(),
+        })?;
+        let warehouse_id = warehouse_id
+            .ok_or_else(|| Error::BusinessRule(format!("Lot {} sans entrepot", lot_number)))?;
+
+        let location = match (&data.location_id, &data.location_qr) {
+            (Some(location_id), _) => self.storage_location(location_id, &warehouse_id)?,
+            (None, Some(qr_code)) => {
+                let location = self.resolve_location_scan(qr_code)?;
+                self.storage_location(&location.id, &warehouse_id)?
+            }
+            (None, None) => {
+                return Err(Error::Validation {
+                    field: "location_id".to_string(),
+                    message: "Emplacement requis".to_string(),
+                })
+            }
+        };
+
+        self.lot_repo.assign_location(&data.product_type, &data.lot_id, &location.id, true)?;
+
+        info!(
+            "Put-away: lot {} stored in {} by {}",
+            lot_number, location.code, user_id
+        );
+        Ok(location)
+    }
+
+    /// Stock per warehouse (all warehouses if `None`)
+    pub fn get_warehouse_stock(&self, warehouse_id: Option<&str>) -> Result<Vec<WarehouseStockDto>> {
+        self.warehouse_repo.stock_by_warehouse(warehouse_id)
+    }
+
+    /// Products below their warehouse minimum
+    pub fn get_warehouse_alerts(&self, warehouse_id: Option<&str>) -> Result<Vec<WarehouseStockDto>> {
+        Ok(self.get_warehouse_stock(warehouse_id)?
+            .into_iter()
+            .filter(|s| s.status != StockStatus::Ok)
+            .collect())
+    }
+
+    /// Set the minimum stock of a product in a warehouse
+    pub fn set_warehouse_threshold(&self, data: SetWarehouseThresholdDto) -> Result<()> {
+        if data.min_stock.is_negative() || data.reorder_point < data.min_stock {
+            return Err(Error::Validation {
+                field: "min_stock".to_string(),
+                message: format!("Seuils invalides: minimum {}, reapprovisionnement {}", data.min_stock, data.reorder_point),
+            });
+        }
+        self.warehouse_repo.get_warehouse(&data.warehouse_id)?
+            .ok_or_else(|| Self::warehouse_not_found(&data.warehouse_id))?;
+
+        self.warehouse_repo.set_threshold(
+            &data.warehouse_id,
+            &data.product_type,
+            &data.product_id,
+            data.min_stock,
+            data.reorder_point,
+        )?;
+        Ok(())
+    }
+
+    /// Given warehouse, or the default one
+    fn resolve_warehouse(&self, warehouse_id: Option<&str>) -> Result<String> {
+        match warehouse_id {
+            Some(id) => {
+                let warehouse = self.warehouse_repo.get_warehouse(id)?
+                    .ok_or_else(|| Self::warehouse_not_found(id))?;
+                if !warehouse.is_active {
+                    return Err(Error::BusinessRule(format!("Entrepot {} inactif", warehouse.code)));
+                }
+                Ok(warehouse.id)
+            }
+            None => Ok(self.warehouse_repo.default_warehouse_id()?),
+        }
+    }
+
+    /// Active location of `warehouse_id` where lots can be stored
+    fn storage_location(&self, location_id: &str, warehouse_id: &str) -> Result<LocationDto> {
+        let location = self.warehouse_repo.get_location(location_id)?
+            .ok_or_else(|| Self::location_not_found(location_id))?;
+        if !location.is_active {
+            return Err(Error::BusinessRule(format!("Emplacement {} inactif", location.code)));
+        }
+        if location.warehouse_id != warehouse_id {
+            return Err(Error::BusinessRule(format!("Emplacement {} hors de l'entrepot du lot", location.code)));
+        }
+        Ok(location)
+    }
+
+    fn warehouse_not_found(id: &str) -> Error {
+        Error::NotFound {
+            entity_type: "Warehouse".to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    fn location_not_found(id: &str) -> Error {
+        Error::NotFound {
+            entity_type: "Location".to_string(),
+            id: id.to_string(),
+        }
+    }
+
     // =========================================================================
     // INVENTORY ADJUSTMENT
     // =========================================================================
@@ -449,17 +824,20 @@ impl StockService {
         let current_stock = self.calculate_stock(&data.product_type, &data.product_id)?;
         let difference = data.physical_quantity - current_stock;
 
-        if difference.abs() < 0.001 {
-            return Err(anyhow!("Pas de difference entre stock physique et theorique"));
+        if difference.is_zero() {
+            return Err(Error::BusinessRule("Pas de difference entre stock physique et theorique".to_string()));
         }
 
         // Validate reason
         if data.reason.len() < 5 {
-            return Err(anyhow!("Raison trop courte (min 5 caracteres)"));
+            return Err(Error::Validation {
+                field: "reason".to_string(),
+                message: "Raison trop courte (min 5 caracteres)".to_string(),
+            });
         }
 
         // Determine movement type
-        let movement_type = if difference > 0.0 { "IN" } else { "OUT" };
+        let movement_type = if difference.is_positive() { "IN" } else { "OUT" };
         let quantity = difference.abs();
 
         // Create movement
@@ -497,7 +875,7 @@ impl StockService {
         info!(
             "Inventory adjustment: {} {} (diff: {} {})",
             product_name,
-            if difference > 0.0 { "+" } else { "-" },
+            if difference.is_positive() { "+" } else { "-" },
             quantity,
             data.product_type
         );
@@ -509,9 +887,9 @@ impl StockService {
             previous_stock: current_stock,
             physical_stock: data.physical_quantity,
             difference,
-            movement_type: if difference > 0.0 { MovementType::In } else { MovementType::Out },
+            movement_type: if difference.is_positive() { MovementType::In } else { MovementType::Out },
             reason: data.reason,
-            adjusted_at: Utc::now().to_rfc3339(),
+            adjusted_at: self.clock.now().to_rfc3339(),
             adjusted_by: user_id.to_string(),
         })
     }
@@ -527,13 +905,19 @@ impl StockService {
         user_id: &str,
     ) -> Result<LossDeclarationDto> {
         // Validate quantity
-        if data.quantity <= 0.0 {
-            return Err(anyhow!("Quantite doit etre superieure a 0"));
+        if !data.quantity.is_positive() {
+            return Err(Error::Validation {
+                field: "quantity".to_string(),
+                message: "Quantite doit etre superieure a 0".to_string(),
+            });
         }
 
         // Validate reason
         if data.reason.len() < 3 {
-            return Err(anyhow!("Raison trop courte"));
+            return Err(Error::Validation {
+                field: "reason".to_string(),
+                message: "Raison trop courte".to_string(),
+            });
         }
 
         // Get product info
@@ -541,7 +925,10 @@ impl StockService {
             self.product_repo.get_mp(&data.product_id)?.map(|p| p.name)
         } else {
             self.product_repo.get_pf(&data.product_id)?.map(|p| p.name)
-        }.ok_or_else(|| anyhow!("Produit non trouve"))?;
+        }.ok_or_else(|| Error::NotFound {
+            entity_type: "Product".to_string(),
+            id: data.product_id.clone(),
+        })?;
 
         // Get lot info if specified
         let lot_number = if let Some(ref lot_id) = data.lot_id {
@@ -582,7 +969,7 @@ impl StockService {
         if let Some(ref lot_id) = data.lot_id {
             if data.product_type == "MP" {
                 if let Some(lot) = self.lot_repo.get_mp(lot_id)? {
-                    let new_qty = (lot.quantity_remaining - data.quantity).max(0.0);
+                    let new_qty = (lot.quantity_remaining - data.quantity).max(Qty::zero());
                     self.lot_repo.update_quantity_mp(lot_id, new_qty)?;
                 }
             }
@@ -602,7 +989,7 @@ impl StockService {
             quantity: data.quantity,
             reason: data.reason,
             description: data.description,
-            declared_at: Utc::now().to_rfc3339(),
+            declared_at: self.clock.now().to_rfc3339(),
             declared_by: user_id.to_string(),
         })
     }
@@ -634,6 +1021,5 @@ impl StockService {
     /// Get expiring lots
     pub fn get_expiring_lots(&self, days: i32) -> Result<Vec<ExpiringLotDto>> {
         self.lot_repo.get_expiring(days)
-            .map_err(|e| anyhow!("{}", e))
     }
 }
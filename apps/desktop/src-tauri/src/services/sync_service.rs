@@ -15,8 +15,9 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
-use crate::core::AppConfig;
+use crate::core::{Activity, ActivityTracker, AppConfig};
 use crate::dto::{PullResultDto, PushResultDto, SyncResultDto, SyncStatusDto};
+use crate::services::TrashService;
 
 /// Sync service for offline-first operation
 pub struct SyncService {
@@ -26,6 +27,8 @@ pub struct SyncService {
     http_client: Client,
     config: Arc<RwLock<AppConfig>>,
     device_id: EntityId,
+    trash_service: Arc<TrashService>,
+    activity: Arc<ActivityTracker>,
     is_online: Arc<AtomicBool>,
     last_push: Arc<RwLock<Option<DateTime<Utc>>>>,
     last_pull: Arc<RwLock<Option<DateTime<Utc>>>>,
@@ -38,6 +41,8 @@ impl SyncService {
         sync_queue: Arc<SyncQueue>,
         config: Arc<RwLock<AppConfig>>,
         device_id: EntityId,
+        trash_service: Arc<TrashService>,
+        activity: Arc<ActivityTracker>,
     ) -> Self {
         Self {
             db,
@@ -49,6 +54,8 @@ impl SyncService {
                 .unwrap_or_default(),
             config,
             device_id,
+            trash_service,
+            activity,
             is_online: Arc::new(AtomicBool::new(false)),
             last_push: Arc::new(RwLock::new(None)),
             last_pull: Arc::new(RwLock::new(None)),
@@ -85,6 +92,8 @@ impl SyncService {
 
     /// Push local events to server
     pub async fn push(&self, auth_token: &str) -> Result<PushResultDto> {
+        let _activity = self.activity.begin(Activity::Sync);
+
         // Get unsynced events
         let events = self.event_store.get_unsynced(100)?;
 
@@ -164,6 +173,7 @@ impl SyncService {
 
     /// Pull events from server
     pub async fn pull(&self, auth_token: &str) -> Result<PullResultDto> {
+        let _activity = self.activity.begin(Activity::Sync);
         let config = self.config.read().await;
         let last_pull = self.last_pull.read().await;
 
@@ -259,7 +269,19 @@ impl SyncService {
     }
 
     /// Apply a single event from server
-    fn apply_event(&self, _event: &SyncEventDto) -> Result<()> {
+    fn apply_event(&self, event: &SyncEventDto) -> Result<()> {
+        let payload: serde_json::Value = serde_json::from_str(&event.payload)?;
+
+        // Tombstones (EntityDeleted / EntityRestored)
+        if self.trash_service.apply_remote(
+            &event.event_type,
+            &event.aggregate_type,
+            &event.aggregate_id,
+            &payload,
+        )? {
+            return Ok(());
+        }
+
         // TODO: Implement event application based on aggregate_type
         // This should update local database based on event type
         Ok(())
@@ -0,0 +1,457 @@
+//! Transfer Service
+//!
+//! Business logic for inter-warehouse transfer orders:
+//! - Lot picking (by id or scanned lot QR code)
+//! - Shipping: stock leaves the origin warehouse for the in-transit location
+//!   of the destination, with an OUT and an IN movement linked to the transfer
+//! - Receipt into the destination reception dock, with discrepancies
+
+use manchengo_core::{AuditInfo, EntityId, Error, Money, Qty, Result, SharedClock};
+use manchengo_database::Database;
+use manchengo_domain::stock::{ProductType, TransferLine, TransferOrder, TransferReceipt};
+use rusqlite::Connection;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::dto::*;
+use crate::repositories::{LotRepository, MovementRepository, TransferRepository, WarehouseRepository};
+
+/// Lot as seen by a transfer
+struct PickedLot {
+    product_id: String,
+    lot_number: String,
+    warehouse_id: Option<String>,
+    quantity_remaining: Qty,
+    unit_cost: i64,
+    status: LotStatus,
+}
+
+/// Transfer service for inter-warehouse stock moves
+pub struct TransferService {
+    db: Arc<Database>,
+    transfer_repo: Arc<TransferRepository>,
+    lot_repo: Arc<LotRepository>,
+    movement_repo: Arc<MovementRepository>,
+    warehouse_repo: Arc<WarehouseRepository>,
+    clock: SharedClock,
+}
+
+impl TransferService {
+    pub fn new(
+        db: Arc<Database>,
+        transfer_repo: Arc<TransferRepository>,
+        lot_repo: Arc<LotRepository>,
+        movement_repo: Arc<MovementRepository>,
+        warehouse_repo: Arc<WarehouseRepository>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            db,
+            transfer_repo,
+            lot_repo,
+            movement_repo,
+            warehouse_repo,
+            clock,
+        }
+    }
+
+    /// List transfers
+    pub fn list_transfers(&self, filter: TransferFilter) -> Result<Vec<TransferOrderDto>> {
+        self.transfer_repo.list(filter)
+    }
+
+    /// Get single transfer
+    pub fn get_transfer(&self, id: &str) -> Result<TransferOrderDto> {
+        self.transfer_repo.get(id)?.ok_or_else(|| Error::NotFound {
+            entity_type: "TransferOrder".to_string(),
+            id: id.to_string(),
+        })
+    }
+
+    /// Create draft transfer between two active warehouses
+    pub fn create_transfer(&self, data: CreateTransferDto, user_id: &str) -> Result<TransferOrderDto> {
+        for warehouse_id in [&data.from_warehouse_id, &data.to_warehouse_id] {
+            let warehouse = self.warehouse_repo.get_warehouse(warehouse_id)?.ok_or_else(|| Error::NotFound {
+                entity_type: "Warehouse".to_string(),
+                id: warehouse_id.clone(),
+            })?;
+            if !warehouse.is_active {
+                return Err(Error::BusinessRule(format!("Entrepot {} inactif", warehouse.code)));
+            }
+        }
+
+        // Validates the route before a reference is allocated
+        TransferOrder::new(
+            String::new(),
+            Self::parse_id("from_warehouse_id", &data.from_warehouse_id)?,
+            Self::parse_id("to_warehouse_id", &data.to_warehouse_id)?,
+            Self::parse_id("user_id", user_id)?,
+            self.clock.as_ref(),
+        )?;
+
+        let id = EntityId::new().to_string();
+        let reference = self.transfer_repo.create(&id, self.clock.today(), &data, user_id)?;
+
+        info!("Created transfer {} by {}", reference, user_id);
+        self.get_transfer(&id)
+    }
+
+    /// Pick a lot of the origin warehouse, by id or scanned QR code
+    ///
+    /// Without a quantity the whole remaining lot is picked.
+    pub fn add_line(&self, data: AddTransferLineDto, user_id: &str) -> Result<TransferOrderDto> {
+        let transfer = self.get_transfer(&data.transfer_id)?;
+        let mut order = self.to_domain(&transfer)?;
+
+        let (product_type, lot_id) = match (&data.product_type, &data.lot_id, &data.lot_qr) {
+            (Some(product_type), Some(lot_id), _) => (product_type.clone(), lot_id.clone()),
+            (_, _, Some(qr_code)) => self.lot_repo.find_by_qr(qr_code)?.ok_or_else(|| Error::NotFound {
+                entity_type: "Lot".to_string(),
+                id: qr_code.clone(),
+            })?,
+            _ => {
+                return Err(Error::Validation {
+                    field: "lot_id".to_string(),
+                    message: "Lot requis (identifiant ou QR code)".to_string(),
+                })
+            }
+        };
+
+        let lot = self.picked_lot(&product_type, &lot_id)?;
+        if lot.warehouse_id.as_deref() != Some(transfer.from_warehouse_id.as_str()) {
+            return Err(Error::BusinessRule(format!(
+                "Lot {} hors de l'entrepot {}",
+                lot.lot_number, transfer.from_warehouse_code
+            )));
+        }
+        if lot.status != LotStatus::Available {
+            return Err(Error::BusinessRule(format!("Lot {} non disponible", lot.lot_number)));
+        }
+
+        let line_id = order.add_line(
+            Self::product_type(&product_type),
+            Self::parse_id("product_id", &lot.product_id)?,
+            Self::parse_id("lot_id", &lot_id)?,
+            lot.lot_number.clone(),
+            data.quantity.unwrap_or(lot.quantity_remaining),
+            lot.quantity_remaining,
+            Money::from_centimes(lot.unit_cost),
+            Self::parse_id("user_id", user_id)?,
+            self.clock.as_ref(),
+        )?;
+        let line = order.lines.iter().find(|l| l.id == line_id).expect("line just added");
+        self.transfer_repo.add_line(&transfer.id, line)?;
+
+        info!(
+            "Transfer {}: picked {} of lot {}",
+            transfer.reference, line.quantity_shipped, lot.lot_number
+        );
+        self.get_transfer(&transfer.id)
+    }
+
+    /// Remove a picked lot
+    pub fn remove_line(&self, transfer_id: &str, line_id: &str, user_id: &str) -> Result<TransferOrderDto> {
+        let transfer = self.get_transfer(transfer_id)?;
+        let mut order = self.to_domain(&transfer)?;
+        order.remove_line(
+            Self::parse_id("line_id", line_id)?,
+            Self::parse_id("user_id", user_id)?,
+            self.clock.as_ref(),
+        )?;
+
+        self.transfer_repo.remove_line(transfer_id, line_id)?;
+        self.get_transfer(transfer_id)
+    }
+
+    /// Ship transfer (DRAFT -> SHIPPED)
+    ///
+    /// Each picked lot leaves the origin warehouse for the in-transit location
+    /// of the destination at its own unit cost. A lot shipped in full travels
+    /// as is; otherwise the shipped part is split into a new lot. All stock
+    /// moves and the status change are written in one transaction.
+    pub fn ship(&self, id: &str, user_id: &str) -> Result<TransferOrderDto> {
+        let transfer = self.get_transfer(id)?;
+        let mut order = self.to_domain(&transfer)?;
+        order.ship(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        let transit = self.warehouse_repo.transit_location(&transfer.to_warehouse_id)?.ok_or_else(|| {
+            Error::Configuration(format!("Entrepot {} sans emplacement de transit", transfer.to_warehouse_code))
+        })?;
+
+        // Check every lot before moving any stock
+        let mut lots = Vec::with_capacity(transfer.lines.len());
+        for line in &transfer.lines {
+            let lot = self.picked_lot(&line.product_type, &line.lot_id)?;
+            if lot.warehouse_id.as_deref() != Some(transfer.from_warehouse_id.as_str()) {
+                return Err(Error::BusinessRule(format!(
+                    "Lot {} hors de l'entrepot {}",
+                    lot.lot_number, transfer.from_warehouse_code
+                )));
+            }
+            if lot.status != LotStatus::Available {
+                return Err(Error::BusinessRule(format!("Lot {} non disponible", lot.lot_number)));
+            }
+            if lot.quantity_remaining < line.quantity_shipped {
+                return Err(Error::InsufficientStock {
+                    product: lot.lot_number,
+                    required: line.quantity_shipped.as_f64(),
+                    available: lot.quantity_remaining.as_f64(),
+                });
+            }
+            lots.push(lot);
+        }
+
+        self.db.transaction(|tx| {
+            for (line, lot) in transfer.lines.iter().zip(&lots) {
+                let whole_lot = line.quantity_shipped == lot.quantity_remaining;
+                if !whole_lot {
+                    LotRepository::update_quantity_on(
+                        tx,
+                        &line.product_type,
+                        &line.lot_id,
+                        lot.quantity_remaining - line.quantity_shipped,
+                    )?;
+                }
+                self.movement(tx, &transfer, line, &line.lot_id, "OUT", line.quantity_shipped, "OUT", None, user_id)?;
+
+                let transit_lot_id = if whole_lot {
+                    LotRepository::move_to_warehouse(
+                        tx,
+                        &line.product_type,
+                        &line.lot_id,
+                        &transfer.to_warehouse_id,
+                        Some(&transit.id),
+                    )?;
+                    line.lot_id.clone()
+                } else {
+                    let split_id = EntityId::new().to_string();
+                    let lot_number = LotRepository::split(
+                        tx,
+                        &line.product_type,
+                        &line.lot_id,
+                        &split_id,
+                        line.quantity_shipped,
+                        line.unit_cost,
+                        &transfer.to_warehouse_id,
+                        Some(&transit.id),
+                    )?;
+                    info!("Transfer {}: lot {} split into {}", transfer.reference, lot.lot_number, lot_number);
+                    split_id
+                };
+                self.movement(tx, &transfer, line, &transit_lot_id, "IN", line.quantity_shipped, "IN", None, user_id)?;
+                TransferRepository::set_transit_lot(tx, &line.id, &transit_lot_id)?;
+            }
+
+            TransferRepository::mark_shipped(tx, id, user_id)
+        })?;
+
+        info!(
+            "Shipped transfer {} ({} -> {}, value {} centimes)",
+            transfer.reference,
+            transfer.from_warehouse_code,
+            transfer.to_warehouse_code,
+            order.value_shipped().centimes()
+        );
+        self.get_transfer(id)
+    }
+
+    /// Receive transfer (SHIPPED -> RECEIVED)
+    ///
+    /// Lots go to the destination reception dock, pending put-away. Missing
+    /// quantities leave stock with an OUT movement carrying the reason. All
+    /// stock moves and the status change are written in one transaction.
+    pub fn receive(&self, data: ReceiveTransferDto, user_id: &str) -> Result<TransferOrderDto> {
+        let transfer = self.get_transfer(&data.transfer_id)?;
+        let mut order = self.to_domain(&transfer)?;
+
+        let receipts = data
+            .lines
+            .iter()
+            .map(|r| {
+                Ok(TransferReceipt {
+                    line_id: Self::parse_id("line_id", &r.line_id)?,
+                    quantity_received: r.quantity_received,
+                    reason: r.reason.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        order.receive(&receipts, Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        let reception = self.warehouse_repo.reception_location(&transfer.to_warehouse_id)?.ok_or_else(|| {
+            Error::Configuration(format!("Entrepot {} sans quai de reception", transfer.to_warehouse_code))
+        })?;
+
+        // Resolve the lots in transit before writing anything
+        let mut transit_lots = Vec::with_capacity(transfer.lines.len());
+        for (line, received) in transfer.lines.iter().zip(&order.lines) {
+            let transit_lot_id = line.transit_lot_id.as_deref().ok_or_else(|| {
+                Error::Internal(format!("Transfert {}: lot en transit inconnu pour {}", transfer.reference, line.lot_number))
+            })?;
+            let remaining = if received.shortage().is_positive() {
+                Some(self.picked_lot(&line.product_type, transit_lot_id)?.quantity_remaining)
+            } else {
+                None
+            };
+            transit_lots.push((transit_lot_id, remaining));
+        }
+
+        self.db.transaction(|tx| {
+            for ((line, received), (transit_lot_id, remaining)) in
+                transfer.lines.iter().zip(&order.lines).zip(&transit_lots)
+            {
+                if let Some(remaining) = remaining {
+                    let shortage = received.shortage();
+                    LotRepository::update_quantity_on(
+                        tx,
+                        &line.product_type,
+                        transit_lot_id,
+                        (*remaining - shortage).max(Qty::zero()),
+                    )?;
+                    let note = format!(
+                        "Ecart transfert {}: {}",
+                        transfer.reference,
+                        received.discrepancy_reason.as_deref().unwrap_or_default()
+                    );
+                    self.movement(tx, &transfer, line, transit_lot_id, "OUT", shortage, "ECART", Some(&note), user_id)?;
+                    warn!(
+                        "Transfer {}: lot {} received {} short ({})",
+                        transfer.reference, line.lot_number, shortage, note
+                    );
+                }
+
+                LotRepository::assign_location_on(tx, &line.product_type, transit_lot_id, &reception.id, false)?;
+            }
+
+            TransferRepository::mark_received(tx, &transfer.id, &order.lines, user_id)
+        })?;
+
+        info!(
+            "Received transfer {} at {} (value {} of {} centimes)",
+            transfer.reference,
+            transfer.to_warehouse_code,
+            order.value_received().centimes(),
+            order.value_shipped().centimes()
+        );
+        self.get_transfer(&transfer.id)
+    }
+
+    /// Cancel a draft transfer
+    pub fn cancel(&self, id: &str, user_id: &str) -> Result<TransferOrderDto> {
+        let transfer = self.get_transfer(id)?;
+        let mut order = self.to_domain(&transfer)?;
+        order.cancel(Self::parse_id("user_id", user_id)?, self.clock.as_ref())?;
+
+        self.transfer_repo.cancel(id)?;
+
+        info!("Cancelled transfer {}", transfer.reference);
+        self.get_transfer(id)
+    }
+
+    // Internal helpers
+
+    /// Stock movement of a transfer line, linked to the transfer
+    #[allow(clippy::too_many_arguments)]
+    fn movement(
+        &self,
+        conn: &Connection,
+        transfer: &TransferOrderDto,
+        line: &TransferLineDto,
+        lot_id: &str,
+        movement_type: &str,
+        quantity: Qty,
+        step: &str,
+        note: Option<&str>,
+        user_id: &str,
+    ) -> Result<()> {
+        self.movement_repo.create_on(
+            conn,
+            &EntityId::new().to_string(),
+            movement_type,
+            &line.product_type,
+            &line.product_id,
+            Some(lot_id),
+            quantity,
+            Some(line.unit_cost),
+            "TRANSFERT",
+            Some("TRANSFER"),
+            Some(&transfer.id),
+            user_id,
+            &format!("TRF-{}-{}", line.id, step),
+            note.or(Some(transfer.reference.as_str())),
+        )
+    }
+
+    fn picked_lot(&self, product_type: &str, lot_id: &str) -> Result<PickedLot> {
+        let lot = match product_type {
+            "MP" => self.lot_repo.get_mp(lot_id)?.map(|l| PickedLot {
+                product_id: l.product_id,
+                lot_number: l.lot_number,
+                warehouse_id: l.warehouse_id,
+                quantity_remaining: l.quantity_remaining,
+                unit_cost: l.unit_cost,
+                status: l.status,
+            }),
+            _ => self.lot_repo.get_pf(lot_id)?.map(|l| PickedLot {
+                product_id: l.product_id,
+                lot_number: l.lot_number,
+                warehouse_id: l.warehouse_id,
+                quantity_remaining: l.quantity_remaining,
+                unit_cost: l.unit_cost,
+                status: l.status,
+            }),
+        };
+        lot.ok_or_else(|| Error::NotFound {
+            entity_type: "Lot".to_string(),
+            id: lot_id.to_string(),
+        })
+    }
+
+    /// Rebuild the domain transfer to apply its rules
+    fn to_domain(&self, transfer: &TransferOrderDto) -> Result<TransferOrder> {
+        let lines = transfer
+            .lines
+            .iter()
+            .map(|l| {
+                Ok(TransferLine {
+                    id: Self::parse_id("line_id", &l.id)?,
+                    product_type: Self::product_type(&l.product_type),
+                    product_id: Self::parse_id("product_id", &l.product_id)?,
+                    lot_id: Self::parse_id("lot_id", &l.lot_id)?,
+                    lot_number: l.lot_number.clone(),
+                    quantity_shipped: l.quantity_shipped,
+                    quantity_received: l.quantity_received,
+                    unit_cost: Money::from_centimes(l.unit_cost),
+                    discrepancy_reason: l.discrepancy_reason.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TransferOrder {
+            id: Self::parse_id("id", &transfer.id)?,
+            reference: transfer.reference.clone(),
+            from_warehouse_id: Self::parse_id("from_warehouse_id", &transfer.from_warehouse_id)?,
+            to_warehouse_id: Self::parse_id("to_warehouse_id", &transfer.to_warehouse_id)?,
+            status: transfer.status,
+            lines,
+            shipped_at: None,
+            received_at: None,
+            notes: transfer.notes.clone(),
+            audit: AuditInfo::new(Self::parse_id("created_by", &transfer.created_by)?, self.clock.as_ref()),
+        })
+    }
+
+    fn product_type(code: &str) -> ProductType {
+        if code == "MP" {
+            ProductType::Mp
+        } else {
+            ProductType::Pf
+        }
+    }
+
+    fn parse_id(field: &str, value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Validation {
+            field: field.to_string(),
+            message: format!("Identifiant invalide: {}", value),
+        })
+    }
+}
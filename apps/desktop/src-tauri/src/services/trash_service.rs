@@ -0,0 +1,338 @@
+//! Trash Service
+//!
+//! Uniform soft delete for master data:
+//! - Sets `deleted_at`/`deleted_by` instead of removing rows
+//! - Emits tombstone events to `_events` so deletes sync to other devices
+//! - Lists and restores trashed entities (admin trash view)
+//! - Applies tombstones pulled from the server
+//!
+//! Not every soft-deletable table is trashable from the UI (see `TRASHABLE`);
+//! tombstones pulled from the server still apply to all of them.
+
+use chrono::Utc;
+use manchengo_core::{EntityId, Error, Result, SystemClock};
+use manchengo_database::schema::soft_delete;
+use manchengo_database::Database;
+use manchengo_domain::events::lifecycle::{
+    EntityDeleted, EntityRestored, ENTITY_DELETED, ENTITY_RESTORED,
+};
+use manchengo_sync::EventStore;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::dto::TrashItemDto;
+
+/// Entities the UI can move to trash: (aggregate type, table, label column,
+/// has `updated_at`)
+///
+/// The other soft-deletable tables are left out on purpose:
+/// - users are deactivated, they remain the authors of the audit trail
+/// - lots and warehouses hold stock, the ledger must keep pointing to them
+/// - transactional documents (orders, deliveries, invoices, payments, cost
+///   entries) are voided or cancelled, never deleted
+const TRASHABLE: &[(&str, &str, &str, bool)] = &[
+    ("Client", "clients", "name", true),
+    ("Recipe", "recipes", "name", true),
+    ("Supplier", "suppliers", "name", true),
+    ("ProductMp", "products_mp", "name", true),
+    ("ProductPf", "products_pf", "name", true),
+    ("PriceList", "price_lists", "name", true),
+    ("Vehicle", "vehicles", "name", false),
+];
+
+/// Soft delete / restore service
+pub struct TrashService {
+    db: Arc<Database>,
+    device_id: EntityId,
+}
+
+impl TrashService {
+    pub fn new(db: Arc<Database>, device_id: EntityId) -> Self {
+        Self { db, device_id }
+    }
+
+    // =========================================================================
+    // LOCAL OPERATIONS
+    // =========================================================================
+
+    /// Move an entity to trash and emit its tombstone
+    ///
+    /// The row update and the tombstone commit together: a deleted row
+    /// without its tombstone would never sync.
+    pub fn delete(
+        &self,
+        entity_type: &str,
+        id: &str,
+        user_id: &str,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let (table, _, stamped) = Self::trashable(entity_type)?;
+        let entity_id = Self::parse_id("id", id)?;
+        let deleted_by = Self::parse_id("user_id", user_id)?;
+        let deleted_at = Utc::now().to_rfc3339();
+
+        let tombstone = EntityDeleted {
+            entity_type: entity_type.to_string(),
+            entity_id,
+            deleted_at: deleted_at.clone(),
+            deleted_by,
+            reason: reason.map(|r| r.to_string()),
+        };
+
+        self.db.transaction(|tx| {
+            let affected = tx
+                .execute(
+                    &format!(
+                        "UPDATE {} SET deleted_at = ?, deleted_by = ?{} WHERE id = ? AND deleted_at IS NULL",
+                        table,
+                        Self::touch(stamped)
+                    ),
+                    rusqlite::params![deleted_at, user_id, id],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            if affected == 0 {
+                return Err(Error::NotFound {
+                    entity_type: entity_type.to_string(),
+                    id: id.to_string(),
+                });
+            }
+
+            let version = EventStore::next_version_on(tx, entity_type, entity_id)?;
+            // Tombstones carry the real deletion time, never a business date
+            EventStore::append_on(tx, &tombstone.to_envelope(self.device_id, version, &SystemClock)?)
+        })?;
+
+        info!("{} {} moved to trash by {}", entity_type, id, user_id);
+        Ok(())
+    }
+
+    /// Restore an entity from trash, with its restore event
+    pub fn restore(&self, entity_type: &str, id: &str, user_id: &str) -> Result<()> {
+        let (table, _, stamped) = Self::trashable(entity_type)?;
+        let entity_id = Self::parse_id("id", id)?;
+        let restored_by = Self::parse_id("user_id", user_id)?;
+
+        let event = EntityRestored {
+            entity_type: entity_type.to_string(),
+            entity_id,
+            restored_at: Utc::now().to_rfc3339(),
+            restored_by,
+        };
+
+        self.db.transaction(|tx| {
+            let affected = tx
+                .execute(
+                    &format!(
+                        "UPDATE {} SET deleted_at = NULL, deleted_by = NULL{} WHERE id = ? AND deleted_at IS NOT NULL",
+                        table,
+                        Self::touch(stamped)
+                    ),
+                    [id],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            if affected == 0 {
+                return Err(Error::NotFound {
+                    entity_type: entity_type.to_string(),
+                    id: id.to_string(),
+                });
+            }
+
+            let version = EventStore::next_version_on(tx, entity_type, entity_id)?;
+            EventStore::append_on(tx, &event.to_envelope(self.device_id, version, &SystemClock)?)
+        })?;
+
+        info!("{} {} restored by {}", entity_type, id, user_id);
+        Ok(())
+    }
+
+    /// List trashed entities, most recent first
+    pub fn list(&self, entity_type: Option<&str>) -> Result<Vec<TrashItemDto>> {
+        let entries: Vec<&(&str, &str, &str, bool)> = match entity_type {
+            Some(t) => {
+                Self::trashable(t)?;
+                TRASHABLE.iter().filter(|(e, _, _, _)| *e == t).collect()
+            }
+            None => TRASHABLE.iter().collect(),
+        };
+
+        self.db.with_connection(|conn| {
+            let mut result = Vec::new();
+
+            for (entity, table, label, _) in entries {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT id, {}, deleted_at, deleted_by FROM {} WHERE deleted_at IS NOT NULL",
+                        label, table
+                    ))
+                    .map_err(|e| Error::Database(e.to_string()))?;
+
+                let items = stmt
+                    .query_map([], |row| {
+                        Ok(TrashItemDto {
+                            entity_type: entity.to_string(),
+                            entity_id: row.get(0)?,
+                            label: row.get(1)?,
+                            deleted_at: row.get(2)?,
+                            deleted_by: row.get(3)?,
+                        })
+                    })
+                    .map_err(|e| Error::Database(e.to_string()))?;
+
+                for item in items {
+                    result.push(item.map_err(|e| Error::Database(e.to_string()))?);
+                }
+            }
+
+            result.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+            Ok(result)
+        })
+    }
+
+    // =========================================================================
+    // REMOTE TOMBSTONES
+    // =========================================================================
+
+    /// Apply a tombstone pulled from the server (no event is re-emitted)
+    ///
+    /// Returns false when the event is not a lifecycle event.
+    pub fn apply_remote(
+        &self,
+        event_type: &str,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        payload: &serde_json::Value,
+    ) -> Result<bool> {
+        if event_type != ENTITY_DELETED && event_type != ENTITY_RESTORED {
+            return Ok(false);
+        }
+
+        let table = soft_delete::table_for(aggregate_type).ok_or_else(|| {
+            Error::Sync(format!("Type non supprimable: {}", aggregate_type))
+        })?;
+
+        self.db.with_connection(|conn| {
+            if event_type == ENTITY_DELETED {
+                let tombstone: EntityDeleted = serde_json::from_value(payload.clone())?;
+                conn.execute(
+                    &format!(
+                        "UPDATE {} SET deleted_at = ?, deleted_by = ? WHERE id = ? AND deleted_at IS NULL",
+                        table
+                    ),
+                    rusqlite::params![
+                        tombstone.deleted_at,
+                        tombstone.deleted_by.to_string(),
+                        aggregate_id
+                    ],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+            } else {
+                conn.execute(
+                    &format!(
+                        "UPDATE {} SET deleted_at = NULL, deleted_by = NULL WHERE id = ?",
+                        table
+                    ),
+                    [aggregate_id],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+            }
+            Ok(())
+        })?;
+
+        info!("Applied remote {} on {} {}", event_type, aggregate_type, aggregate_id);
+        Ok(true)
+    }
+
+    // =========================================================================
+    // HELPERS
+    // =========================================================================
+
+    fn trashable(entity_type: &str) -> Result<(&'static str, &'static str, bool)> {
+        TRASHABLE
+            .iter()
+            .find(|(e, _, _, _)| *e == entity_type)
+            .map(|(_, table, label, stamped)| (*table, *label, *stamped))
+            .ok_or_else(|| {
+                warn!("Trash requested for unsupported entity {}", entity_type);
+                Error::Validation {
+                    field: "entity_type".to_string(),
+                    message: format!("Type non supprimable: {}", entity_type),
+                }
+            })
+    }
+
+    fn touch(stamped: bool) -> &'static str {
+        if stamped {
+            ", updated_at = datetime('now')"
+        } else {
+            ""
+        }
+    }
+
+    fn parse_id(field: &str, value: &str) -> Result<EntityId> {
+        value.parse().map_err(|_| Error::Validation {
+            field: field.to_string(),
+            message: format!("Identifiant invalide: {}", value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manchengo_database::DatabaseConfig;
+
+    #[test]
+    fn test_delete_writes_tombstone() {
+        let db = Arc::new(Database::open(DatabaseConfig::in_memory()).unwrap());
+        db.with_connection(manchengo_database::migrations::initialize_database)
+            .unwrap();
+        let client_id = EntityId::new().to_string();
+        let user_id = EntityId::new().to_string();
+        db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO clients (id, code, name, client_type, created_by, updated_by)
+                 VALUES (?1, 'CLI-001', 'Superette Amine', 'SUPERETTE', ?2, ?2)",
+                [&client_id, &user_id],
+            )
+            .map_err(|e| Error::Database(e.to_string()))
+        })
+        .unwrap();
+
+        let service = TrashService::new(db.clone(), EntityId::new());
+        service.delete("Client", &client_id, &user_id, Some("doublon")).unwrap();
+
+        let (deleted_at, tombstones): (Option<String>, i64) = db
+            .with_connection(|conn| {
+                conn.query_row(
+                    "SELECT c.deleted_at,
+                            (SELECT COUNT(*) FROM _events
+                             WHERE event_type = ?2 AND aggregate_type = 'Client' AND aggregate_id = ?1)
+                     FROM clients c WHERE c.id = ?1",
+                    [client_id.as_str(), ENTITY_DELETED],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|e| Error::Database(e.to_string()))
+            })
+            .unwrap();
+        assert!(deleted_at.is_some());
+        assert_eq!(tombstones, 1);
+
+        // Already in trash: nothing changes, no second tombstone
+        assert!(service.delete("Client", &client_id, &user_id, None).is_err());
+        let tombstones: i64 = db
+            .with_connection(|conn| {
+                conn.query_row(
+                    "SELECT COUNT(*) FROM _events WHERE event_type = ?2 AND aggregate_type = 'Client' AND aggregate_id = ?1",
+                    [client_id.as_str(), ENTITY_DELETED],
+                    |row| row.get(0),
+                )
+                .map_err(|e| Error::Database(e.to_string()))
+            })
+            .unwrap();
+        assert_eq!(tombstones, 1);
+        service.restore("Client", &client_id, &user_id).unwrap();
+        assert_eq!(service.list(None).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,243 @@
+//! Valuation Service
+//!
+//! Stock value for the accountant, replayed from the movement ledger:
+//! - CMUP (running weighted average per product), as allowed by the SCF
+//! - FIFO at lot cost, with the CMUP - FIFO difference to reconcile
+//! - Period-end reports per product, category and warehouse, at any past date
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use manchengo_core::{Error, Money, Result, SharedClock};
+use manchengo_domain::stock::{ProductValuation, StockValuation};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::dto::*;
+use crate::repositories::valuation_repo::ValuedProduct;
+use crate::repositories::{ValuationRepository, WarehouseRepository};
+
+/// Valuation service (read-only)
+pub struct ValuationService {
+    valuation_repo: Arc<ValuationRepository>,
+    warehouse_repo: Arc<WarehouseRepository>,
+    clock: SharedClock,
+}
+
+impl ValuationService {
+    pub fn new(
+        valuation_repo: Arc<ValuationRepository>,
+        warehouse_repo: Arc<WarehouseRepository>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            valuation_repo,
+            warehouse_repo,
+            clock,
+        }
+    }
+
+    /// Stock valuation at the end of a day
+    ///
+    /// With a warehouse, only the stock held there is valued; the average
+    /// cost stays the company-wide CMUP of the product.
+    pub fn report(&self, filter: StockValuationFilter) -> Result<StockValuationReportDto> {
+        let as_of = self.as_of_date(filter.as_of.as_deref())?;
+        let movements = self.valuation_repo.movements(
+            filter.product_type.as_deref(),
+            None,
+            &(as_of + Duration::days(1)).to_string(),
+        )?;
+        let valuations = StockValuation::value(&movements, Self::end_of_day(as_of));
+
+        let products: HashMap<String, ValuedProduct> = self
+            .valuation_repo
+            .products()?
+            .into_iter()
+            .map(|p| (p.id.clone(), p))
+            .collect();
+        let warehouses: HashMap<String, WarehouseDto> = self
+            .warehouse_repo
+            .list_warehouses(false)?
+            .into_iter()
+            .map(|w| (w.id.clone(), w))
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut by_warehouse: Vec<ValuationGroupDto> = Vec::new();
+        for valuation in &valuations {
+            let product = products.get(&valuation.product_id.to_string()).ok_or_else(|| Error::NotFound {
+                entity_type: "Product".to_string(),
+                id: valuation.product_id.to_string(),
+            })?;
+
+            let mut held = false;
+            for stock in &valuation.warehouses {
+                let warehouse_id = stock.warehouse_id.map(|id| id.to_string());
+                if filter.warehouse_id.is_some() && filter.warehouse_id != warehouse_id {
+                    continue;
+                }
+                held = true;
+                let group = Self::group(&mut by_warehouse, warehouse_id.as_deref(), || {
+                    match warehouse_id.as_deref().and_then(|id| warehouses.get(id)) {
+                        Some(w) => (w.code.clone(), w.name.clone()),
+                        None => ("-".to_string(), "Sans entrepot".to_string()),
+                    }
+                });
+                group.products_count += 1;
+                group.cmup_value += stock.cmup_value.centimes();
+                group.fifo_value += stock.fifo_value.centimes();
+            }
+            if held {
+                lines.push(Self::present(product, valuation, filter.warehouse_id.as_deref()));
+            }
+        }
+        lines.sort_by(|a, b| (&a.product_type, &a.product_code).cmp(&(&b.product_type, &b.product_code)));
+
+        let mut by_category: Vec<ValuationGroupDto> = Vec::new();
+        for line in &lines {
+            let product = &products[&line.product_id];
+            let group = Self::group(&mut by_category, product.category_id.as_deref(), || {
+                (
+                    product.category_code.clone().unwrap_or_else(|| "-".to_string()),
+                    product.category_name.clone().unwrap_or_else(|| "Sans categorie".to_string()),
+                )
+            });
+            group.products_count += 1;
+            group.cmup_value += line.cmup_value;
+            group.fifo_value += line.fifo_value;
+        }
+
+        for group in by_category.iter_mut().chain(by_warehouse.iter_mut()) {
+            group.difference = group.cmup_value - group.fifo_value;
+        }
+        by_category.sort_by(|a, b| a.code.cmp(&b.code));
+        by_warehouse.sort_by(|a, b| a.code.cmp(&b.code));
+
+        let cmup_value: i64 = lines.iter().map(|l| l.cmup_value).sum();
+        let fifo_value: i64 = lines.iter().map(|l| l.fifo_value).sum();
+
+        info!(
+            "Stock valuation as of {}: {} products, CMUP {} / FIFO {} centimes",
+            as_of,
+            lines.len(),
+            cmup_value,
+            fifo_value
+        );
+        Ok(StockValuationReportDto {
+            as_of: as_of.to_string(),
+            warehouse_id: filter.warehouse_id,
+            products: lines,
+            categories: by_category,
+            warehouses: by_warehouse,
+            cmup_value,
+            fifo_value,
+            difference: cmup_value - fifo_value,
+        })
+    }
+
+    /// Running weighted average cost of a product, movement by movement
+    pub fn cost_history(
+        &self,
+        product_type: &str,
+        product_id: &str,
+        as_of: Option<&str>,
+    ) -> Result<Vec<CostHistoryDto>> {
+        let as_of = self.as_of_date(as_of)?;
+        let movements = self.valuation_repo.movements(
+            Some(product_type),
+            Some(product_id),
+            &(as_of + Duration::days(1)).to_string(),
+        )?;
+
+        Ok(StockValuation::cost_history(&movements, Self::end_of_day(as_of))
+            .into_iter()
+            .map(|step| CostHistoryDto {
+                occurred_at: step.occurred_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                movement_type: if step.is_entry { MovementType::In } else { MovementType::Out },
+                quantity: step.quantity,
+                unit_cost: step.unit_cost.centimes(),
+                quantity_after: step.after.quantity,
+                average_cost: step.after.average.centimes(),
+                value_after: step.after.value.centimes(),
+            })
+            .collect())
+    }
+
+    // Internal helpers
+
+    /// Valuation date, today if absent; the future is not valued
+    fn as_of_date(&self, date: Option<&str>) -> Result<NaiveDate> {
+        let today = self.clock.today();
+        let as_of = match date {
+            Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| Error::Validation {
+                field: "as_of".to_string(),
+                message: format!("Date invalide: {}", d),
+            })?,
+            None => today,
+        };
+        if as_of > today {
+            return Err(Error::Validation {
+                field: "as_of".to_string(),
+                message: format!("Date de valorisation future: {}", as_of),
+            });
+        }
+        Ok(as_of)
+    }
+
+    fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+        date.and_hms_opt(23, 59, 59).expect("valid time").and_utc()
+    }
+
+    /// Product line, restricted to one warehouse if asked
+    fn present(product: &ValuedProduct, valuation: &ProductValuation, warehouse_id: Option<&str>) -> ProductValuationDto {
+        let (quantity, cmup_value, fifo_value) = match warehouse_id {
+            Some(id) => valuation
+                .warehouses
+                .iter()
+                .filter(|w| w.warehouse_id.map(|w| w.to_string()).as_deref() == Some(id))
+                .fold((Default::default(), Money::zero(), Money::zero()), |(q, c, f), w| {
+                    (q + w.quantity, c + w.cmup_value, f + w.fifo_value)
+                }),
+            None => (valuation.quantity, valuation.cmup_value, valuation.fifo_value),
+        };
+
+        ProductValuationDto {
+            product_type: product.product_type.clone(),
+            product_id: product.id.clone(),
+            product_code: product.code.clone(),
+            product_name: product.name.clone(),
+            unit: product.unit.clone(),
+            category_id: product.category_id.clone(),
+            category_name: product.category_name.clone(),
+            quantity,
+            average_cost: valuation.average_cost.centimes(),
+            cmup_value: cmup_value.centimes(),
+            fifo_value: fifo_value.centimes(),
+            difference: (cmup_value - fifo_value).centimes(),
+        }
+    }
+
+    /// Group of a report, added on first use
+    fn group<'a, F>(groups: &'a mut Vec<ValuationGroupDto>, id: Option<&str>, label: F) -> &'a mut ValuationGroupDto
+    where
+        F: FnOnce() -> (String, String),
+    {
+        let position = match groups.iter().position(|g| g.id.as_deref() == id) {
+            Some(position) => position,
+            None => {
+                let (code, name) = label();
+                groups.push(ValuationGroupDto {
+                    id: id.map(str::to_string),
+                    code,
+                    name,
+                    products_count: 0,
+                    cmup_value: 0,
+                    fifo_value: 0,
+                    difference: 0,
+                });
+                groups.len() - 1
+            }
+        };
+        &mut groups[position]
+    }
+}
@@ -3,23 +3,25 @@
 //! Enhanced AppState with all services and repositories.
 //! This is the central state container passed to all Tauri commands.
 
-use manchengo_core::EntityId;
-use manchengo_database::Database;
+use manchengo_core::{EntityId, QrKeyRing, SharedClock};
+use manchengo_database::maintenance::MaintenancePolicy;
+use manchengo_database::{Database, DocumentSequences};
 use manchengo_sync::{EventStore, SyncQueue};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::core::{AppConfig, BackgroundScheduler, SessionManager};
+use crate::core::{ActivityTracker, AppConfig, BackgroundScheduler, SessionManager};
 use crate::repositories::{
-    ClientRepository, InvoiceRepository, LotRepository, MovementRepository,
-    ProductRepository, ProductionRepository, PurchaseOrderRepository, RecipeRepository,
-    SupplierRepository,
+    AuditRepository, ClientRepository, ExchangeRateRepository, FiscalRuleRepository, InventoryRepository, InvoiceRepository, LotRepository, MovementRepository,
+    ProductRepository, ProductionRepository, PurchaseOrderRepository, RecipeRepository, ReservationRepository,
+    StockHistoryRepository, SupplierRepository, TransferRepository, ValuationRepository, WarehouseRepository,
 };
 use crate::services::{
-    ApproService, CommercialService, InvoiceService, ProductionService,
-    StockService, SyncService,
+    ApproService, ArchiveService, CommercialService, IntegrityService, InventoryService, InvoiceService, MaintenanceService,
+    ProductionService, SalesOrderService, StockHistoryService, StockService, SyncService, TransferService, TrashService,
+    ValuationService,
 };
 
 /// Global application state
@@ -79,6 +81,33 @@ pub struct AppState {
     /// Invoice service (invoices, payments, fiscal)
     pub invoice_service: Arc<InvoiceService>,
 
+    /// Integrity service (stock ledger checks and repairs)
+    pub integrity_service: Arc<IntegrityService>,
+
+    /// Trash service (soft deletes, restore, tombstones)
+    pub trash_service: Arc<TrashService>,
+
+    /// Maintenance service (WAL, ANALYZE, vacuum, purges)
+    pub maintenance_service: Arc<MaintenanceService>,
+
+    /// Archive service (closed fiscal years)
+    pub archive_service: Arc<ArchiveService>,
+
+    /// Transfer service (inter-warehouse transfer orders)
+    pub transfer_service: Arc<TransferService>,
+
+    /// Inventory service (physical stock-take sessions)
+    pub inventory_service: Arc<InventoryService>,
+
+    /// Valuation service (CMUP and FIFO stock value)
+    pub valuation_service: Arc<ValuationService>,
+
+    /// Stock history service (point-in-time stock, daily snapshots)
+    pub stock_history_service: Arc<StockHistoryService>,
+
+    /// Sales order service (lot reservations, delivery preparation)
+    pub sales_order_service: Arc<SalesOrderService>,
+
     // =========================================================================
     // REPOSITORIES
     // =========================================================================
@@ -110,6 +139,12 @@ pub struct AppState {
     /// Invoice repository
     pub invoice_repo: Arc<InvoiceRepository>,
 
+    /// Audit log repository
+    pub audit_repo: Arc<AuditRepository>,
+
+    /// Warehouse and location repository
+    pub warehouse_repo: Arc<WarehouseRepository>,
+
     // =========================================================================
     // RUNTIME
     // =========================================================================
@@ -117,6 +152,9 @@ pub struct AppState {
     /// Background task scheduler
     pub scheduler: Arc<BackgroundScheduler>,
 
+    /// In-flight sync/production tracker (blocks maintenance)
+    pub activity: Arc<ActivityTracker>,
+
     /// Online status flag
     pub is_online: Arc<AtomicBool>,
 }
@@ -149,30 +187,70 @@ impl AppState {
         // Generate or load device ID
         let device_id = Self::load_or_create_device_id(&config);
 
+        let maintenance_policy = MaintenancePolicy {
+            event_retention_days: config.event_retention_days,
+            qr_scan_retention_days: config.qr_scan_retention_days,
+            ..Default::default()
+        };
+
+        // Each PC numbers documents under its own prefix so two PCs never
+        // issue the same invoice number: there is no central allocator
+        let document_prefix = config
+            .document_prefix
+            .clone()
+            .unwrap_or_else(|| Self::default_document_prefix(device_id));
+        db.with_connection(|conn| DocumentSequences::set_device_prefix(conn, Some(&document_prefix)))
+            .map_err(|e| format!("Invalid document prefix: {}", e))?;
+
         // Configuration wrapped in RwLock
         let config = Arc::new(RwLock::new(config));
 
+        // Initialize session manager, also the clock (business date) that
+        // dates documents and movements; background jobs run on the system clock
+        let session = Arc::new(SessionManager::new(device_id));
+        let clock: SharedClock = session.clone();
+        let system_clock = session.system_clock();
+
         // =====================================================================
         // INITIALIZE REPOSITORIES
         // =====================================================================
 
         let product_repo = Arc::new(ProductRepository::new(db.clone()));
-        let lot_repo = Arc::new(LotRepository::new(db.clone()));
-        let movement_repo = Arc::new(MovementRepository::new(db.clone()));
+        let lot_repo = Arc::new(LotRepository::new(db.clone(), clock.clone(), system_clock.clone()));
+        let movement_repo = Arc::new(MovementRepository::new(db.clone(), clock.clone()));
         let supplier_repo = Arc::new(SupplierRepository::new(db.clone()));
         let recipe_repo = Arc::new(RecipeRepository::new(db.clone()));
         let production_repo = Arc::new(ProductionRepository::new(db.clone()));
         let po_repo = Arc::new(PurchaseOrderRepository::new(db.clone()));
         let client_repo = Arc::new(ClientRepository::new(db.clone()));
         let invoice_repo = Arc::new(InvoiceRepository::new(db.clone()));
-
-        // Initialize session manager
-        let session = Arc::new(SessionManager::new(device_id));
+        let fiscal_rule_repo = Arc::new(FiscalRuleRepository::new(db.clone()));
+        let audit_repo = Arc::new(AuditRepository::new(db.clone(), device_id));
+        let exchange_rate_repo = Arc::new(ExchangeRateRepository::new(db.clone()));
+        let warehouse_repo = Arc::new(WarehouseRepository::new(db.clone()));
+        let transfer_repo = Arc::new(TransferRepository::new(db.clone()));
+        let inventory_repo = Arc::new(InventoryRepository::new(db.clone()));
+        let valuation_repo = Arc::new(ValuationRepository::new(db.clone()));
+        let stock_history_repo = Arc::new(StockHistoryRepository::new(db.clone()));
+        let reservation_repo = Arc::new(ReservationRepository::new(db.clone()));
+
+        // Location labels are signed; without keys they cannot be created
+        let qr_keys = match QrKeyRing::from_env() {
+            Ok(keys) => Some(Arc::new(keys)),
+            Err(e) => {
+                warn!("QR keys not configured, location labels disabled: {}", e);
+                None
+            }
+        };
 
         // =====================================================================
         // INITIALIZE SERVICES
         // =====================================================================
 
+        let activity = Arc::new(ActivityTracker::new());
+
+        let trash_service = Arc::new(TrashService::new(db.clone(), device_id));
+
         let stock_service = Arc::new(StockService::new(
             db.clone(),
             event_store.clone(),
@@ -180,6 +258,10 @@ impl AppState {
             lot_repo.clone(),
             movement_repo.clone(),
             supplier_repo.clone(),
+            exchange_rate_repo.clone(),
+            warehouse_repo.clone(),
+            qr_keys,
+            clock.clone(),
         ));
 
         let sync_service = Arc::new(SyncService::new(
@@ -188,6 +270,8 @@ impl AppState {
             sync_queue.clone(),
             config.clone(),
             device_id,
+            trash_service.clone(),
+            activity.clone(),
         ));
 
         let production_service = Arc::new(ProductionService::new(
@@ -198,6 +282,9 @@ impl AppState {
             product_repo.clone(),
             lot_repo.clone(),
             stock_service.clone(),
+            trash_service.clone(),
+            activity.clone(),
+            clock.clone(),
         ));
 
         let appro_service = Arc::new(ApproService::new(
@@ -205,13 +292,17 @@ impl AppState {
             event_store.clone(),
             po_repo.clone(),
             supplier_repo.clone(),
+            lot_repo.clone(),
+            exchange_rate_repo,
             stock_service.clone(),
+            clock.clone(),
         ));
 
         let commercial_service = Arc::new(CommercialService::new(
             db.clone(),
             event_store.clone(),
             client_repo.clone(),
+            trash_service.clone(),
         ));
 
         let invoice_service = Arc::new(InvoiceService::new(
@@ -219,6 +310,69 @@ impl AppState {
             event_store.clone(),
             invoice_repo.clone(),
             client_repo.clone(),
+            product_repo.clone(),
+            fiscal_rule_repo,
+            clock.clone(),
+        ));
+
+        let integrity_service = Arc::new(IntegrityService::new(
+            db.clone(),
+            lot_repo.clone(),
+            movement_repo.clone(),
+            clock.clone(),
+        ));
+
+        let maintenance_service = Arc::new(MaintenanceService::new(
+            db.clone(),
+            activity.clone(),
+            maintenance_policy,
+        ));
+
+        let archive_service = Arc::new(ArchiveService::new(
+            db.clone(),
+            activity.clone(),
+            AppConfig::data_dir().join("archives"),
+            clock.clone(),
+        ));
+
+        let transfer_service = Arc::new(TransferService::new(
+            db.clone(),
+            transfer_repo,
+            lot_repo.clone(),
+            movement_repo.clone(),
+            warehouse_repo.clone(),
+            clock.clone(),
+        ));
+
+        let inventory_service = Arc::new(InventoryService::new(
+            inventory_repo,
+            lot_repo.clone(),
+            movement_repo.clone(),
+            warehouse_repo.clone(),
+            clock.clone(),
+        ));
+
+        let valuation_service = Arc::new(ValuationService::new(
+            valuation_repo.clone(),
+            warehouse_repo.clone(),
+            clock.clone(),
+        ));
+
+        let sales_order_service = Arc::new(SalesOrderService::new(
+            db.clone(),
+            reservation_repo,
+            product_repo.clone(),
+            client_repo.clone(),
+            movement_repo.clone(),
+            stock_service.clone(),
+            system_clock.clone(),
+        ));
+
+        let stock_history_service = Arc::new(StockHistoryService::new(
+            stock_history_repo,
+            valuation_repo,
+            warehouse_repo.clone(),
+            system_clock,
         ));
 
         // Initialize scheduler
@@ -240,6 +394,15 @@ impl AppState {
             appro_service,
             commercial_service,
             invoice_service,
+            integrity_service,
+            trash_service,
+            maintenance_service,
+            archive_service,
+            transfer_service,
+            inventory_service,
+            valuation_service,
+            stock_history_service,
+            sales_order_service,
             // Repositories
             product_repo,
             lot_repo,
@@ -250,13 +413,22 @@ impl AppState {
             po_repo,
             client_repo,
             invoice_repo,
+            audit_repo,
+            warehouse_repo,
             // Runtime
             scheduler,
+            activity,
             is_online: Arc::new(AtomicBool::new(false)),
         })
     }
 
     /// Load existing device ID or create new one
+    /// Document prefix of a PC without a configured one: the first four hex
+    /// digits of its device ID
+    fn default_document_prefix(device_id: EntityId) -> String {
+        device_id.to_string().chars().filter(|c| *c != '-').take(4).collect::<String>().to_uppercase()
+    }
+
     fn load_or_create_device_id(config: &AppConfig) -> EntityId {
         let device_id_path = AppConfig::data_dir().join("device_id");
 
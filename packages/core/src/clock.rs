@@ -0,0 +1,135 @@
+//! Clock abstraction
+//!
+//! Domain code never calls `Utc::now()` directly: it asks a `Clock`. The
+//! application runs on `SystemClock`, tests pin time with `FixedClock`, and
+//! late data entry (yesterday's receptions) runs on an `OffsetClock` whose
+//! dates are reported as a business date in the audit trail.
+
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+/// Source of the current time
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+
+    /// Business date when it differs from the real date (backdated entry)
+    fn business_date(&self) -> Option<NaiveDate> {
+        None
+    }
+}
+
+/// Shared clock handle
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock stopped at a given instant, moved by hand in tests
+#[derive(Debug)]
+pub struct FixedClock {
+    now: RwLock<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: RwLock::new(now) }
+    }
+
+    /// Clock stopped at noon UTC on `date`
+    pub fn at_date(date: NaiveDate) -> Self {
+        Self::new(date.and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()).and_utc())
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.write().unwrap_or_else(|e| e.into_inner());
+        *now += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Another clock shifted by a constant offset
+#[derive(Debug, Clone)]
+pub struct OffsetClock {
+    inner: SharedClock,
+    offset: Duration,
+}
+
+impl OffsetClock {
+    pub fn new(inner: SharedClock, offset: Duration) -> Self {
+        Self { inner, offset }
+    }
+
+    /// Shift whole days so that `today()` is `date`, keeping the time of day
+    pub fn at_date(inner: SharedClock, date: NaiveDate) -> Self {
+        let offset = Duration::days((date - inner.today()).num_days());
+        Self::new(inner, offset)
+    }
+
+    pub fn offset(&self) -> Duration {
+        self.offset
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.inner.now() + self.offset
+    }
+
+    fn business_date(&self) -> Option<NaiveDate> {
+        (!self.offset.is_zero()).then(|| self.today())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_fixed_clock_moves_only_by_hand() {
+        let clock = FixedClock::at_date(date(2024, 3, 15));
+        assert_eq!(clock.today(), date(2024, 3, 15));
+        assert_eq!(clock.now(), clock.now());
+        assert_eq!(clock.business_date(), None);
+
+        clock.advance(Duration::hours(13));
+        assert_eq!(clock.today(), date(2024, 3, 16));
+    }
+
+    #[test]
+    fn test_offset_clock_reports_business_date() {
+        let real: SharedClock = Arc::new(FixedClock::at_date(date(2024, 3, 15)));
+        let yesterday = OffsetClock::at_date(real.clone(), date(2024, 3, 14));
+        assert_eq!(yesterday.today(), date(2024, 3, 14));
+        assert_eq!(yesterday.now().time(), real.now().time());
+        assert_eq!(yesterday.business_date(), Some(date(2024, 3, 14)));
+
+        let same_day = OffsetClock::at_date(real, date(2024, 3, 15));
+        assert_eq!(same_day.business_date(), None);
+    }
+}
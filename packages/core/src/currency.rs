@@ -0,0 +1,364 @@
+//! Currencies and exchange rates
+//!
+//! Cultures, rennet and packaging film are invoiced in EUR or USD while stock
+//! is valued in DZD. A `CurrencyAmount` keeps the supplier's amount in its own
+//! currency; `ExchangeRateTable` converts it with the rate in force on the
+//! reception date (the latest rate entered on or before that date).
+//!
+//! Rates are stored as micro-dinars per unit of foreign currency
+//! (1 EUR = 145.234567 DA -> 145_234_567) so conversions stay integer.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::types::Money;
+
+/// Fixed-point scale of exchange rates
+pub const RATE_SCALE: i64 = 1_000_000;
+
+/// Currency (ISO 4217)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    #[default]
+    Dzd,
+    Eur,
+    Usd,
+}
+
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Dzd => "DZD",
+            Self::Eur => "EUR",
+            Self::Usd => "USD",
+        }
+    }
+
+    /// Parse an ISO code ("eur" accepted)
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_uppercase().as_str() {
+            "DZD" | "DA" => Some(Self::Dzd),
+            "EUR" => Some(Self::Eur),
+            "USD" => Some(Self::Usd),
+            _ => None,
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        *self == Self::Dzd
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Amount tagged with its currency, in minor units (centimes, cents)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrencyAmount {
+    pub amount: i64,
+    pub currency: Currency,
+}
+
+impl CurrencyAmount {
+    pub fn new(amount: i64, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    /// From a major-unit amount (12.50 EUR)
+    pub fn from_major(amount: f64, currency: Currency) -> Self {
+        Self::new((amount * 100.0).round() as i64, currency)
+    }
+
+    pub fn as_major(&self) -> f64 {
+        self.amount as f64 / 100.0
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Sum of two amounts of the same currency
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
+        if self.currency != rhs.currency {
+            return Err(Error::Validation {
+                field: "currency".to_string(),
+                message: format!("Addition de {} et {}", self.currency, rhs.currency),
+            });
+        }
+        let amount = self
+            .amount
+            .checked_add(rhs.amount)
+            .ok_or_else(|| Error::Internal("CurrencyAmount overflow on addition".to_string()))?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Multiply by a quantity (unit price x quantity)
+    pub fn times(self, quantity: f64) -> Self {
+        Self::new((self.amount as f64 * quantity).round() as i64, self.currency)
+    }
+}
+
+impl From<Money> for CurrencyAmount {
+    fn from(money: Money) -> Self {
+        Self::new(money.centimes(), Currency::Dzd)
+    }
+}
+
+impl fmt::Display for CurrencyAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} {}", self.as_major(), self.currency)
+    }
+}
+
+/// Where a rate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RateSource {
+    #[default]
+    Manual,
+    Csv,
+}
+
+impl RateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Manual => "MANUAL",
+            Self::Csv => "CSV",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "CSV" => Self::Csv,
+            _ => Self::Manual,
+        }
+    }
+}
+
+/// DZD value of one unit of a foreign currency on a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub currency: Currency,
+    pub rate_date: NaiveDate,
+    /// Micro-dinars per unit (see [`RATE_SCALE`])
+    pub rate: i64,
+    #[serde(default)]
+    pub source: RateSource,
+}
+
+impl ExchangeRate {
+    pub fn new(currency: Currency, rate_date: NaiveDate, rate_dzd: f64, source: RateSource) -> Result<Self> {
+        let rate = Self {
+            currency,
+            rate_date,
+            rate: (rate_dzd * RATE_SCALE as f64).round() as i64,
+            source,
+        };
+        rate.validate()?;
+        Ok(rate)
+    }
+
+    pub fn rate_dzd(&self) -> f64 {
+        self.rate as f64 / RATE_SCALE as f64
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.currency.is_local() {
+            return Err(Error::Validation {
+                field: "currency".to_string(),
+                message: "Pas de taux de change pour le dinar".to_string(),
+            });
+        }
+        if self.rate <= 0 {
+            return Err(Error::Validation {
+                field: "rate".to_string(),
+                message: format!("Taux {} invalide: {}", self.currency, self.rate_dzd()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Convert an amount of this currency to DZD, rounded half away from zero
+    pub fn convert(&self, amount: CurrencyAmount) -> Result<Money> {
+        if amount.currency != self.currency {
+            return Err(Error::Validation {
+                field: "currency".to_string(),
+                message: format!("Taux {} applique a un montant {}", self.currency, amount.currency),
+            });
+        }
+
+        let scaled = amount.amount as i128 * self.rate as i128;
+        let half = RATE_SCALE as i128 / 2;
+        let rounded = if scaled >= 0 {
+            (scaled + half) / RATE_SCALE as i128
+        } else {
+            (scaled - half) / RATE_SCALE as i128
+        };
+        let centimes = i64::try_from(rounded)
+            .map_err(|_| Error::Internal("Money overflow on conversion".to_string()))?;
+
+        Ok(Money::from_centimes(centimes))
+    }
+}
+
+/// All known rates, looked up by currency and date
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRateTable {
+    rates: Vec<ExchangeRate>,
+}
+
+impl ExchangeRateTable {
+    /// Build from rates; a later entry for the same currency and date wins
+    pub fn new(rates: Vec<ExchangeRate>) -> Result<Self> {
+        for rate in &rates {
+            rate.validate()?;
+        }
+
+        let mut table = Self { rates: Vec::with_capacity(rates.len()) };
+        for rate in rates {
+            table.insert(rate);
+        }
+        Ok(table)
+    }
+
+    fn insert(&mut self, rate: ExchangeRate) {
+        let key = |r: &ExchangeRate| (r.currency, r.rate_date);
+        match self.rates.binary_search_by_key(&key(&rate), key) {
+            Ok(i) => self.rates[i] = rate,
+            Err(i) => self.rates.insert(i, rate),
+        }
+    }
+
+    /// Rate in force on a date: the latest one entered on or before it
+    pub fn rate_on(&self, currency: Currency, date: NaiveDate) -> Result<&ExchangeRate> {
+        self.rates
+            .iter()
+            .rev()
+            .find(|r| r.currency == currency && r.rate_date <= date)
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "ExchangeRate".to_string(),
+                id: format!("{} {}", currency, date),
+            })
+    }
+
+    /// DZD value of an amount on a date (DZD amounts pass through)
+    pub fn to_dzd(&self, amount: CurrencyAmount, date: NaiveDate) -> Result<Money> {
+        if amount.currency.is_local() {
+            return Ok(Money::from_centimes(amount.amount));
+        }
+        self.rate_on(amount.currency, date)?.convert(amount)
+    }
+
+    pub fn rates(&self) -> &[ExchangeRate] {
+        &self.rates
+    }
+}
+
+/// Parse a rate file: `date;currency;rate` per line (YYYY-MM-DD, ISO code,
+/// DZD per unit). `,` also works as separator; with `;` the rate may use a
+/// decimal comma as exported by spreadsheets. A header line is skipped.
+pub fn parse_rates_csv(content: &str) -> Result<Vec<ExchangeRate>> {
+    let mut rates = Vec::new();
+
+    for (idx, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |message: String| Error::Validation {
+            field: "csv".to_string(),
+            message: format!("Ligne {}: {}", idx + 1, message),
+        };
+
+        let fields: Vec<&str> = if line.contains(';') {
+            line.split(';').map(str::trim).collect()
+        } else {
+            line.split(',').map(str::trim).collect()
+        };
+        if fields.len() != 3 {
+            return Err(invalid(format!("3 colonnes attendues, {} trouvees", fields.len())));
+        }
+
+        let date = match NaiveDate::parse_from_str(fields[0], "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) if idx == 0 && rates.is_empty() => continue, // header
+            Err(_) => return Err(invalid(format!("date invalide: {}", fields[0]))),
+        };
+        let currency = Currency::from_code(fields[1])
+            .ok_or_else(|| invalid(format!("devise inconnue: {}", fields[1])))?;
+        let value: f64 = fields[2]
+            .replace(',', ".")
+            .parse()
+            .map_err(|_| invalid(format!("taux invalide: {}", fields[2])))?;
+
+        let rate = ExchangeRate::new(currency, date, value, RateSource::Csv)
+            .map_err(|e| invalid(e.to_string()))?;
+        rates.push(rate);
+    }
+
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_conversion_rounds_to_centime() {
+        let rate = ExchangeRate::new(Currency::Eur, date(2024, 3, 1), 145.234567, RateSource::Manual).unwrap();
+        assert_eq!(rate.rate, 145_234_567);
+
+        // 12.34 EUR = 1792.19455678 DA
+        let dzd = rate.convert(CurrencyAmount::from_major(12.34, Currency::Eur)).unwrap();
+        assert_eq!(dzd.centimes(), 179_219);
+
+        assert!(rate.convert(CurrencyAmount::new(100, Currency::Usd)).is_err());
+        assert!(ExchangeRate::new(Currency::Dzd, date(2024, 3, 1), 1.0, RateSource::Manual).is_err());
+        assert!(ExchangeRate::new(Currency::Eur, date(2024, 3, 1), 0.0, RateSource::Manual).is_err());
+    }
+
+    #[test]
+    fn test_rate_in_force_on_reception_date() {
+        let table = ExchangeRateTable::new(vec![
+            ExchangeRate::new(Currency::Eur, date(2024, 3, 4), 146.0, RateSource::Manual).unwrap(),
+            ExchangeRate::new(Currency::Eur, date(2024, 3, 1), 145.0, RateSource::Manual).unwrap(),
+            ExchangeRate::new(Currency::Usd, date(2024, 3, 2), 134.5, RateSource::Manual).unwrap(),
+            ExchangeRate::new(Currency::Eur, date(2024, 3, 4), 146.5, RateSource::Csv).unwrap(),
+        ])
+        .unwrap();
+
+        let amount = CurrencyAmount::new(10_000, Currency::Eur);
+        assert_eq!(table.to_dzd(amount, date(2024, 3, 3)).unwrap().centimes(), 1_450_000);
+        assert_eq!(table.to_dzd(amount, date(2024, 3, 4)).unwrap().centimes(), 1_465_000);
+        assert!(table.to_dzd(amount, date(2024, 2, 29)).is_err());
+
+        let dzd = CurrencyAmount::new(12_345, Currency::Dzd);
+        assert_eq!(table.to_dzd(dzd, date(2020, 1, 1)).unwrap().centimes(), 12_345);
+    }
+
+    #[test]
+    fn test_parse_rates_csv() {
+        let csv = "date;devise;taux\n2024-03-01;EUR;145,25\n\n2024-03-01;usd;134.1\n";
+        let rates = parse_rates_csv(csv).unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].rate, 145_250_000);
+        assert_eq!(rates[1].currency, Currency::Usd);
+        assert_eq!(rates[1].source, RateSource::Csv);
+
+        assert_eq!(parse_rates_csv("2024-03-01,EUR,145.25").unwrap().len(), 1);
+
+        let err = parse_rates_csv("2024-03-01;EUR;145\n2024-03-02;GBP;170").unwrap_err();
+        assert!(err.to_string().contains("Ligne 2"));
+    }
+}
@@ -0,0 +1,362 @@
+//! Error code catalogue
+//!
+//! Every error crossing the Tauri boundary carries a stable code the frontend
+//! can match on, a severity, named parameters and a message rendered from the
+//! French and Arabic templates below. Codes are part of the API: never rename
+//! one, add a new code instead.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::words::Language;
+
+/// Stable error code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+    #[serde(rename = "DATABASE_ERROR")]
+    Database,
+    #[serde(rename = "VALIDATION_ERROR")]
+    Validation,
+    #[serde(rename = "INVALID_ID")]
+    InvalidId,
+    #[serde(rename = "NOT_FOUND")]
+    NotFound,
+    #[serde(rename = "BUSINESS_RULE")]
+    BusinessRule,
+    #[serde(rename = "INSUFFICIENT_STOCK")]
+    InsufficientStock,
+    #[serde(rename = "INVALID_STATE_TRANSITION")]
+    InvalidStateTransition,
+    #[serde(rename = "LOT_EXPIRED")]
+    LotExpired,
+    #[serde(rename = "QR_CODE_ERROR")]
+    QrCode,
+    #[serde(rename = "SYNC_ERROR")]
+    Sync,
+    #[serde(rename = "UNAUTHORIZED")]
+    NotAuthenticated,
+    #[serde(rename = "FORBIDDEN")]
+    Forbidden,
+    #[serde(rename = "ACCESS_DENIED")]
+    AccessDenied,
+    #[serde(rename = "SESSION_EXPIRED")]
+    SessionExpired,
+    #[serde(rename = "DEVICE_NOT_REGISTERED")]
+    DeviceNotRegistered,
+    #[serde(rename = "SERIALIZATION_ERROR")]
+    Serialization,
+    #[serde(rename = "IO_ERROR")]
+    Io,
+    #[serde(rename = "CONFIGURATION_ERROR")]
+    Configuration,
+    #[serde(rename = "INTERNAL_ERROR")]
+    Internal,
+}
+
+/// How the frontend should present an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Severity {
+    /// The user can fix the input and retry
+    Warning,
+    /// The operation failed
+    Error,
+    /// The application is in a bad state (database, configuration)
+    Critical,
+}
+
+/// A message in both interface languages
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalizedMessage {
+    pub fr: String,
+    pub ar: String,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 19] = [
+        ErrorCode::Database,
+        ErrorCode::Validation,
+        ErrorCode::InvalidId,
+        ErrorCode::NotFound,
+        ErrorCode::BusinessRule,
+        ErrorCode::InsufficientStock,
+        ErrorCode::InvalidStateTransition,
+        ErrorCode::LotExpired,
+        ErrorCode::QrCode,
+        ErrorCode::Sync,
+        ErrorCode::NotAuthenticated,
+        ErrorCode::Forbidden,
+        ErrorCode::AccessDenied,
+        ErrorCode::SessionExpired,
+        ErrorCode::DeviceNotRegistered,
+        ErrorCode::Serialization,
+        ErrorCode::Io,
+        ErrorCode::Configuration,
+        ErrorCode::Internal,
+    ];
+
+    /// Code as sent to the frontend
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Database => "DATABASE_ERROR",
+            ErrorCode::Validation => "VALIDATION_ERROR",
+            ErrorCode::InvalidId => "INVALID_ID",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::BusinessRule => "BUSINESS_RULE",
+            ErrorCode::InsufficientStock => "INSUFFICIENT_STOCK",
+            ErrorCode::InvalidStateTransition => "INVALID_STATE_TRANSITION",
+            ErrorCode::LotExpired => "LOT_EXPIRED",
+            ErrorCode::QrCode => "QR_CODE_ERROR",
+            ErrorCode::Sync => "SYNC_ERROR",
+            ErrorCode::NotAuthenticated => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::AccessDenied => "ACCESS_DENIED",
+            ErrorCode::SessionExpired => "SESSION_EXPIRED",
+            ErrorCode::DeviceNotRegistered => "DEVICE_NOT_REGISTERED",
+            ErrorCode::Serialization => "SERIALIZATION_ERROR",
+            ErrorCode::Io => "IO_ERROR",
+            ErrorCode::Configuration => "CONFIGURATION_ERROR",
+            ErrorCode::Internal => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErrorCode::Validation
+            | ErrorCode::InvalidId
+            | ErrorCode::NotFound
+            | ErrorCode::BusinessRule
+            | ErrorCode::InsufficientStock
+            | ErrorCode::InvalidStateTransition
+            | ErrorCode::LotExpired
+            | ErrorCode::QrCode
+            | ErrorCode::NotAuthenticated
+            | ErrorCode::Forbidden
+            | ErrorCode::AccessDenied
+            | ErrorCode::SessionExpired => Severity::Warning,
+            ErrorCode::Sync | ErrorCode::DeviceNotRegistered | ErrorCode::Serialization | ErrorCode::Io => {
+                Severity::Error
+            }
+            ErrorCode::Database | ErrorCode::Configuration | ErrorCode::Internal => Severity::Critical,
+        }
+    }
+
+    /// Message template, parameters written `{name}`
+    pub fn template(&self, lang: Language) -> &'static str {
+        match (self, lang) {
+            (ErrorCode::Database, Language::Fr) => "Erreur de base de données",
+            (ErrorCode::Database, Language::Ar) => "خطأ في قاعدة البيانات",
+            (ErrorCode::Validation, Language::Fr) => "Valeur invalide pour « {field} » : {message}",
+            (ErrorCode::Validation, Language::Ar) => "قيمة غير صالحة للحقل «{field}»: {message}",
+            (ErrorCode::InvalidId, Language::Fr) => "Identifiant invalide : {id}",
+            (ErrorCode::InvalidId, Language::Ar) => "معرف غير صالح: {id}",
+            (ErrorCode::NotFound, Language::Fr) => "{entity_type} introuvable : {id}",
+            (ErrorCode::NotFound, Language::Ar) => "العنصر غير موجود ({entity_type}): {id}",
+            (ErrorCode::BusinessRule, Language::Fr) => "Opération refusée : {message}",
+            (ErrorCode::BusinessRule, Language::Ar) => "العملية مرفوضة: {message}",
+            (ErrorCode::InsufficientStock, Language::Fr) => {
+                "Stock insuffisant pour {product} : {required} demandé, {available} disponible"
+            }
+            (ErrorCode::InsufficientStock, Language::Ar) => {
+                "المخزون غير كاف لـ {product}: المطلوب {required}، المتوفر {available}"
+            }
+            (ErrorCode::InvalidStateTransition, Language::Fr) => {
+                "{entity} ne peut pas passer de {from} à {to}"
+            }
+            (ErrorCode::InvalidStateTransition, Language::Ar) => {
+                "لا يمكن نقل {entity} من الحالة {from} إلى {to}"
+            }
+            (ErrorCode::LotExpired, Language::Fr) => "Le lot {lot_id} est périmé depuis le {expiry_date}",
+            (ErrorCode::LotExpired, Language::Ar) => "الحصة {lot_id} منتهية الصلاحية منذ {expiry_date}",
+            (ErrorCode::QrCode, Language::Fr) => "QR code invalide : {message}",
+            (ErrorCode::QrCode, Language::Ar) => "رمز QR غير صالح: {message}",
+            (ErrorCode::Sync, Language::Fr) => "Erreur de synchronisation : {message}",
+            (ErrorCode::Sync, Language::Ar) => "خطأ في المزامنة: {message}",
+            (ErrorCode::NotAuthenticated, Language::Fr) => "Non authentifié, veuillez vous connecter",
+            (ErrorCode::NotAuthenticated, Language::Ar) => "غير مصادق، يرجى تسجيل الدخول",
+            (ErrorCode::Forbidden, Language::Fr) => "Accès refusé, rôle requis : {required}",
+            (ErrorCode::Forbidden, Language::Ar) => "تم رفض الوصول، الدور المطلوب: {required}",
+            (ErrorCode::AccessDenied, Language::Fr) => "Accès refusé : {message}",
+            (ErrorCode::AccessDenied, Language::Ar) => "تم رفض الوصول: {message}",
+            (ErrorCode::SessionExpired, Language::Fr) => "Session expirée, veuillez vous reconnecter",
+            (ErrorCode::SessionExpired, Language::Ar) => "انتهت الجلسة، يرجى تسجيل الدخول من جديد",
+            (ErrorCode::DeviceNotRegistered, Language::Fr) => "Poste non enregistré",
+            (ErrorCode::DeviceNotRegistered, Language::Ar) => "الجهاز غير مسجل",
+            (ErrorCode::Serialization, Language::Fr) => "Données illisibles",
+            (ErrorCode::Serialization, Language::Ar) => "بيانات غير قابلة للقراءة",
+            (ErrorCode::Io, Language::Fr) => "Erreur d'accès au fichier",
+            (ErrorCode::Io, Language::Ar) => "خطأ في الوصول إلى الملف",
+            (ErrorCode::Configuration, Language::Fr) => "Configuration invalide : {message}",
+            (ErrorCode::Configuration, Language::Ar) => "إعدادات غير صالحة: {message}",
+            (ErrorCode::Internal, Language::Fr) => "Erreur interne",
+            (ErrorCode::Internal, Language::Ar) => "خطأ داخلي",
+        }
+    }
+
+    /// Render the template, unknown parameters are left as `{name}`
+    pub fn render(&self, lang: Language, params: &BTreeMap<String, String>) -> String {
+        params
+            .iter()
+            .fold(self.template(lang).to_string(), |message, (name, value)| {
+                message.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    /// Render in both languages
+    pub fn localize(&self, params: &BTreeMap<String, String>) -> LocalizedMessage {
+        LocalizedMessage {
+            fr: self.render(Language::Fr, params),
+            ar: self.render(Language::Ar, params),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error {
+    /// Stable code of this error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Database(_) => ErrorCode::Database,
+            Error::Validation { .. } => ErrorCode::Validation,
+            Error::NotFound { .. } => ErrorCode::NotFound,
+            Error::BusinessRule(_) => ErrorCode::BusinessRule,
+            Error::InsufficientStock { .. } => ErrorCode::InsufficientStock,
+            Error::InvalidStateTransition { .. } => ErrorCode::InvalidStateTransition,
+            Error::LotExpired { .. } => ErrorCode::LotExpired,
+            Error::QrCode(_) => ErrorCode::QrCode,
+            Error::Sync(_) => ErrorCode::Sync,
+            Error::Authorization(_) => ErrorCode::AccessDenied,
+            Error::Serialization(_) => ErrorCode::Serialization,
+            Error::Io(_) => ErrorCode::Io,
+            Error::Configuration(_) => ErrorCode::Configuration,
+            Error::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Template parameters of this error
+    ///
+    /// Technical details (database, IO, internal) are not parameters: the
+    /// user sees the generic message, the details go to the log.
+    pub fn params(&self) -> BTreeMap<String, String> {
+        let pairs: Vec<(&str, String)> = match self {
+            Error::Validation { field, message } => {
+                vec![("field", field.clone()), ("message", message.clone())]
+            }
+            Error::NotFound { entity_type, id } => {
+                vec![("entity_type", entity_type.clone()), ("id", id.clone())]
+            }
+            Error::BusinessRule(message)
+            | Error::QrCode(message)
+            | Error::Sync(message)
+            | Error::Authorization(message)
+            | Error::Configuration(message) => vec![("message", message.clone())],
+            Error::InsufficientStock {
+                product,
+                required,
+                available,
+            } => vec![
+                ("product", product.clone()),
+                ("required", required.to_string()),
+                ("available", available.to_string()),
+            ],
+            Error::InvalidStateTransition { entity, from, to } => vec![
+                ("entity", entity.clone()),
+                ("from", from.clone()),
+                ("to", to.clone()),
+            ],
+            Error::LotExpired { lot_id, expiry_date } => {
+                vec![("lot_id", lot_id.clone()), ("expiry_date", expiry_date.clone())]
+            }
+            Error::Database(_) | Error::Serialization(_) | Error::Io(_) | Error::Internal(_) => vec![],
+        };
+        pairs
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    /// Message in both interface languages
+    pub fn localized(&self) -> LocalizedMessage {
+        self.code().localize(&self.params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable_and_serialized_as_str() {
+        for code in ErrorCode::ALL {
+            assert_eq!(serde_json::to_string(&code).unwrap(), format!("\"{}\"", code.as_str()));
+        }
+        let mut codes: Vec<_> = ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn test_templates_use_only_declared_params() {
+        let errors = [
+            Error::Validation {
+                field: "f".into(),
+                message: "m".into(),
+            },
+            Error::NotFound {
+                entity_type: "LotPf".into(),
+                id: "1".into(),
+            },
+            Error::BusinessRule("m".into()),
+            Error::InsufficientStock {
+                product: "p".into(),
+                required: 2.0,
+                available: 1.0,
+            },
+            Error::InvalidStateTransition {
+                entity: "e".into(),
+                from: "a".into(),
+                to: "b".into(),
+            },
+            Error::LotExpired {
+                lot_id: "l".into(),
+                expiry_date: "d".into(),
+            },
+            Error::QrCode("m".into()),
+            Error::Sync("m".into()),
+            Error::Authorization("m".into()),
+            Error::Configuration("m".into()),
+            Error::Database("secret".into()),
+        ];
+        for error in errors {
+            let message = error.localized();
+            assert!(!message.fr.contains('{'), "{}", message.fr);
+            assert!(!message.ar.contains('{'), "{}", message.ar);
+            assert!(!message.fr.contains("secret"));
+        }
+    }
+
+    #[test]
+    fn test_insufficient_stock_rendering() {
+        let error = Error::InsufficientStock {
+            product: "Lait cru".into(),
+            required: 120.0,
+            available: 80.5,
+        };
+        assert_eq!(error.code(), ErrorCode::InsufficientStock);
+        assert_eq!(error.code().severity(), Severity::Warning);
+        let message = error.localized();
+        assert_eq!(
+            message.fr,
+            "Stock insuffisant pour Lait cru : 120 demandé, 80.5 disponible"
+        );
+        assert!(message.ar.contains("Lait cru") && message.ar.contains("80.5"));
+    }
+}
@@ -4,6 +4,9 @@
 //! - Timbre fiscal (stamp duty) for cash payments
 //! - TVA (VAT) rates
 //!
+//! The constants below are the 2025 values. Invoices use the date-effective
+//! rule sets of [`crate::fiscal_rules`], seeded from these constants.
+//!
 //! ⚠️ LEGAL REQUIREMENT - DO NOT SIMPLIFY OR MODIFY WITHOUT LEGAL REVIEW
 //! These calculations are based on Algerian fiscal law and must be exact.
 
@@ -44,31 +47,31 @@ pub const TVA_STANDARD: f64 = 0.19;
 pub const TVA_REDUCED: f64 = 0.09;
 
 /// Timbre fiscal threshold - No stamp duty below 300 DA
-const TIMBRE_THRESHOLD_DA: i64 = 300;
+pub(crate) const TIMBRE_THRESHOLD_DA: i64 = 300;
 
 /// Timbre fiscal minimum amount - 5 DA
-const TIMBRE_MINIMUM_DA: i64 = 5;
+pub(crate) const TIMBRE_MINIMUM_DA: i64 = 5;
 
 /// Timbre fiscal bracket size - 100 DA tranches
-const TIMBRE_BRACKET_SIZE_DA: i64 = 100;
+pub(crate) const TIMBRE_BRACKET_SIZE_DA: i64 = 100;
 
 /// Bracket 1 limit: 30,000 DA (300 tranches)
-const TIMBRE_BRACKET_1_TRANCHES: i64 = 300;
+pub(crate) const TIMBRE_BRACKET_1_TRANCHES: i64 = 300;
 
 /// Bracket 2 limit: 100,000 DA (1000 tranches total, 700 in bracket 2)
-const TIMBRE_BRACKET_2_TRANCHES: i64 = 700;
+pub(crate) const TIMBRE_BRACKET_2_TRANCHES: i64 = 700;
 
 /// Total tranches for brackets 1+2
-const TIMBRE_BRACKET_12_TOTAL: i64 = 1000;
+pub(crate) const TIMBRE_BRACKET_12_TOTAL: i64 = 1000;
 
 /// Rate for bracket 1: 1 DA per 100 DA tranche
-const TIMBRE_RATE_BRACKET_1_DA: f64 = 1.0;
+pub(crate) const TIMBRE_RATE_BRACKET_1_DA: f64 = 1.0;
 
 /// Rate for bracket 2: 1.5 DA per 100 DA tranche
-const TIMBRE_RATE_BRACKET_2_DA: f64 = 1.5;
+pub(crate) const TIMBRE_RATE_BRACKET_2_DA: f64 = 1.5;
 
 /// Rate for bracket 3: 2 DA per 100 DA tranche
-const TIMBRE_RATE_BRACKET_3_DA: f64 = 2.0;
+pub(crate) const TIMBRE_RATE_BRACKET_3_DA: f64 = 2.0;
 
 /// Calculate Algerian timbre fiscal (stamp duty) for cash payments.
 ///
@@ -135,6 +138,11 @@ pub fn calculate_timbre_fiscal(total_ttc_da: i64, payment_method: PaymentMethod)
     // For integer division with ceiling: (a + b - 1) / b
     let tranches = (total_ttc_da + TIMBRE_BRACKET_SIZE_DA - 1) / TIMBRE_BRACKET_SIZE_DA;
 
+    timbre_for_tranches(tranches)
+}
+
+/// Timbre in DA for a number of started 100 DA tranches
+fn timbre_for_tranches(tranches: i64) -> i64 {
     let mut timbre: f64 = 0.0;
 
     // Bracket 1: 300 DA → 30,000 DA (first 300 tranches at 1 DA each)
@@ -165,6 +173,8 @@ pub fn calculate_timbre_fiscal(total_ttc_da: i64, payment_method: PaymentMethod)
 /// Calculate timbre fiscal with amounts in centimes.
 ///
 /// This is a convenience wrapper that accepts centimes and returns centimes.
+/// Tranches are counted on the exact amount: 30,000.50 DA starts a 301st
+/// tranche.
 ///
 /// # Arguments
 ///
@@ -178,9 +188,13 @@ pub fn calculate_timbre_fiscal_centimes(
     total_ttc_centimes: i64,
     payment_method: PaymentMethod,
 ) -> i64 {
-    let total_da = total_ttc_centimes / 100;
-    let timbre_da = calculate_timbre_fiscal(total_da, payment_method);
-    timbre_da * 100
+    if payment_method != PaymentMethod::Especes || total_ttc_centimes < TIMBRE_THRESHOLD_DA * 100 {
+        return 0;
+    }
+
+    let tranche_centimes = TIMBRE_BRACKET_SIZE_DA * 100;
+    let tranches = (total_ttc_centimes + tranche_centimes - 1) / tranche_centimes;
+    timbre_for_tranches(tranches) * 100
 }
 
 /// Calculate TVA amount
@@ -192,7 +206,8 @@ pub fn calculate_timbre_fiscal_centimes(
 ///
 /// # Returns
 ///
-/// TVA amount (floored to avoid overcharging)
+/// TVA amount (floored to avoid overcharging). Invoices round with an
+/// explicit policy, see [`crate::tax`].
 pub fn calculate_tva(amount_ht: i64, tva_rate: f64) -> i64 {
     (amount_ht as f64 * tva_rate).floor() as i64
 }
@@ -295,6 +310,15 @@ mod tests {
             calculate_timbre_fiscal_centimes(5_000_000, PaymentMethod::Especes),
             60_000
         );
+
+        // 30,000.50 DA starts a 301st tranche (bracket 2): 300 + 1.5 → 302 DA
+        assert_eq!(
+            calculate_timbre_fiscal_centimes(3_000_050, PaymentMethod::Especes),
+            30_200
+        );
+
+        // 299.99 DA stays below the threshold
+        assert_eq!(calculate_timbre_fiscal_centimes(29_999, PaymentMethod::Especes), 0);
     }
 
     #[test]
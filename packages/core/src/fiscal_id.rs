@@ -0,0 +1,346 @@
+//! Algerian fiscal identifiers (NIF, NIS, RC, Article d'imposition)
+//!
+//! Each identifier is parsed into its segments rather than only checked for
+//! length, so a wilaya code out of range or an unknown legal form is caught
+//! before an invoice is issued.
+//!
+//! | Identifier | Format                     | Segments                                   |
+//! |------------|----------------------------|--------------------------------------------|
+//! | NIF        | 15 digits                  | prefix (4), wilaya (2), sequence (9)       |
+//! | NIS        | 11 digits                  | year (2), wilaya (2), sequence (7)         |
+//! | RC         | `WW/CC-NNNNNNN F YY`       | wilaya, centre, sequence, legal form, year |
+//! | AI         | 11 digits                  | wilaya (2), commune (2), sequence (7)      |
+//!
+//! Spaces are ignored, so "16/00 - 0123456 B 07" is accepted.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::Error;
+
+/// Wilaya codes run from 01 to 58
+pub const WILAYA_COUNT: u8 = 58;
+
+/// Fiscal identifier error
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FiscalIdError {
+    #[error("{field} obligatoire")]
+    Missing { field: &'static str },
+
+    #[error("{field} invalide '{value}': attendu {expected}")]
+    Format {
+        field: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("{field}: code wilaya {code:02} inexistant")]
+    UnknownWilaya { field: &'static str, code: u8 },
+
+    #[error("RC: forme juridique '{letter}' inconnue (A ou B)")]
+    UnknownLegalForm { letter: char },
+
+    #[error("Wilaya du NIF ({nif:02}) differente de celle du RC ({rc:02})")]
+    WilayaMismatch { nif: u8, rc: u8 },
+}
+
+impl FiscalIdError {
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::Missing { field } | Self::Format { field, .. } | Self::UnknownWilaya { field, .. } => field,
+            Self::UnknownLegalForm { .. } => "rc",
+            Self::WilayaMismatch { .. } => "nif",
+        }
+    }
+}
+
+impl From<FiscalIdError> for Error {
+    fn from(err: FiscalIdError) -> Self {
+        Error::Validation {
+            field: err.field().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Legal form letter of the RC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LegalForm {
+    /// A: commercant personne physique
+    PersonnePhysique,
+    /// B: societe (personne morale)
+    PersonneMorale,
+}
+
+impl LegalForm {
+    pub fn letter(&self) -> char {
+        match self {
+            Self::PersonnePhysique => 'A',
+            Self::PersonneMorale => 'B',
+        }
+    }
+}
+
+/// Numero d'Identification Fiscale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nif {
+    pub prefix: u16,
+    pub wilaya: u8,
+    pub sequence: u64,
+}
+
+/// Numero d'Identification Statistique
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nis {
+    pub year: u8,
+    pub wilaya: u8,
+    pub sequence: u32,
+}
+
+/// Registre de Commerce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rc {
+    pub wilaya: u8,
+    pub centre: u8,
+    pub sequence: u32,
+    pub legal_form: LegalForm,
+    pub year: u8,
+}
+
+/// Article d'imposition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArticleImposition {
+    pub wilaya: u8,
+    pub commune: u8,
+    pub sequence: u32,
+}
+
+fn normalize(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase()
+}
+
+fn digits(field: &'static str, value: &str, len: usize, expected: &'static str) -> Result<String, FiscalIdError> {
+    let normalized = normalize(value);
+    if normalized.is_empty() {
+        return Err(FiscalIdError::Missing { field });
+    }
+    if normalized.len() != len || !normalized.chars().all(|c| c.is_ascii_digit()) {
+        return Err(FiscalIdError::Format {
+            field,
+            value: value.to_string(),
+            expected,
+        });
+    }
+    Ok(normalized)
+}
+
+fn wilaya(field: &'static str, code: &str) -> Result<u8, FiscalIdError> {
+    let code: u8 = code.parse().unwrap_or(0);
+    if (1..=WILAYA_COUNT).contains(&code) {
+        Ok(code)
+    } else {
+        Err(FiscalIdError::UnknownWilaya { field, code })
+    }
+}
+
+impl Nif {
+    pub fn parse(value: &str) -> Result<Self, FiscalIdError> {
+        let d = digits("nif", value, 15, "15 chiffres")?;
+        Ok(Self {
+            prefix: d[0..4].parse().unwrap_or(0),
+            wilaya: wilaya("nif", &d[4..6])?,
+            sequence: d[6..].parse().unwrap_or(0),
+        })
+    }
+}
+
+impl Nis {
+    pub fn parse(value: &str) -> Result<Self, FiscalIdError> {
+        let d = digits("nis", value, 11, "11 chiffres")?;
+        Ok(Self {
+            year: d[0..2].parse().unwrap_or(0),
+            wilaya: wilaya("nis", &d[2..4])?,
+            sequence: d[4..].parse().unwrap_or(0),
+        })
+    }
+}
+
+impl ArticleImposition {
+    pub fn parse(value: &str) -> Result<Self, FiscalIdError> {
+        let d = digits("article_imposition", value, 11, "11 chiffres")?;
+        Ok(Self {
+            wilaya: wilaya("article_imposition", &d[0..2])?,
+            commune: d[2..4].parse().unwrap_or(0),
+            sequence: d[4..].parse().unwrap_or(0),
+        })
+    }
+}
+
+impl Rc {
+    const EXPECTED: &'static str = "WW/CC-NNNNNNN A|B AA";
+
+    pub fn parse(value: &str) -> Result<Self, FiscalIdError> {
+        let normalized = normalize(value);
+        if normalized.is_empty() {
+            return Err(FiscalIdError::Missing { field: "rc" });
+        }
+
+        let format_error = || FiscalIdError::Format {
+            field: "rc",
+            value: value.to_string(),
+            expected: Self::EXPECTED,
+        };
+
+        // WW/CC-NNNNNNNFYY
+        let bytes = normalized.as_bytes();
+        let is_digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+        if bytes.len() != 16
+            || bytes[2] != b'/'
+            || bytes[5] != b'-'
+            || !is_digits(0..2)
+            || !is_digits(3..5)
+            || !is_digits(6..13)
+            || !is_digits(14..16)
+        {
+            return Err(format_error());
+        }
+
+        let legal_form = match bytes[13] {
+            b'A' => LegalForm::PersonnePhysique,
+            b'B' => LegalForm::PersonneMorale,
+            letter if letter.is_ascii_alphabetic() => {
+                return Err(FiscalIdError::UnknownLegalForm { letter: letter as char })
+            }
+            _ => return Err(format_error()),
+        };
+
+        Ok(Self {
+            wilaya: wilaya("rc", &normalized[0..2])?,
+            centre: normalized[3..5].parse().unwrap_or(0),
+            sequence: normalized[6..13].parse().unwrap_or(0),
+            legal_form,
+            year: normalized[14..16].parse().unwrap_or(0),
+        })
+    }
+}
+
+/// Check the identifiers that are provided (`None` or blank are skipped)
+///
+/// Returns every error found, including a NIF/RC wilaya mismatch.
+pub fn validate_identifiers(
+    nif: Option<&str>,
+    nis: Option<&str>,
+    rc: Option<&str>,
+    article_imposition: Option<&str>,
+) -> Vec<FiscalIdError> {
+    fn provided(value: Option<&str>) -> Option<&str> {
+        value.filter(|s| !s.trim().is_empty())
+    }
+
+    let mut errors = Vec::new();
+
+    let nif = provided(nif).map(Nif::parse);
+    let rc = provided(rc).map(Rc::parse);
+    let nis = provided(nis).map(Nis::parse);
+    let ai = provided(article_imposition).map(ArticleImposition::parse);
+
+    if let (Some(Ok(nif)), Some(Ok(rc))) = (&nif, &rc) {
+        if nif.wilaya != rc.wilaya {
+            errors.push(FiscalIdError::WilayaMismatch {
+                nif: nif.wilaya,
+                rc: rc.wilaya,
+            });
+        }
+    }
+
+    errors.extend(nif.and_then(Result::err));
+    errors.extend(nis.and_then(Result::err));
+    errors.extend(rc.and_then(Result::err));
+    errors.extend(ai.and_then(Result::err));
+    errors
+}
+
+/// [`validate_identifiers`] failing on the first error
+pub fn check_identifiers(
+    nif: Option<&str>,
+    nis: Option<&str>,
+    rc: Option<&str>,
+    article_imposition: Option<&str>,
+) -> crate::error::Result<()> {
+    match validate_identifiers(nif, nis, rc, article_imposition).into_iter().next() {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nif() {
+        let nif = Nif::parse("000216001234567").unwrap();
+        assert_eq!(nif.wilaya, 16);
+        assert_eq!(nif.sequence, 1_234_567);
+
+        assert!(matches!(Nif::parse("00021600123456"), Err(FiscalIdError::Format { .. })));
+        assert!(matches!(Nif::parse("00021600123456A"), Err(FiscalIdError::Format { .. })));
+        assert_eq!(
+            Nif::parse("000299001234567"),
+            Err(FiscalIdError::UnknownWilaya { field: "nif", code: 99 })
+        );
+        assert_eq!(Nif::parse("  "), Err(FiscalIdError::Missing { field: "nif" }));
+    }
+
+    #[test]
+    fn test_parse_rc() {
+        let rc = Rc::parse("16/00 - 0123456 B 07").unwrap();
+        assert_eq!(rc.wilaya, 16);
+        assert_eq!(rc.centre, 0);
+        assert_eq!(rc.sequence, 123_456);
+        assert_eq!(rc.legal_form, LegalForm::PersonneMorale);
+        assert_eq!(rc.year, 7);
+
+        assert_eq!(Rc::parse("31/02-7654321a19").unwrap().legal_form, LegalForm::PersonnePhysique);
+        assert_eq!(
+            Rc::parse("16/00-0123456Z07"),
+            Err(FiscalIdError::UnknownLegalForm { letter: 'Z' })
+        );
+        assert!(matches!(Rc::parse("1600-0123456B07"), Err(FiscalIdError::Format { .. })));
+        assert!(matches!(Rc::parse("16/00-012345B07"), Err(FiscalIdError::Format { .. })));
+        assert_eq!(
+            Rc::parse("00/00-0123456B07"),
+            Err(FiscalIdError::UnknownWilaya { field: "rc", code: 0 })
+        );
+    }
+
+    #[test]
+    fn test_parse_nis_and_article() {
+        assert_eq!(Nis::parse("07160012345").unwrap().wilaya, 16);
+        assert!(Nis::parse("07600012345").is_err());
+
+        let ai = ArticleImposition::parse("16 01 2345678").unwrap();
+        assert_eq!((ai.wilaya, ai.commune, ai.sequence), (16, 1, 2_345_678));
+        assert!(ArticleImposition::parse("6001234567").is_err());
+    }
+
+    #[test]
+    fn test_wilaya_consistency() {
+        assert!(validate_identifiers(
+            Some("000216001234567"),
+            None,
+            Some("16/00-0123456B07"),
+            Some("16012345678"),
+        )
+        .is_empty());
+
+        assert_eq!(
+            validate_identifiers(Some("000231001234567"), None, Some("16/00-0123456B07"), None),
+            vec![FiscalIdError::WilayaMismatch { nif: 31, rc: 16 }]
+        );
+
+        // Blank identifiers are not checked here
+        assert!(validate_identifiers(None, Some(""), None, None).is_empty());
+    }
+}
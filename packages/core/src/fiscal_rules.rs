@@ -0,0 +1,328 @@
+//! Date-effective fiscal rules
+//!
+//! Finance Laws change TVA rates, timbre brackets and exemptions from a given
+//! date. Each `FiscalRuleSet` carries its validity period; invoices use the
+//! set in force on their `invoice_date`, so a 2024 invoice is always
+//! recomputed with 2024 rules.
+//!
+//! The 2025 set is built from the constants in [`crate::fiscal`] and seeds
+//! the `fiscal_rule_sets` table.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::fiscal::{self, PaymentMethod};
+use crate::tax::RoundingPolicy;
+
+/// Progressive timbre bracket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimbreBracket {
+    /// Last tranche of the bracket (cumulative), `None` for the open bracket
+    pub up_to_tranches: Option<i64>,
+    /// Stamp duty per tranche, in centimes (150 = 1.50 DA)
+    pub rate_per_tranche_centimes: i64,
+}
+
+/// Versioned set of fiscal rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiscalRuleSet {
+    /// Reference of the text (e.g. "LF-2025")
+    pub version: String,
+    pub valid_from: NaiveDate,
+    /// Exclusive end date, `None` while in force
+    pub valid_to: Option<NaiveDate>,
+
+    // TVA
+    pub tva_standard: f64,
+    /// Product category code -> TVA rate (overrides the standard rate)
+    #[serde(default)]
+    pub category_rates: BTreeMap<String, f64>,
+
+    // Timbre fiscal (cash payments only)
+    pub timbre_threshold_da: i64,
+    pub timbre_minimum_da: i64,
+    pub timbre_tranche_da: i64,
+    pub timbre_brackets: Vec<TimbreBracket>,
+
+    /// Client categories exempt from TVA and timbre (e.g. export, franchise)
+    #[serde(default)]
+    pub exempt_client_categories: Vec<String>,
+
+    /// TVA rounding of invoices
+    #[serde(default)]
+    pub rounding: RoundingPolicy,
+}
+
+impl FiscalRuleSet {
+    /// Rules of the 2025 Finance Law, from the `fiscal` constants
+    pub fn finance_law_2025() -> Self {
+        Self {
+            version: "LF-2025".to_string(),
+            valid_from: NaiveDate::from_ymd_opt(2025, 1, 1).expect("valid date"),
+            valid_to: None,
+            tva_standard: fiscal::TVA_STANDARD,
+            category_rates: BTreeMap::new(),
+            timbre_threshold_da: fiscal::TIMBRE_THRESHOLD_DA,
+            timbre_minimum_da: fiscal::TIMBRE_MINIMUM_DA,
+            timbre_tranche_da: fiscal::TIMBRE_BRACKET_SIZE_DA,
+            timbre_brackets: vec![
+                TimbreBracket {
+                    up_to_tranches: Some(fiscal::TIMBRE_BRACKET_1_TRANCHES),
+                    rate_per_tranche_centimes: (fiscal::TIMBRE_RATE_BRACKET_1_DA * 100.0) as i64,
+                },
+                TimbreBracket {
+                    up_to_tranches: Some(fiscal::TIMBRE_BRACKET_12_TOTAL),
+                    rate_per_tranche_centimes: (fiscal::TIMBRE_RATE_BRACKET_2_DA * 100.0) as i64,
+                },
+                TimbreBracket {
+                    up_to_tranches: None,
+                    rate_per_tranche_centimes: (fiscal::TIMBRE_RATE_BRACKET_3_DA * 100.0) as i64,
+                },
+            ],
+            exempt_client_categories: Vec::new(),
+            rounding: RoundingPolicy::default(),
+        }
+    }
+
+    /// Rates hard-coded before rule sets existed, in force up to `until`
+    ///
+    /// Open-start, so credit notes and corrections of older invoices still
+    /// find their rules.
+    pub fn historical(until: NaiveDate) -> Self {
+        Self {
+            version: "HISTORIQUE".to_string(),
+            valid_from: NaiveDate::MIN,
+            valid_to: Some(until),
+            ..Self::finance_law_2025()
+        }
+    }
+
+    /// Whether the set applies on a date
+    pub fn is_in_force(&self, date: NaiveDate) -> bool {
+        self.valid_from <= date && self.valid_to.map(|end| date < end).unwrap_or(true)
+    }
+
+    /// TVA rate for a product category (standard rate if not listed)
+    pub fn tva_rate(&self, category: Option<&str>) -> f64 {
+        category
+            .and_then(|c| self.category_rates.get(c))
+            .copied()
+            .unwrap_or(self.tva_standard)
+    }
+
+    /// Whether a client category is exempt
+    pub fn is_exempt(&self, client_category: Option<&str>) -> bool {
+        client_category
+            .map(|c| self.exempt_client_categories.iter().any(|e| e.eq_ignore_ascii_case(c)))
+            .unwrap_or(false)
+    }
+
+    /// Timbre fiscal in DA for a TTC amount in DA
+    pub fn timbre_da(&self, total_ttc_da: i64, payment_method: PaymentMethod) -> i64 {
+        self.timbre_centimes(total_ttc_da * 100, payment_method) / 100
+    }
+
+    /// Timbre fiscal in centimes for a TTC amount in centimes
+    ///
+    /// Tranches are counted on the exact amount, centimes included.
+    pub fn timbre_centimes(&self, total_ttc_centimes: i64, payment_method: PaymentMethod) -> i64 {
+        if payment_method != PaymentMethod::Especes
+            || total_ttc_centimes < self.timbre_threshold_da * 100
+        {
+            return 0;
+        }
+
+        let tranche = self.timbre_tranche_da.max(1) * 100;
+        let tranches = (total_ttc_centimes + tranche - 1) / tranche;
+
+        let mut centimes = 0i64;
+        let mut counted = 0i64;
+        for bracket in &self.timbre_brackets {
+            let upper = bracket.up_to_tranches.unwrap_or(i64::MAX).min(tranches);
+            if upper > counted {
+                centimes += (upper - counted) * bracket.rate_per_tranche_centimes;
+                counted = upper;
+            }
+            if counted >= tranches {
+                break;
+            }
+        }
+
+        // Round up to the dinar, then apply the minimum
+        let timbre = (centimes + 99) / 100;
+        timbre.max(self.timbre_minimum_da) * 100
+    }
+
+    /// Check rates and brackets are consistent
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Error::Validation {
+            field: "fiscal_rules".to_string(),
+            message,
+        };
+
+        if let Some(end) = self.valid_to {
+            if end <= self.valid_from {
+                return Err(invalid(format!("{}: fin de validite avant le debut", self.version)));
+            }
+        }
+
+        let rates = std::iter::once(self.tva_standard).chain(self.category_rates.values().copied());
+        for rate in rates {
+            if !(0.0..1.0).contains(&rate) {
+                return Err(invalid(format!("{}: taux de TVA invalide {}", self.version, rate)));
+            }
+        }
+
+        let mut previous = 0;
+        for (i, bracket) in self.timbre_brackets.iter().enumerate() {
+            match bracket.up_to_tranches {
+                Some(up_to) if up_to <= previous => {
+                    return Err(invalid(format!("{}: tranches de timbre non croissantes", self.version)));
+                }
+                Some(up_to) => previous = up_to,
+                None if i + 1 != self.timbre_brackets.len() => {
+                    return Err(invalid(format!("{}: tranche ouverte avant la derniere", self.version)));
+                }
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// All known rule sets, looked up by date
+#[derive(Debug, Clone, Default)]
+pub struct FiscalRuleBook {
+    sets: Vec<FiscalRuleSet>,
+}
+
+impl FiscalRuleBook {
+    /// Build from rule sets, rejecting overlapping periods
+    pub fn new(mut sets: Vec<FiscalRuleSet>) -> Result<Self> {
+        sets.sort_by_key(|s| s.valid_from);
+
+        for set in &sets {
+            set.validate()?;
+        }
+        for pair in sets.windows(2) {
+            let overlaps = pair[0].valid_to.map(|end| end > pair[1].valid_from).unwrap_or(true);
+            if overlaps {
+                return Err(Error::Validation {
+                    field: "fiscal_rules".to_string(),
+                    message: format!(
+                        "Periodes de {} et {} se chevauchent",
+                        pair[0].version, pair[1].version
+                    ),
+                });
+            }
+        }
+
+        Ok(Self { sets })
+    }
+
+    /// Rule set in force on a date
+    pub fn in_force_on(&self, date: NaiveDate) -> Result<&FiscalRuleSet> {
+        self.sets
+            .iter()
+            .rev()
+            .find(|s| s.is_in_force(date))
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "FiscalRuleSet".to_string(),
+                id: date.to_string(),
+            })
+    }
+
+    pub fn sets(&self) -> &[FiscalRuleSet] {
+        &self.sets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_2025_rules_match_constants() {
+        let rules = FiscalRuleSet::finance_law_2025();
+        rules.validate().unwrap();
+
+        for amount in [0, 299, 300, 500, 15_000, 30_000, 30_001, 50_000, 100_000, 150_000, 200_000] {
+            assert_eq!(
+                rules.timbre_da(amount, PaymentMethod::Especes),
+                fiscal::calculate_timbre_fiscal(amount, PaymentMethod::Especes),
+                "montant {}",
+                amount
+            );
+        }
+        for centimes in [29_999, 30_000, 3_000_050, 10_000_001, 20_000_099] {
+            assert_eq!(
+                rules.timbre_centimes(centimes, PaymentMethod::Especes),
+                fiscal::calculate_timbre_fiscal_centimes(centimes, PaymentMethod::Especes),
+                "centimes {}",
+                centimes
+            );
+        }
+        assert_eq!(rules.timbre_da(50_000, PaymentMethod::Cheque), 0);
+        assert_eq!(rules.tva_rate(Some("PF-FROM")), fiscal::TVA_STANDARD);
+    }
+
+    #[test]
+    fn test_rule_set_picked_by_date() {
+        let mut old = FiscalRuleSet::finance_law_2025();
+        old.version = "LF-2024".to_string();
+        old.valid_from = date(2024, 1, 1);
+        old.valid_to = Some(date(2025, 1, 1));
+        old.category_rates.insert("PF-LAIT".to_string(), 0.09);
+
+        let mut new = FiscalRuleSet::finance_law_2025();
+        new.exempt_client_categories.push("EXPORT".to_string());
+
+        let book = FiscalRuleBook::new(vec![new, old]).unwrap();
+
+        let rules_2024 = book.in_force_on(date(2024, 12, 31)).unwrap();
+        assert_eq!(rules_2024.version, "LF-2024");
+        assert_eq!(rules_2024.tva_rate(Some("PF-LAIT")), 0.09);
+        assert!(!rules_2024.is_exempt(Some("EXPORT")));
+
+        let rules_2026 = book.in_force_on(date(2026, 3, 1)).unwrap();
+        assert_eq!(rules_2026.version, "LF-2025");
+        assert_eq!(rules_2026.tva_rate(Some("PF-LAIT")), 0.19);
+        assert!(rules_2026.is_exempt(Some("export")));
+
+        assert!(book.in_force_on(date(2023, 6, 1)).is_err());
+    }
+
+    #[test]
+    fn test_historical_rules_before_first_set() {
+        let book = FiscalRuleBook::new(vec![
+            FiscalRuleSet::finance_law_2025(),
+            FiscalRuleSet::historical(date(2025, 1, 1)),
+        ])
+        .unwrap();
+
+        let rules_2024 = book.in_force_on(date(2024, 6, 30)).unwrap();
+        assert_eq!(rules_2024.version, "HISTORIQUE");
+        assert_eq!(
+            rules_2024.timbre_da(50_000, PaymentMethod::Especes),
+            fiscal::calculate_timbre_fiscal(50_000, PaymentMethod::Especes)
+        );
+        assert_eq!(book.in_force_on(date(1999, 1, 1)).unwrap().version, "HISTORIQUE");
+        assert_eq!(book.in_force_on(date(2025, 1, 1)).unwrap().version, "LF-2025");
+    }
+
+    #[test]
+    fn test_overlapping_periods_rejected() {
+        let mut old = FiscalRuleSet::finance_law_2025();
+        old.version = "LF-2024".to_string();
+        old.valid_from = date(2024, 1, 1);
+
+        assert!(FiscalRuleBook::new(vec![old, FiscalRuleSet::finance_law_2025()]).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+//! GS1 identifiers and GS1-128 element strings
+//!
+//! Supermarket chains scan EAN-13 on consumer units and GS1-128 on cartons.
+//! Supported Application Identifiers:
+//!
+//! | AI   | Content               | Format                         |
+//! |------|-----------------------|--------------------------------|
+//! | 01   | GTIN                  | 14 digits                      |
+//! | 10   | Lot number            | up to 20 characters, variable  |
+//! | 15   | Best before (DDM)     | YYMMDD                         |
+//! | 17   | Expiry (DLC)          | YYMMDD                         |
+//! | 3103 | Net weight, kg        | 6 digits, 3 decimals           |
+//!
+//! Variable-length fields are terminated by FNC1, transmitted by scanners as
+//! the GS character (0x1D). Human readable form `(01)...(10)...` is accepted
+//! too, for manual entry.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::Error;
+use crate::qty::Qty;
+
+/// FNC1 as transmitted by scanners (ASCII group separator)
+pub const GS: char = '\u{1D}';
+
+/// Symbology identifier sent by some scanners in front of GS1-128 data
+const SYMBOLOGY_ID: &str = "]C1";
+
+/// Maximum length of AI(10)
+const MAX_LOT_LEN: usize = 20;
+
+/// GS1 error
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Gs1Error {
+    #[error("GTIN invalide '{value}': 8, 12, 13 ou 14 chiffres attendus")]
+    GtinFormat { value: String },
+
+    #[error("GTIN '{value}': chiffre de controle {found} incorrect (attendu {expected})")]
+    CheckDigit { value: String, expected: u8, found: u8 },
+
+    #[error("Identifiant d'application ({ai}) non supporte")]
+    UnknownAi { ai: String },
+
+    #[error("AI ({ai}) incomplet")]
+    Truncated { ai: String },
+
+    #[error("AI ({ai}): valeur invalide '{value}'")]
+    InvalidValue { ai: String, value: String },
+
+    #[error("Code GS1 sans GTIN (01)")]
+    MissingGtin,
+}
+
+impl From<Gs1Error> for Error {
+    fn from(err: Gs1Error) -> Self {
+        Error::Validation {
+            field: "gtin".to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+// ============================================================================
+// GTIN
+// ============================================================================
+
+/// Global Trade Item Number (GTIN-8, GTIN-12/UPC, GTIN-13/EAN-13, GTIN-14)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Gtin(String);
+
+impl Gtin {
+    /// Parse and verify the check digit
+    pub fn parse(value: &str) -> Result<Self, Gs1Error> {
+        let digits = value.trim();
+        if !matches!(digits.len(), 8 | 12 | 13 | 14) || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(Gs1Error::GtinFormat {
+                value: value.to_string(),
+            });
+        }
+
+        let (body, last) = digits.split_at(digits.len() - 1);
+        let expected = Self::check_digit(body);
+        let found = last.as_bytes()[0] - b'0';
+        if expected != found {
+            return Err(Gs1Error::CheckDigit {
+                value: value.to_string(),
+                expected,
+                found,
+            });
+        }
+        Ok(Self(digits.to_string()))
+    }
+
+    /// GS1 mod-10 check digit of the digits before it
+    ///
+    /// Weights 3 and 1 alternate from the rightmost digit.
+    pub fn check_digit(body: &str) -> u8 {
+        let sum: u32 = body
+            .bytes()
+            .rev()
+            .enumerate()
+            .map(|(i, b)| (b - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 })
+            .sum();
+        ((10 - sum % 10) % 10) as u8
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// EAN-13 consumer unit code
+    pub fn is_ean13(&self) -> bool {
+        self.0.len() == 13
+    }
+
+    /// Left-padded to 14 digits, as carried in AI(01)
+    pub fn to_gtin14(&self) -> String {
+        format!("{:0>14}", self.0)
+    }
+
+    /// Same item, whatever the GTIN length
+    pub fn same_item(&self, other: &Gtin) -> bool {
+        self.to_gtin14() == other.to_gtin14()
+    }
+}
+
+impl std::fmt::Display for Gtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for Gtin {
+    type Error = Gs1Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Gtin> for String {
+    fn from(gtin: Gtin) -> Self {
+        gtin.0
+    }
+}
+
+// ============================================================================
+// GS1-128
+// ============================================================================
+
+/// Content of a GS1-128 carton label
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gs1Label {
+    pub gtin: Gtin,
+    pub lot: Option<String>,
+    /// AI(17), DLC
+    pub expiry_date: Option<NaiveDate>,
+    /// AI(15), DDM
+    pub best_before: Option<NaiveDate>,
+    /// AI(3103)
+    pub net_weight_kg: Option<Qty>,
+}
+
+impl Gs1Label {
+    pub fn new(gtin: Gtin) -> Self {
+        Self {
+            gtin,
+            lot: None,
+            expiry_date: None,
+            best_before: None,
+            net_weight_kg: None,
+        }
+    }
+
+    fn elements(&self) -> Result<Vec<(&'static str, String)>, Gs1Error> {
+        let mut elements = vec![("01", self.gtin.to_gtin14())];
+        if let Some(date) = self.best_before {
+            elements.push(("15", encode_date(date)));
+        }
+        if let Some(date) = self.expiry_date {
+            elements.push(("17", encode_date(date)));
+        }
+        if let Some(weight) = self.net_weight_kg {
+            if !(0..=999_999).contains(&weight.milli()) {
+                return Err(Gs1Error::InvalidValue {
+                    ai: "3103".to_string(),
+                    value: weight.to_string(),
+                });
+            }
+            elements.push(("3103", format!("{:06}", weight.milli())));
+        }
+        // Variable length last, so it needs no FNC1 terminator
+        if let Some(lot) = &self.lot {
+            if lot.is_empty() || lot.len() > MAX_LOT_LEN || !lot.chars().all(is_gs1_char) {
+                return Err(Gs1Error::InvalidValue {
+                    ai: "10".to_string(),
+                    value: lot.clone(),
+                });
+            }
+            elements.push(("10", lot.clone()));
+        }
+        Ok(elements)
+    }
+
+    /// Element string to encode in the barcode (without the leading FNC1)
+    pub fn encode(&self) -> Result<String, Gs1Error> {
+        Ok(self
+            .elements()?
+            .into_iter()
+            .map(|(ai, value)| format!("{}{}", ai, value))
+            .collect())
+    }
+
+    /// Text printed under the barcode, e.g. `(01)03612345678904(10)LOT1`
+    pub fn human_readable(&self) -> Result<String, Gs1Error> {
+        Ok(self
+            .elements()?
+            .into_iter()
+            .map(|(ai, value)| format!("({}){}", ai, value))
+            .collect())
+    }
+
+    /// Decode a scanned element string or a human readable string
+    pub fn decode(data: &str) -> Result<Self, Gs1Error> {
+        let data = data.trim();
+        let data = data.strip_prefix(SYMBOLOGY_ID).unwrap_or(data);
+        let data = data.trim_start_matches(GS);
+
+        let elements = if data.starts_with('(') {
+            split_human_readable(data)?
+        } else {
+            split_element_string(data)?
+        };
+
+        let mut gtin = None;
+        let mut label = Self {
+            gtin: Gtin(String::new()),
+            lot: None,
+            expiry_date: None,
+            best_before: None,
+            net_weight_kg: None,
+        };
+
+        for (ai, value) in elements {
+            let invalid = || Gs1Error::InvalidValue {
+                ai: ai.clone(),
+                value: value.clone(),
+            };
+            match ai.as_str() {
+                "01" => gtin = Some(Gtin::parse(&value)?),
+                "10" => {
+                    if value.is_empty() || value.len() > MAX_LOT_LEN {
+                        return Err(invalid());
+                    }
+                    label.lot = Some(value.clone());
+                }
+                "15" => label.best_before = Some(decode_date(&value).ok_or_else(invalid)?),
+                "17" => label.expiry_date = Some(decode_date(&value).ok_or_else(invalid)?),
+                "3103" => {
+                    let milli: i64 = value.parse().map_err(|_| invalid())?;
+                    label.net_weight_kg = Some(Qty::from_milli(milli));
+                }
+                _ => return Err(Gs1Error::UnknownAi { ai }),
+            }
+        }
+
+        label.gtin = gtin.ok_or(Gs1Error::MissingGtin)?;
+        Ok(label)
+    }
+
+    /// Whether a scanned string looks like GS1 data rather than an MCG QR code
+    pub fn looks_like_gs1(data: &str) -> bool {
+        let data = data.trim();
+        data.starts_with(SYMBOLOGY_ID) || data.starts_with(GS) || data.starts_with("(01)") || data.starts_with("01")
+    }
+}
+
+/// Fixed length of the value for each supported AI, `None` when variable
+fn fixed_length(ai: &str) -> Option<Option<usize>> {
+    match ai {
+        "01" => Some(Some(14)),
+        "15" | "17" | "3103" => Some(Some(6)),
+        "10" => Some(None),
+        _ => None,
+    }
+}
+
+/// AI at the start of `data`: four digits for the 31nn weight family
+fn read_ai(data: &str) -> Result<String, Gs1Error> {
+    let len = if data.starts_with("31") { 4 } else { 2 };
+    match data.get(..len) {
+        Some(ai) if ai.chars().all(|c| c.is_ascii_digit()) => Ok(ai.to_string()),
+        _ => Err(Gs1Error::Truncated { ai: data.to_string() }),
+    }
+}
+
+fn split_element_string(mut data: &str) -> Result<Vec<(String, String)>, Gs1Error> {
+    let mut elements = Vec::new();
+    while !data.is_empty() {
+        let ai = read_ai(data)?;
+        let rest = &data[ai.len()..];
+
+        let (value, next) = match fixed_length(&ai) {
+            None => return Err(Gs1Error::UnknownAi { ai }),
+            Some(Some(len)) => {
+                if rest.len() < len || !rest.is_char_boundary(len) {
+                    return Err(Gs1Error::Truncated { ai });
+                }
+                // A superfluous FNC1 after a fixed field is tolerated
+                (&rest[..len], rest[len..].trim_start_matches(GS))
+            }
+            Some(None) => match rest.find(GS) {
+                Some(end) => (&rest[..end], &rest[end + 1..]),
+                None => (rest, ""),
+            },
+        };
+
+        elements.push((ai, value.to_string()));
+        data = next;
+    }
+    Ok(elements)
+}
+
+fn split_human_readable(data: &str) -> Result<Vec<(String, String)>, Gs1Error> {
+    data.split('(')
+        .skip(1)
+        .map(|part| {
+            let (ai, value) = part.split_once(')').ok_or_else(|| Gs1Error::Truncated { ai: part.to_string() })?;
+            match fixed_length(ai) {
+                None => Err(Gs1Error::UnknownAi { ai: ai.to_string() }),
+                Some(Some(len)) if value.len() != len => Err(Gs1Error::InvalidValue {
+                    ai: ai.to_string(),
+                    value: value.to_string(),
+                }),
+                Some(_) => Ok((ai.to_string(), value.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// GS1 character set 82 (printable ASCII except space, `"`, `#`, `$`, `@`, `[`, `\`, `]`, `^`, `` ` ``, `{`, `|`, `}`, `~`)
+fn is_gs1_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!%&'()*+,-./:;<=>?_".contains(c)
+}
+
+fn encode_date(date: NaiveDate) -> String {
+    date.format("%y%m%d").to_string()
+}
+
+/// YYMMDD, years 2000-2099; day 00 means the last day of the month
+fn decode_date(value: &str) -> Option<NaiveDate> {
+    if value.len() != 6 || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year = 2000 + value[0..2].parse::<i32>().ok()?;
+    let month: u32 = value[2..4].parse().ok()?;
+    let day: u32 = value[4..6].parse().ok()?;
+
+    if day == 0 {
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        return NaiveDate::from_ymd_opt(year, month, 1)
+            .and(NaiveDate::from_ymd_opt(next_year, next_month, 1))?
+            .pred_opt();
+    }
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_gtin_check_digit() {
+        assert_eq!(Gtin::check_digit("400638133393"), 1);
+        assert!(Gtin::parse("4006381333931").unwrap().is_ean13());
+        assert!(Gtin::parse("96385074").is_ok());
+        assert!(Gtin::parse("10614141000415").is_ok());
+
+        assert_eq!(
+            Gtin::parse("4006381333932"),
+            Err(Gs1Error::CheckDigit {
+                value: "4006381333932".to_string(),
+                expected: 1,
+                found: 2
+            })
+        );
+        assert!(matches!(Gtin::parse("40063813339"), Err(Gs1Error::GtinFormat { .. })));
+
+        let ean = Gtin::parse("4006381333931").unwrap();
+        assert_eq!(ean.to_gtin14(), "04006381333931");
+        assert!(ean.same_item(&Gtin::parse("04006381333931").unwrap()));
+    }
+
+    #[test]
+    fn test_gs1_128_round_trip() {
+        let label = Gs1Label {
+            gtin: Gtin::parse("10614141000415").unwrap(),
+            lot: Some("LPF-240115-001".to_string()),
+            expiry_date: Some(date(2024, 3, 15)),
+            best_before: None,
+            net_weight_kg: Some(Qty::from_f64(12.5)),
+        };
+
+        let encoded = label.encode().unwrap();
+        assert_eq!(encoded, "011061414100041517240315310301250010LPF-240115-001");
+        assert_eq!(
+            label.human_readable().unwrap(),
+            "(01)10614141000415(17)240315(3103)012500(10)LPF-240115-001"
+        );
+
+        assert_eq!(Gs1Label::decode(&encoded).unwrap(), label);
+        assert_eq!(Gs1Label::decode(&format!("]C1{}", encoded)).unwrap(), label);
+        assert_eq!(Gs1Label::decode(&label.human_readable().unwrap()).unwrap(), label);
+    }
+
+    #[test]
+    fn test_decode_fnc1_separated() {
+        // Lot first, terminated by FNC1
+        let scanned = format!("011061414100041510L42{}17240300", GS);
+        let label = Gs1Label::decode(&scanned).unwrap();
+        assert_eq!(label.lot.as_deref(), Some("L42"));
+        // Day 00: last day of the month
+        assert_eq!(label.expiry_date, Some(date(2024, 3, 31)));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(Gs1Label::decode("10L42"), Err(Gs1Error::MissingGtin));
+        assert!(Gs1Label::decode("0110614141000415").is_ok());
+        assert!(matches!(Gs1Label::decode("01106141410004"), Err(Gs1Error::Truncated { .. })));
+        assert!(matches!(
+            Gs1Label::decode("0110614141000415211234"),
+            Err(Gs1Error::UnknownAi { .. })
+        ));
+        assert!(matches!(
+            Gs1Label::decode("011061414100041517241345"),
+            Err(Gs1Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Gs1Label::decode("0110614141000416"),
+            Err(Gs1Error::CheckDigit { .. })
+        ));
+    }
+}
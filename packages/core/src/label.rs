@@ -0,0 +1,522 @@
+//! Printable labels
+//!
+//! Renders the `qr_code` strings of lots, orders and deliveries as labels:
+//!
+//! - SVG: full label, for screen preview and office printers
+//! - PNG: QR code only, to embed in documents
+//! - ZPL: Zebra command stream (`^BQ` QR barcode)
+//! - EPL: Eltron/Zebra EPL2 command stream (`b` QR barcode)
+//!
+//! All formats share one layout computed in millimetres: company header
+//! across the top, text block on the left (product, lot, DLC/DDM, net
+//! weight), QR code on the right. Printer streams convert millimetres to
+//! dots from the template DPI.
+
+use chrono::NaiveDate;
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::qty::Qty;
+use crate::types::UnitOfMeasure;
+
+/// Light modules around the QR code, as required by the QR specification
+const QUIET_ZONE: usize = 4;
+
+/// Label output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LabelFormat {
+    Svg,
+    Png,
+    Zpl,
+    Epl,
+}
+
+// ============================================================================
+// QR MATRIX
+// ============================================================================
+
+/// Encoded QR code modules (error correction level M)
+#[derive(Debug, Clone)]
+pub struct QrMatrix {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrMatrix {
+    pub fn encode(data: &str) -> Result<Self> {
+        let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+            .map_err(|e| Error::QrCode(format!("Encodage QR impossible: {}", e)))?;
+        Ok(Self {
+            width: code.width(),
+            modules: code.to_colors().into_iter().map(|c| c == Color::Dark).collect(),
+        })
+    }
+
+    /// Modules per side, without the quiet zone
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+
+    /// Modules per side, with the quiet zone
+    fn full_width(&self) -> usize {
+        self.width + 2 * QUIET_ZONE
+    }
+
+    /// SVG path of the dark modules, one unit per module, quiet zone included
+    fn svg_path(&self) -> String {
+        let mut path = String::new();
+        for y in 0..self.width {
+            for x in 0..self.width {
+                if self.is_dark(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE));
+                }
+            }
+        }
+        path
+    }
+
+    /// Standalone SVG of the QR code
+    pub fn to_svg(&self, module_px: u32) -> String {
+        let size = self.full_width();
+        let px = size as u32 * module_px;
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{px}\" height=\"{px}\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">\n",
+                "<rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/>\n",
+                "<path fill=\"#000\" d=\"{path}\"/>\n",
+                "</svg>\n"
+            ),
+            px = px,
+            size = size,
+            path = self.svg_path()
+        )
+    }
+
+    /// Grayscale PNG of the QR code
+    pub fn to_png(&self, module_px: u32) -> Result<Vec<u8>> {
+        let module_px = module_px.max(1) as usize;
+        let side = self.full_width() * module_px;
+
+        let mut pixels = vec![0xFFu8; side * side];
+        for y in 0..self.width {
+            for x in 0..self.width {
+                if !self.is_dark(x, y) {
+                    continue;
+                }
+                for dy in 0..module_px {
+                    let row = ((y + QUIET_ZONE) * module_px + dy) * side;
+                    let start = row + (x + QUIET_ZONE) * module_px;
+                    pixels[start..start + module_px].fill(0);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| Error::Internal(format!("Encodage PNG impossible: {}", e)))?;
+        Ok(out)
+    }
+}
+
+// ============================================================================
+// TEMPLATE & DATA
+// ============================================================================
+
+/// Label stock size and printer resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelTemplate {
+    pub width_mm: u32,
+    pub height_mm: u32,
+    /// Printer resolution (203, 300 or 600)
+    pub dpi: u32,
+}
+
+impl LabelTemplate {
+    /// 100 x 60 mm at 203 dpi, the pallet / carton label used in the warehouse
+    pub fn standard() -> Self {
+        Self {
+            width_mm: 100,
+            height_mm: 60,
+            dpi: 203,
+        }
+    }
+
+    /// Millimetres to printer dots, rounded
+    fn dots(&self, mm: f64) -> u32 {
+        (mm * self.dpi as f64 / 25.4).round() as u32
+    }
+}
+
+impl Default for LabelTemplate {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// Content printed on a label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelData {
+    pub company_header: String,
+    pub product_name: String,
+    pub lot_number: String,
+    /// Date limite de consommation
+    pub dlc: Option<NaiveDate>,
+    /// Date de durabilite minimale
+    pub ddm: Option<NaiveDate>,
+    pub net_weight: Option<(Qty, UnitOfMeasure)>,
+    /// Encoded `QrCodeData`
+    pub qr_payload: String,
+}
+
+/// Text style on the label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextStyle {
+    Header,
+    Title,
+    Body,
+}
+
+impl TextStyle {
+    /// Character height in millimetres
+    fn height_mm(&self) -> f64 {
+        match self {
+            TextStyle::Header => 4.0,
+            TextStyle::Title => 3.5,
+            TextStyle::Body => 3.0,
+        }
+    }
+
+    /// EPL resident font (1 to 5, larger is bigger)
+    fn epl_font(&self) -> u8 {
+        match self {
+            TextStyle::Header => 4,
+            TextStyle::Title => 3,
+            TextStyle::Body => 2,
+        }
+    }
+}
+
+/// Text line placed on the label, position in millimetres
+#[derive(Debug, Clone)]
+struct TextLine {
+    style: TextStyle,
+    x: f64,
+    y: f64,
+    text: String,
+}
+
+/// Positions shared by every output format
+#[derive(Debug, Clone)]
+struct Layout {
+    lines: Vec<TextLine>,
+    qr_x: f64,
+    qr_y: f64,
+    qr_size: f64,
+}
+
+const MARGIN_MM: f64 = 3.0;
+const LINE_SPACING: f64 = 1.5;
+
+// ============================================================================
+// LABEL
+// ============================================================================
+
+/// A label ready to render
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub template: LabelTemplate,
+    pub data: LabelData,
+}
+
+impl Label {
+    pub fn new(template: LabelTemplate, data: LabelData) -> Self {
+        Self { template, data }
+    }
+
+    /// Render in the requested format
+    pub fn render(&self, format: LabelFormat) -> Result<Vec<u8>> {
+        match format {
+            LabelFormat::Svg => self.to_svg().map(String::into_bytes),
+            LabelFormat::Png => self.qr_png(8),
+            LabelFormat::Zpl => self.to_zpl().map(String::into_bytes),
+            LabelFormat::Epl => self.to_epl().map(String::into_bytes),
+        }
+    }
+
+    fn text_lines(&self) -> Vec<(TextStyle, String)> {
+        let data = &self.data;
+        let mut lines = vec![
+            (TextStyle::Header, data.company_header.clone()),
+            (TextStyle::Title, data.product_name.clone()),
+            (TextStyle::Body, format!("Lot: {}", data.lot_number)),
+        ];
+        if let Some(dlc) = data.dlc {
+            lines.push((TextStyle::Body, format!("DLC: {}", dlc.format("%d/%m/%Y"))));
+        }
+        if let Some(ddm) = data.ddm {
+            lines.push((TextStyle::Body, format!("DDM: {}", ddm.format("%d/%m/%Y"))));
+        }
+        if let Some((qty, unit)) = data.net_weight {
+            lines.push((TextStyle::Body, format!("Poids net: {} {}", qty, unit.code())));
+        }
+        lines
+    }
+
+    fn layout(&self) -> Layout {
+        let width = self.template.width_mm as f64;
+        let height = self.template.height_mm as f64;
+
+        // The header spans the full width, the QR code sits below it
+        let qr_y = MARGIN_MM + TextStyle::Header.height_mm() * LINE_SPACING;
+        let qr_size = (height - qr_y - MARGIN_MM).min(width / 2.0 - MARGIN_MM);
+        let qr_x = width - MARGIN_MM - qr_size;
+
+        let mut y = MARGIN_MM;
+        let lines = self
+            .text_lines()
+            .into_iter()
+            .map(|(style, text)| {
+                let line = TextLine {
+                    style,
+                    x: MARGIN_MM,
+                    y,
+                    text,
+                };
+                y += style.height_mm() * LINE_SPACING;
+                line
+            })
+            .collect();
+
+        Layout {
+            lines,
+            qr_x,
+            qr_y,
+            qr_size,
+        }
+    }
+
+    /// QR code alone as PNG
+    pub fn qr_png(&self, module_px: u32) -> Result<Vec<u8>> {
+        QrMatrix::encode(&self.data.qr_payload)?.to_png(module_px)
+    }
+
+    /// Full label as SVG, sized in millimetres
+    pub fn to_svg(&self) -> Result<String> {
+        let qr = QrMatrix::encode(&self.data.qr_payload)?;
+        let layout = self.layout();
+        let (w, h) = (self.template.width_mm, self.template.height_mm);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">\n\
+             <rect width=\"{w}\" height=\"{h}\" fill=\"#fff\"/>\n",
+            w = w,
+            h = h
+        );
+
+        for line in &layout.lines {
+            let size = line.style.height_mm();
+            let weight = if line.style == TextStyle::Body { "normal" } else { "bold" };
+            svg.push_str(&format!(
+                "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Arial, sans-serif\" font-size=\"{:.2}\" font-weight=\"{}\">{}</text>\n",
+                line.x,
+                line.y + size,
+                size,
+                weight,
+                xml_escape(&line.text)
+            ));
+        }
+
+        let scale = layout.qr_size / qr.full_width() as f64;
+        svg.push_str(&format!(
+            "<path transform=\"translate({:.2} {:.2}) scale({:.4})\" fill=\"#000\" shape-rendering=\"crispEdges\" d=\"{}\"/>\n",
+            layout.qr_x,
+            layout.qr_y,
+            scale,
+            qr.svg_path()
+        ));
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    /// QR module size in dots, so the code fills the reserved square
+    fn qr_magnification(&self, layout: &Layout, max: u32) -> Result<u32> {
+        let qr = QrMatrix::encode(&self.data.qr_payload)?;
+        let dots = self.template.dots(layout.qr_size);
+        Ok((dots / qr.full_width() as u32).clamp(1, max))
+    }
+
+    /// Zebra ZPL II command stream
+    pub fn to_zpl(&self) -> Result<String> {
+        let layout = self.layout();
+        let t = &self.template;
+        let magnification = self.qr_magnification(&layout, 10)?;
+
+        let mut zpl = String::from("^XA\n^CI28\n");
+        zpl.push_str(&format!(
+            "^PW{}\n^LL{}\n^LH0,0\n",
+            t.dots(t.width_mm as f64),
+            t.dots(t.height_mm as f64)
+        ));
+
+        for line in &layout.lines {
+            let height = t.dots(line.style.height_mm());
+            zpl.push_str(&format!(
+                "^FO{},{}^A0N,{},{}^FH_^FD{}^FS\n",
+                t.dots(line.x),
+                t.dots(line.y),
+                height,
+                height,
+                zpl_escape(&line.text)
+            ));
+        }
+
+        // Error correction M, automatic input mode
+        zpl.push_str(&format!(
+            "^FO{},{}^BQN,2,{}^FH_^FDMA,{}^FS\n",
+            t.dots(layout.qr_x),
+            t.dots(layout.qr_y),
+            magnification,
+            zpl_escape(&self.data.qr_payload)
+        ));
+        zpl.push_str("^PQ1\n^XZ\n");
+        Ok(zpl)
+    }
+
+    /// EPL2 command stream
+    pub fn to_epl(&self) -> Result<String> {
+        let layout = self.layout();
+        let t = &self.template;
+        let magnification = self.qr_magnification(&layout, 99)?;
+
+        // Leading blank line resets the parser, N clears the image buffer
+        let mut epl = String::from("\nN\n");
+        epl.push_str(&format!(
+            "q{}\nQ{},24\n",
+            t.dots(t.width_mm as f64),
+            t.dots(t.height_mm as f64)
+        ));
+
+        for line in &layout.lines {
+            epl.push_str(&format!(
+                "A{},{},0,{},1,1,N,\"{}\"\n",
+                t.dots(line.x),
+                t.dots(line.y),
+                line.style.epl_font(),
+                epl_escape(&line.text)
+            ));
+        }
+
+        epl.push_str(&format!(
+            "b{},{},Q,m2,s{},eM,\"{}\"\n",
+            t.dots(layout.qr_x),
+            t.dots(layout.qr_y),
+            magnification,
+            epl_escape(&self.data.qr_payload)
+        ));
+        epl.push_str("P1\n");
+        Ok(epl)
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `^FH_` hex escapes for the ZPL control characters
+fn zpl_escape(text: &str) -> String {
+    text.replace('_', "_5F").replace('^', "_5E").replace('~', "_7E")
+}
+
+fn epl_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set `UPDATE_GOLDEN=1` to rewrite the files after an intended change
+    fn assert_golden(name: &str, actual: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/labels")
+            .join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("golden file {} missing", name));
+        assert_eq!(actual, expected, "output differs from {}", name);
+    }
+
+    fn sample_label() -> Label {
+        Label::new(
+            LabelTemplate::standard(),
+            LabelData {
+                company_header: "SARL Manchengo - Fromagerie".to_string(),
+                product_name: "Camembert 250g".to_string(),
+                lot_number: "LPF-240115-001".to_string(),
+                dlc: NaiveDate::from_ymd_opt(2024, 3, 15),
+                ddm: None,
+                net_weight: Some((Qty::from_f64(12.5), UnitOfMeasure::Kilogram)),
+                qr_payload: "MCG2:LPF:0190f3c2-7a5e-7b3d-9c1a-2f4e6d8b0a11:LPF-240115-001:20240315:K1:3F2A9C0B7D1E4A5B6C7D8E9F"
+                    .to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_svg_golden() {
+        assert_golden("lot_pf.svg", &sample_label().to_svg().unwrap());
+    }
+
+    #[test]
+    fn test_zpl_golden() {
+        assert_golden("lot_pf.zpl", &sample_label().to_zpl().unwrap());
+    }
+
+    #[test]
+    fn test_epl_golden() {
+        assert_golden("lot_pf.epl", &sample_label().to_epl().unwrap());
+    }
+
+    #[test]
+    fn test_png_matches_matrix() {
+        let label = sample_label();
+        let matrix = QrMatrix::encode(&label.data.qr_payload).unwrap();
+        let png = label.qr_png(2).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(png));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        let side = (matrix.width() + 2 * QUIET_ZONE) * 2;
+        assert_eq!((info.width as usize, info.height as usize), (side, side));
+        for (x, y) in [(0, 0), (3, 5), (matrix.width() - 1, matrix.width() - 1)] {
+            let px = pixels[((y + QUIET_ZONE) * 2) * side + (x + QUIET_ZONE) * 2];
+            assert_eq!(px == 0, matrix.is_dark(x, y));
+        }
+        // Quiet zone stays light
+        assert_eq!(pixels[0], 0xFF);
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(zpl_escape("A^B~C_D"), "A_5EB_7EC_5FD");
+        assert_eq!(epl_escape("Lait \"cru\""), "Lait \\\"cru\\\"");
+        assert_eq!(xml_escape("Sel & <epices>"), "Sel &amp; &lt;epices&gt;");
+    }
+}
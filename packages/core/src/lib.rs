@@ -3,14 +3,38 @@
 //! This crate provides foundational types and utilities shared across
 //! all Manchengo ERP components.
 
+pub mod clock;
+pub mod currency;
 pub mod error;
+pub mod error_code;
 pub mod fiscal;
+pub mod fiscal_id;
+pub mod fiscal_rules;
+pub mod gs1;
+pub mod label;
+pub mod qr;
+pub mod qty;
+pub mod tax;
 pub mod types;
+pub mod units;
 pub mod utils;
+pub mod words;
 
+pub use clock::{Clock, FixedClock, OffsetClock, SharedClock, SystemClock};
+pub use currency::{parse_rates_csv, Currency, CurrencyAmount, ExchangeRate, ExchangeRateTable, RateSource, RATE_SCALE};
 pub use error::{Error, Result};
+pub use error_code::{ErrorCode, LocalizedMessage, Severity};
 pub use fiscal::{
     calculate_timbre_fiscal, calculate_timbre_fiscal_centimes, calculate_ttc, calculate_tva,
     PaymentMethod, TVA_REDUCED, TVA_STANDARD,
 };
+pub use fiscal_id::{FiscalIdError, LegalForm};
+pub use fiscal_rules::{FiscalRuleBook, FiscalRuleSet, TimbreBracket};
+pub use gs1::{Gs1Error, Gs1Label, Gtin};
+pub use label::{Label, LabelData, LabelFormat, LabelTemplate, QrMatrix};
+pub use qr::{QrCodeData, QrEntityType, QrKey, QrKeyRing, QrValidationError, QrValidationResult};
+pub use qty::Qty;
+pub use tax::{RoundingMode, RoundingPolicy, RoundingScope, TaxLine, TvaBreakdown, TvaGroup};
 pub use types::*;
+pub use units::{PackDefinition, UnitConversions};
+pub use words::{amount_in_words, AmountInWords, Language};
@@ -0,0 +1,582 @@
+//! QR code payloads
+//!
+//! Two formats are in circulation:
+//!
+//! - v1 (legacy, verify only): `MCG:{TYPE}:{ID}:{REFERENCE}:{CHECKSUM}`,
+//!   checksum = first 8 bytes of SHA256(`{ID}:{REFERENCE}:{SECRET}`).
+//!   The expiry date is not encoded.
+//! - v2: `MCG2:{TYPE}:{ID}:{REFERENCE}:{EXPIRY}:{KID}:{MAC}`, expiry as
+//!   `YYYYMMDD` (or `-`), MAC = HMAC-SHA256 of everything before it with the
+//!   key `KID`, truncated to 12 bytes (24 hex chars).
+//!
+//! New labels are always v2, signed with the first key of the [`QrKeyRing`].
+//! The other keys stay valid for decoding, so keys can be rotated without
+//! reprinting: add the new key first, keep the old one until its labels are
+//! gone. v1 labels already on pallets are checked against the legacy secrets.
+
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::types::EntityId;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const V1_PREFIX: &str = "MCG";
+const V2_PREFIX: &str = "MCG2";
+
+/// Truncated MAC length in bytes
+const MAC_BYTES: usize = 12;
+
+/// Types of entities that can have QR codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QrEntityType {
+    LotMp,      // Raw material lot
+    LotPf,      // Finished product lot
+    Order,      // Production order
+    Delivery,   // Delivery note
+    Location,   // Warehouse location
+}
+
+impl QrEntityType {
+    /// Code used in the encoded string
+    pub fn code(&self) -> &'static str {
+        match self {
+            QrEntityType::LotMp => "LMP",
+            QrEntityType::LotPf => "LPF",
+            QrEntityType::Order => "ORD",
+            QrEntityType::Delivery => "DLV",
+            QrEntityType::Location => "LOC",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "LMP" => Some(QrEntityType::LotMp),
+            "LPF" => Some(QrEntityType::LotPf),
+            "ORD" => Some(QrEntityType::Order),
+            "DLV" => Some(QrEntityType::Delivery),
+            "LOC" => Some(QrEntityType::Location),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// KEYS
+// ============================================================================
+
+/// HMAC key with its identifier (printed in the QR string)
+#[derive(Clone)]
+pub struct QrKey {
+    pub kid: String,
+    secret: Vec<u8>,
+}
+
+impl std::fmt::Debug for QrKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QrKey").field("kid", &self.kid).finish_non_exhaustive()
+    }
+}
+
+impl QrKey {
+    /// Key id: 1 to 8 letters or digits
+    pub fn new(kid: &str, secret: &[u8]) -> Result<Self> {
+        if kid.is_empty() || kid.len() > 8 || !kid.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Error::Configuration(format!(
+                "Identifiant de cle QR invalide '{}' (1 a 8 lettres ou chiffres)",
+                kid
+            )));
+        }
+        if secret.len() < 16 {
+            return Err(Error::Configuration(format!(
+                "Cle QR '{}' trop courte (16 octets minimum)",
+                kid
+            )));
+        }
+        Ok(Self {
+            kid: kid.to_string(),
+            secret: secret.to_vec(),
+        })
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// Active QR keys; the first one signs new labels
+#[derive(Debug, Clone)]
+pub struct QrKeyRing {
+    keys: Vec<QrKey>,
+    legacy_secrets: Vec<String>,
+}
+
+impl QrKeyRing {
+    pub fn new(keys: Vec<QrKey>) -> Result<Self> {
+        if keys.is_empty() {
+            return Err(Error::Configuration("Aucune cle QR active".to_string()));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|k| k.kid == key.kid) {
+                return Err(Error::Configuration(format!("Cle QR '{}' en double", key.kid)));
+            }
+        }
+        Ok(Self {
+            keys,
+            legacy_secrets: Vec::new(),
+        })
+    }
+
+    /// Accept v1 labels signed with this secret
+    pub fn with_legacy_secret(mut self, secret: &str) -> Self {
+        self.legacy_secrets.push(secret.to_string());
+        self
+    }
+
+    /// Load from the environment
+    ///
+    /// - `QR_KEYS`: `KID=secret,KID=secret`, signing key first
+    /// - `QR_SECRET_KEY`: v1 secret; also the signing key `K0` when `QR_KEYS`
+    ///   is unset
+    /// - `QR_LEGACY_SECRETS`: further v1 secrets, comma separated (sites that
+    ///   ran without `QR_SECRET_KEY` list the former built-in default here)
+    ///
+    /// There is no built-in secret: without keys this is an error.
+    pub fn from_env() -> Result<Self> {
+        let legacy = std::env::var("QR_SECRET_KEY").ok().filter(|s| !s.is_empty());
+
+        let keys = match std::env::var("QR_KEYS") {
+            Ok(spec) if !spec.trim().is_empty() => spec
+                .split(',')
+                .map(|entry| {
+                    let (kid, secret) = entry.trim().split_once('=').ok_or_else(|| {
+                        Error::Configuration("QR_KEYS: format attendu KID=secret".to_string())
+                    })?;
+                    QrKey::new(kid, secret.as_bytes())
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => match &legacy {
+                Some(secret) => vec![QrKey::new("K0", secret.as_bytes())?],
+                None => {
+                    return Err(Error::Configuration(
+                        "QR_KEYS ou QR_SECRET_KEY doit etre defini".to_string(),
+                    ))
+                }
+            },
+        };
+
+        let mut ring = Self::new(keys)?;
+        if let Some(secret) = legacy {
+            ring = ring.with_legacy_secret(&secret);
+        }
+        if let Ok(secrets) = std::env::var("QR_LEGACY_SECRETS") {
+            for secret in secrets.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                ring = ring.with_legacy_secret(secret);
+            }
+        }
+        Ok(ring)
+    }
+
+    pub fn signing_key(&self) -> &QrKey {
+        &self.keys[0]
+    }
+
+    fn key(&self, kid: &str) -> Option<&QrKey> {
+        self.keys.iter().find(|k| k.kid == kid)
+    }
+}
+
+// ============================================================================
+// QR CODE DATA
+// ============================================================================
+
+/// Data encoded in QR codes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrCodeData {
+    pub entity_type: QrEntityType,
+    pub entity_id: EntityId,
+    pub reference: String,
+    pub expiry_date: Option<NaiveDate>,
+    /// Key that signed the payload, `None` for v1 labels
+    #[serde(default)]
+    pub key_id: Option<String>,
+    /// v2: truncated HMAC; v1: SHA256 checksum
+    pub checksum: String,
+}
+
+impl QrCodeData {
+    /// Create and sign with the ring's signing key (v2)
+    pub fn new(
+        entity_type: QrEntityType,
+        entity_id: EntityId,
+        reference: String,
+        expiry_date: Option<NaiveDate>,
+        keys: &QrKeyRing,
+    ) -> Self {
+        let key = keys.signing_key();
+        let mut qr = Self {
+            entity_type,
+            entity_id,
+            reference,
+            expiry_date,
+            key_id: Some(key.kid.clone()),
+            checksum: String::new(),
+        };
+        let tag = key.mac(&qr.signed_payload()).finalize().into_bytes();
+        qr.checksum = hex::encode_upper(&tag[..MAC_BYTES]);
+        qr
+    }
+
+    /// Encode to the QR string (v2, or v1 for legacy data)
+    pub fn encode(&self) -> String {
+        match self.key_id {
+            Some(_) => format!("{}:{}", self.signed_payload(), self.checksum),
+            None => format!(
+                "{}:{}:{}:{}:{}",
+                V1_PREFIX,
+                self.entity_type.code(),
+                self.entity_id,
+                self.reference,
+                self.checksum
+            ),
+        }
+    }
+
+    /// v2 string without the MAC
+    fn signed_payload(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            V2_PREFIX,
+            self.entity_type.code(),
+            self.entity_id,
+            self.reference,
+            self.expiry_date
+                .map(|d| d.format("%Y%m%d").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            self.key_id.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// Decode and verify a QR string (v2 or v1)
+    ///
+    /// Expiry is not checked here, see [`QrCodeData::validate`].
+    pub fn decode(data: &str, keys: &QrKeyRing) -> std::result::Result<Self, QrValidationError> {
+        let parts: Vec<&str> = data.split(':').collect();
+
+        let result = match parts.first().copied() {
+            Some(V2_PREFIX) => Self::decode_v2(&parts, keys),
+            Some(V1_PREFIX) => Self::decode_v1(&parts, keys),
+            Some(_) => Err(QrValidationError::InvalidPrefix),
+            None => Err(QrValidationError::InvalidFormat),
+        };
+
+        match &result {
+            Ok(qr) => tracing::debug!(
+                "QR decode success: type={:?}, id={}, ref={}, kid={:?}",
+                qr.entity_type,
+                qr.entity_id,
+                qr.reference,
+                qr.key_id
+            ),
+            Err(e) => tracing::warn!("QR decode failed: {:?}", e),
+        }
+        result
+    }
+
+    fn decode_common(
+        type_code: &str,
+        id: &str,
+        reference: &str,
+    ) -> std::result::Result<(QrEntityType, EntityId, String), QrValidationError> {
+        let entity_type = QrEntityType::from_code(type_code).ok_or(QrValidationError::UnknownEntityType)?;
+        let uuid = Uuid::parse_str(id).map_err(|_| QrValidationError::InvalidUuid)?;
+        if reference.is_empty() {
+            return Err(QrValidationError::EmptyReference);
+        }
+        Ok((entity_type, EntityId::from_uuid(uuid), reference.to_string()))
+    }
+
+    fn decode_v2(parts: &[&str], keys: &QrKeyRing) -> std::result::Result<Self, QrValidationError> {
+        // MCG2:TYPE:ID:REFERENCE:EXPIRY:KID:MAC
+        if parts.len() != 7 {
+            return Err(QrValidationError::InvalidFormat);
+        }
+
+        let (entity_type, entity_id, reference) = Self::decode_common(parts[1], parts[2], parts[3])?;
+        let expiry_date = match parts[4] {
+            "-" => None,
+            date => Some(
+                NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| QrValidationError::InvalidExpiry)?,
+            ),
+        };
+        let key = keys.key(parts[5]).ok_or(QrValidationError::UnknownKey)?;
+
+        let qr = Self {
+            entity_type,
+            entity_id,
+            reference,
+            expiry_date,
+            key_id: Some(key.kid.clone()),
+            checksum: parts[6].to_string(),
+        };
+
+        // Verify against the exact signed bytes, in constant time
+        let tag = hex::decode(parts[6]).map_err(|_| QrValidationError::ChecksumMismatch)?;
+        if tag.len() != MAC_BYTES {
+            return Err(QrValidationError::ChecksumMismatch);
+        }
+        let payload = data_before_mac(parts);
+        key.mac(&payload)
+            .verify_truncated_left(&tag)
+            .map_err(|_| QrValidationError::ChecksumMismatch)?;
+
+        Ok(qr)
+    }
+
+    fn decode_v1(parts: &[&str], keys: &QrKeyRing) -> std::result::Result<Self, QrValidationError> {
+        // MCG:TYPE:ID:REFERENCE:CHECKSUM
+        if parts.len() != 5 {
+            return Err(QrValidationError::InvalidFormat);
+        }
+
+        let (entity_type, entity_id, reference) = Self::decode_common(parts[1], parts[2], parts[3])?;
+        let received = parts[4];
+
+        let valid = keys
+            .legacy_secrets
+            .iter()
+            .any(|secret| constant_time_compare(received, &v1_checksum(&entity_id, &reference, secret)));
+        if !valid {
+            return Err(QrValidationError::ChecksumMismatch);
+        }
+
+        Ok(Self {
+            entity_type,
+            entity_id,
+            reference,
+            expiry_date: None, // Not encoded in v1
+            key_id: None,
+            checksum: received.to_string(),
+        })
+    }
+
+    /// Decode, verify and check expiry on a date
+    pub fn validate(data: &str, keys: &QrKeyRing, today: NaiveDate) -> QrValidationResult {
+        match Self::decode(data, keys) {
+            Ok(qr) if qr.is_expired(today) => QrValidationResult::failure(QrValidationError::ExpiredQr),
+            Ok(qr) => QrValidationResult::success(&qr),
+            Err(e) => QrValidationResult::failure(e),
+        }
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expiry_date.map(|d| d < today).unwrap_or(false)
+    }
+
+    /// Whether this is a v1 label (no key id, no expiry)
+    pub fn is_legacy(&self) -> bool {
+        self.key_id.is_none()
+    }
+
+    /// Check if this is a delivery QR code
+    pub fn is_delivery(&self) -> bool {
+        matches!(self.entity_type, QrEntityType::Delivery)
+    }
+
+    /// Get entity ID as string
+    pub fn entity_id_string(&self) -> String {
+        self.entity_id.to_string()
+    }
+}
+
+/// Everything before the last segment, as it was signed
+fn data_before_mac(parts: &[&str]) -> String {
+    parts[..parts.len() - 1].join(":")
+}
+
+/// v1 checksum: first 8 bytes of SHA256(`{ID}:{REFERENCE}:{SECRET}`)
+fn v1_checksum(entity_id: &EntityId, reference: &str, secret: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}:{}:{}", entity_id, reference, secret).as_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// Constant-time string comparison to prevent timing attacks
+fn constant_time_compare(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut result = 0u8;
+    for (x, y) in a.bytes().zip(b.bytes()) {
+        result |= x ^ y;
+    }
+    result == 0
+}
+
+// ============================================================================
+// QR CODE VALIDATION RESULT
+// ============================================================================
+
+/// Result of QR code validation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrValidationResult {
+    pub is_valid: bool,
+    pub entity_type: Option<QrEntityType>,
+    pub entity_id: Option<String>,
+    pub reference: Option<String>,
+    pub error: Option<QrValidationError>,
+}
+
+/// Possible QR validation errors
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QrValidationError {
+    InvalidFormat,
+    InvalidPrefix,
+    UnknownEntityType,
+    InvalidUuid,
+    EmptyReference,
+    InvalidExpiry,
+    UnknownKey,
+    ChecksumMismatch,
+    ExpiredQr,
+}
+
+impl QrValidationResult {
+    pub fn success(qr: &QrCodeData) -> Self {
+        Self {
+            is_valid: true,
+            entity_type: Some(qr.entity_type),
+            entity_id: Some(qr.entity_id.to_string()),
+            reference: Some(qr.reference.clone()),
+            error: None,
+        }
+    }
+
+    pub fn failure(error: QrValidationError) -> Self {
+        Self {
+            is_valid: false,
+            entity_type: None,
+            entity_id: None,
+            reference: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(kid: &str) -> QrKey {
+        QrKey::new(kid, format!("secret-{}-0123456789abcdef", kid).as_bytes()).unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_v2_round_trip_with_expiry() {
+        let keys = QrKeyRing::new(vec![key("K1")]).unwrap();
+        let qr = QrCodeData::new(
+            QrEntityType::LotPf,
+            EntityId::new(),
+            "LPF-240115-001".to_string(),
+            Some(date(2024, 3, 1)),
+            &keys,
+        );
+
+        let encoded = qr.encode();
+        assert!(encoded.starts_with("MCG2:LPF:"));
+        assert!(encoded.contains(":20240301:K1:"));
+        assert_eq!(encoded.rsplit(':').next().unwrap().len(), 2 * MAC_BYTES);
+
+        let decoded = QrCodeData::decode(&encoded, &keys).unwrap();
+        assert_eq!(decoded.entity_id, qr.entity_id);
+        assert_eq!(decoded.expiry_date, Some(date(2024, 3, 1)));
+        assert_eq!(decoded.key_id.as_deref(), Some("K1"));
+
+        assert!(QrCodeData::validate(&encoded, &keys, date(2024, 3, 1)).is_valid);
+        assert_eq!(
+            QrCodeData::validate(&encoded, &keys, date(2024, 3, 2)).error,
+            Some(QrValidationError::ExpiredQr)
+        );
+    }
+
+    #[test]
+    fn test_v2_tampering_rejected() {
+        let keys = QrKeyRing::new(vec![key("K1")]).unwrap();
+        let encoded = QrCodeData::new(
+            QrEntityType::LotMp,
+            EntityId::new(),
+            "LMP-001".to_string(),
+            Some(date(2024, 3, 1)),
+            &keys,
+        )
+        .encode();
+
+        // Pushing the expiry date invalidates the MAC
+        let tampered = encoded.replace(":20240301:", ":20250301:");
+        assert_eq!(
+            QrCodeData::decode(&tampered, &keys).unwrap_err(),
+            QrValidationError::ChecksumMismatch
+        );
+
+        let other = QrKeyRing::new(vec![key("K9")]).unwrap();
+        assert_eq!(QrCodeData::decode(&encoded, &other).unwrap_err(), QrValidationError::UnknownKey);
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old_ring = QrKeyRing::new(vec![key("K1")]).unwrap();
+        let old_label = QrCodeData::new(
+            QrEntityType::Delivery,
+            EntityId::new(),
+            "BL-001".to_string(),
+            None,
+            &old_ring,
+        )
+        .encode();
+
+        // K2 signs new labels, K1 labels still decode
+        let ring = QrKeyRing::new(vec![key("K2"), key("K1")]).unwrap();
+        assert_eq!(ring.signing_key().kid, "K2");
+        assert!(QrCodeData::decode(&old_label, &ring).is_ok());
+
+        // Once K1 is retired, its labels are refused
+        let retired = QrKeyRing::new(vec![key("K2")]).unwrap();
+        assert_eq!(QrCodeData::decode(&old_label, &retired).unwrap_err(), QrValidationError::UnknownKey);
+    }
+
+    #[test]
+    fn test_v1_labels_still_decode() {
+        let id = EntityId::new();
+        let legacy = format!("MCG:LMP:{}:LOT-2023-001:{}", id, v1_checksum(&id, "LOT-2023-001", "old-secret"));
+
+        let ring = QrKeyRing::new(vec![key("K1")]).unwrap();
+        assert_eq!(QrCodeData::decode(&legacy, &ring).unwrap_err(), QrValidationError::ChecksumMismatch);
+
+        let ring = ring.with_legacy_secret("old-secret");
+        let decoded = QrCodeData::decode(&legacy, &ring).unwrap();
+        assert!(decoded.is_legacy());
+        assert_eq!(decoded.reference, "LOT-2023-001");
+        assert_eq!(decoded.encode(), legacy);
+    }
+
+    #[test]
+    fn test_invalid_keys_rejected() {
+        assert!(QrKey::new("K:1", b"0123456789abcdef").is_err());
+        assert!(QrKey::new("K1", b"short").is_err());
+        assert!(QrKeyRing::new(vec![]).is_err());
+        assert!(QrKeyRing::new(vec![key("K1"), key("K1")]).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Fixed-point stock quantities
+//!
+//! `Qty` counts thousandths of the unit (grams for kg, millilitres for
+//! litres) in an `i64`, so lots empty exactly and balances never drift.
+//! Pieces, cartons and palettes round to whole units.
+//!
+//! JSON keeps the plain decimal form (`2.5`); SQLite stores the integer
+//! (`2500`) when the `sqlite` feature is enabled.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::{Money, UnitOfMeasure};
+
+/// Fixed-point quantity in thousandths of its unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Qty(i64);
+
+impl Qty {
+    /// Thousandths per unit
+    pub const SCALE: i64 = 1000;
+
+    pub fn zero() -> Self {
+        Self(0)
+    }
+
+    pub fn from_milli(milli: i64) -> Self {
+        Self(milli)
+    }
+
+    /// From a decimal value, rounded to the unit precision
+    pub fn new(value: f64, unit: UnitOfMeasure) -> Self {
+        Self::from_f64(value).round_to(unit)
+    }
+
+    /// From a decimal value, rounded to the thousandth
+    pub fn from_f64(value: f64) -> Self {
+        Self((value * Self::SCALE as f64).round() as i64)
+    }
+
+    /// Whole units (pieces, cartons)
+    pub fn units(units: i64) -> Self {
+        Self(units.checked_mul(Self::SCALE).expect("Qty overflow"))
+    }
+
+    pub fn milli(&self) -> i64 {
+        self.0
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    /// Round to the precision of a unit (whole pieces, grams for kg)
+    pub fn round_to(self, unit: UnitOfMeasure) -> Self {
+        let step = 10_i64.pow(3 - unit.decimals());
+        if step == 1 {
+            return self;
+        }
+        let half = step / 2;
+        let rounded = if self.0 >= 0 {
+            (self.0 + half) / step
+        } else {
+            (self.0 - half) / step
+        };
+        Self(rounded * step)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Scale by a ratio (recipe scaling), rounded to the unit precision
+    pub fn scale(self, ratio: f64, unit: UnitOfMeasure) -> Self {
+        Self((self.0 as f64 * ratio).round() as i64).round_to(unit)
+    }
+
+    /// Value of this quantity at a unit price, rounded to the centime
+    pub fn value_at(&self, unit_price: Money) -> Money {
+        let centimes = self.0 as i128 * unit_price.centimes() as i128;
+        let half = Self::SCALE as i128 / 2;
+        let rounded = if centimes >= 0 {
+            (centimes + half) / Self::SCALE as i128
+        } else {
+            (centimes - half) / Self::SCALE as i128
+        };
+        Money::from_centimes(rounded as i64)
+    }
+
+    /// Price per unit of a total cost spread over this quantity
+    pub fn unit_price_of(&self, total: Money) -> Option<Money> {
+        if self.0 == 0 {
+            return None;
+        }
+        let centimes = total.centimes() as i128 * Self::SCALE as i128 / self.0 as i128;
+        Some(Money::from_centimes(centimes as i64))
+    }
+}
+
+impl std::ops::Add for Qty {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.checked_add(rhs.0).expect("Qty overflow on addition"))
+    }
+}
+
+impl std::ops::Sub for Qty {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.checked_sub(rhs.0).expect("Qty overflow on subtraction"))
+    }
+}
+
+/// Whole multiples (batches, cartons)
+impl std::ops::Mul<i64> for Qty {
+    type Output = Self;
+    fn mul(self, rhs: i64) -> Self {
+        Self(self.0.checked_mul(rhs).expect("Qty overflow on multiplication"))
+    }
+}
+
+impl std::ops::AddAssign for Qty {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::SubAssign for Qty {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::ops::Neg for Qty {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl std::iter::Sum for Qty {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, q| acc + q)
+    }
+}
+
+impl std::fmt::Display for Qty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let units = abs / Self::SCALE as u64;
+        let milli = abs % Self::SCALE as u64;
+        if milli == 0 {
+            write!(f, "{}{}", sign, units)
+        } else {
+            let decimals = format!("{:03}", milli);
+            write!(f, "{}{}.{}", sign, units, decimals.trim_end_matches('0'))
+        }
+    }
+}
+
+impl Serialize for Qty {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Qty {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        if !value.is_finite() {
+            return Err(serde::de::Error::custom("quantite invalide"));
+        }
+        Ok(Self::from_f64(value))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::ToSql for Qty {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(rusqlite::types::ToSqlOutput::from(self.0))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::FromSql for Qty {
+    /// Stored as INTEGER thousandths; REAL results (AVG, TOTAL) are rounded
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value {
+            rusqlite::types::ValueRef::Integer(i) => Ok(Self(i)),
+            rusqlite::types::ValueRef::Real(f) => Ok(Self(f.round() as i64)),
+            _ => Err(rusqlite::types::FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_per_unit() {
+        assert_eq!(Qty::new(1.23456, UnitOfMeasure::Kilogram).milli(), 1235);
+        assert_eq!(Qty::new(2.6, UnitOfMeasure::Piece), Qty::units(3));
+        assert_eq!(Qty::new(-2.5, UnitOfMeasure::Carton), Qty::units(-3));
+        assert_eq!(Qty::new(12.4, UnitOfMeasure::Gram), Qty::units(12));
+    }
+
+    #[test]
+    fn test_no_float_dust() {
+        // 0.1 + 0.2 - 0.3 is not zero in f64
+        let total = Qty::from_f64(0.1) + Qty::from_f64(0.2) - Qty::from_f64(0.3);
+        assert!(total.is_zero());
+
+        let mut remaining = Qty::from_f64(1.0);
+        for _ in 0..10 {
+            remaining -= Qty::from_f64(0.1);
+        }
+        assert!(remaining.is_zero());
+    }
+
+    #[test]
+    fn test_serde_keeps_decimal_form() {
+        let qty = Qty::from_f64(2.5);
+        assert_eq!(serde_json::to_string(&qty).unwrap(), "2.5");
+        assert_eq!(serde_json::from_str::<Qty>("2.5").unwrap(), qty);
+        assert_eq!(serde_json::from_str::<Qty>("3").unwrap(), Qty::units(3));
+    }
+
+    #[test]
+    fn test_value_and_display() {
+        let qty = Qty::from_f64(2.5);
+        assert_eq!(qty.value_at(Money::from_centimes(1001)), Money::from_centimes(2503));
+        assert_eq!(qty.unit_price_of(Money::from_centimes(5000)), Some(Money::from_centimes(2000)));
+        assert_eq!(Qty::zero().unit_price_of(Money::from_centimes(5000)), None);
+
+        assert_eq!(qty.to_string(), "2.5");
+        assert_eq!(Qty::units(4).to_string(), "4");
+        assert_eq!((-Qty::from_milli(50)).to_string(), "-0.05");
+    }
+}
@@ -0,0 +1,327 @@
+//! TVA rounding and breakdown
+//!
+//! All amounts are in centimes. Rates are converted to basis points
+//! (0.19 → 1900) so rounding is done in integer arithmetic and every
+//! result satisfies HT + TVA = TTC exactly.
+//!
+//! Two choices are explicit:
+//! - the rounding mode of a fractional centime (floor, half-up, half-even)
+//! - the rounding scope: each line on its own, or each TVA rate group of the
+//!   invoice (what the accountant recomputes from the TVA summary)
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Basis points in 100%
+const BP_SCALE: i128 = 10_000;
+
+/// How a fractional centime is rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoundingMode {
+    /// Round down (historical `calculate_tva` behaviour)
+    Floor,
+    /// Half away from zero
+    HalfUp,
+    /// Half to even (banker's rounding)
+    #[default]
+    HalfEven,
+}
+
+/// Where TVA is rounded on an invoice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoundingScope {
+    /// Each line's TVA is rounded, the invoice TVA is their sum
+    PerLine,
+    /// TVA is rounded once per rate, then allocated to the lines
+    #[default]
+    PerRateGroup,
+}
+
+/// Invoice rounding policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RoundingPolicy {
+    pub scope: RoundingScope,
+    pub mode: RoundingMode,
+}
+
+impl RoundingMode {
+    /// Round `num / den` (den > 0) to an integer
+    fn div(self, num: i128, den: i128) -> i64 {
+        let q = num.div_euclid(den);
+        let twice_rem = 2 * num.rem_euclid(den);
+
+        let rounded = match self {
+            RoundingMode::Floor => q,
+            _ if twice_rem < den => q,
+            _ if twice_rem > den => q + 1,
+            // Exactly half
+            RoundingMode::HalfUp if num >= 0 => q + 1,
+            RoundingMode::HalfUp => q,
+            RoundingMode::HalfEven if q % 2 == 0 => q,
+            RoundingMode::HalfEven => q + 1,
+        };
+        rounded as i64
+    }
+}
+
+/// TVA rate in basis points
+fn rate_bp(tva_rate: f64) -> i128 {
+    (tva_rate * BP_SCALE as f64).round() as i128
+}
+
+/// TVA on an HT amount
+pub fn tva_amount(amount_ht: i64, tva_rate: f64, mode: RoundingMode) -> i64 {
+    mode.div(amount_ht as i128 * rate_bp(tva_rate), BP_SCALE)
+}
+
+/// HT amount contained in a TTC amount (price agreed TTC)
+///
+/// ```
+/// use manchengo_core::tax::{ht_from_ttc, RoundingMode};
+///
+/// // 1,190.00 DA TTC at 19% → 1,000.00 DA HT
+/// assert_eq!(ht_from_ttc(119_000, 0.19, RoundingMode::HalfEven), 100_000);
+/// ```
+pub fn ht_from_ttc(amount_ttc: i64, tva_rate: f64, mode: RoundingMode) -> i64 {
+    mode.div(amount_ttc as i128 * BP_SCALE, BP_SCALE + rate_bp(tva_rate))
+}
+
+/// Split a TTC amount into (HT, TVA), with HT + TVA = TTC
+pub fn split_ttc(amount_ttc: i64, tva_rate: f64, mode: RoundingMode) -> (i64, i64) {
+    let ht = ht_from_ttc(amount_ttc, tva_rate, mode);
+    (ht, amount_ttc - ht)
+}
+
+/// Invoice line as seen by the TVA calculation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaxLine {
+    pub amount_ht: i64,
+    pub tva_rate: f64,
+    /// Agreed TTC: the line keeps it and its TVA is TTC - HT
+    pub amount_ttc: Option<i64>,
+}
+
+impl TaxLine {
+    /// Line priced HT
+    pub fn from_ht(amount_ht: i64, tva_rate: f64) -> Self {
+        Self {
+            amount_ht,
+            tva_rate,
+            amount_ttc: None,
+        }
+    }
+
+    /// Line priced TTC
+    pub fn from_ttc(amount_ttc: i64, tva_rate: f64, mode: RoundingMode) -> Self {
+        Self {
+            amount_ht: ht_from_ttc(amount_ttc, tva_rate, mode),
+            tva_rate,
+            amount_ttc: Some(amount_ttc),
+        }
+    }
+}
+
+/// TVA summary for one rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TvaGroup {
+    pub tva_rate: f64,
+    pub base_ht: i64,
+    pub tva: i64,
+}
+
+/// TVA of an invoice, per line and per rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TvaBreakdown {
+    /// TVA of each line, in input order
+    pub line_tva: Vec<i64>,
+    /// One entry per rate, lowest rate first
+    pub groups: Vec<TvaGroup>,
+    pub total_ht: i64,
+    pub total_tva: i64,
+}
+
+impl TvaBreakdown {
+    pub fn total_ttc(&self) -> i64 {
+        self.total_ht + self.total_tva
+    }
+}
+
+/// Compute the TVA of invoice lines under a rounding policy
+///
+/// With [`RoundingScope::PerRateGroup`], each group's TVA is rounded once on
+/// its HT base and spread over its lines by cumulative rounding, so line
+/// TVAs always add up to the group TVA. Lines with an agreed TTC keep it.
+pub fn tva_breakdown(lines: &[TaxLine], policy: RoundingPolicy) -> TvaBreakdown {
+    #[derive(Default)]
+    struct Group {
+        tva_rate: f64,
+        base_ht: i64,
+        tva: i64,
+        // Cumulative HT and TVA of the lines priced HT
+        priced_ht: i64,
+        priced_tva: i64,
+    }
+
+    let mut groups: BTreeMap<i128, Group> = BTreeMap::new();
+    let mut line_tva = Vec::with_capacity(lines.len());
+
+    for line in lines {
+        let group = groups.entry(rate_bp(line.tva_rate)).or_default();
+        group.tva_rate = line.tva_rate;
+        group.base_ht += line.amount_ht;
+
+        let tva = match (line.amount_ttc, policy.scope) {
+            (Some(ttc), _) => ttc - line.amount_ht,
+            (None, RoundingScope::PerLine) => tva_amount(line.amount_ht, line.tva_rate, policy.mode),
+            (None, RoundingScope::PerRateGroup) => {
+                group.priced_ht += line.amount_ht;
+                let cumulative = tva_amount(group.priced_ht, line.tva_rate, policy.mode);
+                let tva = cumulative - group.priced_tva;
+                group.priced_tva = cumulative;
+                tva
+            }
+        };
+
+        group.tva += tva;
+        line_tva.push(tva);
+    }
+
+    let groups: Vec<TvaGroup> = groups
+        .into_values()
+        .map(|g| TvaGroup {
+            tva_rate: g.tva_rate,
+            base_ht: g.base_ht,
+            tva: g.tva,
+        })
+        .collect();
+
+    TvaBreakdown {
+        total_ht: groups.iter().map(|g| g.base_ht).sum(),
+        total_tva: groups.iter().map(|g| g.tva).sum(),
+        line_tva,
+        groups,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const MODES: [RoundingMode; 3] = [RoundingMode::Floor, RoundingMode::HalfUp, RoundingMode::HalfEven];
+
+    #[test]
+    fn test_rounding_modes() {
+        // 50 × 19% = 9.5 centimes
+        assert_eq!(tva_amount(50, 0.19, RoundingMode::Floor), 9);
+        assert_eq!(tva_amount(50, 0.19, RoundingMode::HalfUp), 10);
+        assert_eq!(tva_amount(50, 0.19, RoundingMode::HalfEven), 10);
+        // 150 × 9% = 13.5 centimes
+        assert_eq!(tva_amount(150, 0.09, RoundingMode::HalfEven), 14);
+        // 250 × 9% = 22.5 centimes
+        assert_eq!(tva_amount(250, 0.09, RoundingMode::HalfEven), 22);
+        assert_eq!(tva_amount(250, 0.09, RoundingMode::HalfUp), 23);
+        // Credit notes round symmetrically
+        assert_eq!(tva_amount(-50, 0.19, RoundingMode::HalfUp), -10);
+        assert_eq!(tva_amount(-250, 0.09, RoundingMode::HalfEven), -22);
+    }
+
+    #[test]
+    fn test_per_rate_group_matches_accountant() {
+        // Three lines of 0.33 DA: per line 3 × round(6.27) = 18, per group round(18.81) = 19
+        let lines = vec![TaxLine::from_ht(33, 0.19); 3];
+
+        let per_line = tva_breakdown(
+            &lines,
+            RoundingPolicy { scope: RoundingScope::PerLine, mode: RoundingMode::HalfEven },
+        );
+        assert_eq!(per_line.total_tva, 18);
+
+        let per_group = tva_breakdown(&lines, RoundingPolicy::default());
+        assert_eq!(per_group.total_tva, 19);
+        assert_eq!(per_group.line_tva.iter().sum::<i64>(), 19);
+    }
+
+    #[test]
+    fn test_breakdown_by_rate() {
+        let lines = [
+            TaxLine::from_ht(100_000, 0.19),
+            TaxLine::from_ht(50_000, 0.09),
+            TaxLine::from_ttc(119_000, 0.19, RoundingMode::HalfEven),
+        ];
+        let breakdown = tva_breakdown(&lines, RoundingPolicy::default());
+
+        assert_eq!(
+            breakdown.groups,
+            vec![
+                TvaGroup { tva_rate: 0.09, base_ht: 50_000, tva: 4_500 },
+                TvaGroup { tva_rate: 0.19, base_ht: 200_000, tva: 38_000 },
+            ]
+        );
+        assert_eq!(breakdown.line_tva, vec![19_000, 4_500, 19_000]);
+        assert_eq!(breakdown.total_ttc(), 292_500);
+    }
+
+    fn rate() -> impl Strategy<Value = f64> {
+        prop_oneof![Just(0.0), Just(0.09), Just(0.19), (0..3000u32).prop_map(|bp| bp as f64 / 10_000.0)]
+    }
+
+    fn mode() -> impl Strategy<Value = RoundingMode> {
+        prop::sample::select(MODES.to_vec())
+    }
+
+    fn tax_line() -> impl Strategy<Value = TaxLine> {
+        (0..100_000_000i64, rate(), any::<bool>()).prop_map(|(amount, rate, ttc)| {
+            if ttc {
+                TaxLine::from_ttc(amount, rate, RoundingMode::HalfEven)
+            } else {
+                TaxLine::from_ht(amount, rate)
+            }
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_split_ttc_adds_up(ttc in -1_000_000_000_000i64..1_000_000_000_000, rate in rate(), mode in mode()) {
+            let (ht, tva) = split_ttc(ttc, rate, mode);
+            prop_assert_eq!(ht + tva, ttc);
+        }
+
+        #[test]
+        fn prop_ttc_round_trip(ht in 0..1_000_000_000_000i64, rate in rate()) {
+            for mode in [RoundingMode::HalfUp, RoundingMode::HalfEven] {
+                let ttc = ht + tva_amount(ht, rate, mode);
+                prop_assert_eq!(ht_from_ttc(ttc, rate, mode), ht);
+            }
+        }
+
+        #[test]
+        fn prop_breakdown_adds_up(
+            lines in prop::collection::vec(tax_line(), 0..30),
+            scope in prop::sample::select(vec![RoundingScope::PerLine, RoundingScope::PerRateGroup]),
+            mode in mode(),
+        ) {
+            let breakdown = tva_breakdown(&lines, RoundingPolicy { scope, mode });
+
+            prop_assert_eq!(breakdown.line_tva.len(), lines.len());
+            prop_assert_eq!(breakdown.total_ht, lines.iter().map(|l| l.amount_ht).sum::<i64>());
+            prop_assert_eq!(breakdown.total_tva, breakdown.line_tva.iter().sum::<i64>());
+            prop_assert_eq!(breakdown.total_tva, breakdown.groups.iter().map(|g| g.tva).sum::<i64>());
+            prop_assert_eq!(breakdown.total_ht + breakdown.total_tva, breakdown.total_ttc());
+
+            for (line, tva) in lines.iter().zip(&breakdown.line_tva) {
+                if let Some(ttc) = line.amount_ttc {
+                    prop_assert_eq!(line.amount_ht + tva, ttc);
+                }
+            }
+
+            if scope == RoundingScope::PerRateGroup && lines.iter().all(|l| l.amount_ttc.is_none()) {
+                for group in &breakdown.groups {
+                    prop_assert_eq!(group.tva, tva_amount(group.base_ht, group.tva_rate, mode));
+                }
+            }
+        }
+    }
+}
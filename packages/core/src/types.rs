@@ -2,9 +2,14 @@
 
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
 use uuid::Uuid;
 
+use crate::clock::Clock;
+use crate::error::Result;
+use crate::fiscal_id::{validate_identifiers, FiscalIdError};
+use crate::qty::Qty;
+use crate::units::{PackDefinition, UnitConversions};
+
 // ============================================================================
 // IDENTIFIERS
 // ============================================================================
@@ -167,6 +172,33 @@ impl Quantity {
     pub fn is_positive(&self) -> bool {
         self.value > 0.0
     }
+
+    /// Fixed-point stock quantity, rounded to the unit precision
+    pub fn to_qty(&self) -> Qty {
+        Qty::new(self.value, self.unit)
+    }
+
+    /// Convert to another unit of the same dimension (metric conversions)
+    pub fn convert_to(self, unit: UnitOfMeasure) -> Result<Self> {
+        UnitConversions::default().convert(self, unit, None)
+    }
+
+    /// Convert to another unit, using the product pack for count units
+    pub fn convert_with_pack(self, unit: UnitOfMeasure, pack: &PackDefinition) -> Result<Self> {
+        UnitConversions::default().convert(self, unit, Some(pack))
+    }
+
+    /// Add a quantity of a compatible unit, result in this unit
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
+        let rhs = rhs.convert_to(self.unit)?;
+        Ok(Self::new(self.value + rhs.value, self.unit))
+    }
+
+    /// Subtract a quantity of a compatible unit, result in this unit
+    pub fn checked_sub(self, rhs: Self) -> Result<Self> {
+        let rhs = rhs.convert_to(self.unit)?;
+        Ok(Self::new(self.value - rhs.value, self.unit))
+    }
 }
 
 /// Units of measure
@@ -194,6 +226,28 @@ impl UnitOfMeasure {
             Self::Palette => "PAL",
         }
     }
+
+    /// Decimal places kept in stock quantities (grams for kg, whole pieces)
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Self::Kilogram | Self::Litre => 3,
+            Self::Gram | Self::Millilitre | Self::Piece | Self::Carton | Self::Palette => 0,
+        }
+    }
+
+    /// Parse a `ref_units` code
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "KG" => Some(Self::Kilogram),
+            "G" => Some(Self::Gram),
+            "L" => Some(Self::Litre),
+            "ML" => Some(Self::Millilitre),
+            "PC" => Some(Self::Piece),
+            "CTN" => Some(Self::Carton),
+            "PAL" => Some(Self::Palette),
+            _ => None,
+        }
+    }
 }
 
 // ============================================================================
@@ -207,22 +261,27 @@ pub struct AuditInfo {
     pub created_by: EntityId,
     pub updated_at: DateTime<Utc>,
     pub updated_by: EntityId,
+    /// Business date of the last change when it was entered late
+    #[serde(default)]
+    pub business_date: Option<NaiveDate>,
 }
 
 impl AuditInfo {
-    pub fn new(user_id: EntityId) -> Self {
-        let now = Utc::now();
+    pub fn new(user_id: EntityId, clock: &dyn Clock) -> Self {
+        let now = clock.now();
         Self {
             created_at: now,
             created_by: user_id,
             updated_at: now,
             updated_by: user_id,
+            business_date: clock.business_date(),
         }
     }
 
-    pub fn update(&mut self, user_id: EntityId) {
-        self.updated_at = Utc::now();
+    pub fn update(&mut self, user_id: EntityId, clock: &dyn Clock) {
+        self.updated_at = clock.now();
         self.updated_by = user_id;
+        self.business_date = clock.business_date();
     }
 }
 
@@ -309,19 +368,20 @@ impl FiscalIdentity {
         }
     }
 
-    pub fn validate(&self) -> Vec<String> {
-        let mut errors = Vec::new();
-
-        if self.nif.is_empty() {
-            errors.push("NIF is required".to_string());
-        }
-        if self.nis.is_empty() {
-            errors.push("NIS is required".to_string());
-        }
-        if self.rc.is_empty() {
-            errors.push("RC is required".to_string());
-        }
-
+    /// Structural check of the identifiers (NIF, NIS and RC are required)
+    pub fn validate(&self) -> Vec<FiscalIdError> {
+        let mut errors: Vec<FiscalIdError> = [("nif", &self.nif), ("nis", &self.nis), ("rc", &self.rc)]
+            .into_iter()
+            .filter(|(_, value)| value.trim().is_empty())
+            .map(|(field, _)| FiscalIdError::Missing { field })
+            .collect();
+
+        errors.extend(validate_identifiers(
+            Some(&self.nif),
+            Some(&self.nis),
+            Some(&self.rc),
+            Some(&self.article_imposition),
+        ));
         errors
     }
 }
@@ -354,292 +414,6 @@ pub struct Address {
     pub postal_code: Option<String>,
 }
 
-// ============================================================================
-// QR CODE DATA
-// ============================================================================
-
-/// Types of entities that can have QR codes
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum QrEntityType {
-    LotMp,      // Raw material lot
-    LotPf,      // Finished product lot
-    Order,      // Production order
-    Delivery,   // Delivery note
-    Location,   // Warehouse location
-}
-
-/// Data encoded in QR codes
-/// Format: MCG:{TYPE}:{ID}:{REFERENCE}:{CHECKSUM}
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QrCodeData {
-    pub entity_type: QrEntityType,
-    pub entity_id: EntityId,
-    pub reference: String,
-    pub expiry_date: Option<NaiveDate>,
-    pub checksum: String,
-}
-
-// ============================================================================
-// QR CODE IMPLEMENTATION — SECURE DECODE WITH SHA256 CHECKSUM
-// ============================================================================
-
-impl QrCodeData {
-    /// Get the secret key from environment variable, falling back to a compile-time default.
-    /// IMPORTANT: Set QR_SECRET_KEY in production environment.
-    fn get_secret() -> String {
-        std::env::var("QR_SECRET_KEY").unwrap_or_else(|_| "MCG_QR_SECRET_2024_DEV".to_string())
-    }
-
-    /// Create a new QrCodeData with auto-generated checksum
-    pub fn new(
-        entity_type: QrEntityType,
-        entity_id: EntityId,
-        reference: String,
-        expiry_date: Option<NaiveDate>,
-    ) -> Self {
-        let mut qr = Self {
-            entity_type,
-            entity_id,
-            reference,
-            expiry_date,
-            checksum: String::new(),
-        };
-        qr.checksum = qr.compute_checksum(&Self::get_secret());
-        qr
-    }
-
-    /// Create QrCodeData with custom secret key
-    pub fn new_with_secret(
-        entity_type: QrEntityType,
-        entity_id: EntityId,
-        reference: String,
-        expiry_date: Option<NaiveDate>,
-        secret_key: &str,
-    ) -> Self {
-        let mut qr = Self {
-            entity_type,
-            entity_id,
-            reference,
-            expiry_date,
-            checksum: String::new(),
-        };
-        qr.checksum = qr.compute_checksum(secret_key);
-        qr
-    }
-
-    /// Encode QR data to string format
-    /// Output: MCG:{TYPE}:{ID}:{REFERENCE}:{CHECKSUM}
-    pub fn encode(&self) -> String {
-        format!(
-            "MCG:{}:{}:{}:{}",
-            self.entity_type_code(),
-            self.entity_id,
-            self.reference,
-            self.checksum
-        )
-    }
-
-    /// Decode and validate QR string with default secret
-    /// Returns None if:
-    /// - Format is invalid
-    /// - Prefix is not "MCG"
-    /// - Entity type is unknown
-    /// - UUID is invalid
-    /// - Checksum validation fails
-    pub fn decode(data: &str) -> Option<Self> {
-        Self::decode_with_secret(data, &Self::get_secret())
-    }
-
-    /// Decode and validate QR string with custom secret key
-    /// Full validation including SHA256 checksum verification
-    pub fn decode_with_secret(data: &str, secret_key: &str) -> Option<Self> {
-        // Split by colon delimiter
-        let parts: Vec<&str> = data.split(':').collect();
-
-        // Validate segment count: MCG:TYPE:ID:REFERENCE:CHECKSUM = 5 parts
-        if parts.len() != 5 {
-            tracing::warn!("QR decode failed: expected 5 segments, got {}", parts.len());
-            return None;
-        }
-
-        // Validate MCG prefix
-        if parts[0] != "MCG" {
-            tracing::warn!("QR decode failed: invalid prefix '{}'", parts[0]);
-            return None;
-        }
-
-        // Parse entity type
-        let entity_type = match parts[1] {
-            "LMP" => QrEntityType::LotMp,
-            "LPF" => QrEntityType::LotPf,
-            "ORD" => QrEntityType::Order,
-            "DLV" => QrEntityType::Delivery,
-            "LOC" => QrEntityType::Location,
-            unknown => {
-                tracing::warn!("QR decode failed: unknown entity type '{}'", unknown);
-                return None;
-            }
-        };
-
-        // Parse UUID
-        let uuid = match Uuid::parse_str(parts[2]) {
-            Ok(u) => u,
-            Err(e) => {
-                tracing::warn!("QR decode failed: invalid UUID '{}': {}", parts[2], e);
-                return None;
-            }
-        };
-        let entity_id = EntityId::from_uuid(uuid);
-
-        // Extract reference and checksum
-        let reference = parts[3].to_string();
-        let received_checksum = parts[4].to_string();
-
-        // Validate reference is not empty
-        if reference.is_empty() {
-            tracing::warn!("QR decode failed: empty reference");
-            return None;
-        }
-
-        // Build QR data (without checksum initially)
-        let qr_data = Self {
-            entity_type,
-            entity_id,
-            reference,
-            expiry_date: None, // Not encoded in QR string
-            checksum: received_checksum.clone(),
-        };
-
-        // CRITICAL: Verify checksum
-        let expected_checksum = qr_data.compute_checksum(secret_key);
-        if !constant_time_compare(&received_checksum, &expected_checksum) {
-            tracing::warn!(
-                "QR decode failed: checksum mismatch for entity {}",
-                qr_data.entity_id
-            );
-            return None;
-        }
-
-        tracing::debug!(
-            "QR decode success: type={:?}, id={}, ref={}",
-            qr_data.entity_type,
-            qr_data.entity_id,
-            qr_data.reference
-        );
-
-        Some(qr_data)
-    }
-
-    /// Compute SHA256 checksum for this QR data
-    /// Input: {ENTITY_ID}:{REFERENCE}:{SECRET_KEY}
-    /// Output: First 16 chars of hex-encoded SHA256 hash
-    fn compute_checksum(&self, secret_key: &str) -> String {
-        let input = format!(
-            "{}:{}:{}",
-            self.entity_id,
-            self.reference,
-            secret_key
-        );
-
-        let mut hasher = Sha256::new();
-        hasher.update(input.as_bytes());
-        let result = hasher.finalize();
-
-        // Return first 16 characters of hex-encoded hash (64 bits of entropy)
-        hex::encode(&result[..8])
-    }
-
-    /// Verify if this QR data has a valid checksum
-    pub fn verify_checksum(&self, secret_key: &str) -> bool {
-        let expected = self.compute_checksum(secret_key);
-        constant_time_compare(&self.checksum, &expected)
-    }
-
-    /// Get the entity type code for encoding
-    fn entity_type_code(&self) -> &'static str {
-        match self.entity_type {
-            QrEntityType::LotMp => "LMP",
-            QrEntityType::LotPf => "LPF",
-            QrEntityType::Order => "ORD",
-            QrEntityType::Delivery => "DLV",
-            QrEntityType::Location => "LOC",
-        }
-    }
-
-    /// Check if this is a delivery QR code
-    pub fn is_delivery(&self) -> bool {
-        matches!(self.entity_type, QrEntityType::Delivery)
-    }
-
-    /// Get entity ID as string
-    pub fn entity_id_string(&self) -> String {
-        self.entity_id.to_string()
-    }
-}
-
-/// Constant-time string comparison to prevent timing attacks
-fn constant_time_compare(a: &str, b: &str) -> bool {
-    if a.len() != b.len() {
-        return false;
-    }
-
-    let mut result = 0u8;
-    for (x, y) in a.bytes().zip(b.bytes()) {
-        result |= x ^ y;
-    }
-    result == 0
-}
-
-// ============================================================================
-// QR CODE VALIDATION RESULT
-// ============================================================================
-
-/// Result of QR code validation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QrValidationResult {
-    pub is_valid: bool,
-    pub entity_type: Option<QrEntityType>,
-    pub entity_id: Option<String>,
-    pub reference: Option<String>,
-    pub error: Option<QrValidationError>,
-}
-
-/// Possible QR validation errors
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum QrValidationError {
-    InvalidFormat,
-    InvalidPrefix,
-    UnknownEntityType,
-    InvalidUuid,
-    EmptyReference,
-    ChecksumMismatch,
-    ExpiredQr,
-}
-
-impl QrValidationResult {
-    pub fn success(qr: &QrCodeData) -> Self {
-        Self {
-            is_valid: true,
-            entity_type: Some(qr.entity_type),
-            entity_id: Some(qr.entity_id.to_string()),
-            reference: Some(qr.reference.clone()),
-            error: None,
-        }
-    }
-
-    pub fn failure(error: QrValidationError) -> Self {
-        Self {
-            is_valid: false,
-            entity_type: None,
-            entity_id: None,
-            reference: None,
-            error: Some(error),
-        }
-    }
-}
-
 // ============================================================================
 // DEVICE & SYNC
 // ============================================================================
@@ -0,0 +1,230 @@
+//! Unit conversions for quantities
+//!
+//! Metric conversions follow the `ref_units` table (`base_unit`,
+//! `conversion_factor`). Count units (piece, carton, palette) depend on the
+//! product and convert through its pack definition.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::types::{Quantity, UnitOfMeasure};
+
+/// Conversion of each unit to its base unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitConversions {
+    /// (unit, base unit, factor): 1 unit = factor base units
+    factors: Vec<(UnitOfMeasure, UnitOfMeasure, f64)>,
+}
+
+impl Default for UnitConversions {
+    /// Same values as the `ref_units` reference data
+    fn default() -> Self {
+        use UnitOfMeasure::*;
+
+        Self {
+            factors: vec![
+                (Kilogram, Kilogram, 1.0),
+                (Gram, Kilogram, 0.001),
+                (Litre, Litre, 1.0),
+                (Millilitre, Litre, 0.001),
+                (Piece, Piece, 1.0),
+                (Carton, Carton, 1.0),
+                (Palette, Palette, 1.0),
+            ],
+        }
+    }
+}
+
+impl UnitConversions {
+    /// Build from `ref_units` rows: (code, base_unit, conversion_factor).
+    ///
+    /// Codes without a `UnitOfMeasure` (T, SAC, BTE) are ignored.
+    pub fn from_ref_units<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Option<String>, f64)>,
+    {
+        let mut conversions = Self::default();
+
+        for (code, base_unit, factor) in rows {
+            let Some(unit) = UnitOfMeasure::from_code(&code) else {
+                continue;
+            };
+            let base = match base_unit.as_deref() {
+                Some(base) => match UnitOfMeasure::from_code(base) {
+                    Some(base) => base,
+                    None => continue,
+                },
+                None => unit,
+            };
+            if factor <= 0.0 {
+                continue;
+            }
+
+            if let Some(entry) = conversions.factors.iter_mut().find(|(u, _, _)| *u == unit) {
+                *entry = (unit, base, factor);
+            }
+        }
+
+        conversions
+    }
+
+    /// Base unit and factor of a unit
+    pub fn base_of(&self, unit: UnitOfMeasure) -> (UnitOfMeasure, f64) {
+        self.factors
+            .iter()
+            .find(|(u, _, _)| *u == unit)
+            .map(|(_, base, factor)| (*base, *factor))
+            .unwrap_or((unit, 1.0))
+    }
+
+    /// Convert a quantity, using the product pack for count units
+    pub fn convert(
+        &self,
+        quantity: Quantity,
+        to: UnitOfMeasure,
+        pack: Option<&PackDefinition>,
+    ) -> Result<Quantity> {
+        if quantity.unit == to {
+            return Ok(quantity);
+        }
+
+        let (from_base, from_factor) = self.base_of(quantity.unit);
+        let (to_base, to_factor) = self.base_of(to);
+
+        let ratio = if from_base == to_base {
+            Some(from_factor / to_factor)
+        } else {
+            pack.and_then(|p| p.ratio(from_base, to_base))
+                .map(|r| r * from_factor / to_factor)
+        };
+
+        match ratio {
+            Some(ratio) => Ok(Quantity::new(quantity.value * ratio, to)),
+            None => Err(Error::Validation {
+                field: "unit".to_string(),
+                message: format!(
+                    "Conversion impossible de {} vers {}",
+                    quantity.unit.code(),
+                    to.code()
+                ),
+            }),
+        }
+    }
+}
+
+/// Packaging of a product (pieces per carton, cartons per palette)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct PackDefinition {
+    pub pieces_per_carton: Option<f64>,
+    pub cartons_per_pallet: Option<f64>,
+}
+
+impl PackDefinition {
+    pub fn new(pieces_per_carton: Option<f64>, cartons_per_pallet: Option<f64>) -> Self {
+        Self {
+            pieces_per_carton,
+            cartons_per_pallet,
+        }
+    }
+
+    /// Number of `to` units in one `from` unit
+    pub fn ratio(&self, from: UnitOfMeasure, to: UnitOfMeasure) -> Option<f64> {
+        use UnitOfMeasure::*;
+
+        let pieces_per_carton = self.pieces_per_carton.filter(|v| *v > 0.0);
+        let cartons_per_pallet = self.cartons_per_pallet.filter(|v| *v > 0.0);
+
+        match (from, to) {
+            _ if from == to => Some(1.0),
+            (Carton, Piece) => pieces_per_carton,
+            (Palette, Carton) => cartons_per_pallet,
+            (Palette, Piece) => Some(cartons_per_pallet? * pieces_per_carton?),
+            (Piece, Carton) | (Carton, Palette) | (Piece, Palette) => {
+                self.ratio(to, from).map(|r| 1.0 / r)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_conversions() {
+        let conversions = UnitConversions::default();
+
+        let kg = conversions
+            .convert(Quantity::new(2500.0, UnitOfMeasure::Gram), UnitOfMeasure::Kilogram, None)
+            .unwrap();
+        assert!((kg.value - 2.5).abs() < 1e-9);
+
+        let ml = conversions
+            .convert(Quantity::litre(1.5), UnitOfMeasure::Millilitre, None)
+            .unwrap();
+        assert!((ml.value - 1500.0).abs() < 1e-9);
+
+        assert!(conversions
+            .convert(Quantity::kg(1.0), UnitOfMeasure::Litre, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_pack_conversions() {
+        let conversions = UnitConversions::default();
+        let pack = PackDefinition::new(Some(12.0), Some(40.0));
+
+        let pieces = conversions
+            .convert(Quantity::new(3.0, UnitOfMeasure::Carton), UnitOfMeasure::Piece, Some(&pack))
+            .unwrap();
+        assert_eq!(pieces.value, 36.0);
+
+        let pallets = conversions
+            .convert(Quantity::piece(960.0), UnitOfMeasure::Palette, Some(&pack))
+            .unwrap();
+        assert!((pallets.value - 2.0).abs() < 1e-9);
+
+        // No pack, or incomplete pack: count units do not convert
+        assert!(conversions
+            .convert(Quantity::new(1.0, UnitOfMeasure::Carton), UnitOfMeasure::Piece, None)
+            .is_err());
+        assert!(conversions
+            .convert(
+                Quantity::new(1.0, UnitOfMeasure::Palette),
+                UnitOfMeasure::Piece,
+                Some(&PackDefinition::new(None, Some(40.0)))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_ref_units() {
+        let conversions = UnitConversions::from_ref_units(vec![
+            ("G".to_string(), Some("KG".to_string()), 0.001),
+            ("T".to_string(), Some("KG".to_string()), 1000.0),
+        ]);
+
+        assert_eq!(conversions.base_of(UnitOfMeasure::Gram), (UnitOfMeasure::Kilogram, 0.001));
+        assert_eq!(conversions.base_of(UnitOfMeasure::Piece), (UnitOfMeasure::Piece, 1.0));
+    }
+
+    #[test]
+    fn test_checked_quantity_arithmetic() {
+        let total = Quantity::kg(1.0)
+            .checked_add(Quantity::new(250.0, UnitOfMeasure::Gram))
+            .unwrap();
+        assert_eq!(total.unit, UnitOfMeasure::Kilogram);
+        assert!((total.value - 1.25).abs() < 1e-9);
+
+        let rest = Quantity::new(500.0, UnitOfMeasure::Gram)
+            .checked_sub(Quantity::kg(0.2))
+            .unwrap();
+        assert!((rest.value - 300.0).abs() < 1e-9);
+
+        assert!(Quantity::kg(1.0).checked_add(Quantity::litre(1.0)).is_err());
+        assert!(Quantity::piece(1.0)
+            .checked_add(Quantity::new(1.0, UnitOfMeasure::Carton))
+            .is_err());
+    }
+}
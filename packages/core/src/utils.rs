@@ -1,40 +1,30 @@
 //! Utility functions for Manchengo ERP
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::fiscal_id::{Nif, Nis};
 
 /// Generate a reference code with prefix and date
-pub fn generate_reference(prefix: &str, sequence: u32) -> String {
-    let now = Utc::now();
-    format!(
-        "{}-{}{:02}{:02}-{:05}",
-        prefix,
-        now.format("%y"),
-        now.format("%m"),
-        now.format("%d"),
-        sequence
-    )
+pub fn generate_reference(prefix: &str, sequence: u32, date: NaiveDate) -> String {
+    format!("{}-{}-{:05}", prefix, date.format("%y%m%d"), sequence)
 }
 
-/// Validate Algerian NIF format
+/// Validate Algerian NIF format (see [`crate::fiscal_id::Nif`] for the segments)
 pub fn validate_nif(nif: &str) -> bool {
-    // NIF should be 15 digits
-    nif.len() == 15 && nif.chars().all(|c| c.is_ascii_digit())
+    Nif::parse(nif).is_ok()
 }
 
-/// Validate Algerian NIS format
+/// Validate Algerian NIS format (see [`crate::fiscal_id::Nis`] for the segments)
 pub fn validate_nis(nis: &str) -> bool {
-    // NIS should be 11 digits
-    nis.len() == 11 && nis.chars().all(|c| c.is_ascii_digit())
+    Nis::parse(nis).is_ok()
 }
 
 /// Calculate checksum for QR code data
 pub fn calculate_checksum(data: &str) -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+    use sha2::{Digest, Sha256};
 
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    format!("{:08x}", hasher.finish() & 0xFFFFFFFF)
+    let digest = Sha256::digest(data.as_bytes());
+    hex::encode(&digest[..4])
 }
 
 /// Parse ISO 8601 datetime string
@@ -50,9 +40,11 @@ mod tests {
 
     #[test]
     fn test_generate_reference() {
-        let ref1 = generate_reference("BL", 1);
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let ref1 = generate_reference("BL", 1, date);
         assert!(ref1.starts_with("BL-"));
         assert!(ref1.ends_with("-00001"));
+        assert_eq!(ref1, "BL-240305-00001");
     }
 
     #[test]
@@ -60,6 +52,7 @@ mod tests {
         assert!(validate_nif("123456789012345"));
         assert!(!validate_nif("12345")); // Too short
         assert!(!validate_nif("12345678901234A")); // Contains letter
+        assert!(!validate_nif("123499789012345")); // Wilaya 99
     }
 
     #[test]
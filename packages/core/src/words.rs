@@ -0,0 +1,488 @@
+//! Amounts in words (French and Arabic)
+//!
+//! Algerian invoices state the total in words:
+//! "Arrêtée la présente facture à la somme de mille deux cents dinars".
+//!
+//! French follows the traditional spelling: hyphens below one hundred only,
+//! "vingt" and "cent" take an "s" when multiplied and ending the number
+//! (but not before "mille"), "mille" is invariable, "million" and "milliard"
+//! are nouns ("un million de dinars").
+//!
+//! Arabic uses the masculine forms and the counted-noun rules of "dinar":
+//! دينار واحد، ديناران، ثلاثة دنانير، أحد عشر دينارا، مائة دينار.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Money;
+
+/// Output language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Language {
+    Fr,
+    Ar,
+}
+
+/// An amount written out in both invoice languages
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountInWords {
+    pub fr: String,
+    pub ar: String,
+}
+
+impl AmountInWords {
+    pub fn new(amount: Money) -> Self {
+        Self {
+            fr: amount_in_words(amount, Language::Fr),
+            ar: amount_in_words(amount, Language::Ar),
+        }
+    }
+}
+
+/// Write an amount in dinars and centimes
+///
+/// ```
+/// use manchengo_core::words::{amount_in_words, Language};
+/// use manchengo_core::Money;
+///
+/// assert_eq!(
+///     amount_in_words(Money::from_centimes(120_050), Language::Fr),
+///     "mille deux cents dinars et cinquante centimes"
+/// );
+/// assert_eq!(amount_in_words(Money::from_centimes(100_000), Language::Ar), "ألف دينار");
+/// ```
+pub fn amount_in_words(amount: Money, lang: Language) -> String {
+    let centimes = amount.centimes();
+    let abs = centimes.unsigned_abs();
+    let (dinars, cents) = (abs / 100, abs % 100);
+
+    let words = match lang {
+        Language::Fr => {
+            let dinars_part = fr::counted(dinars, "dinar");
+            match (dinars, cents) {
+                (_, 0) => dinars_part,
+                (0, _) => fr::counted(cents, "centime"),
+                _ => format!("{} et {}", dinars_part, fr::counted(cents, "centime")),
+            }
+        }
+        Language::Ar => {
+            let dinars_part = ar::counted(dinars, &ar::DINAR);
+            match (dinars, cents) {
+                (_, 0) => dinars_part,
+                (0, _) => ar::counted(cents, &ar::CENTIME),
+                _ => format!("{} و{}", dinars_part, ar::counted(cents, &ar::CENTIME)),
+            }
+        }
+    };
+
+    if centimes < 0 {
+        match lang {
+            Language::Fr => format!("moins {}", words),
+            Language::Ar => format!("ناقص {}", words),
+        }
+    } else {
+        words
+    }
+}
+
+/// Write an integer in words
+pub fn number_in_words(n: u64, lang: Language) -> String {
+    match lang {
+        Language::Fr => fr::number(n),
+        Language::Ar => ar::number(n),
+    }
+}
+
+mod fr {
+    const UNITS: [&str; 17] = [
+        "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix",
+        "onze", "douze", "treize", "quatorze", "quinze", "seize",
+    ];
+    const TENS: [&str; 7] = ["", "", "vingt", "trente", "quarante", "cinquante", "soixante"];
+
+    /// 1..=99
+    fn below_100(n: u64) -> String {
+        let n = n as usize;
+        match n {
+            0..=16 => UNITS[n].to_string(),
+            17..=19 => format!("dix-{}", UNITS[n - 10]),
+            20..=69 => match n % 10 {
+                0 => TENS[n / 10].to_string(),
+                1 => format!("{} et un", TENS[n / 10]),
+                u => format!("{}-{}", TENS[n / 10], UNITS[u]),
+            },
+            71 => "soixante et onze".to_string(),
+            70..=79 => format!("soixante-{}", below_100(n as u64 - 60)),
+            80 => "quatre-vingts".to_string(),
+            _ => format!("quatre-vingt-{}", below_100(n as u64 - 80)),
+        }
+    }
+
+    /// 1..=999; `ending` is false when "mille" follows ("deux cent mille")
+    fn below_1000(n: u64, ending: bool) -> String {
+        let (h, r) = (n / 100, n % 100);
+        let words = match (h, r) {
+            (0, _) => below_100(r),
+            (1, 0) => "cent".to_string(),
+            (1, _) => format!("cent {}", below_100(r)),
+            (_, 0) => format!("{} cents", UNITS[h as usize]),
+            (_, _) => format!("{} cent {}", UNITS[h as usize], below_100(r)),
+        };
+
+        if !ending && (words.ends_with("cents") || words.ends_with("vingts")) {
+            words[..words.len() - 1].to_string()
+        } else {
+            words
+        }
+    }
+
+    fn noun(count: u64, noun: &str) -> String {
+        let words = if count < 1000 { below_1000(count, true) } else { number(count) };
+        if count > 1 {
+            format!("{} {}s", words, noun)
+        } else {
+            format!("{} {}", words, noun)
+        }
+    }
+
+    pub(super) fn number(n: u64) -> String {
+        if n == 0 {
+            return UNITS[0].to_string();
+        }
+
+        let milliards = n / 1_000_000_000;
+        let millions = (n / 1_000_000) % 1000;
+        let thousands = (n / 1000) % 1000;
+        let rest = n % 1000;
+
+        let mut parts = Vec::new();
+        if milliards > 0 {
+            parts.push(noun(milliards, "milliard"));
+        }
+        if millions > 0 {
+            parts.push(noun(millions, "million"));
+        }
+        match thousands {
+            0 => {}
+            1 => parts.push("mille".to_string()),
+            _ => parts.push(format!("{} mille", below_1000(thousands, false))),
+        }
+        if rest > 0 {
+            parts.push(below_1000(rest, true));
+        }
+        parts.join(" ")
+    }
+
+    /// "un dinar", "deux dinars", "un million de dinars"
+    pub(super) fn counted(n: u64, unit: &str) -> String {
+        let words = number(n);
+        if n <= 1 {
+            format!("{} {}", words, unit)
+        } else if words.ends_with("million") || words.ends_with("millions")
+            || words.ends_with("milliard") || words.ends_with("milliards")
+        {
+            format!("{} de {}s", words, unit)
+        } else {
+            format!("{} {}s", words, unit)
+        }
+    }
+}
+
+mod ar {
+    /// Forms of a counted noun: singular, dual, plural (3-10), accusative (11-99)
+    pub(super) struct Forms {
+        singular: &'static str,
+        dual: &'static str,
+        plural: &'static str,
+        accusative: &'static str,
+    }
+
+    pub(super) const DINAR: Forms = Forms {
+        singular: "دينار",
+        dual: "ديناران",
+        plural: "دنانير",
+        accusative: "دينارا",
+    };
+    pub(super) const CENTIME: Forms = Forms {
+        singular: "سنتيم",
+        dual: "سنتيمان",
+        plural: "سنتيمات",
+        accusative: "سنتيما",
+    };
+    const THOUSAND: Forms = Forms {
+        singular: "ألف",
+        dual: "ألفان",
+        plural: "آلاف",
+        accusative: "ألفا",
+    };
+    const MILLION: Forms = Forms {
+        singular: "مليون",
+        dual: "مليونان",
+        plural: "ملايين",
+        accusative: "مليونا",
+    };
+    const MILLIARD: Forms = Forms {
+        singular: "مليار",
+        dual: "ملياران",
+        plural: "مليارات",
+        accusative: "مليارا",
+    };
+
+    const ONES: [&str; 11] = [
+        "صفر", "واحد", "اثنان", "ثلاثة", "أربعة", "خمسة", "ستة", "سبعة", "ثمانية", "تسعة", "عشرة",
+    ];
+    const TENS: [&str; 10] = [
+        "", "", "عشرون", "ثلاثون", "أربعون", "خمسون", "ستون", "سبعون", "ثمانون", "تسعون",
+    ];
+    const HUNDREDS: [&str; 10] = [
+        "", "مائة", "مائتان", "ثلاثمائة", "أربعمائة", "خمسمائة", "ستمائة", "سبعمائة", "ثمانمائة",
+        "تسعمائة",
+    ];
+
+    /// Duals lose their final nun before a noun (مائتا دينار، ألفا دينار)
+    const DUALS: [&str; 4] = ["مائتان", "ألفان", "مليونان", "ملياران"];
+    /// Counted scales lose their alif before a noun (أحد عشر ألف دينار)
+    const ACCUSATIVE_SCALES: [&str; 3] = [" ألفا", " مليونا", " مليارا"];
+
+    /// 1..=99
+    fn below_100(n: u64) -> String {
+        let n = n as usize;
+        match n {
+            0..=10 => ONES[n].to_string(),
+            11 => "أحد عشر".to_string(),
+            12 => "اثنا عشر".to_string(),
+            13..=19 => format!("{} عشر", ONES[n - 10]),
+            _ => match n % 10 {
+                0 => TENS[n / 10].to_string(),
+                u => format!("{} و{}", ONES[u], TENS[n / 10]),
+            },
+        }
+    }
+
+    /// 1..=999
+    fn below_1000(n: u64) -> String {
+        let (h, r) = (n / 100, n % 100);
+        match (h, r) {
+            (0, _) => below_100(r),
+            (_, 0) => HUNDREDS[h as usize].to_string(),
+            (_, _) => format!("{} و{}", HUNDREDS[h as usize], below_100(r)),
+        }
+    }
+
+    fn construct(mut words: String) -> String {
+        if DUALS.iter().chain(&ACCUSATIVE_SCALES).any(|w| words.ends_with(w)) {
+            words.pop();
+        }
+        words
+    }
+
+    /// Number followed by the right form of a noun
+    fn with_noun(n: u64, words: String, forms: &Forms) -> String {
+        match n % 100 {
+            3..=10 => format!("{} {}", words, forms.plural),
+            11..=99 => format!("{} {}", words, forms.accusative),
+            _ => format!("{} {}", construct(words), forms.singular),
+        }
+    }
+
+    fn scale(count: u64, forms: &Forms) -> String {
+        match count {
+            1 => forms.singular.to_string(),
+            2 => forms.dual.to_string(),
+            _ => {
+                let words = if count < 1000 { below_1000(count) } else { number(count) };
+                with_noun(count, words, forms)
+            }
+        }
+    }
+
+    pub(super) fn number(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+
+        let milliards = n / 1_000_000_000;
+        let millions = (n / 1_000_000) % 1000;
+        let thousands = (n / 1000) % 1000;
+        let rest = n % 1000;
+
+        let mut parts = Vec::new();
+        if milliards > 0 {
+            parts.push(scale(milliards, &MILLIARD));
+        }
+        if millions > 0 {
+            parts.push(scale(millions, &MILLION));
+        }
+        if thousands > 0 {
+            parts.push(scale(thousands, &THOUSAND));
+        }
+        if rest > 0 {
+            parts.push(below_1000(rest));
+        }
+        parts.join(" و")
+    }
+
+    /// "دينار واحد", "ديناران", "ثلاثة دنانير", "مائتا دينار"
+    pub(super) fn counted(n: u64, forms: &Forms) -> String {
+        match n {
+            0 => format!("{} {}", ONES[0], forms.singular),
+            1 => format!("{} {}", forms.singular, ONES[1]),
+            2 => forms.dual.to_string(),
+            _ => with_noun(n, number(n), forms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fr(dinars: i64) -> String {
+        amount_in_words(Money::from_centimes(dinars * 100), Language::Fr)
+    }
+
+    fn ar(dinars: i64) -> String {
+        amount_in_words(Money::from_centimes(dinars * 100), Language::Ar)
+    }
+
+    #[test]
+    fn test_french_below_hundred() {
+        let expected = [
+            (0, "zéro dinar"),
+            (1, "un dinar"),
+            (16, "seize dinars"),
+            (17, "dix-sept dinars"),
+            (21, "vingt et un dinars"),
+            (22, "vingt-deux dinars"),
+            (61, "soixante et un dinars"),
+            (70, "soixante-dix dinars"),
+            (71, "soixante et onze dinars"),
+            (72, "soixante-douze dinars"),
+            (77, "soixante-dix-sept dinars"),
+            (80, "quatre-vingts dinars"),
+            (81, "quatre-vingt-un dinars"),
+            (90, "quatre-vingt-dix dinars"),
+            (91, "quatre-vingt-onze dinars"),
+            (99, "quatre-vingt-dix-neuf dinars"),
+        ];
+        for (n, words) in expected {
+            assert_eq!(fr(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_french_vingt_cent_agreement() {
+        let expected = [
+            (100, "cent dinars"),
+            (101, "cent un dinars"),
+            (180, "cent quatre-vingts dinars"),
+            (200, "deux cents dinars"),
+            (201, "deux cent un dinars"),
+            (280, "deux cent quatre-vingts dinars"),
+            (1_000, "mille dinars"),
+            (1_001, "mille un dinars"),
+            (2_000, "deux mille dinars"),
+            (21_000, "vingt et un mille dinars"),
+            (80_000, "quatre-vingt mille dinars"),
+            (200_000, "deux cent mille dinars"),
+            (280_000, "deux cent quatre-vingt mille dinars"),
+            (1_200, "mille deux cents dinars"),
+        ];
+        for (n, words) in expected {
+            assert_eq!(fr(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_french_large_amounts() {
+        let expected = [
+            (1_000_000, "un million de dinars"),
+            (2_000_000, "deux millions de dinars"),
+            (1_000_001, "un million un dinars"),
+            (1_280_000, "un million deux cent quatre-vingt mille dinars"),
+            (2_500_000, "deux millions cinq cent mille dinars"),
+            (80_000_000, "quatre-vingts millions de dinars"),
+            (200_000_000, "deux cents millions de dinars"),
+            (1_000_000_000, "un milliard de dinars"),
+            (3_000_200_000, "trois milliards deux cent mille dinars"),
+        ];
+        for (n, words) in expected {
+            assert_eq!(fr(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_french_centimes() {
+        let money = Money::from_centimes;
+        assert_eq!(amount_in_words(money(1), Language::Fr), "un centime");
+        assert_eq!(amount_in_words(money(50), Language::Fr), "cinquante centimes");
+        assert_eq!(amount_in_words(money(101), Language::Fr), "un dinar et un centime");
+        assert_eq!(
+            amount_in_words(money(1_234_567), Language::Fr),
+            "douze mille trois cent quarante-cinq dinars et soixante-sept centimes"
+        );
+        assert_eq!(
+            amount_in_words(money(100_000_080), Language::Fr),
+            "un million de dinars et quatre-vingts centimes"
+        );
+        assert_eq!(amount_in_words(money(-8_000), Language::Fr), "moins quatre-vingts dinars");
+    }
+
+    #[test]
+    fn test_arabic_counted_noun() {
+        let expected = [
+            (0, "صفر دينار"),
+            (1, "دينار واحد"),
+            (2, "ديناران"),
+            (3, "ثلاثة دنانير"),
+            (10, "عشرة دنانير"),
+            (11, "أحد عشر دينارا"),
+            (12, "اثنا عشر دينارا"),
+            (71, "واحد وسبعون دينارا"),
+            (80, "ثمانون دينارا"),
+            (100, "مائة دينار"),
+            (103, "مائة وثلاثة دنانير"),
+            (200, "مائتا دينار"),
+            (250, "مائتان وخمسون دينارا"),
+        ];
+        for (n, words) in expected {
+            assert_eq!(ar(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_arabic_large_amounts() {
+        let expected = [
+            (1_000, "ألف دينار"),
+            (2_000, "ألفا دينار"),
+            (3_000, "ثلاثة آلاف دينار"),
+            (11_000, "أحد عشر ألف دينار"),
+            (11_500, "أحد عشر ألفا وخمسمائة دينار"),
+            (200_000, "مائتا ألف دينار"),
+            (1_200, "ألف ومائتا دينار"),
+            (1_000_000, "مليون دينار"),
+            (2_000_000, "مليونا دينار"),
+            (5_000_000, "خمسة ملايين دينار"),
+            (1_000_000_000, "مليار دينار"),
+            (1_500_075, "مليون وخمسمائة ألف وخمسة وسبعون دينارا"),
+        ];
+        for (n, words) in expected {
+            assert_eq!(ar(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_arabic_centimes() {
+        let money = Money::from_centimes;
+        assert_eq!(amount_in_words(money(50), Language::Ar), "خمسون سنتيما");
+        assert_eq!(amount_in_words(money(10_005), Language::Ar), "مائة دينار وخمسة سنتيمات");
+        assert_eq!(amount_in_words(money(202), Language::Ar), "ديناران وسنتيمان");
+    }
+
+    #[test]
+    fn test_amount_in_words_both_languages() {
+        let words = AmountInWords::new(Money::from_centimes(7_100));
+        assert_eq!(words.fr, "soixante et onze dinars");
+        assert_eq!(words.ar, "واحد وسبعون دينارا");
+    }
+}
@@ -0,0 +1,72 @@
+-- Manchengo ERP - Soft Deletes Migration
+-- Version: 5
+-- Description: Add deleted_at/deleted_by to entity tables (soft delete + sync tombstones)
+-- Note: stock_movements and event tables are append-only and never deleted
+
+ALTER TABLE users ADD COLUMN deleted_at TEXT;
+ALTER TABLE users ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_users_deleted ON users(deleted_at);
+
+ALTER TABLE suppliers ADD COLUMN deleted_at TEXT;
+ALTER TABLE suppliers ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_suppliers_deleted ON suppliers(deleted_at);
+
+ALTER TABLE products_mp ADD COLUMN deleted_at TEXT;
+ALTER TABLE products_mp ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_products_mp_deleted ON products_mp(deleted_at);
+
+ALTER TABLE products_pf ADD COLUMN deleted_at TEXT;
+ALTER TABLE products_pf ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_products_pf_deleted ON products_pf(deleted_at);
+
+ALTER TABLE lots_mp ADD COLUMN deleted_at TEXT;
+ALTER TABLE lots_mp ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_lots_mp_deleted ON lots_mp(deleted_at);
+
+ALTER TABLE lots_pf ADD COLUMN deleted_at TEXT;
+ALTER TABLE lots_pf ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_lots_pf_deleted ON lots_pf(deleted_at);
+
+ALTER TABLE warehouses ADD COLUMN deleted_at TEXT;
+ALTER TABLE warehouses ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_warehouses_deleted ON warehouses(deleted_at);
+
+ALTER TABLE recipes ADD COLUMN deleted_at TEXT;
+ALTER TABLE recipes ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_recipes_deleted ON recipes(deleted_at);
+
+ALTER TABLE production_orders ADD COLUMN deleted_at TEXT;
+ALTER TABLE production_orders ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_production_orders_deleted ON production_orders(deleted_at);
+
+ALTER TABLE clients ADD COLUMN deleted_at TEXT;
+ALTER TABLE clients ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_clients_deleted ON clients(deleted_at);
+
+ALTER TABLE price_lists ADD COLUMN deleted_at TEXT;
+ALTER TABLE price_lists ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_price_lists_deleted ON price_lists(deleted_at);
+
+ALTER TABLE sales_orders ADD COLUMN deleted_at TEXT;
+ALTER TABLE sales_orders ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_sales_orders_deleted ON sales_orders(deleted_at);
+
+ALTER TABLE vehicles ADD COLUMN deleted_at TEXT;
+ALTER TABLE vehicles ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_vehicles_deleted ON vehicles(deleted_at);
+
+ALTER TABLE deliveries ADD COLUMN deleted_at TEXT;
+ALTER TABLE deliveries ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_deliveries_deleted ON deliveries(deleted_at);
+
+ALTER TABLE invoices ADD COLUMN deleted_at TEXT;
+ALTER TABLE invoices ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_invoices_deleted ON invoices(deleted_at);
+
+ALTER TABLE payments ADD COLUMN deleted_at TEXT;
+ALTER TABLE payments ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_payments_deleted ON payments(deleted_at);
+
+ALTER TABLE cost_entries ADD COLUMN deleted_at TEXT;
+ALTER TABLE cost_entries ADD COLUMN deleted_by TEXT;
+CREATE INDEX idx_cost_entries_deleted ON cost_entries(deleted_at);
@@ -0,0 +1,15 @@
+-- Manchengo ERP - Maintenance Runs Migration
+-- Version: 6
+-- Description: Add _maintenance_runs table recording scheduled maintenance jobs
+
+CREATE TABLE IF NOT EXISTS _maintenance_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job TEXT NOT NULL,              -- WAL_CHECKPOINT, ANALYZE, INCREMENTAL_VACUUM, PURGE_EVENTS, PRUNE_QR_SCANS
+    status TEXT NOT NULL,           -- SUCCESS, FAILED, SKIPPED
+    started_at TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    rows_affected INTEGER NOT NULL DEFAULT 0,
+    message TEXT
+);
+
+CREATE INDEX idx_maintenance_runs_job ON _maintenance_runs(job, started_at);
@@ -0,0 +1,11 @@
+-- Manchengo ERP - Fiscal Archives Migration
+-- Version: 7
+-- Description: Add _fiscal_archives registry of closed fiscal years moved to archive files
+
+CREATE TABLE IF NOT EXISTS _fiscal_archives (
+    fiscal_year INTEGER PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    rows_archived INTEGER NOT NULL DEFAULT 0,
+    archived_at TEXT NOT NULL DEFAULT (datetime('now')),
+    archived_by TEXT NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Manchengo ERP - Product Packs Migration
+-- Version: 8
+-- Description: Add product_packs (pieces per carton, cartons per palette) for unit conversions
+
+CREATE TABLE IF NOT EXISTS product_packs (
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    pieces_per_carton REAL,
+    cartons_per_pallet REAL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (product_type, product_id)
+);
@@ -0,0 +1,74 @@
+-- Manchengo ERP - Fixed-Point Quantities Migration
+-- Version: 9
+-- Description: Store stock quantities as INTEGER thousandths of the unit (Qty)
+-- Note: each column is rebuilt (add, copy, drop, rename) so it gets INTEGER affinity
+
+-- lots_mp
+ALTER TABLE lots_mp ADD COLUMN quantity_initial_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE lots_mp SET quantity_initial_milli = CAST(ROUND(quantity_initial * 1000) AS INTEGER);
+ALTER TABLE lots_mp DROP COLUMN quantity_initial;
+ALTER TABLE lots_mp RENAME COLUMN quantity_initial_milli TO quantity_initial;
+ALTER TABLE lots_mp ADD COLUMN quantity_remaining_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE lots_mp SET quantity_remaining_milli = CAST(ROUND(quantity_remaining * 1000) AS INTEGER);
+ALTER TABLE lots_mp DROP COLUMN quantity_remaining;
+ALTER TABLE lots_mp RENAME COLUMN quantity_remaining_milli TO quantity_remaining;
+
+-- lots_pf
+ALTER TABLE lots_pf ADD COLUMN quantity_initial_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE lots_pf SET quantity_initial_milli = CAST(ROUND(quantity_initial * 1000) AS INTEGER);
+ALTER TABLE lots_pf DROP COLUMN quantity_initial;
+ALTER TABLE lots_pf RENAME COLUMN quantity_initial_milli TO quantity_initial;
+ALTER TABLE lots_pf ADD COLUMN quantity_remaining_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE lots_pf SET quantity_remaining_milli = CAST(ROUND(quantity_remaining * 1000) AS INTEGER);
+ALTER TABLE lots_pf DROP COLUMN quantity_remaining;
+ALTER TABLE lots_pf RENAME COLUMN quantity_remaining_milli TO quantity_remaining;
+
+-- stock_movements
+ALTER TABLE stock_movements ADD COLUMN quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_milli = CAST(ROUND(quantity * 1000) AS INTEGER);
+ALTER TABLE stock_movements DROP COLUMN quantity;
+ALTER TABLE stock_movements RENAME COLUMN quantity_milli TO quantity;
+ALTER TABLE stock_movements ADD COLUMN quantity_before_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_before_milli = CAST(ROUND(quantity_before * 1000) AS INTEGER);
+ALTER TABLE stock_movements DROP COLUMN quantity_before;
+ALTER TABLE stock_movements RENAME COLUMN quantity_before_milli TO quantity_before;
+ALTER TABLE stock_movements ADD COLUMN quantity_after_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_after_milli = CAST(ROUND(quantity_after * 1000) AS INTEGER);
+ALTER TABLE stock_movements DROP COLUMN quantity_after;
+ALTER TABLE stock_movements RENAME COLUMN quantity_after_milli TO quantity_after;
+
+-- recipes
+ALTER TABLE recipes ADD COLUMN output_quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE recipes SET output_quantity_milli = CAST(ROUND(output_quantity * 1000) AS INTEGER);
+ALTER TABLE recipes DROP COLUMN output_quantity;
+ALTER TABLE recipes RENAME COLUMN output_quantity_milli TO output_quantity;
+
+-- recipe_lines
+ALTER TABLE recipe_lines ADD COLUMN quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE recipe_lines SET quantity_milli = CAST(ROUND(quantity * 1000) AS INTEGER);
+ALTER TABLE recipe_lines DROP COLUMN quantity;
+ALTER TABLE recipe_lines RENAME COLUMN quantity_milli TO quantity;
+
+-- production_orders
+ALTER TABLE production_orders ADD COLUMN planned_quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE production_orders SET planned_quantity_milli = CAST(ROUND(planned_quantity * 1000) AS INTEGER);
+ALTER TABLE production_orders DROP COLUMN planned_quantity;
+ALTER TABLE production_orders RENAME COLUMN planned_quantity_milli TO planned_quantity;
+
+-- production_orders
+ALTER TABLE production_orders ADD COLUMN actual_quantity_milli INTEGER;
+UPDATE production_orders SET actual_quantity_milli = CAST(ROUND(actual_quantity * 1000) AS INTEGER);
+ALTER TABLE production_orders DROP COLUMN actual_quantity;
+ALTER TABLE production_orders RENAME COLUMN actual_quantity_milli TO actual_quantity;
+
+-- production_consumptions
+ALTER TABLE production_consumptions ADD COLUMN quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE production_consumptions SET quantity_milli = CAST(ROUND(quantity * 1000) AS INTEGER);
+ALTER TABLE production_consumptions DROP COLUMN quantity;
+ALTER TABLE production_consumptions RENAME COLUMN quantity_milli TO quantity;
+
+-- production_outputs
+ALTER TABLE production_outputs ADD COLUMN quantity_milli INTEGER NOT NULL DEFAULT 0;
+UPDATE production_outputs SET quantity_milli = CAST(ROUND(quantity * 1000) AS INTEGER);
+ALTER TABLE production_outputs DROP COLUMN quantity;
+ALTER TABLE production_outputs RENAME COLUMN quantity_milli TO quantity;
@@ -0,0 +1,64 @@
+-- Manchengo ERP - Fixed-Point Quantities Rollback
+-- Version: 9
+-- Description: Restore stock quantities as REAL values in the unit
+
+ALTER TABLE lots_mp ADD COLUMN quantity_initial_real REAL NOT NULL DEFAULT 0;
+UPDATE lots_mp SET quantity_initial_real = quantity_initial / 1000.0;
+ALTER TABLE lots_mp DROP COLUMN quantity_initial;
+ALTER TABLE lots_mp RENAME COLUMN quantity_initial_real TO quantity_initial;
+ALTER TABLE lots_mp ADD COLUMN quantity_remaining_real REAL NOT NULL DEFAULT 0;
+UPDATE lots_mp SET quantity_remaining_real = quantity_remaining / 1000.0;
+ALTER TABLE lots_mp DROP COLUMN quantity_remaining;
+ALTER TABLE lots_mp RENAME COLUMN quantity_remaining_real TO quantity_remaining;
+
+ALTER TABLE lots_pf ADD COLUMN quantity_initial_real REAL NOT NULL DEFAULT 0;
+UPDATE lots_pf SET quantity_initial_real = quantity_initial / 1000.0;
+ALTER TABLE lots_pf DROP COLUMN quantity_initial;
+ALTER TABLE lots_pf RENAME COLUMN quantity_initial_real TO quantity_initial;
+ALTER TABLE lots_pf ADD COLUMN quantity_remaining_real REAL NOT NULL DEFAULT 0;
+UPDATE lots_pf SET quantity_remaining_real = quantity_remaining / 1000.0;
+ALTER TABLE lots_pf DROP COLUMN quantity_remaining;
+ALTER TABLE lots_pf RENAME COLUMN quantity_remaining_real TO quantity_remaining;
+
+ALTER TABLE stock_movements ADD COLUMN quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_real = quantity / 1000.0;
+ALTER TABLE stock_movements DROP COLUMN quantity;
+ALTER TABLE stock_movements RENAME COLUMN quantity_real TO quantity;
+ALTER TABLE stock_movements ADD COLUMN quantity_before_real REAL NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_before_real = quantity_before / 1000.0;
+ALTER TABLE stock_movements DROP COLUMN quantity_before;
+ALTER TABLE stock_movements RENAME COLUMN quantity_before_real TO quantity_before;
+ALTER TABLE stock_movements ADD COLUMN quantity_after_real REAL NOT NULL DEFAULT 0;
+UPDATE stock_movements SET quantity_after_real = quantity_after / 1000.0;
+ALTER TABLE stock_movements DROP COLUMN quantity_after;
+ALTER TABLE stock_movements RENAME COLUMN quantity_after_real TO quantity_after;
+
+ALTER TABLE recipes ADD COLUMN output_quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE recipes SET output_quantity_real = output_quantity / 1000.0;
+ALTER TABLE recipes DROP COLUMN output_quantity;
+ALTER TABLE recipes RENAME COLUMN output_quantity_real TO output_quantity;
+
+ALTER TABLE recipe_lines ADD COLUMN quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE recipe_lines SET quantity_real = quantity / 1000.0;
+ALTER TABLE recipe_lines DROP COLUMN quantity;
+ALTER TABLE recipe_lines RENAME COLUMN quantity_real TO quantity;
+
+ALTER TABLE production_orders ADD COLUMN planned_quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE production_orders SET planned_quantity_real = planned_quantity / 1000.0;
+ALTER TABLE production_orders DROP COLUMN planned_quantity;
+ALTER TABLE production_orders RENAME COLUMN planned_quantity_real TO planned_quantity;
+
+ALTER TABLE production_orders ADD COLUMN actual_quantity_real REAL;
+UPDATE production_orders SET actual_quantity_real = actual_quantity / 1000.0;
+ALTER TABLE production_orders DROP COLUMN actual_quantity;
+ALTER TABLE production_orders RENAME COLUMN actual_quantity_real TO actual_quantity;
+
+ALTER TABLE production_consumptions ADD COLUMN quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE production_consumptions SET quantity_real = quantity / 1000.0;
+ALTER TABLE production_consumptions DROP COLUMN quantity;
+ALTER TABLE production_consumptions RENAME COLUMN quantity_real TO quantity;
+
+ALTER TABLE production_outputs ADD COLUMN quantity_real REAL NOT NULL DEFAULT 0;
+UPDATE production_outputs SET quantity_real = quantity / 1000.0;
+ALTER TABLE production_outputs DROP COLUMN quantity;
+ALTER TABLE production_outputs RENAME COLUMN quantity_real TO quantity;
@@ -0,0 +1,14 @@
+-- Manchengo ERP - Fiscal Rule Sets Migration
+-- Version: 10
+-- Description: Add fiscal_rule_sets (date-effective TVA rates, timbre brackets, exemptions)
+
+CREATE TABLE IF NOT EXISTS fiscal_rule_sets (
+    version TEXT PRIMARY KEY,           -- e.g. LF-2025
+    valid_from TEXT NOT NULL,           -- YYYY-MM-DD, inclusive
+    valid_to TEXT,                      -- YYYY-MM-DD, exclusive (NULL = in force)
+    rules TEXT NOT NULL,                -- JSON FiscalRuleSet
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_fiscal_rule_sets_validity ON fiscal_rule_sets(valid_from);
@@ -0,0 +1,9 @@
+-- Manchengo ERP - Product GTIN Migration
+-- Version: 11
+-- Description: Add EAN-13 (consumer unit) and GTIN-14 (carton) to finished products
+
+ALTER TABLE products_pf ADD COLUMN gtin TEXT;          -- EAN-13, check digit verified
+ALTER TABLE products_pf ADD COLUMN carton_gtin TEXT;   -- GTIN-14, GS1-128 AI(01)
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_products_pf_gtin ON products_pf(gtin) WHERE gtin IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_products_pf_carton_gtin ON products_pf(carton_gtin) WHERE carton_gtin IS NOT NULL;
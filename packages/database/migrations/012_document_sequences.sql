@@ -0,0 +1,29 @@
+-- Manchengo ERP - Document Sequences Migration
+-- Version: 12
+-- Description: Add gapless document numbering (per type, year and device prefix)
+
+CREATE TABLE IF NOT EXISTS document_sequences (
+    doc_type TEXT NOT NULL,             -- INVOICE, PURCHASE_ORDER, PRODUCTION_ORDER, DELIVERY
+    year INTEGER NOT NULL,
+    device_prefix TEXT NOT NULL DEFAULT '',  -- '' = central numbering
+    last_value INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (doc_type, year, device_prefix)
+);
+
+-- Every allocated number, voided ones included
+CREATE TABLE IF NOT EXISTS document_numbers (
+    number TEXT PRIMARY KEY,            -- e.g. FAC-2025-000042, FAC-P2-2025-000007
+    doc_type TEXT NOT NULL,
+    year INTEGER NOT NULL,
+    device_prefix TEXT NOT NULL DEFAULT '',
+    value INTEGER NOT NULL,
+    document_id TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'ISSUED',  -- ISSUED, VOIDED
+    void_reason TEXT,
+    allocated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    voided_at TEXT,
+    UNIQUE (doc_type, year, device_prefix, value)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_numbers_document ON document_numbers(document_id);
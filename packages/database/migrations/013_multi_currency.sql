@@ -0,0 +1,56 @@
+-- Manchengo ERP - Multi-Currency Purchases Migration
+-- Version: 13
+-- Description: Dated exchange rates, foreign-currency purchase orders and lot costs
+-- Note: rates are micro-dinars per unit (145.25 DA/EUR -> 145250000);
+--       foreign amounts are in minor units (cents) of their currency
+
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    currency TEXT NOT NULL,             -- EUR, USD
+    rate_date TEXT NOT NULL,            -- YYYY-MM-DD
+    rate INTEGER NOT NULL CHECK (rate > 0),
+    source TEXT NOT NULL DEFAULT 'MANUAL',  -- MANUAL, CSV
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT NOT NULL,
+    PRIMARY KEY (currency, rate_date)
+);
+
+-- Purchase orders: amounts in the order currency, DZD value at the order-date rate
+CREATE TABLE IF NOT EXISTS purchase_orders (
+    id TEXT PRIMARY KEY,
+    reference TEXT NOT NULL UNIQUE,
+    supplier_id TEXT NOT NULL REFERENCES suppliers(id),
+    status TEXT NOT NULL DEFAULT 'DRAFT',  -- DRAFT, CONFIRMED, SENT, RECEIVED, CANCELLED
+    expected_delivery TEXT,
+    received_date TEXT,
+    currency TEXT NOT NULL DEFAULT 'DZD',
+    exchange_rate INTEGER,
+    total_amount INTEGER NOT NULL DEFAULT 0,
+    total_amount_dzd INTEGER NOT NULL DEFAULT 0,  -- centimes
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_orders_supplier ON purchase_orders(supplier_id, created_at);
+
+CREATE TABLE IF NOT EXISTS purchase_order_lines (
+    id TEXT PRIMARY KEY,
+    purchase_order_id TEXT NOT NULL REFERENCES purchase_orders(id),
+    product_mp_id TEXT NOT NULL REFERENCES products_mp(id),
+    quantity INTEGER NOT NULL,
+    quantity_received INTEGER,
+    unit_price REAL NOT NULL,           -- order currency, minor units
+    line_total INTEGER NOT NULL,
+    sort_order INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_order_lines_order ON purchase_order_lines(purchase_order_id);
+
+-- Lots keep the supplier price in its currency and the rate used for unit_cost
+ALTER TABLE lots_mp ADD COLUMN currency TEXT NOT NULL DEFAULT 'DZD';
+ALTER TABLE lots_mp ADD COLUMN purchase_unit_cost INTEGER;
+ALTER TABLE lots_mp ADD COLUMN exchange_rate INTEGER;
+ALTER TABLE lots_mp ADD COLUMN exchange_rate_date TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_lots_mp_supplier ON lots_mp(supplier_id, reception_date);
@@ -0,0 +1,64 @@
+-- Manchengo ERP - Warehouse Locations Migration
+-- Version: 14
+-- Description: Bin locations, warehouse/location on lots and movements, per-warehouse thresholds
+-- Note: existing lots and movements are assigned to the default warehouse
+
+ALTER TABLE warehouses ADD COLUMN wilaya_code TEXT REFERENCES ref_wilayas(code);
+
+-- Warehouse ids become entity ids (QR labels of locations reference them);
+-- nothing references warehouses yet
+UPDATE warehouses SET id = '00000000-0000-4000-8000-000000000001' WHERE id = 'wh-default';
+
+-- Default site, holds everything recorded before warehouses
+INSERT INTO warehouses (id, code, name, is_default)
+SELECT '00000000-0000-4000-8000-000000000001', 'USINE', 'Usine', 1
+WHERE NOT EXISTS (SELECT 1 FROM warehouses WHERE is_default = 1);
+
+CREATE TABLE IF NOT EXISTS warehouse_locations (
+    id TEXT PRIMARY KEY,
+    warehouse_id TEXT NOT NULL REFERENCES warehouses(id),
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    location_type TEXT NOT NULL,  -- RECEPTION, COLD_ROOM, RIPENING_CELLAR, STORAGE, SHIPPING, QUARANTINE
+    is_active INTEGER NOT NULL DEFAULT 1,
+    qr_code TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT NOT NULL,
+    UNIQUE (warehouse_id, code)
+);
+
+-- Reception dock of every warehouse: received lots wait here for put-away
+INSERT INTO warehouse_locations (id, warehouse_id, code, name, location_type, created_by)
+SELECT lower(hex(randomblob(16))), w.id, 'RECEPTION', 'Quai de reception', 'RECEPTION', 'system'
+FROM warehouses w
+WHERE NOT EXISTS (
+    SELECT 1 FROM warehouse_locations l WHERE l.warehouse_id = w.id AND l.location_type = 'RECEPTION'
+);
+
+ALTER TABLE lots_mp ADD COLUMN warehouse_id TEXT REFERENCES warehouses(id);
+ALTER TABLE lots_mp ADD COLUMN location_id TEXT REFERENCES warehouse_locations(id);
+ALTER TABLE lots_mp ADD COLUMN put_away_at TEXT;
+ALTER TABLE lots_pf ADD COLUMN warehouse_id TEXT REFERENCES warehouses(id);
+ALTER TABLE lots_pf ADD COLUMN location_id TEXT REFERENCES warehouse_locations(id);
+ALTER TABLE lots_pf ADD COLUMN put_away_at TEXT;
+ALTER TABLE stock_movements ADD COLUMN warehouse_id TEXT REFERENCES warehouses(id);
+ALTER TABLE stock_movements ADD COLUMN location_id TEXT REFERENCES warehouse_locations(id);
+
+UPDATE lots_mp SET warehouse_id = (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1), put_away_at = created_at;
+UPDATE lots_pf SET warehouse_id = (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1), put_away_at = created_at;
+UPDATE stock_movements SET warehouse_id = (SELECT id FROM warehouses WHERE is_default = 1 LIMIT 1);
+
+CREATE INDEX IF NOT EXISTS idx_lots_mp_warehouse ON lots_mp(warehouse_id, product_id);
+CREATE INDEX IF NOT EXISTS idx_lots_pf_warehouse ON lots_pf(warehouse_id, product_id);
+CREATE INDEX IF NOT EXISTS idx_stock_movements_warehouse ON stock_movements(warehouse_id, product_id);
+
+-- Minimum stock per warehouse (the product minimum applies to the whole company)
+CREATE TABLE IF NOT EXISTS warehouse_stock_thresholds (
+    warehouse_id TEXT NOT NULL REFERENCES warehouses(id),
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    min_stock INTEGER NOT NULL DEFAULT 0,  -- thousandths of the stock unit
+    reorder_point INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (warehouse_id, product_type, product_id)
+);
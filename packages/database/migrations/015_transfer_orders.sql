@@ -0,0 +1,50 @@
+-- Manchengo ERP - Transfer Orders Migration
+-- Version: 15
+-- Description: Inter-warehouse transfer orders, in-transit locations, lot split lineage
+
+CREATE TABLE IF NOT EXISTS transfer_orders (
+    id TEXT PRIMARY KEY,
+    reference TEXT NOT NULL UNIQUE,  -- TRF-2025-000001
+    from_warehouse_id TEXT NOT NULL REFERENCES warehouses(id),
+    to_warehouse_id TEXT NOT NULL REFERENCES warehouses(id),
+    status TEXT NOT NULL DEFAULT 'DRAFT',  -- DRAFT, SHIPPED, RECEIVED, CANCELLED
+    notes TEXT,
+    shipped_at TEXT,
+    shipped_by TEXT,
+    received_at TEXT,
+    received_by TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK (from_warehouse_id <> to_warehouse_id)
+);
+
+CREATE TABLE IF NOT EXISTS transfer_order_lines (
+    id TEXT PRIMARY KEY,
+    transfer_id TEXT NOT NULL REFERENCES transfer_orders(id) ON DELETE CASCADE,
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    lot_id TEXT NOT NULL,        -- picked lot at the origin
+    lot_number TEXT NOT NULL,
+    quantity_shipped INTEGER NOT NULL,  -- thousandths of the stock unit
+    quantity_received INTEGER,
+    unit_cost INTEGER NOT NULL DEFAULT 0,  -- centimes, carried to the destination lot
+    transit_lot_id TEXT,         -- lot travelling (and received) at the destination
+    discrepancy_reason TEXT,
+    UNIQUE (transfer_id, lot_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfer_orders_status ON transfer_orders(status);
+CREATE INDEX IF NOT EXISTS idx_transfer_order_lines_transfer ON transfer_order_lines(transfer_id);
+
+-- Goods on the road: one in-transit location per destination warehouse
+INSERT INTO warehouse_locations (id, warehouse_id, code, name, location_type, created_by)
+SELECT lower(hex(randomblob(16))), w.id, 'EN-TRANSIT', 'En transit', 'TRANSIT', 'system'
+FROM warehouses w
+WHERE NOT EXISTS (
+    SELECT 1 FROM warehouse_locations l WHERE l.warehouse_id = w.id AND l.location_type = 'TRANSIT'
+);
+
+-- Lots split by a partial transfer keep a link to the lot they come from
+ALTER TABLE lots_mp ADD COLUMN parent_lot_id TEXT REFERENCES lots_mp(id);
+ALTER TABLE lots_pf ADD COLUMN parent_lot_id TEXT REFERENCES lots_pf(id);
@@ -0,0 +1,13 @@
+-- Manchengo ERP - Lot Selection Strategies Migration
+-- Version: 16
+-- Description: Per-product lot selection strategy (FIFO, FEFO, manual, minimum remaining shelf life)
+
+CREATE TABLE IF NOT EXISTS product_lot_strategies (
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    strategy TEXT NOT NULL DEFAULT 'FIFO',  -- FIFO, FEFO, MANUAL, MIN_SHELF_LIFE
+    min_shelf_life_days INTEGER,  -- MIN_SHELF_LIFE only
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (product_type, product_id),
+    CHECK (strategy <> 'MIN_SHELF_LIFE' OR min_shelf_life_days IS NOT NULL)
+);
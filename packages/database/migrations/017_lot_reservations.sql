@@ -0,0 +1,24 @@
+-- Manchengo ERP - Lot Reservations Migration
+-- Version: 17
+-- Description: Finished product lots held for confirmed sales orders until an expiry
+
+CREATE TABLE IF NOT EXISTS lot_reservations (
+    id TEXT PRIMARY KEY,
+    lot_pf_id TEXT NOT NULL REFERENCES lots_pf(id),
+    product_pf_id TEXT NOT NULL REFERENCES products_pf(id),
+    sales_order_id TEXT NOT NULL REFERENCES sales_orders(id),
+    sales_order_line_id TEXT NOT NULL REFERENCES sales_order_lines(id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),  -- thousandths, lot unit
+    status TEXT NOT NULL DEFAULT 'ACTIVE',  -- ACTIVE, CONSUMED, RELEASED, EXPIRED
+    expires_at TEXT NOT NULL,
+    release_reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT REFERENCES users(id),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Available-to-promise sums the active holds of a lot
+CREATE INDEX IF NOT EXISTS idx_lot_reservations_lot ON lot_reservations(lot_pf_id, status);
+CREATE INDEX IF NOT EXISTS idx_lot_reservations_order ON lot_reservations(sales_order_id);
+-- Timeout sweep
+CREATE INDEX IF NOT EXISTS idx_lot_reservations_expiry ON lot_reservations(status, expires_at);
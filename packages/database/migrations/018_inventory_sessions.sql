@@ -0,0 +1,43 @@
+-- Manchengo ERP - Inventory Sessions Migration
+-- Version: 18
+-- Description: Physical inventory sessions with frozen theoretical quantities, count sheets and variance approval
+
+CREATE TABLE IF NOT EXISTS inventory_sessions (
+    id TEXT PRIMARY KEY,
+    reference TEXT NOT NULL UNIQUE,  -- INV-2025-000001
+    warehouse_id TEXT NOT NULL REFERENCES warehouses(id),
+    status TEXT NOT NULL DEFAULT 'COUNTING',  -- COUNTING, REVIEW, APPROVED, CANCELLED
+    blind INTEGER NOT NULL DEFAULT 0,  -- counters do not see theoretical quantities
+    recount_threshold_pct REAL NOT NULL DEFAULT 10,
+    notes TEXT,
+    submitted_at TEXT,
+    approved_at TEXT,
+    approved_by TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS inventory_lines (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL REFERENCES inventory_sessions(id) ON DELETE CASCADE,
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    lot_id TEXT NOT NULL,
+    lot_number TEXT NOT NULL,
+    location_id TEXT,
+    quantity_theoretical INTEGER NOT NULL,  -- thousandths, frozen at opening
+    unit_cost INTEGER NOT NULL DEFAULT 0,   -- centimes
+    first_count INTEGER,
+    first_count_method TEXT,  -- SCAN, MANUAL
+    first_counted_by TEXT,
+    first_counted_at TEXT,
+    second_count INTEGER,
+    second_count_method TEXT,
+    second_counted_by TEXT,
+    second_counted_at TEXT,
+    UNIQUE (session_id, lot_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_sessions_status ON inventory_sessions(warehouse_id, status);
+CREATE INDEX IF NOT EXISTS idx_inventory_lines_session ON inventory_lines(session_id);
@@ -0,0 +1,21 @@
+-- Manchengo ERP - Stock Snapshots Migration
+-- Version: 19
+-- Description: Daily closing balances per lot and warehouse, for point-in-time stock queries
+
+CREATE TABLE IF NOT EXISTS stock_snapshots (
+    snapshot_date TEXT PRIMARY KEY,  -- balances at the end of this day
+    lines_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS stock_snapshot_lines (
+    snapshot_date TEXT NOT NULL REFERENCES stock_snapshots(snapshot_date) ON DELETE CASCADE,
+    product_type TEXT NOT NULL,  -- MP, PF
+    product_id TEXT NOT NULL,
+    lot_id TEXT,
+    warehouse_id TEXT,
+    quantity INTEGER NOT NULL,  -- thousandths, ledger balance
+    last_movement_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_stock_snapshot_lines_date ON stock_snapshot_lines(snapshot_date, product_type, product_id);
@@ -0,0 +1,10 @@
+-- Manchengo ERP - Movement Entry Time Migration
+-- Version: 20
+-- Description: Separate the real entry time of a stock movement from its business timestamp
+--
+-- created_at is the business timestamp (backdated entries carry the business
+-- date); recorded_at is when the movement was actually keyed in.
+
+ALTER TABLE stock_movements ADD COLUMN recorded_at TEXT;
+
+UPDATE stock_movements SET recorded_at = created_at WHERE recorded_at IS NULL;
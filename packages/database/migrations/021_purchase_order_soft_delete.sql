@@ -0,0 +1,10 @@
+-- Manchengo ERP - Purchase Order Soft Delete Migration
+-- Version: 21
+-- Description: Make purchase orders soft-deletable like the other documents
+--
+-- Purchase orders were created after the soft delete migration and only had
+-- is_deleted; a tombstone pulled from the server sets deleted_at.
+
+ALTER TABLE purchase_orders ADD COLUMN deleted_at TEXT;
+ALTER TABLE purchase_orders ADD COLUMN deleted_by TEXT;
+CREATE INDEX IF NOT EXISTS idx_purchase_orders_deleted ON purchase_orders(deleted_at);
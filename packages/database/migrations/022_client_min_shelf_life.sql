@@ -0,0 +1,8 @@
+-- Manchengo ERP - Client Minimum Shelf Life Migration
+-- Version: 22
+-- Description: Minimum remaining shelf life required by a client
+--
+-- Some clients (supermarkets) refuse lots close to their DLC. When set, the
+-- minimum applies on top of the product lot strategy at order confirmation.
+
+ALTER TABLE clients ADD COLUMN min_shelf_life_days INTEGER;
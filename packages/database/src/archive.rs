@@ -0,0 +1,430 @@
+//! Fiscal-year archives
+//!
+//! Closed fiscal years are moved out of the live database into one
+//! read-only SQLite file per year (legal retention is ten years). Reads whose
+//! date range reaches an archived year attach the files and query the live
+//! and archived rows together.
+
+use manchengo_core::{Error, Result};
+use rusqlite::{Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// SQLite allows 10 attached databases by default
+pub const MAX_ATTACHED_ARCHIVES: usize = 8;
+
+/// Table moved to the archive, with the rows selected for a fiscal year.
+///
+/// The filter is a WHERE clause on `main.<table>` and must use both `?1`
+/// (first day of the year) and `?2` (first day of the next year).
+#[derive(Debug, Clone)]
+pub struct ArchivedTable {
+    pub table: &'static str,
+    pub filter: String,
+}
+
+/// Registered archive of a closed fiscal year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiscalArchiveInfo {
+    pub fiscal_year: i32,
+    pub file_path: String,
+    pub rows_archived: i64,
+    pub archived_at: String,
+    pub archived_by: String,
+}
+
+/// Fiscal archive operations
+pub struct FiscalArchive;
+
+impl FiscalArchive {
+    /// Archive file name for a fiscal year
+    pub fn file_name(year: i32) -> String {
+        format!("manchengo_{}.archive.db", year)
+    }
+
+    /// First day of the year and first day of the next year (ISO dates)
+    pub fn year_bounds(year: i32) -> (String, String) {
+        (format!("{:04}-01-01", year), format!("{:04}-01-01", year + 1))
+    }
+
+    /// All registered archives, oldest first
+    pub fn list(conn: &Connection) -> Result<Vec<FiscalArchiveInfo>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT fiscal_year, file_path, rows_archived, archived_at, archived_by
+                 FROM _fiscal_archives ORDER BY fiscal_year",
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], Self::row_to_info)
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+        }
+        Ok(result)
+    }
+
+    /// Archive of a fiscal year, if any
+    pub fn get(conn: &Connection, year: i32) -> Result<Option<FiscalArchiveInfo>> {
+        conn.query_row(
+            "SELECT fiscal_year, file_path, rows_archived, archived_at, archived_by
+             FROM _fiscal_archives WHERE fiscal_year = ?",
+            [year],
+            Self::row_to_info,
+        )
+        .optional()
+        .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    /// Move a closed fiscal year into a new archive file.
+    ///
+    /// `before_move` runs in the same transaction before rows are removed
+    /// (e.g. to post opening balances). The file is made read-only once the
+    /// transaction is committed. Returns the number of rows archived.
+    pub fn archive_year<F>(
+        conn: &mut Connection,
+        year: i32,
+        path: &Path,
+        tables: &[ArchivedTable],
+        archived_by: &str,
+        before_move: F,
+    ) -> Result<i64>
+    where
+        F: FnOnce(&Transaction) -> Result<()>,
+    {
+        if Self::get(conn, year)?.is_some() {
+            return Err(Error::BusinessRule(format!("Exercice {} deja archive", year)));
+        }
+
+        if path.exists() {
+            return Err(Error::BusinessRule(format!(
+                "Le fichier d'archive existe deja: {}",
+                path.display()
+            )));
+        }
+
+        let alias = Self::alias(year);
+        let file_path = path.to_string_lossy().to_string();
+
+        conn.execute(&format!("ATTACH DATABASE ?1 AS {}", alias), [&file_path])
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let result = Self::move_rows(conn, year, &alias, &file_path, tables, archived_by, before_move);
+
+        conn.execute_batch(&format!("DETACH DATABASE {};", alias))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = match result {
+            Ok(rows) => rows,
+            Err(e) => {
+                // Nothing was committed: drop the partial file
+                let _ = std::fs::remove_file(path);
+                return Err(e);
+            }
+        };
+
+        Self::seal(path)?;
+        info!("Fiscal year {} archived to {} ({} rows)", year, file_path, rows);
+        Ok(rows)
+    }
+
+    /// Attach the archives overlapping `[from, to]` for the duration of `f`.
+    ///
+    /// Without a lower bound only the live database is read: archives are
+    /// consulted when the range explicitly reaches an archived year.
+    pub fn with_range<F, T>(conn: &Connection, from: Option<&str>, to: Option<&str>, f: F) -> Result<T>
+    where
+        F: FnOnce(&ArchiveScope) -> Result<T>,
+    {
+        let from_year = match from.and_then(Self::year_of) {
+            Some(year) => year,
+            None => return f(&ArchiveScope::default()),
+        };
+        let to_year = to.and_then(Self::year_of).unwrap_or(i32::MAX);
+
+        let archives: Vec<FiscalArchiveInfo> = Self::list(conn)?
+            .into_iter()
+            .filter(|a| a.fiscal_year >= from_year && a.fiscal_year <= to_year)
+            .collect();
+
+        if archives.len() > MAX_ATTACHED_ARCHIVES {
+            return Err(Error::Validation {
+                field: "from_date".to_string(),
+                message: format!("Periode trop longue (max {} exercices archives)", MAX_ATTACHED_ARCHIVES),
+            });
+        }
+
+        let mut scope = ArchiveScope::default();
+        for archive in &archives {
+            if !Path::new(&archive.file_path).exists() {
+                Self::detach_all(conn, &scope);
+                return Err(Error::NotFound {
+                    entity_type: "FiscalArchive".to_string(),
+                    id: archive.file_path.clone(),
+                });
+            }
+
+            let alias = Self::alias(archive.fiscal_year);
+            if let Err(e) = conn.execute(&format!("ATTACH DATABASE ?1 AS {}", alias), [&archive.file_path]) {
+                Self::detach_all(conn, &scope);
+                return Err(Error::Database(e.to_string()));
+            }
+            scope.aliases.push(alias);
+        }
+
+        let result = f(&scope);
+        Self::detach_all(conn, &scope);
+        result
+    }
+
+    // =========================================================================
+    // HELPERS
+    // =========================================================================
+
+    fn move_rows<F>(
+        conn: &mut Connection,
+        year: i32,
+        alias: &str,
+        file_path: &str,
+        tables: &[ArchivedTable],
+        archived_by: &str,
+        before_move: F,
+    ) -> Result<i64>
+    where
+        F: FnOnce(&Transaction) -> Result<()>,
+    {
+        let (start, end) = Self::year_bounds(year);
+        let tx = conn.transaction().map_err(|e| Error::Database(e.to_string()))?;
+
+        before_move(&tx)?;
+
+        let mut total = 0i64;
+        for t in tables {
+            tx.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {alias}.{table} AS SELECT * FROM main.{table} WHERE 0;",
+                alias = alias,
+                table = t.table
+            ))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+            let copied = tx
+                .execute(
+                    &format!(
+                        "INSERT INTO {}.{} SELECT * FROM main.{} WHERE {}",
+                        alias, t.table, t.table, t.filter
+                    ),
+                    [&start, &end],
+                )
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            let deleted = tx
+                .execute(&format!("DELETE FROM main.{} WHERE {}", t.table, t.filter), [&start, &end])
+                .map_err(|e| Error::Database(e.to_string()))?;
+
+            if copied != deleted {
+                return Err(Error::Database(format!(
+                    "Archivage incoherent pour {}: {} copies, {} supprimes",
+                    t.table, copied, deleted
+                )));
+            }
+
+            total += copied as i64;
+        }
+
+        tx.execute(
+            "INSERT INTO _fiscal_archives (fiscal_year, file_path, rows_archived, archived_by)
+             VALUES (?, ?, ?, ?)",
+            rusqlite::params![year, file_path, total, archived_by],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+        tx.commit().map_err(|e| Error::Database(e.to_string()))?;
+        Ok(total)
+    }
+
+    fn seal(path: &Path) -> Result<()> {
+        let mut permissions = std::fs::metadata(path)
+            .map_err(|e| Error::Database(e.to_string()))?
+            .permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(path, permissions).map_err(|e| Error::Database(e.to_string()))
+    }
+
+    fn detach_all(conn: &Connection, scope: &ArchiveScope) {
+        for alias in &scope.aliases {
+            if let Err(e) = conn.execute_batch(&format!("DETACH DATABASE {};", alias)) {
+                warn!("Failed to detach archive {}: {}", alias, e);
+            }
+        }
+    }
+
+    fn alias(year: i32) -> String {
+        format!("fy{}", year)
+    }
+
+    fn year_of(date: &str) -> Option<i32> {
+        date.get(0..4)?.parse().ok()
+    }
+
+    fn row_to_info(row: &rusqlite::Row) -> rusqlite::Result<FiscalArchiveInfo> {
+        Ok(FiscalArchiveInfo {
+            fiscal_year: row.get(0)?,
+            file_path: row.get(1)?,
+            rows_archived: row.get(2)?,
+            archived_at: row.get(3)?,
+            archived_by: row.get(4)?,
+        })
+    }
+}
+
+/// Archives attached for a read
+#[derive(Debug, Default)]
+pub struct ArchiveScope {
+    aliases: Vec<String>,
+}
+
+impl ArchiveScope {
+    /// Check if any archive is attached
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// SQL source for a table: `main.<table>` or a UNION ALL subquery over
+    /// the live table and its archived copies.
+    ///
+    /// Columns added to the live table after an archive was written are read
+    /// as NULL from that archive.
+    pub fn source(&self, conn: &Connection, table: &str) -> Result<String> {
+        if self.aliases.is_empty() {
+            return Ok(format!("main.{}", table));
+        }
+
+        let columns = Self::columns(conn, "main", table)?;
+        let mut selects = vec![format!("SELECT {} FROM main.{}", columns.join(", "), table)];
+
+        for alias in &self.aliases {
+            let archived = Self::columns(conn, alias, table)?;
+            if archived.is_empty() {
+                continue;
+            }
+
+            let projection: Vec<String> = columns
+                .iter()
+                .map(|c| {
+                    if archived.contains(c) {
+                        c.clone()
+                    } else {
+                        format!("NULL AS {}", c)
+                    }
+                })
+                .collect();
+
+            selects.push(format!("SELECT {} FROM {}.{}", projection.join(", "), alias, table));
+        }
+
+        Ok(format!("({})", selects.join(" UNION ALL ")))
+    }
+
+    fn columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::initialize_database;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO qr_scans (id, user_id, qr_data, scan_context, created_at) VALUES
+                ('s1', 'u', 'q', 'RECEPTION', '2023-03-01 10:00:00'),
+                ('s2', 'u', 'q', 'RECEPTION', '2023-11-20 10:00:00'),
+                ('s3', 'u', 'q', 'RECEPTION', '2024-02-01 10:00:00');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn scans() -> Vec<ArchivedTable> {
+        vec![ArchivedTable {
+            table: "qr_scans",
+            filter: "created_at >= ?1 AND created_at < ?2".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_archive_year_moves_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FiscalArchive::file_name(2023));
+        let mut conn = setup();
+
+        let mut opening_posted = false;
+        let rows = FiscalArchive::archive_year(&mut conn, 2023, &path, &scans(), "admin", |_| {
+            opening_posted = true;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(rows, 2);
+        assert!(opening_posted);
+        assert!(std::fs::metadata(&path).unwrap().permissions().readonly());
+
+        let live: i64 = conn
+            .query_row("SELECT COUNT(*) FROM qr_scans", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(live, 1);
+
+        let again = FiscalArchive::archive_year(&mut conn, 2023, &path, &scans(), "admin", |_| Ok(()));
+        assert!(again.is_err());
+    }
+
+    #[test]
+    fn test_range_reads_attach_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FiscalArchive::file_name(2023));
+        let mut conn = setup();
+        FiscalArchive::archive_year(&mut conn, 2023, &path, &scans(), "admin", |_| Ok(())).unwrap();
+
+        let count_in = |from: Option<&str>| -> i64 {
+            FiscalArchive::with_range(&conn, from, None, |scope| {
+                let source = scope.source(&conn, "qr_scans")?;
+                conn.query_row(&format!("SELECT COUNT(*) FROM {} s", source), [], |row| row.get(0))
+                    .map_err(|e| Error::Database(e.to_string()))
+            })
+            .unwrap()
+        };
+
+        assert_eq!(count_in(None), 1);
+        assert_eq!(count_in(Some("2024-01-01")), 1);
+        assert_eq!(count_in(Some("2023-06-01")), 3);
+
+        // Archives are detached after the read
+        let attached: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_database_list WHERE name NOT IN ('main', 'temp')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(attached, 0);
+    }
+}
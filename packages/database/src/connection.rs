@@ -6,6 +6,8 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use tracing::{debug, info};
 
+use crate::profiler::QueryProfiler;
+
 /// Database configuration
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
@@ -17,6 +19,8 @@ pub struct DatabaseConfig {
     pub foreign_keys: bool,
     /// Busy timeout in milliseconds
     pub busy_timeout_ms: u32,
+    /// Install the query profiler (slow-query log, statement stats)
+    pub profiling: bool,
 }
 
 impl Default for DatabaseConfig {
@@ -26,6 +30,7 @@ impl Default for DatabaseConfig {
             wal_mode: true,
             foreign_keys: true,
             busy_timeout_ms: 5000,
+            profiling: true,
         }
     }
 }
@@ -55,13 +60,17 @@ impl Database {
             | OpenFlags::SQLITE_OPEN_CREATE
             | OpenFlags::SQLITE_OPEN_FULL_MUTEX;
 
-        let conn = if config.path == ":memory:" {
+        let mut conn = if config.path == ":memory:" {
             Connection::open_in_memory()
         } else {
             Connection::open_with_flags(&config.path, flags)
         }
         .map_err(|e| Error::Database(e.to_string()))?;
 
+        if config.profiling {
+            QueryProfiler::install(&mut conn);
+        }
+
         let db = Self {
             config: config.clone(),
             connection: Arc::new(Mutex::new(conn)),
@@ -5,11 +5,20 @@
 //! - Schema migrations
 //! - Repository pattern implementation
 //! - Event log for sync
+//! - Scheduled maintenance jobs
+//! - Fiscal-year archive files
+//! - Query profiling and slow-query log
+//! - Gapless document numbering
 
+pub mod archive;
 pub mod connection;
+pub mod maintenance;
 pub mod migrations;
+pub mod profiler;
 pub mod repository;
 pub mod schema;
+pub mod sequences;
 
 pub use connection::{Database, DatabaseConfig};
 pub use repository::Repository;
+pub use sequences::{DocumentNumber, DocumentSequences, DocumentType, SequenceAudit};
@@ -0,0 +1,435 @@
+//! Database maintenance jobs
+//!
+//! Long-running factory PCs accumulate WAL pages, synced events and QR scans.
+//! These jobs keep the database bounded; each run is recorded in
+//! `_maintenance_runs` with its duration.
+
+use chrono::{Duration, Utc};
+use manchengo_core::{Error, Result};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Maintenance job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaintenanceJob {
+    /// Checkpoint and truncate the WAL file
+    WalCheckpoint,
+    /// Refresh query planner statistics
+    Analyze,
+    /// Release free pages back to the file system
+    IncrementalVacuum,
+    /// Delete synced events past the retention window (anchors kept)
+    PurgeEvents,
+    /// Delete QR scans past the retention window
+    PruneQrScans,
+}
+
+impl MaintenanceJob {
+    /// All jobs, in execution order
+    pub const ALL: [MaintenanceJob; 5] = [
+        Self::PurgeEvents,
+        Self::PruneQrScans,
+        Self::WalCheckpoint,
+        Self::IncrementalVacuum,
+        Self::Analyze,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WalCheckpoint => "WAL_CHECKPOINT",
+            Self::Analyze => "ANALYZE",
+            Self::IncrementalVacuum => "INCREMENTAL_VACUUM",
+            Self::PurgeEvents => "PURGE_EVENTS",
+            Self::PruneQrScans => "PRUNE_QR_SCANS",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|job| job.as_str() == s)
+    }
+
+    /// Minimum delay between two successful runs
+    pub fn default_interval(&self) -> Duration {
+        match self {
+            Self::WalCheckpoint => Duration::hours(1),
+            Self::Analyze => Duration::days(1),
+            Self::IncrementalVacuum => Duration::days(7),
+            Self::PurgeEvents => Duration::days(1),
+            Self::PruneQrScans => Duration::days(1),
+        }
+    }
+}
+
+/// Run outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaintenanceStatus {
+    Success,
+    Failed,
+    Skipped,
+}
+
+impl MaintenanceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "SUCCESS",
+            Self::Failed => "FAILED",
+            Self::Skipped => "SKIPPED",
+        }
+    }
+}
+
+/// Retention and sizing parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenancePolicy {
+    /// Synced events older than this are purged
+    pub event_retention_days: i64,
+    /// QR scans older than this are pruned
+    pub qr_scan_retention_days: i64,
+    /// Maximum pages released per incremental vacuum
+    pub vacuum_pages: i64,
+}
+
+impl Default for MaintenancePolicy {
+    fn default() -> Self {
+        Self {
+            event_retention_days: 90,
+            qr_scan_retention_days: 180,
+            vacuum_pages: 2000,
+        }
+    }
+}
+
+/// Recorded maintenance run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRun {
+    pub job: MaintenanceJob,
+    pub status: MaintenanceStatus,
+    pub started_at: String,
+    pub duration_ms: i64,
+    pub rows_affected: i64,
+    pub message: Option<String>,
+}
+
+/// Maintenance runner
+pub struct Maintenance;
+
+impl Maintenance {
+    /// Run a job and record it in `_maintenance_runs`
+    ///
+    /// The incremental vacuum is skipped on a database not converted yet: the
+    /// conversion is a full VACUUM, left to [`Self::enable_incremental_vacuum`].
+    pub fn run(conn: &Connection, job: MaintenanceJob, policy: &MaintenancePolicy) -> Result<MaintenanceRun> {
+        if job == MaintenanceJob::IncrementalVacuum && !Self::incremental_vacuum_enabled(conn)? {
+            warn!("Incremental vacuum skipped: database not converted to incremental auto-vacuum");
+            return Self::skip(conn, job, "Auto-vacuum incremental non active");
+        }
+
+        let started_at = Utc::now().to_rfc3339();
+        let started = Instant::now();
+
+        let outcome = match job {
+            MaintenanceJob::WalCheckpoint => Self::wal_checkpoint(conn),
+            MaintenanceJob::Analyze => Self::analyze(conn),
+            MaintenanceJob::IncrementalVacuum => Self::incremental_vacuum(conn, policy.vacuum_pages),
+            MaintenanceJob::PurgeEvents => Self::purge_events(conn, policy.event_retention_days),
+            MaintenanceJob::PruneQrScans => Self::prune_qr_scans(conn, policy.qr_scan_retention_days),
+        };
+
+        let run = match outcome {
+            Ok(rows_affected) => MaintenanceRun {
+                job,
+                status: MaintenanceStatus::Success,
+                started_at,
+                duration_ms: started.elapsed().as_millis() as i64,
+                rows_affected,
+                message: None,
+            },
+            Err(e) => {
+                warn!("Maintenance job {} failed: {}", job.as_str(), e);
+                MaintenanceRun {
+                    job,
+                    status: MaintenanceStatus::Failed,
+                    started_at,
+                    duration_ms: started.elapsed().as_millis() as i64,
+                    rows_affected: 0,
+                    message: Some(e.to_string()),
+                }
+            }
+        };
+
+        Self::record(conn, &run)?;
+        info!(
+            "Maintenance {} {} in {} ms ({} rows)",
+            job.as_str(),
+            run.status.as_str(),
+            run.duration_ms,
+            run.rows_affected
+        );
+        Ok(run)
+    }
+
+    /// Record a skipped run (database busy)
+    pub fn skip(conn: &Connection, job: MaintenanceJob, reason: &str) -> Result<MaintenanceRun> {
+        let run = MaintenanceRun {
+            job,
+            status: MaintenanceStatus::Skipped,
+            started_at: Utc::now().to_rfc3339(),
+            duration_ms: 0,
+            rows_affected: 0,
+            message: Some(reason.to_string()),
+        };
+        Self::record(conn, &run)?;
+        Ok(run)
+    }
+
+    /// Whether free pages can be released without a full VACUUM
+    pub fn incremental_vacuum_enabled(conn: &Connection) -> Result<bool> {
+        conn.query_row("PRAGMA auto_vacuum", [], |row| row.get::<_, i64>(0))
+            .map(|mode| mode == 2)
+            .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    /// Convert the database to incremental auto-vacuum (full VACUUM, once)
+    pub fn enable_incremental_vacuum(conn: &Connection) -> Result<()> {
+        if Self::incremental_vacuum_enabled(conn)? {
+            return Ok(());
+        }
+        info!("Converting database to incremental auto-vacuum");
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")
+            .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    /// Jobs whose last successful run is older than their interval
+    pub fn due_jobs(conn: &Connection) -> Result<Vec<MaintenanceJob>> {
+        let now = Utc::now();
+        let mut due = Vec::new();
+
+        for job in MaintenanceJob::ALL {
+            let last = Self::last_success(conn, job)?;
+            let is_due = match last.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok()) {
+                Some(last) => now - last.with_timezone(&Utc) >= job.default_interval(),
+                None => true,
+            };
+
+            if is_due {
+                due.push(job);
+            }
+        }
+
+        Ok(due)
+    }
+
+    /// Last successful run of a job
+    pub fn last_success(conn: &Connection, job: MaintenanceJob) -> Result<Option<String>> {
+        conn.query_row(
+            "SELECT started_at FROM _maintenance_runs
+             WHERE job = ? AND status = 'SUCCESS' ORDER BY id DESC LIMIT 1",
+            [job.as_str()],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    /// Most recent runs, newest first
+    pub fn history(conn: &Connection, limit: i64) -> Result<Vec<MaintenanceRun>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT job, status, started_at, duration_ms, rows_affected, message
+                 FROM _maintenance_runs ORDER BY id DESC LIMIT ?",
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([limit], |row| {
+                let job: String = row.get(0)?;
+                let status: String = row.get(1)?;
+                Ok(MaintenanceRun {
+                    job: MaintenanceJob::parse(&job).unwrap_or(MaintenanceJob::Analyze),
+                    status: match status.as_str() {
+                        "SUCCESS" => MaintenanceStatus::Success,
+                        "SKIPPED" => MaintenanceStatus::Skipped,
+                        _ => MaintenanceStatus::Failed,
+                    },
+                    started_at: row.get(2)?,
+                    duration_ms: row.get(3)?,
+                    rows_affected: row.get(4)?,
+                    message: row.get(5)?,
+                })
+            })
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+        }
+        Ok(result)
+    }
+
+    fn record(conn: &Connection, run: &MaintenanceRun) -> Result<()> {
+        conn.execute(
+            "INSERT INTO _maintenance_runs (job, status, started_at, duration_ms, rows_affected, message)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                run.job.as_str(),
+                run.status.as_str(),
+                run.started_at,
+                run.duration_ms,
+                run.rows_affected,
+                run.message,
+            ],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // JOBS
+    // =========================================================================
+
+    /// Returns the number of WAL frames checkpointed
+    fn wal_checkpoint(conn: &Connection) -> Result<i64> {
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get::<_, i64>(2))
+            .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    fn analyze(conn: &Connection) -> Result<i64> {
+        conn.execute_batch("ANALYZE;")
+            .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(0)
+    }
+
+    /// Returns the number of pages released (database already converted)
+    fn incremental_vacuum(conn: &Connection, pages: i64) -> Result<i64> {
+        let pragma = |sql: &str| -> Result<i64> {
+            conn.query_row(sql, [], |row| row.get(0))
+                .map_err(|e| Error::Database(e.to_string()))
+        };
+
+        let before = pragma("PRAGMA freelist_count")?;
+        conn.execute_batch(&format!("PRAGMA incremental_vacuum({});", pages.max(1)))
+            .map_err(|e| Error::Database(e.to_string()))?;
+        let after = pragma("PRAGMA freelist_count")?;
+
+        Ok(before - after)
+    }
+
+    /// Delete synced events past retention.
+    ///
+    /// The latest event of each aggregate is kept as an anchor: versions are
+    /// computed from MAX(version), purging it would restart the sequence.
+    fn purge_events(conn: &Connection, retention_days: i64) -> Result<i64> {
+        let cutoff = (Utc::now() - Duration::days(retention_days)).to_rfc3339();
+
+        let deleted = conn
+            .execute(
+                "DELETE FROM _events
+                 WHERE synced = 1
+                   AND occurred_at < ?1
+                   AND id NOT IN (SELECT event_id FROM _sync_queue)
+                   AND version < (
+                       SELECT MAX(e2.version) FROM _events e2
+                       WHERE e2.aggregate_type = _events.aggregate_type
+                         AND e2.aggregate_id = _events.aggregate_id
+                   )",
+                [&cutoff],
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        Ok(deleted as i64)
+    }
+
+    fn prune_qr_scans(conn: &Connection, retention_days: i64) -> Result<i64> {
+        let deleted = conn
+            .execute(
+                "DELETE FROM qr_scans WHERE created_at < datetime('now', ?)",
+                [format!("-{} days", retention_days)],
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        Ok(deleted as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::initialize_database;
+
+    fn insert_event(conn: &Connection, aggregate_id: &str, version: i64, days_ago: i64, synced: bool) {
+        conn.execute(
+            "INSERT INTO _events (id, aggregate_type, aggregate_id, event_type, payload,
+                                  occurred_at, user_id, device_id, version, synced)
+             VALUES (?, 'LotMp', ?, 'LotMpCreated', '{}', ?, 'u', 'd', ?, ?)",
+            rusqlite::params![
+                uuid::Uuid::new_v4().to_string(),
+                aggregate_id,
+                (Utc::now() - Duration::days(days_ago)).to_rfc3339(),
+                version,
+                synced as i32,
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_purge_keeps_anchor_and_unsynced() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+
+        insert_event(&conn, "a", 1, 200, true);
+        insert_event(&conn, "a", 2, 150, true);
+        insert_event(&conn, "a", 3, 120, true); // anchor
+        insert_event(&conn, "b", 1, 200, false); // not synced
+        insert_event(&conn, "b", 2, 1, true); // recent
+
+        let run = Maintenance::run(&conn, MaintenanceJob::PurgeEvents, &MaintenancePolicy::default()).unwrap();
+        assert_eq!(run.status, MaintenanceStatus::Success);
+        assert_eq!(run.rows_affected, 2);
+
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM _events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 3);
+    }
+
+    #[test]
+    fn test_runs_are_recorded_and_due() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+
+        assert_eq!(Maintenance::due_jobs(&conn).unwrap().len(), MaintenanceJob::ALL.len());
+
+        Maintenance::run(&conn, MaintenanceJob::Analyze, &MaintenancePolicy::default()).unwrap();
+        Maintenance::skip(&conn, MaintenanceJob::PruneQrScans, "sync en cours").unwrap();
+
+        let due = Maintenance::due_jobs(&conn).unwrap();
+        assert!(!due.contains(&MaintenanceJob::Analyze));
+        assert!(due.contains(&MaintenanceJob::PruneQrScans));
+
+        let history = Maintenance::history(&conn, 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, MaintenanceStatus::Skipped);
+        assert!(Maintenance::last_success(&conn, MaintenanceJob::Analyze).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_vacuum_waits_for_conversion() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(dir.path().join("vacuum.db")).unwrap();
+        initialize_database(&conn).unwrap();
+        let policy = MaintenancePolicy::default();
+
+        let run = Maintenance::run(&conn, MaintenanceJob::IncrementalVacuum, &policy).unwrap();
+        assert_eq!(run.status, MaintenanceStatus::Skipped);
+        assert!(!Maintenance::incremental_vacuum_enabled(&conn).unwrap());
+
+        Maintenance::enable_incremental_vacuum(&conn).unwrap();
+        let run = Maintenance::run(&conn, MaintenanceJob::IncrementalVacuum, &policy).unwrap();
+        assert_eq!(run.status, MaintenanceStatus::Success);
+    }
+}
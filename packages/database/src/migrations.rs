@@ -32,6 +32,123 @@ pub const MIGRATIONS: &[Migration] = &[
         up: include_str!("../migrations/003_sample_products.sql"),
         down: "DELETE FROM products_mp WHERE created_by = 'system'; DELETE FROM products_pf WHERE created_by = 'system';",
     },
+    Migration {
+        version: 5,
+        name: "soft_deletes",
+        up: include_str!("../migrations/005_soft_deletes.sql"),
+        down: "-- Rollback not supported (SQLite cannot drop columns reliably)",
+    },
+    Migration {
+        version: 6,
+        name: "maintenance_runs",
+        up: include_str!("../migrations/006_maintenance_runs.sql"),
+        down: "DROP TABLE IF EXISTS _maintenance_runs;",
+    },
+    Migration {
+        version: 7,
+        name: "fiscal_archives",
+        up: include_str!("../migrations/007_fiscal_archives.sql"),
+        down: "DROP TABLE IF EXISTS _fiscal_archives;",
+    },
+    Migration {
+        version: 8,
+        name: "product_packs",
+        up: include_str!("../migrations/008_product_packs.sql"),
+        down: "DROP TABLE IF EXISTS product_packs;",
+    },
+    Migration {
+        version: 9,
+        name: "fixed_point_quantities",
+        up: include_str!("../migrations/009_fixed_point_quantities.sql"),
+        down: include_str!("../migrations/009_fixed_point_quantities_down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "fiscal_rule_sets",
+        up: include_str!("../migrations/010_fiscal_rule_sets.sql"),
+        down: "DROP TABLE IF EXISTS fiscal_rule_sets;",
+    },
+    Migration {
+        version: 11,
+        name: "product_gtin",
+        up: include_str!("../migrations/011_product_gtin.sql"),
+        down: "DROP INDEX IF EXISTS idx_products_pf_gtin;
+               DROP INDEX IF EXISTS idx_products_pf_carton_gtin;
+               ALTER TABLE products_pf DROP COLUMN gtin;
+               ALTER TABLE products_pf DROP COLUMN carton_gtin;",
+    },
+    Migration {
+        version: 12,
+        name: "document_sequences",
+        up: include_str!("../migrations/012_document_sequences.sql"),
+        down: "DROP TABLE IF EXISTS document_numbers;
+               DROP TABLE IF EXISTS document_sequences;",
+    },
+    Migration {
+        version: 13,
+        name: "multi_currency",
+        up: include_str!("../migrations/013_multi_currency.sql"),
+        down: "DROP INDEX IF EXISTS idx_lots_mp_supplier;
+               ALTER TABLE lots_mp DROP COLUMN currency;
+               ALTER TABLE lots_mp DROP COLUMN purchase_unit_cost;
+               ALTER TABLE lots_mp DROP COLUMN exchange_rate;
+               ALTER TABLE lots_mp DROP COLUMN exchange_rate_date;
+               DROP TABLE IF EXISTS exchange_rates;",
+    },
+    Migration {
+        version: 14,
+        name: "warehouse_locations",
+        up: include_str!("../migrations/014_warehouse_locations.sql"),
+        down: "-- Rollback not supported (columns referenced by indexes and foreign keys)",
+    },
+    Migration {
+        version: 15,
+        name: "transfer_orders",
+        up: include_str!("../migrations/015_transfer_orders.sql"),
+        down: "DROP TABLE IF EXISTS transfer_order_lines; DROP TABLE IF EXISTS transfer_orders;",
+    },
+    Migration {
+        version: 16,
+        name: "lot_selection_strategies",
+        up: include_str!("../migrations/016_lot_selection_strategies.sql"),
+        down: "DROP TABLE IF EXISTS product_lot_strategies;",
+    },
+    Migration {
+        version: 17,
+        name: "lot_reservations",
+        up: include_str!("../migrations/017_lot_reservations.sql"),
+        down: "DROP TABLE IF EXISTS lot_reservations;",
+    },
+    Migration {
+        version: 18,
+        name: "inventory_sessions",
+        up: include_str!("../migrations/018_inventory_sessions.sql"),
+        down: "DROP TABLE IF EXISTS inventory_lines; DROP TABLE IF EXISTS inventory_sessions;",
+    },
+    Migration {
+        version: 19,
+        name: "stock_snapshots",
+        up: include_str!("../migrations/019_stock_snapshots.sql"),
+        down: "DROP TABLE IF EXISTS stock_snapshot_lines; DROP TABLE IF EXISTS stock_snapshots;",
+    },
+    Migration {
+        version: 20,
+        name: "movement_recorded_at",
+        up: include_str!("../migrations/020_movement_recorded_at.sql"),
+        down: "ALTER TABLE stock_movements DROP COLUMN recorded_at;",
+    },
+    Migration {
+        version: 21,
+        name: "purchase_order_soft_delete",
+        up: include_str!("../migrations/021_purchase_order_soft_delete.sql"),
+        down: "DROP INDEX IF EXISTS idx_purchase_orders_deleted; ALTER TABLE purchase_orders DROP COLUMN deleted_by; ALTER TABLE purchase_orders DROP COLUMN deleted_at;",
+    },
+    Migration {
+        version: 22,
+        name: "client_min_shelf_life",
+        up: include_str!("../migrations/022_client_min_shelf_life.sql"),
+        down: "ALTER TABLE clients DROP COLUMN min_shelf_life_days;",
+    },
 ];
 
 /// Migration manager
@@ -162,4 +279,126 @@ mod tests {
 
         assert_eq!(migrator.current_version().unwrap(), 0);
     }
+
+    #[test]
+    fn test_migrate_adds_soft_delete_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+
+        let migrator = Migrator::new(&conn);
+        assert!(!migrator.has_pending().unwrap());
+
+        for (_, table) in crate::schema::soft_delete::TABLES {
+            conn.execute(
+                &format!("UPDATE {} SET deleted_at = datetime('now'), deleted_by = 'u' WHERE 0", table),
+                [],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_quantities_stored_as_integer_thousandths() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        let migrator = Migrator::new(&conn);
+        migrator.ensure_migrations_table().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version < 9) {
+            migrator.apply_migration(migration).unwrap();
+        }
+
+        conn.execute_batch(
+            "INSERT INTO recipes (id, code, name, product_pf_id, output_quantity, output_unit, created_by, updated_by)
+             VALUES ('r1', 'R1', 'Recette', 'pf', 12.345, 'KG', 'u', 'u');",
+        )
+        .unwrap();
+        migrator.migrate().unwrap();
+
+        let (value, kind): (i64, String) = conn
+            .query_row(
+                "SELECT output_quantity, typeof(output_quantity) FROM recipes WHERE id = 'r1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(value, 12345);
+        assert_eq!(kind, "integer");
+    }
+
+    #[test]
+    fn test_backdated_movements_keep_entry_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        let migrator = Migrator::new(&conn);
+        migrator.ensure_migrations_table().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version < 20) {
+            migrator.apply_migration(migration).unwrap();
+        }
+
+        conn.execute_batch(
+            "INSERT INTO stock_movements (id, product_type, product_id, lot_id, movement_type, quantity, unit,
+                                          quantity_before, quantity_after, created_at, created_by)
+             VALUES ('m1', 'MP', 'p', 'l', 'IN', 1000, 'KG', 0, 1000, '2024-03-14 09:30:00', 'u');",
+        )
+        .unwrap();
+        migrator.migrate().unwrap();
+
+        // Existing movements were keyed in at their own date
+        let recorded_at: String = conn
+            .query_row("SELECT recorded_at FROM stock_movements WHERE id = 'm1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(recorded_at, "2024-03-14 09:30:00");
+
+        // A backdated entry keeps its business date and the real entry time
+        conn.execute_batch(
+            "INSERT INTO stock_movements (id, product_type, product_id, lot_id, movement_type, quantity, unit,
+                                          quantity_before, quantity_after, created_at, recorded_at, created_by)
+             VALUES ('m2', 'MP', 'p', 'l', 'IN', 1000, 'KG', 1000, 2000, '2024-03-14 10:00:00', datetime('now'), 'u');",
+        )
+        .unwrap();
+        let later: bool = conn
+            .query_row("SELECT recorded_at > created_at FROM stock_movements WHERE id = 'm2'", [], |row| row.get(0))
+            .unwrap();
+        assert!(later);
+    }
+
+    #[test]
+    fn test_existing_lots_moved_to_default_warehouse() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        let migrator = Migrator::new(&conn);
+        migrator.ensure_migrations_table().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version < 14) {
+            migrator.apply_migration(migration).unwrap();
+        }
+
+        conn.execute_batch(
+            "INSERT INTO lots_mp (id, lot_number, product_id, quantity_initial, quantity_remaining, unit,
+                                  reception_date, qr_code, created_by, updated_by)
+             VALUES ('l1', 'LOT-1', 'mp', 1000, 1000, 'KG', '2024-01-15', 'qr', 'u', 'u');",
+        )
+        .unwrap();
+        migrator.migrate().unwrap();
+
+        let (warehouse, put_away): (String, Option<String>) = conn
+            .query_row("SELECT warehouse_id, put_away_at FROM lots_mp WHERE id = 'l1'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        let default: String = conn
+            .query_row("SELECT id FROM warehouses WHERE is_default = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(default, "00000000-0000-4000-8000-000000000001");
+        assert_eq!(warehouse, default);
+        assert!(put_away.is_some());
+
+        let docks: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM warehouse_locations WHERE warehouse_id = ? AND location_type = 'RECEPTION'",
+                [&default],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(docks, 1);
+    }
 }
@@ -0,0 +1,401 @@
+//! SQL profiling and slow-query log
+//!
+//! Installed on every connection through rusqlite's `profile` hook. The hook
+//! only accepts a plain function, so statistics live in a process-wide
+//! registry shared by all connections:
+//! - Per-statement aggregates (count, p50, p95, max) on normalised SQL
+//! - Queries slower than the threshold are logged and kept in a short list
+//! - Optional `EXPLAIN QUERY PLAN` of slow SELECTs, run on demand
+
+use manchengo_core::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::warn;
+
+/// Samples kept per statement for percentiles
+const SAMPLES_PER_STATEMENT: usize = 256;
+/// Distinct statements tracked
+const MAX_STATEMENTS: usize = 500;
+/// Slow queries kept
+const MAX_SLOW_QUERIES: usize = 50;
+
+/// Profiler settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilerConfig {
+    /// Queries at or above this duration are logged as slow
+    pub slow_threshold_ms: u64,
+    /// Run EXPLAIN QUERY PLAN on slow SELECTs
+    pub explain_slow_queries: bool,
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> Self {
+        Self {
+            slow_threshold_ms: 200,
+            explain_slow_queries: false,
+        }
+    }
+}
+
+/// Aggregates for one normalised statement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub sql: String,
+    pub params: usize,
+    pub count: u64,
+    pub total_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Slow query occurrence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQuery {
+    pub sql: String,
+    pub params: usize,
+    pub duration_ms: f64,
+    pub recorded_at: String,
+    pub query_plan: Option<Vec<String>>,
+    #[serde(skip)]
+    raw_sql: String,
+}
+
+#[derive(Default)]
+struct StatementEntry {
+    params: usize,
+    count: u64,
+    total: Duration,
+    max: Duration,
+    samples: VecDeque<Duration>,
+}
+
+#[derive(Default)]
+struct Registry {
+    config: ProfilerConfig,
+    statements: HashMap<String, StatementEntry>,
+    slow: VecDeque<SlowQuery>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+/// Process-wide query profiler
+pub struct QueryProfiler;
+
+impl QueryProfiler {
+    /// Set the profiler settings (applies to all connections)
+    pub fn configure(config: ProfilerConfig) {
+        if let Ok(mut registry) = registry().lock() {
+            registry.config = config;
+        }
+    }
+
+    /// Current settings
+    pub fn config() -> ProfilerConfig {
+        registry().lock().map(|r| r.config.clone()).unwrap_or_default()
+    }
+
+    /// Install the profiling hook on a connection
+    pub fn install(conn: &mut Connection) {
+        conn.profile(Some(Self::record));
+    }
+
+    /// Profile callback
+    fn record(sql: &str, duration: Duration) {
+        // Do not profile the profiler's own EXPLAIN queries
+        if sql.trim_start().to_uppercase().starts_with("EXPLAIN") {
+            return;
+        }
+
+        let Ok(mut registry) = registry().lock() else {
+            return;
+        };
+
+        let normalized = normalize_sql(sql);
+        let params = count_params(sql);
+
+        if registry.statements.len() < MAX_STATEMENTS || registry.statements.contains_key(&normalized) {
+            let entry = registry.statements.entry(normalized.clone()).or_default();
+            entry.params = params;
+            entry.count += 1;
+            entry.total += duration;
+            entry.max = entry.max.max(duration);
+            if entry.samples.len() == SAMPLES_PER_STATEMENT {
+                entry.samples.pop_front();
+            }
+            entry.samples.push_back(duration);
+        }
+
+        if duration >= Duration::from_millis(registry.config.slow_threshold_ms) {
+            let duration_ms = as_ms(duration);
+            warn!("Slow query ({:.1} ms, {} params): {}", duration_ms, params, normalized);
+
+            if registry.slow.len() == MAX_SLOW_QUERIES {
+                registry.slow.pop_front();
+            }
+            registry.slow.push_back(SlowQuery {
+                sql: normalized,
+                params,
+                duration_ms,
+                recorded_at: chrono::Utc::now().to_rfc3339(),
+                query_plan: None,
+                raw_sql: sql.to_string(),
+            });
+        }
+    }
+
+    /// Per-statement aggregates, slowest p95 first
+    pub fn stats(limit: usize) -> Vec<QueryStats> {
+        let Ok(registry) = registry().lock() else {
+            return Vec::new();
+        };
+
+        let mut stats: Vec<QueryStats> = registry
+            .statements
+            .iter()
+            .map(|(sql, entry)| {
+                let mut samples: Vec<Duration> = entry.samples.iter().copied().collect();
+                samples.sort();
+                QueryStats {
+                    sql: sql.clone(),
+                    params: entry.params,
+                    count: entry.count,
+                    total_ms: as_ms(entry.total),
+                    p50_ms: percentile(&samples, 0.50),
+                    p95_ms: percentile(&samples, 0.95),
+                    max_ms: as_ms(entry.max),
+                }
+            })
+            .collect();
+
+        stats.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+        stats.truncate(limit);
+        stats
+    }
+
+    /// Recent slow queries, newest first
+    pub fn slow_queries() -> Vec<SlowQuery> {
+        registry()
+            .lock()
+            .map(|r| r.slow.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Run EXPLAIN QUERY PLAN on slow SELECTs not explained yet.
+    ///
+    /// Must be called outside the profile hook (the hook cannot query).
+    pub fn explain_slow_queries(conn: &Connection) -> Result<usize> {
+        let pending: Vec<(usize, String)> = match registry().lock() {
+            Ok(r) if r.config.explain_slow_queries => r
+                .slow
+                .iter()
+                .enumerate()
+                .filter(|(_, q)| q.query_plan.is_none() && is_select(&q.raw_sql))
+                .map(|(i, q)| (i, q.raw_sql.clone()))
+                .collect(),
+            _ => return Ok(0),
+        };
+
+        let mut plans = Vec::new();
+        for (index, sql) in pending {
+            // Parameters are bound to NULL, which is enough for the plan
+            let plan = conn
+                .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+                .and_then(|mut stmt| {
+                    let nulls = vec![rusqlite::types::Null; stmt.parameter_count()];
+                    stmt.query_map(rusqlite::params_from_iter(nulls), |row| {
+                        row.get::<_, String>(3)
+                    })?
+                    .collect::<rusqlite::Result<Vec<String>>>()
+                });
+
+            match plan {
+                Ok(plan) => plans.push((index, sql, plan)),
+                Err(e) => warn!("EXPLAIN QUERY PLAN failed: {}", e),
+            }
+        }
+
+        let mut registry = registry().lock().map_err(|e| manchengo_core::Error::Database(e.to_string()))?;
+        let mut explained = 0;
+        for (index, sql, plan) in plans {
+            // The list may have rotated meanwhile
+            if let Some(query) = registry.slow.get_mut(index).filter(|q| q.raw_sql == sql) {
+                query.query_plan = Some(plan);
+                explained += 1;
+            }
+        }
+
+        Ok(explained)
+    }
+
+    /// Clear all statistics
+    pub fn reset() {
+        if let Ok(mut registry) = registry().lock() {
+            registry.statements.clear();
+            registry.slow.clear();
+        }
+    }
+}
+
+/// Collapse whitespace and replace literals by `?`
+pub fn normalize_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut last_space = true;
+
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // String literal ('' escapes a quote)
+            while let Some(n) = chars.next() {
+                if n == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            out.push('?');
+            last_space = false;
+        } else if c.is_ascii_digit() && !out.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '?') {
+            while chars.peek().is_some_and(|n| n.is_ascii_digit() || *n == '.') {
+                chars.next();
+            }
+            out.push('?');
+            last_space = false;
+        } else if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+                last_space = true;
+            }
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Number of bound parameters (`?`, `?NNN`, `:name`, `@name`, `$name`)
+pub fn count_params(sql: &str) -> usize {
+    let mut anonymous = 0;
+    let mut max_numbered = 0;
+    let mut named = std::collections::HashSet::new();
+    let mut in_string = false;
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            in_string = !in_string;
+            continue;
+        }
+        if in_string {
+            continue;
+        }
+
+        match c {
+            '?' => {
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                match digits.parse::<usize>() {
+                    Ok(n) => max_numbered = max_numbered.max(n),
+                    Err(_) => anonymous += 1,
+                }
+            }
+            ':' | '@' | '$' if chars.peek().is_some_and(|n| n.is_alphabetic() || *n == '_') => {
+                let mut name = String::new();
+                while let Some(n) = chars.peek().filter(|n| n.is_alphanumeric() || **n == '_') {
+                    name.push(*n);
+                    chars.next();
+                }
+                named.insert(name);
+            }
+            _ => {}
+        }
+    }
+
+    anonymous.max(max_numbered) + named.len()
+}
+
+fn is_select(sql: &str) -> bool {
+    let head = sql.trim_start().to_uppercase();
+    head.starts_with("SELECT") || head.starts_with("WITH")
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((sorted.len() as f64) * p).ceil() as usize;
+    as_ms(sorted[rank.saturating_sub(1).min(sorted.len() - 1)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_sql() {
+        assert_eq!(
+            normalize_sql("SELECT *\n   FROM lots_mp  WHERE status = 'AVAILABLE' AND qty > 10.5 LIMIT 100"),
+            "SELECT * FROM lots_mp WHERE status = ? AND qty > ? LIMIT ?"
+        );
+        assert_eq!(normalize_sql("SELECT col1 FROM t2 WHERE a = ?1"), "SELECT col1 FROM t2 WHERE a = ?1");
+        assert_eq!(normalize_sql("SELECT 'it''s'"), "SELECT ?");
+    }
+
+    #[test]
+    fn test_count_params() {
+        assert_eq!(count_params("SELECT * FROM t WHERE a = ? AND b = ?"), 2);
+        assert_eq!(count_params("INSERT INTO t VALUES (?1, ?2, ?1, ?3)"), 3);
+        assert_eq!(count_params("SELECT ':not' FROM t WHERE a = :a AND b = :a"), 1);
+    }
+
+    #[test]
+    fn test_profile_records_slow_queries() {
+        QueryProfiler::configure(ProfilerConfig {
+            slow_threshold_ms: 0,
+            explain_slow_queries: true,
+        });
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        QueryProfiler::install(&mut conn);
+        conn.execute_batch("CREATE TABLE profiler_probe (id INTEGER PRIMARY KEY, name TEXT);")
+            .unwrap();
+        for _ in 0..3 {
+            conn.query_row("SELECT COUNT(*) FROM profiler_probe WHERE name = ?", ["x"], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+        }
+
+        let stats = QueryProfiler::stats(MAX_STATEMENTS);
+        let probe = stats
+            .iter()
+            .find(|s| s.sql == "SELECT COUNT(*) FROM profiler_probe WHERE name = ?")
+            .unwrap();
+        assert!(probe.count >= 3);
+        assert_eq!(probe.params, 1);
+        assert!(probe.p50_ms <= probe.p95_ms);
+
+        QueryProfiler::explain_slow_queries(&conn).unwrap();
+        let slow = QueryProfiler::slow_queries();
+        assert!(slow
+            .iter()
+            .any(|q| q.sql.contains("profiler_probe") && q.query_plan.is_some()));
+    }
+}
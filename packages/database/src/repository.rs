@@ -4,6 +4,8 @@ use manchengo_core::{EntityId, Error, Result};
 use rusqlite::{params, Connection, Row};
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::schema::soft_delete::NOT_DELETED;
+
 /// Generic repository trait for CRUD operations
 pub trait Repository<T> {
     /// Table name for this entity
@@ -20,12 +22,19 @@ pub trait Repository<T> {
 }
 
 /// Repository operations helper
+///
+/// Entities are soft-deleted (`deleted_at`/`deleted_by`); every read is
+/// scoped to live rows unless it explicitly targets the trash.
 pub struct RepositoryOps;
 
 impl RepositoryOps {
     /// Find entity by ID
     pub fn find_by_id<T: Repository<T>>(conn: &Connection, id: &EntityId) -> Result<Option<T>> {
-        let sql = format!("SELECT * FROM {} WHERE id = ?1", T::table_name());
+        let sql = format!(
+            "SELECT * FROM {} WHERE id = ?1 AND {}",
+            T::table_name(),
+            NOT_DELETED
+        );
 
         let result = conn.query_row(&sql, [id.to_string()], T::from_row);
 
@@ -38,20 +47,7 @@ impl RepositoryOps {
 
     /// Find all entities
     pub fn find_all<T: Repository<T>>(conn: &Connection) -> Result<Vec<T>> {
-        let sql = format!("SELECT * FROM {}", T::table_name());
-
-        let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
-
-        let rows = stmt
-            .query_map([], T::from_row)
-            .map_err(|e| Error::Database(e.to_string()))?;
-
-        let mut result = Vec::new();
-        for row in rows {
-            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
-        }
-
-        Ok(result)
+        Self::find_where(conn, "1=1", &[])
     }
 
     /// Find entities with WHERE clause
@@ -60,36 +56,81 @@ impl RepositoryOps {
         where_clause: &str,
         params: &[&dyn rusqlite::ToSql],
     ) -> Result<Vec<T>> {
-        let sql = format!("SELECT * FROM {} WHERE {}", T::table_name(), where_clause);
-
-        let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} AND ({})",
+            T::table_name(),
+            NOT_DELETED,
+            where_clause
+        );
+
+        Self::query_all(conn, &sql, params)
+    }
 
-        let rows = stmt
-            .query_map(rusqlite::params_from_iter(params), T::from_row)
-            .map_err(|e| Error::Database(e.to_string()))?;
+    /// Find soft-deleted entities (trash)
+    pub fn find_deleted<T: Repository<T>>(conn: &Connection) -> Result<Vec<T>> {
+        let sql = format!(
+            "SELECT * FROM {} WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            T::table_name()
+        );
 
-        let mut result = Vec::new();
-        for row in rows {
-            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
-        }
-
-        Ok(result)
+        Self::query_all(conn, &sql, &[])
     }
 
     /// Count entities with optional WHERE clause
     pub fn count<T: Repository<T>>(conn: &Connection, where_clause: Option<&str>) -> Result<i64> {
         let sql = match where_clause {
-            Some(w) => format!("SELECT COUNT(*) FROM {} WHERE {}", T::table_name(), w),
-            None => format!("SELECT COUNT(*) FROM {}", T::table_name()),
+            Some(w) => format!(
+                "SELECT COUNT(*) FROM {} WHERE {} AND ({})",
+                T::table_name(),
+                NOT_DELETED,
+                w
+            ),
+            None => format!("SELECT COUNT(*) FROM {} WHERE {}", T::table_name(), NOT_DELETED),
         };
 
         conn.query_row(&sql, [], |row| row.get(0))
             .map_err(|e| Error::Database(e.to_string()))
     }
 
-    /// Delete entity by ID
-    pub fn delete<T: Repository<T>>(conn: &Connection, id: &EntityId) -> Result<bool> {
-        let sql = format!("DELETE FROM {} WHERE id = ?1", T::table_name());
+    /// Soft delete entity by ID
+    pub fn delete<T: Repository<T>>(
+        conn: &Connection,
+        id: &EntityId,
+        deleted_by: &EntityId,
+    ) -> Result<bool> {
+        let sql = format!(
+            "UPDATE {} SET deleted_at = datetime('now'), deleted_by = ?2 WHERE id = ?1 AND {}",
+            T::table_name(),
+            NOT_DELETED
+        );
+
+        let affected = conn
+            .execute(&sql, [id.to_string(), deleted_by.to_string()])
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        Ok(affected > 0)
+    }
+
+    /// Restore a soft-deleted entity
+    pub fn restore<T: Repository<T>>(conn: &Connection, id: &EntityId) -> Result<bool> {
+        let sql = format!(
+            "UPDATE {} SET deleted_at = NULL, deleted_by = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+            T::table_name()
+        );
+
+        let affected = conn
+            .execute(&sql, [id.to_string()])
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        Ok(affected > 0)
+    }
+
+    /// Permanently remove an entity that is already in the trash
+    pub fn purge<T: Repository<T>>(conn: &Connection, id: &EntityId) -> Result<bool> {
+        let sql = format!(
+            "DELETE FROM {} WHERE id = ?1 AND deleted_at IS NOT NULL",
+            T::table_name()
+        );
 
         let affected = conn
             .execute(&sql, [id.to_string()])
@@ -100,7 +141,11 @@ impl RepositoryOps {
 
     /// Check if entity exists
     pub fn exists<T: Repository<T>>(conn: &Connection, id: &EntityId) -> Result<bool> {
-        let sql = format!("SELECT 1 FROM {} WHERE id = ?1 LIMIT 1", T::table_name());
+        let sql = format!(
+            "SELECT 1 FROM {} WHERE id = ?1 AND {} LIMIT 1",
+            T::table_name(),
+            NOT_DELETED
+        );
 
         let result = conn.query_row(&sql, [id.to_string()], |_| Ok(true));
 
@@ -110,6 +155,25 @@ impl RepositoryOps {
             Err(e) => Err(Error::Database(e.to_string())),
         }
     }
+
+    fn query_all<T: Repository<T>>(
+        conn: &Connection,
+        sql: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<T>> {
+        let mut stmt = conn.prepare(sql).map_err(|e| Error::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), T::from_row)
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row.map_err(|e| Error::Database(e.to_string()))?);
+        }
+
+        Ok(result)
+    }
 }
 
 /// Paginated query result
@@ -251,6 +315,82 @@ mod tests {
         assert!(query.contains("LIMIT 10"));
     }
 
+    struct Thing {
+        id: String,
+        name: String,
+    }
+
+    impl Repository<Thing> for Thing {
+        fn table_name() -> &'static str {
+            "things"
+        }
+
+        fn from_row(row: &Row) -> rusqlite::Result<Thing> {
+            Ok(Thing { id: row.get("id")?, name: row.get("name")? })
+        }
+
+        fn insert_columns() -> &'static [&'static str] {
+            &["id", "name"]
+        }
+
+        fn to_params(&self) -> Vec<Box<dyn rusqlite::ToSql>> {
+            vec![Box::new(self.id.clone()), Box::new(self.name.clone())]
+        }
+    }
+
+    fn things_db() -> (Connection, EntityId, EntityId) {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE things (id TEXT PRIMARY KEY, name TEXT, deleted_at TEXT, deleted_by TEXT)",
+        )
+        .unwrap();
+
+        let (a, b) = (EntityId::new(), EntityId::new());
+        for (id, name) in [(a, "a"), (b, "b")] {
+            conn.execute("INSERT INTO things (id, name) VALUES (?1, ?2)", [id.to_string(), name.to_string()])
+                .unwrap();
+        }
+        (conn, a, b)
+    }
+
+    #[test]
+    fn test_soft_delete_hides_rows() {
+        let (conn, a, b) = things_db();
+        let user = EntityId::new();
+
+        assert!(RepositoryOps::delete::<Thing>(&conn, &a, &user).unwrap());
+        assert!(!RepositoryOps::delete::<Thing>(&conn, &a, &user).unwrap());
+
+        assert!(RepositoryOps::find_by_id::<Thing>(&conn, &a).unwrap().is_none());
+        assert!(!RepositoryOps::exists::<Thing>(&conn, &a).unwrap());
+        assert_eq!(RepositoryOps::count::<Thing>(&conn, None).unwrap(), 1);
+
+        let live = RepositoryOps::find_all::<Thing>(&conn).unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].id, b.to_string());
+
+        let trash = RepositoryOps::find_deleted::<Thing>(&conn).unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].name, "a");
+    }
+
+    #[test]
+    fn test_restore_and_purge() {
+        let (conn, a, b) = things_db();
+        let user = EntityId::new();
+
+        // Only trashed rows can be purged
+        assert!(!RepositoryOps::purge::<Thing>(&conn, &b).unwrap());
+
+        RepositoryOps::delete::<Thing>(&conn, &a, &user).unwrap();
+        assert!(RepositoryOps::restore::<Thing>(&conn, &a).unwrap());
+        assert!(RepositoryOps::exists::<Thing>(&conn, &a).unwrap());
+
+        RepositoryOps::delete::<Thing>(&conn, &a, &user).unwrap();
+        assert!(RepositoryOps::purge::<Thing>(&conn, &a).unwrap());
+        assert!(RepositoryOps::find_deleted::<Thing>(&conn).unwrap().is_empty());
+    }
+
     #[test]
     fn test_query_builder_pagination() {
         let query = QueryBuilder::new("clients")
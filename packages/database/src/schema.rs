@@ -7,6 +7,10 @@ pub mod core {
     pub const SYNC_QUEUE: &str = "_sync_queue";
     pub const CONFLICTS: &str = "_conflicts";
     pub const CONFIG: &str = "_config";
+    pub const MAINTENANCE_RUNS: &str = "_maintenance_runs";
+    pub const FISCAL_ARCHIVES: &str = "_fiscal_archives";
+    pub const DOCUMENT_SEQUENCES: &str = "document_sequences";
+    pub const DOCUMENT_NUMBERS: &str = "document_numbers";
     pub const AUDIT_LOG: &str = "audit_log";
 }
 
@@ -24,6 +28,7 @@ pub mod appro {
     pub const PURCHASE_ORDERS: &str = "purchase_orders";
     pub const PURCHASE_ORDER_LINES: &str = "purchase_order_lines";
     pub const RECEPTION_NOTES: &str = "reception_notes";
+    pub const EXCHANGE_RATES: &str = "exchange_rates";
 }
 
 /// Stock domain tables
@@ -33,8 +38,18 @@ pub mod stock {
     pub const LOTS_MP: &str = "lots_mp";
     pub const LOTS_PF: &str = "lots_pf";
     pub const STOCK_MOVEMENTS: &str = "stock_movements";
+    pub const PRODUCT_PACKS: &str = "product_packs";
+    pub const PRODUCT_LOT_STRATEGIES: &str = "product_lot_strategies";
+    pub const LOT_RESERVATIONS: &str = "lot_reservations";
     pub const WAREHOUSES: &str = "warehouses";
     pub const WAREHOUSE_LOCATIONS: &str = "warehouse_locations";
+    pub const WAREHOUSE_STOCK_THRESHOLDS: &str = "warehouse_stock_thresholds";
+    pub const TRANSFER_ORDERS: &str = "transfer_orders";
+    pub const TRANSFER_ORDER_LINES: &str = "transfer_order_lines";
+    pub const INVENTORY_SESSIONS: &str = "inventory_sessions";
+    pub const INVENTORY_LINES: &str = "inventory_lines";
+    pub const STOCK_SNAPSHOTS: &str = "stock_snapshots";
+    pub const STOCK_SNAPSHOT_LINES: &str = "stock_snapshot_lines";
 }
 
 /// Production domain tables
@@ -69,6 +84,46 @@ pub mod finance {
     pub const INVOICE_LINES: &str = "invoice_lines";
     pub const PAYMENTS: &str = "payments";
     pub const COST_ENTRIES: &str = "cost_entries";
+    pub const FISCAL_RULE_SETS: &str = "fiscal_rule_sets";
+}
+
+/// Soft delete columns and deletable entities
+pub mod soft_delete {
+    pub const DELETED_AT: &str = "deleted_at";
+    pub const DELETED_BY: &str = "deleted_by";
+
+    /// Default scoping clause hiding deleted rows
+    pub const NOT_DELETED: &str = "deleted_at IS NULL";
+
+    /// Aggregate type -> table for every soft-deletable entity
+    pub const TABLES: &[(&str, &str)] = &[
+        ("User", "users"),
+        ("Supplier", "suppliers"),
+        ("ProductMp", "products_mp"),
+        ("ProductPf", "products_pf"),
+        ("LotMp", "lots_mp"),
+        ("LotPf", "lots_pf"),
+        ("Warehouse", "warehouses"),
+        ("Recipe", "recipes"),
+        ("ProductionOrder", "production_orders"),
+        ("Client", "clients"),
+        ("PriceList", "price_lists"),
+        ("SalesOrder", "sales_orders"),
+        ("Vehicle", "vehicles"),
+        ("Delivery", "deliveries"),
+        ("Invoice", "invoices"),
+        ("PurchaseOrder", "purchase_orders"),
+        ("Payment", "payments"),
+        ("CostEntry", "cost_entries"),
+    ];
+
+    /// Resolve the table of a soft-deletable aggregate type
+    pub fn table_for(aggregate_type: &str) -> Option<&'static str> {
+        TABLES
+            .iter()
+            .find(|(aggregate, _)| *aggregate == aggregate_type)
+            .map(|(_, table)| *table)
+    }
 }
 
 /// Entity status values
@@ -100,6 +155,15 @@ pub mod status {
     pub const DELIVERED: &str = "DELIVERED";
     pub const RETURNED: &str = "RETURNED";
 
+    // Transfer statuses (DRAFT, CANCELLED shared)
+    pub const SHIPPED: &str = "SHIPPED";
+    pub const RECEIVED: &str = "RECEIVED";
+
+    // Inventory session statuses (CANCELLED shared)
+    pub const COUNTING: &str = "COUNTING";
+    pub const REVIEW: &str = "REVIEW";
+    pub const APPROVED: &str = "APPROVED";
+
     // Payment statuses
     pub const UNPAID: &str = "UNPAID";
     pub const PARTIAL: &str = "PARTIAL";
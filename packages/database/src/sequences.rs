@@ -0,0 +1,401 @@
+//! Document numbering
+//!
+//! Fiscal documents must be numbered without gaps or duplicates. Numbers come
+//! from `document_sequences`, one counter per document type, year and device
+//! prefix, and are allocated on the connection of the transaction that
+//! inserts the document: a rolled back insert releases its number, a committed
+//! one keeps it. Every allocated number is recorded in `document_numbers`;
+//! voiding a document marks its number VOIDED, it is never reused.
+//!
+//! Format: `FAC-P2-2025-000042` on the PC with device prefix `P2`: every PC
+//! numbers under its own prefix, so two PCs never issue the same number.
+//! `FAC-2025-000042` is left for numbers from a central allocator.
+
+use chrono::{Datelike, NaiveDate};
+use manchengo_core::{Error, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// `_config` key holding the device prefix, absent when numbering centrally
+pub const DEVICE_PREFIX_KEY: &str = "document_prefix";
+
+/// Numbered document type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DocumentType {
+    Invoice,
+    PurchaseOrder,
+    ProductionOrder,
+    Transfer,
+    Inventory,
+}
+
+impl DocumentType {
+    pub const ALL: [DocumentType; 5] = [
+        Self::Invoice,
+        Self::PurchaseOrder,
+        Self::ProductionOrder,
+        Self::Transfer,
+        Self::Inventory,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Invoice => "INVOICE",
+            Self::PurchaseOrder => "PURCHASE_ORDER",
+            Self::ProductionOrder => "PRODUCTION_ORDER",
+            Self::Transfer => "TRANSFER",
+            Self::Inventory => "INVENTORY",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == s)
+    }
+
+    /// Prefix of the printed number
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Invoice => "FAC",
+            Self::PurchaseOrder => "BC",
+            Self::ProductionOrder => "PROD",
+            Self::Transfer => "TRF",
+            Self::Inventory => "INV",
+        }
+    }
+
+    /// Table holding the documents, for the orphan check
+    fn table(&self) -> &'static str {
+        match self {
+            Self::Invoice => "invoices",
+            Self::PurchaseOrder => "purchase_orders",
+            Self::ProductionOrder => "production_orders",
+            Self::Transfer => "transfer_orders",
+            Self::Inventory => "inventory_sessions",
+        }
+    }
+}
+
+/// An allocated number
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentNumber {
+    pub doc_type: DocumentType,
+    pub year: i32,
+    pub device_prefix: Option<String>,
+    pub value: i64,
+    pub number: String,
+}
+
+impl DocumentNumber {
+    fn format(doc_type: DocumentType, year: i32, device_prefix: Option<&str>, value: i64) -> String {
+        match device_prefix {
+            Some(prefix) => format!("{}-{}-{}-{:06}", doc_type.code(), prefix, year, value),
+            None => format!("{}-{}-{:06}", doc_type.code(), year, value),
+        }
+    }
+}
+
+/// Audit of one sequence (type, year, device prefix)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceAudit {
+    pub doc_type: DocumentType,
+    pub year: i32,
+    pub device_prefix: Option<String>,
+    pub last_value: i64,
+    pub issued: i64,
+    /// Voided numbers, kept in the sequence
+    pub voided: Vec<String>,
+    /// Values allocated by the counter but never recorded
+    pub missing: Vec<i64>,
+    /// Issued numbers whose document no longer exists
+    pub orphaned: Vec<String>,
+}
+
+impl SequenceAudit {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+/// Document sequence operations
+pub struct DocumentSequences;
+
+impl DocumentSequences {
+    /// Device prefix currently applied to new numbers
+    pub fn device_prefix(conn: &Connection) -> Result<Option<String>> {
+        conn.query_row("SELECT value FROM _config WHERE key = ?", [DEVICE_PREFIX_KEY], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::Database(e.to_string()))
+    }
+
+    /// Set the device prefix, or clear it when a central allocator numbers
+    pub fn set_device_prefix(conn: &Connection, prefix: Option<&str>) -> Result<()> {
+        match prefix {
+            Some(prefix) => {
+                if prefix.is_empty() || prefix.len() > 4 || !prefix.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+                    return Err(Error::Validation {
+                        field: "document_prefix".to_string(),
+                        message: format!("Prefixe '{}' invalide (1 a 4 majuscules ou chiffres)", prefix),
+                    });
+                }
+                conn.execute(
+                    "INSERT INTO _config (key, value, updated_at) VALUES (?, ?, datetime('now'))
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                    params![DEVICE_PREFIX_KEY, prefix],
+                )
+            }
+            None => conn.execute("DELETE FROM _config WHERE key = ?", [DEVICE_PREFIX_KEY]),
+        }
+        .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Allocate the next number for a document
+    ///
+    /// Call on the transaction that inserts the document, so both commit or
+    /// roll back together.
+    pub fn allocate(
+        conn: &Connection,
+        doc_type: DocumentType,
+        date: NaiveDate,
+        document_id: &str,
+    ) -> Result<DocumentNumber> {
+        let year = date.year();
+        let device_prefix = Self::device_prefix(conn)?;
+        let prefix_key = device_prefix.as_deref().unwrap_or("");
+
+        let value: i64 = conn
+            .query_row(
+                "INSERT INTO document_sequences (doc_type, year, device_prefix, last_value, updated_at)
+                 VALUES (?, ?, ?, 1, datetime('now'))
+                 ON CONFLICT(doc_type, year, device_prefix) DO UPDATE SET
+                    last_value = last_value + 1,
+                    updated_at = excluded.updated_at
+                 RETURNING last_value",
+                params![doc_type.as_str(), year, prefix_key],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let number = DocumentNumber::format(doc_type, year, device_prefix.as_deref(), value);
+
+        conn.execute(
+            "INSERT INTO document_numbers (number, doc_type, year, device_prefix, value, document_id, status, allocated_at)
+             VALUES (?, ?, ?, ?, ?, ?, 'ISSUED', datetime('now'))",
+            params![number, doc_type.as_str(), year, prefix_key, value, document_id],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+        Ok(DocumentNumber {
+            doc_type,
+            year,
+            device_prefix,
+            value,
+            number,
+        })
+    }
+
+    /// Mark a number VOIDED; it stays in the sequence
+    ///
+    /// Numbers issued before sequences existed are not recorded and are
+    /// ignored.
+    pub fn void(conn: &Connection, number: &str, reason: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE document_numbers SET status = 'VOIDED', void_reason = ?, voided_at = datetime('now')
+             WHERE number = ? AND status = 'ISSUED'",
+            params![reason, number],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Gap audit of every sequence of a year
+    pub fn gap_report(conn: &Connection, year: i32) -> Result<Vec<SequenceAudit>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT doc_type, device_prefix, last_value FROM document_sequences
+                 WHERE year = ? ORDER BY doc_type, device_prefix",
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+        let sequences = stmt
+            .query_map([year], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+            })
+            .map_err(|e| Error::Database(e.to_string()))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut report = Vec::new();
+        for (doc_type, prefix, last_value) in sequences {
+            let Some(doc_type) = DocumentType::parse(&doc_type) else {
+                continue;
+            };
+            report.push(Self::audit(conn, doc_type, year, &prefix, last_value)?);
+        }
+        Ok(report)
+    }
+
+    fn audit(conn: &Connection, doc_type: DocumentType, year: i32, prefix: &str, last_value: i64) -> Result<SequenceAudit> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT value, number, status FROM document_numbers
+                 WHERE doc_type = ? AND year = ? AND device_prefix = ?
+                 ORDER BY value",
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![doc_type.as_str(), year, prefix], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })
+            .map_err(|e| Error::Database(e.to_string()))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut missing = Vec::new();
+        let mut expected = 1;
+        for (value, _, _) in &rows {
+            missing.extend(expected..*value);
+            expected = value + 1;
+        }
+        missing.extend(expected..=last_value);
+
+        let voided = rows
+            .iter()
+            .filter(|(_, _, status)| status == "VOIDED")
+            .map(|(_, number, _)| number.clone())
+            .collect();
+
+        Ok(SequenceAudit {
+            doc_type,
+            year,
+            device_prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+            last_value,
+            issued: rows.len() as i64,
+            voided,
+            missing,
+            orphaned: Self::orphaned(conn, doc_type, year, prefix)?,
+        })
+    }
+
+    /// Issued numbers whose document row is gone
+    fn orphaned(conn: &Connection, doc_type: DocumentType, year: i32, prefix: &str) -> Result<Vec<String>> {
+        let table_exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [doc_type.table()],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(e.to_string()))?;
+        if !table_exists {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            "SELECT n.number FROM document_numbers n
+             WHERE n.doc_type = ? AND n.year = ? AND n.device_prefix = ? AND n.status = 'ISSUED'
+               AND NOT EXISTS (SELECT 1 FROM {} d WHERE d.id = n.document_id)
+             ORDER BY n.value",
+            doc_type.table()
+        );
+        let mut stmt = conn.prepare(&sql).map_err(|e| Error::Database(e.to_string()))?;
+        let numbers = stmt
+            .query_map(params![doc_type.as_str(), year, prefix], |row| row.get(0))
+            .map_err(|e| Error::Database(e.to_string()))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(|e| Error::Database(e.to_string()))?;
+        Ok(numbers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::initialize_database;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_database(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_numbers_per_type_and_year() {
+        let conn = setup();
+
+        let first = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 1, 2), "a").unwrap();
+        let second = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 6, 1), "b").unwrap();
+        let order = DocumentSequences::allocate(&conn, DocumentType::PurchaseOrder, date(2025, 6, 1), "c").unwrap();
+        let next_year = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2026, 1, 1), "d").unwrap();
+
+        assert_eq!(first.number, "FAC-2025-000001");
+        assert_eq!(second.number, "FAC-2025-000002");
+        assert_eq!(order.number, "BC-2025-000001");
+        assert_eq!(next_year.number, "FAC-2026-000001");
+    }
+
+    #[test]
+    fn test_device_prefix_has_own_counter() {
+        let conn = setup();
+        DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 1, 2), "a").unwrap();
+
+        DocumentSequences::set_device_prefix(&conn, Some("P2")).unwrap();
+        let offline = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 1, 2), "b").unwrap();
+        assert_eq!(offline.number, "FAC-P2-2025-000001");
+
+        DocumentSequences::set_device_prefix(&conn, None).unwrap();
+        let central = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 1, 2), "c").unwrap();
+        assert_eq!(central.number, "FAC-2025-000002");
+
+        assert!(DocumentSequences::set_device_prefix(&conn, Some("p-2")).is_err());
+    }
+
+    #[test]
+    fn test_rollback_releases_number() {
+        let mut conn = setup();
+
+        let tx = conn.transaction().unwrap();
+        DocumentSequences::allocate(&tx, DocumentType::Invoice, date(2025, 3, 1), "a").unwrap();
+        tx.rollback().unwrap();
+
+        let number = DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 3, 1), "b").unwrap();
+        assert_eq!(number.value, 1);
+    }
+
+    #[test]
+    fn test_gap_report() {
+        let conn = setup();
+        let numbers: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|id| DocumentSequences::allocate(&conn, DocumentType::Invoice, date(2025, 3, 1), id).unwrap())
+            .collect();
+
+        // Documents "a" and "c" exist, "b" was voided
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        for id in ["a", "c"] {
+            conn.execute(
+                "INSERT INTO invoices (id, invoice_number, client_id, invoice_date, total_ht, total_tva, total_ttc,
+                                       status, created_by, updated_by)
+                 VALUES (?, ?, 'client', '2025-03-01', 0, 0, 0, 'VALIDATED', 'u', 'u')",
+                params![id, format!("N-{}", id)],
+            )
+            .unwrap();
+        }
+        DocumentSequences::void(&conn, &numbers[1].number, "Erreur client").unwrap();
+
+        let report = DocumentSequences::gap_report(&conn, 2025).unwrap();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].is_clean());
+        assert_eq!(report[0].voided, vec!["FAC-2025-000002".to_string()]);
+
+        // A number removed from the ledger and a deleted document show up
+        conn.execute("DELETE FROM document_numbers WHERE value = 1", []).unwrap();
+        conn.execute("DELETE FROM invoices WHERE id = 'c'", []).unwrap();
+        let audit = &DocumentSequences::gap_report(&conn, 2025).unwrap()[0];
+        assert_eq!(audit.missing, vec![1]);
+        assert_eq!(audit.orphaned, vec!["FAC-2025-000003".to_string()]);
+    }
+}
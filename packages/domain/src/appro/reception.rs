@@ -1,7 +1,7 @@
 //! Raw material reception with OCR support
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AuditInfo, EntityId, Error, Money, Result, UnitOfMeasure};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Money, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Reception status
@@ -68,6 +68,7 @@ impl ReceptionNote {
         supplier_id: EntityId,
         reception_date: NaiveDate,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -83,11 +84,12 @@ impl ReceptionNote {
             validated_at: None,
             validated_by: None,
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
     /// Create from OCR scan
+    #[allow(clippy::too_many_arguments)]
     pub fn from_ocr(
         reception_number: String,
         supplier_id: EntityId,
@@ -96,8 +98,9 @@ impl ReceptionNote {
         ocr_text: String,
         confidence: f64,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
-        let mut note = Self::new(reception_number, supplier_id, reception_date, user_id);
+        let mut note = Self::new(reception_number, supplier_id, reception_date, user_id, clock);
         note.bl_photo_path = Some(bl_photo_path);
         note.ocr_raw_text = Some(ocr_text);
         note.ocr_confidence = Some(confidence);
@@ -112,6 +115,7 @@ impl ReceptionNote {
         unit: UnitOfMeasure,
         unit_cost: Money,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != ReceptionStatus::Draft {
             return Err(Error::BusinessRule(
@@ -135,7 +139,7 @@ impl ReceptionNote {
             expiry_date: None,
         });
 
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
@@ -149,8 +153,9 @@ impl ReceptionNote {
         ocr_quantity: f64,
         ocr_unit_cost: Money,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
-        self.add_line(product_mp_id, quantity, unit, unit_cost, user_id)?;
+        self.add_line(product_mp_id, quantity, unit, unit_cost, user_id, clock)?;
 
         if let Some(line) = self.lines.last_mut() {
             line.ocr_quantity = Some(ocr_quantity);
@@ -161,7 +166,7 @@ impl ReceptionNote {
     }
 
     /// Validate the reception (human confirmation)
-    pub fn validate(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn validate(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != ReceptionStatus::Draft {
             return Err(Error::InvalidStateTransition {
                 entity: "ReceptionNote".to_string(),
@@ -178,15 +183,15 @@ impl ReceptionNote {
         }
 
         self.status = ReceptionStatus::Validated;
-        self.validated_at = Some(Utc::now());
+        self.validated_at = Some(clock.now());
         self.validated_by = Some(user_id);
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
 
         Ok(())
     }
 
     /// Cancel the reception
-    pub fn cancel(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn cancel(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != ReceptionStatus::Draft {
             return Err(Error::BusinessRule(
                 "Can only cancel draft receptions".to_string(),
@@ -194,7 +199,7 @@ impl ReceptionNote {
         }
 
         self.status = ReceptionStatus::Cancelled;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
@@ -1,6 +1,6 @@
 //! Supplier management
 
-use manchengo_core::{Address, AuditInfo, EntityId, FiscalIdentity};
+use manchengo_core::{Address, AuditInfo, Clock, EntityId, FiscalIdentity, Result};
 use serde::{Deserialize, Serialize};
 
 /// Supplier entity
@@ -28,7 +28,7 @@ pub struct Supplier {
 }
 
 impl Supplier {
-    pub fn new(code: String, name: String, user_id: EntityId) -> Self {
+    pub fn new(code: String, name: String, user_id: EntityId, clock: &dyn Clock) -> Self {
         Self {
             id: EntityId::new(),
             code,
@@ -40,11 +40,21 @@ impl Supplier {
             fiscal_identity: None,
             notes: None,
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
     pub fn has_fiscal_identity(&self) -> bool {
         self.fiscal_identity.is_some()
     }
+
+    /// Set fiscal identity, rejecting malformed identifiers
+    pub fn set_fiscal_identity(&mut self, fiscal_identity: FiscalIdentity, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        if let Some(err) = fiscal_identity.validate().into_iter().next() {
+            return Err(err.into());
+        }
+        self.fiscal_identity = Some(fiscal_identity);
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
 }
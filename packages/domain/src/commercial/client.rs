@@ -1,6 +1,6 @@
 //! Client management
 
-use manchengo_core::{Address, AuditInfo, ClientType, EntityId, FiscalIdentity, Money};
+use manchengo_core::{Address, AuditInfo, ClientType, Clock, EntityId, FiscalIdError, FiscalIdentity, Money, Result};
 use serde::{Deserialize, Serialize};
 
 /// Client entity
@@ -42,6 +42,7 @@ impl Client {
         name: String,
         client_type: ClientType,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -61,7 +62,7 @@ impl Client {
             current_balance: Money::zero(),
             notes: None,
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
@@ -76,15 +77,15 @@ impl Client {
     }
 
     /// Add to client balance (they owe us more)
-    pub fn add_to_balance(&mut self, amount: Money, user_id: EntityId) {
+    pub fn add_to_balance(&mut self, amount: Money, user_id: EntityId, clock: &dyn Clock) {
         self.current_balance = self.current_balance + amount;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 
     /// Reduce client balance (payment received)
-    pub fn reduce_balance(&mut self, amount: Money, user_id: EntityId) {
+    pub fn reduce_balance(&mut self, amount: Money, user_id: EntityId, clock: &dyn Clock) {
         self.current_balance = self.current_balance - amount;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 
     /// Check if client has required fiscal identity
@@ -92,11 +93,21 @@ impl Client {
         self.fiscal_identity.is_some()
     }
 
-    /// Validate fiscal identity completeness
-    pub fn validate_fiscal_identity(&self) -> Vec<String> {
+    /// Set fiscal identity, rejecting malformed identifiers
+    pub fn set_fiscal_identity(&mut self, fiscal_identity: FiscalIdentity, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        if let Some(err) = fiscal_identity.validate().into_iter().next() {
+            return Err(err.into());
+        }
+        self.fiscal_identity = Some(fiscal_identity);
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Validate fiscal identity structure (NIF, NIS, RC, article)
+    pub fn validate_fiscal_identity(&self) -> Vec<FiscalIdError> {
         match &self.fiscal_identity {
             Some(fi) => fi.validate(),
-            None => vec!["Fiscal identity not set".to_string()],
+            None => vec![FiscalIdError::Missing { field: "identite fiscale" }],
         }
     }
 }
@@ -1,7 +1,7 @@
 //! Price list management by client type
 
 use chrono::NaiveDate;
-use manchengo_core::{AuditInfo, ClientType, EntityId, Money};
+use manchengo_core::{AuditInfo, ClientType, Clock, EntityId, Money};
 use serde::{Deserialize, Serialize};
 
 /// Price list for a client type
@@ -35,6 +35,7 @@ impl PriceList {
         client_type: ClientType,
         valid_from: NaiveDate,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -45,7 +46,7 @@ impl PriceList {
             valid_until: None,
             lines: Vec::new(),
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
@@ -86,6 +87,7 @@ impl PriceList {
         price_ht: Money,
         min_quantity: f64,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) {
         // Remove existing line for same product and min_quantity
         self.lines
@@ -99,7 +101,7 @@ impl PriceList {
             min_quantity,
         });
 
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 }
 
@@ -1,9 +1,13 @@
 //! Sales order management
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AlgerianTaxRates, AuditInfo, EntityId, Error, Money, Result, UnitOfMeasure};
+use manchengo_core::{
+    AlgerianTaxRates, AuditInfo, Clock, EntityId, Error, Money, PackDefinition, Quantity, Result, UnitOfMeasure,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::stock::{FifoLotSelectorPf, LotPf, LotSelectionStrategy, ReservationBook};
+
 /// Sales order status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -75,6 +79,8 @@ pub struct SalesOrderLine {
     pub product_pf_id: EntityId,
     pub quantity: f64,
     pub unit: UnitOfMeasure,
+    /// Ordered quantity in the product stock unit (used for FIFO delivery)
+    pub stock_quantity: Quantity,
     pub unit_price_ht: Money,
     pub tva_rate: f64,
     pub total_ht: Money,
@@ -90,6 +96,7 @@ impl SalesOrder {
         client_id: EntityId,
         order_date: NaiveDate,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -105,19 +112,25 @@ impl SalesOrder {
             payment_status: PaymentStatus::Unpaid,
             amount_paid: Money::zero(),
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
     /// Add a line to the order
+    ///
+    /// The sold quantity is converted to the product stock unit through its
+    /// pack (e.g. cartons sold, pieces stocked).
+    #[allow(clippy::too_many_arguments)]
     pub fn add_line(
         &mut self,
         product_pf_id: EntityId,
-        quantity: f64,
-        unit: UnitOfMeasure,
+        quantity: Quantity,
+        stock_unit: UnitOfMeasure,
+        pack: &PackDefinition,
         unit_price_ht: Money,
         tva_rate: f64,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != SalesOrderStatus::Draft {
             return Err(Error::BusinessRule(
@@ -125,7 +138,9 @@ impl SalesOrder {
             ));
         }
 
-        let total_ht = Money::from_centimes((quantity * unit_price_ht.centimes() as f64) as i64);
+        let stock_quantity = quantity.convert_with_pack(stock_unit, pack)?;
+
+        let total_ht = Money::from_centimes((quantity.value * unit_price_ht.centimes() as f64) as i64);
         let total_tva = Money::from_centimes((total_ht.centimes() as f64 * tva_rate) as i64);
         let total_ttc = total_ht + total_tva;
 
@@ -133,8 +148,9 @@ impl SalesOrder {
             id: EntityId::new(),
             sales_order_id: self.id,
             product_pf_id,
-            quantity,
-            unit,
+            quantity: quantity.value,
+            unit: quantity.unit,
+            stock_quantity,
             unit_price_ht,
             tva_rate,
             total_ht,
@@ -145,12 +161,27 @@ impl SalesOrder {
         });
 
         self.recalculate_totals();
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
-    /// Confirm the order
-    pub fn confirm(&mut self, user_id: EntityId) -> Result<()> {
+    /// Confirm the order and reserve its lots until `hold_until`
+    ///
+    /// Each line holds lots of its product with the product strategy
+    /// (`strategies`, FIFO when absent), among what is still available to
+    /// promise. Manual products are held in FIFO order: the operator picks
+    /// the lots at preparation time. Nothing is reserved unless every line
+    /// is covered.
+    #[allow(clippy::too_many_arguments)]
+    pub fn confirm(
+        &mut self,
+        lots: &mut [LotPf],
+        book: &mut ReservationBook,
+        strategies: &[(EntityId, LotSelectionStrategy)],
+        hold_until: DateTime<Utc>,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<()> {
         if self.status != SalesOrderStatus::Draft {
             return Err(Error::InvalidStateTransition {
                 entity: "SalesOrder".to_string(),
@@ -166,13 +197,77 @@ impl SalesOrder {
             });
         }
 
+        // Plan every line first, on a scratch copy of the book
+        let now = clock.now();
+        let mut planned = book.clone();
+        let mut scratch = lots.to_vec();
+        let mut holds = Vec::new();
+        for line in &self.lines {
+            let strategy = match strategies.iter().find(|(id, _)| *id == line.product_pf_id) {
+                Some((_, LotSelectionStrategy::Manual)) | None => LotSelectionStrategy::Fifo,
+                Some((_, strategy)) => *strategy,
+            };
+            let product_lots: Vec<LotPf> = planned
+                .unreserved_lots(&scratch, now)
+                .into_iter()
+                .filter(|lot| lot.product_id == line.product_pf_id)
+                .collect();
+            let selection = FifoLotSelectorPf::select_lots_with(
+                &strategy,
+                &product_lots,
+                line.stock_quantity,
+                &PackDefinition::default(),
+                &[],
+                clock.today(),
+            )?
+            .require_complete(&line.product_pf_id.to_string())?;
+
+            for pick in selection.picks {
+                let lot = scratch.iter_mut().find(|l| l.id == pick.lot_id).expect("picked lot is a candidate");
+                planned.reserve(lot, self.id, line.id, pick.quantity, hold_until, user_id, clock)?;
+                holds.push((pick.lot_id, line.id, pick.quantity));
+            }
+        }
+
+        for (lot_id, line_id, quantity) in holds {
+            let lot = lots.iter_mut().find(|l| l.id == lot_id).expect("planned lot is in the list");
+            book.reserve(lot, self.id, line_id, quantity, hold_until, user_id, clock)?;
+        }
+
         self.status = SalesOrderStatus::Confirmed;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Cancel the order and release its reservations
+    pub fn cancel(
+        &mut self,
+        lots: &mut [LotPf],
+        book: &mut ReservationBook,
+        reason: &str,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        if !matches!(self.status, SalesOrderStatus::Draft | SalesOrderStatus::Confirmed) {
+            return Err(Error::InvalidStateTransition {
+                entity: "SalesOrder".to_string(),
+                from: self.status.as_str().to_string(),
+                to: "CANCELLED".to_string(),
+            });
+        }
+
+        book.release_order(self.id, lots, reason, user_id, clock);
+        self.status = SalesOrderStatus::Cancelled;
+        self.notes = Some(match self.notes.take() {
+            Some(notes) => format!("{}\nAnnulee: {}", notes, reason),
+            None => format!("Annulee: {}", reason),
+        });
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Record payment received
-    pub fn record_payment(&mut self, amount: Money, user_id: EntityId) {
+    pub fn record_payment(&mut self, amount: Money, user_id: EntityId, clock: &dyn Clock) {
         self.amount_paid = self.amount_paid + amount;
 
         if self.amount_paid.centimes() >= self.total_ttc.centimes() {
@@ -181,7 +276,7 @@ impl SalesOrder {
             self.payment_status = PaymentStatus::Partial;
         }
 
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 
     /// Get remaining amount to pay
@@ -1,9 +1,12 @@
 //! Delivery note management
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AuditInfo, EntityId, Error, Money, QrCodeData, QrEntityType, Result, UnitOfMeasure};
+use manchengo_core::{AmountInWords, AuditInfo, Clock, EntityId, Error, Gs1Label, Money, QrCodeData, QrEntityType, QrKeyRing, Qty, Quantity, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
+use crate::commercial::SalesOrder;
+use crate::stock::{LotPf, ProductPf, ReservationBook, ScannedCode};
+
 /// Delivery status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -145,16 +148,12 @@ impl Delivery {
         delivery_number: String,
         planned_date: NaiveDate,
         user_id: EntityId,
+        qr_keys: &QrKeyRing,
+        clock: &dyn Clock,
     ) -> Self {
         let id = EntityId::new();
 
-        let qr_data = QrCodeData {
-            entity_type: QrEntityType::Delivery,
-            entity_id: id,
-            reference: delivery_number.clone(),
-            expiry_date: None,
-            checksum: String::new(),
-        };
+        let qr_data = QrCodeData::new(QrEntityType::Delivery, id, delivery_number.clone(), None, qr_keys);
 
         Self {
             id,
@@ -173,7 +172,7 @@ impl Delivery {
             total_weight_kg: 0.0,
             qr_code: qr_data.encode(),
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
@@ -184,6 +183,7 @@ impl Delivery {
         sales_order_id: Option<EntityId>,
         sequence: i32,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<EntityId> {
         if self.status != DeliveryStatus::Draft {
             return Err(Error::BusinessRule(
@@ -212,12 +212,116 @@ impl Delivery {
 
         let line_id = line.id;
         self.lines.push(line);
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(line_id)
     }
 
+    /// Prepare a client line from the lots reserved for its sales order
+    ///
+    /// Every reservation still holding stock for the order becomes an item;
+    /// the reserved quantity leaves its lot and the reservation is consumed.
+    /// Fails if an order line has no reservation left (cancelled or timed
+    /// out). Returns the (lot, quantity) picked for the stock movements.
+    pub fn add_reserved_items(
+        &mut self,
+        line_id: EntityId,
+        order: &SalesOrder,
+        book: &mut ReservationBook,
+        lots: &mut [LotPf],
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<Vec<(EntityId, Qty)>> {
+        if self.status != DeliveryStatus::Draft {
+            return Err(Error::BusinessRule(
+                "Can only add items to draft deliveries".to_string(),
+            ));
+        }
+
+        let line = self
+            .lines
+            .iter_mut()
+            .find(|l| l.id == line_id)
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "DeliveryLine".to_string(),
+                id: line_id.to_string(),
+            })?;
+        if line.sales_order_id != Some(order.id) {
+            return Err(Error::BusinessRule(format!(
+                "Delivery line is not for order {}",
+                order.order_number
+            )));
+        }
+
+        let now = clock.now();
+        let mut planned = Vec::new();
+        for order_line in &order.lines {
+            let holds = book.holding_for_line(order_line.id, now);
+            if holds.is_empty() {
+                return Err(Error::BusinessRule(format!(
+                    "Aucune reservation active pour la commande {}: annulee ou expiree",
+                    order.order_number
+                )));
+            }
+            let unit_price_ht = order_line
+                .stock_quantity
+                .to_qty()
+                .unit_price_of(order_line.total_ht)
+                .unwrap_or_else(Money::zero);
+            for hold in holds {
+                planned.push((hold.id, hold.lot_id, hold.quantity, order_line.product_pf_id, order_line.tva_rate, unit_price_ht));
+            }
+        }
+
+        // Check every lot before touching any of them
+        for (_, lot_id, quantity, ..) in &planned {
+            let lot = lots.iter().find(|l| l.id == *lot_id).ok_or_else(|| Error::NotFound {
+                entity_type: "LotPf".to_string(),
+                id: lot_id.to_string(),
+            })?;
+            if *quantity > lot.quantity_remaining {
+                return Err(Error::InsufficientStock {
+                    product: lot.lot_number.clone(),
+                    required: quantity.as_f64(),
+                    available: lot.quantity_remaining.as_f64(),
+                });
+            }
+        }
+
+        let mut picked = Vec::with_capacity(planned.len());
+        for (reservation_id, lot_id, quantity, product_pf_id, tva_rate, unit_price_ht) in planned {
+            let lot = lots.iter_mut().find(|l| l.id == lot_id).expect("checked above");
+            lot.deliver(quantity, user_id, clock)?;
+            book.consume(reservation_id, user_id, clock)?;
+
+            let total_ht = quantity.value_at(unit_price_ht);
+            let total_tva = Money::from_centimes((total_ht.centimes() as f64 * tva_rate) as i64);
+            line.items.push(DeliveryLineItem {
+                id: EntityId::new(),
+                delivery_line_id: line.id,
+                product_pf_id,
+                lot_pf_id: lot_id,
+                quantity_planned: quantity.as_f64(),
+                quantity_delivered: 0.0,
+                unit: lot.unit,
+                unit_price_ht,
+                total_ht,
+                qr_scanned_at: None,
+                qr_scanned_by: None,
+            });
+            line.total_ht = line.total_ht + total_ht;
+            line.total_ttc = line.total_ttc + total_ht + total_tva;
+            self.total_ht = self.total_ht + total_ht;
+            self.total_tva = self.total_tva + total_tva;
+            self.total_ttc = self.total_ttc + total_ht + total_tva;
+            picked.push((lot_id, quantity));
+        }
+
+        self.audit.update(user_id, clock);
+        Ok(picked)
+    }
+
     /// Mark as prepared (ready for loading)
-    pub fn mark_prepared(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn mark_prepared(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != DeliveryStatus::Draft {
             return Err(Error::InvalidStateTransition {
                 entity: "Delivery".to_string(),
@@ -234,7 +338,7 @@ impl Delivery {
         }
 
         self.status = DeliveryStatus::Prepared;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
@@ -245,6 +349,7 @@ impl Delivery {
         lot_pf_id: EntityId,
         quantity: f64,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != DeliveryStatus::Prepared && self.status != DeliveryStatus::Loaded {
             return Err(Error::BusinessRule(
@@ -270,16 +375,80 @@ impl Delivery {
                 format!("Lot {} not expected in this delivery line", lot_pf_id),
             ))?;
 
-        item.qr_scanned_at = Some(Utc::now());
+        item.qr_scanned_at = Some(clock.now());
         item.qr_scanned_by = Some(user_id);
 
         self.status = DeliveryStatus::Loaded;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
+    /// Record a scanned QR or GS1-128 code during loading
+    ///
+    /// Resolves the code to its finished product lot, then marks the item as
+    /// scanned like [`Delivery::scan_item`]. A GS1 net weight (AI 3103) is
+    /// converted to the lot unit and must not exceed the planned quantity;
+    /// a weight that cannot be converted (count unit without a unit weight)
+    /// is not checked. Returns the lot id.
+    pub fn scan_code(
+        &mut self,
+        line_id: EntityId,
+        code: &ScannedCode,
+        products: &[ProductPf],
+        lots: &[LotPf],
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<EntityId> {
+        let lot = code.resolve_lot_pf(products, lots)?;
+        let item = self
+            .lines
+            .iter()
+            .find(|l| l.id == line_id)
+            .and_then(|l| l.items.iter().find(|i| i.lot_pf_id == lot.id));
+        let mut quantity = item.map(|i| i.quantity_planned).unwrap_or(0.0);
+
+        if let (
+            ScannedCode::Gs1(Gs1Label {
+                net_weight_kg: Some(weight),
+                ..
+            }),
+            Some(item),
+        ) = (code, item)
+        {
+            let product = products.iter().find(|p| p.id == lot.product_id);
+            if let Some(scanned) = Self::weight_in_unit(*weight, item.unit, product) {
+                let planned = Qty::new(item.quantity_planned, item.unit);
+                if scanned > planned {
+                    return Err(Error::BusinessRule(format!(
+                        "Poids scanne {} kg ({} {}) superieur aux {} {} prevus pour le lot {}",
+                        weight,
+                        scanned,
+                        item.unit.code(),
+                        planned,
+                        item.unit.code(),
+                        lot.lot_number
+                    )));
+                }
+                quantity = scanned.as_f64();
+            }
+        }
+
+        self.scan_item(line_id, lot.id, quantity, user_id, clock)?;
+        Ok(lot.id)
+    }
+
+    /// A net weight in kg expressed in `unit`, through the unit weight of the
+    /// product for count units
+    fn weight_in_unit(weight: Qty, unit: UnitOfMeasure, product: Option<&ProductPf>) -> Option<Qty> {
+        if let Ok(converted) = Quantity::kg(weight.as_f64()).convert_to(unit) {
+            return Some(converted.to_qty());
+        }
+        let unit_weight = product.filter(|p| p.unit == unit)?.weight_kg.filter(|w| *w > 0.0)?;
+        Some(Qty::new(weight.as_f64() / unit_weight, unit))
+    }
+
     /// Start delivery (depart)
-    pub fn depart(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn depart(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != DeliveryStatus::Loaded {
             return Err(Error::InvalidStateTransition {
                 entity: "Delivery".to_string(),
@@ -300,8 +469,8 @@ impl Delivery {
         }
 
         self.status = DeliveryStatus::InTransit;
-        self.departure_at = Some(Utc::now());
-        self.audit.update(user_id);
+        self.departure_at = Some(clock.now());
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
@@ -313,6 +482,7 @@ impl Delivery {
         payment: Option<(Money, PaymentMethod)>,
         signature_path: Option<String>,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != DeliveryStatus::InTransit {
             return Err(Error::BusinessRule(
@@ -351,7 +521,7 @@ impl Delivery {
             DeliveryLineStatus::Refused
         };
 
-        line.delivered_at = Some(Utc::now());
+        line.delivered_at = Some(clock.now());
         line.signature_path = signature_path;
 
         if let Some((amount, method)) = payment {
@@ -360,7 +530,7 @@ impl Delivery {
         }
 
         // Check if all clients done
-        self.update_overall_status(user_id);
+        self.update_overall_status(user_id, clock);
         Ok(())
     }
 
@@ -370,6 +540,7 @@ impl Delivery {
         line_id: EntityId,
         reason: String,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         let line = self
             .lines
@@ -382,13 +553,13 @@ impl Delivery {
 
         line.status = DeliveryLineStatus::Refused;
         line.refusal_reason = Some(reason);
-        line.delivered_at = Some(Utc::now());
+        line.delivered_at = Some(clock.now());
 
-        self.update_overall_status(user_id);
+        self.update_overall_status(user_id, clock);
         Ok(())
     }
 
-    fn update_overall_status(&mut self, user_id: EntityId) {
+    fn update_overall_status(&mut self, user_id: EntityId, clock: &dyn Clock) {
         let all_done = self
             .lines
             .iter()
@@ -413,10 +584,10 @@ impl Delivery {
                 DeliveryStatus::Returned
             };
 
-            self.completed_at = Some(Utc::now());
+            self.completed_at = Some(clock.now());
         }
 
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 
     /// Get total payment collected
@@ -426,3 +597,80 @@ impl Delivery {
             .fold(Money::zero(), |acc, l| acc + l.payment_collected)
     }
 }
+
+impl DeliveryLine {
+    /// Total TTC in words, printed on the client's delivery note
+    pub fn total_in_words(&self) -> AmountInWords {
+        AmountInWords::new(self.total_ttc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manchengo_core::{FixedClock, QrKey};
+
+    #[test]
+    fn test_gs1_scan_checks_weight_against_planned_pieces() {
+        let user = EntityId::new();
+        let clock = FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let keys = QrKeyRing::new(vec![QrKey::new("T1", b"test-secret-0123456789").unwrap()]).unwrap();
+
+        let mut product = ProductPf::new(
+            "PF-CAM250".to_string(),
+            "Camembert 250g".to_string(),
+            UnitOfMeasure::Piece,
+            Money::from_dzd(350.0),
+            user,
+            &clock,
+        );
+        product.weight_kg = Some(0.25);
+        product
+            .set_gtins(Some("6130000000015"), Some("16130000000012"), user, &clock)
+            .unwrap();
+        let lot = LotPf::new(
+            "LPF-240115-001".to_string(),
+            product.id,
+            None,
+            Qty::units(120),
+            UnitOfMeasure::Piece,
+            Money::from_dzd(200.0),
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            user,
+            &keys,
+            &clock,
+        );
+
+        let mut delivery = Delivery::new("BL-0001".to_string(), clock.today(), user, &keys, &clock);
+        let line_id = delivery.add_line(EntityId::new(), None, 1, user, &clock).unwrap();
+        delivery.lines[0].items.push(DeliveryLineItem {
+            id: EntityId::new(),
+            delivery_line_id: line_id,
+            product_pf_id: product.id,
+            lot_pf_id: lot.id,
+            quantity_planned: 8.0,
+            quantity_delivered: 0.0,
+            unit: UnitOfMeasure::Piece,
+            unit_price_ht: Money::from_dzd(350.0),
+            total_ht: Money::from_dzd(2800.0),
+            qr_scanned_at: None,
+            qr_scanned_by: None,
+        });
+        delivery.mark_prepared(user, &clock).unwrap();
+        let (products, lots) = (vec![product], vec![lot]);
+
+        // 2.5 kg of 250 g pieces is 10 pieces, 8 planned
+        let carton = ScannedCode::parse("(01)16130000000012(3103)002500(10)LPF-240115-001", &keys).unwrap();
+        assert!(delivery.scan_code(line_id, &carton, &products, &lots, user, &clock).is_err());
+        assert!(delivery.lines[0].items[0].qr_scanned_at.is_none());
+
+        // 1.75 kg is 7 pieces
+        let carton = ScannedCode::parse("(01)16130000000012(3103)001750(10)LPF-240115-001", &keys).unwrap();
+        assert_eq!(
+            delivery.scan_code(line_id, &carton, &products, &lots, user, &clock).unwrap(),
+            lots[0].id
+        );
+        assert!(delivery.lines[0].items[0].qr_scanned_at.is_some());
+        assert_eq!(delivery.status, DeliveryStatus::Loaded);
+    }
+}
@@ -1,7 +1,7 @@
 //! Domain events for event sourcing and sync
 
 use chrono::{DateTime, Utc};
-use manchengo_core::EntityId;
+use manchengo_core::{Clock, EntityId, Qty};
 use serde::{Deserialize, Serialize};
 
 /// Base trait for all domain events
@@ -37,19 +37,114 @@ impl EventEnvelope {
         user_id: EntityId,
         device_id: EntityId,
         version: i64,
+        clock: &dyn Clock,
     ) -> Result<Self, serde_json::Error> {
-        Ok(Self {
+        Ok(Self::from_parts(
+            event.aggregate_type(),
+            event.aggregate_id(),
+            event.event_type(),
+            serde_json::to_value(event)?,
+            user_id,
+            device_id,
+            version,
+            clock,
+        ))
+    }
+
+    /// Build an envelope for an event whose aggregate type is only known at runtime
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_parts(
+        aggregate_type: &str,
+        aggregate_id: EntityId,
+        event_type: &str,
+        payload: serde_json::Value,
+        user_id: EntityId,
+        device_id: EntityId,
+        version: i64,
+        clock: &dyn Clock,
+    ) -> Self {
+        Self {
             id: EntityId::new(),
-            aggregate_type: event.aggregate_type().to_string(),
-            aggregate_id: event.aggregate_id(),
-            event_type: event.event_type().to_string(),
-            payload: serde_json::to_value(event)?,
-            occurred_at: Utc::now(),
+            aggregate_type: aggregate_type.to_string(),
+            aggregate_id,
+            event_type: event_type.to_string(),
+            payload,
+            occurred_at: clock.now(),
             user_id,
             device_id,
             version,
             synced: false,
-        })
+        }
+    }
+}
+
+// ============================================================================
+// LIFECYCLE EVENTS (TOMBSTONES)
+// ============================================================================
+
+pub mod lifecycle {
+    use super::*;
+
+    pub const ENTITY_DELETED: &str = "EntityDeleted";
+    pub const ENTITY_RESTORED: &str = "EntityRestored";
+
+    /// Tombstone: entity moved to trash (propagates deletes across devices)
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct EntityDeleted {
+        pub entity_type: String,
+        pub entity_id: EntityId,
+        pub deleted_at: String,
+        pub deleted_by: EntityId,
+        pub reason: Option<String>,
+    }
+
+    impl EntityDeleted {
+        pub fn to_envelope(
+            &self,
+            device_id: EntityId,
+            version: i64,
+            clock: &dyn Clock,
+        ) -> Result<EventEnvelope, serde_json::Error> {
+            Ok(EventEnvelope::from_parts(
+                &self.entity_type,
+                self.entity_id,
+                ENTITY_DELETED,
+                serde_json::to_value(self)?,
+                self.deleted_by,
+                device_id,
+                version,
+                clock,
+            ))
+        }
+    }
+
+    /// Entity restored from trash
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct EntityRestored {
+        pub entity_type: String,
+        pub entity_id: EntityId,
+        pub restored_at: String,
+        pub restored_by: EntityId,
+    }
+
+    impl EntityRestored {
+        pub fn to_envelope(
+            &self,
+            device_id: EntityId,
+            version: i64,
+            clock: &dyn Clock,
+        ) -> Result<EventEnvelope, serde_json::Error> {
+            Ok(EventEnvelope::from_parts(
+                &self.entity_type,
+                self.entity_id,
+                ENTITY_RESTORED,
+                serde_json::to_value(self)?,
+                self.restored_by,
+                device_id,
+                version,
+                clock,
+            ))
+        }
     }
 }
 
@@ -67,7 +162,7 @@ pub mod stock {
         pub lot_number: String,
         pub product_id: EntityId,
         pub supplier_id: Option<EntityId>,
-        pub quantity: f64,
+        pub quantity: Qty,
         pub unit_cost_centimes: i64,
         pub reception_date: String,
         pub expiry_date: Option<String>,
@@ -82,8 +177,8 @@ pub mod stock {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct LotMpQuantityReduced {
         pub lot_id: EntityId,
-        pub quantity_before: f64,
-        pub quantity_after: f64,
+        pub quantity_before: Qty,
+        pub quantity_after: Qty,
         pub reason: String,
         pub reference_type: Option<String>,
         pub reference_id: Option<EntityId>,
@@ -115,7 +210,7 @@ pub mod stock {
         pub lot_number: String,
         pub product_id: EntityId,
         pub production_order_id: Option<EntityId>,
-        pub quantity: f64,
+        pub quantity: Qty,
         pub unit_cost_centimes: i64,
         pub production_date: String,
         pub expiry_date: Option<String>,
@@ -130,8 +225,8 @@ pub mod stock {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct LotPfQuantityReduced {
         pub lot_id: EntityId,
-        pub quantity_before: f64,
-        pub quantity_after: f64,
+        pub quantity_before: Qty,
+        pub quantity_after: Qty,
         pub reason: String,
         pub reference_type: Option<String>,
         pub reference_id: Option<EntityId>,
@@ -157,7 +252,7 @@ pub mod production {
         pub order_number: String,
         pub recipe_id: EntityId,
         pub product_pf_id: EntityId,
-        pub planned_quantity: f64,
+        pub planned_quantity: Qty,
         pub planned_date: String,
     }
 
@@ -195,7 +290,7 @@ pub mod production {
         pub order_id: EntityId,
         pub lot_mp_id: EntityId,
         pub product_mp_id: EntityId,
-        pub quantity: f64,
+        pub quantity: Qty,
         pub unit_cost_centimes: i64,
     }
 
@@ -208,7 +303,7 @@ pub mod production {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct ProductionOrderCompleted {
         pub order_id: EntityId,
-        pub actual_quantity: f64,
+        pub actual_quantity: Qty,
         pub lot_pf_id: EntityId,
         pub total_cost_centimes: i64,
         pub completed_at: String,
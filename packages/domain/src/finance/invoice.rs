@@ -1,7 +1,7 @@
 //! Invoice management with Algerian fiscal compliance
 
 use chrono::NaiveDate;
-use manchengo_core::{AlgerianTaxRates, AuditInfo, EntityId, Error, Money, Result, UnitOfMeasure};
+use manchengo_core::{AlgerianTaxRates, AmountInWords, AuditInfo, Clock, EntityId, Error, Money, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Invoice status
@@ -91,6 +91,7 @@ impl Invoice {
         client_id: EntityId,
         invoice_date: NaiveDate,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -108,7 +109,7 @@ impl Invoice {
             amount_paid: Money::zero(),
             status: InvoiceStatus::Draft,
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
@@ -122,6 +123,7 @@ impl Invoice {
         unit_price_ht: Money,
         tva_rate: f64,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != InvoiceStatus::Draft {
             return Err(Error::BusinessRule(
@@ -148,12 +150,12 @@ impl Invoice {
         });
 
         self.recalculate_totals();
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Validate the invoice (finalize for sending)
-    pub fn validate(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn validate(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != InvoiceStatus::Draft {
             return Err(Error::InvalidStateTransition {
                 entity: "Invoice".to_string(),
@@ -170,12 +172,12 @@ impl Invoice {
         }
 
         self.status = InvoiceStatus::Validated;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Record payment
-    pub fn record_payment(&mut self, amount: Money, user_id: EntityId) {
+    pub fn record_payment(&mut self, amount: Money, user_id: EntityId, clock: &dyn Clock) {
         self.amount_paid = self.amount_paid + amount;
 
         if self.amount_paid.centimes() >= self.total_ttc.centimes() {
@@ -185,7 +187,7 @@ impl Invoice {
             self.payment_status = InvoicePaymentStatus::Partial;
         }
 
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
     }
 
     /// Get remaining amount
@@ -195,6 +197,11 @@ impl Invoice {
         )
     }
 
+    /// Total TTC in words ("Arretee la presente facture a la somme de ...")
+    pub fn total_in_words(&self) -> AmountInWords {
+        AmountInWords::new(self.total_ttc)
+    }
+
     fn recalculate_totals(&mut self) {
         self.total_ht = self
             .lines
@@ -1,7 +1,7 @@
 //! Payment tracking
 
 use chrono::NaiveDate;
-use manchengo_core::{EntityId, Money};
+use manchengo_core::{AmountInWords, EntityId, Money};
 use serde::{Deserialize, Serialize};
 
 /// Payment method
@@ -124,6 +124,11 @@ impl Payment {
     pub fn is_validated(&self) -> bool {
         self.status == PaymentStatus::Validated
     }
+
+    /// Amount in words, printed on the receipt
+    pub fn amount_in_words(&self) -> AmountInWords {
+        AmountInWords::new(self.amount)
+    }
 }
 
 /// Payment allocation to invoices
@@ -1,7 +1,7 @@
 //! Production order management
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AuditInfo, EntityId, Error, Money, QrCodeData, QrEntityType, Result, UnitOfMeasure};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Money, QrCodeData, QrEntityType, QrKeyRing, Qty, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Production order status
@@ -47,8 +47,8 @@ pub struct ProductionOrder {
     pub product_pf_id: EntityId,
 
     // Quantities
-    pub planned_quantity: f64,
-    pub actual_quantity: Option<f64>,
+    pub planned_quantity: Qty,
+    pub actual_quantity: Option<Qty>,
     pub unit: UnitOfMeasure,
 
     // Dates
@@ -82,7 +82,7 @@ pub struct ProductionConsumption {
     pub production_order_id: EntityId,
     pub lot_mp_id: EntityId,
     pub product_mp_id: EntityId,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: UnitOfMeasure,
     pub unit_cost: Money,
     pub total_cost: Money,
@@ -91,24 +91,21 @@ pub struct ProductionConsumption {
 }
 
 impl ProductionOrder {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         order_number: String,
         recipe_id: EntityId,
         product_pf_id: EntityId,
-        planned_quantity: f64,
+        planned_quantity: Qty,
         unit: UnitOfMeasure,
         planned_date: NaiveDate,
         user_id: EntityId,
+        qr_keys: &QrKeyRing,
+        clock: &dyn Clock,
     ) -> Self {
         let id = EntityId::new();
 
-        let qr_data = QrCodeData {
-            entity_type: QrEntityType::Order,
-            entity_id: id,
-            reference: order_number.clone(),
-            expiry_date: None,
-            checksum: String::new(),
-        };
+        let qr_data = QrCodeData::new(QrEntityType::Order, id, order_number.clone(), None, qr_keys);
 
         Self {
             id,
@@ -128,12 +125,12 @@ impl ProductionOrder {
             consumptions: Vec::new(),
             qr_code: qr_data.encode(),
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
     /// Confirm the production order
-    pub fn confirm(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn confirm(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if !self.status.can_transition_to(ProductionOrderStatus::Confirmed) {
             return Err(Error::InvalidStateTransition {
                 entity: "ProductionOrder".to_string(),
@@ -143,12 +140,12 @@ impl ProductionOrder {
         }
 
         self.status = ProductionOrderStatus::Confirmed;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Start production
-    pub fn start(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn start(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if !self.status.can_transition_to(ProductionOrderStatus::InProgress) {
             return Err(Error::InvalidStateTransition {
                 entity: "ProductionOrder".to_string(),
@@ -158,20 +155,22 @@ impl ProductionOrder {
         }
 
         self.status = ProductionOrderStatus::InProgress;
-        self.started_at = Some(Utc::now());
-        self.audit.update(user_id);
+        self.started_at = Some(clock.now());
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Record MP consumption
+    #[allow(clippy::too_many_arguments)]
     pub fn record_consumption(
         &mut self,
         lot_mp_id: EntityId,
         product_mp_id: EntityId,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
         unit_cost: Money,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Result<()> {
         if self.status != ProductionOrderStatus::InProgress {
             return Err(Error::BusinessRule(
@@ -179,7 +178,7 @@ impl ProductionOrder {
             ));
         }
 
-        let total_cost = Money::from_centimes((quantity * unit_cost.centimes() as f64) as i64);
+        let total_cost = quantity.value_at(unit_cost);
 
         self.consumptions.push(ProductionConsumption {
             id: EntityId::new(),
@@ -190,19 +189,19 @@ impl ProductionOrder {
             unit,
             unit_cost,
             total_cost,
-            consumed_at: Utc::now(),
+            consumed_at: clock.now(),
             consumed_by: user_id,
         });
 
         self.total_mp_cost = self.total_mp_cost + total_cost;
         self.recalculate_total_cost();
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
 
         Ok(())
     }
 
     /// Complete production with output quantity
-    pub fn complete(&mut self, actual_quantity: f64, user_id: EntityId) -> Result<()> {
+    pub fn complete(&mut self, actual_quantity: Qty, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if !self.status.can_transition_to(ProductionOrderStatus::Completed) {
             return Err(Error::InvalidStateTransition {
                 entity: "ProductionOrder".to_string(),
@@ -211,7 +210,7 @@ impl ProductionOrder {
             });
         }
 
-        if actual_quantity <= 0.0 {
+        if !actual_quantity.is_positive() {
             return Err(Error::Validation {
                 field: "actual_quantity".to_string(),
                 message: "Output quantity must be positive".to_string(),
@@ -220,15 +219,15 @@ impl ProductionOrder {
 
         self.actual_quantity = Some(actual_quantity);
         self.status = ProductionOrderStatus::Completed;
-        self.completed_at = Some(Utc::now());
+        self.completed_at = Some(clock.now());
         self.recalculate_total_cost();
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
 
         Ok(())
     }
 
     /// Cancel order
-    pub fn cancel(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn cancel(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if !self.status.can_transition_to(ProductionOrderStatus::Cancelled) {
             return Err(Error::InvalidStateTransition {
                 entity: "ProductionOrder".to_string(),
@@ -244,15 +243,14 @@ impl ProductionOrder {
         }
 
         self.status = ProductionOrderStatus::Cancelled;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Calculate unit cost of finished product
     pub fn unit_cost(&self) -> Option<Money> {
-        self.actual_quantity.map(|qty| {
-            Money::from_centimes((self.total_cost.centimes() as f64 / qty) as i64)
-        })
+        self.actual_quantity
+            .and_then(|qty| qty.unit_price_of(self.total_cost))
     }
 
     fn recalculate_total_cost(&mut self) {
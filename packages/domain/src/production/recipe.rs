@@ -1,6 +1,6 @@
 //! Production recipes (Fiches techniques)
 
-use manchengo_core::{AuditInfo, EntityId, UnitOfMeasure};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Qty, Quantity, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Production recipe definition
@@ -10,7 +10,7 @@ pub struct Recipe {
     pub code: String,
     pub name: String,
     pub product_pf_id: EntityId,
-    pub output_quantity: f64,
+    pub output_quantity: Qty,
     pub output_unit: UnitOfMeasure,
     pub description: Option<String>,
     pub ingredients: Vec<RecipeIngredient>,
@@ -24,7 +24,7 @@ pub struct RecipeIngredient {
     pub id: EntityId,
     pub recipe_id: EntityId,
     pub product_mp_id: EntityId,
-    pub quantity: f64,
+    pub quantity: Qty,
     pub unit: UnitOfMeasure,
     pub is_optional: bool,
     pub notes: Option<String>,
@@ -35,9 +35,10 @@ impl Recipe {
         code: String,
         name: String,
         product_pf_id: EntityId,
-        output_quantity: f64,
+        output_quantity: Qty,
         output_unit: UnitOfMeasure,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -49,14 +50,14 @@ impl Recipe {
             description: None,
             ingredients: Vec::new(),
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
     pub fn add_ingredient(
         &mut self,
         product_mp_id: EntityId,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
     ) {
         self.ingredients.push(RecipeIngredient {
@@ -71,11 +72,53 @@ impl Recipe {
     }
 
     /// Scale ingredients for a given output quantity
-    pub fn scale_ingredients(&self, target_quantity: f64) -> Vec<(EntityId, f64, UnitOfMeasure)> {
-        let ratio = target_quantity / self.output_quantity;
-        self.ingredients
+    ///
+    /// The target is converted to the recipe output unit first, so a batch
+    /// planned in grams scales a recipe defined in kilograms correctly.
+    pub fn scale_ingredients(&self, target: Quantity) -> Result<Vec<(EntityId, Quantity)>> {
+        if !self.output_quantity.is_positive() {
+            return Err(Error::Validation {
+                field: "output_quantity".to_string(),
+                message: format!("Recette {}: quantite produite nulle", self.code),
+            });
+        }
+
+        let target = target.convert_to(self.output_unit)?;
+        let ratio = target.value / self.output_quantity.as_f64();
+
+        Ok(self
+            .ingredients
             .iter()
-            .map(|ing| (ing.product_mp_id, ing.quantity * ratio, ing.unit))
-            .collect()
+            .map(|ing| (ing.product_mp_id, Quantity::new(ing.quantity.as_f64() * ratio, ing.unit)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manchengo_core::SystemClock;
+
+    #[test]
+    fn test_scale_ingredients_converts_target_unit() {
+        let mut recipe = Recipe::new(
+            "REC-01".to_string(),
+            "Camembert".to_string(),
+            EntityId::new(),
+            Qty::units(10),
+            UnitOfMeasure::Kilogram,
+            EntityId::new(),
+            &SystemClock,
+        );
+        let lait = EntityId::new();
+        recipe.add_ingredient(lait, Qty::units(80), UnitOfMeasure::Litre);
+
+        let scaled = recipe
+            .scale_ingredients(Quantity::new(5000.0, UnitOfMeasure::Gram))
+            .unwrap();
+        assert_eq!(scaled[0].0, lait);
+        assert_eq!(scaled[0].1, Quantity::litre(40.0));
+
+        assert!(recipe.scale_ingredients(Quantity::litre(5.0)).is_err());
     }
 }
@@ -1,26 +1,39 @@
 //! Delivery orchestration service
 
-use manchengo_core::{EntityId, Money, Result};
+use chrono::{DateTime, Utc};
+use manchengo_core::{EntityId, Money, PackDefinition, Qty, Quantity, Result};
 use crate::delivery::Delivery;
-use crate::stock::{FifoLotSelectorPf, LotPf};
+use crate::stock::{FifoLotSelectorPf, LotPf, LotPick, LotSelectionStrategy, ReservationBook};
 
 /// Delivery operations
 pub struct DeliveryService;
 
 impl DeliveryService {
-    /// Select lots for delivery using FIFO
+    /// Select lots for delivery with the product strategy
+    ///
+    /// Only stock not held for a sales order is offered. Fails when the
+    /// lots cannot cover the quantity; each pick carries the reason it was
+    /// chosen.
+    #[allow(clippy::too_many_arguments)]
     pub fn select_lots_for_delivery(
         available_lots: &[LotPf],
+        reservations: &ReservationBook,
         product_id: EntityId,
-        required_quantity: f64,
-    ) -> Result<Vec<(EntityId, f64)>> {
-        let product_lots: Vec<_> = available_lots
-            .iter()
+        required: Quantity,
+        pack: &PackDefinition,
+        strategy: &LotSelectionStrategy,
+        manual: &[(EntityId, Qty)],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<LotPick>> {
+        let product_lots: Vec<_> = reservations
+            .unreserved_lots(available_lots, now)
+            .into_iter()
             .filter(|l| l.product_id == product_id)
-            .cloned()
             .collect();
 
-        FifoLotSelectorPf::select_lots(&product_lots, required_quantity)
+        let selection = FifoLotSelectorPf::select_lots_with(strategy, &product_lots, required, pack, manual, now.date_naive())?
+            .require_complete(&product_id.to_string())?;
+        Ok(selection.picks)
     }
 
     /// Calculate total weight for delivery
@@ -1,9 +1,9 @@
 //! Production orchestration service
 
 use chrono::NaiveDate;
-use manchengo_core::{EntityId, Money, Result, UnitOfMeasure};
+use manchengo_core::{EntityId, Money, Qty, Quantity, Result};
 use crate::production::{ProductionOrder, Recipe};
-use crate::stock::{LotMp, LotPf};
+use crate::stock::LotMp;
 use super::StockService;
 
 /// Production operations
@@ -13,19 +13,25 @@ impl ProductionService {
     /// Check if recipe ingredients are available
     pub fn check_ingredients_available(
         recipe: &Recipe,
-        planned_quantity: f64,
+        planned: Quantity,
         available_lots: &[LotMp],
-    ) -> Vec<(EntityId, f64, f64)> {
-        // Returns: (product_id, required, available)
-        let scaled = recipe.scale_ingredients(planned_quantity);
+    ) -> Result<Vec<(EntityId, Qty, Qty)>> {
+        // Returns: (product_id, required, available), in the lots' unit
+        let scaled = recipe.scale_ingredients(planned)?;
         let mut availability = Vec::new();
 
-        for (product_id, required, _unit) in scaled {
+        for (product_id, required) in scaled {
             let (available, _) = StockService::check_availability(available_lots, product_id);
-            availability.push((product_id, required, available));
+            let stock_unit = available_lots
+                .iter()
+                .find(|l| l.product_id == product_id)
+                .map(|l| l.unit)
+                .unwrap_or(required.unit);
+            let required = required.convert_to(stock_unit)?;
+            availability.push((product_id, required.to_qty(), available));
         }
 
-        availability
+        Ok(availability)
     }
 
     /// Check if production order can start
@@ -36,9 +42,9 @@ impl ProductionService {
     ) -> Result<bool> {
         let availability = Self::check_ingredients_available(
             recipe,
-            order.planned_quantity,
+            Quantity::new(order.planned_quantity.as_f64(), order.unit),
             available_lots,
-        );
+        )?;
 
         // All ingredients must be available
         Ok(availability.iter().all(|(_, req, avail)| avail >= req))
@@ -48,10 +54,10 @@ impl ProductionService {
     pub fn calculate_unit_cost(
         total_mp_cost: Money,
         additional_costs: Money,
-        output_quantity: f64,
+        output_quantity: Qty,
     ) -> Money {
         let total = total_mp_cost + additional_costs;
-        Money::from_centimes((total.centimes() as f64 / output_quantity) as i64)
+        output_quantity.unit_price_of(total).unwrap_or_default()
     }
 
     /// Generate lot number for production output
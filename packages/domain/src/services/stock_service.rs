@@ -1,6 +1,7 @@
 //! Stock management service
 
-use manchengo_core::{EntityId, Money, Result, UnitOfMeasure};
+use chrono::NaiveDate;
+use manchengo_core::{Clock, EntityId, Money, Qty, Quantity, Result};
 use crate::stock::{FifoLotSelector, LotMp, LotStatus, MovementType, ProductType, StockMovement};
 
 /// Stock management operations
@@ -9,13 +10,14 @@ pub struct StockService;
 impl StockService {
     /// Consume raw materials using FIFO
     ///
-    /// Returns list of consumptions: (lot_id, quantity, cost)
+    /// Returns list of consumptions: (lot_id, quantity, cost), quantities in lot units
     pub fn consume_mp_fifo(
         available_lots: &mut [LotMp],
         product_id: EntityId,
-        required_quantity: f64,
+        required: Quantity,
         user_id: EntityId,
-    ) -> Result<Vec<(EntityId, f64, Money)>> {
+        clock: &dyn Clock,
+    ) -> Result<Vec<(EntityId, Qty, Money)>> {
         // Filter lots for this product
         let product_lots: Vec<_> = available_lots
             .iter()
@@ -24,7 +26,7 @@ impl StockService {
             .collect();
 
         // Get FIFO selection
-        let selections = FifoLotSelector::select_lots(&product_lots, required_quantity)?;
+        let selections = FifoLotSelector::select_lots(&product_lots, required, clock.today())?;
 
         let mut consumptions = Vec::new();
 
@@ -35,8 +37,8 @@ impl StockService {
                 .find(|l| l.id == lot_id)
                 .expect("Lot should exist");
 
-            let cost = Money::from_centimes((quantity * lot.unit_cost.centimes() as f64) as i64);
-            lot.consume(quantity, user_id)?;
+            let cost = quantity.value_at(lot.unit_cost);
+            lot.consume(quantity, user_id, clock)?;
 
             consumptions.push((lot_id, quantity, cost));
         }
@@ -47,9 +49,10 @@ impl StockService {
     /// Create stock movement for lot consumption
     pub fn create_consumption_movement(
         lot: &LotMp,
-        quantity: f64,
+        quantity: Qty,
         movement_type: MovementType,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> StockMovement {
         StockMovement::exit(
             ProductType::Mp,
@@ -60,6 +63,7 @@ impl StockService {
             lot.unit,
             lot.quantity_remaining + quantity, // Before consumption
             user_id,
+            clock,
         )
     }
 
@@ -67,13 +71,13 @@ impl StockService {
     pub fn check_availability(
         lots: &[LotMp],
         product_id: EntityId,
-    ) -> (f64, i32) {
+    ) -> (Qty, i32) {
         let available: Vec<_> = lots
             .iter()
             .filter(|l| l.product_id == product_id && l.status == LotStatus::Available)
             .collect();
 
-        let total_qty: f64 = available.iter().map(|l| l.quantity_remaining).sum();
+        let total_qty: Qty = available.iter().map(|l| l.quantity_remaining).sum();
         let lot_count = available.len() as i32;
 
         (total_qty, lot_count)
@@ -83,9 +87,9 @@ impl StockService {
     pub fn get_expiring_lots(
         lots: &[LotMp],
         days: i32,
+        today: NaiveDate,
     ) -> Vec<&LotMp> {
-        let threshold = chrono::Utc::now().date_naive()
-            + chrono::Duration::days(days as i64);
+        let threshold = today + chrono::Duration::days(days as i64);
 
         lots.iter()
             .filter(|l| {
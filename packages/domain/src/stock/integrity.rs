@@ -0,0 +1,183 @@
+//! Stock ledger integrity checks
+//!
+//! A lot's `quantity_remaining` is a cached balance; the stock movements are
+//! the ledger. This module compares both and plans corrective actions.
+
+use manchengo_core::{EntityId, Qty};
+use serde::{Deserialize, Serialize};
+
+use super::{LotStatus, ProductType};
+
+/// Lot balance as stored vs. recomputed from its movements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotLedgerSnapshot {
+    pub lot_id: EntityId,
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub status: LotStatus,
+    pub quantity_remaining: Qty,
+    pub ledger_balance: Qty,
+    pub movements_count: i64,
+}
+
+impl LotLedgerSnapshot {
+    /// Difference between the stored quantity and the ledger balance
+    pub fn drift(&self) -> Qty {
+        self.quantity_remaining - self.ledger_balance
+    }
+}
+
+/// Kind of integrity issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerIssueKind {
+    /// Lot quantity differs from SUM(IN) - SUM(OUT)
+    BalanceMismatch,
+    /// Lot quantity or ledger balance below zero
+    NegativeBalance,
+    /// Lot fully consumed but still available/reserved
+    ZeroNotConsumed,
+    /// Movement referencing a lot or product that does not exist
+    OrphanMovement,
+}
+
+impl LedgerIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BalanceMismatch => "BALANCE_MISMATCH",
+            Self::NegativeBalance => "NEGATIVE_BALANCE",
+            Self::ZeroNotConsumed => "ZERO_NOT_CONSUMED",
+            Self::OrphanMovement => "ORPHAN_MOVEMENT",
+        }
+    }
+}
+
+/// Corrective action for a lot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerRepair {
+    /// Post an adjustment movement (positive = IN, negative = OUT)
+    AdjustLedger { quantity: Qty },
+    /// Reset a negative lot quantity to zero
+    ClampLotToZero,
+    /// Set lot status to CONSUMED
+    MarkConsumed,
+}
+
+/// Ledger integrity checker
+pub struct LedgerIntegrityChecker;
+
+impl LedgerIntegrityChecker {
+    /// Detect issues on a single lot
+    pub fn check_lot(snapshot: &LotLedgerSnapshot) -> Vec<LedgerIssueKind> {
+        let mut issues = Vec::new();
+
+        if !snapshot.drift().is_zero() {
+            issues.push(LedgerIssueKind::BalanceMismatch);
+        }
+
+        if snapshot.quantity_remaining.is_negative() || snapshot.ledger_balance.is_negative() {
+            issues.push(LedgerIssueKind::NegativeBalance);
+        }
+
+        if snapshot.quantity_remaining.is_zero() && snapshot.status.is_consumable() {
+            issues.push(LedgerIssueKind::ZeroNotConsumed);
+        }
+
+        issues
+    }
+
+    /// Plan the repairs bringing a lot back to a consistent state.
+    ///
+    /// The lot quantity (clamped at zero) is taken as the reference: the
+    /// ledger is adjusted to match it, never rewritten.
+    pub fn plan_repairs(snapshot: &LotLedgerSnapshot) -> Vec<LedgerRepair> {
+        let mut repairs = Vec::new();
+        let target = snapshot.quantity_remaining.max(Qty::zero());
+
+        if snapshot.quantity_remaining.is_negative() {
+            repairs.push(LedgerRepair::ClampLotToZero);
+        }
+
+        let adjustment = target - snapshot.ledger_balance;
+        if !adjustment.is_zero() {
+            repairs.push(LedgerRepair::AdjustLedger { quantity: adjustment });
+        }
+
+        if target.is_zero() && snapshot.status.is_consumable() {
+            repairs.push(LedgerRepair::MarkConsumed);
+        }
+
+        repairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(quantity_remaining: f64, ledger_balance: f64, status: LotStatus) -> LotLedgerSnapshot {
+        LotLedgerSnapshot {
+            lot_id: EntityId::new(),
+            product_type: ProductType::Mp,
+            product_id: EntityId::new(),
+            status,
+            quantity_remaining: Qty::from_f64(quantity_remaining),
+            ledger_balance: Qty::from_f64(ledger_balance),
+            movements_count: 2,
+        }
+    }
+
+    #[test]
+    fn test_consistent_lot_has_no_issue() {
+        let lot = snapshot(40.0, 40.0, LotStatus::Available);
+        assert!(LedgerIntegrityChecker::check_lot(&lot).is_empty());
+        assert!(LedgerIntegrityChecker::plan_repairs(&lot).is_empty());
+    }
+
+    #[test]
+    fn test_mismatch_adjusts_ledger_to_lot() {
+        let lot = snapshot(30.0, 45.0, LotStatus::Available);
+        assert_eq!(
+            LedgerIntegrityChecker::check_lot(&lot),
+            vec![LedgerIssueKind::BalanceMismatch]
+        );
+        assert_eq!(
+            LedgerIntegrityChecker::plan_repairs(&lot),
+            vec![LedgerRepair::AdjustLedger { quantity: Qty::units(-15) }]
+        );
+    }
+
+    #[test]
+    fn test_negative_lot_is_clamped() {
+        let lot = snapshot(-5.0, -5.0, LotStatus::Available);
+        let issues = LedgerIntegrityChecker::check_lot(&lot);
+        assert!(issues.contains(&LedgerIssueKind::NegativeBalance));
+
+        let repairs = LedgerIntegrityChecker::plan_repairs(&lot);
+        assert_eq!(
+            repairs,
+            vec![
+                LedgerRepair::ClampLotToZero,
+                LedgerRepair::AdjustLedger { quantity: Qty::units(5) },
+                LedgerRepair::MarkConsumed,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_lot_not_consumed() {
+        let lot = snapshot(0.0, 0.0, LotStatus::Available);
+        assert_eq!(
+            LedgerIntegrityChecker::check_lot(&lot),
+            vec![LedgerIssueKind::ZeroNotConsumed]
+        );
+        assert_eq!(
+            LedgerIntegrityChecker::plan_repairs(&lot),
+            vec![LedgerRepair::MarkConsumed]
+        );
+
+        let consumed = snapshot(0.0, 0.0, LotStatus::Consumed);
+        assert!(LedgerIntegrityChecker::check_lot(&consumed).is_empty());
+    }
+}
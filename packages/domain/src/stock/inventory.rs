@@ -0,0 +1,545 @@
+//! Physical inventory (stock-take) sessions
+//!
+//! A session freezes the theoretical quantity of every lot of a warehouse,
+//! then collects counts: counting -> review (variance report) -> approved.
+//! Counts come from a lot QR scan or a manual entry. A blind session hides
+//! the theoretical quantities from the counters. A first count too far from
+//! the theoretical quantity needs a second count, which is the one kept.
+//!
+//! Approval turns every variance into an ADJUSTMENT_PLUS or ADJUSTMENT_MINUS
+//! movement valued at the lot cost.
+
+use chrono::{DateTime, Utc};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Money, Qty, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{MovementType, ProductType};
+
+/// Inventory session status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InventoryStatus {
+    Counting,
+    Review,
+    Approved,
+    Cancelled,
+}
+
+impl InventoryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Counting => "COUNTING",
+            Self::Review => "REVIEW",
+            Self::Approved => "APPROVED",
+            Self::Cancelled => "CANCELLED",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "COUNTING" => Some(Self::Counting),
+            "REVIEW" => Some(Self::Review),
+            "APPROVED" => Some(Self::Approved),
+            "CANCELLED" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// How a quantity was counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CountMethod {
+    /// Lot QR code scanned
+    Scan,
+    /// Quantity typed in
+    Manual,
+}
+
+impl CountMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scan => "SCAN",
+            Self::Manual => "MANUAL",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "SCAN" => Some(Self::Scan),
+            "MANUAL" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Lot as frozen when the session opens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventorySnapshotLot {
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub location_id: Option<EntityId>,
+    pub quantity: Qty,
+    pub unit_cost: Money,
+}
+
+/// One lot of the count sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryLine {
+    pub id: EntityId,
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub location_id: Option<EntityId>,
+    /// Quantity in stock when the session opened
+    pub quantity_theoretical: Qty,
+    pub unit_cost: Money,
+    pub first_count: Option<Qty>,
+    pub first_count_method: Option<CountMethod>,
+    pub first_counted_by: Option<EntityId>,
+    pub second_count: Option<Qty>,
+    pub second_count_method: Option<CountMethod>,
+    pub second_counted_by: Option<EntityId>,
+}
+
+impl InventoryLine {
+    /// Quantity retained: the second count when there is one
+    pub fn counted(&self) -> Option<Qty> {
+        self.second_count.or(self.first_count)
+    }
+
+    /// Counted minus theoretical, zero until counted
+    pub fn variance(&self) -> Qty {
+        match self.counted() {
+            Some(counted) => counted - self.quantity_theoretical,
+            None => Qty::zero(),
+        }
+    }
+
+    /// Variance valued at the lot cost
+    pub fn variance_value(&self) -> Money {
+        self.variance().value_at(self.unit_cost)
+    }
+
+    /// Whether the first count is off by more than `threshold_pct` percent
+    /// and no second count was made yet
+    ///
+    /// Any variance on a lot expected empty counts as large.
+    pub fn needs_second_count(&self, threshold_pct: f64) -> bool {
+        let Some(first) = self.first_count else {
+            return false;
+        };
+        if self.second_count.is_some() {
+            return false;
+        }
+        let variance = (first - self.quantity_theoretical).abs();
+        if self.quantity_theoretical.is_zero() {
+            return variance.is_positive();
+        }
+        variance.as_f64() * 100.0 / self.quantity_theoretical.as_f64() > threshold_pct
+    }
+}
+
+/// Variance of one lot, for the report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryVariance {
+    pub line_id: EntityId,
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub quantity_theoretical: Qty,
+    pub quantity_counted: Qty,
+    pub variance: Qty,
+    pub value: Money,
+}
+
+/// Variance report of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryVarianceReport {
+    /// Lots whose count differs from the theoretical quantity
+    pub variances: Vec<InventoryVariance>,
+    pub lots_counted: usize,
+    pub lots_uncounted: usize,
+    /// Value found in excess
+    pub value_gain: Money,
+    /// Value missing, as a positive amount
+    pub value_loss: Money,
+}
+
+impl InventoryVarianceReport {
+    pub fn net_value(&self) -> Money {
+        Money::from_centimes(self.value_gain.centimes() - self.value_loss.centimes())
+    }
+}
+
+/// Adjustment to post for an approved variance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryAdjustment {
+    pub line_id: EntityId,
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub lot_id: EntityId,
+    pub movement_type: MovementType,
+    /// Always positive; the movement type carries the direction
+    pub quantity: Qty,
+    pub unit_cost: Money,
+}
+
+/// Outcome of recording a count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CountOutcome {
+    /// Count retained
+    Recorded,
+    /// First count too far off, a second count is required
+    SecondCountRequired,
+}
+
+/// Physical inventory session of one warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventorySession {
+    pub id: EntityId,
+    pub reference: String,
+    pub warehouse_id: EntityId,
+    pub status: InventoryStatus,
+    /// Counters do not see theoretical quantities
+    pub blind: bool,
+    /// Variance, in percent of the theoretical quantity, above which a
+    /// second count is required
+    pub recount_threshold_pct: f64,
+    pub lines: Vec<InventoryLine>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub approved_by: Option<EntityId>,
+    pub notes: Option<String>,
+    pub audit: AuditInfo,
+}
+
+impl InventorySession {
+    /// Open a session on the lots frozen in `snapshot`
+    pub fn open(
+        reference: String,
+        warehouse_id: EntityId,
+        snapshot: Vec<InventorySnapshotLot>,
+        blind: bool,
+        recount_threshold_pct: f64,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        if !(0.0..=100.0).contains(&recount_threshold_pct) {
+            return Err(Error::Validation {
+                field: "recount_threshold_pct".to_string(),
+                message: format!("Seuil de recomptage invalide: {}%", recount_threshold_pct),
+            });
+        }
+        if snapshot.is_empty() {
+            return Err(Error::BusinessRule("Aucun lot a inventorier dans l'entrepot".to_string()));
+        }
+
+        let lines = snapshot
+            .into_iter()
+            .map(|lot| InventoryLine {
+                id: EntityId::new(),
+                product_type: lot.product_type,
+                product_id: lot.product_id,
+                lot_id: lot.lot_id,
+                lot_number: lot.lot_number,
+                location_id: lot.location_id,
+                quantity_theoretical: lot.quantity,
+                unit_cost: lot.unit_cost,
+                first_count: None,
+                first_count_method: None,
+                first_counted_by: None,
+                second_count: None,
+                second_count_method: None,
+                second_counted_by: None,
+            })
+            .collect();
+
+        Ok(Self {
+            id: EntityId::new(),
+            reference,
+            warehouse_id,
+            status: InventoryStatus::Counting,
+            blind,
+            recount_threshold_pct,
+            lines,
+            submitted_at: None,
+            approved_at: None,
+            approved_by: None,
+            notes: None,
+            audit: AuditInfo::new(user_id, clock),
+        })
+    }
+
+    /// Record the count of a lot
+    ///
+    /// The first count of a lot is retained unless it is off by more than
+    /// the session threshold; the lot then takes one second count, which is
+    /// final.
+    pub fn record_count(
+        &mut self,
+        lot_id: EntityId,
+        quantity: Qty,
+        method: CountMethod,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<CountOutcome> {
+        if self.status != InventoryStatus::Counting {
+            return Err(Error::BusinessRule(format!(
+                "Inventaire {} clos au comptage",
+                self.reference
+            )));
+        }
+        if quantity.is_negative() {
+            return Err(Error::Validation {
+                field: "quantity".to_string(),
+                message: "Quantite comptee negative".to_string(),
+            });
+        }
+
+        let threshold = self.recount_threshold_pct;
+        let line = self
+            .lines
+            .iter_mut()
+            .find(|l| l.lot_id == lot_id)
+            .ok_or_else(|| Error::BusinessRule(format!("Lot {} hors de l'inventaire {}", lot_id, self.reference)))?;
+
+        if line.first_count.is_none() {
+            line.first_count = Some(quantity);
+            line.first_count_method = Some(method);
+            line.first_counted_by = Some(user_id);
+        } else if line.needs_second_count(threshold) {
+            line.second_count = Some(quantity);
+            line.second_count_method = Some(method);
+            line.second_counted_by = Some(user_id);
+        } else {
+            return Err(Error::BusinessRule(format!("Lot {} deja compte", line.lot_number)));
+        }
+
+        let outcome = if line.needs_second_count(threshold) {
+            CountOutcome::SecondCountRequired
+        } else {
+            CountOutcome::Recorded
+        };
+        self.audit.update(user_id, clock);
+        Ok(outcome)
+    }
+
+    /// Close counting and hand the variance report to the manager
+    ///
+    /// Every lot must be counted (zero for a lot not found) and every
+    /// second count done.
+    pub fn submit(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.transition(InventoryStatus::Counting, InventoryStatus::Review)?;
+
+        let uncounted = self.lines.iter().filter(|l| l.counted().is_none()).count();
+        if uncounted > 0 {
+            return Err(Error::BusinessRule(format!("{} lot(s) non compte(s)", uncounted)));
+        }
+        if let Some(line) = self.lines.iter().find(|l| l.needs_second_count(self.recount_threshold_pct)) {
+            return Err(Error::BusinessRule(format!("Lot {}: second comptage requis", line.lot_number)));
+        }
+
+        self.status = InventoryStatus::Review;
+        self.submitted_at = Some(clock.now());
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Send the session back to counting
+    pub fn reopen(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.transition(InventoryStatus::Review, InventoryStatus::Counting)?;
+        self.status = InventoryStatus::Counting;
+        self.submitted_at = None;
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Approve the variances
+    ///
+    /// Returns the adjustments to post, one per lot with a variance.
+    pub fn approve(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<Vec<InventoryAdjustment>> {
+        self.transition(InventoryStatus::Review, InventoryStatus::Approved)?;
+
+        let adjustments = self
+            .lines
+            .iter()
+            .filter(|l| !l.variance().is_zero())
+            .map(|l| InventoryAdjustment {
+                line_id: l.id,
+                product_type: l.product_type,
+                product_id: l.product_id,
+                lot_id: l.lot_id,
+                movement_type: if l.variance().is_positive() {
+                    MovementType::AdjustmentPlus
+                } else {
+                    MovementType::AdjustmentMinus
+                },
+                quantity: l.variance().abs(),
+                unit_cost: l.unit_cost,
+            })
+            .collect();
+
+        self.status = InventoryStatus::Approved;
+        self.approved_at = Some(clock.now());
+        self.approved_by = Some(user_id);
+        self.audit.update(user_id, clock);
+        Ok(adjustments)
+    }
+
+    /// Cancel a session not yet approved
+    pub fn cancel(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        if !matches!(self.status, InventoryStatus::Counting | InventoryStatus::Review) {
+            return Err(Error::InvalidStateTransition {
+                entity: "InventorySession".to_string(),
+                from: self.status.as_str().to_string(),
+                to: InventoryStatus::Cancelled.as_str().to_string(),
+            });
+        }
+        self.status = InventoryStatus::Cancelled;
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Variances valued at lot cost
+    pub fn variance_report(&self) -> InventoryVarianceReport {
+        let mut value_gain = Money::zero();
+        let mut value_loss = Money::zero();
+        let mut variances = Vec::new();
+        for line in &self.lines {
+            let Some(counted) = line.counted() else {
+                continue;
+            };
+            if line.variance().is_zero() {
+                continue;
+            }
+            let value = line.variance_value();
+            if value.is_positive() {
+                value_gain = value_gain + value;
+            } else {
+                value_loss = value_loss + Money::from_centimes(-value.centimes());
+            }
+            variances.push(InventoryVariance {
+                line_id: line.id,
+                lot_id: line.lot_id,
+                lot_number: line.lot_number.clone(),
+                quantity_theoretical: line.quantity_theoretical,
+                quantity_counted: counted,
+                variance: line.variance(),
+                value,
+            });
+        }
+
+        let lots_counted = self.lines.iter().filter(|l| l.counted().is_some()).count();
+        InventoryVarianceReport {
+            variances,
+            lots_counted,
+            lots_uncounted: self.lines.len() - lots_counted,
+            value_gain,
+            value_loss,
+        }
+    }
+
+    fn transition(&self, from: InventoryStatus, to: InventoryStatus) -> Result<()> {
+        if self.status != from {
+            return Err(Error::InvalidStateTransition {
+                entity: "InventorySession".to_string(),
+                from: self.status.as_str().to_string(),
+                to: to.as_str().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use manchengo_core::FixedClock;
+
+    fn test_clock() -> FixedClock {
+        FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+    }
+
+    fn snapshot_lot(number: &str, units: i64, cost: i64) -> InventorySnapshotLot {
+        InventorySnapshotLot {
+            product_type: ProductType::Pf,
+            product_id: EntityId::new(),
+            lot_id: EntityId::new(),
+            lot_number: number.to_string(),
+            location_id: None,
+            quantity: Qty::units(units),
+            unit_cost: Money::from_centimes(cost),
+        }
+    }
+
+    fn lot_id_of(session: &InventorySession, number: &str) -> EntityId {
+        session.lines.iter().find(|l| l.lot_number == number).unwrap().lot_id
+    }
+
+    #[test]
+    fn test_counts_variance_report_and_approval() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let lots = vec![snapshot_lot("A", 100, 500), snapshot_lot("B", 50, 1_000), snapshot_lot("C", 20, 200)];
+        let (a, b, c) = (lots[0].lot_id, lots[1].lot_id, lots[2].lot_id);
+        let mut session =
+            InventorySession::open("INV-2024-000001".to_string(), EntityId::new(), lots, true, 10.0, user, &clock).unwrap();
+
+        assert_eq!(session.record_count(a, Qty::units(98), CountMethod::Scan, user, &clock).unwrap(), CountOutcome::Recorded);
+        assert_eq!(session.record_count(c, Qty::units(20), CountMethod::Manual, user, &clock).unwrap(), CountOutcome::Recorded);
+        assert!(session.record_count(a, Qty::units(97), CountMethod::Scan, user, &clock).is_err());
+        assert!(session.submit(user, &clock).is_err());
+
+        // 40 for 50 is 20% off: counted again
+        assert_eq!(
+            session.record_count(b, Qty::units(40), CountMethod::Manual, user, &clock).unwrap(),
+            CountOutcome::SecondCountRequired
+        );
+        assert!(session.submit(user, &clock).is_err());
+        assert_eq!(session.record_count(b, Qty::units(53), CountMethod::Scan, user, &clock).unwrap(), CountOutcome::Recorded);
+
+        let report = session.variance_report();
+        assert_eq!(report.variances.len(), 2);
+        assert_eq!(report.value_loss, Money::from_centimes(1_000));
+        assert_eq!(report.value_gain, Money::from_centimes(3_000));
+        assert_eq!(report.net_value(), Money::from_centimes(2_000));
+
+        session.submit(user, &clock).unwrap();
+        let adjustments = session.approve(user, &clock).unwrap();
+        assert_eq!(adjustments.len(), 2);
+        let minus = adjustments.iter().find(|a| a.lot_id == lot_id_of(&session, "A")).unwrap();
+        assert_eq!(minus.movement_type, MovementType::AdjustmentMinus);
+        assert_eq!(minus.quantity, Qty::units(2));
+        let plus = adjustments.iter().find(|a| a.lot_id == lot_id_of(&session, "B")).unwrap();
+        assert_eq!(plus.movement_type, MovementType::AdjustmentPlus);
+        assert_eq!(plus.quantity, Qty::units(3));
+        assert_eq!(session.status, InventoryStatus::Approved);
+        assert!(session.cancel(user, &clock).is_err());
+    }
+
+    #[test]
+    fn test_lot_expected_empty_needs_second_count() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let lots = vec![snapshot_lot("EMPTY", 0, 500)];
+        let lot_id = lots[0].lot_id;
+        let mut session =
+            InventorySession::open("INV-2024-000002".to_string(), EntityId::new(), lots, false, 5.0, user, &clock).unwrap();
+
+        assert_eq!(
+            session.record_count(lot_id, Qty::units(1), CountMethod::Manual, user, &clock).unwrap(),
+            CountOutcome::SecondCountRequired
+        );
+        session.record_count(lot_id, Qty::zero(), CountMethod::Manual, user, &clock).unwrap();
+        session.submit(user, &clock).unwrap();
+        session.reopen(user, &clock).unwrap();
+        assert!(session.record_count(lot_id, Qty::units(1), CountMethod::Manual, user, &clock).is_err());
+        session.submit(user, &clock).unwrap();
+        assert!(session.approve(user, &clock).unwrap().is_empty());
+    }
+}
@@ -1,9 +1,11 @@
 //! Raw material lot management with FIFO support
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AuditInfo, EntityId, Error, Money, QrCodeData, QrEntityType, Result, UnitOfMeasure};
+use manchengo_core::{AuditInfo, Clock, CurrencyAmount, EntityId, Error, ExchangeRate, Money, QrCodeData, QrEntityType, QrKeyRing, Qty, Quantity, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
+use super::{Location, LotCandidate, LotSelection, LotSelectionStrategy};
+
 /// Status of a raw material lot
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -52,8 +54,8 @@ pub struct LotMp {
     pub supplier_id: Option<EntityId>,
 
     // Quantities
-    pub quantity_initial: f64,
-    pub quantity_remaining: f64,
+    pub quantity_initial: Qty,
+    pub quantity_remaining: Qty,
     pub unit: UnitOfMeasure,
 
     // Dates
@@ -61,15 +63,29 @@ pub struct LotMp {
     pub production_date: Option<NaiveDate>,
     pub expiry_date: Option<NaiveDate>,
 
-    // Cost
+    // Cost (DZD)
     pub unit_cost: Money,
     pub total_cost: Money,
 
+    /// Supplier unit price in its own currency, for imported lots
+    #[serde(default)]
+    pub purchase_unit_cost: Option<CurrencyAmount>,
+    /// Rate used to convert `purchase_unit_cost` into `unit_cost`
+    #[serde(default)]
+    pub exchange_rate: Option<ExchangeRate>,
+
     // Traceability
     pub supplier_lot_number: Option<String>,
     pub supplier_bl_number: Option<String>,
     pub bl_photo_path: Option<String>,
 
+    // Storage (None on lots recorded before warehouses)
+    #[serde(default)]
+    pub warehouse_id: Option<EntityId>,
+    /// Bin location, `None` until put away
+    #[serde(default)]
+    pub location_id: Option<EntityId>,
+
     // Status
     pub status: LotStatus,
     pub blocked_reason: Option<String>,
@@ -84,26 +100,22 @@ pub struct LotMp {
 
 impl LotMp {
     /// Create a new raw material lot
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lot_number: String,
         product_id: EntityId,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
         unit_cost: Money,
         reception_date: NaiveDate,
         user_id: EntityId,
+        qr_keys: &QrKeyRing,
+        clock: &dyn Clock,
     ) -> Self {
         let id = EntityId::new();
-        let total_cost = Money::from_centimes((quantity * unit_cost.centimes() as f64) as i64);
+        let total_cost = quantity.value_at(unit_cost);
 
-        // Generate QR code data
-        let qr_data = QrCodeData {
-            entity_type: QrEntityType::LotMp,
-            entity_id: id,
-            reference: lot_number.clone(),
-            expiry_date: None,
-            checksum: String::new(), // Will be calculated
-        };
+        let qr_data = QrCodeData::new(QrEntityType::LotMp, id, lot_number.clone(), None, qr_keys);
 
         Self {
             id,
@@ -118,35 +130,98 @@ impl LotMp {
             expiry_date: None,
             unit_cost,
             total_cost,
+            purchase_unit_cost: None,
+            exchange_rate: None,
             supplier_lot_number: None,
             supplier_bl_number: None,
             bl_photo_path: None,
+            warehouse_id: None,
+            location_id: None,
             status: LotStatus::Available,
             blocked_reason: None,
             qr_code: qr_data.encode(),
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
+    /// Set the expiry date and re-sign the QR code so the label carries it
+    pub fn set_expiry_date(
+        &mut self,
+        expiry_date: Option<NaiveDate>,
+        qr_keys: &QrKeyRing,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) {
+        self.expiry_date = expiry_date;
+        self.qr_code = QrCodeData::new(QrEntityType::LotMp, self.id, self.lot_number.clone(), expiry_date, qr_keys).encode();
+        self.audit.update(user_id, clock);
+    }
+
+    /// Cost a lot bought in foreign currency, converted at the reception-date rate
+    pub fn set_purchase_cost(
+        &mut self,
+        unit_price: CurrencyAmount,
+        rate: Option<ExchangeRate>,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let unit_cost = match rate {
+            _ if unit_price.currency.is_local() => Money::from_centimes(unit_price.amount),
+            Some(rate) if rate.rate_date <= self.reception_date => rate.convert(unit_price)?,
+            Some(rate) => {
+                return Err(Error::Validation {
+                    field: "exchange_rate".to_string(),
+                    message: format!(
+                        "Taux du {} posterieur a la reception du {}",
+                        rate.rate_date, self.reception_date
+                    ),
+                })
+            }
+            None => {
+                return Err(Error::Validation {
+                    field: "exchange_rate".to_string(),
+                    message: format!("Taux {} manquant", unit_price.currency),
+                })
+            }
+        };
+
+        self.unit_cost = unit_cost;
+        self.total_cost = self.quantity_initial.value_at(unit_cost);
+        self.purchase_unit_cost = (!unit_price.currency.is_local()).then_some(unit_price);
+        self.exchange_rate = rate.filter(|_| !unit_price.currency.is_local());
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Place the lot in a location of its warehouse (put-away, internal move)
+    pub fn put_away(&mut self, location: &Location, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        let warehouse_id = *self.warehouse_id.get_or_insert(location.warehouse_id);
+        location.accepts(warehouse_id)?;
+
+        self.location_id = Some(location.id);
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
     /// Check if lot is expired
-    pub fn is_expired(&self) -> bool {
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
         if let Some(expiry) = self.expiry_date {
-            expiry < chrono::Utc::now().date_naive()
+            expiry < today
         } else {
             false
         }
     }
 
     /// Check if lot can be consumed
-    pub fn can_consume(&self, quantity: f64) -> bool {
+    pub fn can_consume(&self, quantity: Qty, today: NaiveDate) -> bool {
         self.status.is_consumable()
-            && !self.is_expired()
+            && !self.is_expired(today)
             && self.quantity_remaining >= quantity
     }
 
     /// Consume quantity from lot (FIFO)
-    pub fn consume(&mut self, quantity: f64, user_id: EntityId) -> Result<f64> {
+    pub fn consume(&mut self, quantity: Qty, user_id: EntityId, clock: &dyn Clock) -> Result<Qty> {
         if !self.status.is_consumable() {
             return Err(Error::BusinessRule(format!(
                 "Lot {} cannot be consumed (status: {:?})",
@@ -154,7 +229,7 @@ impl LotMp {
             )));
         }
 
-        if self.is_expired() {
+        if self.is_expired(clock.today()) {
             return Err(Error::LotExpired {
                 lot_id: self.id.to_string(),
                 expiry_date: self.expiry_date.map(|d| d.to_string()).unwrap_or_default(),
@@ -164,15 +239,15 @@ impl LotMp {
         if quantity > self.quantity_remaining {
             return Err(Error::InsufficientStock {
                 product: self.lot_number.clone(),
-                required: quantity,
-                available: self.quantity_remaining,
+                required: quantity.as_f64(),
+                available: self.quantity_remaining.as_f64(),
             });
         }
 
         self.quantity_remaining -= quantity;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
 
-        if self.quantity_remaining <= 0.0 {
+        if self.quantity_remaining.is_zero() {
             self.status = LotStatus::Consumed;
         }
 
@@ -180,7 +255,7 @@ impl LotMp {
     }
 
     /// Block lot (quality issue, etc.)
-    pub fn block(&mut self, reason: String, user_id: EntityId) -> Result<()> {
+    pub fn block(&mut self, reason: String, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status == LotStatus::Consumed {
             return Err(Error::InvalidStateTransition {
                 entity: "LotMp".to_string(),
@@ -191,31 +266,31 @@ impl LotMp {
 
         self.status = LotStatus::Blocked;
         self.blocked_reason = Some(reason);
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Unblock lot
-    pub fn unblock(&mut self, user_id: EntityId) -> Result<()> {
+    pub fn unblock(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status != LotStatus::Blocked {
             return Err(Error::BusinessRule(
                 "Only blocked lots can be unblocked".to_string(),
             ));
         }
 
-        self.status = if self.quantity_remaining > 0.0 {
+        self.status = if self.quantity_remaining.is_positive() {
             LotStatus::Available
         } else {
             LotStatus::Consumed
         };
         self.blocked_reason = None;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Calculate remaining value
     pub fn remaining_value(&self) -> Money {
-        Money::from_centimes((self.quantity_remaining * self.unit_cost.centimes() as f64) as i64)
+        self.quantity_remaining.value_at(self.unit_cost)
     }
 }
 
@@ -223,91 +298,143 @@ impl LotMp {
 pub struct FifoLotSelector;
 
 impl FifoLotSelector {
+    /// FIFO selection among the lots stored in one warehouse
+    pub fn select_lots_in_warehouse(
+        available_lots: &[LotMp],
+        warehouse_id: EntityId,
+        required: Quantity,
+        today: NaiveDate,
+    ) -> Result<Vec<(EntityId, Qty)>> {
+        let lots: Vec<LotMp> = available_lots
+            .iter()
+            .filter(|lot| lot.warehouse_id == Some(warehouse_id))
+            .cloned()
+            .collect();
+        Self::select_lots(&lots, required, today)
+    }
+
     /// Select lots to consume in FIFO order
-    /// Returns list of (lot_id, quantity_to_consume)
+    /// Returns list of (lot_id, quantity_to_consume), in each lot's unit
     pub fn select_lots(
         available_lots: &[LotMp],
-        required_quantity: f64,
-    ) -> Result<Vec<(EntityId, f64)>> {
-        let mut remaining = required_quantity;
-        let mut selections = Vec::new();
+        required: Quantity,
+        today: NaiveDate,
+    ) -> Result<Vec<(EntityId, Qty)>> {
+        let selection = Self::select_lots_with(&LotSelectionStrategy::Fifo, available_lots, required, &[], today)?
+            .require_complete("Multiple lots")?;
+        Ok(selection.picks.into_iter().map(|p| (p.lot_id, p.quantity)).collect())
+    }
 
-        // Sort by reception date (FIFO), then by expiry date
-        let mut sorted_lots: Vec<_> = available_lots
+    /// Select lots with the product strategy
+    ///
+    /// Picks are returned in each lot's unit; `manual` picks are in the
+    /// requested unit.
+    pub fn select_lots_with(
+        strategy: &LotSelectionStrategy,
+        available_lots: &[LotMp],
+        required: Quantity,
+        manual: &[(EntityId, Qty)],
+        today: NaiveDate,
+    ) -> Result<LotSelection> {
+        let consumable: Vec<&LotMp> = available_lots
             .iter()
-            .filter(|lot| lot.can_consume(0.01)) // Any consumable amount
+            .filter(|lot| lot.status.is_consumable())
             .collect();
 
-        sorted_lots.sort_by(|a, b| {
-            a.reception_date
-                .cmp(&b.reception_date)
-                .then_with(|| a.expiry_date.cmp(&b.expiry_date))
-        });
-
-        for lot in sorted_lots {
-            if remaining <= 0.0 {
-                break;
-            }
-
-            let consume = lot.quantity_remaining.min(remaining);
-            selections.push((lot.id, consume));
-            remaining -= consume;
-        }
-
-        if remaining > 0.001 {
-            // Small tolerance for floating point
-            return Err(Error::InsufficientStock {
-                product: "Multiple lots".to_string(),
-                required: required_quantity,
-                available: required_quantity - remaining,
+        // Compare in the requested unit, consume in the lot unit
+        let mut candidates = Vec::with_capacity(consumable.len());
+        for lot in &consumable {
+            candidates.push(LotCandidate {
+                lot_id: lot.id,
+                lot_number: lot.lot_number.clone(),
+                entry_date: lot.reception_date,
+                expiry_date: lot.expiry_date,
+                quantity: Quantity::new(lot.quantity_remaining.as_f64(), lot.unit)
+                    .convert_to(required.unit)?
+                    .to_qty(),
             });
         }
 
-        Ok(selections)
+        let mut selection = strategy.select(&candidates, required.to_qty(), manual, today)?;
+        for pick in &mut selection.picks {
+            let lot = consumable.iter().find(|l| l.id == pick.lot_id).expect("picked lot is a candidate");
+            pick.quantity = Quantity::new(pick.quantity.as_f64(), required.unit)
+                .convert_to(lot.unit)?
+                .to_qty()
+                .min(lot.quantity_remaining);
+        }
+        Ok(selection)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stock::LocationType;
+    use manchengo_core::{FixedClock, OffsetClock, SharedClock};
+    use std::sync::Arc;
+
+    fn test_keys() -> QrKeyRing {
+        QrKeyRing::new(vec![manchengo_core::QrKey::new("T1", b"test-secret-0123456789").unwrap()]).unwrap()
+    }
+
+    fn test_clock() -> FixedClock {
+        FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap())
+    }
 
     fn create_test_lot(quantity: f64, reception_date: NaiveDate) -> LotMp {
         LotMp::new(
             format!("LOT-{}", reception_date),
             EntityId::new(),
-            quantity,
+            Qty::from_f64(quantity),
             UnitOfMeasure::Kilogram,
             Money::from_dzd(100.0),
             reception_date,
             EntityId::new(),
+            &test_keys(),
+            &test_clock(),
         )
     }
 
     #[test]
     fn test_lot_creation() {
         let lot = create_test_lot(100.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
-        assert_eq!(lot.quantity_remaining, 100.0);
+        assert_eq!(lot.quantity_remaining, Qty::from_f64(100.0));
         assert_eq!(lot.status, LotStatus::Available);
+        assert!(lot.qr_code.starts_with("MCG2:LMP:"));
+    }
+
+    #[test]
+    fn test_expiry_date_signed_into_qr() {
+        let keys = test_keys();
+        let mut lot = create_test_lot(10.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let expiry = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+        lot.set_expiry_date(Some(expiry), &keys, EntityId::new(), &test_clock());
+
+        let qr = QrCodeData::decode(&lot.qr_code, &keys).unwrap();
+        assert_eq!(qr.expiry_date, Some(expiry));
+        assert_eq!(qr.entity_id, lot.id);
     }
 
     #[test]
     fn test_lot_consume() {
         let mut lot = create_test_lot(100.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
         let user = EntityId::new();
+        let clock = test_clock();
 
-        let remaining = lot.consume(30.0, user).unwrap();
-        assert_eq!(remaining, 70.0);
+        let remaining = lot.consume(Qty::from_f64(30.0), user, &clock).unwrap();
+        assert_eq!(remaining, Qty::from_f64(70.0));
         assert_eq!(lot.status, LotStatus::Available);
 
-        let remaining = lot.consume(70.0, user).unwrap();
-        assert_eq!(remaining, 0.0);
+        let remaining = lot.consume(Qty::from_f64(70.0), user, &clock).unwrap();
+        assert_eq!(remaining, Qty::zero());
         assert_eq!(lot.status, LotStatus::Consumed);
     }
 
     #[test]
     fn test_lot_consume_insufficient() {
         let mut lot = create_test_lot(50.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
-        let result = lot.consume(100.0, EntityId::new());
+        let result = lot.consume(Qty::from_f64(100.0), EntityId::new(), &test_clock());
         assert!(result.is_err());
     }
 
@@ -319,13 +446,126 @@ mod tests {
             create_test_lot(40.0, NaiveDate::from_ymd_opt(2024, 1, 20).unwrap()),
         ];
 
-        let selections = FifoLotSelector::select_lots(&lots, 70.0).unwrap();
+        let selections = FifoLotSelector::select_lots(&lots, Quantity::kg(70.0), test_clock().today()).unwrap();
 
         // Should select from oldest first (Jan 10: 30kg, then Jan 15: 40kg)
         assert_eq!(selections.len(), 2);
         assert_eq!(selections[0].0, lots[1].id); // Jan 10 lot
-        assert_eq!(selections[0].1, 30.0);
+        assert_eq!(selections[0].1, Qty::from_f64(30.0));
         assert_eq!(selections[1].0, lots[0].id); // Jan 15 lot
-        assert_eq!(selections[1].1, 40.0);
+        assert_eq!(selections[1].1, Qty::from_f64(40.0));
+    }
+
+    #[test]
+    fn test_fifo_selection_converts_units() {
+        let lots = vec![create_test_lot(2.0, NaiveDate::from_ymd_opt(2024, 1, 10).unwrap())];
+
+        // 500 g taken from a lot counted in kg
+        let selections = FifoLotSelector::select_lots(&lots, Quantity::new(500.0, UnitOfMeasure::Gram), test_clock().today()).unwrap();
+        assert_eq!(selections.len(), 1);
+        assert_eq!(selections[0].1, Qty::from_f64(0.5));
+
+        assert!(FifoLotSelector::select_lots(&lots, Quantity::litre(1.0), test_clock().today()).is_err());
+    }
+
+    #[test]
+    fn test_lot_consumed_without_float_dust() {
+        let mut lot = create_test_lot(1.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let user = EntityId::new();
+        let clock = test_clock();
+
+        for _ in 0..10 {
+            lot.consume(Qty::from_f64(0.1), user, &clock).unwrap();
+        }
+
+        assert!(lot.quantity_remaining.is_zero());
+        assert_eq!(lot.status, LotStatus::Consumed);
+    }
+
+    #[test]
+    fn test_expiry_follows_clock() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let mut lot = create_test_lot(10.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        lot.set_expiry_date(NaiveDate::from_ymd_opt(2024, 2, 2), &test_keys(), user, &clock);
+
+        assert!(lot.consume(Qty::from_f64(1.0), user, &clock).is_ok());
+        clock.advance(chrono::Duration::days(2));
+        assert!(lot.is_expired(clock.today()));
+        assert!(matches!(
+            lot.consume(Qty::from_f64(1.0), user, &clock),
+            Err(Error::LotExpired { .. })
+        ));
+    }
+
+    #[test]
+    fn test_backdated_entry_recorded_in_audit() {
+        let real: SharedClock = Arc::new(test_clock());
+        let yesterday = OffsetClock::at_date(real, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        let mut lot = create_test_lot(10.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(lot.audit.business_date, None);
+
+        lot.consume(Qty::from_f64(1.0), EntityId::new(), &yesterday).unwrap();
+        assert_eq!(lot.audit.business_date, NaiveDate::from_ymd_opt(2024, 1, 31));
+        assert_eq!(lot.audit.updated_at.date_naive(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+    }
+
+    #[test]
+    fn test_imported_lot_costed_at_reception_rate() {
+        use manchengo_core::{Currency, RateSource};
+
+        let reception = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut lot = create_test_lot(4.0, reception);
+        let price = CurrencyAmount::from_major(12.50, Currency::Eur);
+
+        let late_rate = ExchangeRate::new(Currency::Eur, reception.succ_opt().unwrap(), 146.0, RateSource::Manual).unwrap();
+        assert!(lot.set_purchase_cost(price, Some(late_rate), EntityId::new(), &test_clock()).is_err());
+        assert!(lot.set_purchase_cost(price, None, EntityId::new(), &test_clock()).is_err());
+
+        let rate = ExchangeRate::new(Currency::Eur, reception, 145.0, RateSource::Manual).unwrap();
+        lot.set_purchase_cost(price, Some(rate), EntityId::new(), &test_clock()).unwrap();
+        assert_eq!(lot.unit_cost, Money::from_dzd(1812.50));
+        assert_eq!(lot.total_cost, Money::from_dzd(7250.0));
+        assert_eq!(lot.purchase_unit_cost, Some(price));
+    }
+
+    #[test]
+    fn test_fifo_scoped_to_warehouse() {
+        let factory = EntityId::new();
+        let depot = EntityId::new();
+        let mut old = create_test_lot(50.0, NaiveDate::from_ymd_opt(2024, 1, 10).unwrap());
+        old.warehouse_id = Some(depot);
+        let mut recent = create_test_lot(50.0, NaiveDate::from_ymd_opt(2024, 1, 20).unwrap());
+        recent.warehouse_id = Some(factory);
+
+        let lots = vec![old, recent.clone()];
+        let required = Quantity::new(30.0, UnitOfMeasure::Kilogram);
+        let today = test_clock().today();
+
+        let selections = FifoLotSelector::select_lots_in_warehouse(&lots, factory, required, today).unwrap();
+        assert_eq!(selections, vec![(recent.id, Qty::from_f64(30.0))]);
+
+        let too_much = Quantity::new(80.0, UnitOfMeasure::Kilogram);
+        assert!(FifoLotSelector::select_lots_in_warehouse(&lots, factory, too_much, today).is_err());
+    }
+
+    #[test]
+    fn test_put_away_stays_in_lot_warehouse() {
+        let keys = test_keys();
+        let clock = test_clock();
+        let factory = EntityId::new();
+        let location = |warehouse_id, code: &str, location_type| {
+            Location::new(warehouse_id, code.to_string(), code.to_string(), location_type, EntityId::new(), &keys, &clock)
+                .unwrap()
+        };
+        let cold_room = location(factory, "CF-01", LocationType::ColdRoom);
+        let depot_bin = location(EntityId::new(), "D-01", LocationType::Storage);
+
+        let mut lot = create_test_lot(10.0, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        lot.warehouse_id = Some(factory);
+
+        assert!(lot.put_away(&depot_bin, EntityId::new(), &clock).is_err());
+        lot.put_away(&cold_room, EntityId::new(), &clock).unwrap();
+        assert_eq!(lot.location_id, Some(cold_room.id));
     }
 }
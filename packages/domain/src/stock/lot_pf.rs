@@ -1,10 +1,12 @@
 //! Finished product lot management
 
 use chrono::{DateTime, NaiveDate, Utc};
-use manchengo_core::{AuditInfo, EntityId, Error, Money, QrCodeData, QrEntityType, Result, UnitOfMeasure};
+use manchengo_core::{
+    AuditInfo, Clock, EntityId, Error, Money, PackDefinition, QrCodeData, QrEntityType, QrKeyRing, Qty, Quantity, Result, UnitOfMeasure,
+};
 use serde::{Deserialize, Serialize};
 
-use super::LotStatus;
+use super::{Location, LotCandidate, LotSelection, LotSelectionStrategy, LotStatus};
 
 /// Finished product lot (Lot Produit Fini)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,8 +17,8 @@ pub struct LotPf {
     pub production_order_id: Option<EntityId>,
 
     // Quantities
-    pub quantity_initial: f64,
-    pub quantity_remaining: f64,
+    pub quantity_initial: Qty,
+    pub quantity_remaining: Qty,
     pub unit: UnitOfMeasure,
 
     // Dates
@@ -27,6 +29,13 @@ pub struct LotPf {
     pub unit_cost: Money,
     pub total_cost: Money,
 
+    // Storage (None on lots recorded before warehouses)
+    #[serde(default)]
+    pub warehouse_id: Option<EntityId>,
+    /// Bin location, `None` until put away
+    #[serde(default)]
+    pub location_id: Option<EntityId>,
+
     // Status
     pub status: LotStatus,
     pub blocked_reason: Option<String>,
@@ -41,26 +50,23 @@ pub struct LotPf {
 
 impl LotPf {
     /// Create a new finished product lot
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lot_number: String,
         product_id: EntityId,
         production_order_id: Option<EntityId>,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
         unit_cost: Money,
         production_date: NaiveDate,
         user_id: EntityId,
+        qr_keys: &QrKeyRing,
+        clock: &dyn Clock,
     ) -> Self {
         let id = EntityId::new();
-        let total_cost = Money::from_centimes((quantity * unit_cost.centimes() as f64) as i64);
+        let total_cost = quantity.value_at(unit_cost);
 
-        let qr_data = QrCodeData {
-            entity_type: QrEntityType::LotPf,
-            entity_id: id,
-            reference: lot_number.clone(),
-            expiry_date: None,
-            checksum: String::new(),
-        };
+        let qr_data = QrCodeData::new(QrEntityType::LotPf, id, lot_number.clone(), None, qr_keys);
 
         Self {
             id,
@@ -74,60 +80,100 @@ impl LotPf {
             expiry_date: None,
             unit_cost,
             total_cost,
+            warehouse_id: None,
+            location_id: None,
             status: LotStatus::Available,
             blocked_reason: None,
             qr_code: qr_data.encode(),
             notes: None,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
+    /// Set the expiry date and re-sign the QR code so the label carries it
+    pub fn set_expiry_date(
+        &mut self,
+        expiry_date: Option<NaiveDate>,
+        qr_keys: &QrKeyRing,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) {
+        self.expiry_date = expiry_date;
+        self.qr_code = QrCodeData::new(QrEntityType::LotPf, self.id, self.lot_number.clone(), expiry_date, qr_keys).encode();
+        self.audit.update(user_id, clock);
+    }
+
+    /// Place the lot in a location of its warehouse (put-away, internal move)
+    pub fn put_away(&mut self, location: &Location, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        let warehouse_id = *self.warehouse_id.get_or_insert(location.warehouse_id);
+        location.accepts(warehouse_id)?;
+
+        self.location_id = Some(location.id);
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
     /// Check if lot is expired
-    pub fn is_expired(&self) -> bool {
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
         if let Some(expiry) = self.expiry_date {
-            expiry < chrono::Utc::now().date_naive()
+            expiry < today
         } else {
             false
         }
     }
 
     /// Check if lot can be delivered
-    pub fn can_deliver(&self, quantity: f64) -> bool {
+    pub fn can_deliver(&self, quantity: Qty, today: NaiveDate) -> bool {
         self.status.is_consumable()
-            && !self.is_expired()
+            && !self.is_expired(today)
             && self.quantity_remaining >= quantity
     }
 
     /// Reserve quantity for delivery
-    pub fn reserve(&mut self, quantity: f64, user_id: EntityId) -> Result<()> {
-        if !self.can_deliver(quantity) {
+    ///
+    /// `already_reserved` is what other reservations still hold on the lot.
+    /// The lot turns RESERVED once nothing is left to promise.
+    pub fn reserve(&mut self, quantity: Qty, already_reserved: Qty, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        let available = self.quantity_remaining.saturating_sub(already_reserved).max(Qty::zero());
+        if !quantity.is_positive() || !self.can_deliver(quantity, clock.today()) || quantity > available {
             return Err(Error::InsufficientStock {
                 product: self.lot_number.clone(),
-                required: quantity,
-                available: self.quantity_remaining,
+                required: quantity.as_f64(),
+                available: available.as_f64(),
             });
         }
 
-        // Note: In a full implementation, we'd track reserved vs available separately
-        self.status = LotStatus::Reserved;
-        self.audit.update(user_id);
+        if quantity == available {
+            self.status = LotStatus::Reserved;
+        }
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
+    /// Make a reserved lot available again once its holds drop
+    ///
+    /// `still_reserved` is what the remaining reservations hold on the lot.
+    pub fn release_reservation(&mut self, still_reserved: Qty, user_id: EntityId, clock: &dyn Clock) {
+        if self.status == LotStatus::Reserved && still_reserved < self.quantity_remaining {
+            self.status = LotStatus::Available;
+            self.audit.update(user_id, clock);
+        }
+    }
+
     /// Deliver quantity from lot
-    pub fn deliver(&mut self, quantity: f64, user_id: EntityId) -> Result<f64> {
+    pub fn deliver(&mut self, quantity: Qty, user_id: EntityId, clock: &dyn Clock) -> Result<Qty> {
         if quantity > self.quantity_remaining {
             return Err(Error::InsufficientStock {
                 product: self.lot_number.clone(),
-                required: quantity,
-                available: self.quantity_remaining,
+                required: quantity.as_f64(),
+                available: self.quantity_remaining.as_f64(),
             });
         }
 
         self.quantity_remaining -= quantity;
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
 
-        if self.quantity_remaining <= 0.0 {
+        if self.quantity_remaining.is_zero() {
             self.status = LotStatus::Consumed;
         } else {
             self.status = LotStatus::Available;
@@ -137,7 +183,7 @@ impl LotPf {
     }
 
     /// Block lot
-    pub fn block(&mut self, reason: String, user_id: EntityId) -> Result<()> {
+    pub fn block(&mut self, reason: String, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
         if self.status == LotStatus::Consumed {
             return Err(Error::InvalidStateTransition {
                 entity: "LotPf".to_string(),
@@ -148,18 +194,18 @@ impl LotPf {
 
         self.status = LotStatus::Blocked;
         self.blocked_reason = Some(reason);
-        self.audit.update(user_id);
+        self.audit.update(user_id, clock);
         Ok(())
     }
 
     /// Calculate remaining value
     pub fn remaining_value(&self) -> Money {
-        Money::from_centimes((self.quantity_remaining * self.unit_cost.centimes() as f64) as i64)
+        self.quantity_remaining.value_at(self.unit_cost)
     }
 
     /// Calculate selling value at given price
     pub fn selling_value(&self, unit_price: Money) -> Money {
-        Money::from_centimes((self.quantity_remaining * unit_price.centimes() as f64) as i64)
+        self.quantity_remaining.value_at(unit_price)
     }
 }
 
@@ -167,44 +213,75 @@ impl LotPf {
 pub struct FifoLotSelectorPf;
 
 impl FifoLotSelectorPf {
+    /// FIFO selection among the lots stored in one warehouse
+    pub fn select_lots_in_warehouse(
+        available_lots: &[LotPf],
+        warehouse_id: EntityId,
+        required: Quantity,
+        pack: &PackDefinition,
+        today: NaiveDate,
+    ) -> Result<Vec<(EntityId, Qty)>> {
+        let lots: Vec<LotPf> = available_lots
+            .iter()
+            .filter(|lot| lot.warehouse_id == Some(warehouse_id))
+            .cloned()
+            .collect();
+        Self::select_lots(&lots, required, pack, today)
+    }
+
     /// Select lots to deliver in FIFO order
+    ///
+    /// Quantities sold in cartons or palettes convert through the product
+    /// pack; selections are returned in each lot's unit.
     pub fn select_lots(
         available_lots: &[LotPf],
-        required_quantity: f64,
-    ) -> Result<Vec<(EntityId, f64)>> {
-        let mut remaining = required_quantity;
-        let mut selections = Vec::new();
+        required: Quantity,
+        pack: &PackDefinition,
+        today: NaiveDate,
+    ) -> Result<Vec<(EntityId, Qty)>> {
+        let selection = Self::select_lots_with(&LotSelectionStrategy::Fifo, available_lots, required, pack, &[], today)?
+            .require_complete("Multiple PF lots")?;
+        Ok(selection.picks.into_iter().map(|p| (p.lot_id, p.quantity)).collect())
+    }
 
-        // Sort by production date (FIFO), then by expiry date
-        let mut sorted_lots: Vec<_> = available_lots
+    /// Select lots to deliver with the product strategy
+    ///
+    /// Picks are returned in each lot's unit; `manual` picks are in the
+    /// requested unit.
+    pub fn select_lots_with(
+        strategy: &LotSelectionStrategy,
+        available_lots: &[LotPf],
+        required: Quantity,
+        pack: &PackDefinition,
+        manual: &[(EntityId, Qty)],
+        today: NaiveDate,
+    ) -> Result<LotSelection> {
+        let deliverable: Vec<&LotPf> = available_lots
             .iter()
-            .filter(|lot| lot.can_deliver(0.01))
+            .filter(|lot| lot.status.is_consumable())
             .collect();
 
-        sorted_lots.sort_by(|a, b| {
-            a.production_date
-                .cmp(&b.production_date)
-                .then_with(|| a.expiry_date.cmp(&b.expiry_date))
-        });
-
-        for lot in sorted_lots {
-            if remaining <= 0.0 {
-                break;
-            }
-
-            let deliver = lot.quantity_remaining.min(remaining);
-            selections.push((lot.id, deliver));
-            remaining -= deliver;
-        }
-
-        if remaining > 0.001 {
-            return Err(Error::InsufficientStock {
-                product: "Multiple PF lots".to_string(),
-                required: required_quantity,
-                available: required_quantity - remaining,
+        let mut candidates = Vec::with_capacity(deliverable.len());
+        for lot in &deliverable {
+            candidates.push(LotCandidate {
+                lot_id: lot.id,
+                lot_number: lot.lot_number.clone(),
+                entry_date: lot.production_date,
+                expiry_date: lot.expiry_date,
+                quantity: Quantity::new(lot.quantity_remaining.as_f64(), lot.unit)
+                    .convert_with_pack(required.unit, pack)?
+                    .to_qty(),
             });
         }
 
-        Ok(selections)
+        let mut selection = strategy.select(&candidates, required.to_qty(), manual, today)?;
+        for pick in &mut selection.picks {
+            let lot = deliverable.iter().find(|l| l.id == pick.lot_id).expect("picked lot is a candidate");
+            pick.quantity = Quantity::new(pick.quantity.as_f64(), required.unit)
+                .convert_with_pack(lot.unit, pack)?
+                .to_qty()
+                .min(lot.quantity_remaining);
+        }
+        Ok(selection)
     }
 }
@@ -0,0 +1,369 @@
+//! Lot selection strategies
+//!
+//! Which lots a consumption or a delivery draws from is a product setting:
+//! - FIFO: oldest entry first (aged cheese, raw materials)
+//! - FEFO: earliest expiry first (fresh cheese)
+//! - Manual: the operator picks the lots
+//! - Minimum remaining shelf life: FEFO among lots that keep at least N days
+//!
+//! Every chosen lot comes with the reason it was chosen, and every skipped lot
+//! with the reason it was skipped.
+
+use chrono::NaiveDate;
+use manchengo_core::{EntityId, Error, Qty, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How lots of a product are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LotSelectionStrategy {
+    /// First in, first out
+    #[default]
+    Fifo,
+    /// First expired, first out
+    Fefo,
+    /// Lots picked by the operator
+    Manual,
+    /// FEFO among lots expiring in `min_days` days or later
+    MinShelfLife { min_days: u32 },
+}
+
+impl LotSelectionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fifo => "FIFO",
+            Self::Fefo => "FEFO",
+            Self::Manual => "MANUAL",
+            Self::MinShelfLife { .. } => "MIN_SHELF_LIFE",
+        }
+    }
+
+    /// Rebuild from its stored code and minimum shelf life
+    pub fn from_code(code: &str, min_days: Option<u32>) -> Option<Self> {
+        match code {
+            "FIFO" => Some(Self::Fifo),
+            "FEFO" => Some(Self::Fefo),
+            "MANUAL" => Some(Self::Manual),
+            "MIN_SHELF_LIFE" => min_days.map(|min_days| Self::MinShelfLife { min_days }),
+            _ => None,
+        }
+    }
+
+    /// Minimum remaining shelf life in days, if any
+    pub fn min_shelf_life_days(&self) -> Option<u32> {
+        match self {
+            Self::MinShelfLife { min_days } => Some(*min_days),
+            _ => None,
+        }
+    }
+
+    /// Apply a client's minimum remaining shelf life
+    ///
+    /// The client minimum turns FIFO, FEFO and manual picking into FEFO above
+    /// it; a product minimum already in place is kept when larger.
+    pub fn with_client_min_shelf_life(self, client_min_days: Option<u32>) -> Self {
+        match client_min_days {
+            None => self,
+            Some(days) => Self::MinShelfLife {
+                min_days: self.min_shelf_life_days().map_or(days, |product| product.max(days)),
+            },
+        }
+    }
+
+    /// Choose lots covering `required`
+    ///
+    /// `manual` lists the (lot, quantity) picks of the Manual strategy and is
+    /// ignored otherwise. A shortage is reported, not an error: callers
+    /// previewing a selection still want to see it.
+    pub fn select(
+        &self,
+        candidates: &[LotCandidate],
+        required: Qty,
+        manual: &[(EntityId, Qty)],
+        today: NaiveDate,
+    ) -> Result<LotSelection> {
+        let mut excluded = Vec::new();
+        let mut eligible = Vec::new();
+        for lot in candidates.iter().filter(|l| l.quantity.is_positive()) {
+            match self.exclusion(lot, today) {
+                Some(reason) => excluded.push(LotExclusion {
+                    lot_id: lot.lot_id,
+                    lot_number: lot.lot_number.clone(),
+                    reason,
+                }),
+                None => eligible.push(lot),
+            }
+        }
+
+        let mut picks = Vec::new();
+        let mut remaining = required;
+
+        if *self == Self::Manual {
+            if manual.is_empty() {
+                return Err(Error::Validation {
+                    field: "lots".to_string(),
+                    message: "Selection manuelle des lots requise".to_string(),
+                });
+            }
+            for (lot_id, quantity) in manual {
+                let lot = eligible.iter().find(|l| l.lot_id == *lot_id).ok_or_else(|| {
+                    match excluded.iter().find(|e| e.lot_id == *lot_id) {
+                        Some(e) => Error::BusinessRule(format!("Lot {} non utilisable: {}", e.lot_number, e.reason)),
+                        None => Error::NotFound {
+                            entity_type: "Lot".to_string(),
+                            id: lot_id.to_string(),
+                        },
+                    }
+                })?;
+                if !quantity.is_positive() || *quantity > lot.quantity {
+                    return Err(Error::InsufficientStock {
+                        product: lot.lot_number.clone(),
+                        required: quantity.as_f64(),
+                        available: lot.quantity.as_f64(),
+                    });
+                }
+                picks.push(LotPick {
+                    lot_id: lot.lot_id,
+                    lot_number: lot.lot_number.clone(),
+                    quantity: *quantity,
+                    reason: "Choix manuel".to_string(),
+                });
+                remaining -= *quantity;
+            }
+        } else {
+            eligible.sort_by(|a, b| self.compare(a, b));
+            for lot in eligible {
+                if !remaining.is_positive() {
+                    break;
+                }
+                let quantity = lot.quantity.min(remaining);
+                picks.push(LotPick {
+                    lot_id: lot.lot_id,
+                    lot_number: lot.lot_number.clone(),
+                    quantity,
+                    reason: self.reason(lot, today),
+                });
+                remaining -= quantity;
+            }
+        }
+
+        Ok(LotSelection {
+            strategy: *self,
+            picks,
+            excluded,
+            shortage: remaining.max(Qty::zero()),
+        })
+    }
+
+    /// Why a lot cannot be used, `None` if it can
+    fn exclusion(&self, lot: &LotCandidate, today: NaiveDate) -> Option<String> {
+        let expiry = lot.expiry_date?;
+        if expiry < today {
+            return Some(format!("Perime depuis le {}", expiry));
+        }
+        match self.min_shelf_life_days() {
+            Some(min_days) if lot.days_left(today).unwrap_or(i64::MAX) < min_days as i64 => Some(format!(
+                "DLC restante {} j, minimum {} j",
+                lot.days_left(today).unwrap_or_default(),
+                min_days
+            )),
+            _ => None,
+        }
+    }
+
+    fn compare(&self, a: &LotCandidate, b: &LotCandidate) -> Ordering {
+        match self {
+            Self::Fifo | Self::Manual => a
+                .entry_date
+                .cmp(&b.entry_date)
+                .then_with(|| a.expiry_date.cmp(&b.expiry_date)),
+            // Lots without expiry date come last
+            Self::Fefo | Self::MinShelfLife { .. } => match (a.expiry_date, b.expiry_date) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| a.entry_date.cmp(&b.entry_date)),
+        }
+    }
+
+    fn reason(&self, lot: &LotCandidate, today: NaiveDate) -> String {
+        match (self, lot.expiry_date) {
+            (Self::Fifo | Self::Manual, _) => format!("FIFO: entree du {}, la plus ancienne restante", lot.entry_date),
+            (Self::Fefo, Some(expiry)) => format!("FEFO: peremption au {}, la plus proche restante", expiry),
+            (Self::MinShelfLife { min_days }, Some(expiry)) => format!(
+                "DLC restante {} j (minimum {} j), peremption au {} la plus proche restante",
+                lot.days_left(today).unwrap_or_default(),
+                min_days,
+                expiry
+            ),
+            (_, None) => format!("FEFO: sans date de peremption, apres les lots dates (entree du {})", lot.entry_date),
+        }
+    }
+}
+
+/// Lot that a selection may draw from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotCandidate {
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    /// Reception date (MP) or production date (PF)
+    pub entry_date: NaiveDate,
+    pub expiry_date: Option<NaiveDate>,
+    /// Quantity available, in the unit of the requested quantity
+    pub quantity: Qty,
+}
+
+impl LotCandidate {
+    /// Days of shelf life left
+    pub fn days_left(&self, today: NaiveDate) -> Option<i64> {
+        self.expiry_date.map(|expiry| (expiry - today).num_days())
+    }
+}
+
+/// Lot chosen by a selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotPick {
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub quantity: Qty,
+    pub reason: String,
+}
+
+/// Lot a selection could not use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotExclusion {
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub reason: String,
+}
+
+/// Outcome of a lot selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotSelection {
+    pub strategy: LotSelectionStrategy,
+    pub picks: Vec<LotPick>,
+    pub excluded: Vec<LotExclusion>,
+    /// Quantity the eligible lots could not cover
+    pub shortage: Qty,
+}
+
+impl LotSelection {
+    pub fn is_complete(&self) -> bool {
+        !self.shortage.is_positive()
+    }
+
+    /// Fail when the lots do not cover the request
+    pub fn require_complete(self, product: &str) -> Result<Self> {
+        if self.is_complete() {
+            return Ok(self);
+        }
+        let picked: Qty = self.picks.iter().map(|p| p.quantity).sum();
+        Err(Error::InsufficientStock {
+            product: product.to_string(),
+            required: (picked + self.shortage).as_f64(),
+            available: picked.as_f64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn lot(number: &str, entry: NaiveDate, expiry: Option<NaiveDate>, units: i64) -> LotCandidate {
+        LotCandidate {
+            lot_id: EntityId::new(),
+            lot_number: number.to_string(),
+            entry_date: entry,
+            expiry_date: expiry,
+            quantity: Qty::units(units),
+        }
+    }
+
+    #[test]
+    fn test_fifo_and_fefo_order() {
+        let today = date(3, 1);
+        // Older lot expires later (aged), newer lot expires sooner (fresh)
+        let lots = vec![
+            lot("OLD", date(1, 10), Some(date(6, 30)), 10),
+            lot("NEW", date(2, 20), Some(date(3, 15)), 10),
+        ];
+
+        let fifo = LotSelectionStrategy::Fifo.select(&lots, Qty::units(12), &[], today).unwrap();
+        assert_eq!(fifo.picks[0].lot_number, "OLD");
+        assert_eq!(fifo.picks[1].quantity, Qty::units(2));
+        assert!(fifo.picks[0].reason.starts_with("FIFO"));
+
+        let fefo = LotSelectionStrategy::Fefo.select(&lots, Qty::units(12), &[], today).unwrap();
+        assert_eq!(fefo.picks[0].lot_number, "NEW");
+        assert_eq!(fefo.picks[1].quantity, Qty::units(2));
+        assert!(fefo.picks[0].reason.contains("2024-03-15"));
+        assert!(fefo.is_complete());
+    }
+
+    #[test]
+    fn test_min_shelf_life_excludes_short_lots() {
+        let today = date(3, 1);
+        let lots = vec![
+            lot("SHORT", date(2, 1), Some(date(3, 10)), 10),
+            lot("LONG", date(2, 15), Some(date(4, 30)), 10),
+            lot("EXPIRED", date(1, 1), Some(date(2, 28)), 10),
+        ];
+
+        let strategy = LotSelectionStrategy::MinShelfLife { min_days: 30 };
+        let selection = strategy.select(&lots, Qty::units(15), &[], today).unwrap();
+
+        assert_eq!(selection.picks.len(), 1);
+        assert_eq!(selection.picks[0].lot_number, "LONG");
+        assert_eq!(selection.shortage, Qty::units(5));
+        assert_eq!(selection.excluded.len(), 2);
+        assert!(selection.excluded.iter().any(|e| e.reason == "DLC restante 9 j, minimum 30 j"));
+        assert!(selection.clone().require_complete("Camembert").is_err());
+        assert_eq!(LotSelectionStrategy::from_code("MIN_SHELF_LIFE", Some(30)), Some(strategy));
+    }
+
+    #[test]
+    fn test_client_min_shelf_life_override() {
+        let fifo = LotSelectionStrategy::Fifo;
+        assert_eq!(fifo.with_client_min_shelf_life(None), fifo);
+        assert_eq!(
+            fifo.with_client_min_shelf_life(Some(20)),
+            LotSelectionStrategy::MinShelfLife { min_days: 20 }
+        );
+
+        let product = LotSelectionStrategy::MinShelfLife { min_days: 30 };
+        assert_eq!(product.with_client_min_shelf_life(Some(20)), product);
+        assert_eq!(
+            product.with_client_min_shelf_life(Some(45)),
+            LotSelectionStrategy::MinShelfLife { min_days: 45 }
+        );
+    }
+
+    #[test]
+    fn test_manual_selection() {
+        let today = date(3, 1);
+        let lots = vec![
+            lot("A", date(1, 10), None, 10),
+            lot("B", date(2, 20), None, 10),
+        ];
+
+        assert!(LotSelectionStrategy::Manual.select(&lots, Qty::units(5), &[], today).is_err());
+        assert!(LotSelectionStrategy::Manual
+            .select(&lots, Qty::units(5), &[(lots[1].lot_id, Qty::units(11))], today)
+            .is_err());
+
+        let selection = LotSelectionStrategy::Manual
+            .select(&lots, Qty::units(5), &[(lots[1].lot_id, Qty::units(5))], today)
+            .unwrap();
+        assert_eq!(selection.picks[0].lot_number, "B");
+        assert_eq!(selection.picks[0].reason, "Choix manuel");
+        assert!(selection.is_complete());
+    }
+}
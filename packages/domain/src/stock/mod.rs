@@ -3,15 +3,38 @@
 //! Handles inventory management including:
 //! - Raw material lots (MP)
 //! - Finished product lots (PF)
-//! - FIFO stock consumption
+//! - Lot selection strategies (FIFO, FEFO, manual, minimum shelf life)
+//! - Lot reservations for confirmed sales orders
 //! - Stock movements
+//! - Ledger integrity checks
+//! - Scanned code resolution (MCG QR, GS1-128)
+//! - Warehouses and bin locations
+//! - Inter-warehouse transfer orders
+//! - Physical inventory sessions
+//! - Stock valuation (CMUP and FIFO)
 
 mod lot_mp;
 mod lot_pf;
+mod lot_selection;
+mod reservation;
 mod product;
 mod movement;
+mod integrity;
+mod scan;
+mod warehouse;
+mod transfer;
+mod inventory;
+mod valuation;
 
 pub use lot_mp::*;
 pub use lot_pf::*;
+pub use lot_selection::*;
+pub use reservation::*;
 pub use product::*;
 pub use movement::*;
+pub use integrity::*;
+pub use scan::*;
+pub use warehouse::*;
+pub use transfer::*;
+pub use inventory::*;
+pub use valuation::*;
@@ -1,7 +1,7 @@
 //! Stock movement tracking
 
 use chrono::{DateTime, Utc};
-use manchengo_core::{EntityId, UnitOfMeasure};
+use manchengo_core::{Clock, EntityId, Qty, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Type of stock movement
@@ -85,12 +85,12 @@ pub struct StockMovement {
     pub product_id: EntityId,
     pub lot_id: EntityId,
     pub movement_type: MovementType,
-    pub quantity: f64, // Positive for entries, negative for exits
+    pub quantity: Qty, // Positive for entries, negative for exits
     pub unit: UnitOfMeasure,
     pub reference_type: Option<ReferenceType>,
     pub reference_id: Option<EntityId>,
-    pub quantity_before: f64,
-    pub quantity_after: f64,
+    pub quantity_before: Qty,
+    pub quantity_after: Qty,
     pub notes: Option<String>,
     pub created_at: DateTime<Utc>,
     pub created_by: EntityId,
@@ -103,13 +103,14 @@ impl StockMovement {
         product_id: EntityId,
         lot_id: EntityId,
         movement_type: MovementType,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
-        quantity_before: f64,
+        quantity_before: Qty,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         debug_assert!(movement_type.is_entry());
-        debug_assert!(quantity > 0.0);
+        debug_assert!(quantity.is_positive());
 
         Self {
             id: EntityId::new(),
@@ -124,7 +125,7 @@ impl StockMovement {
             quantity_before,
             quantity_after: quantity_before + quantity,
             notes: None,
-            created_at: Utc::now(),
+            created_at: clock.now(),
             created_by: user_id,
         }
     }
@@ -135,13 +136,14 @@ impl StockMovement {
         product_id: EntityId,
         lot_id: EntityId,
         movement_type: MovementType,
-        quantity: f64,
+        quantity: Qty,
         unit: UnitOfMeasure,
-        quantity_before: f64,
+        quantity_before: Qty,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         debug_assert!(movement_type.is_exit());
-        debug_assert!(quantity > 0.0);
+        debug_assert!(quantity.is_positive());
 
         Self {
             id: EntityId::new(),
@@ -156,7 +158,7 @@ impl StockMovement {
             quantity_before,
             quantity_after: quantity_before - quantity,
             notes: None,
-            created_at: Utc::now(),
+            created_at: clock.now(),
             created_by: user_id,
         }
     }
@@ -180,10 +182,10 @@ impl StockMovement {
 pub struct StockSummary {
     pub product_id: EntityId,
     pub product_type: ProductType,
-    pub total_quantity: f64,
-    pub available_quantity: f64,
-    pub reserved_quantity: f64,
-    pub blocked_quantity: f64,
+    pub total_quantity: Qty,
+    pub available_quantity: Qty,
+    pub reserved_quantity: Qty,
+    pub blocked_quantity: Qty,
     pub lot_count: i32,
     pub unit: UnitOfMeasure,
 }
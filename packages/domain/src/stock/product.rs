@@ -1,7 +1,7 @@
 //! Product definitions for raw materials and finished products
 
 use chrono::{DateTime, Utc};
-use manchengo_core::{AuditInfo, EntityId, Money, UnitOfMeasure};
+use manchengo_core::{AuditInfo, Clock, EntityId, Gtin, Money, Result, UnitOfMeasure};
 use serde::{Deserialize, Serialize};
 
 /// Raw material product definition
@@ -27,6 +27,7 @@ impl ProductMp {
         name: String,
         unit: UnitOfMeasure,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -40,7 +41,7 @@ impl ProductMp {
             is_perishable: true,
             default_shelf_life_days: None,
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 }
@@ -60,6 +61,12 @@ pub struct ProductPf {
     pub default_shelf_life_days: Option<i32>,
     pub base_price_ht: Money,
     pub tva_rate: f64,
+    /// EAN-13 printed on the consumer unit
+    #[serde(default)]
+    pub gtin: Option<Gtin>,
+    /// GTIN-14 of the carton, carried in GS1-128 AI(01)
+    #[serde(default)]
+    pub carton_gtin: Option<Gtin>,
     pub is_active: bool,
     pub audit: AuditInfo,
 }
@@ -71,6 +78,7 @@ impl ProductPf {
         unit: UnitOfMeasure,
         base_price_ht: Money,
         user_id: EntityId,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             id: EntityId::new(),
@@ -85,8 +93,10 @@ impl ProductPf {
             default_shelf_life_days: None,
             base_price_ht,
             tva_rate: 0.19, // Standard Algerian TVA
+            gtin: None,
+            carton_gtin: None,
             is_active: true,
-            audit: AuditInfo::new(user_id),
+            audit: AuditInfo::new(user_id, clock),
         }
     }
 
@@ -95,4 +105,20 @@ impl ProductPf {
         let tva = Money::from_centimes((price_ht.centimes() as f64 * self.tva_rate) as i64);
         price_ht + tva
     }
+
+    /// Set the consumer unit and carton GTINs (check digits verified)
+    pub fn set_gtins(&mut self, gtin: Option<&str>, carton_gtin: Option<&str>, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.gtin = gtin.map(Gtin::parse).transpose()?;
+        self.carton_gtin = carton_gtin.map(Gtin::parse).transpose()?;
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Whether a scanned GTIN designates this product (unit or carton)
+    pub fn has_gtin(&self, gtin: &Gtin) -> bool {
+        [&self.gtin, &self.carton_gtin]
+            .into_iter()
+            .flatten()
+            .any(|own| own.same_item(gtin))
+    }
 }
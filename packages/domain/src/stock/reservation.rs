@@ -0,0 +1,399 @@
+//! Lot reservations for confirmed sales orders
+//!
+//! Confirming a sales order holds finished product lots for each of its lines
+//! until an expiry. A hold ends when delivery preparation picks it, when the
+//! order is cancelled, or when it times out.
+//!
+//! Available-to-promise (ATP) of a lot is its remaining quantity minus the
+//! quantities still held on it.
+
+use chrono::{DateTime, Utc};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Qty, Result};
+use serde::{Deserialize, Serialize};
+
+use super::LotPf;
+
+/// Reservation status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReservationStatus {
+    /// Holding stock for the order line
+    Active,
+    /// Picked by delivery preparation
+    Consumed,
+    /// Released by the order cancellation
+    Released,
+    /// Released because it timed out
+    Expired,
+}
+
+impl ReservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "ACTIVE",
+            Self::Consumed => "CONSUMED",
+            Self::Released => "RELEASED",
+            Self::Expired => "EXPIRED",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "ACTIVE" => Some(Self::Active),
+            "CONSUMED" => Some(Self::Consumed),
+            "RELEASED" => Some(Self::Released),
+            "EXPIRED" => Some(Self::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Quantity of a lot held for a sales order line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotReservation {
+    pub id: EntityId,
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub product_id: EntityId,
+    pub sales_order_id: EntityId,
+    pub sales_order_line_id: EntityId,
+    /// Quantity held, in the lot unit
+    pub quantity: Qty,
+    pub status: ReservationStatus,
+    pub expires_at: DateTime<Utc>,
+    pub release_reason: Option<String>,
+    pub audit: AuditInfo,
+}
+
+impl LotReservation {
+    /// Whether the reservation still holds stock at `now`
+    pub fn is_holding(&self, now: DateTime<Utc>) -> bool {
+        self.status == ReservationStatus::Active && self.expires_at > now
+    }
+
+    fn close(&mut self, status: ReservationStatus, reason: Option<String>, user_id: EntityId, clock: &dyn Clock) {
+        self.status = status;
+        self.release_reason = reason;
+        self.audit.update(user_id, clock);
+    }
+}
+
+/// Reservations held on a set of lots
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReservationBook {
+    pub reservations: Vec<LotReservation>,
+}
+
+impl ReservationBook {
+    pub fn new(reservations: Vec<LotReservation>) -> Self {
+        Self { reservations }
+    }
+
+    /// Quantity held on a lot at `now`
+    pub fn reserved(&self, lot_id: EntityId, now: DateTime<Utc>) -> Qty {
+        self.reservations
+            .iter()
+            .filter(|r| r.lot_id == lot_id && r.is_holding(now))
+            .map(|r| r.quantity)
+            .sum()
+    }
+
+    /// Quantity of a lot that can still be promised
+    ///
+    /// Blocked, consumed and expired lots have nothing to promise.
+    pub fn available_to_promise(&self, lot: &LotPf, now: DateTime<Utc>) -> Qty {
+        if !lot.status.is_consumable() || lot.is_expired(now.date_naive()) {
+            return Qty::zero();
+        }
+        lot.quantity_remaining.saturating_sub(self.reserved(lot.id, now)).max(Qty::zero())
+    }
+
+    /// Quantity of a product that can still be promised, over its lots
+    pub fn product_available_to_promise(&self, lots: &[LotPf], product_id: EntityId, now: DateTime<Utc>) -> Qty {
+        lots.iter()
+            .filter(|lot| lot.product_id == product_id)
+            .map(|lot| self.available_to_promise(lot, now))
+            .sum()
+    }
+
+    /// Lots with their quantity cut down to what can still be promised
+    ///
+    /// Lots with nothing left to promise are dropped.
+    pub fn unreserved_lots(&self, lots: &[LotPf], now: DateTime<Utc>) -> Vec<LotPf> {
+        lots.iter()
+            .filter_map(|lot| {
+                let available = self.available_to_promise(lot, now);
+                available.is_positive().then(|| LotPf {
+                    quantity_remaining: available,
+                    ..lot.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Reservations of a sales order line that still hold stock
+    pub fn holding_for_line(&self, sales_order_line_id: EntityId, now: DateTime<Utc>) -> Vec<&LotReservation> {
+        self.reservations
+            .iter()
+            .filter(|r| r.sales_order_line_id == sales_order_line_id && r.is_holding(now))
+            .collect()
+    }
+
+    /// Hold `quantity` of a lot for a sales order line
+    #[allow(clippy::too_many_arguments)]
+    pub fn reserve(
+        &mut self,
+        lot: &mut LotPf,
+        sales_order_id: EntityId,
+        sales_order_line_id: EntityId,
+        quantity: Qty,
+        expires_at: DateTime<Utc>,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<EntityId> {
+        if expires_at <= clock.now() {
+            return Err(Error::Validation {
+                field: "expires_at".to_string(),
+                message: "L'expiration de la reservation doit etre dans le futur".to_string(),
+            });
+        }
+
+        let already_reserved = self.reserved(lot.id, clock.now());
+        lot.reserve(quantity, already_reserved, user_id, clock)?;
+
+        let reservation = LotReservation {
+            id: EntityId::new(),
+            lot_id: lot.id,
+            lot_number: lot.lot_number.clone(),
+            product_id: lot.product_id,
+            sales_order_id,
+            sales_order_line_id,
+            quantity,
+            status: ReservationStatus::Active,
+            expires_at,
+            release_reason: None,
+            audit: AuditInfo::new(user_id, clock),
+        };
+        let id = reservation.id;
+        self.reservations.push(reservation);
+        Ok(id)
+    }
+
+    /// Release the reservations of a cancelled order
+    ///
+    /// Returns the ids of the released reservations.
+    pub fn release_order(
+        &mut self,
+        sales_order_id: EntityId,
+        lots: &mut [LotPf],
+        reason: &str,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Vec<EntityId> {
+        let now = clock.now();
+        let mut released = Vec::new();
+        for reservation in self
+            .reservations
+            .iter_mut()
+            .filter(|r| r.sales_order_id == sales_order_id && r.status == ReservationStatus::Active)
+        {
+            reservation.close(ReservationStatus::Released, Some(reason.to_string()), user_id, clock);
+            released.push(reservation.id);
+        }
+        self.free_lots(lots, now, user_id, clock);
+        released
+    }
+
+    /// Expire reservations past their expiry
+    ///
+    /// Returns the ids of the expired reservations.
+    pub fn expire(&mut self, lots: &mut [LotPf], user_id: EntityId, clock: &dyn Clock) -> Vec<EntityId> {
+        let now = clock.now();
+        let mut expired = Vec::new();
+        for reservation in self
+            .reservations
+            .iter_mut()
+            .filter(|r| r.status == ReservationStatus::Active && r.expires_at <= now)
+        {
+            reservation.close(
+                ReservationStatus::Expired,
+                Some(format!("Reservation expiree le {}", reservation.expires_at.format("%Y-%m-%d %H:%M"))),
+                user_id,
+                clock,
+            );
+            expired.push(reservation.id);
+        }
+        self.free_lots(lots, now, user_id, clock);
+        expired
+    }
+
+    /// Mark a reservation as picked by delivery preparation
+    pub fn consume(&mut self, reservation_id: EntityId, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        let now = clock.now();
+        let reservation = self
+            .reservations
+            .iter_mut()
+            .find(|r| r.id == reservation_id)
+            .ok_or_else(|| Error::NotFound {
+                entity_type: "LotReservation".to_string(),
+                id: reservation_id.to_string(),
+            })?;
+
+        if !reservation.is_holding(now) {
+            return Err(Error::InvalidStateTransition {
+                entity: "LotReservation".to_string(),
+                from: if reservation.status == ReservationStatus::Active {
+                    "EXPIRED".to_string()
+                } else {
+                    reservation.status.as_str().to_string()
+                },
+                to: "CONSUMED".to_string(),
+            });
+        }
+
+        reservation.close(ReservationStatus::Consumed, None, user_id, clock);
+        Ok(())
+    }
+
+    /// Put fully reserved lots back to available once holds are gone
+    fn free_lots(&self, lots: &mut [LotPf], now: DateTime<Utc>, user_id: EntityId, clock: &dyn Clock) {
+        for lot in lots.iter_mut() {
+            let reserved = self.reserved(lot.id, now);
+            lot.release_reservation(reserved, user_id, clock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commercial::{SalesOrder, SalesOrderStatus};
+    use crate::delivery::Delivery;
+    use crate::stock::LotStatus;
+    use chrono::{Duration, NaiveDate};
+    use manchengo_core::{FixedClock, Money, PackDefinition, QrKey, QrKeyRing, Quantity, UnitOfMeasure};
+
+    fn keys() -> QrKeyRing {
+        QrKeyRing::new(vec![QrKey::new("T1", b"test-secret-0123456789").unwrap()]).unwrap()
+    }
+
+    fn test_clock() -> FixedClock {
+        FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+    }
+
+    fn lot(number: &str, product_id: EntityId, units: i64, day: u32, clock: &FixedClock) -> LotPf {
+        LotPf::new(
+            number.to_string(),
+            product_id,
+            None,
+            Qty::units(units),
+            UnitOfMeasure::Piece,
+            Money::from_centimes(30_000),
+            NaiveDate::from_ymd_opt(2024, 2, day).unwrap(),
+            EntityId::new(),
+            &keys(),
+            clock,
+        )
+    }
+
+    fn order(product_id: EntityId, units: f64, clock: &FixedClock) -> SalesOrder {
+        let mut order = SalesOrder::new("CMD-2024-000001".to_string(), EntityId::new(), clock.today(), EntityId::new(), clock);
+        order
+            .add_line(
+                product_id,
+                Quantity::new(units, UnitOfMeasure::Piece),
+                UnitOfMeasure::Piece,
+                &PackDefinition::default(),
+                Money::from_centimes(50_000),
+                0.19,
+                EntityId::new(),
+                clock,
+            )
+            .unwrap();
+        order
+    }
+
+    #[test]
+    fn test_confirm_reserves_and_cancel_releases() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let product = EntityId::new();
+        let mut lots = vec![lot("OLD", product, 10, 1, &clock), lot("NEW", product, 10, 20, &clock)];
+        let mut book = ReservationBook::default();
+        let hold_until = clock.now() + Duration::days(2);
+
+        let mut first = order(product, 15.0, &clock);
+        first.confirm(&mut lots, &mut book, &[], hold_until, user, &clock).unwrap();
+        assert_eq!(first.status, SalesOrderStatus::Confirmed);
+        assert_eq!(book.reserved(lots[0].id, clock.now()), Qty::units(10));
+        assert_eq!(book.reserved(lots[1].id, clock.now()), Qty::units(5));
+        assert_eq!(lots[0].status, LotStatus::Reserved);
+        assert_eq!(book.product_available_to_promise(&lots, product, clock.now()), Qty::units(5));
+
+        // A second order cannot be promised what the first one holds
+        let mut second = order(product, 8.0, &clock);
+        assert!(second.confirm(&mut lots, &mut book, &[], hold_until, user, &clock).is_err());
+        assert_eq!(second.status, SalesOrderStatus::Draft);
+        assert_eq!(book.reservations.len(), 2);
+
+        first.cancel(&mut lots, &mut book, "Client annule", user, &clock).unwrap();
+        assert_eq!(first.status, SalesOrderStatus::Cancelled);
+        assert_eq!(lots[0].status, LotStatus::Available);
+        assert!(book.reservations.iter().all(|r| r.status == ReservationStatus::Released));
+        second.confirm(&mut lots, &mut book, &[], hold_until, user, &clock).unwrap();
+    }
+
+    #[test]
+    fn test_reservations_time_out() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let product = EntityId::new();
+        let mut lots = vec![lot("A", product, 10, 1, &clock)];
+        let mut book = ReservationBook::default();
+
+        let mut order = order(product, 10.0, &clock);
+        order
+            .confirm(&mut lots, &mut book, &[], clock.now() + Duration::hours(4), user, &clock)
+            .unwrap();
+        assert!(book.available_to_promise(&lots[0], clock.now()).is_zero());
+
+        clock.advance(Duration::hours(5));
+        // Expired holds stop counting even before the sweep runs
+        assert_eq!(book.available_to_promise(&lots[0], clock.now()), Qty::units(10));
+        let expired = book.expire(&mut lots, user, &clock);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(book.reservations[0].status, ReservationStatus::Expired);
+        assert_eq!(lots[0].status, LotStatus::Available);
+    }
+
+    #[test]
+    fn test_delivery_preparation_consumes_reservations() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let product = EntityId::new();
+        let mut lots = vec![lot("A", product, 10, 1, &clock), lot("B", product, 10, 5, &clock)];
+        let mut book = ReservationBook::default();
+
+        let mut order = order(product, 12.0, &clock);
+        order
+            .confirm(&mut lots, &mut book, &[], clock.now() + Duration::days(1), user, &clock)
+            .unwrap();
+
+        let mut delivery = Delivery::new("BL-2024-000001".to_string(), clock.today(), user, &keys(), &clock);
+        let line_id = delivery.add_line(order.client_id, Some(order.id), 1, user, &clock).unwrap();
+        let picked = delivery
+            .add_reserved_items(line_id, &order, &mut book, &mut lots, user, &clock)
+            .unwrap();
+
+        assert_eq!(picked, vec![(lots[0].id, Qty::units(10)), (lots[1].id, Qty::units(2))]);
+        assert_eq!(delivery.lines[0].items.len(), 2);
+        assert_eq!(delivery.total_ht, Money::from_centimes(600_000));
+        assert_eq!(lots[1].quantity_remaining, Qty::units(8));
+        assert!(book.reservations.iter().all(|r| r.status == ReservationStatus::Consumed));
+        assert_eq!(book.product_available_to_promise(&lots, product, clock.now()), Qty::units(8));
+
+        // Nothing left to prepare
+        let other = delivery.add_line(order.client_id, Some(order.id), 2, user, &clock).unwrap();
+        assert!(delivery.add_reserved_items(other, &order, &mut book, &mut lots, user, &clock).is_err());
+    }
+}
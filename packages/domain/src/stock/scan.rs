@@ -0,0 +1,136 @@
+//! Scanned codes
+//!
+//! A finished product lot can be scanned from our MCG QR code or from the
+//! GS1-128 carton label required by supermarket chains. Both resolve to the
+//! same `LotPf`: the QR code by its entity id, GS1 by GTIN and AI(10) lot.
+
+use manchengo_core::{Error, Gs1Label, QrCodeData, QrEntityType, QrKeyRing, Result};
+
+use super::{LotPf, ProductPf};
+
+/// Code read by a scanner
+#[derive(Debug, Clone)]
+pub enum ScannedCode {
+    Qr(QrCodeData),
+    Gs1(Gs1Label),
+}
+
+impl ScannedCode {
+    /// Parse and verify a scanned string
+    pub fn parse(data: &str, qr_keys: &QrKeyRing) -> Result<Self> {
+        if Gs1Label::looks_like_gs1(data) {
+            return Ok(Self::Gs1(Gs1Label::decode(data)?));
+        }
+        QrCodeData::decode(data, qr_keys)
+            .map(Self::Qr)
+            .map_err(|e| Error::QrCode(format!("QR code invalide: {:?}", e)))
+    }
+
+    /// Find the finished product lot designated by the code
+    pub fn resolve_lot_pf<'a>(&self, products: &[ProductPf], lots: &'a [LotPf]) -> Result<&'a LotPf> {
+        let not_found = |id: String| Error::NotFound {
+            entity_type: "LotPf".to_string(),
+            id,
+        };
+
+        match self {
+            Self::Qr(qr) => {
+                if qr.entity_type != QrEntityType::LotPf {
+                    return Err(Error::BusinessRule(format!(
+                        "QR code {:?} scanne, lot PF attendu",
+                        qr.entity_type
+                    )));
+                }
+                lots.iter()
+                    .find(|lot| lot.id == qr.entity_id)
+                    .ok_or_else(|| not_found(qr.entity_id.to_string()))
+            }
+            Self::Gs1(label) => {
+                let lot_number = label.lot.as_deref().ok_or_else(|| {
+                    Error::BusinessRule("Code GS1 sans numero de lot (10)".to_string())
+                })?;
+                let product = products
+                    .iter()
+                    .find(|p| p.has_gtin(&label.gtin))
+                    .ok_or_else(|| Error::NotFound {
+                        entity_type: "ProductPf".to_string(),
+                        id: label.gtin.to_string(),
+                    })?;
+                lots.iter()
+                    .find(|lot| lot.product_id == product.id && lot.lot_number == lot_number)
+                    .ok_or_else(|| not_found(format!("{} / {}", label.gtin, lot_number)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use manchengo_core::{EntityId, FixedClock, Gtin, Money, QrKey, Qty, UnitOfMeasure};
+
+    fn keys() -> QrKeyRing {
+        QrKeyRing::new(vec![QrKey::new("T1", b"test-secret-0123456789").unwrap()]).unwrap()
+    }
+
+    fn fixtures() -> (Vec<ProductPf>, Vec<LotPf>) {
+        let user = EntityId::new();
+        let clock = FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let mut product = ProductPf::new(
+            "PF-CAM250".to_string(),
+            "Camembert 250g".to_string(),
+            UnitOfMeasure::Piece,
+            Money::from_dzd(350.0),
+            user,
+            &clock,
+        );
+        product
+            .set_gtins(Some("6130000000015"), Some("16130000000012"), user, &clock)
+            .unwrap();
+
+        let lot = |number: &str| {
+            LotPf::new(
+                number.to_string(),
+                product.id,
+                None,
+                Qty::units(120),
+                UnitOfMeasure::Piece,
+                Money::from_dzd(200.0),
+                NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+                user,
+                &keys(),
+                &clock,
+            )
+        };
+        let lots = vec![lot("LPF-240115-001"), lot("LPF-240115-002")];
+        (vec![product], lots)
+    }
+
+    #[test]
+    fn test_gs1_and_qr_resolve_same_lot() {
+        let (products, lots) = fixtures();
+        let keys = keys();
+
+        let gs1 = ScannedCode::parse("]C1011613000000001217240315", &keys).unwrap();
+        assert!(matches!(gs1, ScannedCode::Gs1(_)));
+        // No lot in the code: cannot pick a lot
+        assert!(gs1.resolve_lot_pf(&products, &lots).is_err());
+
+        let gs1 = ScannedCode::parse("(01)16130000000012(17)240315(10)LPF-240115-002", &keys).unwrap();
+        let qr = ScannedCode::parse(&lots[1].qr_code, &keys).unwrap();
+        assert_eq!(gs1.resolve_lot_pf(&products, &lots).unwrap().id, lots[1].id);
+        assert_eq!(qr.resolve_lot_pf(&products, &lots).unwrap().id, lots[1].id);
+    }
+
+    #[test]
+    fn test_unknown_gtin_not_resolved() {
+        let (products, lots) = fixtures();
+        let other = Gs1Label {
+            lot: Some("LPF-240115-001".to_string()),
+            ..Gs1Label::new(Gtin::parse("4006381333931").unwrap())
+        };
+        let err = ScannedCode::Gs1(other).resolve_lot_pf(&products, &lots).unwrap_err();
+        assert!(matches!(err, Error::NotFound { .. }));
+    }
+}
@@ -0,0 +1,426 @@
+//! Inter-warehouse transfer orders
+//!
+//! A transfer moves picked lots from one warehouse to another:
+//! draft (picking) -> shipped (stock in transit) -> received. Lots keep their
+//! unit cost on the way, so the stock value leaving a site is the value
+//! arriving at the other one, less the recorded discrepancies.
+
+use chrono::{DateTime, Utc};
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, Money, Qty, Result};
+use serde::{Deserialize, Serialize};
+
+use super::ProductType;
+
+/// Transfer order status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransferStatus {
+    Draft,
+    Shipped,
+    Received,
+    Cancelled,
+}
+
+impl TransferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "DRAFT",
+            Self::Shipped => "SHIPPED",
+            Self::Received => "RECEIVED",
+            Self::Cancelled => "CANCELLED",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "DRAFT" => Some(Self::Draft),
+            "SHIPPED" => Some(Self::Shipped),
+            "RECEIVED" => Some(Self::Received),
+            "CANCELLED" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// One picked lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferLine {
+    pub id: EntityId,
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub lot_id: EntityId,
+    pub lot_number: String,
+    pub quantity_shipped: Qty,
+    pub quantity_received: Option<Qty>,
+    /// Cost of the origin lot, carried to the destination
+    pub unit_cost: Money,
+    pub discrepancy_reason: Option<String>,
+}
+
+impl TransferLine {
+    /// Quantity shipped but not received
+    pub fn shortage(&self) -> Qty {
+        match self.quantity_received {
+            Some(received) => self.quantity_shipped - received,
+            None => Qty::zero(),
+        }
+    }
+}
+
+/// Quantity counted at the destination for a line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferReceipt {
+    pub line_id: EntityId,
+    pub quantity_received: Qty,
+    pub reason: Option<String>,
+}
+
+/// Transfer order between two warehouses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferOrder {
+    pub id: EntityId,
+    pub reference: String,
+    pub from_warehouse_id: EntityId,
+    pub to_warehouse_id: EntityId,
+    pub status: TransferStatus,
+    pub lines: Vec<TransferLine>,
+    pub shipped_at: Option<DateTime<Utc>>,
+    pub received_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub audit: AuditInfo,
+}
+
+impl TransferOrder {
+    pub fn new(
+        reference: String,
+        from_warehouse_id: EntityId,
+        to_warehouse_id: EntityId,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        if from_warehouse_id == to_warehouse_id {
+            return Err(Error::Validation {
+                field: "to_warehouse_id".to_string(),
+                message: "Entrepots de depart et d'arrivee identiques".to_string(),
+            });
+        }
+
+        Ok(Self {
+            id: EntityId::new(),
+            reference,
+            from_warehouse_id,
+            to_warehouse_id,
+            status: TransferStatus::Draft,
+            lines: Vec::new(),
+            shipped_at: None,
+            received_at: None,
+            notes: None,
+            audit: AuditInfo::new(user_id, clock),
+        })
+    }
+
+    /// Pick a lot of the origin warehouse
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_line(
+        &mut self,
+        product_type: ProductType,
+        product_id: EntityId,
+        lot_id: EntityId,
+        lot_number: String,
+        quantity: Qty,
+        available: Qty,
+        unit_cost: Money,
+        user_id: EntityId,
+        clock: &dyn Clock,
+    ) -> Result<EntityId> {
+        if self.status != TransferStatus::Draft {
+            return Err(Error::BusinessRule(
+                "Transfert deja expedie: lignes non modifiables".to_string(),
+            ));
+        }
+        if !quantity.is_positive() {
+            return Err(Error::Validation {
+                field: "quantity".to_string(),
+                message: "Quantite doit etre superieure a 0".to_string(),
+            });
+        }
+        if self.lines.iter().any(|l| l.lot_id == lot_id) {
+            return Err(Error::BusinessRule(format!("Lot {} deja dans le transfert", lot_number)));
+        }
+        if quantity > available {
+            return Err(Error::InsufficientStock {
+                product: lot_number,
+                required: quantity.as_f64(),
+                available: available.as_f64(),
+            });
+        }
+
+        let line = TransferLine {
+            id: EntityId::new(),
+            product_type,
+            product_id,
+            lot_id,
+            lot_number,
+            quantity_shipped: quantity,
+            quantity_received: None,
+            unit_cost,
+            discrepancy_reason: None,
+        };
+        let line_id = line.id;
+        self.lines.push(line);
+        self.audit.update(user_id, clock);
+        Ok(line_id)
+    }
+
+    /// Remove a picked lot
+    pub fn remove_line(&mut self, line_id: EntityId, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        if self.status != TransferStatus::Draft {
+            return Err(Error::BusinessRule(
+                "Transfert deja expedie: lignes non modifiables".to_string(),
+            ));
+        }
+        let before = self.lines.len();
+        self.lines.retain(|l| l.id != line_id);
+        if self.lines.len() == before {
+            return Err(Error::NotFound {
+                entity_type: "TransferLine".to_string(),
+                id: line_id.to_string(),
+            });
+        }
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Goods leave the origin warehouse and are in transit
+    pub fn ship(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.transition(TransferStatus::Draft, TransferStatus::Shipped)?;
+        if self.lines.is_empty() {
+            return Err(Error::Validation {
+                field: "lines".to_string(),
+                message: "Transfert sans lot".to_string(),
+            });
+        }
+
+        self.status = TransferStatus::Shipped;
+        self.shipped_at = Some(clock.now());
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Record what arrived
+    ///
+    /// Lines without a receipt are received in full. A line received short
+    /// needs a reason; receiving more than shipped is refused.
+    pub fn receive(&mut self, receipts: &[TransferReceipt], user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.transition(TransferStatus::Shipped, TransferStatus::Received)?;
+
+        if let Some(unknown) = receipts.iter().find(|r| !self.lines.iter().any(|l| l.id == r.line_id)) {
+            return Err(Error::NotFound {
+                entity_type: "TransferLine".to_string(),
+                id: unknown.line_id.to_string(),
+            });
+        }
+
+        for line in &self.lines {
+            let Some(receipt) = receipts.iter().find(|r| r.line_id == line.id) else {
+                continue;
+            };
+            if receipt.quantity_received.is_negative() || receipt.quantity_received > line.quantity_shipped {
+                return Err(Error::Validation {
+                    field: "quantity_received".to_string(),
+                    message: format!(
+                        "Lot {}: {} recu pour {} expedie",
+                        line.lot_number, receipt.quantity_received, line.quantity_shipped
+                    ),
+                });
+            }
+            let reason_given = receipt.reason.as_deref().is_some_and(|r| !r.trim().is_empty());
+            if receipt.quantity_received < line.quantity_shipped && !reason_given {
+                return Err(Error::Validation {
+                    field: "reason".to_string(),
+                    message: format!("Lot {}: motif de l'ecart requis", line.lot_number),
+                });
+            }
+        }
+
+        for line in &mut self.lines {
+            match receipts.iter().find(|r| r.line_id == line.id) {
+                Some(receipt) => {
+                    line.quantity_received = Some(receipt.quantity_received);
+                    if receipt.quantity_received < line.quantity_shipped {
+                        line.discrepancy_reason = receipt.reason.clone();
+                    }
+                }
+                None => line.quantity_received = Some(line.quantity_shipped),
+            }
+        }
+
+        self.status = TransferStatus::Received;
+        self.received_at = Some(clock.now());
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Cancel a transfer not yet shipped
+    pub fn cancel(&mut self, user_id: EntityId, clock: &dyn Clock) -> Result<()> {
+        self.transition(TransferStatus::Draft, TransferStatus::Cancelled)?;
+        self.status = TransferStatus::Cancelled;
+        self.audit.update(user_id, clock);
+        Ok(())
+    }
+
+    /// Lines received short
+    pub fn discrepancies(&self) -> impl Iterator<Item = &TransferLine> {
+        self.lines.iter().filter(|l| l.shortage().is_positive())
+    }
+
+    /// Stock value that left the origin warehouse
+    pub fn value_shipped(&self) -> Money {
+        self.lines
+            .iter()
+            .fold(Money::zero(), |total, l| total + l.quantity_shipped.value_at(l.unit_cost))
+    }
+
+    /// Stock value that arrived at the destination
+    pub fn value_received(&self) -> Money {
+        self.lines.iter().fold(Money::zero(), |total, l| {
+            total + l.quantity_received.unwrap_or_default().value_at(l.unit_cost)
+        })
+    }
+
+    fn transition(&self, from: TransferStatus, to: TransferStatus) -> Result<()> {
+        if self.status != from {
+            return Err(Error::InvalidStateTransition {
+                entity: "TransferOrder".to_string(),
+                from: self.status.as_str().to_string(),
+                to: to.as_str().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use manchengo_core::FixedClock;
+
+    fn test_clock() -> FixedClock {
+        FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+    }
+
+    fn draft(clock: &FixedClock) -> TransferOrder {
+        TransferOrder::new(
+            "TRF-2024-000001".to_string(),
+            EntityId::new(),
+            EntityId::new(),
+            EntityId::new(),
+            clock,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_partial_receipt_records_discrepancy_and_keeps_value() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let mut transfer = draft(&clock);
+
+        let cheese = transfer
+            .add_line(
+                ProductType::Pf,
+                EntityId::new(),
+                EntityId::new(),
+                "PF-20240301-001".to_string(),
+                Qty::units(100),
+                Qty::units(120),
+                Money::from_centimes(45_000),
+                user,
+                &clock,
+            )
+            .unwrap();
+        transfer
+            .add_line(
+                ProductType::Mp,
+                EntityId::new(),
+                EntityId::new(),
+                "LOT-20240220-003".to_string(),
+                Qty::units(50),
+                Qty::units(50),
+                Money::from_centimes(8_000),
+                user,
+                &clock,
+            )
+            .unwrap();
+        transfer.ship(user, &clock).unwrap();
+        assert!(transfer.add_line(
+            ProductType::Mp,
+            EntityId::new(),
+            EntityId::new(),
+            "LATE".to_string(),
+            Qty::units(1),
+            Qty::units(1),
+            Money::zero(),
+            user,
+            &clock,
+        )
+        .is_err());
+
+        let short = TransferReceipt {
+            line_id: cheese,
+            quantity_received: Qty::units(96),
+            reason: None,
+        };
+        assert!(transfer.receive(std::slice::from_ref(&short), user, &clock).is_err());
+        assert_eq!(transfer.status, TransferStatus::Shipped);
+
+        let short = TransferReceipt {
+            reason: Some("4 pieces ecrasees".to_string()),
+            ..short
+        };
+        transfer.receive(&[short], user, &clock).unwrap();
+
+        assert_eq!(transfer.status, TransferStatus::Received);
+        let discrepancies: Vec<_> = transfer.discrepancies().collect();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].shortage(), Qty::units(4));
+        assert_eq!(transfer.value_shipped(), Money::from_centimes(100 * 45_000 + 50 * 8_000));
+        assert_eq!(
+            transfer.value_shipped() - transfer.value_received(),
+            Money::from_centimes(4 * 45_000)
+        );
+    }
+
+    #[test]
+    fn test_transfer_rules() {
+        let clock = test_clock();
+        let user = EntityId::new();
+        let site = EntityId::new();
+        assert!(TransferOrder::new("TRF".to_string(), site, site, user, &clock).is_err());
+
+        let mut transfer = draft(&clock);
+        assert!(transfer.ship(user, &clock).is_err());
+
+        let lot = EntityId::new();
+        let pick = |t: &mut TransferOrder, qty: i64| {
+            t.add_line(
+                ProductType::Mp,
+                EntityId::new(),
+                lot,
+                "LOT-1".to_string(),
+                Qty::units(qty),
+                Qty::units(10),
+                Money::zero(),
+                user,
+                &clock,
+            )
+        };
+        assert!(pick(&mut transfer, 11).is_err());
+        pick(&mut transfer, 10).unwrap();
+        assert!(pick(&mut transfer, 1).is_err());
+
+        transfer.cancel(user, &clock).unwrap();
+        assert!(transfer.ship(user, &clock).is_err());
+    }
+}
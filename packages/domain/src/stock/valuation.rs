@@ -0,0 +1,352 @@
+//! Stock valuation
+//!
+//! Values stock from the movement ledger two ways:
+//! - CMUP (weighted average unit cost), recomputed after every entry and
+//!   applied to every exit, as allowed by the SCF
+//! - FIFO, where each lot keeps its own cost until it is consumed
+//!
+//! Replaying the ledger up to a date gives the valuation as of that date.
+//! Transfer legs between warehouses move quantity, not value: they are left
+//! out of the average.
+
+use chrono::{DateTime, Utc};
+use manchengo_core::{EntityId, Money, Qty};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::ProductType;
+
+/// A ledger movement as seen by valuation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValuationMovement {
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub lot_id: Option<EntityId>,
+    pub warehouse_id: Option<EntityId>,
+    pub is_entry: bool,
+    /// Transfer leg between warehouses
+    pub internal: bool,
+    pub quantity: Qty,
+    /// Cost carried by the movement, else the lot cost; an entry without
+    /// either comes in at the running average
+    pub unit_cost: Option<Money>,
+    /// Cost of the lot, for FIFO
+    pub lot_cost: Money,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Running weighted average cost of a product
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeightedAverageCost {
+    pub quantity: Qty,
+    pub value: Money,
+    pub average: Money,
+}
+
+impl WeightedAverageCost {
+    /// Entry at a unit cost: the average moves toward it
+    ///
+    /// When stock was exhausted (or oversold), the entry cost becomes the
+    /// new average.
+    pub fn receive(&mut self, quantity: Qty, unit_cost: Money) {
+        let total = self.quantity + quantity;
+        if !self.quantity.is_positive() {
+            self.quantity = total;
+            self.average = unit_cost;
+            self.value = total.value_at(unit_cost);
+            return;
+        }
+
+        self.quantity = total;
+        self.value = self.value + quantity.value_at(unit_cost);
+        if let Some(average) = total.unit_price_of(self.value) {
+            self.average = average;
+        }
+    }
+
+    /// Exit at the current average, returning the value issued
+    pub fn issue(&mut self, quantity: Qty) -> Money {
+        let issued = quantity.value_at(self.average);
+        self.quantity -= quantity;
+        self.value = if self.quantity.is_positive() {
+            self.value - issued
+        } else {
+            self.quantity.value_at(self.average)
+        };
+        issued
+    }
+}
+
+/// Average cost after one movement of a product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostStep {
+    pub occurred_at: DateTime<Utc>,
+    pub is_entry: bool,
+    pub quantity: Qty,
+    /// Entry cost, or the average an exit was issued at
+    pub unit_cost: Money,
+    pub after: WeightedAverageCost,
+}
+
+/// Stock of a product in one warehouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseValuation {
+    pub warehouse_id: Option<EntityId>,
+    pub quantity: Qty,
+    pub cmup_value: Money,
+    pub fifo_value: Money,
+}
+
+/// Stock value of a product at a date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductValuation {
+    pub product_type: ProductType,
+    pub product_id: EntityId,
+    pub quantity: Qty,
+    pub average_cost: Money,
+    pub cmup_value: Money,
+    pub fifo_value: Money,
+    pub warehouses: Vec<WarehouseValuation>,
+}
+
+impl ProductValuation {
+    /// CMUP minus FIFO value
+    pub fn difference(&self) -> Money {
+        self.cmup_value - self.fifo_value
+    }
+}
+
+/// Stock valuation over the movement ledger
+pub struct StockValuation;
+
+impl StockValuation {
+    /// Average cost after each movement of one product, up to `as_of`
+    pub fn cost_history(movements: &[ValuationMovement], as_of: DateTime<Utc>) -> Vec<CostStep> {
+        let mut cost = WeightedAverageCost::default();
+        Self::replay(movements, as_of)
+            .into_iter()
+            .filter(|m| !m.internal)
+            .map(|m| {
+                let unit_cost = if m.is_entry {
+                    let unit_cost = m.unit_cost.unwrap_or(cost.average);
+                    cost.receive(m.quantity, unit_cost);
+                    unit_cost
+                } else {
+                    let average = cost.average;
+                    cost.issue(m.quantity);
+                    average
+                };
+                CostStep {
+                    occurred_at: m.occurred_at,
+                    is_entry: m.is_entry,
+                    quantity: m.quantity,
+                    unit_cost,
+                    after: cost,
+                }
+            })
+            .collect()
+    }
+
+    /// Value every product still in stock at `as_of`, CMUP and FIFO
+    ///
+    /// Lot-less movements have no FIFO cost and are valued at the average.
+    pub fn value(movements: &[ValuationMovement], as_of: DateTime<Utc>) -> Vec<ProductValuation> {
+        let mut order: Vec<EntityId> = Vec::new();
+        let mut by_product: HashMap<EntityId, Vec<&ValuationMovement>> = HashMap::new();
+        for m in Self::replay(movements, as_of) {
+            by_product
+                .entry(m.product_id)
+                .or_insert_with(|| {
+                    order.push(m.product_id);
+                    Vec::new()
+                })
+                .push(m);
+        }
+
+        order
+            .into_iter()
+            .filter_map(|product_id| Self::value_product(&by_product[&product_id]))
+            .collect()
+    }
+
+    fn value_product(movements: &[&ValuationMovement]) -> Option<ProductValuation> {
+        let first = movements.first()?;
+        let mut cost = WeightedAverageCost::default();
+        let mut warehouses: Vec<Option<EntityId>> = Vec::new();
+        let mut stock: HashMap<Option<EntityId>, Qty> = HashMap::new();
+        let mut lots: HashMap<(Option<EntityId>, Option<EntityId>), (Qty, Money)> = HashMap::new();
+
+        for m in movements {
+            if !m.internal {
+                if m.is_entry {
+                    cost.receive(m.quantity, m.unit_cost.unwrap_or(cost.average));
+                } else {
+                    cost.issue(m.quantity);
+                }
+            }
+
+            let signed = if m.is_entry { m.quantity } else { Qty::zero() - m.quantity };
+            let quantity = stock.entry(m.warehouse_id).or_insert_with(|| {
+                warehouses.push(m.warehouse_id);
+                Qty::zero()
+            });
+            *quantity += signed;
+            let lot = lots.entry((m.lot_id, m.warehouse_id)).or_insert((Qty::zero(), m.lot_cost));
+            lot.0 += signed;
+        }
+
+        let warehouses: Vec<WarehouseValuation> = warehouses
+            .into_iter()
+            .filter_map(|warehouse_id| {
+                let quantity = stock[&warehouse_id];
+                let fifo_value = lots
+                    .iter()
+                    .filter(|((_, w), _)| *w == warehouse_id)
+                    .map(|((lot_id, _), (balance, lot_cost))| {
+                        balance.value_at(if lot_id.is_some() { *lot_cost } else { cost.average })
+                    })
+                    .fold(Money::zero(), |total, value| total + value);
+                if quantity.is_zero() && fifo_value.is_zero() {
+                    return None;
+                }
+                Some(WarehouseValuation {
+                    warehouse_id,
+                    quantity,
+                    cmup_value: quantity.value_at(cost.average),
+                    fifo_value,
+                })
+            })
+            .collect();
+
+        if warehouses.is_empty() {
+            return None;
+        }
+
+        Some(ProductValuation {
+            product_type: first.product_type,
+            product_id: first.product_id,
+            quantity: cost.quantity,
+            average_cost: cost.average,
+            cmup_value: warehouses.iter().fold(Money::zero(), |total, w| total + w.cmup_value),
+            fifo_value: warehouses.iter().fold(Money::zero(), |total, w| total + w.fifo_value),
+            warehouses,
+        })
+    }
+
+    /// Movements up to `as_of`, oldest first
+    fn replay(movements: &[ValuationMovement], as_of: DateTime<Utc>) -> Vec<&ValuationMovement> {
+        let mut replayed: Vec<&ValuationMovement> = movements.iter().filter(|m| m.occurred_at <= as_of).collect();
+        replayed.sort_by_key(|m| m.occurred_at);
+        replayed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, 10, 0, 0).unwrap()
+    }
+
+    fn movement(
+        product_id: EntityId,
+        lot: (EntityId, i64),
+        warehouse_id: EntityId,
+        is_entry: bool,
+        units: i64,
+        day: u32,
+    ) -> ValuationMovement {
+        ValuationMovement {
+            product_type: ProductType::Mp,
+            product_id,
+            lot_id: Some(lot.0),
+            warehouse_id: Some(warehouse_id),
+            is_entry,
+            internal: false,
+            quantity: Qty::units(units),
+            unit_cost: Some(Money::from_centimes(lot.1)),
+            lot_cost: Money::from_centimes(lot.1),
+            occurred_at: at(day),
+        }
+    }
+
+    #[test]
+    fn test_average_cost_and_fifo_reconciliation() {
+        let product = EntityId::new();
+        let warehouse = EntityId::new();
+        let lot_a = (EntityId::new(), 10_000);
+        let lot_b = (EntityId::new(), 20_000);
+        let lot_c = (EntityId::new(), 30_000);
+        let movements = vec![
+            movement(product, lot_a, warehouse, true, 10, 1),
+            movement(product, lot_b, warehouse, true, 10, 2),
+            movement(product, lot_a, warehouse, false, 5, 3),
+            movement(product, lot_c, warehouse, true, 5, 4),
+        ];
+
+        let history = StockValuation::cost_history(&movements, at(31));
+        assert_eq!(history[1].after.average, Money::from_centimes(15_000));
+        assert_eq!(history[2].unit_cost, Money::from_centimes(15_000));
+        assert_eq!(history[3].after.average, Money::from_centimes(18_750));
+
+        // As of the 3rd: 15 left at 150 DZD, lots at 5 x 100 + 10 x 200
+        let valuation = StockValuation::value(&movements, at(3));
+        assert_eq!(valuation.len(), 1);
+        assert_eq!(valuation[0].quantity, Qty::units(15));
+        assert_eq!(valuation[0].cmup_value, Money::from_centimes(225_000));
+        assert_eq!(valuation[0].fifo_value, Money::from_centimes(250_000));
+        assert_eq!(valuation[0].difference(), Money::from_centimes(-25_000));
+
+        let valuation = StockValuation::value(&movements, at(31));
+        assert_eq!(valuation[0].cmup_value, Money::from_centimes(375_000));
+        assert_eq!(valuation[0].fifo_value, Money::from_centimes(400_000));
+    }
+
+    #[test]
+    fn test_transfer_moves_quantity_not_average() {
+        let product = EntityId::new();
+        let (main, annex) = (EntityId::new(), EntityId::new());
+        let lot = (EntityId::new(), 10_000);
+        let mut out = movement(product, lot, main, false, 4, 2);
+        out.internal = true;
+        let mut into = movement(product, lot, annex, true, 4, 2);
+        into.internal = true;
+        into.unit_cost = Some(Money::from_centimes(99_900));
+        let movements = vec![movement(product, lot, main, true, 10, 1), out, into];
+
+        let valuation = StockValuation::value(&movements, at(31));
+        let product = &valuation[0];
+        assert_eq!(product.quantity, Qty::units(10));
+        assert_eq!(product.average_cost, Money::from_centimes(10_000));
+        assert_eq!(product.warehouses.len(), 2);
+        assert_eq!(product.warehouses[0].quantity, Qty::units(6));
+        assert_eq!(product.warehouses[1].fifo_value, Money::from_centimes(40_000));
+        assert!(product.difference().is_zero());
+
+        // Nothing in stock before the first entry
+        assert!(StockValuation::value(&movements, at(1) - chrono::Duration::days(1)).is_empty());
+    }
+
+    #[test]
+    fn test_costless_entry_comes_in_at_average() {
+        let product = EntityId::new();
+        let warehouse = EntityId::new();
+        let lot = (EntityId::new(), 10_000);
+        let mut costless = movement(product, (EntityId::new(), 0), warehouse, true, 10, 2);
+        costless.lot_id = None;
+        costless.unit_cost = None;
+        let movements = vec![movement(product, lot, warehouse, true, 10, 1), costless];
+
+        // A return without cost must not drag the average toward zero
+        let history = StockValuation::cost_history(&movements, at(31));
+        assert_eq!(history[1].unit_cost, Money::from_centimes(10_000));
+        assert_eq!(history[1].after.average, Money::from_centimes(10_000));
+
+        let valuation = StockValuation::value(&movements, at(31));
+        assert_eq!(valuation[0].quantity, Qty::units(20));
+        assert_eq!(valuation[0].cmup_value, Money::from_centimes(200_000));
+        assert!(valuation[0].difference().is_zero());
+    }
+}
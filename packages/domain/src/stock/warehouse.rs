@@ -0,0 +1,199 @@
+//! Warehouses and bin locations
+//!
+//! A warehouse is a site (the factory, a depot in another wilaya); a location
+//! is a place inside it where lots are stored: raw-milk reception area, cold
+//! room, ripening cellar. Received lots wait in the reception location until
+//! they are put away, and FIFO only picks lots of one warehouse.
+
+use manchengo_core::{AuditInfo, Clock, EntityId, Error, QrCodeData, QrEntityType, QrKeyRing, Result};
+use serde::{Deserialize, Serialize};
+
+/// Kind of storage place
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LocationType {
+    /// Dock where deliveries wait for put-away (raw milk, MP)
+    Reception,
+    ColdRoom,
+    RipeningCellar,
+    Storage,
+    Shipping,
+    Quarantine,
+    /// Virtual location of lots on the road between two warehouses
+    Transit,
+}
+
+impl LocationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reception => "RECEPTION",
+            Self::ColdRoom => "COLD_ROOM",
+            Self::RipeningCellar => "RIPENING_CELLAR",
+            Self::Storage => "STORAGE",
+            Self::Shipping => "SHIPPING",
+            Self::Quarantine => "QUARANTINE",
+            Self::Transit => "TRANSIT",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "RECEPTION" => Some(Self::Reception),
+            "COLD_ROOM" => Some(Self::ColdRoom),
+            "RIPENING_CELLAR" => Some(Self::RipeningCellar),
+            "STORAGE" => Some(Self::Storage),
+            "SHIPPING" => Some(Self::Shipping),
+            "QUARANTINE" => Some(Self::Quarantine),
+            "TRANSIT" => Some(Self::Transit),
+            _ => None,
+        }
+    }
+
+    /// Lots in these locations are not available for FIFO picking
+    pub fn is_pickable(&self) -> bool {
+        !matches!(self, Self::Reception | Self::Quarantine | Self::Transit)
+    }
+}
+
+/// Storage site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Warehouse {
+    pub id: EntityId,
+    pub code: String,
+    pub name: String,
+    pub wilaya_code: Option<String>,
+    pub address: Option<String>,
+    /// Site used when no warehouse is given (receptions, production)
+    pub is_default: bool,
+    pub is_active: bool,
+}
+
+impl Warehouse {
+    pub fn new(code: String, name: String, wilaya_code: Option<String>) -> Result<Self> {
+        validate_code("warehouse.code", &code)?;
+        Ok(Self {
+            id: EntityId::new(),
+            code,
+            name,
+            wilaya_code,
+            address: None,
+            is_default: false,
+            is_active: true,
+        })
+    }
+}
+
+/// Bin location inside a warehouse, identified on the floor by its QR label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub id: EntityId,
+    pub warehouse_id: EntityId,
+    pub code: String,
+    pub name: String,
+    pub location_type: LocationType,
+    pub is_active: bool,
+    pub qr_code: String,
+    pub audit: AuditInfo,
+}
+
+impl Location {
+    pub fn new(
+        warehouse_id: EntityId,
+        code: String,
+        name: String,
+        location_type: LocationType,
+        user_id: EntityId,
+        qr_keys: &QrKeyRing,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        validate_code("location.code", &code)?;
+
+        let id = EntityId::new();
+        let qr_data = QrCodeData::new(QrEntityType::Location, id, code.clone(), None, qr_keys);
+
+        Ok(Self {
+            id,
+            warehouse_id,
+            code,
+            name,
+            location_type,
+            is_active: true,
+            qr_code: qr_data.encode(),
+            audit: AuditInfo::new(user_id, clock),
+        })
+    }
+
+    /// Check lots of `warehouse_id` may be stored here
+    pub fn accepts(&self, warehouse_id: EntityId) -> Result<()> {
+        if !self.is_active {
+            return Err(Error::BusinessRule(format!("Emplacement {} inactif", self.code)));
+        }
+        if self.warehouse_id != warehouse_id {
+            return Err(Error::BusinessRule(format!(
+                "Emplacement {} hors de l'entrepot du lot",
+                self.code
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Warehouse and location codes appear in QR payloads: no `:` or spaces
+fn validate_code(field: &str, code: &str) -> Result<()> {
+    let valid = !code.is_empty()
+        && code.len() <= 20
+        && code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::Validation {
+            field: field.to_string(),
+            message: format!("Code invalide '{}' (A-Z, 0-9, - et _, 20 caracteres max)", code),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use manchengo_core::{FixedClock, QrKey};
+
+    fn test_keys() -> QrKeyRing {
+        QrKeyRing::new(vec![QrKey::new("T1", b"test-secret-0123456789").unwrap()]).unwrap()
+    }
+
+    fn test_clock() -> FixedClock {
+        FixedClock::at_date(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap())
+    }
+
+    #[test]
+    fn test_location_label_decodes_as_location() {
+        let keys = test_keys();
+        let factory = Warehouse::new("USINE".to_string(), "Usine".to_string(), Some("16".to_string())).unwrap();
+        let cellar = Location::new(
+            factory.id,
+            "CAVE-01".to_string(),
+            "Cave d'affinage 1".to_string(),
+            LocationType::RipeningCellar,
+            EntityId::new(),
+            &keys,
+            &test_clock(),
+        )
+        .unwrap();
+
+        let qr = QrCodeData::decode(&cellar.qr_code, &keys).unwrap();
+        assert_eq!(qr.entity_type, QrEntityType::Location);
+        assert_eq!(qr.entity_id, cellar.id);
+        assert_eq!(qr.reference, "CAVE-01");
+
+        assert!(cellar.accepts(factory.id).is_ok());
+        assert!(cellar.accepts(EntityId::new()).is_err());
+    }
+
+    #[test]
+    fn test_codes_are_qr_safe() {
+        assert!(Warehouse::new("DEPOT-ORAN".to_string(), "Depot Oran".to_string(), Some("31".to_string())).is_ok());
+        assert!(Warehouse::new("depot oran".to_string(), "Depot Oran".to_string(), None).is_err());
+        assert!(Warehouse::new("A:B".to_string(), "X".to_string(), None).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Utc};
 use manchengo_core::{EntityId, Error, Result};
 use manchengo_database::Database;
 use manchengo_domain::events::EventEnvelope;
+use rusqlite::Connection;
 use serde_json;
 use tracing::{debug, info};
 
@@ -20,32 +21,36 @@ impl EventStore {
     /// Append an event to the store
     /// NOTE: version is computed atomically via subquery to prevent race conditions
     pub fn append(&self, event: &EventEnvelope) -> Result<()> {
-        self.db.with_connection(|conn| {
-            conn.execute(
-                "INSERT INTO _events (
-                    id, aggregate_type, aggregate_id, event_type, payload,
-                    occurred_at, user_id, device_id, version, synced
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8,
-                    COALESCE((SELECT MAX(version) FROM _events WHERE aggregate_type = ?2 AND aggregate_id = ?3), 0) + 1,
-                    ?10)",
-                rusqlite::params![
-                    event.id.to_string(),
-                    event.aggregate_type,
-                    event.aggregate_id.to_string(),
-                    event.event_type,
-                    serde_json::to_string(&event.payload)?,
-                    event.occurred_at.to_rfc3339(),
-                    event.user_id.to_string(),
-                    event.device_id.to_string(),
-                    event.version, // ?9 unused in query but keeps param indexing for ?10
-                    event.synced as i32,
-                ],
-            )
-            .map_err(|e| Error::Database(e.to_string()))?;
-
-            debug!("Event {} appended: {}", event.id, event.event_type);
-            Ok(())
-        })
+        self.db.with_connection(|conn| Self::append_on(conn, event))
+    }
+
+    /// Append an event on a caller's connection or transaction, so the event
+    /// commits (or rolls back) with the change it records
+    pub fn append_on(conn: &Connection, event: &EventEnvelope) -> Result<()> {
+        conn.execute(
+            "INSERT INTO _events (
+                id, aggregate_type, aggregate_id, event_type, payload,
+                occurred_at, user_id, device_id, version, synced
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8,
+                COALESCE((SELECT MAX(version) FROM _events WHERE aggregate_type = ?2 AND aggregate_id = ?3), 0) + 1,
+                ?10)",
+            rusqlite::params![
+                event.id.to_string(),
+                event.aggregate_type,
+                event.aggregate_id.to_string(),
+                event.event_type,
+                serde_json::to_string(&event.payload)?,
+                event.occurred_at.to_rfc3339(),
+                event.user_id.to_string(),
+                event.device_id.to_string(),
+                event.version, // ?9 unused in query but keeps param indexing for ?10
+                event.synced as i32,
+            ],
+        )
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+        debug!("Event {} appended: {}", event.id, event.event_type);
+        Ok(())
     }
 
     /// Get all unsynced events
@@ -185,19 +190,19 @@ impl EventStore {
 
     /// Get next version number for an aggregate
     pub fn get_next_version(&self, aggregate_type: &str, aggregate_id: EntityId) -> Result<i64> {
-        self.db.with_connection(|conn| {
-            let version: i64 = conn
-                .query_row(
-                    "SELECT COALESCE(MAX(version), 0) + 1
-                     FROM _events
-                     WHERE aggregate_type = ?1 AND aggregate_id = ?2",
-                    [aggregate_type, &aggregate_id.to_string()],
-                    |row| row.get(0),
-                )
-                .map_err(|e| Error::Database(e.to_string()))?;
+        self.db.with_connection(|conn| Self::next_version_on(conn, aggregate_type, aggregate_id))
+    }
 
-            Ok(version)
-        })
+    /// Next version number, read on a caller's connection or transaction
+    pub fn next_version_on(conn: &Connection, aggregate_type: &str, aggregate_id: EntityId) -> Result<i64> {
+        conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) + 1
+             FROM _events
+             WHERE aggregate_type = ?1 AND aggregate_id = ?2",
+            [aggregate_type, &aggregate_id.to_string()],
+            |row| row.get(0),
+        )
+        .map_err(|e| Error::Database(e.to_string()))
     }
 
     /// Count unsynced events